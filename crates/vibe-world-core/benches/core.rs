@@ -0,0 +1,71 @@
+//! Benchmarks of the work the viewer does every frame or for every tile: picking the tiles in
+//! view, coalescing their downloads, and projecting between lat/lon and world coordinates
+//! Run with `cargo bench -p vibe-world-core`
+
+use std::hint::black_box;
+use criterion::{criterion_group, criterion_main, Criterion};
+use glam::DVec3;
+use vibe_world_core::coalesce::{redundant_requests, TileRequest};
+use vibe_world_core::lod::{select_lod_tiles, LodView, ViewFootprint};
+use vibe_world_core::projection::{lat_lon_to_world, normalize_lon, prepare_line, world_to_lat_lon, AntimeridianMode};
+use vibe_world_core::{TileId, MAX_ZOOM_LEVEL};
+
+// Above Groningen, in world units (tiles at the default zoom level)
+const CAMERA: DVec3 = DVec3::new(4216.5, 2.0, 2668.5);
+const ROOT: TileId = TileId { x: 4216 >> 8, y: 2668 >> 8, z: 5 };
+
+// Looking north, the bottom of the view 45 degrees down and the top above the horizon,
+// where the most zoom levels are selected at once
+fn tilted_view() -> LodView {
+    let rays = [
+        DVec3::new(-1.0, -1.0, -1.0),
+        DVec3::new(1.0, -1.0, -1.0),
+        DVec3::new(1.0, 0.5, -1.0),
+        DVec3::new(-1.0, 0.5, -1.0),
+    ];
+    LodView::new(CAMERA, std::f32::consts::FRAC_PI_2, 1080.0, 256.0)
+        .with_footprint(ViewFootprint::from_rays(CAMERA, rays, 40.0))
+}
+
+fn tile_selection(c: &mut Criterion) {
+    let looking_down = LodView::new(CAMERA * DVec3::new(1.0, 10.0, 1.0), std::f32::consts::FRAC_PI_2, 1080.0, 256.0);
+    c.bench_function("select_lod_tiles looking down", |b| {
+        b.iter(|| select_lod_tiles(black_box(&looking_down), &[ROOT], MAX_ZOOM_LEVEL))
+    });
+    let tilted = tilted_view();
+    c.bench_function("select_lod_tiles to the horizon", |b| {
+        b.iter(|| select_lod_tiles(black_box(&tilted), &[ROOT], MAX_ZOOM_LEVEL))
+    });
+}
+
+fn coalescing(c: &mut Criterion) {
+    // The selected tiles, with the parents of the first half still in flight from an earlier frame
+    let view = tilted_view();
+    let tiles = select_lod_tiles(&view, &[ROOT], MAX_ZOOM_LEVEL);
+    let mut requests: Vec<TileRequest> = tiles
+        .iter()
+        .map(|&tile| TileRequest { tile, priority: view.download_priority(tile).unwrap_or(i32::MAX), selected: true })
+        .collect();
+    requests.extend(tiles[..tiles.len() / 2].iter().filter_map(|tile| {
+        Some(TileRequest { tile: tile.parent()?, priority: 0, selected: false })
+    }));
+    c.bench_function(&format!("redundant_requests of {}", requests.len()), |b| {
+        b.iter(|| redundant_requests(black_box(&requests)))
+    });
+}
+
+fn projection(c: &mut Criterion) {
+    c.bench_function("lat_lon_to_world", |b| b.iter(|| lat_lon_to_world(black_box(53.2194), black_box(6.5665))));
+    c.bench_function("world_to_lat_lon", |b| b.iter(|| world_to_lat_lon(black_box(CAMERA.x), black_box(CAMERA.z))));
+
+    // A track of a thousand points around the world along the equator, crossing ±180 once
+    let track: Vec<(f64, f64)> = (0..1000)
+        .map(|i| ((i as f64 * 0.1).sin(), normalize_lon(-179.0 + i as f64 * 0.36)))
+        .collect();
+    for mode in AntimeridianMode::ALL {
+        c.bench_function(&format!("prepare_line {}", mode.key()), |b| b.iter(|| prepare_line(black_box(&track), mode)));
+    }
+}
+
+criterion_group!(benches, tile_selection, coalescing, projection);
+criterion_main!(benches);
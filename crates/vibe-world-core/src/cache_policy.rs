@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+/// Whether a cached tile downloaded at `downloaded` is due for a check with the server:
+/// `ttl` has passed since its download, or since the server last confirmed it unchanged at
+/// `checked` (0 if never). Times are seconds since the unix epoch
+pub fn tile_expired(downloaded: u64, checked: u64, now: u64, ttl: Duration) -> bool {
+    now.saturating_sub(downloaded.max(checked)) > ttl.as_secs()
+}
+
+/// Policy for the background maintenance of a disk cache of tiles
+#[derive(Clone, Debug)]
+pub struct MaintenancePolicy {
+    pub max_age: Duration,          // Tiles older than this are pruned
+    pub recompress_after: Duration, // Tiles older than this get re-encoded with best compression
+}
+
+impl MaintenancePolicy {
+    /// Whether a tile is pruned, so it gets downloaded fresh next time
+    /// A server confirming the tile unchanged counts as a fresh download, see tile_expired
+    pub fn prunes(&self, downloaded: u64, checked: u64, now: u64) -> bool {
+        tile_expired(downloaded, checked, now, self.max_age)
+    }
+
+    /// Whether a tile has been around long enough to be worth re-encoding
+    pub fn recompresses(&self, downloaded: u64, now: u64) -> bool {
+        now.saturating_sub(downloaded) > self.recompress_after.as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn tiles_expire_after_the_ttl_since_download_or_last_check() {
+        let ttl = Duration::from_secs(3600);
+        assert!(!tile_expired(10_000, 0, 13_000, ttl));
+        assert!(tile_expired(10_000, 0, 14_000, ttl));
+
+        // A 304 restarts the clock
+        assert!(!tile_expired(10_000, 13_500, 14_000, ttl));
+    }
+
+    #[test]
+    fn checked_tiles_are_pruned_later_but_recompressed_by_download_age() {
+        let policy = MaintenancePolicy {
+            max_age: Duration::from_secs(30 * DAY),
+            recompress_after: Duration::from_secs(7 * DAY),
+        };
+        let now = 100 * DAY;
+        assert!(policy.prunes(now - 31 * DAY, 0, now));
+        assert!(!policy.prunes(now - 31 * DAY, now - DAY, now));
+        assert!(!policy.recompresses(now - DAY, now));
+        assert!(policy.recompresses(now - 8 * DAY, now));
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet};
+use crate::tile::TileId;
+
+/// A tile download that is wanted or in flight, with its priority: lower numbers load first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileRequest {
+    pub tile: TileId,
+    pub priority: i32,
+    pub selected: bool, // Wanted by the current tile selection, not only in flight from an earlier one
+}
+
+/// The requests made redundant by a parent or its children being requested as well
+/// A parent whose four children are all requested at the same or a better priority is redundant,
+/// the children cover it in more detail by the time it would arrive. Otherwise a tile whose parent
+/// is selected at the same or a better priority is, the parent covers it sooner; a parent only in
+/// flight from an earlier selection leaves its children be, as the view moved on to them
+/// A tile requested twice counts with its best priority
+pub fn redundant_requests(requests: &[TileRequest]) -> HashSet<TileId> {
+    let mut best: HashMap<TileId, TileRequest> = HashMap::new();
+    for request in requests {
+        best.entry(request.tile)
+            .and_modify(|known| {
+                known.priority = known.priority.min(request.priority);
+                known.selected |= request.selected;
+            })
+            .or_insert(*request);
+    }
+
+    let covered = |request: &TileRequest| {
+        request.tile.children().iter().all(|child| best.get(child).is_some_and(|child| child.priority <= request.priority))
+    };
+    let mut redundant: HashSet<TileId> = best.values().filter(|request| covered(request)).map(|request| request.tile).collect();
+
+    let superseded: Vec<TileId> = best
+        .values()
+        .filter(|request| {
+            let parent = request.tile.parent().and_then(|parent| best.get(&parent));
+            parent.is_some_and(|parent| {
+                parent.selected && parent.priority <= request.priority && !redundant.contains(&parent.tile)
+            })
+        })
+        .map(|request| request.tile)
+        .collect();
+    redundant.extend(superseded);
+    redundant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(tile: TileId, priority: i32, selected: bool) -> TileRequest {
+        TileRequest { tile, priority, selected }
+    }
+
+    #[test]
+    fn parents_covered_by_their_children_are_redundant() {
+        let parent = TileId::new(8414, 5384, 14);
+        let children = parent.children();
+        let mut requests: Vec<_> = children.iter().map(|&child| request(child, 3, true)).collect();
+        requests.push(request(parent, 3, false));
+        assert_eq!(redundant_requests(&requests), HashSet::from([parent]));
+
+        // A child that loads later than the parent leaves the parent needed, and the
+        // parent being selected makes the children redundant instead
+        requests[0].priority = 9;
+        requests[4].selected = true;
+        assert_eq!(redundant_requests(&requests), HashSet::from(children));
+
+        // Three children don't cover the parent; a parent from an earlier selection
+        // doesn't stop the children the view moved on to
+        requests.remove(0);
+        requests[3].selected = false;
+        assert!(redundant_requests(&requests).is_empty());
+    }
+
+    #[test]
+    fn children_wait_for_a_better_parent() {
+        let parent = TileId::new(33, 21, 6);
+        let [first, second, ..] = parent.children();
+        let requests = [request(parent, 2, true), request(first, 2, false), request(second, 5, true), request(second, 1, false)];
+        // The second child counts with its best priority, better than the parent's
+        assert_eq!(redundant_requests(&requests), HashSet::from([first]));
+    }
+}
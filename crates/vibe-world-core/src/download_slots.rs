@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Why a tile is downloaded, each class has its own number of downloads at once
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DownloadClass {
+    Visible,    // Tiles in view
+    Prefetch,   // Tiles where the camera is heading
+    Background, // Low zoom context around the view
+}
+
+impl DownloadClass {
+    pub const ALL: [DownloadClass; 3] = [DownloadClass::Visible, DownloadClass::Prefetch, DownloadClass::Background];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DownloadClass::Visible => "visible",
+            DownloadClass::Prefetch => "prefetch",
+            DownloadClass::Background => "background",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Downloads allowed at once per class
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DownloadLimits {
+    pub visible: usize,
+    pub prefetch: usize,   // Kept low so prefetching doesn't starve visible tiles
+    pub background: usize,
+}
+
+impl Default for DownloadLimits {
+    fn default() -> Self {
+        Self {
+            visible: 16,
+            prefetch: 4,
+            background: 4,
+        }
+    }
+}
+
+impl DownloadLimits {
+    pub fn get(&self, class: DownloadClass) -> usize {
+        match class {
+            DownloadClass::Visible => self.visible,
+            DownloadClass::Prefetch => self.prefetch,
+            DownloadClass::Background => self.background,
+        }
+    }
+}
+
+/// Downloads in flight per class, counted from when a loader task is spawned until it finishes
+/// The count is shared with the tasks, which hold a DownloadSlot while they run
+#[derive(Clone, Debug, Default)]
+pub struct DownloadSlots {
+    pub limits: DownloadLimits,
+    in_flight: Arc<[AtomicUsize; 3]>,
+}
+
+impl DownloadSlots {
+    /// Downloads of a class currently in flight
+    pub fn in_flight(&self, class: DownloadClass) -> usize {
+        self.in_flight[class.index()].load(Ordering::Relaxed)
+    }
+
+    /// Whether another download of the class may start
+    pub fn has_free_slot(&self, class: DownloadClass) -> bool {
+        self.in_flight(class) < self.limits.get(class)
+    }
+
+    /// In flight and allowed downloads of every class, like "visible 3/16, prefetch 0/4, background 1/4"
+    pub fn summary(&self) -> String {
+        DownloadClass::ALL
+            .iter()
+            .map(|&class| format!("{} {}/{}", class.name(), self.in_flight(class), self.limits.get(class)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Count a download of the class as in flight until the returned slot is dropped
+    pub fn acquire(&self, class: DownloadClass) -> DownloadSlot {
+        self.in_flight[class.index()].fetch_add(1, Ordering::Relaxed);
+        DownloadSlot {
+            in_flight: self.in_flight.clone(),
+            class,
+        }
+    }
+}
+
+/// A download in flight, released when dropped
+#[derive(Debug)]
+pub struct DownloadSlot {
+    in_flight: Arc<[AtomicUsize; 3]>,
+    class: DownloadClass,
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        self.in_flight[self.class.index()].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_limited_per_class_and_released_on_drop() {
+        let slots = DownloadSlots {
+            limits: DownloadLimits {
+                visible: 2,
+                prefetch: 1,
+                background: 0,
+            },
+            ..Default::default()
+        };
+
+        let first = slots.acquire(DownloadClass::Visible);
+        let _second = slots.acquire(DownloadClass::Visible);
+        assert_eq!(slots.in_flight(DownloadClass::Visible), 2);
+        assert!(!slots.has_free_slot(DownloadClass::Visible));
+        // Other classes have slots of their own
+        assert!(slots.has_free_slot(DownloadClass::Prefetch));
+        assert!(!slots.has_free_slot(DownloadClass::Background));
+
+        drop(first);
+        assert_eq!(slots.in_flight(DownloadClass::Visible), 1);
+        assert!(slots.has_free_slot(DownloadClass::Visible));
+        assert_eq!(slots.summary(), "visible 1/2, prefetch 0/1, background 0/0");
+    }
+}
@@ -0,0 +1,16 @@
+//! The tile machinery of the viewer without a game engine: tile addresses and their bounds,
+//! the Web Mercator projection into world coordinates, picking tiles by their size on screen,
+//! and the policies for retrying failed tiles, sharing download slots, coalescing requests and
+//! expiring cached tiles
+//! The Bevy plugins of the viewer are built on top of it; other engines and tools can use it
+//! the same way, and its tests run without building Bevy
+
+pub mod tile;
+pub mod projection;
+pub mod lod;
+pub mod retry;
+pub mod download_slots;
+pub mod coalesce;
+pub mod cache_policy;
+
+pub use tile::{TileBounds, TileId, DEFAULT_ZOOM_LEVEL, MAX_ZOOM_LEVEL, MIN_ZOOM_LEVEL};
@@ -0,0 +1,413 @@
+use glam::{DVec2, DVec3};
+use crate::projection::{tile_world_origin, tile_world_size};
+use crate::tile::{TileId, MAX_ZOOM_LEVEL, MIN_ZOOM_LEVEL};
+
+// Fraction of its own size a tile may lie outside the footprint and still be selected,
+// so tiles at the edge of the view are loaded before they turn into view
+const FOOTPRINT_MARGIN: f64 = 0.25;
+
+/// Ground area the camera can see: the view frustum cut off at the horizon
+/// Its corners form a convex polygon, a trapezoid for a tilted view
+#[derive(Clone, Debug, Default)]
+pub struct ViewFootprint {
+    pub corners: Vec<DVec2>, // World X/Z, in order around the polygon
+}
+
+impl ViewFootprint {
+    /// Intersect rays around the edge of the view frustum with the ground
+    /// Rays missing the ground or hitting it beyond the horizon end at the horizon instead
+    pub fn from_rays(camera: DVec3, rays: impl IntoIterator<Item = DVec3>, horizon: f64) -> Self {
+        let origin = DVec2::new(camera.x, camera.z);
+        let corners = rays
+            .into_iter()
+            .filter_map(|ray| {
+                let horizontal = DVec2::new(ray.x, ray.z);
+                let direction = horizontal.try_normalize()?;
+                let ground = if ray.y < 0.0 {
+                    horizontal * (camera.y.max(0.0) / -ray.y)
+                } else {
+                    direction * horizon
+                };
+                Some(origin + ground.clamp_length_max(horizon))
+            })
+            .collect();
+        Self { corners }
+    }
+
+    /// Whether a world X/Z rectangle overlaps the footprint
+    /// A footprint without area, like the one of a camera looking straight up, contains nothing
+    pub fn intersects(&self, min: DVec2, max: DVec2) -> bool {
+        if self.corners.len() < 3 {
+            return false;
+        }
+
+        // Separating axis test: the rectangle's own axes, then the normals of the polygon edges
+        let (poly_min, poly_max) = self
+            .corners
+            .iter()
+            .fold((DVec2::MAX, DVec2::MIN), |(low, high), corner| (low.min(*corner), high.max(*corner)));
+        if poly_max.x < min.x || poly_min.x > max.x || poly_max.y < min.y || poly_min.y > max.y {
+            return false;
+        }
+
+        let rect = [min, DVec2::new(max.x, min.y), max, DVec2::new(min.x, max.y)];
+        for (index, start) in self.corners.iter().enumerate() {
+            let end = self.corners[(index + 1) % self.corners.len()];
+            let normal = (end - *start).perp();
+            let project = |points: &mut dyn Iterator<Item = DVec2>| {
+                points.fold((f64::MAX, f64::MIN), |(low, high), point| {
+                    let distance = normal.dot(point);
+                    (low.min(distance), high.max(distance))
+                })
+            };
+            let (poly_low, poly_high) = project(&mut self.corners.iter().copied());
+            let (rect_low, rect_high) = project(&mut rect.iter().copied());
+            if poly_high < rect_low || rect_high < poly_low {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Measures how large tiles appear on screen, to pick their level of detail
+/// A tile is split into its children only while it projects larger than max_tile_px,
+/// so detail follows what the camera actually sees instead of its height alone
+#[derive(Clone, Debug)]
+pub struct LodView {
+    pub camera: DVec3,    // Camera position in world units, relative to origin
+    pub origin: DVec2,    // World X/Z position the camera and footprint are relative to, e.g. a render origin
+    pub focal_px: f64,    // Pixels covered by one world unit at distance 1
+    pub max_tile_px: f64, // Tiles projecting larger than this are subdivided
+    pub footprint: Option<ViewFootprint>, // Tiles outside it are skipped, None selects all around the camera
+    pub detail_radius: f64, // Tiles closer than this are subdivided down to the highest zoom level
+}
+
+impl LodView {
+    pub fn new(camera: DVec3, fov_y: f32, viewport_height_px: f32, max_tile_px: f64) -> Self {
+        Self {
+            camera,
+            origin: DVec2::ZERO,
+            focal_px: viewport_height_px as f64 / (2.0 * (fov_y as f64 / 2.0).tan()),
+            max_tile_px,
+            footprint: None,
+            detail_radius: 0.0,
+        }
+    }
+
+    /// Measure the camera and footprint from a world position instead of the world's corner
+    pub fn with_origin(mut self, origin: DVec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Restrict tile selection to the ground the camera can see
+    pub fn with_footprint(mut self, footprint: ViewFootprint) -> Self {
+        self.footprint = Some(footprint);
+        self
+    }
+
+    /// Show the ground within `radius` world units of the camera in full detail, however small
+    /// its tiles appear, as walk mode does around the player
+    pub fn with_detail_radius(mut self, radius: f64) -> Self {
+        self.detail_radius = radius;
+        self
+    }
+
+    /// Whether a tile overlaps the view footprint, with a margin around it
+    pub fn sees(&self, id: TileId) -> bool {
+        let Some(footprint) = &self.footprint else {
+            return true;
+        };
+        let size = tile_world_size(id.z);
+        let origin = tile_world_origin(id) - self.origin;
+        let margin = DVec2::splat(size * FOOTPRINT_MARGIN);
+        footprint.intersects(origin - margin, origin + DVec2::splat(size) + margin)
+    }
+
+    /// Distance from the camera to the nearest point of a tile on the ground
+    pub fn distance_to(&self, id: TileId) -> f64 {
+        let origin = tile_world_origin(id) - self.origin;
+        let size = tile_world_size(id.z);
+        let nearest = DVec2::new(
+            self.camera.x.clamp(origin.x, origin.x + size),
+            self.camera.z.clamp(origin.y, origin.y + size),
+        );
+        DVec3::new(self.camera.x - nearest.x, self.camera.y, self.camera.z - nearest.y).length()
+    }
+
+    /// Size in pixels of a tile seen from the camera, measured at its nearest point
+    pub fn projected_size(&self, id: TileId) -> f64 {
+        tile_world_size(id.z) * self.focal_px / self.distance_to(id).max(f64::EPSILON)
+    }
+
+    /// Download priority of a tile, lower loads first: its distance in tiles of the zoom level
+    /// right below the camera, None if the view doesn't see it
+    pub fn download_priority(&self, id: TileId) -> Option<i32> {
+        if !self.sees(id) {
+            return None;
+        }
+        let base_tile_size = tile_world_size(self.zoom_at_distance(self.camera.y.max(0.0)));
+        Some((self.distance_to(id) / base_tile_size) as i32)
+    }
+
+    /// Zoom level a tile at the given distance from the camera is selected at
+    pub fn zoom_at_distance(&self, distance: f64) -> u32 {
+        let mut zoom = MIN_ZOOM_LEVEL;
+        while zoom < MAX_ZOOM_LEVEL
+            && tile_world_size(zoom) * self.focal_px / distance.max(f64::EPSILON) > self.max_tile_px
+        {
+            zoom += 1;
+        }
+        zoom
+    }
+}
+
+/// Walk the tile quadtree down from the root tiles, subdividing a tile only while its
+/// projected size exceeds the view's max_tile_px (or it is within the detail radius), and return the leaves
+/// Tiles near the camera end up detailed, tiles towards the horizon stay coarse,
+/// and tiles outside the view footprint are left out altogether
+pub fn select_lod_tiles(view: &LodView, roots: &[TileId], max_zoom: u32) -> Vec<TileId> {
+    let mut selected = Vec::new();
+    let mut stack = roots.to_vec();
+    while let Some(tile) = stack.pop() {
+        if !view.sees(tile) {
+            continue;
+        }
+        let too_large = view.projected_size(tile) > view.max_tile_px || view.distance_to(tile) < view.detail_radius;
+        if tile.z < max_zoom && too_large {
+            stack.extend(tile.children());
+        } else {
+            selected.push(tile);
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    // Camera above the middle of the zoom 13 tile (4216, 2668)
+    fn view_from(height: f64, max_tile_px: f64) -> LodView {
+        LodView::new(DVec3::new(4216.5, height, 2668.5), std::f32::consts::FRAC_PI_2, 1080.0, max_tile_px)
+    }
+
+    #[test]
+    fn looking_down_selects_tiles_no_larger_than_the_threshold() {
+        let view = view_from(50.0, 256.0);
+        let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+        let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+        let below = view.zoom_at_distance(50.0);
+        for tile in &tiles {
+            assert!(view.projected_size(*tile) <= 256.0, "{tile:?} is too detailed for its distance");
+            assert!(tile.z <= below, "{tile:?} is more detailed than the tile below the camera");
+        }
+        assert!(tiles.iter().any(|tile| tile.z == below));
+    }
+
+    #[test]
+    fn tiles_within_the_detail_radius_are_at_the_highest_zoom() {
+        let view = view_from(0.0005, 256.0).with_detail_radius(0.2);
+        let root = TileId::new(4216 >> 3, 2668 >> 3, 10);
+        let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+        for tile in &tiles {
+            if view.distance_to(*tile) < 0.2 {
+                assert_eq!(tile.z, MAX_ZOOM_LEVEL, "{tile:?} is within the detail radius");
+            }
+        }
+        assert!(tiles.iter().any(|tile| tile.z < MAX_ZOOM_LEVEL));
+    }
+
+    #[test]
+    fn distant_tiles_are_coarser_than_near_ones() {
+        let view = view_from(5.0, 256.0);
+        let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+        let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+        let nearest = tiles.iter().min_by(|a, b| view.distance_to(**a).total_cmp(&view.distance_to(**b))).unwrap();
+        let farthest = tiles.iter().max_by(|a, b| view.distance_to(**a).total_cmp(&view.distance_to(**b))).unwrap();
+        assert!(nearest.z > farthest.z + 3);
+    }
+
+    #[test]
+    fn selected_tiles_cover_the_root_exactly_once() {
+        let view = view_from(20.0, 256.0);
+        let root = TileId::new(4216 >> 6, 2668 >> 6, 7);
+        let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+        // Areas in units of the smallest selected tile
+        let max_z = tiles.iter().map(|tile| tile.z).max().unwrap();
+        let area: u64 = tiles.iter().map(|tile| 1u64 << (2 * (max_z - tile.z))).sum();
+        assert_eq!(area, 1u64 << (2 * (max_z - root.z)));
+    }
+
+    #[test]
+    fn tilted_view_skips_tiles_behind_the_camera_and_beyond_the_horizon() {
+        // Looking north (-Z) from 2 units up, bottom of the view 45 degrees down, top above the horizon
+        let camera = DVec3::new(4216.5, 2.0, 2668.5);
+        let rays = [
+            DVec3::new(-1.0, -1.0, -1.0),
+            DVec3::new(1.0, -1.0, -1.0),
+            DVec3::new(1.0, 0.5, -1.0),
+            DVec3::new(-1.0, 0.5, -1.0),
+        ];
+        let footprint = ViewFootprint::from_rays(camera, rays, 40.0);
+        let view = view_from(2.0, 256.0).with_footprint(footprint);
+        let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+        let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+        assert!(!tiles.is_empty());
+        for tile in &tiles {
+            let origin = tile_world_origin(*tile);
+            let size = tile_world_size(tile.z);
+            assert!(origin.y <= camera.z + size, "{tile:?} is behind the camera");
+            assert!(view.distance_to(*tile) <= 40.0 * 1.5, "{tile:?} is beyond the horizon");
+        }
+    }
+
+    #[test]
+    fn download_priorities_follow_the_distance_and_the_view() {
+        // Looking north (-Z) from 2 units up, as above
+        let camera = DVec3::new(4216.5, 2.0, 2668.5);
+        let rays = [
+            DVec3::new(-1.0, -1.0, -1.0),
+            DVec3::new(1.0, -1.0, -1.0),
+            DVec3::new(1.0, 0.5, -1.0),
+            DVec3::new(-1.0, 0.5, -1.0),
+        ];
+        let view = view_from(2.0, 256.0).with_footprint(ViewFootprint::from_rays(camera, rays, 40.0));
+
+        let near = view.download_priority(TileId::new(4216, 2666, 13)).unwrap();
+        let far = view.download_priority(TileId::new(4216, 2660, 13)).unwrap();
+        assert!(near < far, "{near} should load before {far}");
+        // Behind the camera, e.g. after it turned around
+        assert_eq!(view.download_priority(TileId::new(4216, 2675, 13)), None);
+    }
+
+    #[test]
+    fn views_relative_to_an_origin_select_the_same_tiles() {
+        let rays = [
+            DVec3::new(-1.0, -1.0, -1.0),
+            DVec3::new(1.0, -1.0, -1.0),
+            DVec3::new(1.0, 0.5, -1.0),
+            DVec3::new(-1.0, 0.5, -1.0),
+        ];
+        let absolute = view_from(2.0, 256.0).with_footprint(ViewFootprint::from_rays(DVec3::new(4216.5, 2.0, 2668.5), rays, 40.0));
+
+        // The same camera with a render origin moved right next to it
+        let origin = DVec2::new(4200.0, 2650.0);
+        let camera = DVec3::new(16.5, 2.0, 18.5);
+        let relative = LodView::new(camera, std::f32::consts::FRAC_PI_2, 1080.0, 256.0)
+            .with_origin(origin)
+            .with_footprint(ViewFootprint::from_rays(camera, rays, 40.0));
+
+        let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+        assert_eq!(select_lod_tiles(&relative, &[root], MAX_ZOOM_LEVEL), select_lod_tiles(&absolute, &[root], MAX_ZOOM_LEVEL));
+        let tile = TileId::new(4216, 2666, 13);
+        assert_eq!(relative.download_priority(tile), absolute.download_priority(tile));
+    }
+
+    // Whether any selected tile lies inside another one, or is listed twice
+    fn overlapping(tiles: &[TileId]) -> Option<TileId> {
+        let mut selected = HashSet::new();
+        if let Some(twice) = tiles.iter().copied().find(|tile| !selected.insert(*tile)) {
+            return Some(twice);
+        }
+        tiles.iter().copied().find(|tile| {
+            let mut ancestor = *tile;
+            while let Some(parent) = ancestor.parent() {
+                if selected.contains(&parent) {
+                    return true;
+                }
+                ancestor = parent;
+            }
+            false
+        })
+    }
+
+    // Whether a tile covers a world X/Z point
+    fn covers(tile: TileId, point: DVec2) -> bool {
+        let origin = tile_world_origin(tile);
+        let size = tile_world_size(tile.z);
+        (origin.x..origin.x + size).contains(&point.x) && (origin.y..origin.y + size).contains(&point.y)
+    }
+
+    proptest! {
+        #[test]
+        fn leaves_cover_the_root_once_and_stay_within_bounds(
+            x in 4000.0f64..4450.0,
+            z in 2500.0f64..2900.0,
+            height in 0.001f64..50.0,
+            max_tile_px in 128.0f64..512.0,
+            viewport_px in 480.0f32..2160.0,
+        ) {
+            let view = LodView::new(DVec3::new(x, height, z), std::f32::consts::FRAC_PI_2, viewport_px, max_tile_px);
+            let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+            let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+            prop_assert_eq!(overlapping(&tiles), None);
+            let max_z = tiles.iter().map(|tile| tile.z).max().unwrap();
+            let area: u64 = tiles.iter().map(|tile| 1u64 << (2 * (max_z - tile.z))).sum();
+            prop_assert_eq!(area, 1u64 << (2 * (max_z - root.z)));
+
+            // A tile is only split while its nearest point is within size * focal / max_tile_px
+            // of the camera, which fits a bounded number of tiles per zoom level, and every split
+            // adds three tiles
+            let splits_per_level = (2.0 * view.focal_px / max_tile_px + 2.0).powi(2);
+            let bound = 1.0 + 3.0 * splits_per_level * (MAX_ZOOM_LEVEL - root.z) as f64;
+            prop_assert!((tiles.len() as f64) <= bound, "{} tiles, bound {}", tiles.len(), bound);
+        }
+
+        #[test]
+        fn ground_in_view_is_covered_by_exactly_one_tile(
+            x in 4100.0f64..4340.0,
+            z in 2600.0f64..2800.0,
+            height in 0.01f64..5.0,
+            heading in 0.0f64..std::f64::consts::TAU,
+            weights in proptest::collection::vec(0.01f64..1.0, 4),
+        ) {
+            // A view tilted 45 degrees down, turned to the heading
+            let camera = DVec3::new(x, height, z);
+            let (sin, cos) = heading.sin_cos();
+            let turn = |right: f64, up: f64| DVec3::new(cos * right - sin, up, -sin * right - cos);
+            let rays = [turn(-1.0, -1.5), turn(1.0, -1.5), turn(1.0, -0.5), turn(-1.0, -0.5)];
+            let footprint = ViewFootprint::from_rays(camera, rays, 20.0);
+            let corners = footprint.corners.clone();
+            let view = LodView::new(camera, std::f32::consts::FRAC_PI_2, 1080.0, 256.0).with_footprint(footprint);
+            let root = TileId::new(4216 >> 8, 2668 >> 8, 5);
+            let tiles = select_lod_tiles(&view, &[root], MAX_ZOOM_LEVEL);
+
+            prop_assert_eq!(overlapping(&tiles), None);
+            // A weighted average of the corners of the convex footprint lies inside it
+            let total: f64 = weights.iter().sum();
+            let point = corners.iter().zip(&weights).map(|(corner, weight)| *corner * *weight).sum::<DVec2>() / total;
+            if covers(root, point) {
+                let covering = tiles.iter().filter(|tile| covers(**tile, point)).count();
+                prop_assert_eq!(covering, 1, "{:?} is covered by {} tiles", point, covering);
+            }
+        }
+
+        #[test]
+        fn paths_down_the_quadtree_round_trip(x in 0u32..(1 << 19), y in 0u32..(1 << 19), z in 0u32..=19) {
+            let tile = TileId::new(x >> (19 - z), y >> (19 - z), z);
+
+            // Up to the root through parents, and back down through children
+            let mut path = vec![tile];
+            while let Some(parent) = path.last().unwrap().parent() {
+                path.push(parent);
+            }
+            prop_assert_eq!(*path.last().unwrap(), TileId::new(0, 0, 0));
+            prop_assert_eq!(path.len() as u32, z + 1);
+            for pair in path.windows(2) {
+                let (child, parent) = (pair[0], pair[1]);
+                prop_assert!(parent.children().contains(&child));
+                prop_assert!(parent.children().iter().all(|sibling| sibling.parent() == Some(parent)));
+            }
+        }
+    }
+}
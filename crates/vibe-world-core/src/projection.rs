@@ -0,0 +1,455 @@
+use std::f64::consts::PI;
+use glam::DVec2;
+use crate::tile::{max_tile_index, TileId, DEFAULT_ZOOM_LEVEL};
+
+// Equatorial circumference of the WGS84 ellipsoid
+const EARTH_CIRCUMFERENCE_M: f64 = 40_075_016.686;
+// Mean radius of the earth, for horizon distances
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+/// Latitude limit of Web Mercator, where the square map ends: beyond it the projection runs
+/// off to infinity at the poles
+pub const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+// All geographic math is done in f64 - f32 misplaces tiles by meters at zoom 17+
+// Convert to f32 only when writing Transforms or mesh vertices
+// World coordinates here are absolute: an engine rendering relative to a floating origin
+// subtracts it itself
+
+/// Number of world units spanning the full map width
+/// World coordinates are tile indexes at DEFAULT_ZOOM_LEVEL, so the world is 2^zoom units wide
+pub fn world_size() -> f64 {
+    (1u64 << DEFAULT_ZOOM_LEVEL) as f64
+}
+
+/// Convert a WGS84 latitude/longitude (degrees) to world X/Z coordinates
+/// using the Web Mercator projection that OSM tiles are rendered in
+pub fn lat_lon_to_world(lat: f64, lon: f64) -> DVec2 {
+    let size = world_size();
+    let lat_rad = clamp_latitude(lat).to_radians();
+
+    let x = (lon + 180.0) / 360.0 * size;
+    let z = (1.0 - lat_rad.tan().asinh() / PI) / 2.0 * size;
+
+    DVec2::new(x, z)
+}
+
+/// Convert world X/Z coordinates back to a WGS84 latitude/longitude (degrees)
+pub fn world_to_lat_lon(x: f64, z: f64) -> (f64, f64) {
+    let size = world_size();
+    let lon = x / size * 360.0 - 180.0;
+    let n = PI * (1.0 - 2.0 * z / size);
+    let lat = n.sinh().atan().to_degrees();
+
+    (lat, lon)
+}
+
+/// Keep a latitude on the map, see MAX_LATITUDE; NaN ends up at the equator
+pub fn clamp_latitude(lat: f64) -> f64 {
+    if lat.is_nan() { 0.0 } else { lat.clamp(-MAX_LATITUDE, MAX_LATITUDE) }
+}
+
+/// Size of a tile at the given zoom level in world units
+pub fn tile_world_size(zoom: u32) -> f64 {
+    2_f64.powi(DEFAULT_ZOOM_LEVEL as i32 - zoom as i32)
+}
+
+/// Ground distance in meters covered by one world unit at the given latitude
+/// Web Mercator stretches distances by 1/cos(lat), so this shrinks towards the poles
+pub fn meters_per_world_unit(lat: f64) -> f64 {
+    EARTH_CIRCUMFERENCE_M * lat.to_radians().cos() / world_size()
+}
+
+/// Distance in meters to the horizon seen from an altitude in meters
+/// The map is flat, the earth isn't: ground beyond this is hidden by its curvature
+pub fn horizon_distance_m(altitude_m: f64) -> f64 {
+    let altitude = altitude_m.max(0.0);
+    (altitude * (2.0 * EARTH_RADIUS_M + altitude)).sqrt()
+}
+
+/// Great-circle distance in meters between two (lat, lon) points, on a spherical earth
+/// Unlike distances on the map this isn't stretched by the projection
+pub fn geodesic_distance_m(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let half_lat = (lat2 - lat1) / 2.0;
+    let half_lon = normalize_lon(b.1 - a.1).to_radians() / 2.0;
+
+    // Haversine formula
+    let h = half_lat.sin().powi(2) + lat1.cos() * lat2.cos() * half_lon.sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().min(1.0).asin()
+}
+
+/// Area in square meters enclosed by a polygon of (lat, lon) points, on a spherical earth
+/// The polygon closes itself from the last point back to the first
+pub fn geodesic_area_m2(points: &[(f64, f64)]) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    // Sum of the areas between every edge and the equator, from Chamberlain & Duquette,
+    // "Some algorithms for polygons on a sphere"
+    let mut total = 0.0;
+    for (i, &(lat1, lon1)) in points.iter().enumerate() {
+        let (lat2, lon2) = points[(i + 1) % points.len()];
+        let lon_step = normalize_lon(lon2 - lon1).to_radians();
+        total += lon_step * (2.0 + lat1.to_radians().sin() + lat2.to_radians().sin());
+    }
+    (total * EARTH_RADIUS_M * EARTH_RADIUS_M / 2.0).abs()
+}
+
+/// World X/Z position of a tile's northwest corner
+pub fn tile_world_origin(id: TileId) -> DVec2 {
+    let size = tile_world_size(id.z);
+    DVec2::new(id.column() as f64 * size, id.y as f64 * size)
+}
+
+/// Convert camera world coordinates to OSM tile coordinates
+pub fn world_to_tile_coords(x: f64, z: f64, zoom: u32) -> (u32, u32) {
+    // OSM tile coordinate system has (0,0) at northwest corner
+    // X increases eastward, Y increases southward
+    // Our world coordinate system has:
+    // - X increases eastward (same as OSM X)
+    // - Z increases southward (maps directly to OSM Y)
+
+    // OSM zoom level scaling - at each level, number of tiles doubles in each dimension
+    // At zoom level 0, the world is 1 tile
+    // At zoom level 1, the world is 2x2 tiles
+    // At zoom level 2, the world is 4x4 tiles
+    // And so on - each zoom level multiplies tile count by 2^(zoom difference)
+
+    // Our world coordinates are based on tile indexes at DEFAULT_ZOOM_LEVEL
+    // Scale them to match the requested zoom level
+
+    // For example, if DEFAULT_ZOOM_LEVEL is 13 and zoom is 14:
+    // - Each DEFAULT_ZOOM_LEVEL tile becomes 2x2 tiles at zoom 14
+    // - So we multiply coordinates by 2
+
+    // If DEFAULT_ZOOM_LEVEL is 13 and zoom is 12:
+    // - Each 2x2 tile block at DEFAULT_ZOOM_LEVEL becomes 1 tile at zoom 12
+    // - So we divide coordinates by 2
+
+    let zoom_difference = zoom as i32 - DEFAULT_ZOOM_LEVEL as i32;
+    let scale_factor = 2_f64.powi(zoom_difference);
+
+    // Scale world coordinates to the target zoom level
+    // Done in f64 - at zoom 19 a tile is only 1/64th of a world unit
+    let scaled_x = x * scale_factor;
+    let scaled_z = z * scale_factor;
+
+    // Rows are clamped to the valid tile range for this zoom level (clamping as floats also
+    // keeps negative coordinates at tile 0); columns continue across the antimeridian,
+    // see TileId
+    let max_index = max_tile_index(zoom) as f64;
+    let tile_x = scaled_x.floor() as i64 as u32;
+    let tile_y = scaled_z.floor().clamp(0.0, max_index) as u32;
+
+    (tile_x, tile_y)
+}
+
+/// How overlay geometry crossing the ±180° meridian is handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AntimeridianMode {
+    /// Cut lines where they cross, so every piece stays within -180..180
+    #[default]
+    Split,
+    /// Keep lines in one piece by continuing longitudes past ±180
+    Wrap,
+}
+
+impl AntimeridianMode {
+    pub const ALL: [AntimeridianMode; 2] = [AntimeridianMode::Split, AntimeridianMode::Wrap];
+
+    /// Name in settings files
+    pub fn key(self) -> &'static str {
+        match self {
+            AntimeridianMode::Split => "split",
+            AntimeridianMode::Wrap => "wrap",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+}
+
+/// A lat/lon bounding box that may cross the antimeridian
+/// When it does, west is greater than east
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoBounds {
+    pub north: f64,
+    pub south: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+impl GeoBounds {
+    /// Smallest box containing all (lat, lon) points
+    /// The box crosses the antimeridian if that is narrower than going the other way round
+    pub fn from_points(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Self> {
+        let mut lons = Vec::new();
+        let (mut north, mut south) = (f64::MIN, f64::MAX);
+        for (lat, lon) in points {
+            north = north.max(lat);
+            south = south.min(lat);
+            lons.push(normalize_lon(lon));
+        }
+        if lons.is_empty() {
+            return None;
+        }
+        lons.sort_by(f64::total_cmp);
+
+        // The box is everything except the largest gap between neighbouring longitudes
+        let mut largest_gap = lons[0] + 360.0 - lons[lons.len() - 1];
+        let mut gap_end = 0;
+        for i in 1..lons.len() {
+            let gap = lons[i] - lons[i - 1];
+            if gap > largest_gap {
+                largest_gap = gap;
+                gap_end = i;
+            }
+        }
+
+        let (west, east) = if gap_end == 0 {
+            (lons[0], lons[lons.len() - 1])
+        } else {
+            (lons[gap_end], lons[gap_end - 1])
+        };
+
+        Some(Self { north, south, west, east })
+    }
+
+    /// Whether the box crosses the ±180° meridian
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.west > self.east
+    }
+
+    /// Width of the box in degrees of longitude
+    pub fn lon_span(&self) -> f64 {
+        if self.crosses_antimeridian() {
+            self.east + 360.0 - self.west
+        } else {
+            self.east - self.west
+        }
+    }
+
+    /// Centre of the box as (lat, lon), with the longitude in -180..180
+    pub fn center(&self) -> (f64, f64) {
+        let lat = (self.north + self.south) / 2.0;
+        let lon = normalize_lon(self.west + self.lon_span() / 2.0);
+        (lat, lon)
+    }
+
+    /// Whether a point lies inside the box
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        let lon = normalize_lon(lon);
+        let lon_inside = if self.crosses_antimeridian() {
+            lon >= self.west || lon <= self.east
+        } else {
+            (self.west..=self.east).contains(&lon)
+        };
+        lon_inside && (self.south..=self.north).contains(&lat)
+    }
+}
+
+/// Wrap a longitude into -180..180
+pub fn normalize_lon(lon: f64) -> f64 {
+    let wrapped = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    // Keep +180 as is instead of turning it into -180
+    if wrapped == -180.0 && lon > 0.0 { 180.0 } else { wrapped }
+}
+
+/// Make the longitudes of a (lat, lon) line continuous, so a step from 179° to -179°
+/// becomes a step to 181° instead of a jump across the whole world
+pub fn unwrap_longitudes(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut unwrapped = Vec::with_capacity(points.len());
+    let mut previous: Option<f64> = None;
+
+    for &(lat, lon) in points {
+        let lon = match previous {
+            Some(prev) => prev + normalize_lon(lon - prev),
+            None => normalize_lon(lon),
+        };
+        unwrapped.push((lat, lon));
+        previous = Some(lon);
+    }
+
+    unwrapped
+}
+
+/// Cut a (lat, lon) line where it crosses the antimeridian
+/// The crossing point is interpolated and added to both pieces so no gap appears
+pub fn split_at_antimeridian(points: &[(f64, f64)]) -> Vec<Vec<(f64, f64)>> {
+    let mut pieces = Vec::new();
+    let mut current: Vec<(f64, f64)> = Vec::new();
+
+    for window in unwrap_longitudes(points).windows(2) {
+        let (lat0, lon0) = window[0];
+        let (lat1, lon1) = window[1];
+        if current.is_empty() {
+            current.push((lat0, normalize_lon(lon0)));
+        }
+
+        // Which 360° band each end is in; a change means the segment crosses ±180
+        let band0 = ((lon0 + 180.0) / 360.0).floor();
+        let band1 = ((lon1 + 180.0) / 360.0).floor();
+        if band0 != band1 {
+            let edge = band0.max(band1) * 360.0 - 180.0;
+            let t = (edge - lon0) / (lon1 - lon0);
+            let lat = lat0 + (lat1 - lat0) * t;
+            let side = if lon1 > lon0 { 180.0 } else { -180.0 };
+
+            current.push((lat, side));
+            pieces.push(std::mem::take(&mut current));
+            current.push((lat, -side));
+        }
+
+        current.push((lat1, normalize_lon(lon1)));
+    }
+
+    if current.len() >= 2 {
+        pieces.push(current);
+    }
+    pieces.retain(|piece| piece.len() >= 2);
+    pieces
+}
+
+/// Prepare a (lat, lon) line for projection according to the antimeridian mode
+pub fn prepare_line(points: &[(f64, f64)], mode: AntimeridianMode) -> Vec<Vec<(f64, f64)>> {
+    match mode {
+        AntimeridianMode::Split => split_at_antimeridian(points),
+        AntimeridianMode::Wrap if points.len() >= 2 => vec![unwrap_longitudes(points)],
+        AntimeridianMode::Wrap => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_origin_matches_projected_corner_at_high_zoom() {
+        // A zoom 19 tile is ~0.016 world units wide; f32 world math was off by a sizeable
+        // fraction of that far from the origin
+        for id in [
+            TileId::new(271_720, 170_256, 19), // Groningen
+            TileId::new(482_000, 321_000, 19), // Sydney
+            TileId::new(524_287, 524_287, 19), // South-east corner of the world
+        ] {
+            let bounds = id.bounds();
+            let corner = lat_lon_to_world(bounds.north, bounds.west);
+            let origin = tile_world_origin(id);
+
+            assert!((corner - origin).length() < 1e-9, "{:?}: {:?} != {:?}", id, corner, origin);
+        }
+    }
+
+    #[test]
+    fn poles_are_clamped_to_the_map_edges() {
+        let north = lat_lon_to_world(90.0, 0.0);
+        let south = lat_lon_to_world(-90.0, 0.0);
+        assert!(north.y.abs() < 1e-9 && (south.y - world_size()).abs() < 1e-9);
+        assert_eq!(lat_lon_to_world(f64::NAN, 0.0).y, world_size() / 2.0);
+
+        let (lat, _) = world_to_lat_lon(north.x, north.y);
+        assert!((lat - MAX_LATITUDE).abs() < 1e-9);
+    }
+
+    #[test]
+    fn world_lat_lon_round_trip() {
+        for (lat, lon) in [(53.2194, 6.5665), (-33.8688, 151.2093), (0.0, 0.0), (85.0, -179.9)] {
+            let world = lat_lon_to_world(lat, lon);
+            let (lat2, lon2) = world_to_lat_lon(world.x, world.y);
+            assert!((lat - lat2).abs() < 1e-9 && (lon - lon2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn geodesic_distance_and_area() {
+        // One degree of latitude is about 111 km anywhere
+        assert!((geodesic_distance_m((52.0, 5.0), (53.0, 5.0)) - 111_195.0).abs() < 10.0);
+        // Across the antimeridian the short way round is taken
+        assert!(geodesic_distance_m((0.0, 179.5), (0.0, -179.5)) < 112_000.0);
+
+        // A one degree box on the equator, wound either way
+        let square = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)];
+        let area = geodesic_area_m2(&square);
+        assert!((area / 1e6 - 12_364.0).abs() < 5.0, "{}", area / 1e6);
+        let reversed: Vec<_> = square.iter().rev().copied().collect();
+        assert!((geodesic_area_m2(&reversed) - area).abs() < 1.0);
+        assert_eq!(geodesic_area_m2(&square[..2]), 0.0);
+    }
+
+    #[test]
+    fn bounds_across_antimeridian_are_narrow() {
+        // Fiji to Samoa
+        let bounds = GeoBounds::from_points([(-18.1, 178.4), (-13.8, -171.8), (-17.0, 179.9)]).unwrap();
+        assert!(bounds.crosses_antimeridian());
+        assert!((bounds.lon_span() - 9.8).abs() < 1e-9);
+        assert!(bounds.contains(-15.0, -179.0));
+        assert!(!bounds.contains(-15.0, 0.0));
+
+        let (_, lon) = bounds.center();
+        assert!(!(-170.0..170.0).contains(&lon), "centre {} is not in the Pacific", lon);
+    }
+
+    #[test]
+    fn lines_are_split_at_antimeridian() {
+        let line = [(0.0, 179.0), (2.0, -179.0), (4.0, -178.0)];
+
+        let pieces = split_at_antimeridian(&line);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].last(), Some(&(1.0, 180.0)));
+        assert_eq!(pieces[1].first(), Some(&(1.0, -180.0)));
+
+        let wrapped = prepare_line(&line, AntimeridianMode::Wrap);
+        assert_eq!(wrapped, vec![vec![(0.0, 179.0), (2.0, 181.0), (4.0, 182.0)]]);
+    }
+
+    #[test]
+    fn tile_world_size_halves_per_zoom_level() {
+        assert_eq!(tile_world_size(DEFAULT_ZOOM_LEVEL), 1.0);
+        assert_eq!(tile_world_size(DEFAULT_ZOOM_LEVEL + 1), 0.5);
+        assert_eq!(tile_world_size(DEFAULT_ZOOM_LEVEL - 3), 8.0);
+    }
+
+    #[test]
+    fn world_unit_is_a_zoom_13_tile_on_the_ground() {
+        // A zoom 13 tile is ~4.9 km wide at the equator and half that at 60 degrees
+        assert!((meters_per_world_unit(0.0) - 4891.97).abs() < 0.01);
+        assert!((meters_per_world_unit(60.0) - meters_per_world_unit(0.0) / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn world_coordinates_map_to_reference_tiles() {
+        // The Grote Markt in Groningen, reference values from the OSM tile calculator
+        let world = lat_lon_to_world(53.2194, 6.5665);
+        assert!((world.x - 4245.424356).abs() < 1e-6 && (world.y - 2660.24205).abs() < 1e-6);
+        assert_eq!(world_to_tile_coords(world.x, world.y, 10), (530, 332));
+        assert_eq!(world_to_tile_coords(world.x, world.y, 13), (4245, 2660));
+        assert_eq!(world_to_tile_coords(world.x, world.y, 16), (33963, 21281));
+        assert_eq!(world_to_tile_coords(world.x, world.y, 19), (271_707, 170_255));
+
+        // World coordinates are zoom 13 tile indexes: corners belong to the tile to their southeast
+        assert_eq!(world_to_tile_coords(4245.0, 2660.0, 13), (4245, 2660));
+        assert_eq!(world_to_tile_coords(4245.0, 2660.0, 14), (8490, 5320));
+        assert_eq!(world_to_tile_coords(4245.999, 2660.999, 12), (2122, 1330));
+    }
+
+    #[test]
+    fn rows_are_clamped_and_columns_continue_past_the_map() {
+        let last = max_tile_index(5);
+        assert_eq!(world_to_tile_coords(10.0, -3.0, 5).1, 0);
+        assert_eq!(world_to_tile_coords(10.0, world_size() + 3.0, 5).1, last);
+
+        // West of the antimeridian the column is negative, east of it past the last one
+        let (west, _) = world_to_tile_coords(-0.5, 100.0, 5);
+        assert_eq!(TileId::new(west, 0, 5).column(), -1);
+        assert_eq!(world_to_tile_coords(world_size() + 0.5, 100.0, 5).0, last + 1);
+    }
+
+    #[test]
+    fn horizon_from_a_plane_is_a_few_hundred_km_away() {
+        assert_eq!(horizon_distance_m(0.0), 0.0);
+        assert!((horizon_distance_m(10_000.0) / 1000.0 - 357.1).abs() < 0.1);
+    }
+}
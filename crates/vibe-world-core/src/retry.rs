@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+// Marks a retry that has been requested and not answered yet
+const IN_FLIGHT: f32 = f32::INFINITY;
+
+/// Retry state of a tile that failed to load
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryEntry {
+    pub attempts: u32,     // Failed loads so far
+    pub next_attempt: f32, // Elapsed seconds when the next load is due
+}
+
+/// Failed tiles waiting to be loaded again, with exponential backoff between attempts
+/// Keyed by (x, y, zoom); tiles that keep failing are given up after max_attempts
+pub struct TileRetries {
+    pub entries: HashMap<(u32, u32, u32), RetryEntry>,
+    pub base_delay: f32,  // Seconds before the first retry, doubled for every further one
+    pub max_delay: f32,
+    pub max_attempts: u32,
+}
+
+impl Default for TileRetries {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            base_delay: 2.0,
+            max_delay: 120.0,
+            max_attempts: 6,
+        }
+    }
+}
+
+impl TileRetries {
+    /// Count a failed load and schedule the next attempt
+    /// Returns false once the tile has failed too often to be retried
+    pub fn record_failure(&mut self, key: (u32, u32, u32), now: f32) -> bool {
+        let entry = self.entries.entry(key).or_insert(RetryEntry {
+            attempts: 0,
+            next_attempt: now,
+        });
+        entry.attempts += 1;
+        if entry.attempts >= self.max_attempts {
+            entry.next_attempt = IN_FLIGHT;
+            return false;
+        }
+
+        let delay = self.base_delay * 2f32.powi(entry.attempts as i32 - 1);
+        entry.next_attempt = now + delay.min(self.max_delay);
+        true
+    }
+
+    /// Never retry a tile, e.g. one the server doesn't have
+    pub fn give_up(&mut self, key: (u32, u32, u32)) {
+        self.entries.insert(key, RetryEntry {
+            attempts: self.max_attempts,
+            next_attempt: IN_FLIGHT,
+        });
+    }
+
+    /// Forget a tile, e.g. after it loaded
+    pub fn remove(&mut self, key: (u32, u32, u32)) {
+        self.entries.remove(&key);
+    }
+
+    /// Tiles whose retry is due, which count as in flight from now on
+    pub fn take_due(&mut self, now: f32) -> Vec<(u32, u32, u32)> {
+        let due: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.next_attempt <= now)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &due {
+            if let Some(entry) = self.entries.get_mut(key) {
+                entry.next_attempt = IN_FLIGHT;
+            }
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: (u32, u32, u32) = (4245, 2660, 13);
+
+    #[test]
+    fn retries_back_off_exponentially() {
+        let mut retries = TileRetries::default();
+
+        assert!(retries.record_failure(TILE, 10.0));
+        assert!(retries.take_due(11.9).is_empty());
+        assert_eq!(retries.take_due(12.0), vec![TILE]);
+        // Nothing is due again while the retry is in flight
+        assert!(retries.take_due(1000.0).is_empty());
+
+        assert!(retries.record_failure(TILE, 20.0));
+        assert!(retries.take_due(23.9).is_empty());
+        assert_eq!(retries.take_due(24.0), vec![TILE]);
+    }
+
+    #[test]
+    fn tiles_are_given_up_after_max_attempts() {
+        let mut retries = TileRetries::default();
+        for attempt in 1..retries.max_attempts {
+            assert!(retries.record_failure(TILE, attempt as f32));
+        }
+        assert!(!retries.record_failure(TILE, 100.0));
+        assert!(retries.take_due(f32::MAX).is_empty());
+
+        retries.remove(TILE);
+        retries.give_up(TILE);
+        assert!(retries.take_due(f32::MAX).is_empty());
+
+        retries.remove(TILE);
+        assert!(retries.entries.is_empty());
+    }
+}
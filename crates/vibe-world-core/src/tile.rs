@@ -0,0 +1,298 @@
+use std::f64::consts::PI;
+use crate::projection::{clamp_latitude, normalize_lon};
+
+/// Zoom level whose tiles are one world unit wide, see projection
+pub const DEFAULT_ZOOM_LEVEL: u32 = 13;
+pub const MIN_ZOOM_LEVEL: u32 = 1;  // Furthest zoom out (least detail)
+pub const MAX_ZOOM_LEVEL: u32 = 19;  // Closest zoom in (most detail)
+
+/// Highest row (and column within the world) at a zoom level
+pub fn max_tile_index(zoom: u32) -> u32 {
+    (1 << zoom) - 1 // 2^zoom - 1
+}
+
+/// Slippy map tile address (x, y at zoom z)
+/// Columns count on across the antimeridian so the map continues east-west: past the last
+/// column at 2^z and so on, and west of column 0 below zero, stored as a two's complement u32.
+/// wrapped() gives the column tile servers know, column() the signed one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+/// Geographic extent of a tile in WGS84 degrees
+/// Geographic math is done in f64, f32 loses too much precision at zoom 18+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileBounds {
+    pub north: f64,
+    pub south: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+impl TileId {
+    pub fn new(x: u32, y: u32, z: u32) -> Self {
+        Self { x, y, z }
+    }
+
+    // Find the tile containing a latitude/longitude at the given zoom level
+    // Longitudes past ±180 wrap around to the tile within the world, +180 itself is the last column;
+    // latitudes past the Web Mercator limit, the poles included, are in the first or last row
+    pub fn from_lat_lon(lat: f64, lon: f64, z: u32) -> Self {
+        let n = (1u64 << z) as f64;
+        let max_index = (n - 1.0).max(0.0);
+        let lat_rad = clamp_latitude(lat).to_radians();
+
+        let x = ((normalize_lon(lon) + 180.0) / 360.0 * n).floor().clamp(0.0, max_index);
+        let y = ((1.0 - lat_rad.tan().asinh() / PI) / 2.0 * n).floor().clamp(0.0, max_index);
+
+        Self::new(x as u32, y as u32, z)
+    }
+
+    /// Signed column, negative west of the antimeridian's copy of column 0
+    pub fn column(&self) -> i64 {
+        self.x as i32 as i64
+    }
+
+    /// The same tile with its column within the world, as tile servers address it
+    pub fn wrapped(&self) -> Self {
+        let columns = 1i64 << self.z;
+        Self::new(self.column().rem_euclid(columns) as u32, self.y, self.z)
+    }
+
+    // The tile one zoom level up containing this one
+    // Columns are halved rounding down, also west of column 0
+    pub fn parent(&self) -> Option<Self> {
+        (self.z > 0).then(|| Self::new((self.x as i32 >> 1) as u32, self.y / 2, self.z - 1))
+    }
+
+    // The four tiles one zoom level down covering this one
+    pub fn children(&self) -> [Self; 4] {
+        let (x, y, z) = (self.x.wrapping_mul(2), self.y * 2, self.z + 1);
+        [
+            Self::new(x, y, z),
+            Self::new(x.wrapping_add(1), y, z),
+            Self::new(x, y + 1, z),
+            Self::new(x.wrapping_add(1), y + 1, z),
+        ]
+    }
+
+    pub fn bounds(&self) -> TileBounds {
+        TileBounds::from_tile_id(*self)
+    }
+}
+
+impl TileBounds {
+    pub fn from_tile_id(id: TileId) -> Self {
+        Self {
+            north: tile_y_to_lat(id.y, id.z),
+            south: tile_y_to_lat(id.y + 1, id.z),
+            west: tile_x_to_lon(id.column(), id.z),
+            east: tile_x_to_lon(id.column() + 1, id.z),
+        }
+    }
+
+    // Check if a point lies within the bounds (edges inclusive)
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        lat <= self.north && lat >= self.south && lon >= self.west && lon <= self.east
+    }
+}
+
+// Longitude of the western edge of tile column x, past ±180 for columns outside the world
+fn tile_x_to_lon(x: i64, z: u32) -> f64 {
+    x as f64 / (1u64 << z) as f64 * 360.0 - 180.0
+}
+
+// Latitude of the northern edge of tile row y
+fn tile_y_to_lat(y: u32, z: u32) -> f64 {
+    let n = PI * (1.0 - 2.0 * y as f64 / (1u64 << z) as f64);
+    n.sinh().atan().to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use crate::projection::{lat_lon_to_world, world_to_tile_coords, MAX_LATITUDE as MAX_LAT};
+
+    // Tolerance in degrees for edge comparisons, well below a zoom 19 tile (~0.0007°)
+    const EPSILON: f64 = 1e-9;
+
+    #[test]
+    fn groningen_tile_bounds() {
+        // Groningen city centre (Grote Markt) at zoom 13, reference values from the OSM tile calculator
+        let id = TileId::from_lat_lon(53.2194, 6.5665, 13);
+        assert_eq!(id, TileId::new(4245, 2660, 13));
+
+        let bounds = id.bounds();
+        assert!(bounds.contains(53.2194, 6.5665));
+        assert!((bounds.west - 6.547_852).abs() < 1e-6);
+        assert!((bounds.north - 53.225_768).abs() < 1e-6);
+    }
+
+    #[test]
+    fn groningen_tile_bounds_at_zoom_16() {
+        // The zoom 16 tile holding both the Grote Markt and the Martinitoren
+        let id = TileId::new(33963, 21281, 16);
+        assert_eq!(TileId::from_lat_lon(53.2194, 6.5665, 16), id);
+        assert_eq!(TileId::from_lat_lon(53.2193, 6.5681, 16), id);
+
+        let bounds = TileBounds::from_tile_id(id);
+        assert_eq!(bounds, id.bounds());
+        assert!((bounds.north - 53.222_479_753).abs() < EPSILON);
+        assert!((bounds.south - 53.219_190_818).abs() < EPSILON);
+        assert!((bounds.west - 6.564_331_054_687_5).abs() < EPSILON);
+        assert!((bounds.east - 6.569_824_218_75).abs() < EPSILON);
+    }
+
+    #[test]
+    fn children_split_their_parent_in_four() {
+        let parent = TileId::new(4245, 2660, 13);
+        let [north_west, north_east, south_west, south_east] = parent.children();
+        assert_eq!(north_west, TileId::new(8490, 5320, 14));
+        assert_eq!(south_east, TileId::new(8491, 5321, 14));
+        for child in parent.children() {
+            assert_eq!(child.parent(), Some(parent));
+        }
+
+        // The children share the parent's outer edges and meet in its middle, which on the
+        // Mercator map is north of the halfway latitude in the northern hemisphere
+        let outer = parent.bounds();
+        assert_eq!(north_west.bounds().north, outer.north);
+        assert_eq!(north_west.bounds().west, outer.west);
+        assert_eq!(south_east.bounds().south, outer.south);
+        assert_eq!(south_east.bounds().east, outer.east);
+        let middle = north_east.bounds();
+        assert!((middle.south - 53.212_612_190).abs() < EPSILON);
+        assert!(middle.south > (outer.north + outer.south) / 2.0);
+        assert!((middle.west - (outer.west + outer.east) / 2.0).abs() < EPSILON);
+        assert_eq!(south_west.bounds().north, middle.south);
+    }
+
+    #[test]
+    fn ancestors_contain_their_descendants() {
+        // Up from the zoom 19 tile of the Grote Markt, every ancestor is the tile holding it
+        let mut tile = TileId::new(271_707, 170_255, 19);
+        assert_eq!(TileId::from_lat_lon(53.2194, 6.5665, 19), tile);
+        while let Some(parent) = tile.parent() {
+            assert_eq!(parent, TileId::from_lat_lon(53.2194, 6.5665, parent.z));
+            assert!(parent.children().contains(&tile));
+
+            let (inner, outer) = (tile.bounds(), parent.bounds());
+            assert!(inner.north <= outer.north && inner.south >= outer.south, "{tile:?} not inside {parent:?}");
+            assert!(inner.west >= outer.west && inner.east <= outer.east, "{tile:?} not inside {parent:?}");
+            tile = parent;
+        }
+        assert_eq!(tile, TileId::new(0, 0, 0));
+    }
+
+    #[test]
+    fn world_bounds_at_zoom_zero() {
+        let bounds = TileId::new(0, 0, 0).bounds();
+        assert!((bounds.north - MAX_LAT).abs() < 1e-6);
+        assert!((bounds.south + MAX_LAT).abs() < 1e-6);
+        assert_eq!(bounds.west, -180.0);
+        assert_eq!(bounds.east, 180.0);
+    }
+
+    #[test]
+    fn tile_bounds_at_extreme_latitudes() {
+        for z in 0..=19 {
+            let last = (1u32 << z) - 1;
+            assert_eq!(TileId::from_lat_lon(90.0, 0.0, z).y, 0);
+            assert_eq!(TileId::from_lat_lon(89.9, 0.0, z).y, 0);
+            assert_eq!(TileId::from_lat_lon(-90.0, 0.0, z).y, last);
+            assert_eq!(TileId::from_lat_lon(f64::NAN, 0.0, z).y, (1u32 << z) / 2);
+
+            let top = TileId::new(0, 0, z).bounds();
+            let bottom = TileId::new(0, last, z).bounds();
+            assert!((top.north - MAX_LAT).abs() < 1e-6, "zoom {}: {:?}", z, top);
+            assert!((bottom.south + MAX_LAT).abs() < 1e-6, "zoom {}: {:?}", z, bottom);
+            assert!(top.south.is_finite() && top.south < top.north);
+            assert!(bottom.north.is_finite() && bottom.north > bottom.south);
+        }
+    }
+
+    #[test]
+    fn columns_continue_across_the_antimeridian() {
+        let west = TileId::new(-1i32 as u32, 5, 3);
+        assert_eq!(west.column(), -1);
+        assert_eq!(west.wrapped(), TileId::new(7, 5, 3));
+        assert_eq!(TileId::new(9, 5, 3).wrapped(), TileId::new(1, 5, 3));
+        assert_eq!(west.parent(), Some(TileId::new(-1i32 as u32, 2, 2)));
+        assert_eq!(west.children()[0].column(), -2);
+        assert_eq!(west.children()[1].column(), -1);
+        assert!((west.bounds().west + 225.0).abs() < EPSILON);
+
+        // West of the antimeridian the world continues with the last column
+        let world = lat_lon_to_world(0.0, -180.5);
+        let (x, _) = world_to_tile_coords(world.x, world.y, 3);
+        assert_eq!(TileId::new(x, 4, 3).column(), -1);
+        assert_eq!(TileId::from_lat_lon(0.0, -180.5, 3), TileId::new(7, 4, 3));
+    }
+
+    proptest! {
+        #[test]
+        fn lat_lon_round_trips_through_tile_bounds(
+            lat in -MAX_LAT + 1e-6..MAX_LAT - 1e-6,
+            lon in -180.0f64..180.0,
+            z in 0u32..=19,
+        ) {
+            let bounds = TileId::from_lat_lon(lat, lon, z).bounds();
+
+            prop_assert!(bounds.north > bounds.south);
+            prop_assert!(bounds.east > bounds.west);
+            prop_assert!(lat <= bounds.north + EPSILON && lat >= bounds.south - EPSILON,
+                "lat {} outside {:?}", lat, bounds);
+            prop_assert!(lon >= bounds.west - EPSILON && lon <= bounds.east + EPSILON,
+                "lon {} outside {:?}", lon, bounds);
+        }
+
+        #[test]
+        fn neighbouring_tiles_share_edges(
+            x in 0u32..(1 << 19) - 1,
+            y in 0u32..(1 << 19) - 1,
+        ) {
+            let z = 19;
+            let bounds = TileId::new(x, y, z).bounds();
+            let east = TileId::new(x + 1, y, z).bounds();
+            let south = TileId::new(x, y + 1, z).bounds();
+
+            prop_assert!((bounds.east - east.west).abs() < EPSILON);
+            prop_assert!((bounds.south - south.north).abs() < EPSILON);
+        }
+
+        #[test]
+        fn tile_center_maps_back_to_same_tile(
+            x in 0u32..(1 << 19),
+            y in 0u32..(1 << 19),
+            z in 0u32..=19,
+        ) {
+            let n = 1u32 << z;
+            let id = TileId::new(x % n, y % n, z);
+            let bounds = id.bounds();
+            let center_lat = (bounds.north + bounds.south) / 2.0;
+            let center_lon = (bounds.west + bounds.east) / 2.0;
+
+            prop_assert_eq!(TileId::from_lat_lon(center_lat, center_lon, z), id);
+        }
+
+        #[test]
+        fn world_projection_agrees_with_tile_ids(
+            lat in -80.0f64..80.0,
+            lon in -179.0f64..179.0,
+            z in 0u32..=16,
+        ) {
+            // Both round differently, so allow the result to land on a neighbour
+            // when the point is right on a tile edge
+            let world = lat_lon_to_world(lat, lon);
+            let (tile_x, tile_y) = world_to_tile_coords(world.x, world.y, z);
+            let id = TileId::from_lat_lon(lat, lon, z);
+
+            prop_assert!((tile_x as i64 - id.x as i64).abs() <= 1);
+            prop_assert!((tile_y as i64 - id.y as i64).abs() <= 1);
+        }
+    }
+}
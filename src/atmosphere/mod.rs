@@ -0,0 +1,4 @@
+mod sky_material;
+
+pub use sky_material::{SkyMaterial, SKY_SHADER_HANDLE};
+pub use crate::shader_params::SkyParams;
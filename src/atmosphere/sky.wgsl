@@ -0,0 +1,53 @@
+// Procedural sky: a gradient from the horizon to the zenith with a sun disk and glow
+// Drawn on a dome around the camera, pushed to the far plane so it is behind everything
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_world}
+#import bevy_pbr::mesh_view_bindings::view
+
+struct SkyParams {
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+    sun_direction: vec4<f32>, // xyz towards the sun, w is the strength of the disk and glow
+    sun_color: vec4<f32>,
+};
+
+@group(2) @binding(0) var<uniform> sky: SkyParams;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world = mesh_position_local_to_world(
+        get_world_from_local(vertex.instance_index),
+        vec4<f32>(vertex.position, 1.0),
+    );
+    out.world_position = world.xyz;
+    out.clip_position = view.clip_from_world * world;
+    // Reversed depth: 0 is infinitely far away
+    out.clip_position.z = 0.0;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(in.world_position - view.world_position);
+
+    // Below the horizon the sky only shows where no tiles are loaded: continue the horizon haze
+    let height = max(direction.y, 0.0);
+    var color = mix(sky.horizon.rgb, sky.zenith.rgb, pow(height, 0.5));
+
+    let alignment = max(dot(direction, sky.sun_direction.xyz), 0.0);
+    let disk = smoothstep(0.9998, 0.99995, alignment);
+    let glow = pow(alignment, 64.0) * 0.5;
+    color += sky.sun_color.rgb * (disk + glow) * sky.sun_direction.w;
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+};
+use crate::shader_params::SkyParams;
+
+/// Shader drawing the procedural sky
+pub const SKY_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x3e9a_51c7_84d2_4b0f_a6e3_2c71_9d58_b046);
+
+/// Material of the sky dome around the camera
+/// The dome is drawn at infinite distance, behind everything else
+#[derive(Asset, TypePath, AsBindGroup, Clone, Default)]
+pub struct SkyMaterial {
+    #[uniform(0)]
+    pub params: SkyParams,
+}
+
+impl Material for SkyMaterial {
+    fn vertex_shader() -> ShaderRef {
+        SKY_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SKY_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // The camera is inside the dome
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
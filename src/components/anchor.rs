@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use crate::utils::geo::{GeoPos, GeoTransform};
+
+/// Keeps an entity placed directly in the world (without a parent) at a geographic position
+/// Its Transform's translation is derived from the position whenever the position changes or
+/// the render origin moves, so markers, avatars and models stay put as the map moves under them;
+/// rotation and scale are left to the entity
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct GeoAnchor {
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: f64, // Meters above the map
+}
+
+impl GeoAnchor {
+    pub fn new(lat: f64, lon: f64, altitude: f64) -> Self {
+        Self { lat, lon, altitude }
+    }
+
+    /// Translation of the position relative to the current render origin
+    pub fn translation(&self) -> Vec3 {
+        GeoTransform::new(self.position(), self.altitude).translation()
+    }
+
+    pub fn position(&self) -> GeoPos {
+        GeoPos::new(self.lat, self.lon)
+    }
+}
@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Marker component for the sky dome, which follows the main camera
+#[derive(Component)]
+pub struct SkyDome;
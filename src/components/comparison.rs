@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+/// Marker for the camera drawing the right side of the provider comparison
+/// It follows the main camera and sees the comparison provider's tiles over the map's
+#[derive(Component)]
+pub struct ComparisonCamera;
+
+/// Marker for the camera drawing the UI over both sides of the provider comparison,
+/// as the UI would otherwise be laid out in one side's viewport
+#[derive(Component)]
+pub struct ComparisonUiCamera;
+
+/// Marker for the draggable divider between the two sides of the provider comparison
+#[derive(Component)]
+pub struct ComparisonDivider;
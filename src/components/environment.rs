@@ -0,0 +1,17 @@
+use bevy::prelude::*;
+
+/// Marker component for the time of day slider track
+#[derive(Component)]
+pub struct TimeSlider;
+
+/// Marker component for the knob showing the time on the slider
+#[derive(Component)]
+pub struct TimeSliderKnob;
+
+/// Marker component for the text showing the world time
+#[derive(Component)]
+pub struct WorldTimeText;
+
+/// Marker component for the button returning the clock to the wall-clock time
+#[derive(Component)]
+pub struct RealTimeButton;
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+/// A collectible spawned on a tile, `index` refers to GameContent::collectibles
+#[derive(Component)]
+pub struct Collectible {
+    pub index: usize,
+}
+
+/// Marker component for the HUD text showing the score
+#[derive(Component)]
+pub struct ScoreText;
+
+/// Marker component for the HUD text listing the zones the camera is in
+#[derive(Component)]
+pub struct ZoneText;
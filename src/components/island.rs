@@ -1,9 +1,59 @@
 use bevy::prelude::*;
 
-/// Component to mark a tile as a persistent island (OpenSimulator region)
+/// Root of a persistent island's content: an OpenSimulator region spawned on its tile
+/// The terrain and the objects are its children; the rest is the island's metadata
 #[derive(Component)]
 pub struct PersistentIsland {
     pub name: String,
-    // Add any island-specific data here
-    // For example, custom terrain modifications, objects, etc.
-} 
\ No newline at end of file
+    pub owner: String,
+    pub description: String,
+    pub created_at: Option<u64>, // Unix seconds
+    pub tags: Vec<String>,
+}
+
+/// Outline around a persistent island's tile, shown at every distance
+#[derive(Component)]
+pub struct IslandBorder;
+
+/// Marker component for the form editing an island's metadata
+#[derive(Component)]
+pub struct IslandFormText;
+
+/// Marker component for the tooltip describing the island under the cursor
+#[derive(Component)]
+pub struct IslandTooltip;
+
+/// Marker component for the text describing the brush in island editing mode
+#[derive(Component)]
+pub struct IslandEditorText;
+
+/// The terrain mesh of a persistent island, by its index in Islands
+/// Its transform is identity, the island's root places and scales it
+#[derive(Component)]
+pub struct IslandTerrain {
+    pub island: usize,
+}
+
+/// An object placed on a persistent island in island editing mode,
+/// by its island's index in Islands and its index in the island's placed objects
+#[derive(Component)]
+pub struct IslandObject {
+    pub island: usize,
+    pub index: usize,
+}
+
+/// Marker component for the list of islands to travel to
+#[derive(Component)]
+pub struct IslandListPanel;
+
+/// The text of an island's row on the island list, with its distance from the camera
+#[derive(Component)]
+pub struct IslandDistanceText {
+    pub island: usize,
+}
+
+/// Button flying the camera to an island, by its index in Islands
+#[derive(Component)]
+pub struct TeleportButton {
+    pub island: usize,
+}
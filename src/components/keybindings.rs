@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+
+/// Marker component for the root node of the keybindings page
+#[derive(Component)]
+pub struct KeybindingsPage;
+
+/// Button on the keybindings page that rebinds an action when clicked
+#[derive(Component)]
+pub struct RebindButton {
+    pub action: String,
+}
+
+/// Button on the keybindings page that restores the default bindings
+#[derive(Component)]
+pub struct ResetKeybindingsButton;
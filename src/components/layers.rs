@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+/// Marker component for the root node of the layer panel
+#[derive(Component)]
+pub struct LayerPanel;
+
+/// Button folding the layer panel down to its title and back
+#[derive(Component)]
+pub struct LayerPanelCollapse;
+
+/// A layer listed on the layer panel: an imagery layer by id or an overlay layer by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PanelLayer {
+    Imagery(u32),
+    Overlay(&'static str),
+}
+
+/// What a button on the layer panel does to its layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerAction {
+    ToggleVisible,
+    Move(i32), // Move the layer down (-1) or up (+1) the stack
+}
+
+/// Button on the layer panel acting on a layer
+#[derive(Component)]
+pub struct LayerPanelButton {
+    pub layer: PanelLayer,
+    pub action: LayerAction,
+}
+
+/// Opacity slider track of a layer on the layer panel
+#[derive(Component)]
+pub struct LayerOpacitySlider(pub PanelLayer);
+
+/// Filled part of an opacity slider, as wide as the layer is opaque
+#[derive(Component)]
+pub struct LayerOpacityFill(pub PanelLayer);
+
+/// Text showing a layer's opacity next to its slider
+#[derive(Component)]
+pub struct LayerOpacityText(pub PanelLayer);
+
+/// An overlay belonging to one of the LayerManager's layers, which shows, hides, fades and
+/// lifts it with its layer
+#[derive(Component)]
+pub struct LayerItem {
+    pub layer: &'static str,
+    pub elevation: f32, // Height of the overlay above the tiles, the layer's place in the stack adds to it
+}
+
+/// Marker component for the bar crediting the providers of the visible imagery layers
+#[derive(Component)]
+pub struct AttributionBar;
+
+/// A credit on the attribution bar, clicking it opens the provider's page
+#[derive(Component)]
+pub struct AttributionLink {
+    pub url: String,
+}
+
+/// Marker component for the imagery timeline, shown while a layer with dates is visible
+#[derive(Component)]
+pub struct TimelinePanel;
+
+/// Marker component for the imagery timeline's slider track
+#[derive(Component)]
+pub struct TimelineSlider;
+
+/// Marker component for the knob showing the picked date on the timeline
+#[derive(Component)]
+pub struct TimelineKnob;
+
+/// Marker component for the text naming the timeline's layer and date
+#[derive(Component)]
+pub struct TimelineText;
+
+/// Button stepping the timeline a day back (-1) or forward (+1)
+#[derive(Component)]
+pub struct TimelineStep(pub i32);
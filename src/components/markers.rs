@@ -0,0 +1,13 @@
+use bevy::prelude::*;
+
+/// Marker component for the marker import panel
+#[derive(Component)]
+pub struct MarkerImportPanel;
+
+/// Marker component for the text showing the progress of a marker import
+#[derive(Component)]
+pub struct MarkerImportText;
+
+/// Marker component for the button removing the imported markers
+#[derive(Component)]
+pub struct ClearMarkersButton;
@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+
+/// Marker component for the line drawn through the measurement points
+#[derive(Component)]
+pub struct MeasurementLine;
+
+/// Marker component for the text showing the measured distance and area
+#[derive(Component)]
+pub struct MeasurementText;
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+/// Marker for the top-down orthographic camera rendering into the minimap texture
+#[derive(Component)]
+pub struct MinimapCamera;
+
+/// Marker for the UI node displaying the minimap texture
+#[derive(Component)]
+pub struct MinimapImage;
+
+/// Marker for the UI arrow showing the main camera heading on the minimap
+#[derive(Component)]
+pub struct MinimapHeading;
+
+/// Marker for the detached window with the overview of the map
+#[derive(Component)]
+pub struct OverviewWindow;
+
+/// The top-down orthographic camera rendering into the overview window
+#[derive(Component)]
+pub struct OverviewCamera {
+    pub window: Entity,
+}
@@ -1,5 +1,50 @@
 use bevy::prelude::*;
 
+pub mod minimap;
+pub mod keybindings;
+pub mod settings_menu;
+pub mod game;
+pub mod environment;
+pub mod atmosphere;
+pub mod water;
+pub mod measurement;
+pub mod routing;
+pub mod weather;
+pub mod layers;
+pub mod anchor;
+pub mod markers;
+pub mod comparison;
+pub mod stereo;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod multiplayer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island;
+
+pub use minimap::*;
+pub use keybindings::*;
+pub use settings_menu::*;
+pub use game::*;
+pub use environment::*;
+pub use atmosphere::*;
+pub use water::*;
+pub use measurement::*;
+pub use routing::*;
+pub use weather::*;
+pub use layers::*;
+pub use anchor::*;
+pub use markers::*;
+pub use comparison::*;
+pub use stereo::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use multiplayer::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use island::*;
+
+/// Marker component for the player's first-person camera
+/// Other cameras (minimap, UI) must not be picked up by camera/tile systems
+#[derive(Component)]
+pub struct MainCamera;
+
 /// Marker component for the UI text that displays the current zoom level
 #[derive(Component)]
 pub struct ZoomLevelText;
@@ -10,6 +55,42 @@ pub struct TileCountText;
 #[derive(Component)]
 pub struct FpsCounterText;
 
+/// Marker component for the status bar showing what is under the cursor
+#[derive(Component)]
+pub struct StatusBarText;
+
+/// Marker component for the warning shown while a tile server is paused after repeated errors
+#[derive(Component)]
+pub struct ServerWarningText;
+
+/// The toast message (see ShowToast), with the seconds until it disappears
+#[derive(Component, Default)]
+pub struct ToastText {
+    pub remaining: f32,
+}
+
+/// Marker component for the button copying the view's location and share link
+#[derive(Component)]
+pub struct CopyLocationButton;
+
+/// The loading screen covering the world at startup, with the seconds it has been fading out
+#[derive(Component, Default)]
+pub struct LoadingScreen {
+    pub fading: f32,
+}
+
+/// Marker component for the loading screen's progress text
+#[derive(Component)]
+pub struct LoadingText;
+
+/// Marker component for the tile loading progress, shown while tiles load or with the tile debug overlay
+#[derive(Component)]
+pub struct TileProgressText;
+
+/// A z/x/y label of the tile debug overlay, reused for another tile every frame
+#[derive(Component)]
+pub struct TileDebugLabel;
+
 #[derive(Component)]
 pub struct TileCoords {
     pub x: u32,
@@ -18,5 +99,40 @@ pub struct TileCoords {
     pub last_used: f32,
 }
 
+/// Parent of all tiles and batch meshes of one zoom level, at the render origin
+#[derive(Component)]
+pub struct TileZoomRoot {
+    pub zoom: u32,
+}
+
+/// A batch mesh drawing the atlas tiles of one zoom level on one atlas page
+#[derive(Component)]
+pub struct TileBatch {
+    pub zoom: u32,
+}
+
+/// Decoded size of the texture a tile shows, counted against the TileMemoryBudget
+#[derive(Component)]
+pub struct TileTextureBytes(pub usize);
+
+#[derive(Component)]
+pub struct BackgroundTile;
+
+/// Wrapped elapsed seconds when a tile appeared, it fades in from then on
+#[derive(Component)]
+pub struct TileFadeIn(pub f32);
+
+/// Tints a tile, e.g. with the style's island highlight color to highlight a persistent island
+/// The alpha is the strength of the tint
+#[derive(Component)]
+pub struct TileTint(pub Color);
+
+/// Marker component for tiles showing upscaled imagery from a cached lower zoom level
+/// because the tile itself couldn't be loaded (e.g. while offline)
+#[derive(Component)]
+pub struct LowResTile;
+
+/// Marker component for the red placeholder of a tile that failed to load
+/// It is replaced when a retry succeeds, see TileRetries
 #[derive(Component)]
-pub struct BackgroundTile; 
\ No newline at end of file
+pub struct FallbackTile;
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+/// Marker component for the avatar of another user on the multiplayer server
+#[derive(Component)]
+pub struct Avatar;
+
+/// Marker component for the UI text showing the name above an avatar
+#[derive(Component)]
+pub struct AvatarLabel;
+
+/// Marker component for the text showing the recent chat messages
+#[derive(Component)]
+pub struct ChatLogText;
+
+/// Marker component for the text showing the chat message being typed
+#[derive(Component)]
+pub struct ChatInputText;
+
+/// Marker component for the marker of a chat message pinned to the map
+#[derive(Component)]
+pub struct MapPingMarker;
+
+/// Marker component for the UI text showing the message of a map ping
+#[derive(Component)]
+pub struct MapPingLabel;
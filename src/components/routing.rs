@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+
+/// Marker component for the line drawn along the route
+#[derive(Component)]
+pub struct RouteLine;
+
+/// Marker component for the text showing the route's distance and duration
+#[derive(Component)]
+pub struct RouteText;
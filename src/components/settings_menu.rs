@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+
+/// Marker component for the root node of the settings menu
+#[derive(Component)]
+pub struct SettingsMenu;
+
+/// A user setting that can be changed from the settings menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsField {
+    TileProvider,
+    TextureCache,
+    RenderDistance,
+    FieldOfView,
+    MovementSpeed,
+    LookSensitivity,
+    Stereo,
+    Antimeridian,
+    DebugOverlays,
+}
+
+/// Button on the settings menu that steps a setting down (-1) or up (+1)
+#[derive(Component)]
+pub struct SettingsMenuButton {
+    pub field: SettingsField,
+    pub step: i32,
+}
+
+/// Text on the settings menu showing the current value of a setting
+#[derive(Component)]
+pub struct SettingValueText {
+    pub field: SettingsField,
+}
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+/// One of the two cameras drawing the view in a stereo mode
+/// They follow the main camera, which stops drawing while they are there
+#[derive(Component)]
+pub struct StereoEye {
+    pub left: bool,
+}
+
+/// Marker for the camera drawing the UI over the stereo view, and the anaglyph under it
+#[derive(Component)]
+pub struct StereoUiCamera;
+
+/// Marker for the node covering the window with the anaglyph of the two eyes' images
+#[derive(Component)]
+pub struct AnaglyphView;
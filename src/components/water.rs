@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Marker component for the water surface spawned on a tile
+#[derive(Component)]
+pub struct WaterSurface;
@@ -0,0 +1,17 @@
+use bevy::prelude::*;
+
+/// Marker component for the weather radar panel with the playback controls
+#[derive(Component)]
+pub struct WeatherPanel;
+
+/// Marker component for the text showing the time of the visible radar frame
+#[derive(Component)]
+pub struct WeatherText;
+
+/// What a button on the weather panel does
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeatherButton {
+    Previous,
+    PlayPause,
+    Next,
+}
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+/// Request to move the main camera smoothly to a new position
+/// The camera keeps its current orientation while flying
+#[derive(Event, Clone, Copy, Debug)]
+pub struct FlyTo {
+    pub target: Vec3,
+    pub duration: f32, // Seconds, 0.0 teleports instantly
+}
+
+/// The render origin moved (see FloatingOrigin) and everything placed in the world was moved
+/// back by `offset`; positions kept outside of Transforms have to move along
+#[derive(Event, Clone, Copy, Debug)]
+pub struct OriginShifted {
+    pub offset: Vec3,
+}
@@ -0,0 +1,20 @@
+use bevy::prelude::*;
+
+/// Sent when the camera moves into a trigger zone, `zone` indexes GameContent::zones
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ZoneEntered {
+    pub zone: usize,
+}
+
+/// Sent when the camera leaves a trigger zone
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ZoneExited {
+    pub zone: usize,
+}
+
+/// Sent when the camera picks up a collectible
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollectiblePicked {
+    pub collectible: usize,
+    pub points: u32,
+}
@@ -0,0 +1,15 @@
+pub mod camera;
+pub mod settings;
+pub mod game;
+pub mod tiles;
+pub mod ui;
+pub mod models;
+pub mod overlays;
+
+pub use camera::*;
+pub use settings::*;
+pub use game::*;
+pub use tiles::*;
+pub use ui::*;
+pub use models::*;
+pub use overlays::*;
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+
+/// Request to place a glTF model at a geographic position, e.g. a landmark or island content
+/// The model stays at its position while the map moves under the render origin
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct SpawnGeoModel {
+    pub lat: f64,
+    pub lon: f64,
+    pub heading: f64, // Degrees clockwise from north the model's front (-Z) faces
+    pub scale: f64,   // 1 for a model in meters, as glTF models are
+    pub path: String, // glTF file in the assets directory; its first scene is spawned
+}
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use crate::overlays::dropped_data::{DropTarget, DroppedData};
+
+/// A CSV or GeoJSON file dropped onto the window, routed to the one target that shows it
+/// (see route_dropped_data); the consumer of the target reads the parsed contents
+#[derive(Event, Clone, Debug)]
+pub struct DataFileDropped {
+    pub path: PathBuf,
+    pub target: DropTarget,
+    pub data: DroppedData,
+}
@@ -0,0 +1,6 @@
+use bevy::prelude::*;
+
+/// Sent whenever the user settings changed, including once at startup after loading them
+/// Systems react to this instead of polling the settings every frame
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SettingsChanged;
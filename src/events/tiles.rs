@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::osm::TileId;
+
+/// Sent when a map tile's entity is spawned, so plugins can attach content to it as children
+/// A tile that is replaced (e.g. a placeholder by the real imagery) gets a new entity and a new event
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TileSpawned {
+    pub id: TileId,
+    pub entity: Entity,
+}
+
+/// Sent when a map tile's entity is gone, with the children attached to it
+/// The entity tells a despawned tile from the one replacing it, which has the same id
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TileDespawned {
+    pub id: TileId,
+    pub entity: Entity,
+}
+
+/// Sent when a map tile comes into view or goes out of it
+/// New tiles start out of view, so the first of these follows their TileSpawned
+#[derive(Event, Clone, Copy, Debug)]
+pub struct TileVisibilityChanged {
+    pub id: TileId,
+    pub entity: Entity,
+    pub visible: bool,
+}
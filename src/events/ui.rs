@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// Show a short message at the bottom of the screen for a few seconds, e.g. to confirm an action
+#[derive(Event, Clone, Debug)]
+pub struct ShowToast(pub String);
@@ -0,0 +1,197 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use crate::osm::TileId;
+use crate::resources::constants::MAX_ZOOM_LEVEL;
+use crate::utils::geo::GeoBounds;
+
+/// Command line of a headless run, printed when it can't be parsed
+pub const USAGE: &str = "\
+Usage: vibers --headless --bbox <west,south,east,north> --zoom <min>[-<max>] [--output <dir>] [--photo] [--cache-dir <dir>]
+
+Loads every tile in the box at the given zoom levels through the tile pipeline, filling the
+disk cache. With --output the composited tile images are written to <dir>/<z>/<x>/<y>.png.
+--photo adds the satellite layer to the stack, like the photo view. --cache-dir fills the
+tile cache in <dir> instead of ./tile_cache.";
+
+// Refuse runs that would hammer the tile servers, like a whole country at street level
+const MAX_TILES: u64 = 20_000;
+
+/// What a headless run loads and where its images go
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadlessArgs {
+    pub bounds: GeoBounds,
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    pub output: Option<PathBuf>, // Where composited tile images are written, None to only fill the cache
+    pub photo: bool,             // Show the satellite layer
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl HeadlessArgs {
+    /// Parse the arguments after the program name
+    /// Returns None without --headless, so the viewer starts as usual
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+
+        let mut bounds = None;
+        let mut zooms = None;
+        let mut output = None;
+        let mut photo = false;
+        let mut cache_dir = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--headless" => {}
+                "--bbox" => bounds = Some(parse_bbox(value()?)?),
+                "--zoom" => zooms = Some(parse_zoom_range(value()?)?),
+                "--output" => output = Some(PathBuf::from(value()?)),
+                "--photo" => photo = true,
+                "--cache-dir" => cache_dir = Some(PathBuf::from(value()?)),
+                other => return Err(format!("Unknown argument: {}", other)),
+            }
+        }
+
+        let bounds = bounds.ok_or("--bbox is required")?;
+        let (min_zoom, max_zoom) = zooms.ok_or("--zoom is required")?;
+        let args = Self { bounds, min_zoom, max_zoom, output, photo, cache_dir };
+
+        let count = args.tile_count();
+        if count > MAX_TILES {
+            return Err(format!("The box covers {} tiles, more than the limit of {}", count, MAX_TILES));
+        }
+        Ok(Some(args))
+    }
+
+    /// Every tile to load, zoomed out first
+    pub fn tiles(&self) -> impl Iterator<Item = TileId> + '_ {
+        (self.min_zoom..=self.max_zoom).flat_map(|zoom| tiles_in_bounds(&self.bounds, zoom))
+    }
+
+    /// Number of tiles the run loads
+    pub fn tile_count(&self) -> u64 {
+        (self.min_zoom..=self.max_zoom)
+            .map(|zoom| {
+                let (columns, rows) = tile_ranges(&self.bounds, zoom);
+                columns.iter().map(range_len).sum::<u64>() * range_len(&rows)
+            })
+            .sum()
+    }
+}
+
+// <west>,<south>,<east>,<north> in degrees, west greater than east crosses the antimeridian
+fn parse_bbox(value: &str) -> Result<GeoBounds, String> {
+    let numbers: Vec<f64> = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid --bbox {}: {}", value, e))?;
+    let [west, south, east, north] = numbers[..] else {
+        return Err(format!("--bbox takes west,south,east,north, got {}", value));
+    };
+    if !(-90.0..=90.0).contains(&south) || !(-90.0..=90.0).contains(&north) || south > north {
+        return Err(format!("Invalid --bbox latitudes: south {} north {}", south, north));
+    }
+    if !(-180.0..=180.0).contains(&west) || !(-180.0..=180.0).contains(&east) {
+        return Err(format!("Invalid --bbox longitudes: west {} east {}", west, east));
+    }
+    Ok(GeoBounds { north, south, west, east })
+}
+
+// A single zoom level or an inclusive range like 10-14
+fn parse_zoom_range(value: &str) -> Result<(u32, u32), String> {
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<u32>()
+            .map_err(|e| format!("Invalid --zoom {}: {}", value, e))
+    };
+    let (min, max) = match value.split_once('-') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => {
+            let zoom = parse(value)?;
+            (zoom, zoom)
+        }
+    };
+    if min > max || max > MAX_ZOOM_LEVEL {
+        return Err(format!("--zoom must be a range within 0-{}, got {}", MAX_ZOOM_LEVEL, value));
+    }
+    Ok((min, max))
+}
+
+// Tile columns (two ranges when the box crosses the antimeridian) and rows covering the box
+fn tile_ranges(bounds: &GeoBounds, zoom: u32) -> (Vec<RangeInclusive<u32>>, RangeInclusive<u32>) {
+    let north_west = TileId::from_lat_lon(bounds.north, bounds.west, zoom);
+    let south_east = TileId::from_lat_lon(bounds.south, bounds.east, zoom);
+    let columns = if bounds.crosses_antimeridian() {
+        let last = (1u32 << zoom) - 1;
+        vec![north_west.x..=last, 0..=south_east.x]
+    } else {
+        vec![north_west.x..=south_east.x]
+    };
+    (columns, north_west.y..=south_east.y)
+}
+
+fn range_len(range: &RangeInclusive<u32>) -> u64 {
+    (*range.end() - *range.start()) as u64 + 1
+}
+
+/// The tiles covering a box at a zoom level, row by row
+pub fn tiles_in_bounds(bounds: &GeoBounds, zoom: u32) -> Vec<TileId> {
+    let (columns, rows) = tile_ranges(bounds, zoom);
+    rows.flat_map(|y| {
+        columns
+            .iter()
+            .flat_map(|range| range.clone())
+            .map(move |x| TileId::new(x, y, zoom))
+            .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn arguments_without_headless_start_the_viewer() {
+        assert_eq!(HeadlessArgs::parse(&args("")), Ok(None));
+        assert_eq!(HeadlessArgs::parse(&args("--bbox 1,2,3,4")), Ok(None));
+    }
+
+    #[test]
+    fn headless_arguments_are_parsed_and_checked() {
+        let parsed = HeadlessArgs::parse(&args("--headless --bbox 6.5,53.2,6.6,53.25 --zoom 10-12 --output out --photo"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.bounds, GeoBounds { north: 53.25, south: 53.2, west: 6.5, east: 6.6 });
+        assert_eq!((parsed.min_zoom, parsed.max_zoom), (10, 12));
+        assert_eq!(parsed.output, Some(PathBuf::from("out")));
+        assert!(parsed.photo);
+
+        assert!(HeadlessArgs::parse(&args("--headless --zoom 3")).is_err());
+        assert!(HeadlessArgs::parse(&args("--headless --bbox 1,2,3 --zoom 3")).is_err());
+        assert!(HeadlessArgs::parse(&args("--headless --bbox 1,2,3,4 --zoom 5-4")).is_err());
+        assert!(HeadlessArgs::parse(&args("--headless --bbox 1,2,3,4 --zoom 3 --verbose")).is_err());
+        // The whole world at street level is far too many tiles
+        assert!(HeadlessArgs::parse(&args("--headless --bbox -180,-85,180,85 --zoom 15")).is_err());
+    }
+
+    #[test]
+    fn boxes_are_covered_by_whole_tiles() {
+        let world = GeoBounds { north: 85.0, south: -85.0, west: -180.0, east: 180.0 };
+        assert_eq!(tiles_in_bounds(&world, 0), vec![TileId::new(0, 0, 0)]);
+        assert_eq!(tiles_in_bounds(&world, 1).len(), 4);
+
+        // A box across the antimeridian takes the columns at both edges of the map
+        let pacific = GeoBounds { north: 10.0, south: -10.0, west: 170.0, east: -170.0 };
+        let columns: Vec<u32> = tiles_in_bounds(&pacific, 2).iter().filter(|id| id.y == 1).map(|id| id.x).collect();
+        assert_eq!(columns, vec![3, 0]);
+    }
+}
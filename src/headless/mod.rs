@@ -0,0 +1,126 @@
+// Headless mode: walks the tile pipeline for a box without opening a window,
+// to fill the disk cache or write composited tile images (CI smoke tests, island previews)
+mod args;
+
+pub use args::{HeadlessArgs, USAGE};
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use tokio::task::JoinSet;
+use crate::osm::{composite_layers, init_tile_cache, load_tile_image, open_tile_source, set_cache_dir, tile_layer_image, HostThrottle, SharedNetworkStatus, TileId};
+use crate::resources::{build_runtime, HttpClient, ImageryLayers, UserSettings, SATELLITE_LAYER_ID};
+
+/// How a headless run went
+#[derive(Debug, Default)]
+pub struct HeadlessReport {
+    pub loaded: usize,
+    pub low_res: usize, // Loaded, but some layer only from a cached lower zoom ancestor
+    pub failed: usize,  // Some layer couldn't be loaded at all
+}
+
+/// Load every tile of the run through the tile pipeline, with the layers and servers from the
+/// user settings, writing the composited images if an output directory was given
+pub fn run(args: &HeadlessArgs) -> Result<HeadlessReport, anyhow::Error> {
+    if let Some(cache_dir) = &args.cache_dir {
+        set_cache_dir(cache_dir.clone());
+    }
+    init_tile_cache()?;
+    let settings = UserSettings::load();
+
+    let mut layers = ImageryLayers::default();
+    layers.set_base_server(&settings.tile_server);
+    if let Some(photo) = layers.get_mut(SATELLITE_LAYER_ID) {
+        photo.visible = args.photo;
+    }
+    let client = HttpClient::new(&settings.user_agent).client;
+    let throttle = Arc::new(Mutex::new(HostThrottle::default()));
+    let network = SharedNetworkStatus::default();
+    let sources: Arc<Vec<_>> = Arc::new(
+        layers
+            .sources()
+            .into_iter()
+            .map(|layer| {
+                let source = open_tile_source(&layer.url, &client, &throttle, &network, settings.tile_ttl());
+                (layer, source)
+            })
+            .collect(),
+    );
+
+    println!(
+        "Loading {} tiles at zoom {}-{} from {}",
+        args.tile_count(),
+        args.min_zoom,
+        args.max_zoom,
+        sources.iter().map(|(layer, _)| layer.url.as_str()).collect::<Vec<_>>().join(" + ")
+    );
+
+    // Tiles loaded at the same time, like visible tiles in the viewer
+    // The throttle paces the requests to each host on top of this
+    let max_concurrent_tiles = settings.visible_downloads;
+    let runtime = build_runtime(settings.worker_threads)?;
+    runtime.block_on(async {
+        let mut report = HeadlessReport::default();
+        let mut tasks = JoinSet::new();
+        let mut tiles = args.tiles();
+
+        loop {
+            while tasks.len() < max_concurrent_tiles {
+                let Some(id) = tiles.next() else {
+                    break;
+                };
+                let sources = sources.clone();
+                let output = args.output.clone();
+                tasks.spawn(async move {
+                    let mut stack = Vec::with_capacity(sources.len());
+                    let (mut low_res, mut failed) = (false, false);
+                    for (layer, source) in sources.iter() {
+                        match load_tile_image(source.as_ref(), id).await {
+                            Ok(loaded) => {
+                                low_res |= loaded.low_res;
+                                stack.push((tile_layer_image(loaded.image), layer.opacity));
+                            }
+                            Err(e) => {
+                                eprintln!("Tile {}/{}/{} layer {}: {}", id.z, id.x, id.y, layer.id, e);
+                                failed = true;
+                            }
+                        }
+                    }
+                    // A tile missing a layer isn't written, a blank or partial image would pass for the real thing
+                    if let Some(output) = output.filter(|_| !failed) {
+                        if let Err(e) = write_tile(&output, id, &stack) {
+                            eprintln!("Failed to write tile {}/{}/{}: {}", id.z, id.x, id.y, e);
+                            failed = true;
+                        }
+                    }
+                    (low_res, failed)
+                });
+            }
+
+            let Some(result) = tasks.join_next().await else {
+                break;
+            };
+            match result? {
+                (_, true) => report.failed += 1,
+                (true, false) => report.low_res += 1,
+                (false, false) => report.loaded += 1,
+            }
+        }
+        Ok::<_, anyhow::Error>(report)
+    })
+}
+
+// Composite a tile's layers like the viewer draws them and save the image as <z>/<x>/<y>.png
+fn write_tile(output: &Path, id: TileId, stack: &[(bevy::prelude::Image, f32)]) -> Result<(), anyhow::Error> {
+    let layers: Vec<_> = stack.iter().map(|(image, opacity)| (image, *opacity)).collect();
+    let tile_image = composite_layers(&layers);
+
+    let dir = output.join(id.z.to_string()).join(id.x.to_string());
+    fs::create_dir_all(&dir)?;
+    let rgba = image::RgbaImage::from_raw(tile_image.width(), tile_image.height(), tile_image.data)
+        .ok_or_else(|| anyhow::anyhow!("Composited tile has the wrong size"))?;
+    rgba.save(dir.join(format!("{}.png", id.y)))?;
+    Ok(())
+}
+
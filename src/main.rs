@@ -1,15 +1,106 @@
 use bevy::prelude::*;
 
 mod components;
+mod events;
 mod resources;
 mod systems;
 mod plugins;
 mod utils;
 mod osm;
+mod overlays;
+mod atmosphere;
+mod water;
+// The ShaderType derive emits compile-time field checks that rustc reports as unused functions,
+// so the uniform structs are kept together in one module that allows them
+#[allow(dead_code)]
+mod shader_params;
+mod stereo;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+mod net;
+#[cfg(not(target_arch = "wasm32"))]
+mod opensim;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugins(plugins::AppPlugins)
-        .run();
+    // With --headless tiles are prerendered without opening a window
+    #[cfg(not(target_arch = "wasm32"))]
+    run_headless();
+
+    // Start location, zoom, provider and cache directory from the command line
+    #[cfg(not(target_arch = "wasm32"))]
+    let launch = launch_options();
+    #[cfg(target_arch = "wasm32")]
+    let launch = resources::LaunchOptions::default();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let session = launch.record_session.is_some() || launch.replay_session.is_some();
+    #[cfg(not(target_arch = "wasm32"))]
+    let benchmark = launch.benchmark;
+
+    let mut app = App::new();
+    app
+        .insert_resource(launch)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                // Browser builds fill the page instead of drawing to a fixed size canvas
+                fit_canvas_to_parent: true,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins(plugins::AppPlugins);
+
+    // With --benchmark the camera flies a scripted sweep and the app quits with a report
+    #[cfg(not(target_arch = "wasm32"))]
+    if benchmark {
+        app.add_plugins(plugins::BenchmarkPlugin);
+    }
+
+    // With --record-session or --replay-session camera sessions are logged or played back
+    #[cfg(not(target_arch = "wasm32"))]
+    if session {
+        app.add_plugins(plugins::SessionPlugin);
+    }
+
+    app.run();
+}
+
+// Parse the viewer's command line, exiting with 2 and the usage for bad arguments, or with the
+// help for --help
+// The cache directory is set here, before any system can touch the cache
+#[cfg(not(target_arch = "wasm32"))]
+fn launch_options() -> resources::LaunchOptions {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let launch = resources::LaunchOptions::parse(&args).unwrap_or_else(|e| e.exit());
+    if let Some(cache_dir) = &launch.cache_dir {
+        osm::set_cache_dir(cache_dir.clone());
+    }
+    launch
+}
+
+// Run the headless mode and exit if it was asked for on the command line
+// Exits with 1 when tiles failed to load, so CI smoke tests notice, and 2 for bad arguments
+#[cfg(not(target_arch = "wasm32"))]
+fn run_headless() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let headless_args = match headless::HeadlessArgs::parse(&args) {
+        Ok(Some(headless_args)) => headless_args,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, headless::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match headless::run(&headless_args) {
+        Ok(report) => {
+            println!("{} tiles loaded, {} from lower zoom imagery only, {} failed", report.loaded, report.low_res, report.failed);
+            std::process::exit(if report.failed > 0 { 1 } else { 0 });
+        }
+        Err(e) => {
+            eprintln!("Headless run failed: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
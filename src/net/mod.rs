@@ -0,0 +1,6 @@
+// Networking for multiplayer: the protocol spoken over a WebSocket to the relay server
+mod presence;
+mod session;
+
+pub use presence::{chat_message, hello_message, position_message, GeoPose, ServerMessage};
+pub use session::{run_session, ConnectionStatus, SharedInbox};
@@ -0,0 +1,150 @@
+// Presence and chat protocol spoken with the multiplayer relay server, one JSON object per text message
+//
+// Client to server:
+//   {"type":"hello","name":"ann"}                      sent after every (re)connect
+//   {"type":"position","lat":..,"lon":..,"altitude":..,"heading":..}
+//   {"type":"chat","text":"over here"}                 lat and lon are added for a map ping
+// Server to client:
+//   {"type":"welcome","id":7}                          the id the server knows this client by
+//   {"type":"position","id":3,"name":"bob","lat":..,"lon":..,"altitude":..,"heading":..}
+//   {"type":"chat","id":3,"name":"bob","text":..}      with lat and lon when it is a map ping
+//   {"type":"leave","id":3}
+//
+// Altitude is in meters above the ground, heading in degrees clockwise from north
+use serde_json::{json, Value};
+
+/// Where a user's camera is on the globe
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoPose {
+    pub lat: f64,
+    pub lon: f64,
+    pub altitude: f64,
+    pub heading: f64,
+}
+
+/// A message from the relay server
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    Welcome { id: u64 },
+    Position { id: u64, name: String, pose: GeoPose },
+    Chat { id: u64, name: String, text: String, location: Option<(f64, f64)> },
+    Leave { id: u64 },
+}
+
+/// Introduce this client under a display name
+pub fn hello_message(name: &str) -> String {
+    json!({ "type": "hello", "name": name }).to_string()
+}
+
+/// Tell the other users where this client's camera is
+pub fn position_message(pose: &GeoPose) -> String {
+    json!({
+        "type": "position",
+        "lat": pose.lat,
+        "lon": pose.lon,
+        "altitude": pose.altitude,
+        "heading": pose.heading,
+    })
+    .to_string()
+}
+
+/// Say something to the other users, optionally pinned to a (lat, lon) on the map
+pub fn chat_message(text: &str, location: Option<(f64, f64)>) -> String {
+    let mut value = json!({ "type": "chat", "text": text });
+    if let Some((lat, lon)) = location {
+        value["lat"] = json!(lat);
+        value["lon"] = json!(lon);
+    }
+    value.to_string()
+}
+
+/// Parse a message from the server, None for anything unknown or malformed
+pub fn parse_server_message(text: &str) -> Option<ServerMessage> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    let id = value["id"].as_u64()?;
+    match value["type"].as_str()? {
+        "welcome" => Some(ServerMessage::Welcome { id }),
+        "leave" => Some(ServerMessage::Leave { id }),
+        "position" => Some(ServerMessage::Position {
+            id,
+            name: value["name"].as_str().unwrap_or("Anonymous").to_string(),
+            pose: GeoPose {
+                lat: value["lat"].as_f64()?.clamp(-90.0, 90.0),
+                lon: value["lon"].as_f64()?,
+                altitude: value["altitude"].as_f64().unwrap_or(0.0),
+                heading: value["heading"].as_f64().unwrap_or(0.0),
+            },
+        }),
+        "chat" => Some(ServerMessage::Chat {
+            id,
+            name: value["name"].as_str().unwrap_or("Anonymous").to_string(),
+            text: value["text"].as_str()?.to_string(),
+            location: value["lat"]
+                .as_f64()
+                .zip(value["lon"].as_f64())
+                .map(|(lat, lon)| (lat.clamp(-90.0, 90.0), lon)),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_messages_are_parsed() {
+        assert_eq!(parse_server_message(r#"{"type":"welcome","id":7}"#), Some(ServerMessage::Welcome { id: 7 }));
+        assert_eq!(parse_server_message(r#"{"type":"leave","id":3}"#), Some(ServerMessage::Leave { id: 3 }));
+        assert_eq!(
+            parse_server_message(r#"{"type":"position","id":3,"name":"bob","lat":53.2,"lon":6.5,"altitude":120.0,"heading":90.0}"#),
+            Some(ServerMessage::Position {
+                id: 3,
+                name: "bob".to_string(),
+                pose: GeoPose { lat: 53.2, lon: 6.5, altitude: 120.0, heading: 90.0 },
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_or_unknown_messages_are_ignored() {
+        assert_eq!(parse_server_message("not json"), None);
+        assert_eq!(parse_server_message(r#"{"type":"position","id":3}"#), None);
+        assert_eq!(parse_server_message(r#"{"type":"dance","id":3}"#), None);
+        assert_eq!(parse_server_message(r#"{"type":"leave"}"#), None);
+    }
+
+    #[test]
+    fn client_messages_carry_the_pose() {
+        let pose = GeoPose { lat: 53.2, lon: 6.5, altitude: 120.0, heading: 90.0 };
+        let value: Value = serde_json::from_str(&position_message(&pose)).unwrap();
+        assert_eq!(value["type"], "position");
+        assert_eq!(value["lat"], 53.2);
+        assert_eq!(value["heading"], 90.0);
+        assert_eq!(serde_json::from_str::<Value>(&hello_message("ann")).unwrap()["name"], "ann");
+    }
+
+    #[test]
+    fn chat_messages_may_carry_a_map_ping() {
+        let value: Value = serde_json::from_str(&chat_message("hi", None)).unwrap();
+        assert_eq!(value["text"], "hi");
+        assert!(value.get("lat").is_none());
+        let value: Value = serde_json::from_str(&chat_message("here", Some((53.2, 6.5)))).unwrap();
+        assert_eq!((value["lat"].as_f64(), value["lon"].as_f64()), (Some(53.2), Some(6.5)));
+
+        assert_eq!(
+            parse_server_message(r#"{"type":"chat","id":3,"name":"bob","text":"look","lat":53.2,"lon":6.5}"#),
+            Some(ServerMessage::Chat {
+                id: 3,
+                name: "bob".to_string(),
+                text: "look".to_string(),
+                location: Some((53.2, 6.5)),
+            })
+        );
+        assert_eq!(
+            parse_server_message(r#"{"type":"chat","id":3,"name":"bob","text":"hi"}"#),
+            Some(ServerMessage::Chat { id: 3, name: "bob".to_string(), text: "hi".to_string(), location: None })
+        );
+        assert_eq!(parse_server_message(r#"{"type":"chat","id":3}"#), None);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio_tungstenite::connect_async_with_config;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use crate::net::presence::{parse_server_message, ServerMessage};
+use crate::resources::runtime::sleep;
+
+// Larger messages are refused, presence and chat messages are tiny
+const MAX_MESSAGE_BYTES: usize = 1 << 20;
+
+// Wait before reconnecting after the connection drops, doubled after every failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// State of the connection to the relay server
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ConnectionStatus {
+    #[default]
+    Offline, // No server configured
+    Connecting,
+    Connected,
+    Disconnected(String), // Why the connection dropped; it is retried after a delay
+}
+
+/// What the connection task hands to the game
+#[derive(Default)]
+pub struct SessionInbox {
+    pub messages: Vec<ServerMessage>,
+    pub status: Option<ConnectionStatus>, // Latest status change not picked up yet
+}
+
+pub type SharedInbox = Arc<Mutex<SessionInbox>>;
+
+/// Keep a connection to the relay server open, sending the outgoing messages and
+/// collecting the server's messages in the inbox, reconnecting when it drops
+/// Ends when the sending side of `outgoing` is dropped
+pub async fn run_session(url: String, mut outgoing: UnboundedReceiver<String>, inbox: SharedInbox) {
+    let set_status = |status| inbox.lock().status = Some(status);
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_MESSAGE_BYTES));
+    let mut delay = MIN_RECONNECT_DELAY;
+
+    loop {
+        set_status(ConnectionStatus::Connecting);
+        match connect_async_with_config(url.as_str(), Some(config), true).await {
+            Ok((socket, _)) => {
+                let (mut writer, mut reader) = socket.split();
+                // Positions queued while offline are stale, the game sends a fresh one
+                while outgoing.try_recv().is_ok() {}
+                set_status(ConnectionStatus::Connected);
+                delay = MIN_RECONNECT_DELAY;
+
+                let reason = loop {
+                    tokio::select! {
+                        text = outgoing.recv() => match text {
+                            Some(text) => {
+                                if let Err(e) = writer.send(Message::text(text)).await {
+                                    break e.to_string();
+                                }
+                            }
+                            None => {
+                                let _ = writer.send(Message::Close(None)).await;
+                                return;
+                            }
+                        },
+                        message = reader.next() => match message {
+                            Some(Ok(Message::Text(text))) => {
+                                if let Some(message) = parse_server_message(&text) {
+                                    inbox.lock().messages.push(message);
+                                }
+                            }
+                            Some(Ok(Message::Close(_))) | None => break "closed by the server".to_string(),
+                            // Pings are answered by tungstenite itself, binary messages are skipped
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => break e.to_string(),
+                        },
+                    }
+                };
+                set_status(ConnectionStatus::Disconnected(reason));
+            }
+            Err(e) => set_status(ConnectionStatus::Disconnected(e.to_string())),
+        }
+
+        // Wait before trying again, unless the game gave up on the server meanwhile
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = async { while outgoing.recv().await.is_some() {} } => return,
+        }
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
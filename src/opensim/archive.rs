@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use anyhow::{anyhow, Context};
+use bevy::log::warn;
+use flate2::read::GzDecoder;
+use roxmltree::Document;
+use super::objects::{parse_object_groups, ObjectGroup};
+use super::terrain::Heightmap;
+
+// Region size and water level OpenSim uses when an archive doesn't say otherwise
+const DEFAULT_REGION_SIZE: f32 = 256.0;
+const DEFAULT_WATER_HEIGHT: f32 = 20.0;
+
+/// An OpenSimulator region: its terrain and the objects placed on it
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub size: f32,         // Meters along each side
+    pub water_height: f32, // Meters above the region's zero, shown at the map's sea level
+    pub terrain: Option<Heightmap>,
+    pub objects: Vec<ObjectGroup>,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_REGION_SIZE,
+            water_height: DEFAULT_WATER_HEIGHT,
+            terrain: None,
+            objects: Vec::new(),
+        }
+    }
+}
+
+/// Load a region from an OAR archive (.oar, .tgz) or an XML object export (.xml)
+/// An XML export has no terrain, its objects stand on the map
+pub fn load_region(path: &Path) -> Result<Region, anyhow::Error> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let is_xml = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+    let region = if is_xml {
+        let mut xml = String::new();
+        BufReader::new(file).read_to_string(&mut xml)?;
+        Region {
+            objects: parse_object_groups(&xml).map_err(|e| anyhow!(e))?,
+            ..Region::default()
+        }
+    } else {
+        read_oar(BufReader::new(file))?
+    };
+
+    Ok(region)
+}
+
+/// Read a region from a gzipped OAR archive
+/// Only the terrain, the objects and the water height are used; assets like textures are skipped
+pub fn read_oar(reader: impl Read) -> Result<Region, anyhow::Error> {
+    let mut region = Region::default();
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        // Multi-region archives put each region's directories under regions/<name>/
+        let mut parts = path.rsplit('/');
+        let (Some(file_name), Some(directory)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+
+        match directory {
+            "terrains" if file_name.ends_with(".r32") && region.terrain.is_none() => {
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes)?;
+                let terrain = Heightmap::from_r32(&bytes).map_err(|e| anyhow!("{}: {}", path, e))?;
+                region.size = terrain.size as f32;
+                region.terrain = Some(terrain);
+            }
+            "objects" if file_name.ends_with(".xml") => {
+                let mut xml = String::new();
+                entry.read_to_string(&mut xml)?;
+                // One broken object shouldn't cost the whole region
+                match parse_object_groups(&xml) {
+                    Ok(objects) => region.objects.extend(objects),
+                    Err(e) => warn!("Skipping {}: {}", path, e),
+                }
+            }
+            "settings" if file_name.ends_with(".xml") => {
+                let mut xml = String::new();
+                entry.read_to_string(&mut xml)?;
+                if let Some(water_height) = water_height(&xml) {
+                    region.water_height = water_height;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(region)
+}
+
+// Water height from a region's settings (<RegionSettings><Terrain><WaterHeight>)
+fn water_height(xml: &str) -> Option<f32> {
+    let document = Document::parse(xml).ok()?;
+    document
+        .descendants()
+        .find(|node| node.has_tag_name("WaterHeight"))?
+        .text()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    // Add a file to an archive being built
+    fn append(builder: &mut tar::Builder<GzEncoder<Vec<u8>>>, path: &str, contents: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, contents).unwrap();
+    }
+
+    #[test]
+    fn oar_archives_are_read() {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        append(&mut builder, "archive.xml", b"<archive major_version=\"0\" minor_version=\"8\"/>");
+        let terrain: Vec<u8> = (0..16).flat_map(|i| (i as f32).to_le_bytes()).collect();
+        append(&mut builder, "terrains/Demo.r32", &terrain);
+        append(&mut builder, "settings/Demo.xml", b"<RegionSettings><Terrain><WaterHeight>3.5</WaterHeight></Terrain></RegionSettings>");
+        append(
+            &mut builder,
+            "objects/Box_128-128-25__00000000.xml",
+            br#"<SceneObjectGroup><RootPart><SceneObjectPart>
+                <Name>Box</Name><GroupPosition><X>1</X><Y>2</Y><Z>3</Z></GroupPosition>
+            </SceneObjectPart></RootPart></SceneObjectGroup>"#,
+        );
+        append(&mut builder, "objects/broken.xml", b"<SceneObjectGroup>");
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let region = read_oar(archive.as_slice()).unwrap();
+        assert_eq!(region.size, 4.0);
+        assert_eq!(region.water_height, 3.5);
+        assert_eq!(region.terrain.unwrap().height(3, 3), 15.0);
+        assert_eq!(region.objects.len(), 1);
+        assert_eq!(region.objects[0].name, "Box");
+    }
+}
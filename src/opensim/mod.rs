@@ -0,0 +1,9 @@
+// OpenSimulator regions for persistent islands: OAR archives and XML object exports,
+// read into a terrain heightmap and the objects placed on it
+mod archive;
+mod objects;
+mod terrain;
+
+pub use archive::{load_region, Region};
+pub use objects::PrimShape;
+pub use terrain::{BrushTool, Heightmap};
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use base64::Engine;
+use roxmltree::{Document, Node};
+
+// OpenSim's path curves: prims are extruded along a line or revolved around a circle
+const PATH_CURVE_LINE: u32 = 16;
+
+/// Basic shape a prim is drawn as
+/// Prims are parametric (cuts, twists, hollows...), only the shape they start from is kept
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrimShape {
+    Box,
+    Cylinder,
+    Prism,
+    Sphere,
+    Torus,
+}
+
+/// One prim of a linked object
+/// Positions and rotations are in the viewer's axes (X east, Y up, -Z north), sizes in meters
+#[derive(Clone, Debug)]
+pub struct Prim {
+    pub shape: PrimShape,
+    pub offset: Vec3,   // Relative to the object's root prim
+    pub rotation: Quat, // Relative to the object's root prim
+    pub scale: Vec3,
+    pub color: Color,
+}
+
+/// A linked object (OpenSim's SceneObjectGroup) placed in a region
+#[derive(Clone, Debug)]
+pub struct ObjectGroup {
+    pub name: String,
+    pub position: Vec3, // Region meters, Y from the region's zero height
+    pub rotation: Quat,
+    pub prims: Vec<Prim>, // The root prim first
+}
+
+/// Read the objects from OpenSim XML: an OAR object file or a whole region saved as XML2
+/// Objects missing their position are skipped
+pub fn parse_object_groups(xml: &str) -> Result<Vec<ObjectGroup>, String> {
+    let document = Document::parse(xml).map_err(|e| e.to_string())?;
+
+    Ok(document
+        .descendants()
+        .filter(|node| node.has_tag_name("SceneObjectGroup"))
+        .filter_map(|group| {
+            let root = child(group, "RootPart").and_then(|root| child(root, "SceneObjectPart"))?;
+            let mut prims = vec![Prim {
+                offset: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                ..parse_prim(root)
+            }];
+
+            // Older exports wrap each part in a <Part> element
+            if let Some(others) = child(group, "OtherParts") {
+                prims.extend(others.descendants().filter(|node| node.has_tag_name("SceneObjectPart")).map(parse_prim));
+            }
+
+            Some(ObjectGroup {
+                name: text(root, "Name").unwrap_or_default().to_string(),
+                position: vector(root, "GroupPosition")?,
+                rotation: rotation(root, "RotationOffset").unwrap_or(Quat::IDENTITY),
+                prims,
+            })
+        })
+        .collect())
+}
+
+// Read a prim, relative to its object's root
+fn parse_prim(part: Node) -> Prim {
+    let shape = child(part, "Shape");
+    let profile = shape.and_then(|shape| text(shape, "ProfileShape")).unwrap_or("Square");
+    let path_curve = shape
+        .and_then(|shape| text(shape, "PathCurve"))
+        .and_then(|curve| curve.parse().ok())
+        .unwrap_or(PATH_CURVE_LINE);
+    let color = shape
+        .and_then(|shape| text(shape, "TextureEntry"))
+        .and_then(|entry| base64::engine::general_purpose::STANDARD.decode(entry.trim()).ok())
+        .and_then(|entry| default_face_color(&entry))
+        .unwrap_or(Color::WHITE);
+
+    Prim {
+        shape: prim_shape(profile, path_curve),
+        offset: vector(part, "OffsetPosition").unwrap_or(Vec3::ZERO),
+        rotation: rotation(part, "RotationOffset").unwrap_or(Quat::IDENTITY),
+        // Sizes have no direction, the axes are only swapped
+        scale: xml_vector(part, "Scale").map(|scale| Vec3::new(scale.x, scale.z, scale.y)).unwrap_or(Vec3::ONE),
+        color,
+    }
+}
+
+/// The shape to draw for a prim's profile (its cross section) and path curve
+pub fn prim_shape(profile: &str, path_curve: u32) -> PrimShape {
+    let extruded = path_curve == PATH_CURVE_LINE;
+    match profile {
+        "Circle" if extruded => PrimShape::Cylinder,
+        "HalfCircle" => PrimShape::Sphere,
+        "EquilateralTriangle" | "IsometricTriangle" | "RightTriangle" if extruded => PrimShape::Prism,
+        "Square" if extruded => PrimShape::Box,
+        // Any profile revolved around a circle makes a ring
+        _ if !extruded => PrimShape::Torus,
+        _ => PrimShape::Box,
+    }
+}
+
+/// Color of all faces of a prim that don't override it, from its binary TextureEntry
+/// The entry holds the face textures first, then the colors, each a default followed by
+/// per-face exceptions; colors are stored inverted so that white is all zeros
+pub fn default_face_color(entry: &[u8]) -> Option<Color> {
+    // Default texture, then (face bits, texture) pairs until the face bits are zero
+    let mut position = 16;
+    while read_face_bits(entry, &mut position)? != 0 {
+        position += 16;
+    }
+
+    let rgba = entry.get(position..position + 4)?;
+    Some(Color::srgba_u8(255 - rgba[0], 255 - rgba[1], 255 - rgba[2], 255 - rgba[3]))
+}
+
+// Read a face bit field: 7 bits per byte, the high bit set on all but the last byte
+fn read_face_bits(entry: &[u8], position: &mut usize) -> Option<u32> {
+    let mut bits = 0u32;
+    loop {
+        let byte = *entry.get(*position)?;
+        *position += 1;
+        bits = (bits << 7) | (byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            return Some(bits);
+        }
+    }
+}
+
+// First child element with the given name
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+// Text of a child element
+fn text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|child| child.text())
+}
+
+// Number in a child element
+fn number(node: Node, name: &str) -> Option<f32> {
+    text(node, name)?.trim().parse().ok()
+}
+
+// An <X>, <Y>, <Z> vector in OpenSim's axes (X east, Y north, Z up)
+fn xml_vector(node: Node, name: &str) -> Option<Vec3> {
+    let vector = child(node, name)?;
+    Some(Vec3::new(number(vector, "X")?, number(vector, "Y")?, number(vector, "Z")?))
+}
+
+// A position, turned into the viewer's axes
+fn vector(node: Node, name: &str) -> Option<Vec3> {
+    xml_vector(node, name).map(|v| Vec3::new(v.x, v.z, -v.y))
+}
+
+// A rotation, turned into the viewer's axes
+// The axis change is itself a rotation, so the quaternion's axis part turns like a position
+fn rotation(node: Node, name: &str) -> Option<Quat> {
+    let rotation = child(node, name)?;
+    let (x, y, z, w) = (number(rotation, "X")?, number(rotation, "Y")?, number(rotation, "Z")?, number(rotation, "W")?);
+    Some(Quat::from_xyzw(x, z, -y, w).normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJECT_XML: &str = r#"<SceneObjectGroup>
+        <RootPart><SceneObjectPart>
+            <Name>Lighthouse</Name>
+            <GroupPosition><X>128</X><Y>64</Y><Z>25</Z></GroupPosition>
+            <OffsetPosition><X>0</X><Y>0</Y><Z>0</Z></OffsetPosition>
+            <RotationOffset><X>0</X><Y>0</Y><Z>0.7071068</Z><W>0.7071068</W></RotationOffset>
+            <Shape><ProfileShape>Circle</ProfileShape><PathCurve>16</PathCurve></Shape>
+            <Scale><X>2</X><Y>2</Y><Z>10</Z></Scale>
+        </SceneObjectPart></RootPart>
+        <OtherParts><Part><SceneObjectPart>
+            <Name>Lamp</Name>
+            <GroupPosition><X>128</X><Y>64</Y><Z>25</Z></GroupPosition>
+            <OffsetPosition><X>0</X><Y>0</Y><Z>5.5</Z></OffsetPosition>
+            <RotationOffset><X>0</X><Y>0</Y><Z>0</Z><W>1</W></RotationOffset>
+            <Shape><ProfileShape>HalfCircle</ProfileShape><PathCurve>32</PathCurve></Shape>
+            <Scale><X>1</X><Y>1</Y><Z>1</Z></Scale>
+        </SceneObjectPart></Part></OtherParts>
+    </SceneObjectGroup>"#;
+
+    #[test]
+    fn linked_objects_are_read_in_the_viewers_axes() {
+        let groups = parse_object_groups(OBJECT_XML).unwrap();
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!(group.name, "Lighthouse");
+        // North is -Z, up is Y
+        assert_eq!(group.position, Vec3::new(128.0, 25.0, -64.0));
+        // A quarter turn counterclockwise seen from above turns east into north
+        assert!((group.rotation * Vec3::X).abs_diff_eq(Vec3::NEG_Z, 1e-5));
+
+        assert_eq!(group.prims.len(), 2);
+        assert_eq!(group.prims[0].shape, PrimShape::Cylinder);
+        assert_eq!(group.prims[0].scale, Vec3::new(2.0, 10.0, 2.0));
+        assert_eq!(group.prims[1].shape, PrimShape::Sphere);
+        assert_eq!(group.prims[1].offset, Vec3::new(0.0, 5.5, 0.0));
+    }
+
+    #[test]
+    fn prim_shapes_follow_profile_and_path() {
+        assert_eq!(prim_shape("Square", 16), PrimShape::Box);
+        assert_eq!(prim_shape("Circle", 16), PrimShape::Cylinder);
+        assert_eq!(prim_shape("RightTriangle", 16), PrimShape::Prism);
+        assert_eq!(prim_shape("HalfCircle", 32), PrimShape::Sphere);
+        assert_eq!(prim_shape("Circle", 32), PrimShape::Torus);
+        assert_eq!(prim_shape("Unknown", 16), PrimShape::Box);
+    }
+
+    #[test]
+    fn face_colors_are_stored_inverted() {
+        let mut entry = vec![0u8; 16]; // Default texture
+        entry.extend([0x81, 0x00]); // Face 7...
+        entry.extend([0u8; 16]); // ...has another texture
+        entry.push(0); // End of the textures
+        entry.extend([255 - 200, 255 - 100, 255 - 50, 0]);
+        assert_eq!(default_face_color(&entry), Some(Color::srgba_u8(200, 100, 50, 255)));
+        assert_eq!(default_face_color(&entry[..20]), None);
+    }
+}
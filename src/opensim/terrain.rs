@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+
+// Most vertices along one side of a terrain mesh, larger (var) regions are sampled down to this
+const MAX_TERRAIN_VERTICES: usize = 257;
+// Terrain colors by height above the water: beach, grass and rock
+const SAND_COLOR: [f32; 4] = [0.76, 0.70, 0.50, 1.0];
+const GRASS_COLOR: [f32; 4] = [0.30, 0.50, 0.22, 1.0];
+const ROCK_COLOR: [f32; 4] = [0.50, 0.47, 0.44, 1.0];
+const BEACH_HEIGHT: f32 = 1.5;
+const TREELINE_HEIGHT: f32 = 40.0;
+// Distance between the samples a raycast tests, in meters
+const RAYCAST_STEP: f32 = 0.5;
+
+/// How a brush changes the terrain under it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrushTool {
+    #[default]
+    Raise,
+    Lower,
+    Smooth,
+    Flatten,
+}
+
+impl BrushTool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrushTool::Raise => "Raise",
+            BrushTool::Lower => "Lower",
+            BrushTool::Smooth => "Smooth",
+            BrushTool::Flatten => "Flatten",
+        }
+    }
+
+    /// The tool after this one, wrapping around
+    pub fn next(&self) -> Self {
+        match self {
+            BrushTool::Raise => BrushTool::Lower,
+            BrushTool::Lower => BrushTool::Smooth,
+            BrushTool::Smooth => BrushTool::Flatten,
+            BrushTool::Flatten => BrushTool::Raise,
+        }
+    }
+}
+
+/// Terrain heights of a region, one sample per meter in meters above the region's zero
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    pub size: usize,       // Samples along each side
+    pub heights: Vec<f32>, // Row by row from the south edge, west to east within a row
+}
+
+impl Heightmap {
+    /// Read a terrain in OpenSim's RAW32 format (.r32): little endian f32 samples of a square region
+    pub fn from_r32(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+            return Err(format!("{} bytes is not a whole number of heights", bytes.len()));
+        }
+
+        let heights: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|sample| f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]))
+            .collect();
+        let size = (heights.len() as f64).sqrt() as usize;
+        if size * size != heights.len() {
+            return Err(format!("{} heights don't make a square region", heights.len()));
+        }
+
+        Ok(Self { size, heights })
+    }
+
+    /// A level terrain
+    pub fn flat(size: usize, height: f32) -> Self {
+        Self { size, heights: vec![height; size * size] }
+    }
+
+    /// Write the terrain in OpenSim's RAW32 format, so it can be loaded into a region again
+    pub fn to_r32(&self) -> Vec<u8> {
+        self.heights.iter().flat_map(|height| height.to_le_bytes()).collect()
+    }
+
+    /// Height at a sample, clamped to the edges
+    pub fn height(&self, x: usize, y: usize) -> f32 {
+        let x = x.min(self.size - 1);
+        let y = y.min(self.size - 1);
+        self.heights[y * self.size + x]
+    }
+
+    /// Height between the samples, None outside the region
+    pub fn height_at(&self, x: f32, y: f32) -> Option<f32> {
+        let max = (self.size - 1) as f32;
+        if !(0.0..=max).contains(&x) || !(0.0..=max).contains(&y) {
+            return None;
+        }
+
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (fx, fy) = (x.fract(), y.fract());
+        let south = self.height(x0, y0) * (1.0 - fx) + self.height(x0 + 1, y0) * fx;
+        let north = self.height(x0, y0 + 1) * (1.0 - fx) + self.height(x0 + 1, y0 + 1) * fx;
+        Some(south * (1.0 - fy) + north * fy)
+    }
+
+    /// Where a ray first hits the terrain, in the mesh's coordinates (see `mesh`)
+    /// The direction has to be normalized
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, water_height: f32) -> Option<Vec3> {
+        // Only the part of the ray above the region is tested
+        let max = (self.size - 1) as f32;
+        let (mut enter, mut exit) = (0.0f32, f32::MAX);
+        for (start, step, low, high) in [(origin.x, direction.x, 0.0, max), (origin.z, direction.z, -max, 0.0)] {
+            if step.abs() < 1e-9 {
+                if start < low || start > high {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((low - start) / step, (high - start) / step);
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+        if enter > exit {
+            return None;
+        }
+
+        // Height of the ray above the ground after t meters
+        let above = |t: f32| {
+            let point = origin + direction * t;
+            self.height_at(point.x, -point.z).map_or(f32::MAX, |height| point.y - (height - water_height))
+        };
+
+        let mut t = enter;
+        if above(t) <= 0.0 {
+            return Some(origin + direction * t);
+        }
+        while t < exit {
+            let (mut low, mut high) = (t, (t + RAYCAST_STEP).min(exit));
+            t = high;
+            if above(high) > 0.0 {
+                continue;
+            }
+            // Narrow down the crossing between the last two samples
+            for _ in 0..8 {
+                let middle = (low + high) / 2.0;
+                if above(middle) > 0.0 {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            return Some(origin + direction * high);
+        }
+        None
+    }
+
+    /// Sculpt the terrain with a round brush centered on a sample position (x east, y north)
+    /// Raise and lower move the middle by `amount` meters; smooth and flatten move it that
+    /// fraction of the way to the neighbours' average or to `target`. The effect fades out to the rim
+    pub fn apply_brush(&mut self, tool: BrushTool, center: Vec2, radius: f32, amount: f32, target: f32) {
+        let max = (self.size - 1) as f32;
+        let (x_min, x_max) = ((center.x - radius).floor().max(0.0) as usize, (center.x + radius).ceil().min(max) as usize);
+        let (y_min, y_max) = ((center.y - radius).floor().max(0.0) as usize, (center.y + radius).ceil().min(max) as usize);
+        if center.x + radius < 0.0 || center.y + radius < 0.0 || x_min > x_max || y_min > y_max {
+            return;
+        }
+
+        let original = self.clone();
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                let distance = Vec2::new(x as f32, y as f32).distance(center);
+                if distance >= radius {
+                    continue;
+                }
+                let edge = 1.0 - distance / radius;
+                let falloff = edge * edge * (3.0 - 2.0 * edge);
+
+                let height = &mut self.heights[y * self.size + x];
+                match tool {
+                    BrushTool::Raise => *height += amount * falloff,
+                    BrushTool::Lower => *height -= amount * falloff,
+                    BrushTool::Smooth => {
+                        let neighbours = [(0, 1), (2, 1), (1, 0), (1, 2)]
+                            .map(|(dx, dy)| original.height((x + dx).saturating_sub(1), (y + dy).saturating_sub(1)));
+                        let average = neighbours.iter().sum::<f32>() / 4.0;
+                        *height += (average - *height) * (amount * falloff).min(1.0);
+                    }
+                    BrushTool::Flatten => *height += (target - *height) * (amount * falloff).min(1.0),
+                }
+            }
+        }
+    }
+
+    /// Mesh of the terrain in region meters: X east, Y up from the water level, -Z north
+    pub fn mesh(&self, water_height: f32) -> Mesh {
+        let step = self.size.div_ceil(MAX_TERRAIN_VERTICES - 1).max(1);
+        let count = (self.size - 1) / step + 1;
+
+        let mut positions = Vec::with_capacity(count * count);
+        let mut colors = Vec::with_capacity(count * count);
+        for j in 0..count {
+            for i in 0..count {
+                let (x, y) = (i * step, j * step);
+                let height = self.height(x, y) - water_height;
+                positions.push([x as f32, height, -(y as f32)]);
+                colors.push(terrain_color(height));
+            }
+        }
+
+        let mut indices = Vec::with_capacity((count - 1) * (count - 1) * 6);
+        for j in 0..count as u32 - 1 {
+            for i in 0..count as u32 - 1 {
+                let vertex = |i: u32, j: u32| j * count as u32 + i;
+                indices.extend([vertex(i, j), vertex(i + 1, j), vertex(i, j + 1)]);
+                indices.extend([vertex(i + 1, j), vertex(i + 1, j + 1), vertex(i, j + 1)]);
+            }
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+            .with_inserted_indices(Indices::U32(indices));
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+// Color of the ground at a height above the water
+fn terrain_color(height: f32) -> [f32; 4] {
+    if height < BEACH_HEIGHT {
+        SAND_COLOR
+    } else if height < TREELINE_HEIGHT {
+        GRASS_COLOR
+    } else {
+        ROCK_COLOR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn r32_terrains_are_square() {
+        let bytes: Vec<u8> = [20.0f32, 21.0, 22.0, 23.0].iter().flat_map(|h| h.to_le_bytes()).collect();
+        let heightmap = Heightmap::from_r32(&bytes).unwrap();
+        assert_eq!(heightmap.size, 2);
+        // Rows start at the south edge
+        assert_eq!(heightmap.height(1, 0), 21.0);
+        assert_eq!(heightmap.height(0, 1), 22.0);
+        assert_eq!(heightmap.height(5, 5), 23.0);
+
+        assert!(Heightmap::from_r32(&bytes[..12]).is_err());
+        assert!(Heightmap::from_r32(&bytes[..7]).is_err());
+        assert_eq!(Heightmap::from_r32(&bytes).unwrap().to_r32(), bytes);
+    }
+
+    #[test]
+    fn brushes_fade_out_to_the_rim() {
+        let mut heightmap = Heightmap::flat(32, 20.0);
+        heightmap.apply_brush(BrushTool::Raise, Vec2::new(16.0, 16.0), 4.0, 2.0, 0.0);
+        assert_eq!(heightmap.height(16, 16), 22.0);
+        assert!(heightmap.height(18, 16) > 20.0 && heightmap.height(18, 16) < 22.0);
+        assert_eq!(heightmap.height(20, 16), 20.0);
+
+        heightmap.apply_brush(BrushTool::Flatten, Vec2::new(16.0, 16.0), 8.0, 1.0, 20.0);
+        assert_eq!(heightmap.height(16, 16), 20.0);
+
+        // Brushes partly outside the region only touch the inside
+        heightmap.apply_brush(BrushTool::Lower, Vec2::new(0.0, 0.0), 3.0, 1.0, 0.0);
+        assert_eq!(heightmap.height(0, 0), 19.0);
+    }
+
+    #[test]
+    fn rays_hit_the_ground() {
+        let heightmap = Heightmap::flat(64, 25.0);
+        // Looking down at 45 degrees from 10 meters above the water, 5 above the ground
+        let direction = Vec3::new(1.0, -1.0, 0.0).normalize();
+        let hit = heightmap.raycast(Vec3::new(10.0, 10.0, -20.0), direction, 20.0).unwrap();
+        assert!(hit.abs_diff_eq(Vec3::new(15.0, 5.0, -20.0), 0.01));
+
+        // Rays passing beside the region miss
+        assert!(heightmap.raycast(Vec3::new(-10.0, 10.0, 10.0), direction, 20.0).is_none());
+    }
+}
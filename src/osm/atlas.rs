@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::image::{ImageSampler, ImageSamplerDescriptor};
+use bevy::render::render_resource::{
+    Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension,
+};
+use crate::osm::compression::{bc1_size, compress_bc1};
+use crate::osm::mipmaps::{generate_mips, mip_levels};
+use crate::osm::tile_material::{ATTRIBUTE_TILE_FADE_START, ATTRIBUTE_TILE_LAYER};
+
+/// Width and height of every tile image in the atlas, other sizes are resized on upload
+pub const TILE_LAYER_SIZE: u32 = 256;
+/// Number of tile images in one atlas page (array texture)
+pub const LAYERS_PER_PAGE: u32 = 64;
+// Most texels a tile pixel averages when seen at an angle
+const MAX_ANISOTROPY: u16 = 16;
+
+/// Bytes of one layer of an atlas page, its mip chain included
+pub fn atlas_layer_bytes(compressed: bool) -> usize {
+    (0..mip_levels(TILE_LAYER_SIZE))
+        .map(|level| {
+            let size = TILE_LAYER_SIZE >> level;
+            if compressed {
+                bc1_size(size, size)
+            } else {
+                (size * size * 4) as usize
+            }
+        })
+        .sum()
+}
+
+/// Turn the RGBA8 pixels of a tile image into the data of an atlas layer: its mip chain,
+/// BC1 compressed for a compressed atlas
+/// Images that aren't TILE_LAYER_SIZE square are returned as is, they don't fit a layer
+pub fn atlas_layer_data(rgba: &[u8], compressed: bool) -> Vec<u8> {
+    if rgba.len() != (TILE_LAYER_SIZE * TILE_LAYER_SIZE * 4) as usize {
+        return rgba.to_vec();
+    }
+    let levels = generate_mips(rgba, TILE_LAYER_SIZE);
+    if !compressed {
+        return levels.concat();
+    }
+    levels
+        .iter()
+        .enumerate()
+        .flat_map(|(level, pixels)| {
+            let size = TILE_LAYER_SIZE >> level;
+            compress_bc1(pixels, size, size)
+        })
+        .collect()
+}
+
+/// Create an empty atlas page, RGBA8 or BC1 compressed, with mipmaps and anisotropic sampling
+/// The image stays in the main world so tile images can be written into its layers
+pub fn create_atlas_page(compressed: bool) -> Image {
+    // Image::new checks the data against the first mip level only, and against a pixel size,
+    // which compressed formats don't have
+    let mut image = Image {
+        data: vec![0; atlas_layer_bytes(compressed) * LAYERS_PER_PAGE as usize],
+        asset_usage: RenderAssetUsages::default(),
+        sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
+            anisotropy_clamp: MAX_ANISOTROPY,
+            ..ImageSamplerDescriptor::linear()
+        }),
+        ..default()
+    };
+    image.texture_descriptor.size = Extent3d {
+        width: TILE_LAYER_SIZE,
+        height: TILE_LAYER_SIZE,
+        depth_or_array_layers: LAYERS_PER_PAGE,
+    };
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = if compressed {
+        TextureFormat::Bc1RgbaUnormSrgb
+    } else {
+        TextureFormat::Rgba8UnormSrgb
+    };
+    // Layers are stored one after the other, each with its mip chain
+    image.texture_descriptor.mip_level_count = mip_levels(TILE_LAYER_SIZE);
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..default()
+    });
+    image
+}
+
+/// Copy a tile layer's data (see atlas_layer_data) into a layer of an atlas page
+/// Returns false if the data doesn't have the size of a layer
+pub fn write_atlas_layer(page: &mut Image, layer: u32, data: &[u8]) -> bool {
+    let layer_bytes = page.data.len() / LAYERS_PER_PAGE as usize;
+    if data.len() != layer_bytes || layer >= LAYERS_PER_PAGE {
+        return false;
+    }
+    let offset = layer as usize * layer_bytes;
+    page.data[offset..offset + layer_bytes].copy_from_slice(data);
+    true
+}
+
+/// One tile in a batch mesh
+pub struct BatchQuad {
+    pub transform: Transform, // Maps the unit square to the tile's area, like the tile entity's transform
+    pub layer: u32,
+    pub tint: Color,      // Alpha is the strength of the tint
+    pub fade_start: f32,  // Wrapped elapsed seconds when the tile appeared, see TileFilter::fade_duration
+}
+
+/// Merge tile quads into one mesh in world space
+/// Each quad is split into subdivisions x subdivisions cells, so it can bend onto the globe
+pub fn build_batch_mesh(quads: &[BatchQuad], subdivisions: u32) -> Mesh {
+    let cells = subdivisions.max(1);
+    let side = cells + 1;
+    let vertices_per_quad = (side * side) as usize;
+
+    let mut positions = Vec::with_capacity(quads.len() * vertices_per_quad);
+    let mut uvs = Vec::with_capacity(quads.len() * vertices_per_quad);
+    let mut layers = Vec::with_capacity(quads.len() * vertices_per_quad);
+    let mut colors = Vec::with_capacity(quads.len() * vertices_per_quad);
+    let mut fade_starts = Vec::with_capacity(quads.len() * vertices_per_quad);
+    let mut indices = Vec::with_capacity(quads.len() * (cells * cells) as usize * 6);
+
+    for (i, quad) in quads.iter().enumerate() {
+        let base = (i * vertices_per_quad) as u32;
+        let color = quad.tint.to_linear().to_f32_array();
+        // Grid over the unit tile: OSM (0,0) is the northwest corner, which is UV (0,0)
+        for row in 0..side {
+            for column in 0..side {
+                let (u, v) = (column as f32 / cells as f32, row as f32 / cells as f32);
+                positions.push(quad.transform.transform_point(Vec3::new(u, 0.0, v)).to_array());
+                uvs.push([u, v]);
+                layers.push(quad.layer);
+                colors.push(color);
+                fade_starts.push(quad.fade_start);
+            }
+        }
+        for row in 0..cells {
+            for column in 0..cells {
+                let corner = base + row * side + column;
+                indices.extend_from_slice(&[
+                    corner,
+                    corner + 1,
+                    corner + side + 1,
+                    corner,
+                    corner + side + 1,
+                    corner + side,
+                ]);
+            }
+        }
+    }
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_attribute(ATTRIBUTE_TILE_LAYER, layers)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_attribute(ATTRIBUTE_TILE_FADE_START, fade_starts)
+        .with_inserted_indices(Indices::U32(indices))
+}
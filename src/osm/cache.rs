@@ -1,15 +1,26 @@
 use bevy::prelude::*;
-use std::path::Path;
+use std::path::PathBuf;
 use std::fs;
 use std::io;
 use std::time::Duration;
-use reqwest::Client;
+use async_trait::async_trait;
+use bytes::Bytes;
 use image::DynamicImage;
-use crate::osm::tile::OSMTile;
+use crate::osm::download::{HttpTileSource, TileResponse};
+use crate::osm::http_cache::{file_modified, unix_now, CacheValidators};
+use crate::osm::source::TileSource;
+use crate::osm::tile::{cache_dir, OSMTile, TileId};
+
+// URL scheme of tile directories on disk
+const FILE_SCHEME: &str = "file://";
+
+// How many zoom levels up to look for cached imagery when a tile can't be loaded
+// At 8 levels up a 256px tile covers a single source pixel
+const MAX_ANCESTOR_LEVELS: u32 = 8;
 
 // Initialize the tile cache system
 pub fn init_tile_cache() -> io::Result<()> {
-    let cache_dir = Path::new("tile_cache");
+    let cache_dir = cache_dir();
     if !cache_dir.exists() {
         fs::create_dir_all(cache_dir)?;
         info!("Created tile cache directory: {}", cache_dir.display());
@@ -18,70 +29,182 @@ pub fn init_tile_cache() -> io::Result<()> {
 }
 
 // Try to load a tile from the cache
-pub fn load_tile_from_cache(tile: &OSMTile) -> Option<DynamicImage> {
-    let cache_path = tile.get_cache_path();
-
-    if cache_path.exists() {
-        match image::open(&cache_path) {
-            Ok(img) => {
-                info!("Loaded tile {},{},{} from cache", tile.x, tile.y, tile.z);
-                return Some(img);
-            },
-            Err(e) => {
-                warn!("Failed to load cached tile: {}", e);
-                // Try to remove corrupt cache file
-                let _ = fs::remove_file(&cache_path);
-            }
-        }
+// The file holds the tile as the server sent it, whatever its extension says
+pub fn load_tile_from_cache(tile: &OSMTile, tile_server: &str) -> Option<Bytes> {
+    let cache_path = tile.get_cache_path(tile_server);
+    let bytes = fs::read(&cache_path).ok()?;
+
+    if image::guess_format(&bytes).is_err() {
+        warn!("Removing corrupt cached tile {}", cache_path.display());
+        let _ = fs::remove_file(&cache_path);
+        return None;
     }
-
-    None
+    info!("Loaded tile {},{},{} from cache", tile.x, tile.y, tile.z);
+    Some(bytes.into())
 }
 
 // Save a tile to the cache
-pub fn save_tile_to_cache(tile: &OSMTile, image: &DynamicImage) {
-    let cache_path = tile.get_cache_path();
+pub fn save_tile_to_cache(tile: &OSMTile, tile_server: &str, bytes: &[u8]) {
+    let cache_path = tile.get_cache_path(tile_server);
 
-    match image.save(&cache_path) {
+    match fs::write(&cache_path, bytes) {
         Ok(_) => info!("Saved tile {},{},{} to cache", tile.x, tile.y, tile.z),
         Err(e) => warn!("Failed to cache tile: {}", e),
     }
 }
 
-pub async fn load_tile_image(tile: &OSMTile) -> Result<DynamicImage, anyhow::Error> {
-    // First try loading from cache
-    if let Some(cached_image) = load_tile_from_cache(tile) {
-        return Ok(cached_image);
+// Find the closest cached ancestor of a tile and cut out the area covered by the tile,
+// scaled up to full tile size
+pub fn load_ancestor_from_cache(tile: &OSMTile, tile_server: &str) -> Option<DynamicImage> {
+    let id = TileId::new(tile.x, tile.y, tile.z);
+    let mut ancestor = id;
+
+    for levels_up in 1..=MAX_ANCESTOR_LEVELS.min(tile.z) {
+        ancestor = ancestor.parent()?;
+        let cache_path = OSMTile::new(ancestor.x, ancestor.y, ancestor.z).get_cache_path(tile_server);
+        let Ok(bytes) = fs::read(&cache_path) else {
+            continue;
+        };
+        let Ok(image) = image::load_from_memory(&bytes) else {
+            continue;
+        };
+
+        // Position of the tile inside its ancestor, in ancestor pixels
+        let sub_size = image.width() >> levels_up;
+        if sub_size == 0 {
+            return None;
+        }
+        let offset_x = (id.x - (ancestor.x << levels_up)) * sub_size;
+        let offset_y = (id.y - (ancestor.y << levels_up)) * sub_size;
+
+        info!("Using cached zoom {} ancestor for tile {},{},{}", ancestor.z, tile.x, tile.y, tile.z);
+        let cropped = image.crop_imm(offset_x, offset_y, sub_size, sub_size);
+        return Some(cropped.resize_exact(image.width(), image.height(), image::imageops::FilterType::Triangle));
+    }
+
+    None
+}
+
+/// A tile server's tiles through the disk cache: cached tiles older than the ttl are checked
+/// with the server first, and when a tile can't be fetched (e.g. while offline) the fallback
+/// is upscaled imagery of a cached ancestor. While offline the server isn't asked at all
+pub struct CachedTileSource {
+    http: HttpTileSource,
+    ttl: Duration,
+}
+
+impl CachedTileSource {
+    pub fn new(http: HttpTileSource, ttl: Duration) -> Self {
+        Self { http, ttl }
     }
+}
 
-    // If not in cache, fetch from network
-    info!("Tile not in cache, fetching from network: {},{},{}", tile.x, tile.y, tile.z);
+#[async_trait]
+impl TileSource for CachedTileSource {
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        let tile = OSMTile::new(id.x, id.y, id.z);
+        let tile_server = self.http.url.as_str();
+        let cache_path = tile.get_cache_path(tile_server);
+        let mut validators = CacheValidators::read(&cache_path);
+
+        // First try loading from cache, fresh tiles are used as they are
+        let cached = load_tile_from_cache(&tile, tile_server);
+        if let Some(bytes) = &cached {
+            if !validators.expired(file_modified(&cache_path), unix_now(), self.ttl) {
+                return Ok(bytes.clone());
+            }
+        }
 
-    // Create a client with proper user agent and timeout
-    let client = Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent("bevy_osm_viewer/0.1.0 (github.com/user/bevy_osm_viewer)")
-        .build()?;
+        // Offline only the disk cache is used, expired tiles included, instead of waiting
+        // for every request to fail
+        if self.http.network.is_offline() {
+            return cached.ok_or_else(|| anyhow::anyhow!("Offline and tile {},{},{} isn't cached", tile.x, tile.y, tile.z));
+        }
 
-    let url = tile.get_url();
-    info!("Requesting OSM tile URL: {}", url);
+        // If not in cache, fetch from network
+        match &cached {
+            Some(_) => info!("Cached tile expired, checking with the server: {},{},{}", tile.x, tile.y, tile.z),
+            None => info!("Tile not in cache, fetching from network: {},{},{}", tile.x, tile.y, tile.z),
+        }
 
-    // Attempt to load the tile with better error handling
-    let response = client.get(&url).send().await?;
+        // An expired tile is still better than none when the server can't be reached
+        let conditional = cached.as_ref().map(|_| &validators);
+        let response = match self.http.request(id, conditional).await {
+            Ok(response) => response,
+            Err(e) => return cached.ok_or(e),
+        };
+
+        match (response, cached) {
+            (TileResponse::Image(bytes, validators), _) => {
+                // Save to cache, with the validators for the next check
+                save_tile_to_cache(&tile, tile_server, &bytes);
+                if let Err(e) = validators.write(&cache_path) {
+                    warn!("Failed to store cache validators: {}", e);
+                }
+                Ok(bytes)
+            }
+            (TileResponse::NotModified, Some(cached)) => {
+                info!("Tile {},{},{} not modified", tile.x, tile.y, tile.z);
+                validators.checked = unix_now();
+                if let Err(e) = validators.write(&cache_path) {
+                    warn!("Failed to store cache validators: {}", e);
+                }
+                Ok(cached)
+            }
+            (TileResponse::NotModified, None) => Err(anyhow::anyhow!("Unexpected 304 for an unconditional request")),
+        }
+    }
 
-    if !response.status().is_success() {
-        error!("Failed to load tile {},{} - HTTP status: {}", tile.x, tile.y, response.status());
-        return Err(anyhow::anyhow!("HTTP error: {}", response.status()));
+    fn fallback(&self, id: TileId) -> Option<DynamicImage> {
+        load_ancestor_from_cache(&OSMTile::new(id.x, id.y, id.z), &self.http.url)
     }
+}
 
-    let bytes = response.bytes().await?;
-    info!("Received {} bytes for tile {},{}", bytes.len(), tile.x, tile.y);
+/// Tiles read from a directory, without any downloads: file:// URL templates like
+/// file:///data/tiles/{z}/{x}/{y}.png, e.g. a region exported by --headless --output
+pub struct DiskTileSource {
+    path: String, // Path template, {z}/{x}/{y} are replaced with the tile address
+}
 
-    let image = image::load_from_memory(&bytes)?;
-    info!("Image loaded: {}x{}", image.width(), image.height());
+impl DiskTileSource {
+    /// The directory of a file:// URL template, None for other URLs
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.strip_prefix(FILE_SCHEME)?;
+        path.contains("{z}").then(|| Self { path: path.to_string() })
+    }
+}
 
-    // Save to cache
-    save_tile_to_cache(tile, &image);
+#[async_trait]
+impl TileSource for DiskTileSource {
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        let path = PathBuf::from(OSMTile::new(id.x, id.y, id.z).get_url(&self.path));
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(bytes.into())
+    }
+}
 
-    Ok(image)
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osm::mock::MockTileSource;
+
+    #[tokio::test]
+    async fn directories_are_read_by_tile_address() {
+        let dir = std::env::temp_dir().join(format!("vibers-disk-source-{}", std::process::id()));
+        let id = TileId::new(4216, 2668, 13);
+        let bytes = MockTileSource::from_url("mock://tiles").unwrap().fetch(id).await.unwrap();
+        let tile_dir = dir.join("13").join("4216");
+        fs::create_dir_all(&tile_dir).unwrap();
+        fs::write(tile_dir.join("2668.png"), &bytes).unwrap();
+
+        let url = format!("file://{}/{{z}}/{{x}}/{{y}}.png", dir.display());
+        let source = DiskTileSource::from_url(&url).unwrap();
+        assert_eq!(source.fetch(id).await.unwrap(), bytes);
+        assert!(source.fetch(TileId::new(4217, 2668, 13)).await.is_err());
+        assert!(DiskTileSource::from_url("https://tile.openstreetmap.org/{z}/{x}/{y}.png").is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
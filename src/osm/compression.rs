@@ -0,0 +1,306 @@
+// BC1 (DXT1) block compression of tile images
+// Every 4x4 pixel block becomes two RGB565 end colors and a 2 bit index per pixel, 8 bytes
+// in all: an eighth of RGBA8, so compressed atlas pages take far less GPU memory
+// Alpha is kept as 1 bit, pixels are either opaque or fully transparent
+
+/// Bytes of one compressed 4x4 block
+pub const BC1_BLOCK_BYTES: usize = 8;
+
+// Pixels with less alpha are transparent in the compressed image
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Bytes of a width x height image compressed to BC1
+pub fn bc1_size(width: u32, height: u32) -> usize {
+    width.div_ceil(4) as usize * height.div_ceil(4) as usize * BC1_BLOCK_BYTES
+}
+
+/// Compress RGBA8 pixels to BC1, blocks in rows from the top-left like the pixels
+/// Blocks with transparent pixels use BC1's three color mode, where index 3 is transparent
+pub fn compress_bc1(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bc1_size(width, height));
+    for block_y in (0..height).step_by(4) {
+        for block_x in (0..width).step_by(4) {
+            // Blocks over the edge repeat the last row and column
+            let mut block = [[0u8; 4]; 16];
+            for (i, pixel) in block.iter_mut().enumerate() {
+                let x = (block_x + i as u32 % 4).min(width - 1);
+                let y = (block_y + i as u32 / 4).min(height - 1);
+                let offset = (y * width + x) as usize * 4;
+                pixel.copy_from_slice(&rgba[offset..offset + 4]);
+            }
+            out.extend_from_slice(&compress_block(&block));
+        }
+    }
+    out
+}
+
+fn compress_block(block: &[[u8; 4]; 16]) -> [u8; BC1_BLOCK_BYTES] {
+    let transparent = block.map(|pixel| pixel[3] < ALPHA_THRESHOLD);
+    let opaque: Vec<[f32; 3]> = block
+        .iter()
+        .zip(transparent)
+        .filter(|(_, transparent)| !transparent)
+        .map(|(pixel, _)| [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32])
+        .collect();
+    let has_alpha = opaque.len() < 16;
+
+    let (mut color0, mut color1) = if opaque.is_empty() {
+        (0, 0)
+    } else {
+        let (low, high) = end_colors(&opaque);
+        (to_rgb565(high), to_rgb565(low))
+    };
+    // Four color mode needs color0 > color1, three color mode (with transparency) the opposite
+    if (has_alpha && color0 > color1) || (!has_alpha && color0 < color1) {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let (c0, c1) = (from_rgb565(color0), from_rgb565(color1));
+    let palette: Vec<[f32; 3]> = if color0 > color1 {
+        vec![c0, c1, mix(c0, c1, 1.0 / 3.0), mix(c0, c1, 2.0 / 3.0)]
+    } else {
+        // With equal end colors this is also the mode every index picks the same color in
+        vec![c0, c1, mix(c0, c1, 0.5)]
+    };
+
+    let mut indices = 0u32;
+    for (i, pixel) in block.iter().enumerate() {
+        let index = if transparent[i] {
+            3
+        } else {
+            let color = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+            (0..palette.len())
+                .min_by(|&a, &b| distance(color, palette[a]).total_cmp(&distance(color, palette[b])))
+                .unwrap_or(0) as u32
+        };
+        indices |= index << (i * 2);
+    }
+
+    let mut bytes = [0; BC1_BLOCK_BYTES];
+    bytes[0..2].copy_from_slice(&color0.to_le_bytes());
+    bytes[2..4].copy_from_slice(&color1.to_le_bytes());
+    bytes[4..8].copy_from_slice(&indices.to_le_bytes());
+    bytes
+}
+
+// End colors of the line through the block's colors: their principal axis through the mean,
+// found by power iteration on the covariance, out to the furthest colors along it and inset
+// a little because the ends are rarely used
+fn end_colors(colors: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut mean = [0.0; 3];
+    for color in colors {
+        for channel in 0..3 {
+            mean[channel] += color[channel] / colors.len() as f32;
+        }
+    }
+    let mut covariance = [[0.0f32; 3]; 3];
+    for color in colors {
+        let offset = [0, 1, 2].map(|channel| color[channel] - mean[channel]);
+        for (row, values) in covariance.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                *value += offset[row] * offset[column];
+            }
+        }
+    }
+
+    // Starting from how the channel varying the most goes with the others
+    let widest = (0..3).max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b])).unwrap_or(0);
+    let mut axis = [0.0; 3];
+    axis[widest] = 1.0;
+    for _ in 0..8 {
+        let next = covariance.map(|row| (0..3).map(|channel| row[channel] * axis[channel]).sum::<f32>());
+        let length = next.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            break; // All colors the same, any axis will do
+        }
+        axis = next.map(|value| value / length);
+    }
+
+    let along = |color: &[f32; 3]| (0..3).map(|channel| (color[channel] - mean[channel]) * axis[channel]).sum::<f32>();
+    let (low, high) = colors.iter().map(along).fold((f32::MAX, f32::MIN), |(low, high), t| (low.min(t), high.max(t)));
+    let inset = (high - low) / 16.0;
+    let point = |t: f32| [0, 1, 2].map(|channel| mean[channel] + axis[channel] * t);
+    (point(low + inset), point(high - inset))
+}
+
+fn to_rgb565(color: [f32; 3]) -> u16 {
+    let quantize = |value: f32, max: f32| (value.clamp(0.0, 255.0) / 255.0 * max).round() as u16;
+    (quantize(color[0], 31.0) << 11) | (quantize(color[1], 63.0) << 5) | quantize(color[2], 31.0)
+}
+
+fn from_rgb565(color: u16) -> [f32; 3] {
+    [
+        ((color >> 11) & 31) as f32 * 255.0 / 31.0,
+        ((color >> 5) & 63) as f32 * 255.0 / 63.0,
+        (color & 31) as f32 * 255.0 / 31.0,
+    ]
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|channel| a[channel] + (b[channel] - a[channel]) * t)
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|channel| (a[channel] - b[channel]).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::Image;
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+    use image::{Rgba, RgbaImage};
+    use crate::osm::atlas::TILE_LAYER_SIZE;
+    use crate::osm::heatmap::Heatmap;
+    use crate::osm::{composite_layers, TileId};
+
+    // Decode a BC1 block back to RGBA8, alpha 0 for transparent pixels
+    fn decode_block(bytes: &[u8]) -> [[u8; 4]; 16] {
+        let color0 = u16::from_le_bytes([bytes[0], bytes[1]]);
+        let color1 = u16::from_le_bytes([bytes[2], bytes[3]]);
+        let indices = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        let (c0, c1) = (from_rgb565(color0), from_rgb565(color1));
+        let palette = if color0 > color1 {
+            [c0, c1, mix(c0, c1, 1.0 / 3.0), mix(c0, c1, 2.0 / 3.0)]
+        } else {
+            [c0, c1, mix(c0, c1, 0.5), [0.0; 3]]
+        };
+
+        std::array::from_fn(|i| {
+            let index = (indices >> (i * 2)) & 3;
+            let color = palette[index as usize].map(|value| value.round() as u8);
+            let alpha = if color0 <= color1 && index == 3 { 0 } else { 255 };
+            [color[0], color[1], color[2], alpha]
+        })
+    }
+
+    #[test]
+    fn solid_blocks_keep_their_color() {
+        let rgba = [200, 120, 40, 255].repeat(16);
+        let compressed = compress_bc1(&rgba, 4, 4);
+        assert_eq!(compressed.len(), BC1_BLOCK_BYTES);
+
+        for pixel in decode_block(&compressed) {
+            for channel in 0..3 {
+                assert!((pixel[channel] as i32 - rgba[channel] as i32).abs() <= 4, "{:?}", pixel);
+            }
+            assert_eq!(pixel[3], 255);
+        }
+    }
+
+    #[test]
+    fn gradients_stay_close_and_transparency_is_kept() {
+        // A horizontal gradient with the last column transparent
+        let rgba: Vec<u8> = (0..16)
+            .flat_map(|i| {
+                let x = i % 4;
+                let value = 60 + x as u8 * 40;
+                [value, 255 - value, 90, if x == 3 { 0 } else { 255 }]
+            })
+            .collect();
+        let decoded = decode_block(&compress_bc1(&rgba, 4, 4));
+
+        for (i, pixel) in decoded.iter().enumerate() {
+            let original = &rgba[i * 4..i * 4 + 4];
+            if original[3] == 0 {
+                assert_eq!(pixel[3], 0);
+                continue;
+            }
+            assert_eq!(pixel[3], 255);
+            for channel in 0..3 {
+                assert!((pixel[channel] as i32 - original[channel] as i32).abs() <= 24, "{:?} != {:?}", pixel, original);
+            }
+        }
+    }
+
+    // Decode a whole compressed image back to RGBA8 pixels
+    fn decode_image(compressed: &[u8], width: u32, height: u32) -> Vec<[u8; 4]> {
+        let blocks_wide = width.div_ceil(4);
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let block = ((y / 4) * blocks_wide + x / 4) as usize * BC1_BLOCK_BYTES;
+                decode_block(&compressed[block..block + BC1_BLOCK_BYTES])[(y % 4 * 4 + x % 4) as usize]
+            })
+            .collect()
+    }
+
+    // Root mean square error over the color channels of the pixels kept opaque
+    fn rmse(original: &RgbaImage, decoded: &[[u8; 4]]) -> f64 {
+        let (mut sum, mut count) = (0.0, 0);
+        for (pixel, decoded) in original.pixels().zip(decoded).filter(|(_, decoded)| decoded[3] == 255) {
+            for channel in 0..3 {
+                sum += (pixel[channel] as f64 - decoded[channel] as f64).powi(2);
+                count += 1;
+            }
+        }
+        (sum / count as f64).sqrt()
+    }
+
+    // A street map tile the way OSM's style draws one: land, a park and water under buildings,
+    // and cased roads with antialiased edges
+    fn street_map_tile() -> RgbaImage {
+        let segment_distance = |p: [f32; 2], a: [f32; 2], b: [f32; 2]| {
+            let (ab, ap) = ([b[0] - a[0], b[1] - a[1]], [p[0] - a[0], p[1] - a[1]]);
+            let t = ((ap[0] * ab[0] + ap[1] * ab[1]) / (ab[0] * ab[0] + ab[1] * ab[1])).clamp(0.0, 1.0);
+            (ap[0] - ab[0] * t).hypot(ap[1] - ab[1] * t)
+        };
+        let blend = |under: [f32; 3], over: [f32; 3], coverage: f32| {
+            std::array::from_fn::<f32, 3, _>(|i| under[i] + (over[i] - under[i]) * coverage.clamp(0.0, 1.0))
+        };
+        // (from, to, half width, casing color, fill color)
+        let roads = [
+            ([0.0, 40.0], [256.0, 120.0], 5.0, [200.0, 160.0, 110.0], [252.0, 214.0, 164.0]),
+            ([90.0, 0.0], [140.0, 256.0], 3.5, [187.0, 187.0, 187.0], [255.0, 255.0, 255.0]),
+            ([0.0, 200.0], [256.0, 180.0], 3.5, [187.0, 187.0, 187.0], [255.0, 255.0, 255.0]),
+        ];
+
+        RgbaImage::from_fn(TILE_LAYER_SIZE, TILE_LAYER_SIZE, |x, y| {
+            let p = [x as f32 + 0.5, y as f32 + 0.5];
+            let mut color = [242.0, 239.0, 233.0];
+            color = blend(color, [200.0, 250.0, 204.0], 150.0 - p[0] + p[1] * 0.5 - 100.0);
+            color = blend(color, [170.0, 211.0, 223.0], 60.0 - (p[0] - 210.0).hypot(p[1] - 230.0));
+            if (x % 24 < 16) && (y % 20 < 12) && x > 150 && y < 170 {
+                let edge = x % 24 == 0 || x % 24 == 15 || y % 20 == 0 || y % 20 == 11;
+                color = if edge { [196.0, 182.0, 171.0] } else { [217.0, 208.0, 201.0] };
+            }
+            for (from, to, half_width, casing, fill) in roads {
+                let distance = segment_distance(p, from, to);
+                color = blend(color, casing, half_width + 1.5 - distance);
+                color = blend(color, fill, half_width + 0.5 - distance);
+            }
+            Rgba([color[0] as u8, color[1] as u8, color[2] as u8, 255])
+        })
+    }
+
+    #[test]
+    fn map_tiles_stay_within_the_error_bound() {
+        let street_map = street_map_tile();
+        let compressed = compress_bc1(&street_map, TILE_LAYER_SIZE, TILE_LAYER_SIZE);
+        let decoded = decode_image(&compressed, TILE_LAYER_SIZE, TILE_LAYER_SIZE);
+        assert!(decoded.iter().all(|pixel| pixel[3] == 255));
+        let error = rmse(&street_map, &decoded);
+        assert!(error < 4.5, "RMSE {}", error);
+
+        // A heatmap over the map the way the atlas gets it, composited onto the opaque map first
+        // so its partial alpha isn't cut to BC1's 1 bit
+        let heatmap = Heatmap::new([(53.2194, 6.5665, 3.0), (53.2250, 6.5560, 1.0), (53.2120, 6.5760, 2.0)]);
+        let image = |rgba: RgbaImage| {
+            let size = Extent3d { width: TILE_LAYER_SIZE, height: TILE_LAYER_SIZE, depth_or_array_layers: 1 };
+            Image::new(size, TextureDimension::D2, rgba.into_raw(), TextureFormat::Rgba8UnormSrgb, RenderAssetUsages::MAIN_WORLD)
+        };
+        let (map, heat) = (image(street_map), image(heatmap.render(TileId::new(4245, 2660, 13))));
+        let composited = composite_layers(&[(&map, 1.0), (&heat, 0.8)]);
+        let composited = RgbaImage::from_raw(TILE_LAYER_SIZE, TILE_LAYER_SIZE, composited.data).unwrap();
+        let decoded = decode_image(&compress_bc1(&composited, TILE_LAYER_SIZE, TILE_LAYER_SIZE), TILE_LAYER_SIZE, TILE_LAYER_SIZE);
+        let error = rmse(&composited, &decoded);
+        assert!(error < 5.0, "RMSE {}", error);
+    }
+
+    #[test]
+    fn size_is_an_eighth_of_rgba() {
+        assert_eq!(bc1_size(256, 256), 256 * 256 * 4 / 8);
+        assert_eq!(compress_bc1(&[0; 8 * 4 * 4], 8, 4).len(), 2 * BC1_BLOCK_BYTES);
+    }
+}
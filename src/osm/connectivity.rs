@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use parking_lot::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Client;
+
+/// Whether the tile servers can be reached, natively tiles come from the disk cache only while
+/// they can't. The browser never goes offline here, its HTTP cache takes care of that
+#[derive(Debug, Default)]
+pub struct NetworkStatus {
+    // URL of the request that couldn't connect, probed until it answers; None while online
+    probe_url: Mutex<Option<String>>,
+}
+
+/// Network status shared by the loader tasks of all tile sources, like the throttle
+pub type SharedNetworkStatus = Arc<NetworkStatus>;
+
+impl NetworkStatus {
+    pub fn is_offline(&self) -> bool {
+        self.probe_url.lock().is_some()
+    }
+
+    /// Note that a request to `url` couldn't connect: the loader stays offline until it answers
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn record_unreachable(&self, url: &str) {
+        self.probe_url.lock().get_or_insert_with(|| url.to_string());
+    }
+
+    /// Note that a tile server answered, so the network is back
+    pub fn record_reachable(&self) {
+        self.probe_url.lock().take();
+    }
+
+    /// Request the URL that couldn't connect again, bypassing the throttle
+    /// Any answer, even an error status, means the network is back
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn probe(&self, client: &Client) {
+        let Some(url) = self.probe_url.lock().clone() else {
+            return;
+        };
+        if client.head(&url).send().await.is_ok() {
+            self.record_reachable();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_until_a_server_answers() {
+        let network = NetworkStatus::default();
+        network.record_unreachable("https://a.tile.example.com/0/0/0.png");
+        network.record_unreachable("https://b.tile.example.com/0/0/0.png");
+        assert!(network.is_offline());
+        assert_eq!(network.probe_url.lock().as_deref(), Some("https://a.tile.example.com/0/0/0.png"));
+
+        network.record_reachable();
+        assert!(!network.is_offline());
+    }
+}
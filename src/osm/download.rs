@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use bevy::utils::Instant;
+use std::ops::Range;
+use std::time::Duration;
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, RANGE, RETRY_AFTER};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use crate::osm::connectivity::SharedNetworkStatus;
+use crate::osm::download_stats::{record_bytes, record_request, record_response};
+use crate::osm::http_cache::CacheValidators;
+use crate::osm::source::TileSource;
+use crate::osm::tile_error::TileError;
+use crate::osm::throttle::{url_host, RequestOutcome, SharedThrottle};
+use crate::osm::tile::{OSMTile, TileId};
+use crate::resources::runtime::sleep;
+
+/// What a tile server answered to a tile request
+pub enum TileResponse {
+    NotModified,                   // The cached tile the validators came from is still current
+    Image(Bytes, CacheValidators), // A new image, with the validators to check it with later
+}
+
+/// Request a tile from its server, through the host's throttle
+/// With validators the request is conditional, answered with NotModified if the tile didn't change
+/// Works the same natively and in the browser, where reqwest uses fetch
+pub async fn request_tile(
+    client: &Client,
+    throttle: &SharedThrottle,
+    network: &SharedNetworkStatus,
+    url: &str,
+    validators: Option<&CacheValidators>,
+) -> Result<TileResponse, anyhow::Error> {
+    info!("Requesting OSM tile URL: {}", url);
+
+    // An expired tile is only downloaded again if the server has a newer one
+    let mut request = client.get(url);
+    if let Some(validators) = validators {
+        request = validators.apply(request);
+    }
+
+    let response = send_throttled(throttle, network, url, request).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED && validators.is_some() {
+        return Ok(TileResponse::NotModified);
+    }
+
+    let status = response.status();
+    if !status.is_success() {
+        error!("Failed to load tile {} - HTTP status: {}", url, status);
+        return Err(TileError::of_status(status).unwrap_or(TileError::Http).into());
+    }
+
+    let validators = CacheValidators::from_headers(response.headers());
+    let bytes = response.bytes().await?;
+    record_bytes(bytes.len());
+    info!("Received {} bytes for tile {}", bytes.len(), url);
+
+    // Error pages served with a success status aren't tiles
+    image::guess_format(&bytes)?;
+
+    Ok(TileResponse::Image(bytes, validators))
+}
+
+/// Request the bytes of a file in a range, through the host's throttle
+/// Servers that ignore the range send the whole file, which is cut to the range
+pub async fn request_range(
+    client: &Client,
+    throttle: &SharedThrottle,
+    network: &SharedNetworkStatus,
+    url: &str,
+    range: Range<u64>,
+) -> Result<Bytes, anyhow::Error> {
+    let request = client.get(url).header(RANGE, format!("bytes={}-{}", range.start, range.end.saturating_sub(1)));
+    let response = send_throttled(throttle, network, url, request).await?;
+    let status = response.status();
+    if !status.is_success() {
+        error!("Failed to load bytes {}-{} of {} - HTTP status: {}", range.start, range.end, url, status);
+        return Err(TileError::of_status(status).unwrap_or(TileError::Http).into());
+    }
+
+    let bytes = response.bytes().await?;
+    record_bytes(bytes.len());
+    if status == StatusCode::PARTIAL_CONTENT {
+        return Ok(bytes);
+    }
+    let end = (range.end as usize).min(bytes.len());
+    Ok(bytes.slice((range.start as usize).min(end)..end))
+}
+
+/// Send a tile request once the host's throttle has a slot for it, and record how the server
+/// answered: a server that keeps failing gets no requests for a while
+/// The request counts in the download diagnostics, whose latency starts after the wait for
+/// the throttle. Natively a request that can't connect at all takes the loader offline, see connectivity
+pub async fn send_throttled(
+    throttle: &SharedThrottle,
+    network: &SharedNetworkStatus,
+    url: &str,
+    request: RequestBuilder,
+) -> Result<Response, anyhow::Error> {
+    send(throttle, Some(network), url, request).await
+}
+
+/// Send a request that isn't for tiles, like an Overpass query, through the host's throttle
+/// It counts in neither the download diagnostics nor the connectivity of the tile loader,
+/// so a query server being down doesn't take the tiles offline
+pub async fn send_query_throttled(throttle: &SharedThrottle, url: &str, request: RequestBuilder) -> Result<Response, anyhow::Error> {
+    send(throttle, None, url, request).await
+}
+
+// Tile requests come with the network status to keep up to date
+async fn send(
+    throttle: &SharedThrottle,
+    network: Option<&SharedNetworkStatus>,
+    url: &str,
+    request: RequestBuilder,
+) -> Result<Response, anyhow::Error> {
+    let host = url_host(url);
+    let wait = throttle.lock().acquire(host, Instant::now());
+    match wait {
+        Some(wait) if !wait.is_zero() => sleep(wait).await,
+        Some(_) => {}
+        None => return Err(anyhow::anyhow!("Requests to {} are paused after repeated errors", host)),
+    }
+
+    let sent = Instant::now();
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            throttle.lock().record(host, RequestOutcome::Failed, Instant::now());
+            if network.is_some() {
+                record_request(host, true);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(network) = network.filter(|_| e.is_connect()) {
+                network.record_unreachable(url);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let outcome = match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => RequestOutcome::RateLimited(retry_after(response.headers())),
+        StatusCode::FORBIDDEN => RequestOutcome::Forbidden,
+        status if status.is_server_error() => RequestOutcome::Failed,
+        _ => RequestOutcome::Success,
+    };
+    throttle.lock().record(host, outcome, Instant::now());
+    if let Some(network) = network {
+        record_request(host, outcome != RequestOutcome::Success);
+        network.record_reachable();
+        record_response(sent.elapsed());
+    }
+    Ok(response)
+}
+
+// Delay a rate limited server asks for; only the seconds form of Retry-After is understood
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Tiles downloaded from a tile server, {z}/{x}/{y} in the URL template are replaced with the
+/// tile address. Natively CachedTileSource keeps them on disk; in the browser its HTTP cache does
+pub struct HttpTileSource {
+    pub url: String,
+    pub network: SharedNetworkStatus,
+    client: Client,
+    throttle: SharedThrottle,
+}
+
+impl HttpTileSource {
+    pub fn new(url: &str, client: &Client, throttle: &SharedThrottle, network: &SharedNetworkStatus) -> Self {
+        Self { url: url.to_string(), network: network.clone(), client: client.clone(), throttle: throttle.clone() }
+    }
+
+    /// Request a tile, conditionally with validators, see request_tile
+    pub async fn request(&self, id: TileId, validators: Option<&CacheValidators>) -> Result<TileResponse, anyhow::Error> {
+        let url = OSMTile::new(id.x, id.y, id.z).get_url(&self.url);
+        request_tile(&self.client, &self.throttle, &self.network, &url, validators).await
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl TileSource for HttpTileSource {
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        match self.request(id, None).await? {
+            TileResponse::Image(bytes, _) => Ok(bytes),
+            TileResponse::NotModified => Err(anyhow::anyhow!("Unexpected 304 for an unconditional request")),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::osm::tile_error::TileError;
+
+// Totals of every download since the start, added to by the loader tasks of all tile sources
+static RESPONSES: AtomicU64 = AtomicU64::new(0);
+static LATENCY_MICROS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static HOSTS: Mutex<BTreeMap<String, HostTotals>> = Mutex::new(BTreeMap::new());
+static ERRORS: Mutex<BTreeMap<TileError, u64>> = Mutex::new(BTreeMap::new());
+
+/// Downloads from tile servers since the start
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DownloadTotals {
+    pub responses: u64,     // Responses received, errors included
+    pub latency: Duration,  // Summed time from sending a request to its response
+    pub bytes: u64,         // Bytes of the response bodies
+}
+
+impl DownloadTotals {
+    /// Mean time servers took to respond, zero before the first response
+    pub fn average_latency(&self) -> Duration {
+        if self.responses == 0 {
+            return Duration::ZERO;
+        }
+        self.latency / self.responses as u32
+    }
+}
+
+/// Requests sent to one tile server since the start
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HostTotals {
+    pub requests: u64,
+    pub errors: u64, // Requests that got no answer, a server error or a rate limit
+}
+
+impl HostTotals {
+    /// Share of the requests that failed, zero before the first request
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+}
+
+/// Count a request to `host`, and whether it failed
+pub fn record_request(host: &str, failed: bool) {
+    let mut hosts = HOSTS.lock().unwrap_or_else(|e| e.into_inner());
+    let totals = hosts.entry(host.to_string()).or_default();
+    totals.requests += 1;
+    if failed {
+        totals.errors += 1;
+    }
+}
+
+/// Count a response that arrived after `latency`
+pub fn record_response(latency: Duration) {
+    RESPONSES.fetch_add(1, Ordering::Relaxed);
+    LATENCY_MICROS.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Count the bytes of a downloaded body
+pub fn record_bytes(bytes: usize) {
+    BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+pub fn download_totals() -> DownloadTotals {
+    DownloadTotals {
+        responses: RESPONSES.load(Ordering::Relaxed),
+        latency: Duration::from_micros(LATENCY_MICROS.load(Ordering::Relaxed)),
+        bytes: BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Count a tile that failed to load, of any source
+pub fn record_error(error: TileError) {
+    *ERRORS.lock().unwrap_or_else(|e| e.into_inner()).entry(error).or_default() += 1;
+}
+
+/// Failed tile loads per kind of error since the start
+pub fn error_counts() -> BTreeMap<TileError, u64> {
+    ERRORS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Requests per tile server host since the start
+pub fn host_totals() -> BTreeMap<String, HostTotals> {
+    HOSTS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_is_averaged_over_the_responses() {
+        let totals = DownloadTotals { responses: 4, latency: Duration::from_millis(200), bytes: 0 };
+        assert_eq!(totals.average_latency(), Duration::from_millis(50));
+        assert_eq!(DownloadTotals::default().average_latency(), Duration::ZERO);
+    }
+
+    #[test]
+    fn error_rate_is_the_share_of_failed_requests() {
+        assert_eq!(HostTotals { requests: 8, errors: 2 }.error_rate(), 0.25);
+        assert_eq!(HostTotals::default().error_rate(), 0.0);
+    }
+}
@@ -0,0 +1,275 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use bytes::Bytes;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use parking_lot::Mutex;
+use serde_json::Value;
+use crate::osm::atlas::TILE_LAYER_SIZE;
+use crate::osm::source::TileSource;
+use crate::osm::tile::TileId;
+use crate::utils::geo::clamp_latitude;
+
+/// URL scheme of heatmap layers, followed by the path of the data file
+pub const HEATMAP_SCHEME: &str = "heatmap://";
+
+// Radius in tile pixels a point spreads its weight over, the same at every zoom level
+const RADIUS_PX: f64 = 24.0;
+
+// Colors from no weight to the heaviest point, spaced evenly; the alpha rises with the weight
+// so light areas barely tint the map below
+const COLOR_RAMP: [[u8; 4]; 6] = [
+    [0, 0, 255, 0],
+    [0, 0, 255, 140],
+    [0, 255, 255, 180],
+    [0, 255, 0, 210],
+    [255, 255, 0, 235],
+    [255, 0, 0, 255],
+];
+
+// Column names the weight is read from, in CSV headers and GeoJSON properties
+const WEIGHT_NAMES: [&str; 4] = ["weight", "value", "count", "intensity"];
+
+/// A weighted point of a heatmap, in Web Mercator map coordinates from 0 to 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct HeatPoint {
+    x: f64,
+    y: f64,
+    weight: f64,
+}
+
+/// Weighted points, e.g. traffic counts or air quality readings, drawn as a heatmap
+/// The weights are scaled by the heaviest point, so a heatmap's colors mean the same in every tile
+#[derive(Debug, Default)]
+pub struct Heatmap {
+    points: Vec<HeatPoint>,
+    max_weight: f64,
+}
+
+impl Heatmap {
+    /// Heatmap of (lat, lon, weight) points; points without a positive weight are left out
+    pub fn new(points: impl IntoIterator<Item = (f64, f64, f64)>) -> Self {
+        let points: Vec<HeatPoint> = points
+            .into_iter()
+            .filter(|&(lat, lon, weight)| lat.is_finite() && lon.is_finite() && weight > 0.0)
+            .map(|(lat, lon, weight)| {
+                let (x, y) = mercator(lat, lon);
+                HeatPoint { x, y, weight }
+            })
+            .collect();
+        let max_weight = points.iter().map(|point| point.weight).fold(0.0, f64::max);
+        Self { points, max_weight }
+    }
+
+    /// Read the points of a CSV or GeoJSON file, told apart by the extension
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let text = fs::read_to_string(path)?;
+        let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let points = if is_csv { parse_csv_points(&text)? } else { parse_geojson_points(&text)? };
+        if points.is_empty() {
+            bail!("No weighted points in {}", path.display());
+        }
+        Ok(Self::new(points))
+    }
+
+    /// Rasterize the points around a tile into a heatmap image, transparent where there is no weight
+    pub fn render(&self, id: TileId) -> RgbaImage {
+        let size = TILE_LAYER_SIZE as usize;
+        let scale = (1u64 << id.z) as f64 * TILE_LAYER_SIZE as f64;
+        let (left, top) = (id.x as f64 * TILE_LAYER_SIZE as f64, id.y as f64 * TILE_LAYER_SIZE as f64);
+
+        let mut heat = vec![0.0_f64; size * size];
+        for point in &self.points {
+            let (px, py) = (point.x * scale - left, point.y * scale - top);
+            if px < -RADIUS_PX || py < -RADIUS_PX || px > size as f64 + RADIUS_PX || py > size as f64 + RADIUS_PX {
+                continue;
+            }
+            let weight = point.weight / self.max_weight;
+            let (x0, x1) = ((px - RADIUS_PX).floor().max(0.0) as usize, ((px + RADIUS_PX).ceil() as usize).min(size));
+            let (y0, y1) = ((py - RADIUS_PX).floor().max(0.0) as usize, ((py + RADIUS_PX).ceil() as usize).min(size));
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    // Smooth falloff from the center of the pixel to the edge of the radius
+                    let (dx, dy) = (x as f64 + 0.5 - px, y as f64 + 0.5 - py);
+                    let falloff = 1.0 - (dx * dx + dy * dy) / (RADIUS_PX * RADIUS_PX);
+                    if falloff > 0.0 {
+                        heat[y * size + x] += weight * falloff * falloff;
+                    }
+                }
+            }
+        }
+
+        RgbaImage::from_fn(TILE_LAYER_SIZE, TILE_LAYER_SIZE, |x, y| {
+            color_ramp(heat[y as usize * size + x as usize])
+        })
+    }
+}
+
+/// Color of a heat value, from transparent at 0 to red at 1 and above
+pub fn color_ramp(value: f64) -> Rgba<u8> {
+    if value <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let position = value.min(1.0) * (COLOR_RAMP.len() - 1) as f64;
+    let index = (position.floor() as usize).min(COLOR_RAMP.len() - 2);
+    let t = position - index as f64;
+    let (from, to) = (COLOR_RAMP[index], COLOR_RAMP[index + 1]);
+    Rgba(std::array::from_fn(|channel| {
+        (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * t).round() as u8
+    }))
+}
+
+/// (lat, lon, weight) points of a CSV file with a header row
+/// The header names the columns: lat or latitude, lon, lng or longitude, and optionally a
+/// weight, value, count or intensity column; without one every point weighs 1
+/// Fields are split on commas only, quoted fields aren't supported
+pub fn parse_csv_points(text: &str) -> Result<Vec<(f64, f64, f64)>, anyhow::Error> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<String> = lines
+        .next()
+        .ok_or_else(|| anyhow!("Empty CSV file"))?
+        .split(',')
+        .map(|name| name.trim().trim_matches('"').to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+    let (Some(lat), Some(lon)) = (column(&["lat", "latitude"]), column(&["lon", "lng", "longitude"])) else {
+        bail!("The CSV header has no lat and lon columns");
+    };
+    let weight = column(&WEIGHT_NAMES);
+
+    let mut points = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let number = |index: usize| fields.get(index).and_then(|field| field.parse::<f64>().ok());
+        // Rows with missing or unreadable numbers are skipped, like gaps in a measurement series
+        let (Some(lat), Some(lon)) = (number(lat), number(lon)) else {
+            continue;
+        };
+        match weight {
+            Some(index) => points.extend(number(index).map(|weight| (lat, lon, weight))),
+            None => points.push((lat, lon, 1.0)),
+        }
+    }
+    Ok(points)
+}
+
+/// (lat, lon, weight) points of the Point features of a GeoJSON file
+/// The weight is the first of the weight, value, count or intensity properties that is a
+/// number, 1 without any
+pub fn parse_geojson_points(text: &str) -> Result<Vec<(f64, f64, f64)>, anyhow::Error> {
+    let json: Value = serde_json::from_str(text)?;
+    let features = json["features"].as_array().ok_or_else(|| anyhow!("Not a GeoJSON FeatureCollection"))?;
+
+    Ok(features
+        .iter()
+        .filter(|feature| feature["geometry"]["type"] == "Point")
+        .filter_map(|feature| {
+            let coordinates = &feature["geometry"]["coordinates"];
+            let (lon, lat) = (coordinates.get(0)?.as_f64()?, coordinates.get(1)?.as_f64()?);
+            let weight = WEIGHT_NAMES
+                .iter()
+                .find_map(|name| feature["properties"][name].as_f64())
+                .unwrap_or(1.0);
+            Some((lat, lon, weight))
+        })
+        .collect())
+}
+
+// Web Mercator position of a latitude/longitude, from 0 to 1 across the map
+// Not geo::lat_lon_to_world, which is relative to the render origin the main thread moves
+fn mercator(lat: f64, lon: f64) -> (f64, f64) {
+    let lat = clamp_latitude(lat).to_radians();
+    ((lon + 180.0) / 360.0, (1.0 - lat.tan().asinh() / PI) / 2.0)
+}
+
+/// A layer drawn from a file of weighted points: heatmap:///data/traffic.csv as a layer URL
+/// The file is read on the first tile, so a missing file fails its tiles like an unreachable
+/// server instead of the layer
+pub struct HeatmapTileSource {
+    path: PathBuf,
+    heatmap: Arc<Mutex<Option<Arc<Heatmap>>>>,
+}
+
+impl HeatmapTileSource {
+    /// The heatmap of a heatmap:// URL, None for other URLs
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.strip_prefix(HEATMAP_SCHEME)?;
+        Some(Self {
+            path: PathBuf::from(path),
+            heatmap: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+#[async_trait]
+impl TileSource for HeatmapTileSource {
+    // Encoded like a tile server's tiles, so the heatmap goes through the same decoding
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        // Reading the file and rasterizing block, so they run off the runtime's worker threads
+        let (path, heatmap) = (self.path.clone(), self.heatmap.clone());
+        tokio::task::spawn_blocking(move || {
+            let heatmap = {
+                let mut loaded = heatmap.lock();
+                match loaded.as_ref() {
+                    Some(heatmap) => heatmap.clone(),
+                    None => loaded.insert(Arc::new(Heatmap::load(&path)?)).clone(),
+                }
+            };
+            let mut png = Cursor::new(Vec::new());
+            DynamicImage::ImageRgba8(heatmap.render(id)).write_to(&mut png, ImageFormat::Png)?;
+            Ok(png.into_inner().into())
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_are_read_from_csv_and_geojson() {
+        let csv = "Latitude, Longitude, name, count\n53.2194,6.5665,Vismarkt,120\n53.2406,6.5349,Zernike,\n\n53.2,6.55,Station,80\n";
+        assert_eq!(parse_csv_points(csv).unwrap(), vec![(53.2194, 6.5665, 120.0), (53.2, 6.55, 80.0)]);
+        assert_eq!(parse_csv_points("lat,lng\n53.2,6.5\n").unwrap(), vec![(53.2, 6.5, 1.0)]);
+        assert!(parse_csv_points("name,count\nVismarkt,120\n").is_err());
+
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [6.5665, 53.2194]}, "properties": {"value": 42.5}},
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [6.5349, 53.2406]}, "properties": {}},
+            {"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[6.5, 53.2], [6.6, 53.3]]}, "properties": {}}
+        ]}"#;
+        assert_eq!(parse_geojson_points(geojson).unwrap(), vec![(53.2194, 6.5665, 42.5), (53.2406, 6.5349, 1.0)]);
+    }
+
+    #[test]
+    fn heat_peaks_at_the_heaviest_point() {
+        // Two points in tile 13/4245/2660, the heavier one 32 pixels west of the other
+        let heatmap = Heatmap::new([(53.2194, 6.5665, 10.0), (53.2194, 6.5720, 2.0), (53.2, 6.5, -1.0)]);
+        assert_eq!(heatmap.points.len(), 2);
+
+        let image = heatmap.render(TileId::new(4245, 2660, 13));
+        let (x, y) = {
+            let (x, y) = mercator(53.2194, 6.5665);
+            let scale = (1u64 << 13) as f64 * TILE_LAYER_SIZE as f64;
+            ((x * scale) as u32 - 4245 * TILE_LAYER_SIZE, (y * scale) as u32 - 2660 * TILE_LAYER_SIZE)
+        };
+        assert_eq!(image.get_pixel(x, y)[0], 255, "the heaviest point is red");
+        assert_eq!(image.get_pixel(0, 0)[3], 0, "no heat far from the points");
+
+        // Tiles without points nearby are transparent
+        assert!(heatmap.render(TileId::new(0, 0, 13)).pixels().all(|pixel| pixel[3] == 0));
+    }
+
+    #[test]
+    fn ramp_goes_from_transparent_to_red() {
+        assert_eq!(color_ramp(0.0), Rgba([0, 0, 0, 0]));
+        assert_eq!(color_ramp(1.0), Rgba([255, 0, 0, 255]));
+        assert_eq!(color_ramp(7.0), Rgba([255, 0, 0, 255]));
+        assert_eq!(color_ramp(0.6), Rgba([0, 255, 0, 210]));
+    }
+}
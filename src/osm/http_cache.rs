@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use bevy::utils::SystemTime;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use vibe_world_core::cache_policy::tile_expired;
+
+/// HTTP cache validators of a cached tile, kept in a file next to the tile image
+/// They let an expired tile be checked with a conditional GET instead of downloaded again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub checked: u64, // Seconds since the unix epoch of the last 304 from the server, 0 if never
+}
+
+impl CacheValidators {
+    /// Validators sent with a tile response
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            checked: 0,
+        }
+    }
+
+    /// Read the validators stored for a cached tile image, defaults if there are none
+    pub fn read(tile_path: &Path) -> Self {
+        fs::read_to_string(validators_path(tile_path))
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    /// Store the validators for a cached tile image, or drop stale ones when there are none
+    pub fn write(&self, tile_path: &Path) -> std::io::Result<()> {
+        let path = validators_path(tile_path);
+        if self.etag.is_none() && self.last_modified.is_none() && self.checked == 0 {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        fs::write(path, self.format())
+    }
+
+    /// Remove the validators of a cached tile image that is removed itself
+    pub fn remove(tile_path: &Path) {
+        let _ = fs::remove_file(validators_path(tile_path));
+    }
+
+    // One validator per line: <name> <value>, the value runs to the end of the line
+    fn parse(contents: &str) -> Self {
+        let mut validators = Self::default();
+        for line in contents.lines() {
+            let Some((name, value)) = line.split_once(' ') else {
+                continue;
+            };
+            match name {
+                "etag" => validators.etag = Some(value.to_string()),
+                "last-modified" => validators.last_modified = Some(value.to_string()),
+                "checked" => validators.checked = value.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        validators
+    }
+
+    fn format(&self) -> String {
+        let mut contents = String::new();
+        if let Some(etag) = &self.etag {
+            contents.push_str(&format!("etag {}\n", etag));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            contents.push_str(&format!("last-modified {}\n", last_modified));
+        }
+        if self.checked > 0 {
+            contents.push_str(&format!("checked {}\n", self.checked));
+        }
+        contents
+    }
+
+    /// Add the conditional headers to a request
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+
+    /// Whether a tile downloaded at `downloaded` (unix seconds) is due for a check with the server
+    pub fn expired(&self, downloaded: u64, now: u64, ttl: Duration) -> bool {
+        tile_expired(downloaded, self.checked, now, ttl)
+    }
+}
+
+/// Seconds since the unix epoch when a cached file was last written, 0 if unknown
+pub fn file_modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Current time as seconds since the unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// Validators live next to the tile: 12.png -> 12.http
+fn validators_path(tile_path: &Path) -> PathBuf {
+    tile_path.with_extension("http")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validators_round_trip_through_their_file_format() {
+        let validators = CacheValidators {
+            etag: Some("\"5f3a-1c2b\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            checked: 1_700_000_000,
+        };
+        assert_eq!(CacheValidators::parse(&validators.format()), validators);
+        assert_eq!(CacheValidators::parse("garbage\nunknown value\n"), CacheValidators::default());
+    }
+}
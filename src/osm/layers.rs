@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use crate::osm::atlas::TILE_LAYER_SIZE;
+
+// Shows through where no layer covers the map, like a sheet of paper under transparent layers
+const PAPER: [u8; 3] = [255, 255, 255];
+
+/// A raster source stacked into the tile imagery
+/// Tiles are loaded and cached per layer, keyed by (layer id, x, y, zoom)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerSource {
+    pub id: u32,      // Stable across reordering, so cached imagery stays valid
+    pub url: String,  // Tile URL template
+    pub opacity: f32, // 0 = hidden, 1 = covers the layers below
+}
+
+/// Blend the images of a tile's layers, bottom first, into the image drawn for the tile
+/// Images are RGBA8 of an atlas layer's size, others are skipped; the layer's opacity scales
+/// the image's own alpha. Without any layers the tile is blank paper
+pub fn composite_layers(layers: &[(&Image, f32)]) -> Image {
+    let pixels = (TILE_LAYER_SIZE * TILE_LAYER_SIZE) as usize;
+    let mut data: Vec<u8> = PAPER.iter().copied().chain([255]).cycle().take(pixels * 4).collect();
+    for (image, opacity) in layers {
+        if image.data.len() != data.len() {
+            continue;
+        }
+        let opacity = (opacity.clamp(0.0, 1.0) * 255.0).round() as u32;
+        for (out, pixel) in data.chunks_exact_mut(4).zip(image.data.chunks_exact(4)) {
+            // Alpha in 0..=255*255
+            let alpha = pixel[3] as u32 * opacity;
+            for channel in 0..3 {
+                let blended = out[channel] as u32 * (65_025 - alpha) + pixel[channel] as u32 * alpha;
+                out[channel] = ((blended + 32_512) / 65_025) as u8;
+            }
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: TILE_LAYER_SIZE,
+            height: TILE_LAYER_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(color: [u8; 4]) -> Image {
+        Image::new_fill(
+            Extent3d { width: TILE_LAYER_SIZE, height: TILE_LAYER_SIZE, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &color,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+
+    #[test]
+    fn opaque_top_layer_covers_the_ones_below() {
+        let (map, satellite) = (solid([200, 100, 50, 255]), solid([10, 20, 30, 255]));
+        let image = composite_layers(&[(&map, 1.0), (&satellite, 1.0)]);
+        assert_eq!(&image.data[..4], &[10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn opacity_and_alpha_blend_with_the_layers_below() {
+        let (map, hillshade) = (solid([200, 200, 200, 255]), solid([0, 0, 0, 255]));
+        let image = composite_layers(&[(&map, 1.0), (&hillshade, 0.5)]);
+        assert_eq!(&image.data[..4], &[100, 100, 100, 255]);
+
+        // A transparent overlay leaves the paper white showing
+        let clear = solid([0, 0, 0, 0]);
+        let image = composite_layers(&[(&clear, 1.0)]);
+        assert_eq!(&image.data[..4], &[255, 255, 255, 255]);
+        assert_eq!(composite_layers(&[]).data, image.data);
+    }
+}
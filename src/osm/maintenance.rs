@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::ImageEncoder;
+use vibe_world_core::cache_policy::MaintenancePolicy;
+use crate::osm::http_cache::{unix_now, CacheValidators};
+use crate::osm::tile::cache_dir;
+
+// File name of the disk index inside the cache directory
+const INDEX_FILE: &str = "index.txt";
+
+/// Index record for one cached tile
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+    pub size: u64,
+    pub modified: u64, // Seconds since the unix epoch
+    pub checksum: u32, // CRC32 of the file contents
+    pub recompressed: bool,
+}
+
+/// What happened to a cache entry during maintenance
+#[derive(Debug)]
+pub enum MaintenanceOutcome {
+    Kept(PathBuf, IndexEntry),
+    Recompressed(PathBuf, IndexEntry),
+    Pruned,
+    Corrupt(PathBuf),
+}
+
+/// Path of the index file
+pub fn index_path() -> PathBuf {
+    cache_dir().join(INDEX_FILE)
+}
+
+/// List all cached tile files, relative to the cache directory
+/// Validators left behind by tiles that are gone are removed on the way
+pub fn scan_cache() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![cache_dir().to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "png") {
+                if let Ok(relative) = path.strip_prefix(cache_dir()) {
+                    files.push(relative.to_path_buf());
+                }
+            } else if path.extension().is_some_and(|ext| ext == "http") && !path.with_extension("png").exists() {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
+    files
+}
+
+/// Read the disk index written by the previous maintenance pass
+pub fn read_index() -> HashMap<PathBuf, IndexEntry> {
+    let mut index = HashMap::new();
+    let Ok(file) = fs::File::open(index_path()) else {
+        return index;
+    };
+
+    // One entry per line: <path> <size> <modified> <checksum> <recompressed>
+    for line in io::BufReader::new(file).lines().map_while(Result::ok) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 5 {
+            continue;
+        }
+
+        let entry = (|| {
+            Some(IndexEntry {
+                size: fields[1].parse().ok()?,
+                modified: fields[2].parse().ok()?,
+                checksum: u32::from_str_radix(fields[3], 16).ok()?,
+                recompressed: fields[4] == "1",
+            })
+        })();
+
+        if let Some(entry) = entry {
+            index.insert(PathBuf::from(fields[0]), entry);
+        }
+    }
+
+    index
+}
+
+/// Write the disk index, replacing the previous one
+pub fn write_index(index: &HashMap<PathBuf, IndexEntry>) -> io::Result<()> {
+    // Write to a temporary file first so a crash never leaves a truncated index
+    let temp_path = index_path().with_extension("tmp");
+    let mut writer = BufWriter::new(fs::File::create(&temp_path)?);
+
+    for (path, entry) in index {
+        writeln!(
+            writer,
+            "{} {} {} {:08x} {}",
+            path.display(),
+            entry.size,
+            entry.modified,
+            entry.checksum,
+            if entry.recompressed { 1 } else { 0 }
+        )?;
+    }
+
+    writer.flush()?;
+    drop(writer);
+    fs::rename(temp_path, index_path())
+}
+
+/// Check, prune or recompress a single entry of the cache in `cache_dir`
+/// Blocking file IO - run this off the main thread
+pub fn maintain_entry(
+    cache_dir: &Path,
+    relative_path: &Path,
+    previous: Option<&IndexEntry>,
+    policy: &MaintenancePolicy,
+) -> MaintenanceOutcome {
+    let path = cache_dir.join(relative_path);
+    let relative_path = relative_path.to_path_buf();
+
+    let Ok(metadata) = fs::metadata(&path) else {
+        return MaintenanceOutcome::Pruned;
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let now = unix_now();
+
+    // Prune expired tiles so they get downloaded fresh next time
+    if policy.prunes(modified, CacheValidators::read(&path).checked, now) {
+        remove_tile(&path);
+        return MaintenanceOutcome::Pruned;
+    }
+
+    let Ok(bytes) = fs::read(&path) else {
+        return MaintenanceOutcome::Pruned;
+    };
+    let checksum = crc32fast::hash(&bytes);
+
+    // Verify the checksum if the file hasn't legitimately changed since the last pass
+    let unchanged = previous.is_some_and(|p| p.modified == modified && p.size == metadata.len());
+    if unchanged && previous.is_some_and(|p| p.checksum != checksum) {
+        remove_tile(&path);
+        return MaintenanceOutcome::Corrupt(relative_path);
+    }
+
+    let already_recompressed = unchanged && previous.is_some_and(|p| p.recompressed);
+    if policy.recompresses(modified, now) && !already_recompressed {
+        if let Some(entry) = recompress(&path, &bytes, modified) {
+            return MaintenanceOutcome::Recompressed(relative_path, entry);
+        }
+        // Tiles that don't decode are corrupt
+        remove_tile(&path);
+        return MaintenanceOutcome::Corrupt(relative_path);
+    }
+
+    MaintenanceOutcome::Kept(relative_path, IndexEntry {
+        size: metadata.len(),
+        modified,
+        checksum,
+        recompressed: already_recompressed,
+    })
+}
+
+// Remove a cached tile together with its validators
+fn remove_tile(path: &Path) {
+    let _ = fs::remove_file(path);
+    CacheValidators::remove(path);
+}
+
+// Re-encode a cached PNG with the best compression, keeping the original if that's smaller
+// The modification time is preserved so the tile still expires based on its download time
+fn recompress(path: &Path, bytes: &[u8], modified: u64) -> Option<IndexEntry> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let rgba = image.to_rgba8();
+
+    let mut encoded = Vec::new();
+    PngEncoder::new_with_quality(&mut encoded, CompressionType::Best, FilterType::Adaptive)
+        .write_image(&rgba, rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+        .ok()?;
+
+    let final_bytes = if encoded.len() < bytes.len() {
+        match replace_tile(path, &encoded, modified) {
+            Ok(_) => &encoded,
+            Err(e) => {
+                warn!("Failed to write recompressed tile {}: {}", path.display(), e);
+                bytes
+            }
+        }
+    } else {
+        bytes
+    };
+
+    Some(IndexEntry {
+        size: final_bytes.len() as u64,
+        modified,
+        checksum: crc32fast::hash(final_bytes),
+        recompressed: true,
+    })
+}
+
+// Replace a cached tile with new contents and modification time
+// Written to a temporary file next to it first, like write_index, so the tile loader reading
+// the tile at the same time or a crash never sees a truncated tile
+fn replace_tile(path: &Path, bytes: &[u8], modified: u64) -> io::Result<()> {
+    let temp_path = path.with_extension("png.tmp");
+    let written = fs::write(&temp_path, bytes)
+        .and_then(|_| fs::File::options().write(true).open(&temp_path)?.set_modified(UNIX_EPOCH + Duration::from_secs(modified)))
+        .and_then(|_| fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn policy() -> MaintenancePolicy {
+        MaintenancePolicy { max_age: 30 * DAY, recompress_after: 7 * DAY }
+    }
+
+    // A PNG encoded without compression, so recompressing it always pays off
+    fn uncompressed_tile() -> Vec<u8> {
+        let tile = image::RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8 * 4, y as u8 * 4, 128, 255]));
+        let mut bytes = Vec::new();
+        PngEncoder::new_with_quality(&mut bytes, CompressionType::Fast, FilterType::NoFilter)
+            .write_image(&tile, 64, 64, image::ExtendedColorType::Rgba8)
+            .unwrap();
+        bytes
+    }
+
+    // Write a tile into a test cache directory, downloaded `age` ago
+    fn write_tile(dir: &Path, name: &str, bytes: &[u8], age: Duration) -> (PathBuf, u64) {
+        let modified = SystemTime::now() - age;
+        fs::write(dir.join(name), bytes).unwrap();
+        fs::File::options().write(true).open(dir.join(name)).unwrap().set_modified(modified).unwrap();
+        (PathBuf::from(name), modified.duration_since(UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    #[test]
+    fn expired_and_corrupt_tiles_are_removed() {
+        let dir = std::env::temp_dir().join(format!("vibers-maintenance-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tile = uncompressed_tile();
+
+        let (expired, _) = write_tile(&dir, "expired.png", &tile, 31 * DAY);
+        assert!(matches!(maintain_entry(&dir, &expired, None, &policy()), MaintenanceOutcome::Pruned));
+        assert!(!dir.join(&expired).exists());
+
+        // Changed since the last pass without its size or modification time changing
+        let (corrupt, modified) = write_tile(&dir, "corrupt.png", &tile, DAY);
+        let previous = IndexEntry { size: tile.len() as u64, modified, checksum: 0xdead_beef, recompressed: false };
+        assert!(matches!(maintain_entry(&dir, &corrupt, Some(&previous), &policy()), MaintenanceOutcome::Corrupt(_)));
+        assert!(!dir.join(&corrupt).exists());
+
+        // Recompressing a tile that doesn't decode finds it corrupt too
+        let (garbled, _) = write_tile(&dir, "garbled.png", &tile[..tile.len() / 2], 8 * DAY);
+        assert!(matches!(maintain_entry(&dir, &garbled, None, &policy()), MaintenanceOutcome::Corrupt(_)));
+
+        let (fresh, _) = write_tile(&dir, "fresh.png", &tile, DAY);
+        let MaintenanceOutcome::Kept(_, entry) = maintain_entry(&dir, &fresh, None, &policy()) else {
+            panic!("A fresh tile is kept as it is");
+        };
+        assert_eq!((entry.checksum, entry.recompressed), (crc32fast::hash(&tile), false));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checked_tiles_are_kept_and_validators_go_with_removed_tiles() {
+        let dir = std::env::temp_dir().join(format!("vibers-validators-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tile = uncompressed_tile();
+        let checked_since = |age: Duration| CacheValidators {
+            etag: Some("\"5f3a\"".to_string()),
+            checked: (SystemTime::now() - age).duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            ..Default::default()
+        };
+
+        // Downloaded long ago, but the server confirmed it unchanged recently
+        let (confirmed, _) = write_tile(&dir, "confirmed.png", &tile, 31 * DAY);
+        checked_since(DAY).write(&dir.join(&confirmed)).unwrap();
+        assert!(!matches!(maintain_entry(&dir, &confirmed, None, &policy()), MaintenanceOutcome::Pruned));
+        assert!(dir.join(&confirmed).exists());
+
+        let (expired, _) = write_tile(&dir, "expired.png", &tile, 62 * DAY);
+        checked_since(31 * DAY).write(&dir.join(&expired)).unwrap();
+        assert!(matches!(maintain_entry(&dir, &expired, None, &policy()), MaintenanceOutcome::Pruned));
+        assert!(!dir.join(&expired).exists());
+        assert!(!dir.join("expired.http").exists());
+
+        let (corrupt, modified) = write_tile(&dir, "corrupt.png", &tile, DAY);
+        checked_since(DAY).write(&dir.join(&corrupt)).unwrap();
+        let previous = IndexEntry { size: tile.len() as u64, modified, checksum: 0xdead_beef, recompressed: false };
+        assert!(matches!(maintain_entry(&dir, &corrupt, Some(&previous), &policy()), MaintenanceOutcome::Corrupt(_)));
+        assert!(!dir.join("corrupt.http").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn old_tiles_are_recompressed_in_place() {
+        let dir = std::env::temp_dir().join(format!("vibers-recompress-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let tile = uncompressed_tile();
+        let (old, modified) = write_tile(&dir, "old.png", &tile, 8 * DAY);
+
+        let MaintenanceOutcome::Recompressed(_, entry) = maintain_entry(&dir, &old, None, &policy()) else {
+            panic!("An old tile is recompressed");
+        };
+        let bytes = fs::read(dir.join(&old)).unwrap();
+        assert!(bytes.len() < tile.len());
+        assert_eq!((entry.size, entry.modified, entry.checksum), (bytes.len() as u64, modified, crc32fast::hash(&bytes)));
+        assert_eq!(image::load_from_memory(&bytes).unwrap().to_rgba8(), image::load_from_memory(&tile).unwrap().to_rgba8());
+
+        // The download time is kept for expiry, and no temporary file is left behind
+        let written = fs::metadata(dir.join(&old)).unwrap().modified().unwrap();
+        assert_eq!(written.duration_since(UNIX_EPOCH).unwrap().as_secs(), modified);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Once recompressed it is left alone
+        let MaintenanceOutcome::Kept(_, entry) = maintain_entry(&dir, &old, Some(&entry), &policy()) else {
+            panic!("A recompressed tile is kept");
+        };
+        assert!(entry.recompressed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use crate::osm::source::TileSource;
+use crate::osm::tile::TileId;
+
+// URL scheme of local files, MBTiles files are recognized by their extension
+const FILE_SCHEME: &str = "file://";
+const MBTILES_EXTENSION: &str = ".mbtiles";
+
+/// Tiles read from an MBTiles file, a SQLite database of a whole tileset:
+/// file:///data/groningen.mbtiles as a layer URL
+/// The file is opened on the first tile, so a missing file fails its tiles like an
+/// unreachable server instead of the layer
+pub struct MbTilesSource {
+    path: PathBuf,
+    connection: Arc<Mutex<Option<Connection>>>,
+}
+
+impl MbTilesSource {
+    /// The MBTiles file of a file:// URL ending in .mbtiles, None for other URLs
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.strip_prefix(FILE_SCHEME)?;
+        path.ends_with(MBTILES_EXTENSION).then(|| Self {
+            path: PathBuf::from(path),
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+#[async_trait]
+impl TileSource for MbTilesSource {
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        // SQLite blocks, so the query runs off the runtime's worker threads
+        let (path, connection) = (self.path.clone(), self.connection.clone());
+        tokio::task::spawn_blocking(move || {
+            let mut opened = connection.lock();
+            let connection = match opened.take() {
+                Some(connection) => connection,
+                None => Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+            };
+            let tile = read_tile(&connection, id);
+            *opened = Some(connection);
+            tile?.ok_or_else(|| anyhow::anyhow!("No tile {}/{}/{} in {}", id.z, id.x, id.y, path.display()))
+        })
+        .await?
+    }
+}
+
+// Rows count from the south in MBTiles (the TMS scheme), from the north in tile addresses
+fn read_tile(connection: &Connection, id: TileId) -> Result<Option<Bytes>, rusqlite::Error> {
+    let row = (1u32 << id.z) - 1 - id.y;
+    connection
+        .query_row(
+            "SELECT tile_data FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+            (id.z, id.x, row),
+            |found| found.get::<_, Vec<u8>>(0),
+        )
+        .optional()
+        .map(|data| data.map(Bytes::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tiles_are_read_with_flipped_rows() {
+        let path = std::env::temp_dir().join(format!("vibers-mbtiles-{}.mbtiles", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let writer = Connection::open(&path).unwrap();
+        writer
+            .execute_batch("CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB)")
+            .unwrap();
+        // Tile 13/4216/2668 is row 8191 - 2668 counted from the south
+        writer
+            .execute("INSERT INTO tiles VALUES (13, 4216, 5523, ?1)", [&b"tile"[..]])
+            .unwrap();
+        drop(writer);
+
+        let source = MbTilesSource::from_url(&format!("file://{}", path.display())).unwrap();
+        assert_eq!(&source.fetch(TileId::new(4216, 2668, 13)).await.unwrap()[..], b"tile");
+        assert!(source.fetch(TileId::new(4216, 5523, 13)).await.is_err());
+        assert!(MbTilesSource::from_url("file:///data/tiles/{z}/{x}/{y}.png").is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
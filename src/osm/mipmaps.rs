@@ -0,0 +1,65 @@
+// Mip chains for tile images
+// Tiles seen at a grazing angle cover many texels per pixel; sampling a smaller, averaged copy
+// of the image instead keeps them from shimmering as the camera moves
+
+/// Number of mip levels of a size x size image, down to 1x1
+pub fn mip_levels(size: u32) -> u32 {
+    32 - size.max(1).leading_zeros()
+}
+
+/// The RGBA8 pixels of a square image followed by every smaller level, each half the size of the one before
+/// Each pixel averages the 2x2 pixels above it, weighted by alpha so transparent pixels don't darken their neighbours
+pub fn generate_mips(rgba: &[u8], size: u32) -> Vec<Vec<u8>> {
+    let mut levels = vec![rgba.to_vec()];
+    let mut size = size;
+    while size > 1 {
+        let half = size / 2;
+        let above = levels.last().map(Vec::as_slice).unwrap_or_default();
+        let mut level = Vec::with_capacity((half * half * 4) as usize);
+        for y in 0..half {
+            for x in 0..half {
+                let mut color = [0u32; 3];
+                let mut alpha = 0u32;
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let offset = (((y * 2 + dy) * size + x * 2 + dx) * 4) as usize;
+                    let pixel = &above[offset..offset + 4];
+                    for channel in 0..3 {
+                        color[channel] += pixel[channel] as u32 * pixel[3] as u32;
+                    }
+                    alpha += pixel[3] as u32;
+                }
+                for channel in color {
+                    // Fully transparent pixels stay black
+                    level.push((channel + alpha / 2).checked_div(alpha).unwrap_or(0) as u8);
+                }
+                level.push(((alpha + 2) / 4) as u8);
+            }
+        }
+        levels.push(level);
+        size = half;
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_go_down_to_one_pixel() {
+        assert_eq!(mip_levels(256), 9);
+        assert_eq!(mip_levels(1), 1);
+
+        let levels = generate_mips(&[255; 8 * 8 * 4], 8);
+        let sizes: Vec<usize> = levels.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![8 * 8 * 4, 4 * 4 * 4, 2 * 2 * 4, 4]);
+    }
+
+    #[test]
+    fn pixels_average_the_level_above() {
+        // A 2x2 image: black and white on top, two transparent pixels below
+        let rgba = [0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 0];
+        let levels = generate_mips(&rgba, 2);
+        assert_eq!(levels[1], vec![128, 128, 128, 128]);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::Cursor;
+use async_trait::async_trait;
+use bytes::Bytes;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use crate::osm::source::TileSource;
+use crate::osm::tile::TileId;
+use crate::osm::atlas::TILE_LAYER_SIZE;
+
+// URL scheme of tile servers drawn locally
+const MOCK_SCHEME: &str = "mock://";
+
+// Digits and the slash in a 3x5 pixel font, a row of 3 bits per line
+const GLYPHS: [[u8; 5]; 11] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b001, 0b001, 0b010, 0b100, 0b100],
+];
+const GLYPH_SCALE: u32 = 4; // Screen pixels per font pixel
+const LABEL_MARGIN: u32 = 8;
+
+/// A tile server that isn't one: URL templates starting with mock:// are drawn locally,
+/// every tile a flat color picked from its id and the URL, with a border and its z/x/y written on it
+/// The tiles are the same on every run, for offline development and the tile pipeline's tests
+/// Set one as the tile server (e.g. "mock://tiles") in the settings file to use it
+pub struct MockTileSource {
+    seed: u32, // Different URLs get different colors, so stacked layers can be told apart
+}
+
+impl MockTileSource {
+    /// The mock source of a tile URL template, None for real tile servers
+    pub fn from_url(url: &str) -> Option<Self> {
+        url.starts_with(MOCK_SCHEME).then(|| Self { seed: crc32fast::hash(url.as_bytes()) })
+    }
+
+    /// Draw a tile, at the size of an atlas layer
+    pub fn render(&self, id: TileId) -> DynamicImage {
+        let mut hasher = crc32fast::Hasher::new();
+        for value in [self.seed, id.x, id.y, id.z] {
+            hasher.update(&value.to_le_bytes());
+        }
+        let hash = hasher.finalize().to_le_bytes();
+        // Light colors, so the dark label stays readable
+        let fill = Rgba([128 | hash[0] >> 1, 128 | hash[1] >> 1, 128 | hash[2] >> 1, 255]);
+        let ink = Rgba([fill[0] / 4, fill[1] / 4, fill[2] / 4, 255]);
+
+        let size = TILE_LAYER_SIZE;
+        let mut image = RgbaImage::from_fn(size, size, |x, y| {
+            if x == 0 || y == 0 || x == size - 1 || y == size - 1 {
+                ink
+            } else {
+                fill
+            }
+        });
+
+        let label = format!("{}/{}/{}", id.z, id.x, id.y);
+        let advance = 4 * GLYPH_SCALE;
+        for (i, character) in label.chars().enumerate() {
+            let glyph = match character {
+                '/' => GLYPHS[10],
+                digit => GLYPHS[digit.to_digit(10).unwrap_or(0) as usize],
+            };
+            let left = LABEL_MARGIN + i as u32 * advance;
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    for dy in 0..GLYPH_SCALE {
+                        for dx in 0..GLYPH_SCALE {
+                            let (x, y) = (left + column * GLYPH_SCALE + dx, LABEL_MARGIN + row as u32 * GLYPH_SCALE + dy);
+                            if x < size && y < size {
+                                image.put_pixel(x, y, ink);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        DynamicImage::ImageRgba8(image)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl TileSource for MockTileSource {
+    // Encoded like a tile server's tiles, so the mock goes through the same decoding
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        let mut png = Cursor::new(Vec::new());
+        self.render(id).write_to(&mut png, ImageFormat::Png)?;
+        Ok(png.into_inner().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_mock_urls_are_mocked() {
+        assert!(MockTileSource::from_url("mock://tiles").is_some());
+        assert!(MockTileSource::from_url("https://tile.openstreetmap.org/{z}/{x}/{y}.png").is_none());
+    }
+
+    #[test]
+    fn tiles_are_deterministic_and_differ_by_id_and_url() {
+        let source = MockTileSource::from_url("mock://tiles").unwrap();
+        let id = TileId::new(4216, 2668, 13);
+        let image = source.render(id).to_rgba8();
+        assert_eq!(image.dimensions(), (TILE_LAYER_SIZE, TILE_LAYER_SIZE));
+        assert_eq!(image, source.render(id).to_rgba8());
+
+        // The middle of the tile shows the fill color
+        let fill = |image: &RgbaImage| *image.get_pixel(TILE_LAYER_SIZE / 2, TILE_LAYER_SIZE / 2);
+        assert_ne!(fill(&image), fill(&source.render(TileId::new(4217, 2668, 13)).to_rgba8()));
+        let other = MockTileSource::from_url("mock://photo").unwrap();
+        assert_ne!(fill(&image), fill(&other.render(id).to_rgba8()));
+
+        // The label is drawn in the top-left corner, starting with the middle column of a 1
+        assert_ne!(*image.get_pixel(LABEL_MARGIN + GLYPH_SCALE, LABEL_MARGIN), fill(&image));
+    }
+}
@@ -1,7 +1,58 @@
 mod tile;
+// The disk cache is native only, in the browser the tiles are kept by its HTTP cache
+#[cfg(not(target_arch = "wasm32"))]
 mod cache;
+mod download;
+mod download_stats;
+mod connectivity;
+mod source;
+mod mock;
+mod pmtiles;
+// MBTiles files are read with SQLite, which isn't built for the browser
+#[cfg(not(target_arch = "wasm32"))]
+mod mbtiles;
+// Heatmaps are drawn from local data files
+#[cfg(not(target_arch = "wasm32"))]
+mod heatmap;
+mod http_cache;
+mod throttle;
+// Client for the features built on OpenStreetMap data
+mod overpass;
+mod tile_error;
 mod rendering;
+#[cfg(not(target_arch = "wasm32"))]
+mod maintenance;
+mod atlas;
+mod compression;
+mod mipmaps;
+mod layers;
+mod tile_material;
 
-pub use tile::OSMTile;
-pub use cache::{init_tile_cache, load_tile_image};
-pub use rendering::{create_tile_mesh, create_fallback_tile_mesh}; 
\ No newline at end of file
+pub use tile::{OSMTile, TileBounds, TileId, DEFAULT_TILE_SERVER};
+#[cfg(not(target_arch = "wasm32"))]
+pub use tile::{cache_dir, set_cache_dir};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache::init_tile_cache;
+pub use source::{load_tile_image, open_tile_source, TileSources};
+pub use throttle::{HostThrottle, SharedThrottle};
+pub use overpass::{tag_filter, OverpassClient, OverpassData, OverpassQuery, DEFAULT_OVERPASS_SERVER};
+pub use vibe_world_core::download_slots::{DownloadClass, DownloadLimits, DownloadSlots};
+pub use vibe_world_core::coalesce::{redundant_requests, TileRequest};
+pub use connectivity::SharedNetworkStatus;
+pub use download_stats::{download_totals, error_counts, host_totals, DownloadTotals, HostTotals};
+pub use tile_error::TileError;
+#[cfg(not(target_arch = "wasm32"))]
+pub use heatmap::HEATMAP_SCHEME;
+#[cfg(not(target_arch = "wasm32"))]
+pub use maintenance::{scan_cache, read_index, write_index, maintain_entry, IndexEntry, MaintenanceOutcome};
+#[cfg(not(target_arch = "wasm32"))]
+pub use vibe_world_core::cache_policy::MaintenancePolicy;
+pub use rendering::{tile_layer_image, create_tile_texture, create_atlas_tile, create_fallback_tile_mesh, fallback_glow};
+pub use atlas::{
+    atlas_layer_bytes, atlas_layer_data, build_batch_mesh, create_atlas_page, write_atlas_layer, BatchQuad,
+    LAYERS_PER_PAGE,
+};
+pub use vibe_world_core::lod::{select_lod_tiles, LodView, ViewFootprint};
+pub use layers::{composite_layers, LayerSource};
+pub use tile_material::{TileMaterial, TILE_SHADER_HANDLE};
+pub use crate::shader_params::{TileFilter, TileGlobe}; 
\ No newline at end of file
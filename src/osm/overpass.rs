@@ -0,0 +1,333 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::anyhow;
+use bevy::utils::Instant;
+use parking_lot::Mutex;
+use reqwest::Client;
+use serde_json::Value;
+use crate::osm::download::send_query_throttled;
+use crate::osm::throttle::{HostThrottle, SharedThrottle, ThrottlePolicy};
+use crate::osm::tile::{TileBounds, TileId};
+
+/// Public Overpass API instance; its usage policy asks for at most a few thousand queries a day
+pub const DEFAULT_OVERPASS_SERVER: &str = "https://overpass-api.de/api/interpreter";
+
+// Seconds the server may spend on a query before giving up, the server's own default is 180
+const DEFAULT_TIMEOUT_S: u32 = 25;
+// Spacing between queries; the public servers hand out a couple of query slots per client
+// and answer 429 beyond that
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+const CACHE_ENTRIES: usize = 64;
+// OpenStreetMap data changes slowly, a session can keep the answers it got
+const CACHE_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// A query for the OpenStreetMap elements matching tag filters within a bounding box
+/// The server answers with every matching element and the geometry of the ways
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverpassQuery {
+    pub bounds: TileBounds,
+    pub timeout_s: u32,
+    statements: Vec<String>,
+}
+
+impl OverpassQuery {
+    pub fn new(bounds: TileBounds) -> Self {
+        Self { bounds, timeout_s: DEFAULT_TIMEOUT_S, statements: Vec::new() }
+    }
+
+    /// A query for the elements within a tile
+    pub fn for_tile(id: TileId) -> Self {
+        Self::new(id.bounds())
+    }
+
+    /// Also ask for the nodes matching a filter, see tag_filter
+    pub fn nodes(self, filter: &str) -> Self {
+        self.statement("node", filter)
+    }
+
+    /// Also ask for the ways matching a filter, see tag_filter
+    pub fn ways(self, filter: &str) -> Self {
+        self.statement("way", filter)
+    }
+
+    fn statement(mut self, kind: &str, filter: &str) -> Self {
+        self.statements.push(format!("{}{};", kind, filter));
+        self
+    }
+
+    /// The query in Overpass QL, with the bounding box as a global filter
+    pub fn to_ql(&self) -> String {
+        format!(
+            "[out:json][timeout:{}][bbox:{:.6},{:.6},{:.6},{:.6}];({});out body geom;",
+            self.timeout_s,
+            self.bounds.south,
+            self.bounds.west,
+            self.bounds.north,
+            self.bounds.east,
+            self.statements.concat()
+        )
+    }
+
+    // The bounding box in microdegrees and a hash of the rest, so the same query for the same
+    // area hits the cache however its bounds were computed
+    fn cache_key(&self) -> CacheKey {
+        let bounds = [self.bounds.south, self.bounds.west, self.bounds.north, self.bounds.east]
+            .map(|degrees| (degrees * 1e6).round() as i64);
+        let mut hasher = DefaultHasher::new();
+        (self.timeout_s, &self.statements).hash(&mut hasher);
+        (bounds, hasher.finish())
+    }
+}
+
+/// Overpass QL filter on a tag: `["highway"]` for any value, `["highway"="primary"]` for one
+pub fn tag_filter(key: &str, value: Option<&str>) -> String {
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    match value {
+        Some(value) => format!("[\"{}\"=\"{}\"]", quote(key), quote(value)),
+        None => format!("[\"{}\"]", quote(key)),
+    }
+}
+
+/// An OpenStreetMap node
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsmNode {
+    pub id: i64,
+    pub lat: f64,
+    pub lon: f64,
+    pub tags: HashMap<String, String>,
+}
+
+/// An OpenStreetMap way with the positions of its nodes
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsmWay {
+    pub id: i64,
+    pub nodes: Vec<i64>,
+    pub geometry: Vec<(f64, f64)>, // (lat, lon) of the nodes, in order
+    pub tags: HashMap<String, String>,
+}
+
+/// What an element of a relation is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    Node,
+    Way,
+    Relation,
+}
+
+/// An element of a relation, with its role in it (like `outer` or `inner` for multipolygons)
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsmMember {
+    pub kind: ElementKind,
+    pub id: i64,
+    pub role: String,
+}
+
+/// An OpenStreetMap relation
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsmRelation {
+    pub id: i64,
+    pub members: Vec<OsmMember>,
+    pub tags: HashMap<String, String>,
+}
+
+/// The elements of an Overpass answer, by kind
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OverpassData {
+    pub nodes: Vec<OsmNode>,
+    pub ways: Vec<OsmWay>,
+    pub relations: Vec<OsmRelation>,
+}
+
+/// Read the elements of an Overpass JSON answer
+/// A query that timed out or ran out of memory still comes with a 200 status, partial data and
+/// a remark saying so; it is an error, so the partial data isn't cached as the whole answer
+pub fn parse_overpass_response(body: &str) -> Result<OverpassData, anyhow::Error> {
+    let response: Value = serde_json::from_str(body)?;
+    if let Some(remark) = response["remark"].as_str().filter(|remark| remark.contains("error")) {
+        return Err(anyhow!("Overpass: {}", remark));
+    }
+    let elements = response["elements"].as_array().ok_or_else(|| anyhow!("Overpass answer without elements"))?;
+
+    let mut data = OverpassData::default();
+    for element in elements {
+        let Some(id) = element["id"].as_i64() else {
+            continue;
+        };
+        let tags = parse_tags(&element["tags"]);
+        match element["type"].as_str() {
+            Some("node") => {
+                let (Some(lat), Some(lon)) = (element["lat"].as_f64(), element["lon"].as_f64()) else {
+                    continue;
+                };
+                data.nodes.push(OsmNode { id, lat, lon, tags });
+            }
+            Some("way") => data.ways.push(OsmWay {
+                id,
+                nodes: element["nodes"].as_array().into_iter().flatten().filter_map(Value::as_i64).collect(),
+                geometry: element["geometry"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|point| Some((point["lat"].as_f64()?, point["lon"].as_f64()?)))
+                    .collect(),
+                tags,
+            }),
+            Some("relation") => data.relations.push(OsmRelation {
+                id,
+                members: element["members"].as_array().into_iter().flatten().filter_map(parse_member).collect(),
+                tags,
+            }),
+            _ => {}
+        }
+    }
+    Ok(data)
+}
+
+fn parse_tags(tags: &Value) -> HashMap<String, String> {
+    tags.as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+fn parse_member(member: &Value) -> Option<OsmMember> {
+    let kind = match member["type"].as_str()? {
+        "node" => ElementKind::Node,
+        "way" => ElementKind::Way,
+        "relation" => ElementKind::Relation,
+        _ => return None,
+    };
+    Some(OsmMember {
+        kind,
+        id: member["ref"].as_i64()?,
+        role: member["role"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+type CacheKey = ([i64; 4], u64);
+
+/// Answers to earlier queries, so panning back and forth doesn't ask the server again
+/// The oldest answer makes room when the cache is full
+#[derive(Debug, Default)]
+pub struct OverpassCache {
+    entries: HashMap<CacheKey, (Arc<OverpassData>, Instant)>,
+}
+
+impl OverpassCache {
+    /// The answer to a query, unless it's missing or too old
+    pub fn get(&self, query: &OverpassQuery, now: Instant) -> Option<Arc<OverpassData>> {
+        let (data, fetched) = self.entries.get(&query.cache_key())?;
+        (now.duration_since(*fetched) < CACHE_MAX_AGE).then(|| Arc::clone(data))
+    }
+
+    pub fn insert(&mut self, query: &OverpassQuery, data: Arc<OverpassData>, now: Instant) {
+        if self.entries.len() >= CACHE_ENTRIES {
+            let oldest = self.entries.iter().min_by_key(|(_, (_, fetched))| *fetched).map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(query.cache_key(), (data, now));
+    }
+}
+
+/// Client of an Overpass API server, shared by the features built on OpenStreetMap data
+/// Queries are paced and backed off like tile requests, with a policy of their own as the
+/// public servers allow far fewer queries than tile requests; answers are cached
+/// Clones share the throttle and the cache, so tasks can each take one
+#[derive(Clone)]
+pub struct OverpassClient {
+    pub server: String,
+    client: Client,
+    throttle: SharedThrottle,
+    cache: Arc<Mutex<OverpassCache>>,
+}
+
+impl OverpassClient {
+    pub fn new(server: &str, client: &Client) -> Self {
+        let mut throttle = HostThrottle::default();
+        throttle.policy = ThrottlePolicy { min_interval: MIN_INTERVAL, ..Default::default() };
+        Self {
+            server: server.to_string(),
+            client: client.clone(),
+            throttle: Arc::new(Mutex::new(throttle)),
+            cache: Arc::default(),
+        }
+    }
+
+    /// The elements a query matches, from the cache or the server
+    pub async fn fetch(&self, query: &OverpassQuery) -> Result<Arc<OverpassData>, anyhow::Error> {
+        if let Some(data) = self.cache.lock().get(query, Instant::now()) {
+            return Ok(data);
+        }
+
+        let request = self.client.post(&self.server).form(&[("data", query.to_ql())]);
+        let response = send_query_throttled(&self.throttle, &self.server, request).await?.error_for_status()?;
+        let data = Arc::new(parse_overpass_response(&response.text().await?)?);
+        self.cache.lock().insert(query, Arc::clone(&data), Instant::now());
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_written_in_overpass_ql() {
+        let bounds = TileBounds { north: 53.25, south: 53.2, west: 6.5, east: 6.6 };
+        let query = OverpassQuery::new(bounds)
+            .ways(&tag_filter("highway", None))
+            .nodes(&tag_filter("highway", Some("street_lamp")));
+        assert_eq!(
+            query.to_ql(),
+            "[out:json][timeout:25][bbox:53.200000,6.500000,53.250000,6.600000];\
+             (way[\"highway\"];node[\"highway\"=\"street_lamp\"];);out body geom;"
+        );
+        assert_eq!(tag_filter("name", Some("De \"Oude\" Markt")), r#"["name"="De \"Oude\" Markt"]"#);
+    }
+
+    #[test]
+    fn elements_are_read_by_kind() {
+        let body = r#"{
+            "version": 0.6,
+            "elements": [
+                {"type": "node", "id": 1, "lat": 53.2194, "lon": 6.5665, "tags": {"highway": "street_lamp"}},
+                {"type": "way", "id": 2, "nodes": [1, 3], "geometry": [{"lat": 53.2194, "lon": 6.5665}, {"lat": 53.22, "lon": 6.567}],
+                 "tags": {"highway": "residential", "lanes": "2"}},
+                {"type": "relation", "id": 4, "members": [{"type": "way", "ref": 2, "role": "outer"}], "tags": {"type": "multipolygon"}}
+            ]
+        }"#;
+        let data = parse_overpass_response(body).unwrap();
+        assert_eq!(data.nodes[0].tags["highway"], "street_lamp");
+        assert_eq!(data.ways[0].nodes, vec![1, 3]);
+        assert_eq!(data.ways[0].geometry[1], (53.22, 6.567));
+        assert_eq!(data.relations[0].members, vec![OsmMember { kind: ElementKind::Way, id: 2, role: "outer".to_string() }]);
+
+        let timed_out = r#"{"elements": [], "remark": "runtime error: Query timed out in \"query\" at line 1 after 26 seconds."}"#;
+        assert!(parse_overpass_response(timed_out).is_err());
+    }
+
+    #[test]
+    fn cached_answers_expire_and_make_room() {
+        let mut cache = OverpassCache::default();
+        let now = Instant::now();
+        let query = |x| OverpassQuery::for_tile(TileId::new(x, 2660, 13)).ways(&tag_filter("highway", None));
+
+        cache.insert(&query(4245), Arc::default(), now);
+        assert!(cache.get(&query(4245), now).is_some());
+        assert!(cache.get(&query(4246), now).is_none());
+        assert!(cache.get(&OverpassQuery::for_tile(TileId::new(4245, 2660, 13)), now).is_none());
+        assert!(cache.get(&query(4245), now + CACHE_MAX_AGE).is_none());
+
+        for x in 0..CACHE_ENTRIES as u32 {
+            cache.insert(&query(x), Arc::default(), now + Duration::from_secs(1));
+        }
+        assert_eq!(cache.entries.len(), CACHE_ENTRIES);
+        assert!(cache.get(&query(4245), now).is_none());
+    }
+}
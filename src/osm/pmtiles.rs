@@ -0,0 +1,326 @@
+// PMTiles version 3: a whole tileset in one file, read with HTTP range requests
+// The file starts with a fixed size header and the root directory, which map tile ids to byte
+// ranges of tile data or of leaf directories; tile ids count the tiles of all zoom levels
+// before the tile's own, then along a Hilbert curve within its level
+// See https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md
+use std::collections::HashMap;
+use std::io::Read;
+use std::ops::Range;
+use std::sync::Arc;
+use async_trait::async_trait;
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use parking_lot::Mutex;
+use reqwest::Client;
+use crate::osm::download::request_range;
+use crate::osm::source::TileSource;
+use crate::osm::connectivity::SharedNetworkStatus;
+use crate::osm::throttle::SharedThrottle;
+use crate::osm::tile::TileId;
+
+const PMTILES_EXTENSION: &str = ".pmtiles";
+const MAGIC: &[u8] = b"PMTiles";
+const VERSION: u8 = 3;
+const HEADER_BYTES: usize = 127;
+// The spec keeps the header and root directory within the first 16 KiB, one request reads both
+const ROOT_BYTES: u64 = 16384;
+// Leaf directories can point to further leaves; the spec's writers never nest deeper than this
+const MAX_DIRECTORY_DEPTH: usize = 4;
+// Leaf directories kept after reading them, most tiles of an area share a few
+const MAX_CACHED_LEAVES: usize = 64;
+
+// Compression of directories and tiles
+const COMPRESSION_NONE: u8 = 1;
+const COMPRESSION_GZIP: u8 = 2;
+// Tile types with imagery, vector tiles (1) can't be drawn as map imagery
+const RASTER_TILE_TYPES: [u8; 4] = [2, 3, 4, 5]; // PNG, JPEG, WebP, AVIF
+
+// The fields of the header used to find tiles
+#[derive(Debug, PartialEq)]
+struct Header {
+    root_directory: Range<u64>,
+    leaf_directories_offset: u64,
+    tile_data_offset: u64,
+    internal_compression: u8,
+    tile_compression: u8,
+    tile_type: u8,
+}
+
+// A directory entry: run_length tiles from tile_id on with the same data at offset,
+// or with a run length of 0, the leaf directory at offset covering tile_id and up
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u64,
+    run_length: u32,
+}
+
+// What's read once per file: its header and root directory
+struct Archive {
+    header: Header,
+    root: Vec<Entry>,
+}
+
+/// Tiles read from a PMTiles file on a web server or object store, with range requests:
+/// an http(s) URL ending in .pmtiles as a layer URL
+/// The header and root directory are read with the first tile and kept
+pub struct PmTilesSource {
+    url: String,
+    client: Client,
+    throttle: SharedThrottle,
+    network: SharedNetworkStatus,
+    archive: Mutex<Option<Arc<Archive>>>,
+    leaves: Mutex<HashMap<u64, Arc<Vec<Entry>>>>, // Leaf directories by offset
+}
+
+impl PmTilesSource {
+    /// The PMTiles file of a URL ending in .pmtiles, None for other URLs
+    pub fn from_url(url: &str, client: &Client, throttle: &SharedThrottle, network: &SharedNetworkStatus) -> Option<Self> {
+        let remote = url.starts_with("http://") || url.starts_with("https://");
+        (remote && url.ends_with(PMTILES_EXTENSION)).then(|| Self {
+            url: url.to_string(),
+            client: client.clone(),
+            throttle: throttle.clone(),
+            network: network.clone(),
+            archive: Mutex::new(None),
+            leaves: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Files smaller than a range come back shorter, which the parsing below checks
+    async fn read(&self, range: Range<u64>) -> Result<Bytes, anyhow::Error> {
+        request_range(&self.client, &self.throttle, &self.network, &self.url, range).await
+    }
+
+    // Concurrent first tiles may each read the root, whichever finishes last is kept
+    async fn archive(&self) -> Result<Arc<Archive>, anyhow::Error> {
+        if let Some(archive) = self.archive.lock().clone() {
+            return Ok(archive);
+        }
+        let start = self.read(0..ROOT_BYTES).await?;
+        let header = parse_header(&start)?;
+        let root = start
+            .get(header.root_directory.start as usize..header.root_directory.end as usize)
+            .ok_or_else(|| anyhow::anyhow!("Root directory of {} is outside its first bytes", self.url))?;
+        let root = parse_directory(&decompress(root, header.internal_compression)?)?;
+        let archive = Arc::new(Archive { header, root });
+        *self.archive.lock() = Some(archive.clone());
+        Ok(archive)
+    }
+
+    async fn leaf(&self, header: &Header, entry: &Entry) -> Result<Arc<Vec<Entry>>, anyhow::Error> {
+        if let Some(leaf) = self.leaves.lock().get(&entry.offset) {
+            return Ok(leaf.clone());
+        }
+        let start = header.leaf_directories_offset + entry.offset;
+        let bytes = self.read(start..start + entry.length).await?;
+        let leaf = Arc::new(parse_directory(&decompress(&bytes, header.internal_compression)?)?);
+
+        let mut leaves = self.leaves.lock();
+        if leaves.len() >= MAX_CACHED_LEAVES {
+            leaves.clear();
+        }
+        leaves.insert(entry.offset, leaf.clone());
+        Ok(leaf)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl TileSource for PmTilesSource {
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+        let archive = self.archive().await?;
+        let header = &archive.header;
+        if !RASTER_TILE_TYPES.contains(&header.tile_type) {
+            return Err(anyhow::anyhow!("{} holds vector tiles, not imagery", self.url));
+        }
+
+        let tile_id = tile_id(id);
+        let mut directory = None;
+        for _ in 0..MAX_DIRECTORY_DEPTH {
+            let entries = directory.as_deref().unwrap_or(&archive.root);
+            let Some(entry) = find_entry(entries, tile_id) else {
+                break;
+            };
+            if entry.run_length == 0 {
+                directory = Some(self.leaf(header, &entry).await?);
+                continue;
+            }
+            let start = header.tile_data_offset + entry.offset;
+            let bytes = self.read(start..start + entry.length).await?;
+            return Ok(decompress(&bytes, header.tile_compression)?.into());
+        }
+        Err(anyhow::anyhow!("No tile {}/{}/{} in {}", id.z, id.x, id.y, self.url))
+    }
+}
+
+// Tile id of a tile address: the tiles of the lower zoom levels, then the distance along the
+// level's Hilbert curve
+fn tile_id(id: TileId) -> u64 {
+    let lower_levels = ((1u64 << (2 * id.z)) - 1) / 3;
+    let n = 1u64 << id.z;
+    let (mut x, mut y) = (id.x as u64, id.y as u64);
+    let mut distance = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        distance += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve continues in the next level's orientation
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    lower_levels + distance
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header, anyhow::Error> {
+    if bytes.len() < HEADER_BYTES || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(anyhow::anyhow!("Not a PMTiles file"));
+    }
+    if bytes[7] != VERSION {
+        return Err(anyhow::anyhow!("PMTiles version {} isn't supported", bytes[7]));
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap_or_default());
+    let root_offset = u64_at(8);
+    Ok(Header {
+        root_directory: root_offset..root_offset + u64_at(16),
+        leaf_directories_offset: u64_at(40),
+        tile_data_offset: u64_at(56),
+        internal_compression: bytes[97],
+        tile_compression: bytes[98],
+        tile_type: bytes[99],
+    })
+}
+
+fn decompress(bytes: &[u8], compression: u8) -> Result<Vec<u8>, anyhow::Error> {
+    match compression {
+        COMPRESSION_NONE => Ok(bytes.to_vec()),
+        COMPRESSION_GZIP => {
+            let mut out = Vec::new();
+            GzDecoder::new(bytes).read_to_end(&mut out)?;
+            Ok(out)
+        }
+        other => Err(anyhow::anyhow!("PMTiles compression {} isn't supported", other)),
+    }
+}
+
+// A directory is its number of entries followed by columns of varints: tile id deltas, run
+// lengths, lengths and offsets, where offset 0 means right after the previous entry's data
+// and others are stored plus one
+fn parse_directory(bytes: &[u8]) -> Result<Vec<Entry>, anyhow::Error> {
+    let mut reader = bytes;
+    let mut next = || read_varint(&mut reader).ok_or_else(|| anyhow::anyhow!("PMTiles directory ends early"));
+
+    let count = next()? as usize;
+    let mut entries = Vec::with_capacity(count.min(bytes.len()));
+    let mut tile_id = 0;
+    for _ in 0..count {
+        tile_id += next()?;
+        entries.push(Entry { tile_id, offset: 0, length: 0, run_length: 0 });
+    }
+    for entry in &mut entries {
+        entry.run_length = next()? as u32;
+    }
+    for entry in &mut entries {
+        entry.length = next()?;
+    }
+    for i in 0..count {
+        let stored = next()?;
+        entries[i].offset = match (stored, i.checked_sub(1)) {
+            (0, Some(previous)) => entries[previous].offset + entries[previous].length,
+            _ => stored.saturating_sub(1),
+        };
+    }
+    Ok(entries)
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// The entry covering a tile id: the last entry starting at or before it, if its run reaches
+// the tile, or if it points to a leaf directory
+fn find_entry(entries: &[Entry], tile_id: u64) -> Option<Entry> {
+    let index = entries.partition_point(|entry| entry.tile_id <= tile_id).checked_sub(1)?;
+    let entry = entries[index];
+    (entry.run_length == 0 || tile_id - entry.tile_id < entry.run_length as u64).then_some(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    #[test]
+    fn tile_ids_follow_the_hilbert_curve_per_level() {
+        assert_eq!(tile_id(TileId::new(0, 0, 0)), 0);
+        let level_one: Vec<u64> = [(0, 0), (0, 1), (1, 1), (1, 0)].iter().map(|&(x, y)| tile_id(TileId::new(x, y, 1))).collect();
+        assert_eq!(level_one, [1, 2, 3, 4]);
+        assert_eq!(tile_id(TileId::new(0, 0, 2)), 5);
+        assert_eq!(tile_id(TileId::new(3423, 1763, 12)), 19078479);
+    }
+
+    #[test]
+    fn directories_decode_offsets_and_runs() {
+        // A run of two tiles, a tile right after its data and a leaf directory
+        let mut bytes = Vec::new();
+        for value in [3, 5, 2, 10, 2, 1, 0, 100, 50, 80, 1, 0, 11] {
+            write_varint(&mut bytes, value);
+        }
+        let entries = parse_directory(&bytes).unwrap();
+        assert_eq!(entries, [
+            Entry { tile_id: 5, offset: 0, length: 100, run_length: 2 },
+            Entry { tile_id: 7, offset: 100, length: 50, run_length: 1 },
+            Entry { tile_id: 17, offset: 10, length: 80, run_length: 0 },
+        ]);
+
+        assert_eq!(find_entry(&entries, 6), Some(entries[0]));
+        assert_eq!(find_entry(&entries, 7), Some(entries[1]));
+        assert_eq!(find_entry(&entries, 8), None);
+        assert_eq!(find_entry(&entries, 4), None);
+        assert_eq!(find_entry(&entries, 1000), Some(entries[2]));
+        assert!(parse_directory(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn headers_are_checked_and_read() {
+        let mut bytes = vec![0; HEADER_BYTES];
+        assert!(parse_header(&bytes).is_err());
+        bytes[..7].copy_from_slice(MAGIC);
+        bytes[7] = VERSION;
+        bytes[8..16].copy_from_slice(&127u64.to_le_bytes());
+        bytes[16..24].copy_from_slice(&60u64.to_le_bytes());
+        bytes[56..64].copy_from_slice(&4096u64.to_le_bytes());
+        bytes[97] = COMPRESSION_GZIP;
+        bytes[98] = COMPRESSION_NONE;
+        bytes[99] = 2;
+        let header = parse_header(&bytes).unwrap();
+        assert_eq!(header.root_directory, 127..187);
+        assert_eq!(header.tile_data_offset, 4096);
+        assert_eq!(header.internal_compression, COMPRESSION_GZIP);
+        assert_eq!(header.tile_type, 2);
+    }
+}
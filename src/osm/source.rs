@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use bytes::Bytes;
+use image::DynamicImage;
+use reqwest::Client;
+use crate::osm::download::HttpTileSource;
+use crate::osm::download_stats::record_error;
+use crate::osm::mock::MockTileSource;
+use crate::osm::pmtiles::PmTilesSource;
+use crate::osm::connectivity::SharedNetworkStatus;
+use crate::osm::throttle::SharedThrottle;
+use crate::osm::tile::TileId;
+use crate::osm::tile_error::TileError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osm::cache::{CachedTileSource, DiskTileSource};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osm::heatmap::HeatmapTileSource;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osm::mbtiles::MbTilesSource;
+
+/// Where the imagery of a layer comes from: a tile server, a directory or file of tiles, the mock
+/// The tile pipeline only talks to this trait, so a new kind of provider is a new implementation
+/// Browser futures can't move between threads, so there sources needn't be Send
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait TileSource: Send + Sync {
+    /// The encoded image of a tile, in any format the image crate reads (PNG, JPEG, WebP)
+    async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error>;
+
+    /// Imagery to show in place of a tile that couldn't be fetched, e.g. cut out of a cached
+    /// lower zoom ancestor
+    fn fallback(&self, _id: TileId) -> Option<DynamicImage> {
+        None
+    }
+}
+
+/// Result of loading a tile image
+pub struct LoadedTileImage {
+    pub image: DynamicImage,
+    pub low_res: bool, // The source's fallback instead of the real tile
+}
+
+/// Fetch and decode a tile from its source, or the source's fallback when that fails
+/// Failures are counted per kind of TileError, which the error can be classified with
+/// Tiles across the antimeridian are loaded as the tile they repeat
+pub async fn load_tile_image(source: &dyn TileSource, id: TileId) -> Result<LoadedTileImage, anyhow::Error> {
+    let id = id.wrapped();
+    let fetched = match source.fetch(id).await {
+        Ok(bytes) => image::load_from_memory(&bytes).map_err(anyhow::Error::from),
+        Err(e) => Err(e),
+    };
+    match fetched {
+        Ok(image) => Ok(LoadedTileImage { image, low_res: false }),
+        Err(e) => {
+            record_error(TileError::of(&e));
+            match source.fallback(id) {
+                Some(image) => Ok(LoadedTileImage { image, low_res: true }),
+                None => Err(e),
+            }
+        }
+    }
+}
+
+/// The source for a layer URL:
+/// - mock://… draws tiles locally, see MockTileSource
+/// - http(s)://….pmtiles reads a PMTiles file with range requests
+/// - heatmap://… draws a heatmap of the points in a CSV or GeoJSON file (native only)
+/// - file://….mbtiles reads an MBTiles file (native only)
+/// - file://…/{z}/{x}/{y}.png reads a directory of tiles (native only)
+/// - anything else is a tile server, natively through the disk cache
+pub fn open_tile_source(
+    url: &str,
+    client: &Client,
+    throttle: &SharedThrottle,
+    network: &SharedNetworkStatus,
+    ttl: Duration,
+) -> Arc<dyn TileSource> {
+    if let Some(source) = MockTileSource::from_url(url) {
+        return Arc::new(source);
+    }
+    if let Some(source) = PmTilesSource::from_url(url, client, throttle, network) {
+        return Arc::new(source);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(source) = HeatmapTileSource::from_url(url) {
+            return Arc::new(source);
+        }
+        if let Some(source) = MbTilesSource::from_url(url) {
+            return Arc::new(source);
+        }
+        if let Some(source) = DiskTileSource::from_url(url) {
+            return Arc::new(source);
+        }
+        Arc::new(CachedTileSource::new(HttpTileSource::new(url, client, throttle, network), ttl))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = ttl;
+        Arc::new(HttpTileSource::new(url, client, throttle, network))
+    }
+}
+
+/// The sources of the layers, opened on first use and shared by the loader tasks
+/// Cleared when the settings they were opened with change
+#[derive(Default)]
+pub struct TileSources {
+    sources: HashMap<String, Arc<dyn TileSource>>,
+}
+
+impl TileSources {
+    /// The source of a layer URL, opened with the given client, throttle, network status and
+    /// cache ttl if it's new
+    pub fn get(
+        &mut self,
+        url: &str,
+        client: &Client,
+        throttle: &SharedThrottle,
+        network: &SharedNetworkStatus,
+        ttl: Duration,
+    ) -> Arc<dyn TileSource> {
+        self.sources
+            .entry(url.to_string())
+            .or_insert_with(|| open_tile_source(url, client, throttle, network, ttl))
+            .clone()
+    }
+
+    pub fn clear(&mut self) {
+        self.sources.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A source that has no tiles, with a gray fallback
+    struct EmptySource;
+
+    #[async_trait]
+    impl TileSource for EmptySource {
+        async fn fetch(&self, id: TileId) -> Result<Bytes, anyhow::Error> {
+            Err(anyhow::anyhow!("No tile {}/{}/{}", id.z, id.x, id.y))
+        }
+
+        fn fallback(&self, _id: TileId) -> Option<DynamicImage> {
+            Some(DynamicImage::new_rgba8(1, 1))
+        }
+    }
+
+    #[tokio::test]
+    async fn loads_through_the_source_or_its_fallback() {
+        let id = TileId::new(4216, 2668, 13);
+        let mock = MockTileSource::from_url("mock://tiles").unwrap();
+        let loaded = load_tile_image(&mock, id).await.unwrap();
+        assert!(!loaded.low_res);
+        assert_eq!(loaded.image.to_rgba8(), mock.render(id).to_rgba8());
+
+        let fallback = load_tile_image(&EmptySource, id).await.unwrap();
+        assert!(fallback.low_res);
+        assert_eq!(fallback.image.width(), 1);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use bevy::log::warn;
+use bevy::utils::Instant;
+use parking_lot::Mutex;
+
+/// Limits for the requests sent to each tile server host
+#[derive(Clone, Debug)]
+pub struct ThrottlePolicy {
+    pub min_interval: Duration,    // Spacing between requests to one host
+    pub base_backoff: Duration,    // Pause after the first failure, doubled for every further one
+    pub max_backoff: Duration,
+    pub breaker_threshold: u32,    // Consecutive failures that pause a host entirely
+    pub breaker_cooldown: Duration, // How long a paused host gets no requests at all
+    pub refusal_threshold: u32,    // Consecutive 403s that disable a host for the rest of the session
+}
+
+impl Default for ThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            // Well within the OSM tile usage policy for an interactive viewer
+            min_interval: Duration::from_millis(50),
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(120),
+            refusal_threshold: 3,
+        }
+    }
+}
+
+/// How a request to a tile server went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RequestOutcome {
+    Success,
+    RateLimited(Option<Duration>), // 429, with the server's Retry-After if it sent one
+    Failed,                        // 5xx or no response at all
+    Forbidden,                     // 403, the server refuses this client
+}
+
+#[derive(Debug)]
+struct HostState {
+    next_request: Instant,
+    failures: u32,              // Consecutive failures
+    paused_until: Option<Instant>, // Circuit breaker open until then
+    refusals: u32,              // Consecutive 403s
+    disabled: bool,             // Refused too often, gets no more requests
+}
+
+/// Request pacing, backoff and circuit breaking per tile server host
+/// Shared by the loader tasks, which ask for a slot before every request and report how it went
+#[derive(Debug, Default)]
+pub struct HostThrottle {
+    pub policy: ThrottlePolicy,
+    hosts: HashMap<String, HostState>,
+}
+
+pub type SharedThrottle = Arc<Mutex<HostThrottle>>;
+
+impl HostThrottle {
+    /// Reserve the next request slot for a host
+    /// Returns how long to wait before sending, or None while the host is paused
+    pub fn acquire(&mut self, host: &str, now: Instant) -> Option<Duration> {
+        let interval = self.policy.min_interval;
+        let state = self.hosts.entry(host.to_string()).or_insert(HostState {
+            next_request: now,
+            failures: 0,
+            paused_until: None,
+            refusals: 0,
+            disabled: false,
+        });
+
+        if state.disabled || state.paused_until.is_some_and(|until| now < until) {
+            return None;
+        }
+
+        let slot = state.next_request.max(now);
+        state.next_request = slot + interval;
+        Some(slot - now)
+    }
+
+    /// Record how a request went, backing off after failures
+    pub fn record(&mut self, host: &str, outcome: RequestOutcome, now: Instant) {
+        let Some(state) = self.hosts.get_mut(host) else {
+            return;
+        };
+
+        let retry_after = match outcome {
+            RequestOutcome::Success => {
+                state.failures = 0;
+                state.paused_until = None;
+                state.refusals = 0;
+                return;
+            }
+            // A refusal says nothing about the server's health, so there's no backoff, but a
+            // server that keeps refusing won't change its mind
+            RequestOutcome::Forbidden => {
+                state.refusals += 1;
+                if state.refusals >= self.policy.refusal_threshold && !state.disabled {
+                    warn!("{} keeps refusing tile requests (HTTP 403), no more requests are sent to it", host);
+                    state.disabled = true;
+                }
+                return;
+            }
+            RequestOutcome::RateLimited(retry_after) => retry_after,
+            RequestOutcome::Failed => None,
+        };
+
+        state.failures += 1;
+        let backoff = retry_after.unwrap_or_else(|| {
+            let doublings = (state.failures - 1).min(16);
+            self.policy.base_backoff.saturating_mul(1 << doublings)
+        });
+        state.next_request = state.next_request.max(now + backoff.min(self.policy.max_backoff));
+
+        // Once open the breaker stays open after every further failure, even right after the cooldown
+        if state.failures >= self.policy.breaker_threshold {
+            state.paused_until = Some(now + self.policy.breaker_cooldown);
+        }
+    }
+
+    /// Forget the failures and pauses of every host, e.g. once the network is back after
+    /// the failures were caused by being offline
+    /// Hosts disabled after refusing requests stay disabled
+    #[cfg(not(target_arch = "wasm32"))] // Only the native disk cache goes offline
+    pub fn reset(&mut self) {
+        self.hosts.retain(|_, state| state.disabled);
+    }
+
+    /// Hosts disabled after refusing requests too often
+    pub fn disabled_hosts(&self) -> Vec<String> {
+        let mut disabled: Vec<_> = self.hosts.iter().filter(|(_, state)| state.disabled).map(|(host, _)| host.clone()).collect();
+        disabled.sort();
+        disabled
+    }
+
+    /// Hosts the circuit breaker has paused, with the time left until they are tried again
+    pub fn paused_hosts(&self, now: Instant) -> Vec<(String, Duration)> {
+        let mut paused: Vec<_> = self
+            .hosts
+            .iter()
+            .filter_map(|(host, state)| {
+                let until = state.paused_until.filter(|until| now < *until)?;
+                Some((host.clone(), until - now))
+            })
+            .collect();
+        paused.sort();
+        paused
+    }
+}
+
+/// Host part of a tile URL, which requests are paced by
+pub fn url_host(url: &str) -> &str {
+    let address = url.split("://").nth(1).unwrap_or(url);
+    address.split('/').next().unwrap_or(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOST: &str = "tile.openstreetmap.org";
+
+    #[test]
+    fn requests_to_a_host_are_spaced() {
+        let mut throttle = HostThrottle::default();
+        let now = Instant::now();
+        let interval = throttle.policy.min_interval;
+
+        assert_eq!(throttle.acquire(HOST, now), Some(Duration::ZERO));
+        assert_eq!(throttle.acquire(HOST, now), Some(interval));
+        assert_eq!(throttle.acquire("other.host", now), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn failures_back_off_exponentially_and_honor_retry_after() {
+        let mut throttle = HostThrottle::default();
+        let now = Instant::now();
+        throttle.acquire(HOST, now);
+
+        throttle.record(HOST, RequestOutcome::Failed, now);
+        assert_eq!(throttle.acquire(HOST, now), Some(Duration::from_secs(1)));
+        throttle.record(HOST, RequestOutcome::Failed, now);
+        assert_eq!(throttle.acquire(HOST, now), Some(Duration::from_secs(2)));
+
+        throttle.record(HOST, RequestOutcome::RateLimited(Some(Duration::from_secs(30))), now);
+        assert_eq!(throttle.acquire(HOST, now), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn repeated_failures_pause_the_host_until_the_cooldown_ends() {
+        let mut throttle = HostThrottle::default();
+        let now = Instant::now();
+        throttle.acquire(HOST, now);
+
+        for _ in 0..throttle.policy.breaker_threshold {
+            throttle.record(HOST, RequestOutcome::Failed, now);
+        }
+        assert_eq!(throttle.acquire(HOST, now), None);
+        assert_eq!(throttle.paused_hosts(now), vec![(HOST.to_string(), throttle.policy.breaker_cooldown)]);
+
+        let later = now + throttle.policy.breaker_cooldown;
+        assert!(throttle.acquire(HOST, later).is_some());
+        throttle.record(HOST, RequestOutcome::Success, later);
+        assert!(throttle.paused_hosts(later).is_empty());
+    }
+
+    #[test]
+    fn reset_lifts_pauses() {
+        let mut throttle = HostThrottle::default();
+        let now = Instant::now();
+        throttle.acquire(HOST, now);
+        for _ in 0..throttle.policy.breaker_threshold {
+            throttle.record(HOST, RequestOutcome::Failed, now);
+        }
+
+        throttle.reset();
+        assert_eq!(throttle.acquire(HOST, now), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn hosts_that_keep_refusing_are_disabled() {
+        let mut throttle = HostThrottle::default();
+        let now = Instant::now();
+        throttle.acquire(HOST, now);
+
+        throttle.record(HOST, RequestOutcome::Forbidden, now);
+        throttle.record(HOST, RequestOutcome::Success, now);
+        for _ in 1..throttle.policy.refusal_threshold {
+            throttle.record(HOST, RequestOutcome::Forbidden, now);
+        }
+        assert!(throttle.acquire(HOST, now).is_some());
+
+        throttle.record(HOST, RequestOutcome::Forbidden, now);
+        assert_eq!(throttle.acquire(HOST, now), None);
+        assert_eq!(throttle.disabled_hosts(), vec![HOST.to_string()]);
+        throttle.reset();
+        assert_eq!(throttle.acquire(HOST, now), None);
+    }
+
+    #[test]
+    fn hosts_are_taken_from_tile_urls() {
+        assert_eq!(url_host("https://a.tile.openstreetmap.org/1/2/3.png"), "a.tile.openstreetmap.org");
+        assert_eq!(url_host("tiles.example.com/1/2/3.png"), "tiles.example.com");
+    }
+}
@@ -1,11 +1,20 @@
 use bevy::prelude::*;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::OnceLock;
+
+pub use vibe_world_core::tile::{TileBounds, TileId};
 
 // Constants for the OSM tile system
 #[allow(dead_code)]
 const TILE_SIZE: usize = 256; // Standard OSM tile size in pixels
-const CACHE_DIR: &str = "tile_cache"; // Directory for caching tiles
+const DEFAULT_CACHE_DIR: &str = "tile_cache"; // Directory for caching tiles
+
+// Cache directory chosen on the command line, set once before anything is cached
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Default tile server, {z}/{x}/{y} are replaced with the tile address
+pub const DEFAULT_TILE_SERVER: &str = "https://a.tile.openstreetmap.org/{z}/{x}/{y}.png";
 
 pub struct OSMTile {
     pub x: u32,
@@ -13,25 +22,36 @@ pub struct OSMTile {
     pub z: u32,
 }
 
+/// Directory the tile cache lives in, ./tile_cache unless set_cache_dir chose another
+pub fn cache_dir() -> &'static Path {
+    CACHE_DIR.get_or_init(|| PathBuf::from(DEFAULT_CACHE_DIR))
+}
+
+/// Keep the tile cache in another directory, e.g. from --cache-dir
+/// Only works before the cache is first used; returns false after that
+pub fn set_cache_dir(dir: PathBuf) -> bool {
+    CACHE_DIR.set(dir).is_ok()
+}
+
 impl OSMTile {
     pub fn new(x: u32, y: u32, z: u32) -> Self {
         Self { x, y, z }
     }
 
-    pub fn get_url(&self) -> String {
-        // Use the standard OSM tile server
-        // The URL format is zoom/x/y where:
+    pub fn get_url(&self, tile_server: &str) -> String {
+        // Fill in the tile server URL template where:
         // - x increases from west to east (0 to 2^zoom-1)
         // - y increases from north to south (0 to 2^zoom-1)
-        format!(
-            "https://a.tile.openstreetmap.org/{}/{}/{}.png",
-            self.z, self.x, self.y
-        )
+        tile_server
+            .replace("{z}", &self.z.to_string())
+            .replace("{x}", &self.x.to_string())
+            .replace("{y}", &self.y.to_string())
     }
 
     // Get cache file path for this tile
-    pub fn get_cache_path(&self) -> PathBuf {
-        let cache_path = Path::new(CACHE_DIR)
+    // Tiles from other servers than the default are cached in their own directory
+    pub fn get_cache_path(&self, tile_server: &str) -> PathBuf {
+        let cache_path = cache_root(tile_server)
             .join(self.z.to_string())
             .join(self.x.to_string());
 
@@ -43,6 +63,24 @@ impl OSMTile {
     }
 }
 
+// Cache directory for a tile server: the default server uses the top level for
+// compatibility with existing caches, others get a subdirectory named after their host
+// and the path up to the tile address, so several services on one host stay apart
+fn cache_root(tile_server: &str) -> PathBuf {
+    if tile_server == DEFAULT_TILE_SERVER {
+        return cache_dir().to_path_buf();
+    }
+
+    let address = tile_server.split("://").nth(1).unwrap_or(tile_server);
+    let prefix = address.split('{').next().unwrap_or_default().trim_end_matches('/');
+    let name: String = prefix
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+
+    cache_dir().join("servers").join(name)
+}
+
 impl Clone for OSMTile {
     fn clone(&self) -> Self {
         Self {
@@ -51,4 +89,4 @@ impl Clone for OSMTile {
             z: self.z,
         }
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,138 @@
+// Draws a batch of tiles from one atlas page
+// Every tile quad carries the array layer holding its image, a tint and the time it appeared
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_world}
+#import bevy_pbr::mesh_view_bindings::{fog, globals, view}
+#import bevy_pbr::pbr_functions::apply_fog
+
+struct TileFilter {
+    brightness: f32,
+    grayscale: f32,     // 0 = full color, 1 = grayscale
+    night: f32,         // 0 = day, 1 = fully inverted night colors
+    fade_duration: f32, // Seconds a new tile takes to fade in
+};
+
+@group(2) @binding(0) var tile_texture: texture_2d_array<f32>;
+@group(2) @binding(1) var tile_sampler: sampler;
+struct TileGlobe {
+    tangent: vec4<f32>, // Latitude, longitude (radians), world X and Z of the point touching the map
+    radius: f32,
+    blend: f32,         // 0 = flat map, 1 = globe
+    world_size: f32,
+    origin: vec2<f32>,  // World X/Z at the render origin, vertices are relative to it
+};
+
+@group(2) @binding(2) var<uniform> tile_filter: TileFilter;
+@group(2) @binding(3) var<uniform> tile_globe: TileGlobe;
+
+const PI: f32 = 3.14159265358979;
+// Part of the map's height at its north and south edges over which the tiles fade out,
+// from about 82° latitude to the Web Mercator limit
+const POLAR_FADE: f32 = 0.08;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) layer: u32,
+    @location(3) tint: vec4<f32>,
+    @location(4) fade_start: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) layer: u32,
+    @location(2) tint: vec4<f32>,
+    @location(3) @interpolate(flat) fade_start: f32,
+    @location(4) world_position: vec3<f32>,
+    @location(5) map_z: f32,            // World Z from the map's north edge, before curving
+};
+
+// Position of a flat map point on the globe, which touches the map at the tangent point
+// with north along -Z and east along +X there, like the flat map
+fn globe_position(world: vec3<f32>) -> vec3<f32> {
+    // Web Mercator world coordinates back to latitude/longitude
+    let map = world.xz + tile_globe.origin;
+    let lon = map.x / tile_globe.world_size * 2.0 * PI - PI;
+    let lat = atan(sinh(PI * (1.0 - 2.0 * map.y / tile_globe.world_size)));
+    let normal = vec3<f32>(cos(lat) * cos(lon), cos(lat) * sin(lon), sin(lat));
+
+    // Local east/north/up axes at the tangent point
+    let lat0 = tile_globe.tangent.x;
+    let lon0 = tile_globe.tangent.y;
+    let east = vec3<f32>(-sin(lon0), cos(lon0), 0.0);
+    let north = vec3<f32>(-sin(lat0) * cos(lon0), -sin(lat0) * sin(lon0), cos(lat0));
+    let up = vec3<f32>(cos(lat0) * cos(lon0), cos(lat0) * sin(lon0), sin(lat0));
+
+    let local = vec3<f32>(dot(normal, east), dot(normal, up) - 1.0, -dot(normal, north)) * tile_globe.radius;
+    // Keep the small height offset that orders overlapping zoom levels
+    return vec3<f32>(tile_globe.tangent.z + local.x, local.y + world.y, tile_globe.tangent.w + local.z);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    var world = mesh_position_local_to_world(
+        get_world_from_local(vertex.instance_index),
+        vec4<f32>(vertex.position, 1.0),
+    );
+    out.map_z = world.z + tile_globe.origin.y;
+    if tile_globe.blend > 0.0 {
+        world = vec4<f32>(mix(world.xyz, globe_position(world.xyz), tile_globe.blend), 1.0);
+    }
+    out.clip_position = view.clip_from_world * world;
+    out.world_position = world.xyz;
+    out.uv = vertex.uv;
+    out.layer = vertex.layer;
+    out.tint = vertex.tint;
+    out.fade_start = vertex.fade_start;
+    return out;
+}
+
+// 4x4 ordered dither threshold for a pixel, in 0..1
+fn dither_threshold(frag_coord: vec2<f32>) -> f32 {
+    let bayer = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    let p = vec2<u32>(frag_coord) % 4u;
+    return (bayer[p.y * 4u + p.x] + 0.5) / 16.0;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSample(tile_texture, tile_sampler, in.uv, in.layer).rgb;
+
+    // Fade in by dithering, so tiles stay in the opaque pass and keep writing depth
+    // globals.time wraps around every hour, a negative age means the fade is long over
+    if tile_filter.fade_duration > 0.0 {
+        let age = globals.time - in.fade_start;
+        let opacity = select(clamp(age / tile_filter.fade_duration, 0.0, 1.0), 1.0, age < 0.0);
+        if opacity < dither_threshold(in.clip_position.xy) {
+            discard;
+        }
+    }
+
+    // Dither out towards the poles too, so the map doesn't end in a hard edge at the latitude limit
+    let edge = min(in.map_z, tile_globe.world_size - in.map_z) / (tile_globe.world_size * POLAR_FADE);
+    if edge < 1.0 && edge < dither_threshold(in.clip_position.xy) {
+        discard;
+    }
+
+    // The tint's alpha is its strength
+    color = mix(color, color * in.tint.rgb, in.tint.a);
+
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    color = mix(color, vec3<f32>(luminance), tile_filter.grayscale);
+
+    // Invert the lightness but keep the hue, then darken: light streets on a dark map
+    let night_color = (color + vec3<f32>(1.0 - 2.0 * luminance)) * 0.8;
+    color = mix(color, night_color, tile_filter.night);
+
+    color = clamp(color * tile_filter.brightness, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    // Distance fog from the camera's DistanceFog, hiding where the loaded tiles end
+    return apply_fog(fog, vec4<f32>(color, 1.0), in.world_position, view.world_position);
+}
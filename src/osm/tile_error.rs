@@ -0,0 +1,134 @@
+use std::error::Error;
+use std::fmt;
+use reqwest::StatusCode;
+
+/// Why a tile couldn't be loaded, which decides how the loader handles it:
+/// - timeouts, DNS and connection failures and server errors are retried with backoff
+/// - 429 makes the throttle back off the host, for as long as the server asks
+/// - 404 and undecodable imagery aren't retried, the next attempt would get the same
+/// - 403 isn't retried either, and a host that keeps answering it is disabled
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TileError {
+    Timeout,
+    Dns,         // The server's name couldn't be resolved
+    Connect,     // No connection to the server
+    Forbidden,   // 403, e.g. the server's usage policy blocks this client
+    RateLimited, // 429
+    NotFound,    // 404, the tile doesn't exist
+    Http,        // Any other error status
+    Decode,      // The response isn't an image
+    Other,       // Anything else, like a missing local file
+}
+
+impl TileError {
+    /// The kind of a failed tile load, taken from the error the tile source returned
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<TileError>() {
+            return *error;
+        }
+        if error.downcast_ref::<image::ImageError>().is_some() {
+            return TileError::Decode;
+        }
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(error) => Self::of_request(error),
+            None => TileError::Other,
+        }
+    }
+
+    /// The kind of an error status, None for statuses that aren't errors
+    pub fn of_status(status: StatusCode) -> Option<Self> {
+        match status {
+            StatusCode::FORBIDDEN => Some(TileError::Forbidden),
+            StatusCode::TOO_MANY_REQUESTS => Some(TileError::RateLimited),
+            StatusCode::NOT_FOUND => Some(TileError::NotFound),
+            status if status.is_client_error() || status.is_server_error() => Some(TileError::Http),
+            _ => None,
+        }
+    }
+
+    // reqwest only tells connection failures apart, a failed DNS lookup shows in the error's causes
+    fn of_request(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return TileError::Timeout;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if error.is_connect() {
+            let mut source = error.source();
+            while let Some(cause) = source {
+                if cause.to_string().contains("dns error") {
+                    return TileError::Dns;
+                }
+                source = cause.source();
+            }
+            return TileError::Connect;
+        }
+        if let Some(status) = error.status() {
+            return Self::of_status(status).unwrap_or(TileError::Other);
+        }
+        TileError::Other
+    }
+
+    /// Whether loading the tile again may succeed
+    pub fn retryable(self) -> bool {
+        !matches!(self, TileError::Forbidden | TileError::NotFound | TileError::Decode)
+    }
+
+    /// Short name for the debug HUD and the metrics
+    pub fn name(self) -> &'static str {
+        match self {
+            TileError::Timeout => "timeout",
+            TileError::Dns => "dns",
+            TileError::Connect => "connect",
+            TileError::Forbidden => "403",
+            TileError::RateLimited => "429",
+            TileError::NotFound => "404",
+            TileError::Http => "http",
+            TileError::Decode => "decode",
+            TileError::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            TileError::Timeout => "request timed out",
+            TileError::Dns => "DNS lookup failed",
+            TileError::Connect => "couldn't connect",
+            TileError::Forbidden => "HTTP 403 Forbidden",
+            TileError::RateLimited => "HTTP 429 Too Many Requests",
+            TileError::NotFound => "HTTP 404 Not Found",
+            TileError::Http => "HTTP error",
+            TileError::Decode => "not an image",
+            TileError::Other => "failed",
+        };
+        f.write_str(message)
+    }
+}
+
+impl Error for TileError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_classified() {
+        assert_eq!(TileError::of_status(StatusCode::NOT_FOUND), Some(TileError::NotFound));
+        assert_eq!(TileError::of_status(StatusCode::SERVICE_UNAVAILABLE), Some(TileError::Http));
+        assert_eq!(TileError::of_status(StatusCode::NOT_MODIFIED), None);
+
+        assert_eq!(TileError::of(&TileError::RateLimited.into()), TileError::RateLimited);
+        let decode = image::load_from_memory(b"<html>").unwrap_err();
+        assert_eq!(TileError::of(&decode.into()), TileError::Decode);
+        assert_eq!(TileError::of(&anyhow::anyhow!("missing file")), TileError::Other);
+    }
+
+    #[test]
+    fn missing_and_refused_tiles_are_not_retried() {
+        assert!(TileError::Timeout.retryable());
+        assert!(TileError::RateLimited.retryable());
+        assert!(!TileError::NotFound.retryable());
+        assert!(!TileError::Forbidden.retryable());
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::render::mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef};
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, VertexFormat,
+};
+use crate::shader_params::{TileFilter, TileGlobe};
+
+/// Shader drawing tile batches from an atlas page
+pub const TILE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x5b1d_7c3e_2f4a_4e8b_9a61_0d3c_8e27_f415);
+
+// High ids avoid collisions with Bevy's own attributes, see MeshVertexAttribute
+
+/// Array layer of the tile a vertex belongs to
+pub const ATTRIBUTE_TILE_LAYER: MeshVertexAttribute =
+    MeshVertexAttribute::new("TileLayer", 917_346_201, VertexFormat::Uint32);
+
+/// Time the tile a vertex belongs to appeared, for fading it in
+pub const ATTRIBUTE_TILE_FADE_START: MeshVertexAttribute =
+    MeshVertexAttribute::new("TileFadeStart", 917_346_202, VertexFormat::Float32);
+
+/// Material drawing tiles from an atlas page
+/// Per-tile values (array layer, tint, fade) come from the batch mesh's vertices,
+/// so one material (and draw call) covers every tile of a zoom level on the page.
+/// Tiles stay opaque and fade in by dithering, so they never need sorting
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct TileMaterial {
+    #[texture(0, dimension = "2d_array")]
+    #[sampler(1)]
+    pub texture: Handle<Image>,
+    #[uniform(2)]
+    pub filter: TileFilter,
+    #[uniform(3)]
+    pub globe: TileGlobe,
+}
+
+impl Material for TileMaterial {
+    fn vertex_shader() -> ShaderRef {
+        TILE_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        TILE_SHADER_HANDLE.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
+            ATTRIBUTE_TILE_LAYER.at_shader_location(2),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(3),
+            ATTRIBUTE_TILE_FADE_START.at_shader_location(4),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        // Tiles are visible from below as well
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
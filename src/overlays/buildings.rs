@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::collections::HashMap;
+use crate::osm::{tag_filter, OverpassData, OverpassQuery, TileId};
+use crate::utils::geo::{lat_lon_to_world, meters_per_world_unit, tile_world_origin};
+
+// Height of a floor, and what a roof adds on top of the floors
+const LEVEL_HEIGHT_M: f64 = 3.0;
+const ROOF_HEIGHT_M: f64 = 1.0;
+
+/// Overpass query for the buildings of a tile
+pub fn building_query(id: TileId) -> OverpassQuery {
+    OverpassQuery::for_tile(id).ways(&tag_filter("building", None))
+}
+
+/// Height of a building in meters: its `height` tag, else its `building:levels`, else one made
+/// up from its id, kind and footprint area
+pub fn building_height_m(id: i64, tags: &HashMap<String, String>, area_m2: f64) -> f64 {
+    if let Some(height) = tags.get("height").and_then(|value| parse_meters(value)) {
+        return height;
+    }
+    if let Some(levels) = tags.get("building:levels").and_then(|value| value.trim().parse::<f64>().ok()) {
+        return levels.max(1.0) * LEVEL_HEIGHT_M + ROOF_HEIGHT_M;
+    }
+    let kind = tags.get("building").map_or("yes", String::as_str);
+    procedural_height_m(id, kind, area_m2)
+}
+
+/// A plausible height for a building OpenStreetMap has no height of
+/// Most buildings are only a footprint, and a whole city of them at one height looks like a slab;
+/// the height is picked from the range of the building's kind, larger footprints rising higher,
+/// and seeded by its id so it stays the same on every run
+pub fn procedural_height_m(id: i64, kind: &str, area_m2: f64) -> f64 {
+    let (chance, jitter) = seeded_fractions(id);
+    // Halls and churches are one tall space, not floors
+    match kind {
+        "industrial" | "warehouse" | "manufacture" | "hangar" | "barn" => return 6.0 + chance * 6.0,
+        "church" | "cathedral" | "chapel" | "mosque" | "temple" | "synagogue" => return 12.0 + chance * 13.0,
+        _ => {}
+    }
+
+    let (min_levels, max_levels) = match kind {
+        "garage" | "garages" | "shed" | "carport" | "hut" | "kiosk" | "roof" | "cabin" => (1.0, 1.0),
+        "house" | "detached" | "semidetached_house" | "terrace" | "bungalow" | "farm" => (1.0, 3.0),
+        "apartments" | "dormitory" | "hotel" => (3.0, 9.0),
+        "office" | "commercial" | "hospital" | "university" => (2.0, 8.0),
+        "retail" | "school" | "public" | "civic" | "train_station" => (1.0, 4.0),
+        _ => (1.0, 5.0),
+    };
+    // 0 for a shed-sized footprint up to 1 from about a city block's
+    let size = ((area_m2.max(1.0) / 50.0).log10() / 2.0).clamp(0.0, 1.0);
+    let levels = (min_levels + (0.5 * chance + 0.5 * size) * (max_levels - min_levels)).round();
+    levels * LEVEL_HEIGHT_M + ROOF_HEIGHT_M * jitter
+}
+
+// Two fractions in [0, 1) from an OSM id (splitmix64)
+fn seeded_fractions(id: i64) -> (f64, f64) {
+    let mut state = id as u64;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        (z ^ (z >> 31)) as f64 / (u64::MAX as f64 + 1.0)
+    };
+    (next(), next())
+}
+
+// A length tag in meters ("12", "12 m", "12.5m"); feet are rare enough to be left out
+fn parse_meters(value: &str) -> Option<f64> {
+    let number = value.trim().trim_end_matches('m').trim();
+    number.parse::<f64>().ok().filter(|height| *height > 0.0)
+}
+
+/// One mesh of the buildings of a tile, extruded from their footprints to their heights, with
+/// the vertices relative to the tile's north-west corner
+/// A building is drawn by the tile holding its middle, so buildings on a tile edge are drawn once
+pub fn building_mesh(data: &OverpassData, id: TileId) -> Option<Mesh> {
+    let bounds = id.bounds();
+    let origin = tile_world_origin(id);
+    let meters = meters_per_world_unit((bounds.north + bounds.south) / 2.0);
+
+    let mut mesh = ExtrudedMesh::default();
+    for way in &data.ways {
+        let ring = &way.geometry;
+        if ring.len() < 4 || ring.first() != ring.last() || way.tags.get("building").is_some_and(|value| value == "no") {
+            continue;
+        }
+        let count = (ring.len() - 1) as f64;
+        let middle = ring[1..].iter().fold((0.0, 0.0), |sum, &(lat, lon)| (sum.0 + lat / count, sum.1 + lon / count));
+        if !(bounds.south..bounds.north).contains(&middle.0) || !(bounds.west..bounds.east).contains(&middle.1) {
+            continue;
+        }
+
+        let footprint: Vec<Vec2> = ring[1..]
+            .iter()
+            .map(|&(lat, lon)| (lat_lon_to_world(lat, lon) - origin).as_vec2())
+            .collect();
+        let area_m2 = signed_area(&footprint).abs() as f64 * meters * meters;
+        let height = building_height_m(way.id, &way.tags, area_m2) / meters;
+        mesh.add(&footprint, height as f32);
+    }
+    (!mesh.indices.is_empty()).then(|| mesh.build())
+}
+
+// Walls and flat roofs of buildings, each face with its own vertices so the edges stay sharp
+#[derive(Default)]
+struct ExtrudedMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl ExtrudedMesh {
+    // Add a footprint on the XZ plane (x, z as a Vec2) raised to `height`
+    fn add(&mut self, footprint: &[Vec2], height: f32) {
+        let mut points = footprint.to_vec();
+        if signed_area(&points) < 0.0 {
+            points.reverse();
+        }
+
+        for (i, &start) in points.iter().enumerate() {
+            let end = points[(i + 1) % points.len()];
+            let edge = end - start;
+            if edge.length_squared() == 0.0 {
+                continue;
+            }
+            let outward = Vec2::new(edge.y, -edge.x).normalize();
+            let first = self.positions.len() as u32;
+            for (point, y) in [(start, 0.0), (end, 0.0), (end, height), (start, height)] {
+                self.positions.push([point.x, y, point.y]);
+                self.normals.push([outward.x, 0.0, outward.y]);
+            }
+            self.indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        let first = self.positions.len() as u32;
+        for point in &points {
+            self.positions.push([point.x, height, point.y]);
+            self.normals.push([0.0, 1.0, 0.0]);
+        }
+        self.indices.extend(triangulate(&points).into_iter().map(|i| first + i as u32));
+    }
+
+    fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_indices(Indices::U32(self.indices));
+        mesh
+    }
+}
+
+// Area of a polygon, positive when its points run counterclockwise
+fn signed_area(points: &[Vec2]) -> f32 {
+    let sum: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum();
+    sum / 2.0
+}
+
+// Triangles covering a counterclockwise polygon without holes, by cutting off ears
+// Footprints are often concave (L-shaped, U-shaped), so a fan won't do
+fn triangulate(points: &[Vec2]) -> Vec<usize> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2) * 3);
+    let mut misses = 0;
+    let mut i = 0;
+    while remaining.len() > 3 && misses < remaining.len() {
+        let n = remaining.len();
+        let (prev, current, next) = (remaining[(i + n - 1) % n], remaining[i % n], remaining[(i + 1) % n]);
+        let (a, b, c) = (points[prev], points[current], points[next]);
+        let convex = (b - a).perp_dot(c - b) > 0.0;
+        let empty = remaining
+            .iter()
+            .filter(|&&other| other != prev && other != current && other != next)
+            .all(|&other| !in_triangle(points[other], a, b, c));
+        if convex && empty {
+            triangles.extend_from_slice(&[prev, current, next]);
+            remaining.remove(i % n);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+        i %= remaining.len();
+    }
+    // Self-intersecting footprints run out of ears; their roof is left partly open
+    if remaining.len() == 3 {
+        triangles.extend_from_slice(&remaining);
+    }
+    triangles
+}
+
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let side = |from: Vec2, to: Vec2| (to - from).perp_dot(point - from) >= 0.0;
+    side(a, b) && side(b, c) && side(c, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heights_come_from_tags_before_being_made_up() {
+        let tags = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert_eq!(building_height_m(1, &tags(&[("building", "yes"), ("height", "21.5 m")]), 100.0), 21.5);
+        assert_eq!(building_height_m(1, &tags(&[("building", "yes"), ("building:levels", "4")]), 100.0), 13.0);
+
+        // Made up heights stay the same for a building and differ between buildings
+        let made_up = building_height_m(42, &tags(&[("building", "apartments")]), 800.0);
+        assert_eq!(made_up, procedural_height_m(42, "apartments", 800.0));
+        let heights: Vec<f64> = (0..50).map(|id| procedural_height_m(id, "yes", 200.0)).collect();
+        assert!(heights.iter().any(|height| *height != heights[0]));
+
+        // Sheds stay low, a large apartment block rises above a small one
+        assert!(procedural_height_m(7, "shed", 20.0) < 5.0);
+        let small: f64 = (0..50).map(|id| procedural_height_m(id, "apartments", 100.0)).sum();
+        let large: f64 = (0..50).map(|id| procedural_height_m(id, "apartments", 5000.0)).sum();
+        assert!(large > small);
+    }
+
+    #[test]
+    fn concave_footprints_are_covered() {
+        // L-shaped, counterclockwise
+        let points = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        assert_eq!(signed_area(&points), 3.0);
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 12);
+        let area: f32 = triangles.chunks(3).map(|t| signed_area(&[points[t[0]], points[t[1]], points[t[2]]])).sum();
+        assert_eq!(area, 3.0);
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::anyhow;
+use serde_json::Value;
+use crate::overlays::marker_import::is_marker_file;
+
+/// What a dropped CSV or GeoJSON file is shown as; every file goes to exactly one of these
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropTarget {
+    Markers,      // While marker import is on, see MarkerImport
+    Heatmap,      // Weighted points, added as an imagery layer
+    StreetLabels, // Named roads, labelled on the ground
+}
+
+/// The contents of a dropped data file, read and parsed once for whichever target gets it
+#[derive(Clone, Debug, PartialEq)]
+pub enum DroppedData {
+    Csv(String),
+    GeoJson(Value),
+}
+
+/// Whether a dropped file is a data file for route_dropped_data, told by its extension
+pub fn is_data_file(name: &str) -> bool {
+    is_marker_file(name)
+}
+
+/// Parse a dropped data file and pick the one target it goes to, None when nothing shows it
+/// While marker import is on every data file becomes markers. Otherwise CSV files are heatmap
+/// points, and GeoJSON files go by what most of their features are: Point features make a
+/// heatmap, named lines become street labels. Plain .json files are only read as markers
+pub fn route_dropped_data(name: &str, text: String, marker_import: bool) -> Result<Option<(DropTarget, DroppedData)>, anyhow::Error> {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    let data = if extension == "csv" { DroppedData::Csv(text) } else { DroppedData::GeoJson(serde_json::from_str(&text)?) };
+    if marker_import {
+        return Ok(Some((DropTarget::Markers, data)));
+    }
+
+    let target = match (&data, extension.as_str()) {
+        (DroppedData::Csv(_), _) => Some(DropTarget::Heatmap),
+        (DroppedData::GeoJson(json), "geojson") => {
+            let features = json["features"].as_array().ok_or_else(|| anyhow!("Not a GeoJSON FeatureCollection"))?;
+            let count = |types: &[&str], named: bool| {
+                features
+                    .iter()
+                    .filter(|feature| types.iter().any(|kind| feature["geometry"]["type"] == *kind))
+                    .filter(|feature| !named || feature["properties"]["name"].is_string())
+                    .count()
+            };
+            let (points, lines) = (count(&["Point"], false), count(&["LineString", "MultiLineString"], true));
+            if points > lines {
+                Some(DropTarget::Heatmap)
+            } else if lines > 0 {
+                Some(DropTarget::StreetLabels)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    Ok(target.map(|target| (target, data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_files_go_to_one_target() {
+        let feature = |kind: &str, coordinates: &str, name: &str| {
+            format!(r#"{{"type": "Feature", "geometry": {{"type": "{}", "coordinates": {}}}, "properties": {{"name": "{}"}}}}"#, kind, coordinates, name)
+        };
+        let collection = |features: &[String]| format!(r#"{{"type": "FeatureCollection", "features": [{}]}}"#, features.join(","));
+        let point = feature("Point", "[6.5665, 53.2194]", "Vismarkt");
+        let road = feature("LineString", "[[6.56, 53.21], [6.57, 53.22]]", "Herestraat");
+        let target = |name: &str, text: String, marker_import: bool| {
+            route_dropped_data(name, text, marker_import).unwrap().map(|(target, _)| target)
+        };
+
+        let mixed = collection(&[point.clone(), road.clone(), road.clone()]);
+        assert_eq!(target("city.geojson", mixed.clone(), false), Some(DropTarget::StreetLabels));
+        assert_eq!(target("city.geojson", mixed.clone(), true), Some(DropTarget::Markers));
+        assert_eq!(target("city.geojson", collection(&[point.clone(), point.clone(), road]), false), Some(DropTarget::Heatmap));
+        assert_eq!(target("counts.CSV", "lat,lon\n53.2,6.5\n".to_string(), false), Some(DropTarget::Heatmap));
+
+        // Unnamed lines have nothing to label, and .json files are only markers
+        let unnamed = feature("LineString", "[[6.56, 53.21], [6.57, 53.22]]", "").replace(r#""name": """#, r#""ref": """#);
+        assert_eq!(target("lines.geojson", collection(&[unnamed]), false), None);
+        assert_eq!(target("city.json", mixed, false), None);
+        assert!(route_dropped_data("broken.geojson", "{".to_string(), false).is_err());
+
+        let (_, data) = route_dropped_data("points.geojson", collection(&[point]), false).unwrap().unwrap();
+        assert!(matches!(data, DroppedData::GeoJson(json) if json["features"][0]["properties"]["name"] == "Vismarkt"));
+        assert!(is_data_file("city.geojson") && !is_data_file("track.gpx"));
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use roxmltree::{Document, Node};
+use std::fs;
+use std::path::Path;
+use crate::utils::geo::{lat_lon_to_world, prepare_line, AntimeridianMode, GeoBounds};
+
+/// A single point of a GPX track or route
+#[derive(Clone, Copy, Debug)]
+pub struct GpxPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// A parsed GPX file: every <trkseg> and <rte> becomes its own segment
+#[derive(Clone, Debug, Default)]
+pub struct GpxTrack {
+    pub name: Option<String>,
+    pub segments: Vec<Vec<GpxPoint>>,
+}
+
+/// Visual style of a rendered GPX line
+#[derive(Clone, Debug)]
+pub struct GpxStyle {
+    pub width: f32,            // Line width in world units
+    pub color: Color,
+    pub elevation_offset: f32, // Height above the tile plane to avoid z-fighting
+}
+
+impl Default for GpxStyle {
+    fn default() -> Self {
+        Self {
+            width: 0.002, // Roughly 10 meters at mid latitudes
+            color: Color::srgb(1.0, 0.3, 0.0),
+            elevation_offset: 0.02, // Above both focus and background tiles
+        }
+    }
+}
+
+/// Marker component for entities spawned from a GPX file
+#[derive(Component)]
+pub struct GpxOverlay;
+
+impl GpxTrack {
+    /// Total number of points over all segments
+    pub fn point_count(&self) -> usize {
+        self.segments.iter().map(|s| s.len()).sum()
+    }
+
+    /// Bounding box of all points, crossing the antimeridian when that is narrower
+    pub fn bounds(&self) -> Option<GeoBounds> {
+        GeoBounds::from_points(self.segments.iter().flatten().map(|p| (p.lat, p.lon)))
+    }
+
+    /// Split or wrap segments that cross the antimeridian so they don't span the whole world
+    pub fn handle_antimeridian(&mut self, mode: AntimeridianMode) {
+        self.segments = self
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                let line: Vec<(f64, f64)> = segment.iter().map(|p| (p.lat, p.lon)).collect();
+                prepare_line(&line, mode)
+            })
+            .map(|line| line.into_iter().map(|(lat, lon)| GpxPoint { lat, lon }).collect())
+            .collect();
+    }
+}
+
+/// Load and parse a GPX file from disk
+pub fn load_gpx_file(path: &Path, mode: AntimeridianMode) -> Result<GpxTrack, anyhow::Error> {
+    let contents = fs::read_to_string(path)?;
+    let mut track = parse_gpx(&contents)?;
+    track.handle_antimeridian(mode);
+
+    // Fall back to the file name when the GPX has no <name> element
+    if track.name.is_none() {
+        track.name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+    }
+
+    Ok(track)
+}
+
+/// Parse the contents of a GPX document
+/// Only the parts needed for rendering are read: track/route points and the name
+/// Tags are matched by their local name, so GPX 1.0, 1.1 and namespace-prefixed files all work
+pub fn parse_gpx(contents: &str) -> Result<GpxTrack, anyhow::Error> {
+    let document = Document::parse(contents)?;
+    let mut track = GpxTrack {
+        name: element_text(&document, "name"),
+        segments: Vec::new(),
+    };
+
+    // Tracks are split into segments, routes are a single list of points
+    for (container, point_tag) in [("trkseg", "trkpt"), ("rte", "rtept")] {
+        for block in elements(&document, container) {
+            let points: Vec<GpxPoint> = block
+                .children()
+                .filter(|node| node.tag_name().name() == point_tag)
+                .filter_map(parse_point)
+                .collect();
+
+            if points.len() >= 2 {
+                track.segments.push(points);
+            }
+        }
+    }
+
+    if track.segments.is_empty() {
+        return Err(anyhow::anyhow!("GPX file contains no track or route with at least two points"));
+    }
+
+    Ok(track)
+}
+
+// Parse the position of a single <trkpt>/<rtept> element
+fn parse_point(element: Node) -> Option<GpxPoint> {
+    let lat = element.attribute("lat")?.trim().parse().ok()?;
+    let lon = element.attribute("lon")?.trim().parse().ok()?;
+
+    Some(GpxPoint { lat, lon })
+}
+
+// All elements with the given local name, in document order
+fn elements<'a, 'input>(document: &'a Document<'input>, tag: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    document.descendants().filter(move |node| node.tag_name().name() == tag)
+}
+
+// Text content of the first element with the given local name, None when it is empty
+fn element_text(document: &Document, tag: &str) -> Option<String> {
+    let element = elements(document, tag).next()?;
+    let text: String = element.descendants().filter(|node| node.is_text()).filter_map(|node| node.text()).collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Build a flat ribbon mesh following the given points on the XZ plane
+pub fn create_polyline_mesh(points: &[Vec3], width: f32) -> Mesh {
+    create_polylines_mesh(&[points], width)
+}
+
+/// Build one mesh of flat ribbons following each of the given lines on the XZ plane, so many
+/// lines of the same look are drawn at once
+pub fn create_polylines_mesh(lines: &[&[Vec3]], width: f32) -> Mesh {
+    let half_width = width / 2.0;
+    let point_count: usize = lines.iter().map(|points| points.len()).sum();
+    let mut positions = Vec::with_capacity(point_count * 2);
+    let mut normals = Vec::with_capacity(point_count * 2);
+    let mut uvs = Vec::with_capacity(point_count * 2);
+    let mut indices = Vec::with_capacity(point_count * 6);
+
+    for points in lines {
+        let first = positions.len() as u32;
+        for (i, point) in points.iter().enumerate() {
+            // Direction at this point: average of the incoming and outgoing segments
+            let prev = points[i.saturating_sub(1)];
+            let next = points[(i + 1).min(points.len() - 1)];
+            let direction = Vec3::new(next.x - prev.x, 0.0, next.z - prev.z).normalize_or_zero();
+
+            // Perpendicular on the ground plane
+            let side = Vec3::new(-direction.z, 0.0, direction.x) * half_width;
+
+            positions.push((*point - side).to_array());
+            positions.push((*point + side).to_array());
+            normals.push([0.0, 1.0, 0.0]);
+            normals.push([0.0, 1.0, 0.0]);
+
+            let v = i as f32 / (points.len() - 1).max(1) as f32;
+            uvs.push([0.0, v]);
+            uvs.push([1.0, v]);
+
+            if i + 1 < points.len() {
+                let base = first + (i * 2) as u32;
+                indices.extend_from_slice(&[base, base + 2, base + 1, base + 1, base + 2, base + 3]);
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Spawn a GPX track as polyline meshes above the tiles, one entity per segment
+/// Returns the parent entity holding all segments
+pub fn spawn_gpx_track(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    track: &GpxTrack,
+    style: &GpxStyle,
+) -> Entity {
+    let name = track.name.clone().unwrap_or_else(|| "GPX track".to_string());
+
+    let material = materials.add(StandardMaterial {
+        base_color: style.color,
+        unlit: true, // Overlays should look the same regardless of lighting
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+
+    let parent = commands
+        .spawn((
+            Transform::default(),
+            Visibility::default(),
+            Name::new(format!("GPX {}", name)),
+            GpxOverlay,
+        ))
+        .id();
+
+    for segment in &track.segments {
+        // Build vertices relative to the first point so they keep full f32 precision,
+        // the large absolute offset goes into the Transform instead
+        let anchor = lat_lon_to_world(segment[0].lat, segment[0].lon);
+        let points: Vec<Vec3> = segment
+            .iter()
+            .map(|p| {
+                let local = (lat_lon_to_world(p.lat, p.lon) - anchor).as_vec2();
+                Vec3::new(local.x, 0.0, local.y)
+            })
+            .collect();
+
+        let mesh = meshes.add(create_polyline_mesh(&points, style.width));
+        let child = commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material.clone()),
+                Transform::from_xyz(anchor.x as f32, style.elevation_offset, anchor.y as f32),
+            ))
+            .id();
+        commands.entity(parent).add_child(child);
+    }
+
+    parent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_gpx_files_are_read() {
+        // Attributes on their own lines and spaced out, entities, CDATA and a namespace prefix
+        let gpx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx:gpx xmlns:gpx="http://www.topografix.com/GPX/1/1" version="1.1">
+  <gpx:trk>
+    <gpx:name><![CDATA[Noorderplantsoen & back]]></gpx:name>
+    <gpx:trkseg>
+      <gpx:trkpt
+        lat="53.2246"
+        lon="6.5566"><gpx:ele>2.1</gpx:ele></gpx:trkpt>
+      <gpx:trkpt	lat = '53.2261'	lon = "6.5582"/>
+      <gpx:trkpt lat="not a number" lon="6.56"/>
+    </gpx:trkseg>
+  </gpx:trk>
+  <rte xmlns="http://www.topografix.com/GPX/1/1">
+    <name>Fietsroute &amp; terug</name>
+    <rtept lat="53.21" lon="6.56"/><rtept lat="53.22" lon="6.57"/>
+  </rte>
+</gpx:gpx>"#;
+        let track = parse_gpx(gpx).unwrap();
+        assert_eq!(track.name.as_deref(), Some("Noorderplantsoen & back"));
+        let segments: Vec<Vec<(f64, f64)>> =
+            track.segments.iter().map(|segment| segment.iter().map(|p| (p.lat, p.lon)).collect()).collect();
+        assert_eq!(segments, vec![vec![(53.2246, 6.5566), (53.2261, 6.5582)], vec![(53.21, 6.56), (53.22, 6.57)]]);
+
+        assert!(parse_gpx("<gpx><trk><trkseg><trkpt lat=\"53.2\" lon=\"6.5\"/></trkseg></trk></gpx>").is_err());
+        assert!(parse_gpx("<gpx><trk>").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{anyhow, bail};
+use serde_json::Value;
+use crate::overlays::dropped_data::DroppedData;
+
+/// A marker read from a file: position and name
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedMarker {
+    pub lat: f64,
+    pub lon: f64,
+    pub name: Option<String>,
+}
+
+/// Columns of a CSV file markers are read from, from the [markers] section of the settings;
+/// the name column is also the GeoJSON property the name is read from
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkerColumns {
+    pub lat: String,
+    pub lon: String,
+    pub name: String,
+}
+
+impl Default for MarkerColumns {
+    fn default() -> Self {
+        Self {
+            lat: "lat".to_string(),
+            lon: "lon".to_string(),
+            name: "name".to_string(),
+        }
+    }
+}
+
+/// Whether markers can be read from a file, told by its extension
+pub fn is_marker_file(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    matches!(extension.as_str(), "csv" | "geojson" | "json")
+}
+
+/// Read the markers of a dropped CSV or GeoJSON file
+pub fn parse_marker_file(name: &str, data: &DroppedData, columns: &MarkerColumns) -> Result<Vec<ImportedMarker>, anyhow::Error> {
+    let markers = match data {
+        DroppedData::Csv(text) => parse_marker_csv(text, columns)?,
+        DroppedData::GeoJson(json) => parse_marker_geojson(json, &columns.name)?,
+    };
+    if markers.is_empty() {
+        bail!("No markers in {}", name);
+    }
+    Ok(markers)
+}
+
+/// Markers of a CSV file with a header row naming the columns
+/// Header names are matched without regard to case; fields may be quoted, but quoted fields
+/// holding commas aren't supported
+pub fn parse_marker_csv(text: &str, columns: &MarkerColumns) -> Result<Vec<ImportedMarker>, anyhow::Error> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<String> = lines
+        .next()
+        .ok_or_else(|| anyhow!("Empty CSV file"))?
+        .split(',')
+        .map(|name| name.trim().trim_matches('"').to_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| *column == name.to_lowercase());
+    let (Some(lat), Some(lon)) = (column(&columns.lat), column(&columns.lon)) else {
+        bail!("The CSV header has no {} and {} columns", columns.lat, columns.lon);
+    };
+    let name = column(&columns.name);
+
+    Ok(lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim().trim_matches('"')).collect();
+            let number = |index: usize| fields.get(index).and_then(|field| field.parse::<f64>().ok());
+            // Rows without a readable position are skipped
+            let (lat, lon) = (number(lat)?, number(lon)?);
+            let name = name.and_then(|index| fields.get(index)).filter(|name| !name.is_empty()).map(|name| name.to_string());
+            Some(ImportedMarker { lat, lon, name })
+        })
+        .collect())
+}
+
+/// Markers of the Point features of a GeoJSON file, named by the `name_property` property
+pub fn parse_marker_geojson(json: &Value, name_property: &str) -> Result<Vec<ImportedMarker>, anyhow::Error> {
+    let features = json["features"].as_array().ok_or_else(|| anyhow!("Not a GeoJSON FeatureCollection"))?;
+
+    Ok(features
+        .iter()
+        .filter(|feature| feature["geometry"]["type"] == "Point")
+        .filter_map(|feature| {
+            let coordinates = &feature["geometry"]["coordinates"];
+            let (lon, lat) = (coordinates.get(0)?.as_f64()?, coordinates.get(1)?.as_f64()?);
+            let name = match &feature["properties"][name_property] {
+                Value::String(name) => Some(name.clone()),
+                Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            };
+            Some(ImportedMarker { lat, lon, name })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_are_read_from_csv_and_geojson() {
+        let columns = MarkerColumns { lat: "Y".to_string(), lon: "X".to_string(), name: "Title".to_string() };
+        let csv = "id,title,y,x\n1,\"Vismarkt\",53.2194,6.5665\n2,,53.2406,6.5349\n3,Nowhere,,\n";
+        assert_eq!(
+            parse_marker_csv(csv, &columns).unwrap(),
+            vec![
+                ImportedMarker { lat: 53.2194, lon: 6.5665, name: Some("Vismarkt".to_string()) },
+                ImportedMarker { lat: 53.2406, lon: 6.5349, name: None },
+            ]
+        );
+        assert!(parse_marker_csv(csv, &MarkerColumns::default()).is_err());
+
+        let geojson = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [6.5665, 53.2194]}, "properties": {"name": "Vismarkt"}},
+            {"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[6.5, 53.2], [6.6, 53.3]]}, "properties": {}}
+        ]}"#;
+        let geojson = DroppedData::GeoJson(serde_json::from_str(geojson).unwrap());
+        let markers = parse_marker_file("markets.geojson", &geojson, &MarkerColumns::default()).unwrap();
+        assert_eq!(markers, vec![ImportedMarker { lat: 53.2194, lon: 6.5665, name: Some("Vismarkt".to_string()) }]);
+        assert!(is_marker_file("Markets.CSV"));
+        assert!(!is_marker_file("track.gpx"));
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use std::collections::BTreeMap;
+
+/// Markers close together on screen, shown as one badge with their count
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenCluster {
+    pub position: Vec2,      // Middle of the members on screen
+    pub members: Vec<usize>, // Indices of the clustered points
+}
+
+/// Group screen positions by squares of `cell_px` pixels
+/// A grid keeps it linear in the number of markers, which matters with thousands loaded from a
+/// file; clusters come out sorted by their cell, so they don't swap places between frames
+pub fn cluster_screen_points(points: &[Vec2], cell_px: f32) -> Vec<ScreenCluster> {
+    let mut cells: BTreeMap<(i32, i32), Vec<usize>> = BTreeMap::new();
+    for (i, point) in points.iter().enumerate() {
+        let cell = (point.x / cell_px).floor() as i32;
+        let row = (point.y / cell_px).floor() as i32;
+        cells.entry((row, cell)).or_default().push(i);
+    }
+
+    cells
+        .into_values()
+        .map(|members| {
+            let sum: Vec2 = members.iter().map(|&i| points[i]).sum();
+            ScreenCluster { position: sum / members.len() as f32, members }
+        })
+        .collect()
+}
+
+/// White pin icon, tinted with the marker's color when drawn: a round head with a hole,
+/// pointing down at the marker's position from the bottom middle
+pub fn pin_image() -> Image {
+    const WIDTH: u32 = 32;
+    const HEIGHT: u32 = 48;
+    let head = Vec2::new(16.0, 16.0);
+    let (radius, hole) = (14.0, 5.0);
+
+    let mut data = Vec::with_capacity((WIDTH * HEIGHT * 4) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let point = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let from_head = point.distance(head);
+            // Below the head the pin narrows to its tip
+            let half_width = radius * (1.0 - (point.y - head.y) / (HEIGHT as f32 - head.y)).clamp(0.0, 1.0);
+            let inside = from_head <= radius || (point.y > head.y && (point.x - head.x).abs() <= half_width);
+            let pixel = if !inside {
+                [0, 0, 0, 0]
+            } else if from_head <= hole {
+                [90, 90, 90, 255]
+            } else {
+                [255, 255, 255, 255]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    Image::new(
+        Extent3d { width: WIDTH, height: HEIGHT, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_points_share_a_cluster() {
+        let points = [Vec2::new(10.0, 10.0), Vec2::new(30.0, 20.0), Vec2::new(200.0, 10.0), Vec2::new(12.0, 14.0)];
+        let clusters = cluster_screen_points(&points, 48.0);
+        assert_eq!(
+            clusters,
+            vec![
+                ScreenCluster { position: Vec2::new(52.0 / 3.0, 44.0 / 3.0), members: vec![0, 1, 3] },
+                ScreenCluster { position: Vec2::new(200.0, 10.0), members: vec![2] },
+            ]
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod buildings;
+pub mod dropped_data;
+pub mod gpx;
+pub mod marker_import;
+pub mod markers;
+pub mod roads;
+pub mod routing;
+pub mod street_labels;
+pub mod street_lamps;
+pub mod weather;
+
+// Overlays are imported directly where needed
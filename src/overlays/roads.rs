@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use crate::osm::{tag_filter, OverpassData, OverpassQuery, TileBounds, TileId};
+use crate::overlays::gpx::create_polylines_mesh;
+use crate::utils::geo::{lat_lon_to_world, meters_per_world_unit, tile_world_origin};
+
+/// Kinds of road drawn, from the OpenStreetMap `highway` tag, minor roads first so the major
+/// roads are drawn over them where they meet
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RoadClass {
+    Path,
+    Service,
+    Minor,
+    Tertiary,
+    Secondary,
+    Primary,
+    Trunk,
+    Motorway,
+}
+
+impl RoadClass {
+    /// Class of a `highway` value; values that aren't roads in use (platforms, roads under
+    /// construction, proposed roads) have none
+    pub fn from_highway(value: &str) -> Option<Self> {
+        Some(match value {
+            "motorway" | "motorway_link" => Self::Motorway,
+            "trunk" | "trunk_link" => Self::Trunk,
+            "primary" | "primary_link" => Self::Primary,
+            "secondary" | "secondary_link" => Self::Secondary,
+            "tertiary" | "tertiary_link" => Self::Tertiary,
+            "residential" | "unclassified" | "living_street" | "road" => Self::Minor,
+            "service" | "pedestrian" | "track" => Self::Service,
+            "footway" | "cycleway" | "path" | "steps" | "bridleway" => Self::Path,
+            _ => return None,
+        })
+    }
+
+    /// Typical width of the road on the ground
+    pub fn width_m(self) -> f64 {
+        match self {
+            Self::Motorway => 16.0,
+            Self::Trunk => 14.0,
+            Self::Primary => 12.0,
+            Self::Secondary => 10.0,
+            Self::Tertiary => 9.0,
+            Self::Minor => 7.0,
+            Self::Service => 4.5,
+            Self::Path => 2.0,
+        }
+    }
+
+    /// Color of the road, after the OpenStreetMap map style so the ribbons match the tiles
+    pub fn color(self) -> Color {
+        match self {
+            Self::Motorway => Color::srgb_u8(0xe8, 0x92, 0xa2),
+            Self::Trunk => Color::srgb_u8(0xf9, 0xb2, 0x9c),
+            Self::Primary => Color::srgb_u8(0xfc, 0xd6, 0xa4),
+            Self::Secondary => Color::srgb_u8(0xf7, 0xfa, 0xbf),
+            Self::Tertiary | Self::Minor | Self::Service => Color::WHITE,
+            Self::Path => Color::srgb_u8(0xfa, 0x80, 0x72),
+        }
+    }
+}
+
+/// Overpass query for the roads of a tile
+pub fn road_query(id: TileId) -> OverpassQuery {
+    OverpassQuery::for_tile(id).ways(&tag_filter("highway", None))
+}
+
+/// Ribbons of the roads within a tile, one mesh per road class, with the vertices relative to
+/// the tile's north-west corner
+/// Roads are cut at the tile's edges, so a road crossing several tiles is drawn once, in parts;
+/// tunnels and pedestrian areas (polygons, not lines) are left out
+pub fn road_meshes(data: &OverpassData, id: TileId) -> Vec<(RoadClass, Mesh)> {
+    let bounds = id.bounds();
+    let origin = tile_world_origin(id);
+    let meters = meters_per_world_unit((bounds.north + bounds.south) / 2.0);
+
+    let mut lines: BTreeMap<RoadClass, Vec<Vec<Vec3>>> = BTreeMap::new();
+    for way in &data.ways {
+        let Some(class) = way.tags.get("highway").and_then(|value| RoadClass::from_highway(value)) else {
+            continue;
+        };
+        let tag_is = |key: &str| way.tags.get(key).is_some_and(|value| value != "no");
+        if tag_is("tunnel") || tag_is("area") {
+            continue;
+        }
+
+        for piece in clip_to_bounds(&way.geometry, &bounds) {
+            let points = piece
+                .iter()
+                .map(|&(lat, lon)| {
+                    let offset = lat_lon_to_world(lat, lon) - origin;
+                    Vec3::new(offset.x as f32, 0.0, offset.y as f32)
+                })
+                .collect();
+            lines.entry(class).or_default().push(points);
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(class, lines)| {
+            let lines: Vec<&[Vec3]> = lines.iter().map(Vec::as_slice).collect();
+            (class, create_polylines_mesh(&lines, (class.width_m() / meters) as f32))
+        })
+        .collect()
+}
+
+/// The parts of a (lat, lon) line within the bounds
+pub fn clip_to_bounds(points: &[(f64, f64)], bounds: &TileBounds) -> Vec<Vec<(f64, f64)>> {
+    let mut pieces = Vec::new();
+    let mut piece: Vec<(f64, f64)> = Vec::new();
+    for segment in points.windows(2) {
+        let Some((start, end)) = clip_segment(segment[0], segment[1], bounds) else {
+            if piece.len() >= 2 {
+                pieces.push(std::mem::take(&mut piece));
+            }
+            piece.clear();
+            continue;
+        };
+
+        // A segment entering the bounds starts a new piece
+        if piece.last() != Some(&start) {
+            if piece.len() >= 2 {
+                pieces.push(std::mem::take(&mut piece));
+            }
+            piece = vec![start];
+        }
+        piece.push(end);
+    }
+    if piece.len() >= 2 {
+        pieces.push(piece);
+    }
+    pieces
+}
+
+// The part of a segment within the bounds (Liang-Barsky), ends within the bounds unchanged so
+// consecutive segments still join
+fn clip_segment(a: (f64, f64), b: (f64, f64), bounds: &TileBounds) -> Option<((f64, f64), (f64, f64))> {
+    let delta = (b.0 - a.0, b.1 - a.1);
+    let (mut enter, mut exit) = (0.0_f64, 1.0_f64);
+    let edges = [
+        (-delta.0, a.0 - bounds.south),
+        (delta.0, bounds.north - a.0),
+        (-delta.1, a.1 - bounds.west),
+        (delta.1, bounds.east - a.1),
+    ];
+    for (direction, distance) in edges {
+        if direction == 0.0 {
+            if distance < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = distance / direction;
+        if direction < 0.0 {
+            enter = enter.max(t);
+        } else {
+            exit = exit.min(t);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+
+    let at = |t: f64| {
+        if t <= 0.0 {
+            a
+        } else if t >= 1.0 {
+            b
+        } else {
+            (a.0 + delta.0 * t, a.1 + delta.1 * t)
+        }
+    };
+    Some((at(enter), at(exit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_cut_at_the_bounds() {
+        let bounds = TileBounds { north: 1.0, south: 0.0, west: 0.0, east: 1.0 };
+        // In, out over the east edge and back in
+        let line = [(0.5, 0.25), (0.5, 0.5), (0.5, 1.5), (0.25, 1.5), (0.25, 0.5)];
+        let pieces = clip_to_bounds(&line, &bounds);
+        assert_eq!(pieces, vec![vec![(0.5, 0.25), (0.5, 0.5), (0.5, 1.0)], vec![(0.25, 1.0), (0.25, 0.5)]]);
+
+        assert!(clip_to_bounds(&[(2.0, 2.0), (3.0, 3.0)], &bounds).is_empty());
+        assert_eq!(clip_to_bounds(&[(-1.0, 0.5), (2.0, 0.5)], &bounds), vec![vec![(0.0, 0.5), (1.0, 0.5)]]);
+    }
+
+    #[test]
+    fn roads_are_classed_by_their_highway_tag() {
+        assert_eq!(RoadClass::from_highway("motorway_link"), Some(RoadClass::Motorway));
+        assert_eq!(RoadClass::from_highway("living_street"), Some(RoadClass::Minor));
+        assert_eq!(RoadClass::from_highway("proposed"), None);
+        assert!(RoadClass::Primary > RoadClass::Minor);
+        assert!(RoadClass::Primary.width_m() > RoadClass::Path.width_m());
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, bail};
+use reqwest::Client;
+use serde_json::Value;
+
+/// Public OSRM demo server, used unless the settings file names another
+/// Its usage policy allows light use only, heavier users should run their own
+pub const DEFAULT_ROUTING_SERVER: &str = "https://router.project-osrm.org";
+/// Routing profile of the demo server; self-hosted servers may also offer e.g. `cycling` or `foot`
+pub const DEFAULT_ROUTING_PROFILE: &str = "driving";
+
+/// A route returned by the routing server
+#[derive(Clone, Debug, Default)]
+pub struct Route {
+    pub distance_m: f64,
+    pub duration_s: f64,
+    pub points: Vec<(f64, f64)>, // (lat, lon) along the route, from start to end
+}
+
+/// URL of an OSRM route request between two (lat, lon) points, asking for the full geometry
+/// as GeoJSON; OSRM takes its coordinates as lon,lat
+pub fn osrm_route_url(server: &str, profile: &str, from: (f64, f64), to: (f64, f64)) -> String {
+    format!(
+        "{}/route/v1/{}/{:.6},{:.6};{:.6},{:.6}?overview=full&geometries=geojson",
+        server.trim_end_matches('/'),
+        profile,
+        from.1,
+        from.0,
+        to.1,
+        to.0
+    )
+}
+
+/// The first route of an OSRM route response
+/// Responses with another code than `Ok`, like `NoRoute` between two islands, are errors
+/// with the server's message
+pub fn parse_osrm_route(body: &str) -> Result<Route, anyhow::Error> {
+    let response: Value = serde_json::from_str(body)?;
+    let code = response["code"].as_str().unwrap_or("missing code");
+    if code != "Ok" {
+        let message = response["message"].as_str().unwrap_or(code);
+        bail!("{}", message);
+    }
+
+    let route = response["routes"].get(0).ok_or_else(|| anyhow!("No route found"))?;
+    let points: Vec<(f64, f64)> = route["geometry"]["coordinates"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Route without geometry"))?
+        .iter()
+        .filter_map(|position| Some((position.get(1)?.as_f64()?, position.get(0)?.as_f64()?)))
+        .collect();
+    if points.len() < 2 {
+        bail!("Route without geometry");
+    }
+
+    Ok(Route {
+        distance_m: route["distance"].as_f64().unwrap_or(0.0),
+        duration_s: route["duration"].as_f64().unwrap_or(0.0),
+        points,
+    })
+}
+
+/// Request a route from the routing server
+/// OSRM answers failed routes with an error status and a JSON body saying why, so the body
+/// is parsed whatever the status
+pub async fn request_route(client: &Client, url: &str) -> Result<Route, anyhow::Error> {
+    let response = client.get(url).send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+    parse_osrm_route(&body).map_err(|e| if status.is_success() { e } else { anyhow!("HTTP {}: {}", status, e) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_urls_put_longitude_first() {
+        let url = osrm_route_url("https://osrm.example.org/", "foot", (53.2194, 6.5665), (53.2406, 6.5349));
+        assert_eq!(
+            url,
+            "https://osrm.example.org/route/v1/foot/6.566500,53.219400;6.534900,53.240600?overview=full&geometries=geojson"
+        );
+    }
+
+    #[test]
+    fn routes_are_parsed_from_osrm_responses() {
+        let body = r#"{
+            "code": "Ok",
+            "routes": [{
+                "distance": 3260.4,
+                "duration": 412.7,
+                "geometry": { "type": "LineString", "coordinates": [[6.5665, 53.2194], [6.55, 53.23], [6.5349, 53.2406]] }
+            }],
+            "waypoints": []
+        }"#;
+        let route = parse_osrm_route(body).unwrap();
+        assert_eq!(route.distance_m, 3260.4);
+        assert_eq!(route.duration_s, 412.7);
+        assert_eq!(route.points, vec![(53.2194, 6.5665), (53.23, 6.55), (53.2406, 6.5349)]);
+
+        let error = parse_osrm_route(r#"{"code": "NoRoute", "message": "Impossible route between points"}"#).unwrap_err();
+        assert_eq!(error.to_string(), "Impossible route between points");
+        assert!(parse_osrm_route("<html>").is_err());
+    }
+}
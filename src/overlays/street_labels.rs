@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use cosmic_text::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, SwashCache, SwashContent};
+use crate::resources::StreetLabelSettings;
+use crate::utils::geo::{lat_lon_to_world, prepare_line, AntimeridianMode};
+
+/// Family name of the font bundled with Bevy, used unless the style picks another
+/// or a glyph is missing from it
+pub const LABEL_FONT_FAMILY: &str = "Fira Mono";
+
+/// A named road geometry from vector data, in world coordinates
+#[derive(Clone, Debug)]
+pub struct StreetLine {
+    pub name: String,
+    pub points: Vec<DVec2>,
+}
+
+/// Marker component for street name decals lying on the ground
+#[derive(Component)]
+pub struct StreetLabel;
+
+/// Font state for rasterizing street labels
+#[derive(Resource)]
+pub struct LabelFonts {
+    pub font_system: FontSystem,
+    pub swash_cache: SwashCache,
+    pub family: String, // Font family labels are set in
+}
+
+impl Default for LabelFonts {
+    fn default() -> Self {
+        // The bundled font covers ASCII, system fonts are the fallback for other scripts
+        let mut db = cosmic_text::fontdb::Database::new();
+        db.load_font_data(bevy::text::DEFAULT_FONT_DATA.to_vec());
+        db.load_system_fonts();
+
+        Self {
+            font_system: FontSystem::new_with_locale_and_db("en-US".to_string(), db),
+            swash_cache: SwashCache::new(),
+            family: LABEL_FONT_FAMILY.to_string(),
+        }
+    }
+}
+
+/// A label rendered into a single-line texture strip
+pub struct LabelStrip {
+    pub image: Image,
+    pub glyphs: Vec<(f32, f32)>, // Horizontal pixel range of every glyph in the strip
+}
+
+/// Named LineString and MultiLineString features of a GeoJSON FeatureCollection
+/// Features without a "name" property are skipped since there is nothing to label
+pub fn street_lines(json: &serde_json::Value, mode: AntimeridianMode) -> Result<Vec<StreetLine>, anyhow::Error> {
+    let features = json["features"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("GeoJSON has no features array"))?;
+
+    let mut lines = Vec::new();
+    for feature in features {
+        let Some(name) = feature["properties"]["name"].as_str() else {
+            continue;
+        };
+        let geometry = &feature["geometry"];
+        let coordinates = &geometry["coordinates"];
+
+        let parts: Vec<&serde_json::Value> = match geometry["type"].as_str() {
+            Some("LineString") => vec![coordinates],
+            Some("MultiLineString") => coordinates.as_array().map(|a| a.iter().collect()).unwrap_or_default(),
+            _ => continue,
+        };
+
+        for part in parts {
+            // GeoJSON positions are [lon, lat]
+            let line: Vec<(f64, f64)> = part
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|position| {
+                    let lon = position.get(0)?.as_f64()?;
+                    let lat = position.get(1)?.as_f64()?;
+                    Some((lat, lon))
+                })
+                .collect();
+
+            for piece in prepare_line(&line, mode) {
+                let points: Vec<DVec2> = piece.iter().map(|&(lat, lon)| lat_lon_to_world(lat, lon)).collect();
+                lines.push(StreetLine { name: name.to_string(), points });
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Rasterize a label into a white-on-transparent texture strip, one line high
+pub fn rasterize_label(fonts: &mut LabelFonts, text: &str, font_px: f32) -> Option<LabelStrip> {
+    let LabelFonts { font_system, swash_cache, family } = fonts;
+
+    let mut buffer = Buffer::new(font_system, Metrics::new(font_px, font_px * 1.25));
+    buffer.set_size(font_system, None, None);
+    buffer.set_text(font_system, text, Attrs::new().family(Family::Name(family)), Shaping::Advanced);
+    buffer.shape_until_scroll(font_system, false);
+
+    let run = buffer.layout_runs().next()?;
+    let width = run.line_w.ceil() as u32;
+    let height = run.line_height.ceil() as u32;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut data = vec![0u8; (width * height * 4) as usize];
+    let mut glyphs = Vec::with_capacity(run.glyphs.len());
+
+    for glyph in run.glyphs {
+        glyphs.push((glyph.x, glyph.x + glyph.w));
+
+        let physical = glyph.physical((0.0, run.line_y), 1.0);
+        let Some(image) = swash_cache.get_image(font_system, physical.cache_key) else {
+            continue;
+        };
+
+        // Copy the glyph coverage into the alpha channel of the strip
+        let placement = image.placement;
+        for row in 0..placement.height {
+            for col in 0..placement.width {
+                let x = physical.x + placement.left + col as i32;
+                let y = physical.y - placement.top + row as i32;
+                if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                    continue;
+                }
+
+                let source = (row * placement.width + col) as usize;
+                let alpha = match image.content {
+                    SwashContent::Mask => image.data[source],
+                    SwashContent::Color => image.data[source * 4 + 3],
+                    SwashContent::SubpixelMask => image.data[source * 4],
+                };
+
+                let target = ((y as u32 * width + x as u32) * 4) as usize;
+                data[target..target + 3].copy_from_slice(&[255, 255, 255]);
+                data[target + 3] = data[target + 3].max(alpha);
+            }
+        }
+    }
+
+    let image = Image::new(
+        Extent3d { width, height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+
+    Some(LabelStrip { image, glyphs })
+}
+
+/// Lay a label strip out along a polyline on the XZ plane, one quad per glyph
+/// The text is centred on the line and reads left to right; returns None when it doesn't fit
+pub fn create_curved_label_mesh(points: &[Vec2], strip: &LabelStrip, text_height: f32) -> Option<Mesh> {
+    let strip_size = strip.image.size().as_vec2();
+    let scale = text_height / strip_size.y;
+    let text_length = strip_size.x * scale;
+
+    // Reverse lines that run westward so the text isn't upside down when north is up
+    let mut points = points.to_vec();
+    if points.last()?.x < points.first()?.x {
+        points.reverse();
+    }
+
+    let lengths: Vec<f32> = points.windows(2).map(|w| w[0].distance(w[1])).collect();
+    let total_length: f32 = lengths.iter().sum();
+    if text_length > total_length {
+        return None;
+    }
+    let start = (total_length - text_length) / 2.0;
+
+    let mut positions = Vec::with_capacity(strip.glyphs.len() * 4);
+    let mut uvs = Vec::with_capacity(strip.glyphs.len() * 4);
+    let mut indices = Vec::with_capacity(strip.glyphs.len() * 6);
+
+    for &(x0, x1) in &strip.glyphs {
+        if x1 <= x0 {
+            continue;
+        }
+
+        // Each glyph is placed straight, centred on the line at its position in the text
+        let centre_distance = start + (x0 + x1) / 2.0 * scale;
+        let (centre, direction) = point_along(&points, &lengths, centre_distance);
+
+        // Text "up" is to the left of the reading direction when seen from above
+        let up = Vec2::new(direction.y, -direction.x);
+        let half_width = direction * (x1 - x0) * scale / 2.0;
+        let half_height = up * text_height / 2.0;
+
+        let base = positions.len() as u32;
+        for (corner, uv) in [
+            (centre - half_width + half_height, [x0 / strip_size.x, 0.0]),
+            (centre + half_width + half_height, [x1 / strip_size.x, 0.0]),
+            (centre + half_width - half_height, [x1 / strip_size.x, 1.0]),
+            (centre - half_width - half_height, [x0 / strip_size.x, 1.0]),
+        ] {
+            positions.push([corner.x, 0.0, corner.y]);
+            uvs.push(uv);
+        }
+        indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_indices(Indices::U32(indices));
+    Some(mesh)
+}
+
+// Point and unit direction at a distance along a polyline
+fn point_along(points: &[Vec2], lengths: &[f32], distance: f32) -> (Vec2, Vec2) {
+    let mut remaining = distance;
+    for (i, &length) in lengths.iter().enumerate() {
+        if remaining <= length || i == lengths.len() - 1 {
+            let direction = (points[i + 1] - points[i]).normalize_or(Vec2::X);
+            return (points[i] + direction * remaining.min(length), direction);
+        }
+        remaining -= length;
+    }
+    (points[0], Vec2::X)
+}
+
+/// Spawn a street name decal along a road, hidden until the camera comes close to the ground
+pub fn spawn_street_label(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    images: &mut Assets<Image>,
+    fonts: &mut LabelFonts,
+    line: &StreetLine,
+    settings: &StreetLabelSettings,
+) -> Option<Entity> {
+    let strip = rasterize_label(fonts, &line.name, settings.font_px)?;
+
+    // Vertices relative to the first point keep full f32 precision, like GPX tracks
+    let anchor = line.points[0];
+    let points: Vec<Vec2> = line.points.iter().map(|p| (*p - anchor).as_vec2()).collect();
+    let mesh = create_curved_label_mesh(&points, &strip, settings.text_height)?;
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.15, 0.15, 0.15),
+        base_color_texture: Some(images.add(strip.image)),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        double_sided: true,
+        cull_mode: None,
+        ..default()
+    });
+
+    let entity = commands
+        .spawn((
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(material),
+            Transform::from_xyz(anchor.x as f32, settings.elevation_offset, anchor.y as f32),
+            Visibility::Hidden,
+            Name::new(format!("Street label {}", line.name)),
+            StreetLabel,
+        ))
+        .id();
+
+    Some(entity)
+}
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use std::collections::BTreeMap;
+use crate::osm::{tag_filter, OverpassQuery, TileId};
+
+// Meters per degree of latitude
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Overpass query for the street lamps of a tile
+pub fn lamp_query(id: TileId) -> OverpassQuery {
+    OverpassQuery::for_tile(id).nodes(&tag_filter("highway", Some("street_lamp")))
+}
+
+/// Marker component for a cluster of street lamps, with a glow on the ground and a point light
+/// as children
+#[derive(Component)]
+pub struct StreetLampCluster;
+
+/// Marker component for the point light of a lamp cluster
+#[derive(Component)]
+pub struct StreetLampLight;
+
+/// Nearby street lamps lit as one light
+#[derive(Clone, Debug, PartialEq)]
+pub struct LampCluster {
+    pub lat: f64, // Middle of the lamps
+    pub lon: f64,
+    pub count: usize,
+}
+
+/// Group (lat, lon) lamp positions by squares of `cell_m` meters
+/// A street has a lamp every 30 meters or so, a light for each would be far more than a
+/// renderer takes; a light per cluster looks the same from above
+pub fn cluster_lamps(lamps: &[(f64, f64)], cell_m: f64) -> Vec<LampCluster> {
+    let mut cells: BTreeMap<(i64, i64), (f64, f64, usize)> = BTreeMap::new();
+    for &(lat, lon) in lamps {
+        let cell_lat = cell_m / METERS_PER_DEGREE;
+        let cell_lon = cell_lat / lat.to_radians().cos().max(0.01);
+        let cell = ((lat / cell_lat).floor() as i64, (lon / cell_lon).floor() as i64);
+        let (lat_sum, lon_sum, count) = cells.entry(cell).or_default();
+        *lat_sum += lat;
+        *lon_sum += lon;
+        *count += 1;
+    }
+
+    cells
+        .into_values()
+        .map(|(lat_sum, lon_sum, count)| LampCluster {
+            lat: lat_sum / count as f64,
+            lon: lon_sum / count as f64,
+            count,
+        })
+        .collect()
+}
+
+/// Texture of the glow a lamp cluster casts on the ground: warm light fading out to the edge
+pub fn lamp_glow_image() -> Image {
+    const SIZE: u32 = 64;
+    let mut data = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let offset = Vec2::new(x as f32, y as f32) + 0.5 - SIZE as f32 / 2.0;
+            let falloff = (1.0 - offset.length() / (SIZE as f32 / 2.0)).clamp(0.0, 1.0);
+            data.extend_from_slice(&[255, 200, 120, (falloff * falloff * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d { width: SIZE, height: SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_lamps_share_a_cluster() {
+        let lamps = [(53.21875, 6.56541), (53.21879, 6.56552), (53.21883, 6.56563), (53.22500, 6.57000)];
+        let clusters = cluster_lamps(&lamps, 50.0);
+        assert_eq!(clusters.len(), 2);
+        let street = clusters.iter().find(|cluster| cluster.count == 3).unwrap();
+        assert!((street.lat - 53.21879).abs() < 1e-9);
+        assert!((street.lon - 6.56552).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use reqwest::Client;
+use serde_json::Value;
+use crate::resources::user_settings::Attribution;
+
+/// RainViewer's list of the radar frames it has tiles of, refreshed every 10 minutes
+pub const RAINVIEWER_FRAMES_URL: &str = "https://api.rainviewer.com/public/weather-maps.json";
+/// RainViewer's terms ask for a credit next to its radar tiles
+pub const RAINVIEWER_ATTRIBUTION: Attribution = Attribution {
+    text: "Radar © RainViewer",
+    link: "https://www.rainviewer.com/api.html",
+};
+
+// Tile size, color scheme (2, "Universal Blue") and options (smoothed, snow colored) in the tile URLs
+const TILE_OPTIONS: &str = "256/{z}/{x}/{y}/2/1_1.png";
+
+/// A precipitation radar image of the whole world at one time
+#[derive(Clone, Debug, PartialEq)]
+pub struct RadarFrame {
+    pub time: i64,   // Unix seconds
+    pub url: String, // Tile URL template
+}
+
+impl RadarFrame {
+    /// Time of the frame as shown in the UI, in UTC like the radar data
+    pub fn label(&self) -> String {
+        let minutes = self.time.rem_euclid(24 * 60 * 60) / 60;
+        format!("{:02}:{:02} UTC", minutes / 60, minutes % 60)
+    }
+}
+
+/// The radar frames of a RainViewer weather maps response, oldest first: the past two hours
+/// and the nowcast of the next half hour where the server has one
+pub fn parse_rainviewer_frames(body: &str) -> Result<Vec<RadarFrame>, anyhow::Error> {
+    let response: Value = serde_json::from_str(body)?;
+    let host = response["host"].as_str().ok_or_else(|| anyhow!("Weather maps without a host"))?;
+    let radar = &response["radar"];
+
+    let frames: Vec<RadarFrame> = ["past", "nowcast"]
+        .iter()
+        .filter_map(|kind| radar[kind].as_array())
+        .flatten()
+        .filter_map(|frame| {
+            Some(RadarFrame {
+                time: frame["time"].as_i64()?,
+                url: format!("{}{}/{}", host, frame["path"].as_str()?, TILE_OPTIONS),
+            })
+        })
+        .collect();
+    if frames.is_empty() {
+        return Err(anyhow!("Weather maps without radar frames"));
+    }
+    Ok(frames)
+}
+
+/// Request the radar frames from RainViewer
+pub async fn request_radar_frames(client: &Client) -> Result<Vec<RadarFrame>, anyhow::Error> {
+    let response = client.get(RAINVIEWER_FRAMES_URL).send().await?.error_for_status()?;
+    parse_rainviewer_frames(&response.text().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_read_oldest_first() {
+        let body = r#"{
+            "version": "2.0",
+            "generated": 1760612400,
+            "host": "https://tilecache.rainviewer.com",
+            "radar": {
+                "past": [
+                    {"time": 1760611200, "path": "/v2/radar/1760611200"},
+                    {"time": 1760611800, "path": "/v2/radar/1760611800"}
+                ],
+                "nowcast": [{"time": 1760612400, "path": "/v2/radar/nowcast_5b6d"}]
+            },
+            "satellite": {"infrared": []}
+        }"#;
+        let frames = parse_rainviewer_frames(body).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].url, "https://tilecache.rainviewer.com/v2/radar/1760611200/256/{z}/{x}/{y}/2/1_1.png");
+        assert_eq!(frames[2].time, 1760612400);
+        assert_eq!(frames[1].label(), "10:50 UTC");
+
+        assert!(parse_rainviewer_frames(r#"{"host": "https://tilecache.rainviewer.com", "radar": {}}"#).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+use bevy::asset::load_internal_asset;
+use crate::atmosphere::{SkyMaterial, SKY_SHADER_HANDLE};
+use crate::resources::AtmosphereSettings;
+use crate::systems::atmosphere::{setup_sky, update_sky, update_distance_fog};
+use crate::systems::environment::apply_sun_lighting;
+
+/// Plugin for the procedural sky and the distance fog fading the map into it
+/// Follows the day/night cycle of the EnvironmentPlugin
+pub struct AtmospherePlugin;
+
+impl Plugin for AtmospherePlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, SKY_SHADER_HANDLE, "../atmosphere/sky.wgsl", Shader::from_wgsl);
+
+        app
+            .add_plugins(MaterialPlugin::<SkyMaterial> {
+                prepass_enabled: false,
+                shadows_enabled: false,
+                ..default()
+            })
+            .init_resource::<AtmosphereSettings>()
+            .add_systems(Startup, setup_sky)
+            .add_systems(Update, (update_sky, update_distance_fog).after(apply_sun_lighting));
+    }
+}
@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+use crate::resources::Benchmark;
+use crate::systems::benchmark::{measure_benchmark, start_benchmark};
+use crate::systems::camera_path::play_camera_path;
+
+/// Plugin for the benchmark mode (--benchmark): after a short warmup the camera flies a
+/// scripted sweep over a fixed region, then tiles spawned per second, frame time percentiles
+/// and peak memory are printed and the app quits
+/// Run it twice for a warm tile cache, so the numbers measure the tile pipeline and not the network
+pub struct BenchmarkPlugin;
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Benchmark>()
+            .add_systems(Update, (start_benchmark, measure_benchmark).chain().after(play_camera_path));
+    }
+}
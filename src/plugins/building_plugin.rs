@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use crate::resources::{Buildings, LayerManagerAppExt, Overpass};
+use crate::resources::layer_manager::BUILDINGS_LAYER;
+use crate::systems::buildings::{request_building_tiles, receive_building_tiles};
+
+/// Plugin for standing the buildings around the camera up from the tiles near the ground
+/// Footprints are queried from the Overpass server in the settings and raised to their
+/// OpenStreetMap height, or to a made up one where OpenStreetMap has none
+pub struct BuildingPlugin;
+
+impl Plugin for BuildingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_overlay_layer(BUILDINGS_LAYER)
+            .init_resource::<Overpass>()
+            .init_resource::<Buildings>()
+            .add_systems(Update, (request_building_tiles, receive_building_tiles).chain());
+    }
+}
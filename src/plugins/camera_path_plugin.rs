@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, PathRecorder};
+use crate::resources::input_map::{PLAY_CAMERA_PATH, RECORD_CAMERA_PATH, RENDER_CAMERA_PATH};
+use crate::systems::camera::update_camera_flight;
+use crate::systems::camera_path::{handle_path_recorder_keys, record_camera_path, play_camera_path};
+
+/// Plugin recording camera paths and playing them back as flythroughs
+/// Playback can dump every frame to disk to turn a path into a video
+pub struct CameraPathPlugin;
+
+impl Plugin for CameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(RECORD_CAMERA_PATH, &[KeyCode::F5])
+            .register_input_action(PLAY_CAMERA_PATH, &[KeyCode::F6])
+            .register_input_action(RENDER_CAMERA_PATH, &[KeyCode::F7])
+            .init_resource::<PathRecorder>()
+            .add_systems(Update, (
+                handle_path_recorder_keys,
+                record_camera_path,
+                play_camera_path,
+            ).chain().after(update_camera_flight));
+    }
+}
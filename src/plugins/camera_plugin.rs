@@ -1,6 +1,17 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use crate::events::{FlyTo, OriginShifted};
+use crate::resources::{CameraFlight, CameraMode, FloatingOrigin, GroundHeight, InputMapAppExt, WalkSettings, WalkState};
+use crate::resources::input_map::{
+    MOVE_FORWARD, MOVE_BACKWARD, MOVE_LEFT, MOVE_RIGHT, MOVE_UP, MOVE_DOWN, BOOST,
+    TOGGLE_CAMERA_MODE, TOGGLE_WALK_MODE, TOGGLE_CURSOR_GRAB, TOGGLE_DEBUG,
+};
 use crate::systems::{
-    camera::{mouse_look_system, camera_movement},
+    camera::{
+        mouse_look_system, camera_movement, orbit_camera, toggle_camera_mode, fly_to_dropped_link, start_camera_flight,
+        update_camera_flight, clamp_camera_latitude, toggle_walk_mode, walk_camera,
+    },
+    floating_origin::{anchor_geo_entities, rebase_floating_origin},
     window::{grab_mouse, toggle_cursor_grab},
     debug::{debug_info, toggle_debug_mode},
 };
@@ -11,13 +22,52 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
+            .register_input_action(MOVE_FORWARD, &[KeyCode::KeyW])
+            .register_input_action(MOVE_BACKWARD, &[KeyCode::KeyS])
+            .register_input_action(MOVE_LEFT, &[KeyCode::KeyA])
+            .register_input_action(MOVE_RIGHT, &[KeyCode::KeyD])
+            .register_input_action(MOVE_UP, &[KeyCode::Space])
+            .register_input_action(MOVE_DOWN, &[KeyCode::ControlLeft])
+            .register_input_action(BOOST, &[KeyCode::ShiftLeft])
+            .register_input_action(TOGGLE_CAMERA_MODE, &[KeyCode::KeyV])
+            .register_input_action(TOGGLE_WALK_MODE, &[KeyCode::KeyF])
+            .register_input_action(TOGGLE_CURSOR_GRAB, &[KeyCode::Escape])
+            .register_input_action(TOGGLE_DEBUG, &[KeyCode::Digit1])
+            .add_event::<FlyTo>()
+            .init_resource::<CameraFlight>()
+            .init_resource::<CameraMode>()
+            .init_resource::<GroundHeight>()
+            .init_resource::<WalkSettings>()
+            .init_resource::<WalkState>()
+            .add_event::<OriginShifted>()
+            .init_resource::<FloatingOrigin>()
             .add_systems(Startup, grab_mouse)
             .add_systems(Update, (
-                mouse_look_system,
-                camera_movement,
+                (toggle_camera_mode, toggle_walk_mode).chain(),
+                (mouse_look_system, camera_movement)
+                    .chain()
+                    .after(toggle_walk_mode)
+                    .run_if(resource_equals(CameraMode::Fly)),
+                (mouse_look_system, walk_camera)
+                    .chain()
+                    .after(toggle_walk_mode)
+                    .before(start_camera_flight)
+                    .run_if(resource_equals(CameraMode::Walk)),
+                orbit_camera
+                    .after(toggle_walk_mode)
+                    .before(start_camera_flight)
+                    .run_if(resource_equals(CameraMode::Orbit)),
                 toggle_cursor_grab,
                 debug_info,
                 toggle_debug_mode,
-            ));
+                // Flights override manual movement while they run
+                (fly_to_dropped_link, start_camera_flight, update_camera_flight).chain().after(camera_movement),
+            ))
+            .add_systems(PostUpdate, (
+                // After everything that moves the camera in Update, flights and camera paths included
+                clamp_camera_latitude,
+                rebase_floating_origin,
+                anchor_geo_entities,
+            ).chain().before(TransformSystem::TransformPropagate));
     }
 } 
\ No newline at end of file
@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use crate::resources::{InputMapAppExt, ProviderComparison};
+use crate::resources::input_map::TOGGLE_COMPARISON;
+use crate::systems::comparison::{
+    apply_comparison_tiles, drag_comparison_divider, load_comparison_tiles, manage_comparison_cameras,
+    rebuild_comparison_batches, setup_comparison_divider, start_comparison, sync_comparison_camera, toggle_comparison,
+    update_comparison_viewports,
+};
+use crate::systems::tiles::{parent_tiles_by_zoom, rebuild_tile_batches};
+
+/// Plugin for the split-screen comparison of two tile providers
+pub struct ComparisonPlugin;
+
+impl Plugin for ComparisonPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_COMPARISON, &[KeyCode::KeyB])
+            .init_resource::<ProviderComparison>()
+            .add_systems(Startup, (start_comparison, setup_comparison_divider))
+            .add_systems(Update, (
+                (toggle_comparison, manage_comparison_cameras, drag_comparison_divider, update_comparison_viewports).chain(),
+                load_comparison_tiles,
+                apply_comparison_tiles.after(manage_comparison_cameras),
+                // After the map's batches, whose filter and globe curvature the comparison copies,
+                // and before the new batches are parented
+                rebuild_comparison_batches
+                    .after(apply_comparison_tiles)
+                    .after(rebuild_tile_batches)
+                    .before(parent_tiles_by_zoom),
+            ))
+            // After every system moving the camera
+            .add_systems(PostUpdate, sync_comparison_camera.before(TransformSystem::TransformPropagate));
+    }
+}
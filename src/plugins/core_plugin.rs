@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 use crate::systems::setup::{setup, init_resources};
-use crate::resources::{MouseLookState, DebugSettings};
+use crate::resources::{MouseLookState, DebugSettings, MovementSettings, CameraMotion};
 
 /// Core plugin that handles the basic app setup
 pub struct CorePlugin;
@@ -8,13 +8,14 @@ pub struct CorePlugin;
 impl Plugin for CorePlugin {
     fn build(&self, app: &mut App) {
         // Initialize resources
-        let (osm_data, tokio_runtime) = init_resources();
+        let osm_data = init_resources();
         
         app
             .insert_resource(osm_data)
-            .insert_resource(tokio_runtime)
             .insert_resource(MouseLookState::default())
             .insert_resource(DebugSettings::default())
+            .insert_resource(MovementSettings::default())
+            .insert_resource(CameraMotion::default())
             .add_systems(Startup, setup);
     }
 } 
\ No newline at end of file
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use crate::resources::{EnvironmentSettings, Sun, WorldClock};
+use crate::systems::environment::{
+    advance_world_clock,
+    update_sun,
+    apply_sun_lighting,
+    setup_time_slider,
+    handle_time_slider,
+    update_time_slider,
+};
+
+/// Plugin for the day/night cycle: the sun is placed from the real (or scrubbed) time
+/// and the location below the camera, and drives the sunlight, ambient light and sky color
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<WorldClock>()
+            .init_resource::<Sun>()
+            .init_resource::<EnvironmentSettings>()
+            .add_systems(Startup, setup_time_slider)
+            .add_systems(Update, (
+                handle_time_slider,
+                advance_world_clock,
+                update_sun,
+                apply_sun_lighting,
+                update_time_slider,
+            ).chain());
+    }
+}
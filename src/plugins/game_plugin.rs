@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use crate::events::{CollectiblePicked, ZoneEntered, ZoneExited};
+use crate::resources::{ActiveZones, GameContent, Score, TileGeneratorAppExt};
+use crate::systems::game::{
+    add_demo_game_content,
+    spawn_tile_collectibles,
+    spin_collectibles,
+    update_trigger_zones,
+    pick_up_collectibles,
+    log_game_events,
+    setup_game_hud,
+    update_game_hud,
+};
+
+/// Plugin with the building blocks for location-based games: geofenced trigger zones,
+/// collectibles placed at coordinates (spawned through the tile generator API) and a score HUD
+/// Content goes in the GameContent resource, inserted before the app starts; without any,
+/// a demo zone and ring of collectibles is placed around the start position
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GameContent>()
+            .init_resource::<Score>()
+            .init_resource::<ActiveZones>()
+            .add_event::<ZoneEntered>()
+            .add_event::<ZoneExited>()
+            .add_event::<CollectiblePicked>()
+            .register_tile_generator(spawn_tile_collectibles)
+            .add_systems(Startup, (add_demo_game_content, setup_game_hud))
+            .add_systems(Update, (
+                spin_collectibles,
+                update_trigger_zones,
+                pick_up_collectibles,
+                log_game_events,
+                update_game_hud,
+            ).chain());
+    }
+}
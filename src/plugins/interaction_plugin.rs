@@ -1,11 +1,37 @@
 use bevy::prelude::*;
-use crate::systems::interaction::interact_with_map;
+use crate::resources::{CursorPick, DoubleClickState, InputMapAppExt, WhatsHereClick};
+use crate::resources::input_map::ZOOM_OUT_MODIFIER;
+use crate::systems::interaction::{update_cursor_pick, interact_with_map, scroll_zoom, double_click_zoom, touch_gestures, whats_here};
+use crate::systems::measurement::measuring;
+use crate::systems::routing::routing;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::island_editor::editing_islands;
 
 /// Plugin for map interaction
 pub struct InteractionPlugin;
 
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, interact_with_map);
+        // Clicks place measurement points in measurement mode, pick route points in routing mode
+        // and sculpt in island editing mode
+        let double_click_zoom = double_click_zoom.run_if(not(measuring)).run_if(not(routing));
+        #[cfg(not(target_arch = "wasm32"))]
+        let double_click_zoom = double_click_zoom.run_if(not(editing_islands));
+
+        app
+            .register_input_action(ZOOM_OUT_MODIFIER, &[KeyCode::ShiftLeft, KeyCode::ShiftRight])
+            .init_resource::<CursorPick>()
+            .init_resource::<DoubleClickState>()
+            .init_resource::<WhatsHereClick>()
+            .add_systems(Update, (
+                update_cursor_pick,
+                (
+                    interact_with_map,
+                    scroll_zoom,
+                    double_click_zoom,
+                    touch_gestures,
+                    whats_here,
+                ),
+            ).chain());
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use bevy::input::InputSystem;
+use crate::resources::{InputMapAppExt, IslandAssetLibrary, IslandEditor, Islands};
+use crate::resources::input_map::{
+    BRUSH_LARGER, BRUSH_SMALLER, DELETE_OBJECT, NEXT_BRUSH_TOOL, SWITCH_EDIT_MODE, TOGGLE_ISLAND_EDITING,
+    TOGGLE_ISLAND_GRID, TOGGLE_ISLAND_LIST,
+};
+use crate::systems::island_bounds::{draw_island_grid, spawn_island_borders, tint_island_tiles, update_island_borders};
+use crate::systems::islands::{measure_island_ground, update_islands};
+use crate::systems::camera::{camera_movement, walk_camera};
+use crate::systems::chat::open_chat;
+use crate::systems::island_info::{
+    open_island_form,
+    type_island_form,
+    setup_island_info,
+    update_island_form_text,
+    update_island_tooltip,
+};
+use crate::systems::island_objects::{
+    select_island_objects,
+    shift_object_drag,
+    drag_island_objects,
+    delete_island_object,
+    draw_object_gizmo,
+};
+use crate::systems::island_travel::{handle_teleport_buttons, toggle_island_list, update_island_distances};
+use crate::systems::island_editor::{
+    toggle_island_editing,
+    pick_island_terrain,
+    sculpt_islands,
+    setup_island_editor_text,
+    update_island_editor_text,
+};
+
+/// Plugin for persistent islands: OpenSimulator regions listed in the settings file
+/// (`[[islands]]` with a name, a "zoom/x/y" tile and an OAR or XML region) are placed on
+/// their tile, with terrain and objects, while the camera is near; their tiles are outlined at every distance
+/// In island editing mode (I) the terrain is sculpted with brushes, and objects from a small
+/// asset library are placed, moved, rotated and scaled; both are saved with the island,
+/// like its owner, description and tags, which a tooltip shows when hovering the island
+/// The island list (J) shows every island with its distance, and flies the camera to one
+pub struct IslandPlugin;
+
+impl Plugin for IslandPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_ISLAND_EDITING, &[KeyCode::KeyI])
+            .register_input_action(NEXT_BRUSH_TOOL, &[KeyCode::Tab])
+            .register_input_action(BRUSH_LARGER, &[KeyCode::BracketRight])
+            .register_input_action(BRUSH_SMALLER, &[KeyCode::BracketLeft])
+            .register_input_action(SWITCH_EDIT_MODE, &[KeyCode::KeyO])
+            .register_input_action(DELETE_OBJECT, &[KeyCode::Delete])
+            .register_input_action(TOGGLE_ISLAND_GRID, &[KeyCode::KeyH])
+            .register_input_action(TOGGLE_ISLAND_LIST, &[KeyCode::KeyJ])
+            .insert_resource(Islands::load())
+            .init_resource::<IslandEditor>()
+            .init_resource::<IslandAssetLibrary>()
+            .add_systems(Startup, (setup_island_editor_text, setup_island_info, spawn_island_borders))
+            // The form takes the keys before the chat could open on Enter
+            .add_systems(PreUpdate, type_island_form.after(InputSystem).before(open_chat))
+            .add_systems(Update, (
+                update_islands,
+                toggle_island_editing,
+                update_island_borders,
+                tint_island_tiles,
+                draw_island_grid,
+                pick_island_terrain,
+                sculpt_islands,
+                select_island_objects,
+                shift_object_drag,
+                drag_island_objects,
+                delete_island_object,
+                draw_object_gizmo,
+                open_island_form,
+                update_island_editor_text,
+                update_island_form_text,
+                update_island_tooltip,
+            ).chain())
+            .add_systems(Update, (toggle_island_list, update_island_distances, handle_teleport_buttons).chain())
+            // The ground under the camera for the camera's clearance, see GroundHeight
+            .add_systems(Update, measure_island_ground.before(camera_movement).before(walk_camera));
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, RebindState};
+use crate::resources::input_map::TOGGLE_KEYBINDINGS;
+use crate::systems::keybindings::{
+    toggle_keybindings_page,
+    handle_keybinding_buttons,
+    capture_rebind_key,
+    update_keybinding_labels,
+};
+
+/// Plugin for the configurable key bindings and the page to rebind them
+/// Other plugins register their actions with `App::register_input_action`
+pub struct KeybindingsPlugin;
+
+impl Plugin for KeybindingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_KEYBINDINGS, &[KeyCode::F2])
+            .init_resource::<RebindState>()
+            .add_systems(Update, (
+                toggle_keybindings_page,
+                handle_keybinding_buttons,
+                capture_rebind_key,
+                update_keybinding_labels,
+            ).chain());
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy::render::view::VisibilitySystems;
+use bevy::transform::TransformSystem;
+use crate::resources::{ImageryLayers, InputMapAppExt, LayerManager, TimelineScrub};
+use crate::resources::input_map::{TOGGLE_LAYERS, TOGGLE_PHOTO_VIEW};
+use crate::systems::layers::{
+    sync_base_layer,
+    toggle_photo_view,
+    apply_imagery_layers,
+    toggle_layer_panel,
+    handle_layer_panel_buttons,
+    handle_layer_opacity_sliders,
+    update_layer_panel,
+    update_layer_opacity,
+    apply_overlay_layers,
+    setup_imagery_timeline,
+    handle_imagery_timeline,
+    update_imagery_timeline,
+    setup_attribution_bar,
+    update_attribution_bar,
+    handle_attribution_links,
+};
+use crate::systems::tiles::{apply_pending_tiles, process_tiles};
+
+/// Plugin for stacking raster sources (map, satellite, hillshade) into the tile imagery
+/// Each layer is loaded and cached on its own and blended with its opacity; the layer
+/// panel (L) shows, hides, fades and reorders the layers, P switches between map and photo view
+/// The panel also lists the overlay layers drawn over the imagery, which plugins register
+/// with the LayerManager
+/// The providers of the visible layers are credited in the bottom right corner, as their
+/// tile usage policies require; clicking a credit opens the provider's page
+/// Layers with a {time} in their URL, like the daily satellite layer, get a timeline to pick
+/// the date of their imagery
+pub struct LayerPlugin;
+
+impl Plugin for LayerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_LAYERS, &[KeyCode::KeyL])
+            .register_input_action(TOGGLE_PHOTO_VIEW, &[KeyCode::KeyP])
+            .init_resource::<ImageryLayers>()
+            .init_resource::<TimelineScrub>()
+            .init_resource::<LayerManager>()
+            .add_systems(Startup, (setup_attribution_bar, setup_imagery_timeline))
+            .add_systems(Update, (
+                toggle_layer_panel,
+                toggle_photo_view,
+                handle_layer_panel_buttons,
+                handle_layer_opacity_sliders,
+                update_layer_panel,
+                update_layer_opacity,
+                handle_imagery_timeline,
+                update_imagery_timeline,
+                sync_base_layer,
+                apply_imagery_layers,
+                update_attribution_bar,
+                handle_attribution_links,
+            ).chain().before(process_tiles).before(apply_pending_tiles))
+            // After the overlays were drawn for the frame, before they are placed and culled
+            .add_systems(PostUpdate, apply_overlay_layers
+                .before(TransformSystem::TransformPropagate)
+                .before(VisibilitySystems::VisibilityPropagate));
+    }
+}
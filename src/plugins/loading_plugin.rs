@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use crate::resources::AppState;
+use crate::systems::loading::{fade_out_loading_screen, setup_loading_screen, update_loading_progress};
+use crate::systems::tiles::apply_pending_tiles;
+
+/// Plugin for the startup states: a loading screen covers the world while the tiles around the
+/// start position load (AppState::Loading), and fades into it once they are in (AppState::Running)
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<AppState>()
+            .add_systems(Startup, setup_loading_screen)
+            .add_systems(Update, (
+                update_loading_progress
+                    .after(apply_pending_tiles)
+                    .run_if(in_state(AppState::Loading)),
+                fade_out_loading_screen.run_if(in_state(AppState::Running)),
+            ));
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use crate::events::DataFileDropped;
+use crate::resources::{InputMapAppExt, MarkerBadges, MarkerImport, Markers};
+use crate::resources::input_map::TOGGLE_MARKER_IMPORT;
+use crate::systems::markers::{
+    setup_markers,
+    update_markers,
+    toggle_marker_import,
+    handle_marker_file_drop,
+    import_pending_markers,
+    setup_marker_import_panel,
+    handle_clear_markers_button,
+    update_marker_import_panel,
+};
+
+/// Plugin for pins on the map: markers added with Markers::add_marker are drawn as pins with
+/// their label, and clustered into count badges while zoomed out
+/// K switches importing markers from dropped CSV and GeoJSON files on and off
+pub struct MarkerPlugin;
+
+impl Plugin for MarkerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_MARKER_IMPORT, &[KeyCode::KeyK])
+            .init_resource::<Markers>()
+            .init_resource::<MarkerBadges>()
+            .init_resource::<MarkerImport>()
+            // Sent by OverlayPlugin's drop router
+            .add_event::<DataFileDropped>()
+            .add_systems(Startup, (setup_markers, setup_marker_import_panel))
+            .add_systems(Update, (
+                toggle_marker_import,
+                handle_marker_file_drop,
+                import_pending_markers,
+                handle_clear_markers_button,
+                update_marker_import_panel,
+                update_markers,
+            ).chain());
+    }
+}
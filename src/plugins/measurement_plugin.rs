@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, LayerManagerAppExt, Measurement};
+use crate::resources::input_map::TOGGLE_MEASURE;
+use crate::resources::layer_manager::MEASUREMENT_LAYER;
+use crate::systems::interaction::update_cursor_pick;
+use crate::systems::measurement::{
+    toggle_measurement,
+    place_measurement_points,
+    update_measurement_line,
+    setup_measurement_text,
+    update_measurement_text,
+};
+
+/// Plugin for measuring distances and areas on the map
+/// In measurement mode (M) clicks add ground points; distances and areas are geodesic,
+/// so they are true to the ground whatever the Web Mercator stretch at that latitude
+pub struct MeasurementPlugin;
+
+impl Plugin for MeasurementPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_MEASURE, &[KeyCode::KeyM])
+            .register_overlay_layer(MEASUREMENT_LAYER)
+            .init_resource::<Measurement>()
+            .add_systems(Startup, setup_measurement_text)
+            .add_systems(Update, (
+                toggle_measurement,
+                place_measurement_points,
+                update_measurement_line,
+                update_measurement_text,
+            ).chain().after(update_cursor_pick));
+    }
+}
@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use crate::resources::MinimapSettings;
+use crate::systems::minimap::{
+    setup_minimap,
+    update_minimap_camera,
+    update_minimap_heading,
+    minimap_click_to_fly,
+};
+
+/// Plugin for the top-down minimap in the corner of the screen
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MinimapSettings>()
+            .add_systems(Startup, setup_minimap)
+            .add_systems(Update, (
+                update_minimap_camera,
+                update_minimap_heading,
+                minimap_click_to_fly,
+            ));
+    }
+}
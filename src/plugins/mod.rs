@@ -1,28 +1,129 @@
 pub mod core_plugin;
 pub mod tiles_plugin;
+pub mod tile_debug_plugin;
 pub mod camera_plugin;
 pub mod interaction_plugin;
 pub mod ui_plugin;
+pub mod loading_plugin;
+pub mod comparison_plugin;
+pub mod stereo_plugin;
+pub mod overlay_plugin;
+pub mod minimap_plugin;
+pub mod keybindings_plugin;
+pub mod settings_plugin;
+pub mod style_plugin;
+pub mod game_plugin;
+pub mod environment_plugin;
+pub mod atmosphere_plugin;
+pub mod water_plugin;
+pub mod camera_path_plugin;
+pub mod measurement_plugin;
+pub mod routing_plugin;
+pub mod layer_plugin;
+pub mod weather_plugin;
+pub mod building_plugin;
+pub mod road_plugin;
+pub mod model_plugin;
+pub mod marker_plugin;
+pub mod street_lamp_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod overview_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod multiplayer_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scripting_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark_plugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod session_plugin;
 
 use bevy::prelude::*;
 use bevy::app::PluginGroupBuilder;
 
 pub use core_plugin::CorePlugin;
 pub use tiles_plugin::TilesPlugin;
+pub use tile_debug_plugin::TileDebugPlugin;
 pub use camera_plugin::CameraPlugin;
 pub use interaction_plugin::InteractionPlugin;
 pub use ui_plugin::UIPlugin;
+pub use loading_plugin::LoadingPlugin;
+pub use comparison_plugin::ComparisonPlugin;
+pub use stereo_plugin::StereoPlugin;
+pub use overlay_plugin::OverlayPlugin;
+pub use minimap_plugin::MinimapPlugin;
+pub use keybindings_plugin::KeybindingsPlugin;
+pub use settings_plugin::SettingsPlugin;
+pub use style_plugin::StylePlugin;
+pub use game_plugin::GamePlugin;
+pub use environment_plugin::EnvironmentPlugin;
+pub use atmosphere_plugin::AtmospherePlugin;
+pub use water_plugin::WaterPlugin;
+pub use camera_path_plugin::CameraPathPlugin;
+pub use measurement_plugin::MeasurementPlugin;
+pub use routing_plugin::RoutingPlugin;
+pub use layer_plugin::LayerPlugin;
+pub use weather_plugin::WeatherPlugin;
+pub use building_plugin::BuildingPlugin;
+pub use road_plugin::RoadPlugin;
+pub use model_plugin::ModelPlugin;
+pub use marker_plugin::MarkerPlugin;
+pub use street_lamp_plugin::StreetLampPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use overview_plugin::OverviewPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use multiplayer_plugin::MultiplayerPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use island_plugin::IslandPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use scripting_plugin::ScriptingPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use benchmark_plugin::BenchmarkPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+pub use session_plugin::SessionPlugin;
 
 /// Consolidated plugin struct that groups all application plugins
 pub struct AppPlugins;
 
 impl PluginGroup for AppPlugins {
     fn build(self) -> PluginGroupBuilder {
-        PluginGroupBuilder::start::<Self>()
+        let group = PluginGroupBuilder::start::<Self>()
             .add(CorePlugin)
+            .add(SettingsPlugin)
+            .add(StylePlugin)
+            .add(KeybindingsPlugin)
             .add(CameraPlugin)
+            .add(CameraPathPlugin)
             .add(TilesPlugin)
+            .add(TileDebugPlugin)
+            .add(LayerPlugin)
+            .add(WeatherPlugin)
+            // Overlay layers stack in the order they are added, buildings at the bottom
+            .add(BuildingPlugin)
+            .add(RoadPlugin)
+            .add(StreetLampPlugin)
+            .add(OverlayPlugin)
             .add(InteractionPlugin)
+            .add(MeasurementPlugin)
+            .add(RoutingPlugin)
             .add(UIPlugin)
+            .add(LoadingPlugin)
+            .add(ComparisonPlugin)
+            .add(StereoPlugin)
+            .add(MinimapPlugin)
+            .add(EnvironmentPlugin)
+            .add(AtmospherePlugin)
+            .add(WaterPlugin)
+            .add(ModelPlugin)
+            .add(MarkerPlugin)
+            .add(GamePlugin);
+
+        // Multiplayer needs the native WebSocket client, islands and scripts are read from disk,
+        // and the browser has one canvas to draw on
+        #[cfg(not(target_arch = "wasm32"))]
+        let group = group.add(MultiplayerPlugin).add(IslandPlugin).add(ScriptingPlugin).add(OverviewPlugin);
+
+        group
     }
 } 
\ No newline at end of file
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+use crate::events::SpawnGeoModel;
+use crate::systems::models::{spawn_configured_models, spawn_geo_models};
+
+/// Plugin for glTF models placed at geographic positions, by SpawnGeoModel events or the
+/// [[models]] tables of the settings file; their GeoAnchor keeps them there
+pub struct ModelPlugin;
+
+impl Plugin for ModelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SpawnGeoModel>()
+            .add_systems(Startup, spawn_configured_models)
+            .add_systems(Update, spawn_geo_models);
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy::input::InputSystem;
+use crate::resources::{Chat, InputMapAppExt, Multiplayer};
+use crate::resources::input_map::{OPEN_CHAT, PING_LOCATION};
+use crate::systems::chat::{open_chat, setup_chat, spawn_map_pings, type_chat, update_chat_overlay, update_map_pings};
+use crate::systems::multiplayer::{broadcast_position, connect_multiplayer, receive_presence, update_avatars};
+
+/// Plugin for multiplayer presence: connects to the relay server from the settings
+/// (`[multiplayer] server`), shares the camera position and shows the other users as avatars
+/// Enter opens the chat, T a map ping: a message pinned to the ground under the cursor
+pub struct MultiplayerPlugin;
+
+impl Plugin for MultiplayerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(OPEN_CHAT, &[KeyCode::Enter])
+            .register_input_action(PING_LOCATION, &[KeyCode::KeyT])
+            .init_resource::<Multiplayer>()
+            .init_resource::<Chat>()
+            .add_systems(Startup, setup_chat)
+            // Typing takes the keys before any other system sees them
+            .add_systems(PreUpdate, (type_chat, open_chat).chain().after(InputSystem))
+            .add_systems(Update, (
+                connect_multiplayer,
+                receive_presence,
+                broadcast_position,
+                update_avatars,
+                spawn_map_pings,
+                update_map_pings,
+                update_chat_overlay,
+            ).chain());
+    }
+}
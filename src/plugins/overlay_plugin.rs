@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use crate::overlays::street_labels::LabelFonts;
+use crate::resources::{LayerManagerAppExt, OverlaySettings, StreetLabelSettings};
+use crate::resources::layer_manager::{GPX_LAYER, STREET_NAMES_LAYER};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::layers::apply_imagery_layers;
+use crate::events::DataFileDropped;
+use crate::systems::overlays::{
+    handle_gpx_file_drop, handle_street_lines_drop, route_dropped_data_files, update_street_label_visibility,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::overlays::handle_heatmap_drop;
+
+/// Plugin for vector overlays rendered on top of the map (GPX tracks, routes, street names)
+/// Dropped CSV and GeoJSON files go to one of street labels, heatmaps and markers, see
+/// route_dropped_data; heatmaps become imagery layers, drawn into the tiles
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_overlay_layer(STREET_NAMES_LAYER)
+            .register_overlay_layer(GPX_LAYER)
+            .init_resource::<LabelFonts>()
+            .init_resource::<StreetLabelSettings>()
+            .init_resource::<OverlaySettings>()
+            .add_event::<DataFileDropped>()
+            .add_systems(Update, (
+                handle_gpx_file_drop,
+                route_dropped_data_files,
+                handle_street_lines_drop.after(route_dropped_data_files),
+                update_street_label_visibility,
+            ));
+
+        // Heatmaps are drawn from the dropped file, which the browser doesn't hand over as a path
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Update, handle_heatmap_drop.after(route_dropped_data_files).before(apply_imagery_layers));
+    }
+}
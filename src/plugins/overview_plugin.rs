@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, OverviewSettings};
+use crate::resources::input_map::TOGGLE_OVERVIEW_WINDOW;
+use crate::systems::overview::{close_overview_camera, toggle_overview_window, update_overview_camera};
+
+/// Plugin for the detached overview window, a top-down view of the map next to the main window
+pub struct OverviewPlugin;
+
+impl Plugin for OverviewPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_OVERVIEW_WINDOW, &[KeyCode::F9])
+            .init_resource::<OverviewSettings>()
+            .add_systems(Update, (
+                (toggle_overview_window, close_overview_camera).chain(),
+                update_overview_camera,
+            ));
+    }
+}
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use crate::resources::{LayerManagerAppExt, Overpass, RoadNetwork};
+use crate::resources::layer_manager::ROADS_LAYER;
+use crate::systems::roads::{request_road_tiles, receive_road_tiles};
+
+/// Plugin for drawing roads as ribbons over the tiles near the ground
+/// Up close raster tiles blur, so the roads around the camera are queried from the Overpass
+/// server in the settings and drawn with their class's width and color
+pub struct RoadPlugin;
+
+impl Plugin for RoadPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_overlay_layer(ROADS_LAYER)
+            .init_resource::<Overpass>()
+            .init_resource::<RoadNetwork>()
+            .add_systems(Update, (request_road_tiles, receive_road_tiles).chain());
+    }
+}
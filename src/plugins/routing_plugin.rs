@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, LayerManagerAppExt, Routing};
+use crate::resources::input_map::TOGGLE_ROUTING;
+use crate::resources::layer_manager::ROUTE_LAYER;
+use crate::systems::interaction::update_cursor_pick;
+use crate::systems::routing::{
+    toggle_routing,
+    place_route_points,
+    receive_route,
+    update_route_line,
+    setup_route_text,
+    update_route_text,
+};
+
+/// Plugin for routes between two points on the map
+/// In routing mode (R) the first click picks the start and the second the end; the route is
+/// requested from the OSRM server in the settings and drawn on the map
+pub struct RoutingPlugin;
+
+impl Plugin for RoutingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_ROUTING, &[KeyCode::KeyR])
+            .register_overlay_layer(ROUTE_LAYER)
+            .init_resource::<Routing>()
+            .add_systems(Startup, setup_route_text)
+            .add_systems(Update, (
+                toggle_routing,
+                place_route_points,
+                receive_route,
+                update_route_line,
+                update_route_text,
+            ).chain().after(update_cursor_pick));
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+use crate::resources::scripting::Scripting;
+use crate::systems::models::spawn_geo_models;
+use crate::systems::scripting::{apply_script_commands, handle_script_drop, load_configured_scripts, run_scripts};
+
+/// Plugin for Rhai scripts automating the viewer: tours, generated markers and models, small
+/// interactive experiences, without rebuilding the viewer
+/// Scripts listed in the settings file ([[scripts]] with a path) run at startup, dropped .rhai
+/// files when dropped; their top level code runs once, an `on_update(dt)` hook every frame
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Scripting>()
+            .add_systems(Startup, load_configured_scripts)
+            .add_systems(Update, (
+                handle_script_drop,
+                run_scripts,
+                apply_script_commands,
+            ).chain().before(spawn_geo_models));
+    }
+}
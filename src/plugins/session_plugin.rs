@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use crate::resources::session::CameraSession;
+use crate::systems::camera_path::play_camera_path;
+use crate::systems::session::{record_camera_session, replay_camera_session, start_camera_session};
+use crate::systems::tiles::{process_tiles, update_visible_tiles};
+
+/// Plugin recording camera sessions (--record-session) and replaying them (--replay-session)
+/// for regression testing: replay a session recorded before a change to the tile scheduler while
+/// recording a new one, and the two logs show the decisions of both for identical input
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraSession>()
+            .add_systems(Startup, start_camera_session)
+            // The camera is placed before the tile systems choose tiles for it
+            .add_systems(Update, replay_camera_session
+                .after(play_camera_path)
+                .before(process_tiles)
+                .before(update_visible_tiles))
+            .add_systems(Last, record_camera_session);
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use crate::events::SettingsChanged;
+use crate::resources::{HttpClient, InputMapAppExt, RebindState, TaskRuntime, UserSettings};
+use crate::resources::input_map::TOGGLE_SETTINGS_MENU;
+use crate::systems::settings::{announce_settings_changes, apply_settings};
+use crate::systems::settings_menu::{
+    toggle_settings_menu,
+    handle_settings_menu_buttons,
+    update_settings_menu_values,
+};
+
+/// Plugin that loads the user settings file at startup, saves it when the settings change,
+/// and sends SettingsChanged so other systems pick up new values without a restart
+/// The shared HTTP client and the background task runtime are created here as they are configured by the settings
+/// Also provides the in-game settings menu, which writes straight to UserSettings
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = UserSettings::load();
+
+        app
+            .insert_resource(HttpClient::new(&settings.user_agent))
+            .insert_resource(TaskRuntime::new(settings.worker_threads))
+            .insert_resource(settings)
+            .add_event::<SettingsChanged>()
+            .register_input_action(TOGGLE_SETTINGS_MENU, &[KeyCode::F10])
+            .init_resource::<RebindState>()
+            .add_systems(Update, (
+                toggle_settings_menu,
+                handle_settings_menu_buttons,
+                update_settings_menu_values,
+                announce_settings_changes,
+                apply_settings,
+            ).chain());
+    }
+}
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+use bevy::asset::load_internal_asset;
+use bevy::transform::TransformSystem;
+use crate::resources::StereoView;
+use crate::stereo::{AnaglyphMaterial, ANAGLYPH_SHADER_HANDLE};
+use crate::systems::comparison::manage_comparison_cameras;
+use crate::systems::stereo::{manage_stereo_cameras, resize_stereo_views, sync_stereo_eyes};
+
+/// Plugin for the stereo modes, picked in the settings menu: a red-cyan anaglyph or the two
+/// eyes side by side, drawn by two cameras on either side of the main camera
+pub struct StereoPlugin;
+
+impl Plugin for StereoPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, ANAGLYPH_SHADER_HANDLE, "../stereo/anaglyph.wgsl", Shader::from_wgsl);
+
+        app
+            .add_plugins(UiMaterialPlugin::<AnaglyphMaterial>::default())
+            .init_resource::<StereoView>()
+            // Switching stereo on switches the comparison off, whose cameras then go in the same frame
+            .add_systems(Update, (manage_stereo_cameras, resize_stereo_views).chain().before(manage_comparison_cameras))
+            // After every system moving the camera
+            .add_systems(PostUpdate, sync_stereo_eyes.before(TransformSystem::TransformPropagate));
+    }
+}
@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+use crate::resources::{LayerManagerAppExt, Overpass, StreetLamps};
+use crate::resources::layer_manager::STREET_LAMPS_LAYER;
+use crate::systems::street_lamps::{request_lamp_tiles, receive_lamp_tiles, update_street_lamps};
+
+/// Plugin for lighting the streets at night
+/// Close to the ground the street lamps around the camera are queried from the Overpass server,
+/// grouped into clusters and lit once the sun is down or the map is in night mode
+pub struct StreetLampPlugin;
+
+impl Plugin for StreetLampPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_overlay_layer(STREET_LAMPS_LAYER)
+            .init_resource::<Overpass>()
+            .init_resource::<StreetLamps>()
+            .add_systems(Update, (request_lamp_tiles, receive_lamp_tiles, update_street_lamps).chain());
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::resources::Style;
+use crate::resources::style::style_path;
+use crate::systems::style::{apply_fallback_tile_style, apply_label_style};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::StyleWatcher;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::style::reload_style;
+
+/// Plugin that loads the style file (fallback tile and island colors, GPX lines, label fonts)
+/// and, on the desktop, reloads it when it's edited so looks can be tweaked while the viewer runs
+/// Tiles and islands change color right away, overlays take the new style when they're loaded
+pub struct StylePlugin;
+
+impl Plugin for StylePlugin {
+    fn build(&self, app: &mut App) {
+        let path = style_path();
+        app
+            .insert_resource(Style::load(&path).unwrap_or_default())
+            .add_systems(Update, (apply_fallback_tile_style, apply_label_style));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app
+            .insert_resource(StyleWatcher::new(path))
+            .add_systems(Update, reload_style.before(apply_fallback_tile_style).before(apply_label_style));
+    }
+}
@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, TileDebugOverlay};
+use crate::resources::input_map::TOGGLE_TILE_OVERLAY;
+use crate::systems::tile_debug::{draw_tile_overlay, setup_tile_progress, toggle_tile_overlay};
+use crate::systems::tile_diagnostics::{register_tile_diagnostics, update_tile_diagnostics};
+use crate::systems::tiles::apply_pending_tiles;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::MetricsExport;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::tile_diagnostics::export_metrics;
+
+/// Plugin for the loading progress shown while tiles load, and the tile debug overlay (F3)
+/// that outlines every focus tile colored by its load state, to diagnose duplicated,
+/// overlapping or stuck tiles
+/// Also measures the tile system as Bevy diagnostics (tiles/...), shown with the FPS in
+/// debug mode and available to any other reader of the DiagnosticsStore
+/// Natively they're also written to the metrics file of the settings, if one is set
+pub struct TileDebugPlugin;
+
+impl Plugin for TileDebugPlugin {
+    fn build(&self, app: &mut App) {
+        register_tile_diagnostics(app);
+        app
+            .register_input_action(TOGGLE_TILE_OVERLAY, &[KeyCode::F3])
+            .init_resource::<TileDebugOverlay>()
+            .add_systems(Startup, setup_tile_progress)
+            .add_systems(Update, (
+                (toggle_tile_overlay, draw_tile_overlay).chain(),
+                update_tile_diagnostics,
+            ).after(apply_pending_tiles));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app
+            .init_resource::<MetricsExport>()
+            .add_systems(Update, export_metrics.after(update_tile_diagnostics));
+    }
+}
@@ -2,22 +2,105 @@ use bevy::prelude::*;
 use crate::systems::tiles::{
     process_tiles,
     apply_pending_tiles,
+    retry_failed_tiles,
     update_visible_tiles,
     cleanup_old_tiles,
     auto_detect_zoom_level,
+    release_atlas_layers,
+    rebuild_tile_batches,
+    apply_tile_appearance,
+    run_tile_generators,
+    enforce_tile_memory_budget,
+    send_tile_events,
+    parent_tiles_by_zoom,
+    setup_tile_compression,
+    store_compressed_tiles,
+    reprioritize_downloads,
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::cache_maintenance::run_cache_maintenance;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::systems::connectivity::{probe_network, resume_after_reconnect};
+use crate::systems::globe::{toggle_globe_mode, update_tile_globe};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::{CacheMaintenance, Connectivity};
+use crate::resources::{GlobeSettings, InputMapAppExt, TileAppearance, TileAtlas, TileGenerators, TileMemoryBudget, TileUploadBudget};
+use crate::resources::input_map::TOGGLE_GLOBE;
+use crate::resources::constants::REPRIORITIZE_INTERVAL;
+use bevy::time::common_conditions::on_timer;
+use std::time::Duration;
+use crate::osm::{TileMaterial, TILE_SHADER_HANDLE};
+use bevy::asset::load_internal_asset;
+use bevy::render::view::{check_visibility, VisibilitySystems};
+use crate::components::TileCoords;
+use crate::events::{TileDespawned, TileSpawned, TileVisibilityChanged};
 
 /// Plugin for managing OSM tiles
 pub struct TilesPlugin;
 
 impl Plugin for TilesPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (
-            process_tiles,
-            apply_pending_tiles,
-            update_visible_tiles,
-            cleanup_old_tiles,
-            auto_detect_zoom_level,
-        ));
+        load_internal_asset!(app, TILE_SHADER_HANDLE, "../osm/tile.wgsl", Shader::from_wgsl);
+
+        app
+            // Tiles are unlit map imagery, they neither cast shadows nor need a prepass
+            .add_plugins(MaterialPlugin::<TileMaterial> {
+                prepass_enabled: false,
+                shadows_enabled: false,
+                ..default()
+            })
+            .register_input_action(TOGGLE_GLOBE, &[KeyCode::KeyG])
+            .init_resource::<GlobeSettings>()
+            .init_resource::<TileAtlas>()
+            .init_resource::<TileAppearance>()
+            .init_resource::<TileGenerators>()
+            .init_resource::<TileMemoryBudget>()
+            .init_resource::<TileUploadBudget>()
+            .add_event::<TileSpawned>()
+            .add_event::<TileDespawned>()
+            .add_event::<TileVisibilityChanged>()
+            .add_systems(Startup, setup_tile_compression)
+            .add_systems(Update, (
+                process_tiles,
+                // Slots freed by dropped downloads go to the tiles selected in the same frame
+                reprioritize_downloads
+                    .run_if(on_timer(Duration::from_secs_f32(REPRIORITIZE_INTERVAL)))
+                    .before(process_tiles),
+                apply_pending_tiles,
+                store_compressed_tiles.after(apply_pending_tiles).before(rebuild_tile_batches),
+                release_atlas_layers,
+                apply_tile_appearance,
+                (toggle_globe_mode, update_tile_globe).chain().before(rebuild_tile_batches),
+                rebuild_tile_batches.after(apply_pending_tiles).after(release_atlas_layers),
+                run_tile_generators.after(apply_pending_tiles),
+                retry_failed_tiles.after(apply_pending_tiles).before(process_tiles),
+                update_visible_tiles,
+                enforce_tile_memory_budget.after(update_visible_tiles).after(apply_pending_tiles),
+                cleanup_old_tiles,
+                auto_detect_zoom_level,
+                parent_tiles_by_zoom
+                    .after(apply_pending_tiles)
+                    .after(rebuild_tile_batches)
+                    .after(update_visible_tiles)
+                    .after(cleanup_old_tiles)
+                    .after(enforce_tile_memory_budget),
+            ))
+            // Tiles have no mesh of their own (they are drawn in batches), so Bevy's
+            // mesh visibility check doesn't cover them
+            .add_systems(PostUpdate, (
+                check_visibility::<With<TileCoords>>.in_set(VisibilitySystems::CheckVisibility),
+                send_tile_events.after(VisibilitySystems::CheckVisibility),
+            ));
+
+        // There is no disk cache to maintain or fall back on in the browser
+        #[cfg(not(target_arch = "wasm32"))]
+        app
+            .init_resource::<CacheMaintenance>()
+            .init_resource::<Connectivity>()
+            .add_systems(Update, (
+                run_cache_maintenance,
+                probe_network,
+                resume_after_reconnect.after(apply_pending_tiles).before(process_tiles),
+            ));
     }
 } 
\ No newline at end of file
@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use crate::systems::ui::{setup_ui, update_zoom_level_text, update_tile_count_text, update_fps_counter};
+use crate::events::ShowToast;
+use crate::resources::InputMapAppExt;
+use crate::resources::input_map::COPY_LOCATION;
+use crate::systems::ui::{
+    setup_ui, update_zoom_level_text, update_tile_count_text, update_fps_counter, update_status_bar, update_server_warning,
+    update_toast, copy_location,
+};
 
 /// Plugin for managing UI elements like text displays
 pub struct UIPlugin;
@@ -8,14 +14,19 @@ pub struct UIPlugin;
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app
+            .register_input_action(COPY_LOCATION, &[KeyCode::KeyC])
+            .add_event::<ShowToast>()
             // Add diagnostics for FPS tracking
-            .add_plugins(FrameTimeDiagnosticsPlugin::default())
+            .add_plugins(FrameTimeDiagnosticsPlugin)
             // Add UI setup and update systems
             .add_systems(Startup, setup_ui)
             .add_systems(Update, (
                 update_zoom_level_text,
                 update_tile_count_text,
                 update_fps_counter,
+                update_status_bar,
+                update_server_warning,
+                (copy_location, update_toast).chain(),
             ));
     }
 } 
\ No newline at end of file
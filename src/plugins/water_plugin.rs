@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use bevy::asset::load_internal_asset;
+use crate::resources::{TileGeneratorAppExt, WaterSettings};
+use crate::systems::atmosphere::update_sky;
+use crate::systems::water::{spawn_tile_water, update_water_surfaces, update_water_visibility};
+use crate::water::{WaterMaterial, WATER_SHADER_HANDLE};
+
+/// Plugin adding an animated, reflective water surface to tiles that are mostly water
+/// Water is recognized by the water color of the map style; the surface reflects the sky
+/// of the EnvironmentPlugin and AtmospherePlugin
+pub struct WaterPlugin;
+
+impl Plugin for WaterPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, WATER_SHADER_HANDLE, "../water/water.wgsl", Shader::from_wgsl);
+
+        app
+            .add_plugins(MaterialPlugin::<WaterMaterial> {
+                prepass_enabled: false,
+                shadows_enabled: false,
+                ..default()
+            })
+            .init_resource::<WaterSettings>()
+            .register_tile_generator(spawn_tile_water)
+            .add_systems(Update, (update_water_surfaces, update_water_visibility).after(update_sky));
+    }
+}
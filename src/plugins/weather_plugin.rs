@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use crate::resources::{InputMapAppExt, WeatherRadar};
+use crate::resources::input_map::TOGGLE_WEATHER;
+use crate::systems::layers::apply_imagery_layers;
+use crate::systems::weather::{
+    toggle_weather,
+    receive_radar_frames,
+    handle_weather_buttons,
+    animate_radar,
+    setup_weather_panel,
+    update_weather_panel,
+};
+
+/// Plugin for the animated precipitation radar from RainViewer
+/// F8 loads the radar frames of the last two hours as imagery layers over the map and loops
+/// through them; the weather panel pauses the loop and steps through the frames
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_input_action(TOGGLE_WEATHER, &[KeyCode::F8])
+            .init_resource::<WeatherRadar>()
+            .add_systems(Startup, setup_weather_panel)
+            .add_systems(Update, (
+                toggle_weather,
+                receive_radar_frames,
+                handle_weather_buttons,
+                animate_radar,
+                update_weather_panel,
+            ).chain().before(apply_imagery_layers));
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use crate::resources::{CameraKeyframe, CameraPath};
+use crate::utils::geo::{GeoPos, GeoTransform};
+
+// Center of the benchmark region, Groningen like the start position, so a first run fills the
+// tile cache and later runs measure the pipeline rather than the tile servers
+const REGION_CENTER: (f64, f64) = (53.2194, 6.5665);
+const KM_PER_DEGREE_LAT: f64 = 111.32;
+
+// A pose of the benchmark sweep, relative to the region center
+struct SweepPoint {
+    time: f32,
+    east_km: f64,
+    north_km: f64,
+    altitude_m: f64,
+    yaw_degrees: f32,   // 0 looks north, -90 east
+    pitch_degrees: f32, // Negative looks down
+}
+
+const fn point(time: f32, east_km: f64, north_km: f64, altitude_m: f64, yaw_degrees: f32, pitch_degrees: f32) -> SweepPoint {
+    SweepPoint { time, east_km, north_km, altitude_m, yaw_degrees, pitch_degrees }
+}
+
+// Back and forth over the region looking down, then low over the center looking around at the
+// horizon, where the quadtree selects the most zoom levels at once, and finally up to a high view
+const SWEEP: &[SweepPoint] = &[
+    point(0.0, -4.0, 2.0, 1500.0, -90.0, -70.0),
+    point(10.0, 4.0, 2.0, 1500.0, -90.0, -70.0),
+    point(12.0, 4.0, 0.0, 1500.0, 90.0, -70.0),
+    point(22.0, -4.0, 0.0, 1500.0, 90.0, -70.0),
+    point(24.0, -4.0, -2.0, 1500.0, -90.0, -70.0),
+    point(34.0, 4.0, -2.0, 1500.0, -90.0, -70.0),
+    point(44.0, 0.0, 0.0, 150.0, 0.0, -15.0),
+    point(47.0, 0.0, 0.0, 150.0, -90.0, -15.0),
+    point(50.0, 0.0, 0.0, 150.0, -180.0, -15.0),
+    point(53.0, 0.0, 0.0, 150.0, -270.0, -15.0),
+    point(56.0, 0.0, 0.0, 150.0, -360.0, -15.0),
+    point(66.0, 0.0, 0.0, 20000.0, -360.0, -80.0),
+];
+
+/// The scripted camera sweep of the benchmark, in the current render coordinates
+pub fn benchmark_path() -> CameraPath {
+    let (lat, lon) = REGION_CENTER;
+    let km_per_degree_lon = KM_PER_DEGREE_LAT * lat.to_radians().cos();
+    CameraPath {
+        keyframes: SWEEP
+            .iter()
+            .map(|point| {
+                let position = GeoPos::new(lat + point.north_km / KM_PER_DEGREE_LAT, lon + point.east_km / km_per_degree_lon);
+                CameraKeyframe {
+                    time: point.time,
+                    translation: GeoTransform::new(position, point.altitude_m).translation(),
+                    rotation: Quat::from_euler(
+                        EulerRot::YXZ,
+                        point.yaw_degrees.to_radians(),
+                        point.pitch_degrees.to_radians(),
+                        0.0,
+                    ),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Measurements of a benchmark run (--benchmark), which flies the camera along benchmark_path
+/// once and quits with a report
+#[derive(Resource)]
+pub struct Benchmark {
+    pub warmup: f32,          // Seconds to wait before the sweep, while shaders compile and the first tiles load
+    pub started: bool,        // The sweep is playing
+    pub frame_times: Vec<f32>, // Seconds of every frame during the sweep
+    pub tiles_spawned: usize,
+    pub peak_texture_budget_bytes: usize, // Most tile texture memory counted by TileMemoryBudget at once
+}
+
+impl Default for Benchmark {
+    fn default() -> Self {
+        Self {
+            warmup: 3.0,
+            started: false,
+            frame_times: Vec::new(),
+            tiles_spawned: 0,
+            peak_texture_budget_bytes: 0,
+        }
+    }
+}
+
+impl Benchmark {
+    /// Summary of the measurements so far
+    pub fn report(&self) -> BenchmarkReport {
+        let mut sorted = self.frame_times.clone();
+        sorted.sort_by(f32::total_cmp);
+        let duration: f32 = sorted.iter().sum();
+        let milliseconds = |p: f32| percentile(&sorted, p) * 1000.0;
+
+        BenchmarkReport {
+            duration,
+            frames: sorted.len(),
+            tiles_per_second: if duration > 0.0 { self.tiles_spawned as f32 / duration } else { 0.0 },
+            frame_ms: [milliseconds(0.5), milliseconds(0.95), milliseconds(0.99), milliseconds(1.0)],
+            peak_texture_budget_mb: self.peak_texture_budget_bytes as f32 / (1024.0 * 1024.0),
+            peak_memory_mb: peak_memory_bytes().map(|bytes| bytes as f32 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+/// Outcome of a benchmark run
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkReport {
+    pub duration: f32,
+    pub frames: usize,
+    pub tiles_per_second: f32,      // Tile entities spawned per second
+    pub frame_ms: [f32; 4],         // Frame time percentiles: median, 95th, 99th and the slowest frame
+    pub peak_texture_budget_mb: f32, // Tile texture memory counted by TileMemoryBudget, not the GPU's own
+    pub peak_memory_mb: Option<f32>, // Peak resident memory of the process, where the platform reports it
+}
+
+impl std::fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} frames in {:.1}s, {:.1} tiles/s spawned", self.frames, self.duration, self.tiles_per_second)?;
+        writeln!(
+            f,
+            "Frame time: p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+            self.frame_ms[0], self.frame_ms[1], self.frame_ms[2], self.frame_ms[3]
+        )?;
+        write!(f, "Peak tile texture budget use: {:.1} MB", self.peak_texture_budget_mb)?;
+        match self.peak_memory_mb {
+            Some(memory) => write!(f, ", peak resident memory: {:.1} MB", memory),
+            None => write!(f, ", peak resident memory: unknown"),
+        }
+    }
+}
+
+// Nearest-rank percentile of sorted values, p from 0 to 1
+fn percentile(sorted: &[f32], p: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Peak resident memory of the process, from /proc on Linux
+fn peak_memory_bytes() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    parse_peak_memory(&std::fs::read_to_string("/proc/self/status").ok()?)
+}
+
+// The VmHWM ("high water mark") line of /proc/self/status, in kB
+fn parse_peak_memory(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted: Vec<f32> = (1..=100).map(|i| i as f32).collect();
+        assert_eq!(percentile(&sorted, 0.5), 50.0);
+        assert_eq!(percentile(&sorted, 0.99), 99.0);
+        assert_eq!(percentile(&sorted, 1.0), 100.0);
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn reports_tiles_per_second_and_peak_memory() {
+        let benchmark = Benchmark {
+            frame_times: vec![0.02; 50],
+            tiles_spawned: 30,
+            ..default()
+        };
+        let report = benchmark.report();
+        assert_eq!(report.frames, 50);
+        assert!((report.tiles_per_second - 30.0).abs() < 0.01);
+        assert!((report.frame_ms[1] - 20.0).abs() < 0.01);
+
+        let status = "Name:\tvibers\nVmPeak:\t  900000 kB\nVmHWM:\t  204800 kB\nVmRSS:\t  102400 kB\n";
+        assert_eq!(parse_peak_memory(status), Some(204800 * 1024));
+    }
+
+    #[test]
+    fn sweep_is_ordered_and_ends_high() {
+        let path = benchmark_path();
+        assert!(path.keyframes.windows(2).all(|pair| pair[0].time < pair[1].time));
+        let (first, last) = (path.keyframes[0].translation, path.keyframes.last().unwrap().translation);
+        assert!(last.y > first.y);
+    }
+}
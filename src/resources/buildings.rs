@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+use crate::resources::overpass::OverpassTiles;
+
+/// Buildings extruded from their footprints near the ground, see BuildingPlugin
+#[derive(Resource, Default)]
+pub struct Buildings {
+    pub tiles: OverpassTiles, // Loaded tiles hold a mesh entity with their buildings
+    pub material: Option<Handle<StandardMaterial>>, // Shared by the buildings of all tiles
+}
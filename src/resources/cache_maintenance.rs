@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use parking_lot::Mutex;
+use crate::osm::{IndexEntry, MaintenanceOutcome, MaintenancePolicy};
+
+/// Results handed back from maintenance tasks running on the Tokio runtime
+#[derive(Default)]
+pub struct MaintenanceInbox {
+    pub scanned: Option<(Vec<PathBuf>, HashMap<PathBuf, IndexEntry>)>, // (files, previous index)
+    pub outcomes: Vec<MaintenanceOutcome>,
+}
+
+// State of the idle background cache maintenance task
+#[derive(Resource)]
+pub struct CacheMaintenance {
+    pub policy: MaintenancePolicy,
+    pub batch_size: usize,    // Entries handled per background batch
+    pub pass_interval: f32,   // Seconds between full passes over the cache
+    pub idle_delay: f32,      // Seconds the tile pipeline must be quiet before work starts
+    pub queue: VecDeque<PathBuf>,
+    pub previous_index: HashMap<PathBuf, IndexEntry>,
+    pub index: HashMap<PathBuf, IndexEntry>,
+    pub pass_running: bool,
+    pub last_pass: Option<f32>,
+    pub idle_since: f32,
+    pub busy: Arc<AtomicBool>,
+    pub inbox: Arc<Mutex<MaintenanceInbox>>,
+    pub pruned: usize,
+    pub recompressed: usize,
+    pub corrupt: usize,
+}
+
+impl Default for CacheMaintenance {
+    fn default() -> Self {
+        Self {
+            policy: MaintenancePolicy {
+                max_age: Duration::from_secs(30 * 24 * 60 * 60),
+                recompress_after: Duration::from_secs(24 * 60 * 60),
+            },
+            batch_size: 8,
+            pass_interval: 600.0,
+            idle_delay: 2.0,
+            queue: VecDeque::new(),
+            previous_index: HashMap::new(),
+            index: HashMap::new(),
+            pass_running: false,
+            last_pass: None,
+            idle_since: 0.0,
+            busy: Arc::new(AtomicBool::new(false)),
+            inbox: Arc::new(Mutex::new(MaintenanceInbox::default())),
+            pruned: 0,
+            recompressed: 0,
+            corrupt: 0,
+        }
+    }
+}
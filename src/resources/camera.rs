@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use crate::resources::constants::MAX_ZOOM_LEVEL;
+use crate::utils::geo::tile_world_size;
+
+/// How the main camera is controlled
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// First-person flight: mouse look, WASD to move
+    #[default]
+    Fly,
+    /// Map-style: orbit around the ground point in the middle of the screen,
+    /// right-drag rotates, middle-drag pans
+    Orbit,
+    /// Street level: walk over the ground at eye height, see WalkSettings
+    Walk,
+}
+
+/// An in-progress camera flight started by a FlyTo event
+#[derive(Clone, Copy, Debug)]
+pub struct Flight {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+// Tracks the active camera flight, if any
+#[derive(Resource, Default)]
+pub struct CameraFlight {
+    pub active: Option<Flight>,
+}
+
+/// Keeps the camera near the render origin: once it gets further than `rebase_distance` world
+/// units away, everything is moved back by the camera's offset. Far from (0, 0), f32
+/// Transforms can't resolve high zoom tiles and their vertices jitter
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct FloatingOrigin {
+    pub rebase_distance: f32,
+}
+
+impl Default for FloatingOrigin {
+    fn default() -> Self {
+        // A zoom 19 tile is 1/64th of a world unit: within 64 units f32 resolves it to ~1e-4 of its size
+        Self { rebase_distance: 64.0 }
+    }
+}
+
+impl FloatingOrigin {
+    /// How far to move the render origin for a camera at `camera`, if it has to move at all
+    /// The offset is a whole number of the smallest tiles, which keeps shifted tile corners exact
+    pub fn rebase_offset(&self, camera: Vec3) -> Option<Vec2> {
+        let offset = camera.xz();
+        if offset.length() <= self.rebase_distance {
+            return None;
+        }
+        let step = tile_world_size(MAX_ZOOM_LEVEL) as f32;
+        Some((offset / step).round() * step)
+    }
+}
+
+// Velocity of the main camera from manual movement, used to predict where tiles are needed next
+#[derive(Resource, Default)]
+pub struct CameraMotion {
+    pub velocity: Vec3,
+}
+
+// Speed of the walking camera going up (jumping) or down (falling), in meters per second
+#[derive(Resource, Default)]
+pub struct WalkState {
+    pub vertical_speed: f32,
+}
+
+/// Height of the ground under the main camera in world units, which the camera is kept above
+/// (see MovementSettings::min_clearance): the map at 0, or the terrain of an island
+#[derive(Resource, Default)]
+pub struct GroundHeight {
+    pub under_camera: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebase_only_past_the_distance_by_whole_tiles() {
+        let origin = FloatingOrigin { rebase_distance: 64.0 };
+        assert_eq!(origin.rebase_offset(Vec3::new(40.0, 900.0, -40.0)), None);
+
+        let offset = origin.rebase_offset(Vec3::new(70.004, 5.0, -12.3)).unwrap();
+        let step = tile_world_size(MAX_ZOOM_LEVEL) as f32;
+        assert_eq!(offset, Vec2::new(70.0, -12.296875));
+        assert_eq!((offset / step).fract(), Vec2::ZERO);
+    }
+}
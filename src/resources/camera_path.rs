@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A recorded camera pose, `time` seconds into the path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+/// A camera path through keyframes, played back along a smooth curve
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>, // Ordered by time
+}
+
+impl CameraPath {
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Camera pose at a time along the path, clamped to its ends
+    /// Positions follow a Catmull-Rom spline through the keyframes so the camera doesn't
+    /// change direction abruptly at every keyframe; rotations are interpolated spherically
+    pub fn sample(&self, time: f32) -> Option<Transform> {
+        let keyframes = &self.keyframes;
+        let last = keyframes.len().checked_sub(1)?;
+        let next = keyframes.partition_point(|keyframe| keyframe.time <= time).clamp(1, last.max(1));
+        let (a, b) = (next - 1, next.min(last));
+
+        let span = keyframes[b].time - keyframes[a].time;
+        let t = if span > 0.0 { ((time - keyframes[a].time) / span).clamp(0.0, 1.0) } else { 0.0 };
+
+        let p0 = keyframes[a.saturating_sub(1)].translation;
+        let p1 = keyframes[a].translation;
+        let p2 = keyframes[b].translation;
+        let p3 = keyframes[(b + 1).min(last)].translation;
+        let translation = 0.5
+            * (2.0 * p1
+                + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+
+        let rotation = keyframes[a].rotation.slerp(keyframes[b].rotation, t);
+        Some(Transform::from_translation(translation).with_rotation(rotation))
+    }
+
+    /// Move every keyframe by an offset, e.g. when the render origin moves
+    pub fn shift(&mut self, offset: Vec3) {
+        for keyframe in &mut self.keyframes {
+            keyframe.translation += offset;
+        }
+    }
+
+    /// Serialize the path as JSON: a list of keyframes with time, translation and rotation
+    pub fn to_json(&self) -> String {
+        let keyframes: Vec<Value> = self
+            .keyframes
+            .iter()
+            .map(|keyframe| {
+                json!({
+                    "time": keyframe.time,
+                    "translation": keyframe.translation.to_array(),
+                    "rotation": keyframe.rotation.to_array(),
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&json!({ "keyframes": keyframes })).unwrap_or_default()
+    }
+
+    /// Parse a path written by `to_json`
+    pub fn from_json(contents: &str) -> Result<Self, anyhow::Error> {
+        let value: Value = serde_json::from_str(contents)?;
+        let keyframes = value["keyframes"]
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Camera path has no keyframes"))?;
+
+        let mut path = CameraPath::default();
+        for keyframe in keyframes {
+            let time = keyframe["time"]
+                .as_f64()
+                .ok_or_else(|| anyhow::anyhow!("Keyframe without a time"))?;
+            let translation = floats::<3>(&keyframe["translation"])?;
+            let rotation = floats::<4>(&keyframe["rotation"])?;
+            path.keyframes.push(CameraKeyframe {
+                time: time as f32,
+                translation: Vec3::from_array(translation),
+                rotation: Quat::from_array(rotation).normalize(),
+            });
+        }
+        path.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(path)
+    }
+
+    /// Write the path to a JSON file
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// Read a path from a JSON file
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+// Read a fixed-size array of numbers from JSON
+fn floats<const N: usize>(value: &Value) -> Result<[f32; N], anyhow::Error> {
+    let values = value
+        .as_array()
+        .filter(|values| values.len() == N)
+        .ok_or_else(|| anyhow::anyhow!("Expected {} numbers, got {}", N, value))?;
+
+    let mut result = [0.0; N];
+    for (slot, value) in result.iter_mut().zip(values) {
+        *slot = value
+            .as_f64()
+            .ok_or_else(|| anyhow::anyhow!("Expected a number, got {}", value))? as f32;
+    }
+    Ok(result)
+}
+
+/// What the camera path recorder is doing
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PathRecorderState {
+    #[default]
+    Idle,
+    /// Adding a keyframe every keyframe interval
+    Recording { elapsed: f32, since_keyframe: f32 },
+    /// Moving the camera along the path; with frame dumping the path advances a fixed step
+    /// per frame, so a run always produces the same frames whatever the frame rate
+    Playing { elapsed: f32, frame: Option<u32> },
+}
+
+/// Records camera paths and plays them back for flythrough videos
+#[derive(Resource)]
+pub struct PathRecorder {
+    pub state: PathRecorderState,
+    pub path: CameraPath,        // Last recorded or loaded path
+    pub keyframe_interval: f32,  // Seconds between recorded keyframes
+    pub file: PathBuf,           // Where recorded paths are saved and played back from
+    pub frames_dir: PathBuf,     // Where frames are written while dumping
+    pub frame_rate: f32,         // Frames per second of path time while dumping
+}
+
+impl Default for PathRecorder {
+    fn default() -> Self {
+        Self {
+            state: PathRecorderState::Idle,
+            path: CameraPath::default(),
+            keyframe_interval: 0.25,
+            file: PathBuf::from("camera_path.json"),
+            frames_dir: PathBuf::from("flythrough"),
+            frame_rate: 30.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32, yaw: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            translation: Vec3::new(x, 1.0, 0.0),
+            rotation: Quat::from_rotation_y(yaw),
+        }
+    }
+
+    fn path() -> CameraPath {
+        CameraPath {
+            keyframes: vec![keyframe(0.0, 0.0, 0.0), keyframe(1.0, 1.0, 0.5), keyframe(2.0, 2.0, 1.0), keyframe(3.0, 3.0, 1.5)],
+        }
+    }
+
+    #[test]
+    fn sampling_passes_through_keyframes_and_clamps_to_the_ends() {
+        let path = path();
+        for keyframe in &path.keyframes {
+            let pose = path.sample(keyframe.time).unwrap();
+            assert!(pose.translation.abs_diff_eq(keyframe.translation, 1e-5));
+            assert!(pose.rotation.abs_diff_eq(keyframe.rotation, 1e-5));
+        }
+        assert_eq!(path.sample(-1.0), path.sample(0.0));
+        assert_eq!(path.sample(5.0), path.sample(3.0));
+        // Evenly spaced keyframes on a line are followed at constant speed
+        assert!(path.sample(1.5).unwrap().translation.abs_diff_eq(Vec3::new(1.5, 1.0, 0.0), 1e-5));
+        assert_eq!(CameraPath::default().sample(0.0), None);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let path = path();
+        let loaded = CameraPath::from_json(&path.to_json()).unwrap();
+        assert_eq!(loaded.keyframes.len(), path.keyframes.len());
+        for (loaded, original) in loaded.keyframes.iter().zip(&path.keyframes) {
+            assert_eq!(loaded.time, original.time);
+            assert_eq!(loaded.translation, original.translation);
+            assert!(loaded.rotation.abs_diff_eq(original.rotation, 1e-6));
+        }
+        assert!(CameraPath::from_json("{\"keyframes\": [{\"time\": 0}]}").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// A message in the chat log
+pub struct ChatLine {
+    pub name: String,
+    pub text: String,
+    pub received: f32, // Elapsed seconds when it arrived, it fades from the overlay after a while
+}
+
+/// A chat message pinned to the map, shown as a marker there until it expires
+pub struct MapPing {
+    pub lat: f64,
+    pub lon: f64,
+    pub label: String,
+    pub expires: f32, // Elapsed seconds
+    pub marker: Option<(Entity, Entity)>, // Marker and its UI label, once spawned
+}
+
+/// A message being typed, with the map location it will be pinned to
+pub struct ChatDraft {
+    pub text: String,
+    pub location: Option<(f64, f64)>,
+}
+
+/// Chat between the users on the multiplayer server
+#[derive(Resource)]
+pub struct Chat {
+    pub lines: VecDeque<ChatLine>, // Oldest first
+    pub draft: Option<ChatDraft>,  // Some while typing, keys then go to the chat only
+    pub pings: Vec<MapPing>,
+    pub max_lines: usize,     // Lines kept in the log
+    pub visible_lines: usize, // Lines shown in the overlay
+    pub fade_after: f32,      // Seconds a line stays in the overlay while not typing
+    pub ping_duration: f32,   // Seconds a map ping stays on the map
+    pub max_length: usize,    // Characters in one message
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            draft: None,
+            pings: Vec::new(),
+            max_lines: 100,
+            visible_lines: 8,
+            fade_after: 15.0,
+            ping_duration: 10.0,
+            max_length: 300,
+        }
+    }
+}
+
+impl Chat {
+    /// Add a message to the log, pinning it to the map when it has a location
+    pub fn push(&mut self, name: &str, text: &str, location: Option<(f64, f64)>, now: f32) {
+        let text: String = text.chars().take(self.max_length).collect();
+        if let Some((lat, lon)) = location {
+            self.pings.push(MapPing {
+                lat,
+                lon,
+                label: format!("{}: {}", name, text),
+                expires: now + self.ping_duration,
+                marker: None,
+            });
+        }
+
+        self.lines.push_back(ChatLine { name: name.to_string(), text, received: now });
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    /// The lines the overlay shows: the latest ones while typing, otherwise only the recent ones
+    pub fn visible(&self, now: f32) -> impl Iterator<Item = &ChatLine> {
+        let typing = self.draft.is_some();
+        let skip = self.lines.len().saturating_sub(self.visible_lines);
+        self.lines
+            .iter()
+            .skip(skip)
+            .filter(move |line| typing || now - line.received < self.fade_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_log_keeps_the_latest_lines() {
+        let mut chat = Chat { max_lines: 3, ..default() };
+        for i in 0..5 {
+            chat.push("ann", &i.to_string(), None, 0.0);
+        }
+        let texts: Vec<&str> = chat.lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["2", "3", "4"]);
+        assert!(chat.pings.is_empty());
+    }
+
+    #[test]
+    fn old_lines_fade_unless_typing() {
+        let mut chat = Chat { visible_lines: 2, fade_after: 10.0, ..default() };
+        chat.push("ann", "old", None, 0.0);
+        chat.push("bob", "older", None, 1.0);
+        chat.push("ann", "new", Some((53.2, 6.5)), 20.0);
+
+        let visible: Vec<&str> = chat.visible(25.0).map(|line| line.text.as_str()).collect();
+        assert_eq!(visible, ["new"]);
+
+        chat.draft = Some(ChatDraft { text: String::new(), location: None });
+        let visible: Vec<&str> = chat.visible(25.0).map(|line| line.text.as_str()).collect();
+        assert_eq!(visible, ["older", "new"]);
+
+        assert_eq!(chat.pings.len(), 1);
+        assert_eq!(chat.pings[0].label, "ann: new");
+        assert_eq!(chat.pings[0].expires, 30.0);
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::osm::{TileId, DEFAULT_TILE_SERVER};
+use crate::resources::TileAtlas;
+use crate::resources::user_settings::{tile_provider, Attribution, SATELLITE_SERVER};
+
+/// Render layer of the comparison provider's tiles, seen by the comparison camera only
+pub const COMPARISON_RENDER_LAYER: usize = 1;
+/// Height the comparison tiles are drawn above the map's tiles: above the map's tiles of the
+/// same and a few more zoom levels, below the overlays (roads start at 0.008)
+pub const COMPARISON_LIFT: f32 = 0.0025;
+// The divider stays this far from the window's edges, so both halves stay visible
+const MIN_SIDE: f32 = 0.05;
+
+/// A tile of the comparison provider, loaded for the map tile entity it is drawn over
+pub struct ComparisonTile {
+    pub entity: Entity,
+    pub id: TileId,
+    pub image: Option<image::DynamicImage>, // None if it failed to load, the map's tile shows instead
+}
+
+/// Split-screen comparison of two tile providers (B by default): left of the divider the map
+/// is drawn as usual, right of it a second camera with the same view shows the same tiles with
+/// the imagery of another provider, which is loaded into an atlas of its own
+#[derive(Resource)]
+pub struct ProviderComparison {
+    pub active: bool,
+    pub provider: Option<String>, // URL template of the right side, None picks one when switched on
+    pub divider: f32,             // Position of the divider as a fraction of the window width
+    pub dragging: bool,           // The divider is being dragged
+    pub atlas: TileAtlas,
+    pub requested: HashSet<Entity>, // Tiles whose imagery was requested or is in the atlas
+    pub loaded: Arc<Mutex<Vec<ComparisonTile>>>, // Loaded by tasks, waiting for the atlas
+}
+
+impl Default for ProviderComparison {
+    fn default() -> Self {
+        Self {
+            active: false,
+            provider: None,
+            divider: 0.5,
+            dragging: false,
+            atlas: TileAtlas::default(),
+            requested: HashSet::new(),
+            loaded: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl ProviderComparison {
+    /// Move the divider to a fraction of the window width, keeping both sides visible
+    pub fn set_divider(&mut self, fraction: f32) {
+        self.divider = fraction.clamp(MIN_SIDE, 1.0 - MIN_SIDE);
+    }
+
+    /// Credit of the provider on the right side while comparing, None for custom servers
+    pub fn attribution(&self) -> Option<Attribution> {
+        let provider = self.provider.as_deref().filter(|_| self.active)?;
+        tile_provider(provider).map(|provider| provider.attribution)
+    }
+}
+
+/// The provider to compare the base map with when none was picked: the photo imagery, or the
+/// map when the base map shows photos already
+pub fn default_comparison_provider(base_url: &str) -> &'static str {
+    if base_url == SATELLITE_SERVER {
+        DEFAULT_TILE_SERVER
+    } else {
+        SATELLITE_SERVER
+    }
+}
+
+/// Physical position and size of the left and right viewports for a window and divider
+/// Each side keeps at least one pixel
+pub fn split_viewports(window: UVec2, divider: f32) -> ((UVec2, UVec2), (UVec2, UVec2)) {
+    let split = ((window.x as f32 * divider).round() as u32).clamp(1, window.x.saturating_sub(1).max(1));
+    let left = (UVec2::ZERO, UVec2::new(split, window.y));
+    let right = (UVec2::new(split, 0), UVec2::new(window.x.saturating_sub(split).max(1), window.y));
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewports_split_the_window_at_the_divider() {
+        let (left, right) = split_viewports(UVec2::new(1280, 720), 0.25);
+        assert_eq!(left, (UVec2::ZERO, UVec2::new(320, 720)));
+        assert_eq!(right, (UVec2::new(320, 0), UVec2::new(960, 720)));
+
+        let mut comparison = ProviderComparison::default();
+        comparison.set_divider(1.2);
+        let (left, right) = split_viewports(UVec2::new(1000, 500), comparison.divider);
+        assert_eq!((left.1.x, right.1.x), (950, 50));
+
+        assert_eq!(default_comparison_provider(DEFAULT_TILE_SERVER), SATELLITE_SERVER);
+        assert_eq!(default_comparison_provider(SATELLITE_SERVER), DEFAULT_TILE_SERVER);
+    }
+}
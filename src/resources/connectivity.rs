@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+// Seconds between probe requests while offline
+const PROBE_INTERVAL: f32 = 10.0;
+
+/// The tile loader's offline mode as the viewer last saw it, see osm::connectivity
+/// While offline tiles come from the disk cache only and the network is probed until it's back
+#[derive(Resource)]
+pub struct Connectivity {
+    pub offline: bool,
+    pub probe_timer: Timer,
+    pub probing: Arc<AtomicBool>, // A probe request is in flight
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            probe_timer: Timer::from_seconds(PROBE_INTERVAL, TimerMode::Repeating),
+            probing: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
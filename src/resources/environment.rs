@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use crate::utils::solar::SunPosition;
+
+/// Time of the simulated world, driving the sun
+/// Follows the wall clock until it is scrubbed, then runs on from the chosen time
+#[derive(Resource)]
+pub struct WorldClock {
+    pub unix_seconds: f64,
+    pub real_time: bool, // Follow the wall clock
+    pub speed: f64,      // Simulated seconds per real second when not following the wall clock
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self {
+            unix_seconds: wall_clock_seconds(),
+            real_time: true,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Current wall-clock time as Unix seconds
+pub fn wall_clock_seconds() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+/// Where the sun is seen from below the camera, updated every frame
+#[derive(Resource, Default)]
+pub struct Sun {
+    pub position: SunPosition,
+    pub daylight: f32, // 0 at night, 1 in full daylight, in between during twilight
+}
+
+impl Sun {
+    /// World direction towards the sun; north is -Z, east is +X
+    pub fn direction(&self) -> Vec3 {
+        let azimuth = self.position.azimuth.to_radians() as f32;
+        let elevation = self.position.elevation.to_radians() as f32;
+        Vec3::new(
+            azimuth.sin() * elevation.cos(),
+            elevation.sin(),
+            -azimuth.cos() * elevation.cos(),
+        )
+    }
+}
+
+/// Light and sky colors for day and night, blended by Sun::daylight
+#[derive(Resource)]
+pub struct EnvironmentSettings {
+    pub day_illuminance: f32,   // DirectionalLight illuminance with the sun up
+    pub night_illuminance: f32, // Moonlight
+    pub day_ambient: f32,       // AmbientLight brightness
+    pub night_ambient: f32,
+    pub day_sky: Color,
+    pub twilight_sky: Color, // Sky color with the sun right at the horizon
+    pub night_sky: Color,
+    pub haze: Color, // Daytime haze lightening the sky towards the horizon
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            day_illuminance: 10000.0,
+            night_illuminance: 50.0,
+            day_ambient: 0.5,
+            night_ambient: 0.05,
+            day_sky: Color::srgb(0.53, 0.75, 0.95),
+            twilight_sky: Color::srgb(0.85, 0.5, 0.35),
+            night_sky: Color::srgb(0.02, 0.03, 0.08),
+            haze: Color::srgb(0.78, 0.85, 0.92),
+        }
+    }
+}
+
+// Sun elevation range in degrees around the horizon that tints the sky with twilight colors
+const TWILIGHT_ELEVATION: f64 = 8.0;
+
+impl EnvironmentSettings {
+    /// Color of the sky overhead for the sun's position
+    pub fn sky_color(&self, sun: &Sun) -> Color {
+        let twilight = (1.0 - sun.position.elevation.abs() / TWILIGHT_ELEVATION).clamp(0.0, 1.0) as f32;
+        self.night_sky
+            .mix(&self.day_sky, sun.daylight)
+            .mix(&self.twilight_sky, twilight * 0.6)
+    }
+
+    /// Color of the sky at the horizon, hazier than overhead during the day
+    pub fn horizon_color(&self, sun: &Sun) -> Color {
+        self.sky_color(sun).mix(&self.haze, 0.5 * sun.daylight)
+    }
+}
+
+/// Sky dome and distance fog
+/// The fog thickens towards the edge of the loaded tiles, which lies further away the higher
+/// the camera is, so the fog thins out with altitude like haze seen from a plane
+#[derive(Resource)]
+pub struct AtmosphereSettings {
+    pub fog_enabled: bool,
+    pub visibility_factor: f32, // Distance where the fog hides the map, as a fraction of the loaded extent
+}
+
+impl Default for AtmosphereSettings {
+    fn default() -> Self {
+        Self {
+            fog_enabled: true,
+            visibility_factor: 0.9,
+        }
+    }
+}
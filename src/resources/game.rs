@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+/// A circular geofence that sends ZoneEntered/ZoneExited as the camera crosses it
+#[derive(Clone, Debug)]
+pub struct TriggerZone {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_m: f64,
+}
+
+/// A collectible placed at a fixed coordinate, spawned with whichever tile covers it
+#[derive(Clone, Debug)]
+pub struct CollectibleSpawn {
+    pub lat: f64,
+    pub lon: f64,
+    pub points: u32,
+}
+
+/// Location-based game content; zones and collectibles are referred to by their index
+#[derive(Resource)]
+pub struct GameContent {
+    pub zones: Vec<TriggerZone>,
+    pub collectibles: Vec<CollectibleSpawn>,
+    pub pickup_radius_m: f64, // How close the camera has to get to a collectible
+}
+
+impl Default for GameContent {
+    fn default() -> Self {
+        Self {
+            zones: Vec::new(),
+            collectibles: Vec::new(),
+            pickup_radius_m: 25.0,
+        }
+    }
+}
+
+/// Points scored so far and the collectibles they came from
+#[derive(Resource, Default)]
+pub struct Score {
+    pub points: u32,
+    pub collected: HashSet<usize>, // Collected collectibles are not spawned again with their tile
+}
+
+/// Zones the camera is currently inside
+#[derive(Resource, Default)]
+pub struct ActiveZones {
+    pub inside: HashSet<usize>,
+}
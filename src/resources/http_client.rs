@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+use reqwest::Client;
+
+/// User agent sent with every request unless the settings file sets another
+/// Tile servers like OSM's require one that identifies the application and where to reach its authors
+pub const DEFAULT_USER_AGENT: &str =
+    concat!("vibe-world/", env!("CARGO_PKG_VERSION"), " (+", env!("CARGO_PKG_REPOSITORY"), ")");
+
+/// The placeholder user agent earlier versions saved to the settings file, replaced by the default
+pub const PLACEHOLDER_USER_AGENT: &str = "bevy_osm_viewer/0.1.0 (github.com/user/bevy_osm_viewer)";
+
+// Idle connections kept open per host, enough for the concurrent tile loads of one frame
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_IDLE_PER_HOST: usize = 16;
+#[cfg(not(target_arch = "wasm32"))]
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The HTTP client all downloads go through, so they share one connection pool
+/// Clones share the pool too; loader tasks take a clone of the client
+#[derive(Resource, Clone)]
+pub struct HttpClient {
+    pub client: Client,
+    pub user_agent: String,
+}
+
+impl HttpClient {
+    pub fn new(user_agent: &str) -> Self {
+        // Gzip is asked for: tiles are PNG or JPEG and come back as they are, but the Overpass
+        // JSON and other text responses shrink a lot
+        let builder = Client::builder().user_agent(user_agent).gzip(true);
+
+        // HTTP/2 is negotiated with ALPN where the server offers it, multiplexing the tile
+        // requests over one connection per host. In the browser fetch manages the connections
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = builder
+            .pool_max_idle_per_host(MAX_IDLE_PER_HOST)
+            .pool_idle_timeout(IDLE_TIMEOUT)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .http2_adaptive_window(true)
+            .tcp_nodelay(true);
+
+        let client = builder
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("Failed to configure the HTTP client, using defaults: {}", e);
+                Client::new()
+            });
+
+        Self {
+            client,
+            user_agent: user_agent.to_string(),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use crate::osm::{LayerSource, DEFAULT_TILE_SERVER};
+use crate::resources::environment::wall_clock_seconds;
+use crate::resources::user_settings::{tile_provider, Attribution, ESRI_ATTRIBUTIONS, SATELLITE_SERVER};
+
+/// Id of the base map layer, whose tile server is picked in the settings menu
+pub const BASE_LAYER_ID: u32 = 0;
+/// Id of the photo layer covering the map in photo view
+pub const SATELLITE_LAYER_ID: u32 = 1;
+
+// Relief shading to lay over the map
+const HILLSHADE_SERVER: &str =
+    "https://server.arcgisonline.com/ArcGIS/rest/services/Elevation/World_Hillshade/MapServer/tile/{z}/{y}/{x}";
+const HILLSHADE_ATTRIBUTION: Attribution = Attribution {
+    text: "Hillshade © Esri - Source: Esri, USGS, NGA, NASA, CGIAR and the GIS User Community",
+    link: ESRI_ATTRIBUTIONS,
+};
+
+// NASA's daily true color satellite mosaic, a day's imagery published during the next day
+const DAILY_SATELLITE_SERVER: &str = "https://gibs.earthdata.nasa.gov/wmts/epsg3857/best/MODIS_Terra_CorrectedReflectance_TrueColor/default/{time}/GoogleMapsCompatible_Level9/{z}/{y}/{x}.jpg";
+const DAILY_SATELLITE_ATTRIBUTION: Attribution = Attribution {
+    text: "Imagery © NASA EOSDIS GIBS",
+    link: "https://www.earthdata.nasa.gov/gibs",
+};
+
+/// Placeholder in layer URL templates for the date of the imagery, as YYYY-MM-DD
+pub const TIME_PLACEHOLDER: &str = "{time}";
+// Days a layer with a time placeholder can go back, ending yesterday as daily sources publish
+// a day's imagery during the next day
+const TIMELINE_DAYS: i64 = 30;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Date picked on the imagery timeline while its knob is dragged, applied when the drag ends
+#[derive(Resource, Default)]
+pub struct TimelineScrub {
+    pub index: Option<usize>,
+}
+
+/// A raster tile source in the layer stack
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageryLayer {
+    pub id: u32,
+    pub name: String,
+    pub url: String,         // Tile URL template
+    pub attribution: Option<Attribution>, // Credit shown while the layer is visible, None for custom servers
+    pub opacity: f32,
+    pub visible: bool,
+    pub listed: bool,        // Shown in the layer panel; of the weather radar frames only the shown one is
+    pub times: Vec<String>,  // Dates the time placeholder can take, oldest first; empty without one
+    pub time_index: usize,   // The date shown
+}
+
+impl ImageryLayer {
+    /// The tile URL template with the chosen date filled in
+    pub fn source_url(&self) -> String {
+        match self.times.get(self.time_index) {
+            Some(time) => self.url.replace(TIME_PLACEHOLDER, time),
+            None => self.url.clone(),
+        }
+    }
+
+    // Point the layer at a URL template, showing the latest date if it has a time placeholder
+    fn set_url(&mut self, url: &str) {
+        self.url = url.to_string();
+        self.times = if url.contains(TIME_PLACEHOLDER) { daily_timeline(wall_clock_seconds()) } else { Vec::new() };
+        self.time_index = self.times.len().saturating_sub(1);
+    }
+}
+
+/// Raster sources stacked into the map imagery, bottom first, with their opacity
+/// Changing the stack recomposites the loaded tiles from the cached imagery of every layer
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ImageryLayers {
+    pub layers: Vec<ImageryLayer>,
+    next_id: u32,
+}
+
+impl Default for ImageryLayers {
+    fn default() -> Self {
+        let mut layers = Self {
+            layers: Vec::new(),
+            next_id: BASE_LAYER_ID,
+        };
+        // Added in the order of their ids
+        for (url, opacity, visible) in [(DEFAULT_TILE_SERVER, 1.0, true), (SATELLITE_SERVER, 1.0, false)] {
+            let provider = tile_provider(url).expect("built-in layers use preset providers");
+            layers.add(provider.name, url, Some(provider.attribution), opacity, visible);
+        }
+        layers.add("Hillshade", HILLSHADE_SERVER, Some(HILLSHADE_ATTRIBUTION), 0.3, false);
+        layers.add("Daily satellite", DAILY_SATELLITE_SERVER, Some(DAILY_SATELLITE_ATTRIBUTION), 1.0, false);
+        layers
+    }
+}
+
+impl ImageryLayers {
+    /// Put a layer on top of the stack, returning its id
+    pub fn add(&mut self, name: &str, url: &str, attribution: Option<Attribution>, opacity: f32, visible: bool) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut layer = ImageryLayer {
+            id,
+            name: name.to_string(),
+            url: String::new(),
+            attribution,
+            opacity,
+            visible,
+            listed: true,
+            times: Vec::new(),
+            time_index: 0,
+        };
+        layer.set_url(url);
+        self.layers.push(layer);
+        id
+    }
+
+    /// Take a layer off the stack
+    pub fn remove(&mut self, id: u32) {
+        self.layers.retain(|layer| layer.id != id);
+    }
+
+    /// Look up a layer by id
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut ImageryLayer> {
+        self.layers.iter_mut().find(|layer| layer.id == id)
+    }
+
+    /// Point the base map layer at a tile server, named and credited after its preset
+    /// A custom server from the settings file comes without a known credit
+    pub fn set_base_server(&mut self, url: &str) {
+        let provider = tile_provider(url);
+        if let Some(base) = self.get_mut(BASE_LAYER_ID) {
+            base.set_url(url);
+            base.name = provider.map_or("Custom", |provider| provider.name).to_string();
+            base.attribution = provider.map(|provider| provider.attribution);
+        }
+    }
+
+    /// The layer the imagery timeline picks the date of: the topmost visible layer with dates
+    pub fn timeline_layer(&self) -> Option<&ImageryLayer> {
+        self.layers.iter().rev().find(|layer| layer.visible && !layer.times.is_empty())
+    }
+
+    /// Move a listed layer up (positive) or down (negative) past as many other listed layers,
+    /// so every click on the layer panel visibly moves it
+    pub fn move_layer(&mut self, id: u32, step: i32) {
+        let listed: Vec<usize> = (0..self.layers.len()).filter(|&i| self.layers[i].listed).collect();
+        let Some(rank) = listed.iter().position(|&i| self.layers[i].id == id) else {
+            return;
+        };
+        let index = listed[rank];
+        let target = listed[(rank as i32 + step).clamp(0, listed.len() as i32 - 1) as usize];
+        let layer = self.layers.remove(index);
+        self.layers.insert(target, layer);
+    }
+
+    /// Credits of the visible layers, top of the stack first, without repeats
+    pub fn attributions(&self) -> Vec<Attribution> {
+        let mut attributions = Vec::new();
+        for layer in self.layers.iter().rev() {
+            let Some(attribution) = layer.attribution.filter(|_| layer.visible && layer.opacity > 0.0) else {
+                continue;
+            };
+            if !attributions.contains(&attribution) {
+                attributions.push(attribution);
+            }
+        }
+        attributions
+    }
+
+    /// The layers tiles are built from: the visible ones, bottom first
+    pub fn sources(&self) -> Vec<LayerSource> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.visible && layer.opacity > 0.0)
+            .map(|layer| LayerSource {
+                id: layer.id,
+                url: layer.source_url(),
+                opacity: layer.opacity,
+            })
+            .collect()
+    }
+}
+
+/// The dates of the days before `unix_seconds`, oldest first, as YYYY-MM-DD in UTC
+pub fn daily_timeline(unix_seconds: f64) -> Vec<String> {
+    let today = (unix_seconds / SECONDS_PER_DAY).floor() as i64;
+    (today - TIMELINE_DAYS..today).map(iso_date).collect()
+}
+
+// Date of a day counted from 1970-01-01, with the proleptic Gregorian calendar
+// Days are shifted to start the year in March, so the leap day ends a year
+fn iso_date(days: i64) -> String {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_follow_the_calendar() {
+        assert_eq!(iso_date(0), "1970-01-01");
+        assert_eq!(iso_date(19_782), "2024-02-29");
+        assert_eq!(iso_date(19_783), "2024-03-01");
+        assert_eq!(iso_date(-1), "1969-12-31");
+
+        // Noon on 2026-10-16: the month before, up to yesterday
+        let timeline = daily_timeline(1_792_152_000.0);
+        assert_eq!(timeline.len(), 30);
+        assert_eq!(timeline.first().map(String::as_str), Some("2026-09-16"));
+        assert_eq!(timeline.last().map(String::as_str), Some("2026-10-15"));
+    }
+
+    #[test]
+    fn time_templates_show_the_chosen_date() {
+        let mut layers = ImageryLayers::default();
+        let id = layers.add("Daily", "https://example.org/{time}/{z}/{x}/{y}.jpg", None, 1.0, true);
+        let layer = layers.get_mut(id).unwrap();
+        assert_eq!(layer.time_index, layer.times.len() - 1);
+        layer.time_index = 0;
+        let expected = format!("https://example.org/{}/{{z}}/{{x}}/{{y}}.jpg", layer.times[0]);
+        assert_eq!(layer.source_url(), expected);
+
+        assert!(layers.sources().iter().any(|source| source.url == expected));
+        assert!(layers.get_mut(BASE_LAYER_ID).unwrap().times.is_empty());
+    }
+
+    #[test]
+    fn moves_step_over_unlisted_layers() {
+        let mut layers = ImageryLayers::default();
+        for _ in 0..2 {
+            let frame = layers.add("Weather radar", "https://example.org/{z}/{x}/{y}.png", None, 0.6, false);
+            layers.get_mut(frame).unwrap().listed = false;
+        }
+        let heatmap = layers.add("Heatmap", "heatmap://points.csv", None, 0.7, true);
+
+        layers.move_layer(heatmap, -1);
+        let ids: Vec<u32> = layers.layers.iter().map(|layer| layer.id).collect();
+        assert_eq!(ids, [0, 1, 2, 6, 3, 4, 5]);
+    }
+}
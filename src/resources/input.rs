@@ -6,4 +6,73 @@ pub struct MouseLookState {
     pub mouse_motion: Vec2,
     pub pitch: f32,
     pub yaw: f32,
-} 
\ No newline at end of file
+} 
+// What is under the cursor (or the screen centre while the mouse is locked)
+#[derive(Resource, Default)]
+pub struct CursorPick {
+    pub world: Option<Vec3>,     // Ground intersection in world coordinates
+    pub lat_lon: Option<(f64, f64)>,
+    pub tile: Option<(u32, u32, u32)>, // (x, y, zoom) at the current zoom level
+}
+
+// Tracks clicks for double-click detection
+#[derive(Resource, Default)]
+pub struct DoubleClickState {
+    pub last_click: Option<f32>, // Time of the previous left click in seconds
+}
+
+// Tracks a right click for "what's here?", which only counts when the mouse wasn't dragged
+// (right-drag orbits the camera)
+#[derive(Resource, Default)]
+pub struct WhatsHereClick {
+    pub pressed: bool,
+    pub dragged_px: f32, // Mouse motion since the button went down
+}
+
+/// How two fingers on a touchscreen moved since the previous frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pinch {
+    pub previous_center: Vec2, // Midpoint between the fingers in the previous frame, in screen pixels
+    pub center: Vec2,          // ... and now
+    pub scale: f32,            // Previous distance between the fingers over the current one, below 1 when they spread
+    pub rotation: f32,         // Radians the fingers turned, clockwise on screen
+}
+
+impl Pinch {
+    /// The pinch between the fingers' previous and current positions, None while they touch
+    pub fn between(previous: [Vec2; 2], current: [Vec2; 2]) -> Option<Self> {
+        let (before, after) = (previous[1] - previous[0], current[1] - current[0]);
+        if before.length() < f32::EPSILON || after.length() < f32::EPSILON {
+            return None;
+        }
+
+        Some(Self {
+            previous_center: (previous[0] + previous[1]) / 2.0,
+            center: (current[0] + current[1]) / 2.0,
+            scale: before.length() / after.length(),
+            // Screen Y points down, so the counterclockwise angle of y-up math turns clockwise
+            rotation: before.angle_to(after),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn spreading_and_twisting_fingers() {
+        let spread = Pinch::between([Vec2::new(90.0, 100.0), Vec2::new(110.0, 100.0)], [Vec2::new(80.0, 100.0), Vec2::new(120.0, 100.0)]).unwrap();
+        assert_eq!(spread.center, Vec2::new(100.0, 100.0));
+        assert_eq!(spread.scale, 0.5);
+        assert_eq!(spread.rotation, 0.0);
+
+        // From pointing right to pointing down the screen is a quarter turn clockwise
+        let twist = Pinch::between([Vec2::ZERO, Vec2::new(10.0, 0.0)], [Vec2::ZERO, Vec2::new(0.0, 10.0)]).unwrap();
+        assert!((twist.rotation - FRAC_PI_2).abs() < 1e-6);
+        assert_eq!(twist.scale, 1.0);
+
+        assert_eq!(Pinch::between([Vec2::ZERO, Vec2::ZERO], [Vec2::ZERO, Vec2::ONE]), None);
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use crate::resources::user_settings::{read_settings_table, write_settings_table};
+
+// Table inside the settings file holding the key bindings
+const KEYBINDINGS_TABLE: &str = "keybindings";
+
+// Names of the built-in actions
+pub const MOVE_FORWARD: &str = "move_forward";
+pub const MOVE_BACKWARD: &str = "move_backward";
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+pub const MOVE_UP: &str = "move_up";
+pub const MOVE_DOWN: &str = "move_down";
+pub const BOOST: &str = "boost";
+pub const TOGGLE_CAMERA_MODE: &str = "toggle_camera_mode";
+pub const TOGGLE_WALK_MODE: &str = "toggle_walk_mode";
+pub const COPY_LOCATION: &str = "copy_location";
+pub const RECORD_CAMERA_PATH: &str = "record_camera_path";
+pub const PLAY_CAMERA_PATH: &str = "play_camera_path";
+pub const RENDER_CAMERA_PATH: &str = "render_camera_path";
+pub const TOGGLE_CURSOR_GRAB: &str = "toggle_cursor_grab";
+pub const TOGGLE_DEBUG: &str = "toggle_debug";
+pub const TOGGLE_GLOBE: &str = "toggle_globe";
+pub const TOGGLE_KEYBINDINGS: &str = "toggle_keybindings";
+pub const TOGGLE_LAYERS: &str = "toggle_layers";
+pub const TOGGLE_MARKER_IMPORT: &str = "toggle_marker_import";
+pub const TOGGLE_MEASURE: &str = "toggle_measure";
+pub const TOGGLE_ROUTING: &str = "toggle_routing";
+pub const TOGGLE_WEATHER: &str = "toggle_weather";
+pub const TOGGLE_PHOTO_VIEW: &str = "toggle_photo_view";
+pub const TOGGLE_SETTINGS_MENU: &str = "toggle_settings_menu";
+pub const TOGGLE_TILE_OVERLAY: &str = "toggle_tile_overlay";
+pub const ZOOM_OUT_MODIFIER: &str = "zoom_out_modifier";
+pub const OPEN_CHAT: &str = "open_chat";
+pub const PING_LOCATION: &str = "ping_location";
+pub const TOGGLE_ISLAND_EDITING: &str = "toggle_island_editing";
+pub const NEXT_BRUSH_TOOL: &str = "next_brush_tool";
+pub const BRUSH_LARGER: &str = "brush_larger";
+pub const BRUSH_SMALLER: &str = "brush_smaller";
+pub const SWITCH_EDIT_MODE: &str = "switch_edit_mode";
+pub const DELETE_OBJECT: &str = "delete_object";
+pub const TOGGLE_ISLAND_GRID: &str = "toggle_island_grid";
+pub const TOGGLE_ISLAND_LIST: &str = "toggle_island_list";
+pub const TOGGLE_OVERVIEW_WINDOW: &str = "toggle_overview_window";
+pub const TOGGLE_COMPARISON: &str = "toggle_comparison";
+
+/// A named action and the keys bound to it
+#[derive(Clone, Debug)]
+pub struct InputAction {
+    pub name: String,
+    pub keys: Vec<KeyCode>,
+    pub default_keys: Vec<KeyCode>,
+}
+
+/// Maps named actions to keys
+/// Plugins register their actions with defaults, bindings from the settings file take precedence
+#[derive(Resource, Default)]
+pub struct InputMap {
+    pub actions: Vec<InputAction>, // In registration order, which is also the order shown in the UI
+    loaded: Vec<(String, Vec<KeyCode>)>, // Bindings read from the settings file
+}
+
+impl InputMap {
+    /// Create an input map with the bindings from the settings file, if there is one
+    pub fn load() -> Self {
+        let loaded = read_settings_table()
+            .map(|table| parse_keybindings(&table))
+            .unwrap_or_default();
+
+        Self { actions: Vec::new(), loaded }
+    }
+
+    /// Register an action with its default keys
+    /// A binding from the settings file replaces the defaults; registering twice is a no-op
+    pub fn register(&mut self, name: &str, default_keys: &[KeyCode]) {
+        if self.action(name).is_some() {
+            return;
+        }
+
+        let keys = self
+            .loaded
+            .iter()
+            .find(|(loaded_name, _)| loaded_name == name)
+            .map(|(_, keys)| keys.clone())
+            .unwrap_or_else(|| default_keys.to_vec());
+
+        self.actions.push(InputAction {
+            name: name.to_string(),
+            keys,
+            default_keys: default_keys.to_vec(),
+        });
+    }
+
+    /// Look up a registered action
+    pub fn action(&self, name: &str) -> Option<&InputAction> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    /// Replace the keys bound to an action
+    pub fn rebind(&mut self, name: &str, keys: Vec<KeyCode>) {
+        if let Some(action) = self.actions.iter_mut().find(|action| action.name == name) {
+            action.keys = keys;
+        }
+    }
+
+    /// Whether any key bound to the action is held down
+    pub fn pressed(&self, input: &ButtonInput<KeyCode>, name: &str) -> bool {
+        self.action(name).is_some_and(|action| input.any_pressed(action.keys.iter().copied()))
+    }
+
+    /// Whether any key bound to the action was pressed this frame
+    pub fn just_pressed(&self, input: &ButtonInput<KeyCode>, name: &str) -> bool {
+        self.action(name).is_some_and(|action| input.any_just_pressed(action.keys.iter().copied()))
+    }
+
+    /// Human readable list of the keys bound to an action
+    pub fn describe(&self, name: &str) -> String {
+        let Some(action) = self.action(name) else {
+            return String::new();
+        };
+        let names: Vec<String> = action.keys.iter().map(|key| key_name(*key)).collect();
+        if names.is_empty() {
+            "unbound".to_string()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    /// Save the bindings to the settings file, keeping any other settings in it
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let mut settings = read_settings_table().unwrap_or_default();
+
+        let mut keybindings = toml::Table::new();
+        for action in &self.actions {
+            let keys = action.keys.iter().map(|key| toml::Value::String(key_name(*key))).collect();
+            keybindings.insert(action.name.clone(), toml::Value::Array(keys));
+        }
+        settings.insert(KEYBINDINGS_TABLE.to_string(), toml::Value::Table(keybindings));
+
+        write_settings_table(&settings)
+    }
+}
+
+// Read the [keybindings] table; unknown key names are skipped with a warning
+fn parse_keybindings(settings: &toml::Table) -> Vec<(String, Vec<KeyCode>)> {
+    let Some(keybindings) = settings.get(KEYBINDINGS_TABLE).and_then(|value| value.as_table()) else {
+        return Vec::new();
+    };
+
+    keybindings
+        .iter()
+        .map(|(name, value)| {
+            // Accept a single key as well as a list of keys
+            let names: Vec<&str> = match value {
+                toml::Value::String(key) => vec![key.as_str()],
+                toml::Value::Array(keys) => keys.iter().filter_map(|key| key.as_str()).collect(),
+                _ => Vec::new(),
+            };
+
+            let keys = names
+                .into_iter()
+                .filter_map(|key| {
+                    let parsed = key_from_name(key);
+                    if parsed.is_none() {
+                        warn!("Unknown key '{}' bound to {} in the settings file", key, name);
+                    }
+                    parsed
+                })
+                .collect();
+
+            (name.clone(), keys)
+        })
+        .collect()
+}
+
+// Keys that can be bound, names follow the KeyCode variants
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+    KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+    KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+    KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace, KeyCode::Escape,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight,
+    KeyCode::PageUp, KeyCode::PageDown, KeyCode::Home, KeyCode::End, KeyCode::Insert, KeyCode::Delete,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::BracketLeft, KeyCode::BracketRight, KeyCode::Backslash,
+    KeyCode::Semicolon, KeyCode::Quote, KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Backquote,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::NumpadAdd, KeyCode::NumpadSubtract,
+];
+
+/// Name of a key as written in the settings file
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// Parse a key name from the settings file
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.iter().copied().find(|key| key_name(*key) == name)
+}
+
+/// Whether a key can be bound (and saved) at all
+pub fn is_bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+/// Tracks which action is waiting for a new key on the keybindings page
+#[derive(Resource, Default)]
+pub struct RebindState {
+    pub waiting: Option<String>,
+}
+
+/// Lets plugins register their named actions while building the app
+pub trait InputMapAppExt {
+    fn register_input_action(&mut self, name: &str, default_keys: &[KeyCode]) -> &mut Self;
+}
+
+impl InputMapAppExt for App {
+    fn register_input_action(&mut self, name: &str, default_keys: &[KeyCode]) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(InputMap::load)
+            .register(name, default_keys);
+        self
+    }
+}
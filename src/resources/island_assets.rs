@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Objects that can be placed on islands in island editing mode
+/// Sizes are in region meters and objects stand on their origin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IslandAsset {
+    Box,
+    Cylinder,
+    Sphere,
+    Tree,
+    Pine,
+    Rock,
+}
+
+// The library in the order Tab cycles through it
+const ISLAND_ASSETS: [IslandAsset; 6] = [
+    IslandAsset::Box,
+    IslandAsset::Cylinder,
+    IslandAsset::Sphere,
+    IslandAsset::Tree,
+    IslandAsset::Pine,
+    IslandAsset::Rock,
+];
+
+impl IslandAsset {
+    /// Name shown while editing and written to the island's data
+    pub fn name(&self) -> &'static str {
+        match self {
+            IslandAsset::Box => "box",
+            IslandAsset::Cylinder => "cylinder",
+            IslandAsset::Sphere => "sphere",
+            IslandAsset::Tree => "tree",
+            IslandAsset::Pine => "pine",
+            IslandAsset::Rock => "rock",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ISLAND_ASSETS.into_iter().find(|asset| asset.name() == name)
+    }
+
+    /// The asset after this one in the library, wrapping around
+    pub fn next(&self) -> Self {
+        let index = ISLAND_ASSETS.iter().position(|asset| asset == self).unwrap_or(0);
+        ISLAND_ASSETS[(index + 1) % ISLAND_ASSETS.len()]
+    }
+
+    /// Sphere around the object at scale 1, for picking: height of its middle and its radius
+    pub fn bounds(&self) -> (f32, f32) {
+        match self {
+            IslandAsset::Box => (1.0, 1.75),
+            IslandAsset::Cylinder => (1.5, 1.7),
+            IslandAsset::Sphere => (1.0, 1.0),
+            IslandAsset::Tree => (3.0, 3.2),
+            IslandAsset::Pine => (3.25, 3.3),
+            IslandAsset::Rock => (0.4, 1.4),
+        }
+    }
+}
+
+/// A mesh making up (part of) an asset
+pub struct AssetPart {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub transform: Transform,
+}
+
+/// Meshes and materials of the asset library, shared by all placed objects
+#[derive(Resource)]
+pub struct IslandAssetLibrary {
+    pub parts: HashMap<IslandAsset, Vec<AssetPart>>,
+}
+
+impl FromWorld for IslandAssetLibrary {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let mut material = |color: Color| materials.add(StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.9,
+            ..default()
+        });
+        let wood = material(Color::srgb(0.72, 0.56, 0.36));
+        let stone = material(Color::srgb(0.55, 0.55, 0.52));
+        let plaster = material(Color::srgb(0.92, 0.92, 0.88));
+        let bark = material(Color::srgb(0.36, 0.25, 0.16));
+        let leaves = material(Color::srgb(0.25, 0.50, 0.18));
+        let needles = material(Color::srgb(0.12, 0.33, 0.16));
+
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let mut part = |mesh: Mesh, material: &Handle<StandardMaterial>, transform: Transform| AssetPart {
+            mesh: meshes.add(mesh),
+            material: material.clone(),
+            transform,
+        };
+
+        let parts = HashMap::from([
+            (IslandAsset::Box, vec![
+                part(Cuboid::new(2.0, 2.0, 2.0).into(), &wood, Transform::from_xyz(0.0, 1.0, 0.0)),
+            ]),
+            (IslandAsset::Cylinder, vec![
+                part(Cylinder::new(0.75, 3.0).into(), &stone, Transform::from_xyz(0.0, 1.5, 0.0)),
+            ]),
+            (IslandAsset::Sphere, vec![
+                part(Sphere::new(1.0).into(), &plaster, Transform::from_xyz(0.0, 1.0, 0.0)),
+            ]),
+            (IslandAsset::Tree, vec![
+                part(Cylinder::new(0.2, 3.0).into(), &bark, Transform::from_xyz(0.0, 1.5, 0.0)),
+                part(Sphere::new(1.8).into(), &leaves, Transform::from_xyz(0.0, 4.2, 0.0)),
+            ]),
+            (IslandAsset::Pine, vec![
+                part(Cylinder::new(0.2, 1.5).into(), &bark, Transform::from_xyz(0.0, 0.75, 0.0)),
+                part(Cone { radius: 1.4, height: 5.0 }.into(), &needles, Transform::from_xyz(0.0, 4.0, 0.0)),
+            ]),
+            (IslandAsset::Rock, vec![
+                part(Sphere::new(1.0).into(), &stone, Transform::from_xyz(0.0, 0.4, 0.0).with_scale(Vec3::new(1.4, 0.7, 1.1))),
+            ]),
+        ]);
+
+        Self { parts }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use crate::opensim::BrushTool;
+use crate::osm::TileId;
+use crate::resources::island_assets::IslandAsset;
+
+/// What clicks do in island editing mode
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditMode {
+    #[default]
+    Terrain, // Sculpt with the brush
+    Objects, // Place, select and transform objects from the asset library
+    Info,    // Edit the owner, description and tags of an island
+}
+
+impl EditMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditMode::Terrain => "Terrain",
+            EditMode::Objects => "Objects",
+            EditMode::Info => "Info",
+        }
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(&self) -> Self {
+        match self {
+            EditMode::Terrain => EditMode::Objects,
+            EditMode::Objects => EditMode::Info,
+            EditMode::Info => EditMode::Terrain,
+        }
+    }
+}
+
+/// Field of the island metadata form that typing goes to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FormField {
+    #[default]
+    Owner,
+    Description,
+    Tags,
+}
+
+impl FormField {
+    pub fn next(&self) -> Self {
+        match self {
+            FormField::Owner => FormField::Description,
+            FormField::Description => FormField::Tags,
+            FormField::Tags => FormField::Owner,
+        }
+    }
+}
+
+/// An island's metadata being edited, saved with Enter
+pub struct IslandForm {
+    pub island: usize,
+    pub field: FormField,
+    pub owner: String,
+    pub description: String,
+    pub tags: String, // Comma separated
+}
+
+impl IslandForm {
+    /// The text of the field being typed in
+    pub fn text_mut(&mut self) -> &mut String {
+        match self.field {
+            FormField::Owner => &mut self.owner,
+            FormField::Description => &mut self.description,
+            FormField::Tags => &mut self.tags,
+        }
+    }
+}
+
+/// A brush stroke on an island's terrain, from mouse press to release
+pub struct BrushStroke {
+    pub island: usize,
+    pub target: f32, // Height flatten levels to: the ground where the stroke started
+}
+
+/// Part of the selected object's gizmo that can be dragged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoHandle {
+    Body, // The object itself, moves it over the terrain
+    MoveX,
+    MoveY,
+    MoveZ,
+    Rotate, // Around the vertical axis
+    Scale,
+}
+
+/// The selected object being dragged by a gizmo handle, from mouse press to release
+pub struct ObjectDrag {
+    pub handle: GizmoHandle,
+    pub start_position: Vec3, // Mesh coordinates of the object when the drag started
+    pub start_center: Vec3,   // The same in world space
+    pub start_yaw: f32,
+    pub start_scale: f32,
+    pub grab: f32, // Where the handle was grabbed: distance along the axis, angle or screen distance
+    pub grab_point: Vec3, // Terrain under the cursor (mesh coordinates) when the object itself was grabbed
+    pub moved: bool,
+}
+
+/// Island editing mode, its terrain brush and the object selection
+#[derive(Resource)]
+pub struct IslandEditor {
+    pub active: bool,
+    pub mode: EditMode,
+    pub tool: BrushTool,
+    pub radius: f32,     // Brush radius in region meters
+    pub strength: f32,   // Meters per second raise and lower move the ground under the brush middle
+    pub blend_rate: f32, // Fraction per second smooth and flatten move the ground
+    pub cursor: Option<(Vec2, Ray3d)>, // Cursor on screen and the ray through it
+    pub hover: Option<(usize, Vec3)>, // Island and the point on its terrain (mesh coordinates) under the cursor, also when not editing
+    pub stroke: Option<BrushStroke>,
+    pub asset: IslandAsset,               // What a click on the terrain places
+    pub selected: Option<(usize, usize)>, // Island and index of the selected placed object
+    pub drag: Option<ObjectDrag>,
+    pub form: Option<IslandForm>,
+    pub show_grid: bool,      // Grid of PERSISTENT_ISLAND_ZOOM_LEVEL cells around the cursor
+    pub cell: Option<TileId>, // The grid cell under the cursor while the grid is shown
+}
+
+impl Default for IslandEditor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            mode: EditMode::default(),
+            tool: BrushTool::default(),
+            radius: 8.0,
+            strength: 4.0,
+            blend_rate: 2.0,
+            cursor: None,
+            hover: None,
+            stroke: None,
+            asset: IslandAsset::Tree,
+            selected: None,
+            drag: None,
+            form: None,
+            show_grid: true,
+            cell: None,
+        }
+    }
+}
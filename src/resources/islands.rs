@@ -0,0 +1,364 @@
+use bevy::prelude::*;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::opensim::Region;
+use crate::osm::TileId;
+use crate::resources::island_assets::IslandAsset;
+use crate::resources::user_settings::{read_settings_table, settings_path};
+
+// Array of tables inside the settings file listing the islands
+const ISLANDS_TABLE: &str = "islands";
+// Directory next to the settings file holding the islands' edits, one directory per island
+const ISLAND_DATA_DIR: &str = "islands";
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Where an island's region is in its lifecycle
+pub enum IslandState {
+    Idle,    // Not loaded, the camera hasn't come near yet
+    Loading, // Being read from disk
+    Failed,  // Couldn't be read, not tried again
+    Ready {
+        region: Region,             // Kept, so coming back doesn't read the archive again
+        placed: Vec<PlacedObject>,  // Objects placed in island editing mode
+        entity: Option<Entity>,     // Root of the spawned content while the camera is near
+    },
+}
+
+/// An object from the asset library placed on an island in island editing mode
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedObject {
+    pub asset: IslandAsset,
+    pub position: Vec3, // Mesh coordinates: region meters, X east, -Z north, Y above the water
+    pub yaw: f32,       // Radians, counterclockwise seen from above
+    pub scale: f32,
+}
+
+/// Write placed objects as JSON, in region coordinates like the OAR's objects
+/// (X east, Y north, Z up from the region's zero) so they survive a change of water height
+pub fn placed_objects_to_json(objects: &[PlacedObject], water_height: f32) -> String {
+    let objects: Vec<Value> = objects
+        .iter()
+        .map(|object| {
+            json!({
+                "asset": object.asset.name(),
+                "position": [object.position.x, -object.position.z, object.position.y + water_height],
+                "rotation": object.yaw.to_degrees(),
+                "scale": object.scale,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&json!({ "objects": objects })).unwrap_or_default()
+}
+
+/// Parse objects written by `placed_objects_to_json`; objects of unknown assets are skipped
+pub fn placed_objects_from_json(contents: &str, water_height: f32) -> Result<Vec<PlacedObject>, anyhow::Error> {
+    let value: Value = serde_json::from_str(contents)?;
+    let objects = value["objects"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Placed objects file has no objects"))?;
+
+    let mut placed = Vec::new();
+    for object in objects {
+        let name = object["asset"].as_str().unwrap_or_default();
+        let Some(asset) = IslandAsset::from_name(name) else {
+            warn!("Skipping placed object of unknown asset '{}'", name);
+            continue;
+        };
+        let position = object["position"]
+            .as_array()
+            .filter(|values| values.len() == 3)
+            .and_then(|values| values.iter().map(|value| value.as_f64().map(|v| v as f32)).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| anyhow::anyhow!("Placed {} without a position", name))?;
+        placed.push(PlacedObject {
+            asset,
+            position: Vec3::new(position[0], position[2] - water_height, -position[1]),
+            yaw: (object["rotation"].as_f64().unwrap_or(0.0) as f32).to_radians(),
+            scale: object["scale"].as_f64().unwrap_or(1.0) as f32,
+        });
+    }
+    Ok(placed)
+}
+
+/// Who owns an island and what it is about, edited in island editing mode
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IslandMetadata {
+    pub owner: String,
+    pub description: String,
+    pub created_at: Option<u64>, // Unix seconds when the metadata was first saved
+    pub tags: Vec<String>,
+}
+
+impl IslandMetadata {
+    pub fn to_toml(&self) -> String {
+        let mut table = toml::Table::new();
+        table.insert("owner".into(), self.owner.clone().into());
+        table.insert("description".into(), self.description.clone().into());
+        if let Some(created_at) = self.created_at {
+            table.insert("created_at".into(), (created_at as i64).into());
+        }
+        table.insert("tags".into(), self.tags.clone().into());
+        table.to_string()
+    }
+
+    /// Parse metadata written by `to_toml`; missing fields are left empty
+    pub fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        let table: toml::Table = contents.parse()?;
+        let text = |key: &str| table.get(key).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+        Ok(Self {
+            owner: text("owner"),
+            description: text("description"),
+            created_at: table
+                .get("created_at")
+                .and_then(|value| value.as_integer())
+                .and_then(|seconds| u64::try_from(seconds).ok()),
+            tags: table
+                .get("tags")
+                .and_then(|value| value.as_array())
+                .map(|tags| tags.iter().filter_map(|tag| tag.as_str()).map(str::to_string).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A Unix time as a UTC date, "2024-06-21"
+pub fn format_date(unix_seconds: u64) -> String {
+    // Days to a civil date, from Howard Hinnant's date algorithms
+    let days = (unix_seconds / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// A persistent island: an OpenSimulator region placed on a map tile
+pub struct IslandRegion {
+    pub name: String,
+    pub tile: TileId,      // The region fills this tile
+    pub source: PathBuf,   // OAR archive or XML export
+    pub data_dir: PathBuf, // Where edits to the island are kept, the source is never written
+    pub metadata: IslandMetadata,
+    pub state: IslandState,
+}
+
+impl IslandRegion {
+    /// Terrain as sculpted in island editing mode, replacing the region's own
+    pub fn terrain_path(&self) -> PathBuf {
+        self.data_dir.join("terrain.r32")
+    }
+
+    /// Objects placed in island editing mode
+    pub fn objects_path(&self) -> PathBuf {
+        self.data_dir.join("objects.json")
+    }
+
+    /// Owner, description and tags as edited in island editing mode
+    pub fn metadata_path(&self) -> PathBuf {
+        self.data_dir.join("island.toml")
+    }
+}
+
+/// Regions and their placed objects read on a background thread, by island index, waiting to be spawned
+pub type LoadedRegions = Arc<Mutex<Vec<(usize, Result<(Region, Vec<PlacedObject>), String>)>>>;
+
+/// The persistent islands from the settings file (`[[islands]]`)
+#[derive(Resource)]
+pub struct Islands {
+    pub regions: Vec<IslandRegion>,
+    pub loaded: LoadedRegions,
+    pub spawn_distance: f64,   // Camera distance in island tile sizes at which content appears
+    pub despawn_distance: f64, // Larger than spawn_distance, so it doesn't flicker at the edge
+}
+
+impl Default for Islands {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            loaded: LoadedRegions::default(),
+            spawn_distance: 2.0,
+            despawn_distance: 3.0,
+        }
+    }
+}
+
+impl Islands {
+    /// The island whose tile overlaps a tile of any zoom level
+    pub fn claimed_by(&self, tile: TileId) -> Option<usize> {
+        self.regions.iter().position(|island| tiles_overlap(island.tile, tile))
+    }
+
+    /// Create the islands listed in the settings file
+    /// Relative region paths are relative to the settings file
+    pub fn load() -> Self {
+        let path = settings_path();
+        let base = path.parent().unwrap_or(Path::new("."));
+        let mut regions = read_settings_table()
+            .map(|table| parse_islands(&table, base))
+            .unwrap_or_default();
+
+        // Metadata is small and shown before the region is loaded, so it is read right away
+        for island in &mut regions {
+            let path = island.metadata_path();
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            match IslandMetadata::from_toml(&contents) {
+                Ok(metadata) => island.metadata = metadata,
+                Err(e) => warn!("Failed to read the metadata of island '{}' from {}: {}", island.name, path.display(), e),
+            }
+        }
+
+        Self { regions, ..default() }
+    }
+}
+
+// Read the [[islands]] tables; entries without a valid tile or region are skipped with a warning
+fn parse_islands(settings: &toml::Table, base: &Path) -> Vec<IslandRegion> {
+    let Some(islands) = settings.get(ISLANDS_TABLE).and_then(|value| value.as_array()) else {
+        return Vec::new();
+    };
+
+    islands
+        .iter()
+        .filter_map(|island| {
+            let name = island.get("name").and_then(|value| value.as_str()).unwrap_or("Island");
+            let tile = island.get("tile").and_then(|value| value.as_str()).and_then(parse_tile);
+            let region = island.get("region").and_then(|value| value.as_str());
+            let (Some(tile), Some(region)) = (tile, region) else {
+                warn!("Island '{}' needs a tile (\"zoom/x/y\") and a region path in the settings file", name);
+                return None;
+            };
+
+            Some(IslandRegion {
+                name: name.to_string(),
+                tile,
+                source: base.join(region),
+                data_dir: base.join(ISLAND_DATA_DIR).join(directory_name(name)),
+                metadata: IslandMetadata::default(),
+                state: IslandState::Idle,
+            })
+        })
+        .collect()
+}
+
+// Whether two tiles cover some of the same ground: one of them is inside the other
+fn tiles_overlap(a: TileId, b: TileId) -> bool {
+    let (outer, inner) = if a.z <= b.z { (a, b) } else { (b, a) };
+    let shift = inner.z - outer.z;
+    inner.x >> shift == outer.x && inner.y >> shift == outer.y
+}
+
+// An island name made safe to use as a directory name
+fn directory_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// Parse a "zoom/x/y" tile
+fn parse_tile(text: &str) -> Option<TileId> {
+    let mut parts = text.split('/').map(|part| part.trim().parse::<u32>());
+    let (Some(Ok(z)), Some(Ok(x)), Some(Ok(y)), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let tiles = 1u64 << z.min(31);
+    (z <= 24 && (x as u64) < tiles && (y as u64) < tiles).then(|| TileId::new(x, y, z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn islands_are_read_from_the_settings() {
+        let settings: toml::Table = r#"
+            [[islands]]
+            name = "Noorderplantsoen"
+            tile = "16/33947/21358"
+            region = "islands/park.oar"
+
+            [[islands]]
+            name = "Nowhere"
+            tile = "2/9/0"
+            region = "nowhere.oar"
+        "#
+        .parse()
+        .unwrap();
+
+        let islands = parse_islands(&settings, Path::new("/config"));
+        assert_eq!(islands.len(), 1);
+        assert_eq!(islands[0].name, "Noorderplantsoen");
+        assert_eq!(islands[0].tile, TileId::new(33947, 21358, 16));
+        assert_eq!(islands[0].source, Path::new("/config/islands/park.oar"));
+        assert_eq!(islands[0].terrain_path(), Path::new("/config/islands/Noorderplantsoen/terrain.r32"));
+        assert_eq!(directory_name("Groningen / Zernike"), "Groningen___Zernike");
+        assert!(parse_tile("16/1/2/3").is_none());
+        assert!(parse_tile("a/1/2").is_none());
+    }
+
+    #[test]
+    fn placed_objects_round_trip_in_region_coordinates() {
+        let objects = vec![PlacedObject {
+            asset: IslandAsset::Tree,
+            position: Vec3::new(10.0, 2.0, -30.0),
+            yaw: 0.5,
+            scale: 1.5,
+        }];
+        let json = placed_objects_to_json(&objects, 20.0);
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["objects"][0]["asset"], "tree");
+        assert_eq!(value["objects"][0]["position"], json!([10.0, 30.0, 22.0]));
+
+        let read = placed_objects_from_json(&json, 20.0).unwrap();
+        assert_eq!(read[0].asset, IslandAsset::Tree);
+        assert!(read[0].position.distance(objects[0].position) < 1e-4);
+        assert!((read[0].yaw - 0.5).abs() < 1e-4);
+        assert_eq!(read[0].scale, 1.5);
+
+        let unknown = r#"{"objects": [{"asset": "castle", "position": [0, 0, 0]}]}"#;
+        assert!(placed_objects_from_json(unknown, 20.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn islands_claim_the_cells_their_tile_overlaps() {
+        let islands = Islands {
+            regions: parse_islands(
+                &r#"
+                    [[islands]]
+                    tile = "15/100/200"
+                    region = "a.oar"
+                "#
+                .parse()
+                .unwrap(),
+                Path::new("/config"),
+            ),
+            ..default()
+        };
+        assert_eq!(islands.claimed_by(TileId::new(201, 401, 16)), Some(0));
+        assert_eq!(islands.claimed_by(TileId::new(202, 401, 16)), None);
+        assert_eq!(islands.claimed_by(TileId::new(50, 100, 14)), Some(0));
+        assert_eq!(islands.claimed_by(TileId::new(100, 200, 15)), Some(0));
+    }
+
+    #[test]
+    fn metadata_round_trips() {
+        let metadata = IslandMetadata {
+            owner: "ann".into(),
+            description: "A park with \"quotes\"".into(),
+            created_at: Some(1_718_928_000),
+            tags: vec!["park".into(), "green".into()],
+        };
+        assert_eq!(IslandMetadata::from_toml(&metadata.to_toml()).unwrap(), metadata);
+        assert_eq!(IslandMetadata::from_toml("owner = \"bob\"").unwrap().tags, Vec::<String>::new());
+        assert_eq!(format_date(1_718_928_000), "2024-06-21");
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use bevy::math::DVec2;
+use clap::Parser;
+use crate::resources::constants::{GRONINGEN_X, GRONINGEN_Y, MAX_ZOOM_LEVEL, MIN_ZOOM_LEVEL};
+use crate::resources::user_settings::TILE_PROVIDERS;
+use crate::utils::deep_link::{parse_deep_link, DeepLink};
+use crate::utils::geo::{tile_world_size, GeoPos};
+
+// Camera height per tile width at the start zoom level: with the default 90 degree field of
+// view on a 720 pixel high window the tiles below then cover about 256 pixels, well under
+// LOD_MAX_TILE_PIXELS, so their children don't load yet
+const CAMERA_HEIGHT_PER_TILE: f64 = 1.4;
+
+/// Where and how the viewer starts, from the command line
+/// Unset fields keep the defaults: Groningen, the usual camera height and the settings file
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub start: Option<GeoPos>,
+    pub zoom: Option<u32>,           // Zoom level of the tiles below the camera at the start
+    pub tile_server: Option<String>, // URL template of the provider for this run, the saved one is kept
+    pub cache_dir: Option<PathBuf>,  // Tile cache directory, desktop only
+    pub compare: Option<String>,     // URL template of the provider to compare the map with
+    pub benchmark: bool,             // Fly the benchmark sweep, desktop only
+    pub record_session: Option<PathBuf>, // Camera session log to write, desktop only
+    pub replay_session: Option<PathBuf>, // Camera session log to replay, desktop only
+}
+
+/// The viewer's command line; `vibers --help` prints it
+#[derive(Parser, Debug)]
+#[command(
+    name = "vibers",
+    about = "A 3D viewer of OpenStreetMap tiles",
+    after_help = "With --headless the viewer prerenders tiles instead, and takes its own arguments."
+)]
+struct Cli {
+    /// Place to start above: a geo:53.21,6.56?z=16 link or an openstreetmap.org #map=16/53.21/6.56 URL
+    #[arg(value_name = "LINK", value_parser = parse_link, conflicts_with_all = ["lat", "lon"])]
+    link: Option<DeepLink>,
+    /// Latitude to start above instead of Groningen
+    #[arg(long, value_name = "DEGREES", value_parser = parse_latitude, requires = "lon", allow_negative_numbers = true)]
+    lat: Option<f64>,
+    /// Longitude to start above instead of Groningen
+    #[arg(long, value_name = "DEGREES", value_parser = parse_longitude, requires = "lat", allow_negative_numbers = true)]
+    lon: Option<f64>,
+    /// Start at the height where the map shows tiles of this zoom level, instead of a link's
+    #[arg(long, value_name = "LEVEL", value_parser = parse_zoom)]
+    zoom: Option<u32>,
+    /// Tile server for this run (osm, hot, topo, satellite, sentinel or a URL template); the
+    /// provider saved in the settings is kept
+    #[arg(long, value_name = "NAME", value_parser = parse_provider)]
+    provider: Option<String>,
+    /// Keep downloaded tiles in this directory instead of ./tile_cache
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Start with the map split in two, the right side showing the tiles of this provider
+    #[arg(long, value_name = "NAME", value_parser = parse_provider)]
+    compare: Option<String>,
+    /// Fly the benchmark sweep and quit with a report
+    #[arg(long)]
+    benchmark: bool,
+    /// Log the camera and the tiles requested, spawned and despawned every frame to this file
+    #[arg(long, value_name = "FILE")]
+    record_session: Option<PathBuf>,
+    /// Move the camera through a recorded session frame by frame and quit at its end
+    #[arg(long, value_name = "FILE")]
+    replay_session: Option<PathBuf>,
+}
+
+impl LaunchOptions {
+    /// Parse the arguments after the program name
+    /// The error prints the usage, or the help for --help, with `exit`
+    pub fn parse(args: &[String]) -> Result<Self, clap::Error> {
+        let cli = Cli::try_parse_from(std::iter::once("vibers").chain(args.iter().map(String::as_str)))?;
+        let start = match (cli.lat, cli.lon, cli.link) {
+            (Some(lat), Some(lon), _) => Some(GeoPos::new(lat, lon)),
+            (_, _, Some(link)) => Some(link.position),
+            _ => None,
+        };
+        Ok(Self {
+            start,
+            // --zoom wins over the zoom level of a link
+            zoom: cli.zoom.or(cli.link.and_then(|link| link.zoom)),
+            tile_server: cli.provider,
+            cache_dir: cli.cache_dir,
+            compare: cli.compare,
+            benchmark: cli.benchmark,
+            record_session: cli.record_session,
+            replay_session: cli.replay_session,
+        })
+    }
+
+    /// World X/Z position to start above, Groningen unless --lat and --lon say otherwise
+    pub fn start_world(&self) -> DVec2 {
+        match self.start {
+            Some(start) => start.world(),
+            None => DVec2::new(GRONINGEN_X as f64, GRONINGEN_Y as f64),
+        }
+    }
+
+    /// Camera height in world units to see tiles of the start zoom level, None without --zoom
+    pub fn camera_height(&self) -> Option<f32> {
+        self.zoom.map(camera_height_for_zoom)
+    }
+}
+
+/// Camera height in world units at which the map shows tiles of a zoom level
+pub fn camera_height_for_zoom(zoom: u32) -> f32 {
+    (tile_world_size(zoom) * CAMERA_HEIGHT_PER_TILE) as f32
+}
+
+fn parse_link(value: &str) -> Result<DeepLink, String> {
+    parse_deep_link(value).ok_or_else(|| format!("Not a link to a place: {}", value))
+}
+
+fn parse_latitude(value: &str) -> Result<f64, String> {
+    parse_degrees(value, 85.0)
+}
+
+fn parse_longitude(value: &str) -> Result<f64, String> {
+    parse_degrees(value, 180.0)
+}
+
+fn parse_degrees(value: &str, limit: f64) -> Result<f64, String> {
+    let degrees: f64 = value.parse().map_err(|_| format!("Not a number of degrees: {}", value))?;
+    if !(-limit..=limit).contains(&degrees) {
+        return Err(format!("{} is outside -{}..{}", degrees, limit, limit));
+    }
+    Ok(degrees)
+}
+
+fn parse_zoom(value: &str) -> Result<u32, String> {
+    let zoom: u32 = value.parse().map_err(|_| format!("Not a zoom level: {}", value))?;
+    if !(MIN_ZOOM_LEVEL..=MAX_ZOOM_LEVEL).contains(&zoom) {
+        return Err(format!("Zoom {} is outside {}-{}", zoom, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL));
+    }
+    Ok(zoom)
+}
+
+// A provider's key or name, or a tile source URL of its own
+fn parse_provider(value: &str) -> Result<String, String> {
+    if value.contains("://") {
+        return Ok(value.to_string());
+    }
+    TILE_PROVIDERS
+        .iter()
+        .find(|provider| provider.key == value || provider.name.eq_ignore_ascii_case(value))
+        .map(|provider| provider.url.to_string())
+        .ok_or_else(|| {
+            let keys: Vec<_> = TILE_PROVIDERS.iter().map(|provider| provider.key).collect();
+            format!("Unknown provider {}, pick one of {}", value, keys.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::user_settings::SATELLITE_SERVER;
+
+    fn parse(args: &str) -> Result<LaunchOptions, clap::Error> {
+        LaunchOptions::parse(&args.split_whitespace().map(String::from).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_the_start_position_zoom_and_provider() {
+        let options = parse("--lat 53.21 --lon 6.56 --zoom 16 --provider satellite --cache-dir /tmp/tiles").unwrap();
+        assert_eq!(options.start, Some(GeoPos::new(53.21, 6.56)));
+        assert_eq!(options.zoom, Some(16));
+        assert_eq!(options.tile_server.as_deref(), Some(SATELLITE_SERVER));
+        assert_eq!(options.cache_dir, Some(PathBuf::from("/tmp/tiles")));
+
+        assert_eq!(parse("").unwrap(), LaunchOptions::default());
+        assert_eq!(parse("--benchmark").unwrap(), LaunchOptions { benchmark: true, ..default() });
+        // Values may follow an equals sign, and negative degrees aren't taken for options
+        let south_west = parse("--lat=-33.92 --lon -18.42").unwrap();
+        assert_eq!(south_west.start, Some(GeoPos::new(-33.92, -18.42)));
+        let session = parse("--replay-session before.jsonl --record-session after.jsonl").unwrap();
+        assert_eq!(session.replay_session, Some(PathBuf::from("before.jsonl")));
+        assert_eq!(session.record_session, Some(PathBuf::from("after.jsonl")));
+        assert_eq!(parse("--provider mock://tiles").unwrap().tile_server.as_deref(), Some("mock://tiles"));
+        assert_eq!(parse("--compare satellite").unwrap().compare.as_deref(), Some(SATELLITE_SERVER));
+
+        let linked = parse("https://www.openstreetmap.org/#map=16/53.21/6.56").unwrap();
+        assert_eq!((linked.start, linked.zoom), (Some(GeoPos::new(53.21, 6.56)), Some(16)));
+        assert_eq!(parse("geo:53.21,6.56?z=16 --zoom 12").unwrap().zoom, Some(12));
+    }
+
+    #[test]
+    fn rejects_incomplete_or_out_of_range_values() {
+        assert!(parse("--lat 53.21").is_err());
+        assert!(parse("--lat 91 --lon 6").is_err());
+        assert!(parse("--zoom 25").is_err());
+        assert!(parse("--zoom").is_err());
+        assert!(parse("--provider bing").is_err());
+        assert!(parse("--compare bing").is_err());
+        assert!(parse("--fly").is_err());
+        assert!(parse("groningen").is_err());
+        assert!(parse("geo:53.21,6.56 --lat 53.2 --lon 6.5").is_err());
+        assert_eq!(parse("--help").unwrap_err().kind(), clap::error::ErrorKind::DisplayHelp);
+    }
+
+    #[test]
+    fn closer_zoom_levels_start_lower() {
+        let height = |zoom| LaunchOptions { zoom: Some(zoom), ..default() }.camera_height();
+        assert!(height(16).unwrap() < height(12).unwrap());
+        assert_eq!(LaunchOptions::default().camera_height(), None);
+    }
+}
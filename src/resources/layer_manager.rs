@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+
+// Names of the built-in overlay layers, shown in the layer panel
+pub const BUILDINGS_LAYER: &str = "Buildings";
+pub const ROADS_LAYER: &str = "Roads";
+pub const STREET_LAMPS_LAYER: &str = "Street lamps";
+pub const GPX_LAYER: &str = "GPX tracks";
+pub const STREET_NAMES_LAYER: &str = "Street names";
+pub const ROUTE_LAYER: &str = "Route";
+pub const MEASUREMENT_LAYER: &str = "Measurement";
+
+// Height between successive overlay layers; more than the overlays' own heights differ
+// (roads 0.008 to 0.01, street labels 0.015, lines 0.02), so the upper layer wins the depth test where they cross
+const LAYER_SPACING: f32 = 0.006;
+
+/// A named layer of overlays drawn over the map imagery
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlayLayer {
+    pub name: &'static str,
+    pub visible: bool,
+    pub opacity: f32,
+}
+
+/// The overlay layers drawn over the map imagery (tracks, labels, routes...), bottom first
+/// Plugins register their layers while building the app and tag the overlays they spawn with
+/// a LayerItem; the layer panel shows, hides, fades and reorders the layers
+/// Imagery layers are blended into the tiles instead and kept by ImageryLayers; the panel lists both
+#[derive(Resource, Default)]
+pub struct LayerManager {
+    pub layers: Vec<OverlayLayer>,
+    pub collapsed: bool, // The layer panel only shows its title
+}
+
+impl LayerManager {
+    /// Register a layer on top of the others; registering twice is a no-op
+    pub fn register(&mut self, name: &'static str) {
+        if self.get(name).is_none() {
+            self.layers.push(OverlayLayer { name, visible: true, opacity: 1.0 });
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OverlayLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut OverlayLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    /// Move a layer up (positive) or down (negative) the stack
+    pub fn move_layer(&mut self, name: &str, step: i32) {
+        let Some(index) = self.layers.iter().position(|layer| layer.name == name) else {
+            return;
+        };
+        let target = (index as i32 + step).clamp(0, self.layers.len() as i32 - 1) as usize;
+        let layer = self.layers.remove(index);
+        self.layers.insert(target, layer);
+    }
+
+    /// Height a layer's overlays are lifted by, from its place in the stack
+    pub fn lift(&self, name: &str) -> f32 {
+        self.layers.iter().position(|layer| layer.name == name).unwrap_or(0) as f32 * LAYER_SPACING
+    }
+}
+
+/// Lets plugins register their overlay layers while building the app
+pub trait LayerManagerAppExt {
+    fn register_overlay_layer(&mut self, name: &'static str) -> &mut Self;
+}
+
+impl LayerManagerAppExt for App {
+    fn register_overlay_layer(&mut self, name: &'static str) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(LayerManager::default)
+            .register(name);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_stack_in_registration_order() {
+        let mut manager = LayerManager::default();
+        for name in [GPX_LAYER, STREET_NAMES_LAYER, ROUTE_LAYER, GPX_LAYER] {
+            manager.register(name);
+        }
+        assert_eq!(manager.layers.len(), 3);
+        assert_eq!(manager.lift(GPX_LAYER), 0.0);
+
+        manager.move_layer(GPX_LAYER, 5);
+        let names: Vec<&str> = manager.layers.iter().map(|layer| layer.name).collect();
+        assert_eq!(names, [STREET_NAMES_LAYER, ROUTE_LAYER, GPX_LAYER]);
+        assert_eq!(manager.lift(GPX_LAYER), 2.0 * LAYER_SPACING);
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use crate::osm::TileId;
+
+/// Seconds the loading screen waits at most, so a start without network still shows the world
+pub const LOADING_TIMEOUT: f32 = 20.0;
+/// Seconds the loading screen takes to fade into the world
+pub const LOADING_FADE: f32 = 0.6;
+
+/// Whether the viewer is still loading the tiles around the start position (with the loading
+/// screen over the world) or running, see LoadingPlugin
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    Loading,
+    Running,
+}
+
+/// Progress of the initial ring: the requested tiles overlapping the 3x3 tiles around `center`,
+/// as (resident, total)
+/// Tiles of any zoom count, as the scheduler picks coarser tiles further from the camera; tiles
+/// that failed to load are resident as well, as their fallback is in place
+pub fn ring_progress(
+    center: TileId,
+    requested: &HashSet<(u32, u32, u32)>,
+    is_resident: impl Fn(&(u32, u32, u32)) -> bool,
+) -> (usize, usize) {
+    requested
+        .iter()
+        .filter(|&&(x, y, z)| in_ring(center, TileId::new(x, y, z)))
+        .fold((0, 0), |(resident, total), tile| (resident + is_resident(tile) as usize, total + 1))
+}
+
+// Whether a tile overlaps the 3x3 tiles around the center tile
+fn in_ring(center: TileId, tile: TileId) -> bool {
+    let (center_x, center_y) = (center.column(), center.y as i64);
+    let (x, y) = (tile.column(), tile.y as i64);
+    let near = |a: i64, b: i64| (a - b).abs() <= 1;
+    if tile.z >= center.z {
+        // A finer tile lies in the ring tile containing it
+        let shift = tile.z - center.z;
+        near(x >> shift, center_x) && near(y >> shift, center_y)
+    } else {
+        // A coarser tile contains one of the ring's tiles
+        let shift = center.z - tile.z;
+        (center_x - 1..=center_x + 1).any(|ring_x| ring_x >> shift == x)
+            && (center_y - 1..=center_y + 1).any(|ring_y| ring_y >> shift == y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_counts_overlapping_tiles_of_any_zoom() {
+        let center = TileId::new(67926, 42563, 17);
+        let requested: HashSet<(u32, u32, u32)> = [
+            (67926, 42563, 17),     // The center
+            (67927, 42564, 17),     // Its southeast neighbour
+            (67928, 42563, 17),     // Two columns east, outside the ring
+            (135850, 85124, 18),    // A quarter of the northwest neighbour
+            (33963, 21281, 16),     // The parent of the center
+            (33964, 21281, 16),     // Two columns east at zoom 17, outside the ring
+            (8490, 5320, 14),       // Background imagery far above, containing the ring
+        ]
+        .into_iter()
+        .collect();
+
+        let resident = [(67926, 42563, 17), (33963, 21281, 16), (67928, 42563, 17)];
+        assert_eq!(ring_progress(center, &requested, |tile| resident.contains(tile)), (2, 5));
+        assert_eq!(ring_progress(center, &requested, |_| true), (5, 5));
+        assert_eq!(ring_progress(center, &HashSet::new(), |_| true), (0, 0));
+    }
+
+    #[test]
+    fn ring_continues_across_the_antimeridian() {
+        // West of column 0 columns are negative, stored as two's complement
+        let center = TileId::new(0, 10, 5);
+        let requested: HashSet<(u32, u32, u32)> = [(-1i32 as u32, 10, 5), (31, 10, 5)].into_iter().collect();
+        assert_eq!(ring_progress(center, &requested, |_| true), (1, 1));
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use crate::overlays::marker_import::ImportedMarker;
+use crate::utils::geo::GeoPos;
+
+/// Identifies a marker, to remove it again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MarkerId(pub u64);
+
+/// How a marker is drawn
+#[derive(Clone, Debug)]
+pub enum MarkerIcon {
+    Pin(Color),          // The built-in pin in a color
+    Image(Handle<Image>), // An image, with the marker's position at its bottom middle
+}
+
+/// A pin on the map with an optional label
+#[derive(Clone, Debug)]
+pub struct Marker {
+    pub position: GeoPos,
+    pub icon: MarkerIcon,
+    pub label: Option<String>,
+}
+
+/// Markers shown on the map, see MarkerPlugin
+/// Markers close together on screen are drawn as one badge with their count until zoomed in
+#[derive(Resource, Default)]
+pub struct Markers {
+    markers: BTreeMap<MarkerId, Marker>,
+    next_id: u64,
+}
+
+impl Markers {
+    /// Put a marker on the map
+    pub fn add_marker(&mut self, lat: f64, lon: f64, icon: MarkerIcon, label: Option<String>) -> MarkerId {
+        let id = MarkerId(self.next_id);
+        self.next_id += 1;
+        self.markers.insert(id, Marker { position: GeoPos::new(lat, lon), icon, label });
+        id
+    }
+
+    /// Take a marker off the map, returning it if it was there
+    pub fn remove_marker(&mut self, id: MarkerId) -> Option<Marker> {
+        self.markers.remove(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MarkerId, &Marker)> {
+        self.markers.iter().map(|(id, marker)| (*id, marker))
+    }
+}
+
+/// A reusable UI badge drawing a marker or a cluster of markers
+pub struct MarkerBadge {
+    pub root: Entity,
+    pub icon: Entity,  // The marker's icon
+    pub count: Entity, // Round badge with the cluster's count, its text as child
+    pub count_text: Entity,
+    pub label: Entity,
+}
+
+/// The badges markers are drawn with; more are spawned as more are needed at once
+#[derive(Resource, Default)]
+pub struct MarkerBadges {
+    pub badges: Vec<MarkerBadge>,
+    pub pin: Handle<Image>,
+}
+
+/// Markers imported from dropped CSV and GeoJSON files
+#[derive(Resource, Default)]
+pub struct MarkerImport {
+    pub active: bool, // Dropped CSV and GeoJSON files are imported as markers rather than heatmaps
+    pub pending: Vec<ImportedMarker>, // Read from the files, added a batch per frame
+    pub total: usize,                 // Markers read from the files being imported
+    pub imported: Vec<MarkerId>,      // Removed together by the Clear button
+    pub error: Option<String>,        // Why the last file couldn't be read
+}
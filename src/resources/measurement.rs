@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use crate::utils::geo::{geodesic_area_m2, geodesic_distance_m};
+
+/// Points clicked in measurement mode and what they measure
+/// Points are kept as lat/lon so the totals use the real geodesic distances, not map distances
+#[derive(Resource, Default)]
+pub struct Measurement {
+    pub active: bool,
+    pub points: Vec<(f64, f64)>, // (lat, lon) in click order
+    pub closed: bool,            // The shape returns to the first point and encloses an area
+}
+
+impl Measurement {
+    /// Length of the line through the points, including the closing edge of a polygon
+    pub fn distance_m(&self) -> f64 {
+        let closing = match (self.closed, self.points.first(), self.points.last()) {
+            (true, Some(&first), Some(&last)) => geodesic_distance_m(last, first),
+            _ => 0.0,
+        };
+        let line: f64 = self.points.windows(2).map(|pair| geodesic_distance_m(pair[0], pair[1])).sum();
+        line + closing
+    }
+
+    /// Enclosed area, once the shape is closed
+    pub fn area_m2(&self) -> Option<f64> {
+        (self.closed && self.points.len() >= 3).then(|| geodesic_area_m2(&self.points))
+    }
+
+    /// Start over with no points
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.closed = false;
+    }
+}
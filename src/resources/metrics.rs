@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy::diagnostic::DiagnosticsStore;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::osm::{DownloadTotals, HostTotals, TileError};
+use crate::resources::{TextureCache, TileMemoryBudget};
+
+/// Time since the metrics file was last written, see UserSettings::metrics_file
+#[derive(Resource, Default)]
+pub struct MetricsExport {
+    pub since_export: f32,
+}
+
+/// Metrics of a running session as JSON: every diagnostic in the store with its latest and
+/// smoothed value, downloads with their error rate per tile server, failed tile loads per
+/// kind of error, and cache and memory use
+pub fn metrics_json(
+    uptime_secs: f64,
+    diagnostics: &DiagnosticsStore,
+    downloads: DownloadTotals,
+    hosts: &BTreeMap<String, HostTotals>,
+    errors: &BTreeMap<TileError, u64>,
+    texture_cache: &TextureCache,
+    memory: &TileMemoryBudget,
+) -> Value {
+    let diagnostics: Map<String, Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let value = json!({
+                "value": diagnostic.value(),
+                "smoothed": diagnostic.smoothed(),
+            });
+            (diagnostic.path().to_string(), value)
+        })
+        .collect();
+    let hosts: Map<String, Value> = hosts
+        .iter()
+        .map(|(host, totals)| {
+            let value = json!({
+                "requests": totals.requests,
+                "errors": totals.errors,
+                "error_rate": totals.error_rate(),
+            });
+            (host.clone(), value)
+        })
+        .collect();
+    let errors: Map<String, Value> = errors.iter().map(|(error, count)| (error.name().to_string(), json!(count))).collect();
+
+    json!({
+        "uptime_seconds": uptime_secs,
+        "diagnostics": diagnostics,
+        "downloads": {
+            "responses": downloads.responses,
+            "bytes": downloads.bytes,
+            "average_latency_ms": downloads.average_latency().as_secs_f64() * 1000.0,
+            "hosts": hosts,
+            "errors": errors,
+        },
+        "texture_cache": {
+            "entries": texture_cache.entries.len(),
+            "used_bytes": texture_cache.used_bytes,
+            "capacity_bytes": texture_cache.capacity_bytes,
+            "hits": texture_cache.hits,
+            "misses": texture_cache.misses,
+            "hit_rate": texture_cache.hit_rate(),
+        },
+        "memory": {
+            "tile_texture_bytes": memory.used_bytes,
+            "tile_texture_budget_bytes": memory.budget_bytes,
+            "evicted_tiles": memory.evicted_tiles,
+        },
+    })
+}
+
+/// Write the metrics to `path`, through a temporary file so readers never see half of it
+pub fn write_metrics(path: &Path, metrics: &Value) -> io::Result<()> {
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(metrics)?)?;
+    fs::rename(temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn metrics_report_downloads_per_host() {
+        let downloads = DownloadTotals { responses: 4, latency: Duration::from_millis(400), bytes: 2048 };
+        let hosts = BTreeMap::from([("a.tile.example.com".to_string(), HostTotals { requests: 5, errors: 1 })]);
+        let metrics = metrics_json(
+            12.0,
+            &DiagnosticsStore::default(),
+            downloads,
+            &hosts,
+            &BTreeMap::from([(TileError::NotFound, 3)]),
+            &TextureCache::default(),
+            &TileMemoryBudget::default(),
+        );
+
+        assert_eq!(metrics["downloads"]["bytes"], 2048);
+        assert_eq!(metrics["downloads"]["average_latency_ms"], 100.0);
+        assert_eq!(metrics["downloads"]["hosts"]["a.tile.example.com"]["error_rate"], 0.2);
+        assert_eq!(metrics["downloads"]["errors"]["404"], 3);
+        assert_eq!(metrics["texture_cache"]["entries"], 0);
+    }
+}
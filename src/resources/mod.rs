@@ -3,9 +3,109 @@ pub mod runtime;
 pub mod settings;
 pub mod input;
 pub mod constants;
+pub mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache_maintenance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connectivity;
+pub mod texture_cache;
+pub mod input_map;
+pub mod user_settings;
+pub mod launch_options;
+pub mod comparison;
+pub mod stereo;
+pub mod loading;
+pub mod style;
+pub mod tile_debug;
+pub mod tile_generators;
+pub mod game;
+pub mod tile_memory;
+pub mod tile_atlas;
+pub mod environment;
+pub mod water;
+pub mod camera_path;
+pub mod measurement;
+pub mod routing;
+pub mod overpass;
+pub mod buildings;
+pub mod roads;
+pub mod street_lamps;
+pub mod weather;
+pub mod imagery_layers;
+pub mod layer_manager;
+pub mod markers;
+pub mod models;
+pub mod http_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod multiplayer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod chat;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod islands;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_editor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_assets;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scripting;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod session;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
 
 pub use osm_data::*;
 pub use runtime::*;
 pub use settings::*;
 pub use input::*;
+pub use camera::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use cache_maintenance::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use connectivity::Connectivity;
+pub use texture_cache::*;
+pub use vibe_world_core::retry::TileRetries;
+pub use input_map::{InputMap, InputMapAppExt, RebindState};
+pub use user_settings::UserSettings;
+pub use launch_options::LaunchOptions;
+pub use loading::AppState;
+pub use comparison::ProviderComparison;
+pub use stereo::StereoView;
+pub use style::Style;
+pub use tile_debug::{TileDebugOverlay, TileLoadCounts};
+#[cfg(not(target_arch = "wasm32"))]
+pub use style::StyleWatcher;
+pub use tile_generators::*;
+pub use game::*;
+pub use tile_memory::*;
+pub use tile_atlas::*;
+pub use environment::{AtmosphereSettings, EnvironmentSettings, Sun, WorldClock};
+pub use water::WaterSettings;
+pub use camera_path::{CameraKeyframe, CameraPath, PathRecorder, PathRecorderState};
+pub use measurement::Measurement;
+pub use routing::Routing;
+pub use overpass::Overpass;
+pub use buildings::Buildings;
+pub use roads::RoadNetwork;
+pub use markers::{MarkerBadges, MarkerImport, Markers};
+pub use street_lamps::StreetLamps;
+pub use weather::WeatherRadar;
+pub use imagery_layers::{ImageryLayers, TimelineScrub, BASE_LAYER_ID, SATELLITE_LAYER_ID};
+pub use layer_manager::{LayerManager, LayerManagerAppExt};
+pub use http_client::HttpClient;
+#[cfg(not(target_arch = "wasm32"))]
+pub use multiplayer::{Multiplayer, Peer};
+#[cfg(not(target_arch = "wasm32"))]
+pub use chat::{Chat, ChatDraft};
+#[cfg(not(target_arch = "wasm32"))]
+pub use islands::{IslandMetadata, IslandState, Islands, PlacedObject};
+#[cfg(not(target_arch = "wasm32"))]
+pub use island_editor::{BrushStroke, EditMode, FormField, GizmoHandle, IslandEditor, IslandForm, ObjectDrag};
+#[cfg(not(target_arch = "wasm32"))]
+pub use island_assets::IslandAssetLibrary;
+#[cfg(not(target_arch = "wasm32"))]
+pub use benchmark::Benchmark;
+#[cfg(not(target_arch = "wasm32"))]
+pub use metrics::MetricsExport;
 // Constants are used directly, so no need to re-export 
\ No newline at end of file
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use crate::events::SpawnGeoModel;
+use crate::resources::user_settings::read_settings_table;
+
+const MODELS_TABLE: &str = "models";
+
+/// The models listed in the settings file, placed at startup
+pub fn configured_models() -> Vec<SpawnGeoModel> {
+    read_settings_table().map(|table| parse_models(&table)).unwrap_or_default()
+}
+
+// Read the [[models]] tables; entries without a path or position are skipped with a warning
+fn parse_models(settings: &toml::Table) -> Vec<SpawnGeoModel> {
+    let Some(models) = settings.get(MODELS_TABLE).and_then(|value| value.as_array()) else {
+        return Vec::new();
+    };
+
+    // TOML numbers without a decimal point are integers
+    let number = |model: &toml::Value, key: &str| {
+        model.get(key).and_then(|value| value.as_float().or_else(|| value.as_integer().map(|n| n as f64)))
+    };
+    models
+        .iter()
+        .filter_map(|model| {
+            let path = model.get("path").and_then(|value| value.as_str());
+            let (Some(path), Some(lat), Some(lon)) = (path, number(model, "lat"), number(model, "lon")) else {
+                warn!("Models need a path, lat and lon in the settings file");
+                return None;
+            };
+            Some(SpawnGeoModel {
+                lat,
+                lon,
+                heading: number(model, "heading").unwrap_or(0.0),
+                scale: number(model, "scale").unwrap_or(1.0),
+                path: path.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_are_read_from_the_settings() {
+        let settings: toml::Table = r#"
+            [[models]]
+            path = "models/martinitoren.glb"
+            lat = 53.2193
+            lon = 6.5681
+            heading = 90
+
+            [[models]]
+            path = "models/nowhere.glb"
+        "#
+        .parse()
+        .unwrap();
+
+        let models = parse_models(&settings);
+        assert_eq!(
+            models,
+            vec![SpawnGeoModel {
+                lat: 53.2193,
+                lon: 6.5681,
+                heading: 90.0,
+                scale: 1.0,
+                path: "models/martinitoren.glb".to_string(),
+            }]
+        );
+    }
+}
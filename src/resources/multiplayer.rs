@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::net::{ConnectionStatus, GeoPose, SharedInbox};
+
+/// Another user on the relay server, shown as an avatar at their camera position
+pub struct Peer {
+    pub name: String,
+    pub pose: GeoPose,
+    pub last_seen: f32, // Elapsed seconds of the last update from the server
+    pub avatar: Entity,
+    pub label: Entity,  // UI text with the name, kept above the avatar
+}
+
+/// Connection to the multiplayer relay server and the users seen on it
+#[derive(Resource)]
+pub struct Multiplayer {
+    pub server: String, // URL the session talks to, empty while offline
+    pub name: String,   // Name this user introduced themselves with
+    pub outgoing: Option<UnboundedSender<String>>, // Dropping it ends the session
+    pub inbox: SharedInbox,
+    pub status: ConnectionStatus,
+    pub own_id: Option<u64>, // Id the server knows this user by, its own updates are echoed back by some servers
+    pub peers: HashMap<u64, Peer>,
+    pub send_interval: f32,  // Seconds between position updates while the camera moves
+    pub keepalive: f32,      // Seconds after which the position is sent again even if unchanged
+    pub peer_timeout: f32,   // Seconds without an update before a user is taken off the map
+    pub last_sent: Option<(f32, GeoPose)>, // When the position was last sent, and what it was
+}
+
+impl Default for Multiplayer {
+    fn default() -> Self {
+        Self {
+            server: String::new(),
+            name: String::new(),
+            outgoing: None,
+            inbox: SharedInbox::default(),
+            status: ConnectionStatus::Offline,
+            own_id: None,
+            peers: HashMap::new(),
+            send_interval: 0.2,
+            keepalive: 5.0,
+            peer_timeout: 30.0,
+            last_sent: None,
+        }
+    }
+}
+
+impl Multiplayer {
+    /// Queue a message for the server, dropped while there is no session
+    pub fn send(&self, text: String) {
+        if let Some(outgoing) = &self.outgoing {
+            let _ = outgoing.send(text);
+        }
+    }
+}
@@ -1,15 +1,85 @@
 use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use crate::resources::CancelFlag;
+use std::time::Duration;
 use parking_lot::Mutex;
+use crate::osm::{DownloadClass, DownloadSlots, HostThrottle, LayerSource, SharedNetworkStatus, SharedThrottle, TileError, TileSources};
+use crate::resources::constants::{BACKGROUND_ZOOM_LEVEL, DEFAULT_ZOOM_LEVEL};
+use crate::resources::{ImageryLayers, TextureCache, TileRetries, UserSettings};
+
+/// Imagery of one layer of a pending tile
+pub struct PendingLayer {
+    pub id: u32,
+    pub image: Option<image::DynamicImage>, // None means the layer failed to load
+    pub low_res: bool, // Image was upscaled from a cached lower zoom ancestor
+    pub error: Option<TileError>, // Why the layer failed to load
+}
+
+/// A tile finished by the async loader, waiting to be spawned
+/// Only holds the layers that weren't in the texture cache yet
+pub struct PendingTile {
+    pub x: u32,
+    pub y: u32,
+    pub zoom: u32,
+    pub layers: Vec<PendingLayer>,
+    pub is_background: bool,
+}
+
+pub type PendingTiles = Arc<Mutex<Vec<PendingTile>>>;
+
+/// A download of a visible or prefetched tile in flight
+pub struct TileDownload {
+    pub class: DownloadClass,
+    pub priority: i32, // As last selected or recalculated, lower loads first
+    pub cancel: Arc<CancelFlag>, // Cancelled to stop the loader task, e.g. when the tile became redundant
+}
 
 #[derive(Resource)]
 pub struct OSMData {
-    pub tiles: Vec<(u32, u32, u32, Entity)>, // (x, y, zoom, entity)
-    pub background_tiles: Vec<(u32, u32, u32, Entity)>, // (x, y, zoom, entity) for low-res background
-    pub loaded_tiles: Vec<(u32, u32, u32)>,  // (x, y, zoom)
-    pub loaded_background_tiles: Vec<(u32, u32, u32)>,  // (x, y, zoom) for background
-    pub pending_tiles: Arc<Mutex<Vec<(u32, u32, u32, Option<image::DynamicImage>, bool)>>>, // (x, y, zoom, image, is_background)
+    pub tiles: HashMap<(u32, u32, u32), Entity>, // Spawned tiles by (x, y, zoom)
+    pub background_tiles: HashMap<(u32, u32, u32), Entity>, // ... for low-res background
+    pub loaded_tiles: HashSet<(u32, u32, u32)>,  // Requested or spawned, by (x, y, zoom)
+    pub loaded_background_tiles: HashSet<(u32, u32, u32)>,  // ... for background
+    pub pending_tiles: PendingTiles,
+    pub downloads_in_flight: HashMap<(u32, u32, u32), TileDownload>, // Of visible and prefetched tiles, by (x, y, zoom)
+    pub selection_cutoff: i32, // Worst priority among the visible tiles last selected
     pub current_zoom: u32,
     pub background_zoom: u32, // Zoom level for background tiles
     pub total_time: f32, // Track total time for garbage collection
-} 
\ No newline at end of file
+    pub texture_cache: TextureCache, // Decoded textures of recently seen tiles
+    pub retries: TileRetries, // Failed tiles waiting to be loaded again
+    pub layers: Vec<LayerSource>, // Imagery layers new tiles are built from, bottom first
+    pub render_distance: i32, // Scales how far away tiles still count as in view
+    pub tile_ttl: Duration, // Cached tiles older than this are checked with the server before use
+    pub throttle: SharedThrottle, // Request pacing and backoff per tile server host, shared with the loader tasks
+    pub network: SharedNetworkStatus, // Whether the tile servers can be reached, shared with the loader tasks
+    pub sources: TileSources, // Where the layers' tiles are loaded from, by layer URL
+    pub downloads: DownloadSlots, // Tile downloads in flight per priority class, shared with the loader tasks
+}
+
+impl Default for OSMData {
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            background_tiles: HashMap::new(),
+            loaded_tiles: HashSet::new(),
+            loaded_background_tiles: HashSet::new(),
+            pending_tiles: Arc::new(Mutex::new(Vec::new())),
+            downloads_in_flight: HashMap::new(),
+            selection_cutoff: 0,
+            current_zoom: DEFAULT_ZOOM_LEVEL,
+            background_zoom: BACKGROUND_ZOOM_LEVEL,
+            total_time: 0.0,
+            texture_cache: TextureCache::default(),
+            retries: TileRetries::default(),
+            layers: ImageryLayers::default().sources(),
+            render_distance: 3,
+            tile_ttl: UserSettings::default().tile_ttl(),
+            throttle: Arc::new(Mutex::new(HostThrottle::default())),
+            network: SharedNetworkStatus::default(),
+            sources: TileSources::default(),
+            downloads: DownloadSlots::default(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::osm::{OverpassClient, OverpassData, OverpassQuery, TileId};
+use crate::resources::{HttpClient, TaskRuntime, UserSettings};
+
+/// Where the answer to an Overpass query is put once the server answered
+pub type OverpassReply = Arc<Mutex<Option<Result<Arc<OverpassData>, String>>>>;
+
+/// The Overpass server OpenStreetMap data is queried from
+/// Everything drawn from Overpass data asks through it, so their queries share one throttle
+/// and cache
+#[derive(Resource, Default)]
+pub struct Overpass {
+    client: Option<OverpassClient>,
+}
+
+impl Overpass {
+    /// A client of the server in the settings, made again when the setting changed
+    pub fn client(&mut self, settings: &UserSettings, http_client: &HttpClient) -> OverpassClient {
+        match &self.client {
+            Some(client) if client.server == settings.overpass_server => client.clone(),
+            _ => {
+                let client = OverpassClient::new(&settings.overpass_server, &http_client.client);
+                self.client = Some(client.clone());
+                client
+            }
+        }
+    }
+}
+
+/// The data of a tile around the camera
+pub enum OverpassTile {
+    Loading(OverpassReply),
+    Loaded(Vec<Entity>), // What was drawn for the tile
+    Failed,              // Not asked for again until the camera left it behind
+}
+
+/// Tiles around the camera whose OpenStreetMap data is drawn, queried from Overpass one by one
+#[derive(Default)]
+pub struct OverpassTiles {
+    pub tiles: HashMap<TileId, OverpassTile>,
+}
+
+impl OverpassTiles {
+    /// Forget the tiles more than `range` tiles away from `center`, returning what was drawn for them
+    pub fn drop_beyond(&mut self, center: TileId, range: i64) -> Vec<Entity> {
+        let mut dropped = Vec::new();
+        self.tiles.retain(|id, tile| {
+            if tile_distance(*id, center) <= range {
+                return true;
+            }
+            if let OverpassTile::Loaded(entities) = tile {
+                dropped.append(entities);
+            }
+            false
+        });
+        dropped
+    }
+
+    /// Query the tiles within `range` tiles from `center` that weren't asked for yet, nearest
+    /// first, keeping at most `max_loading` queries in flight
+    pub fn request_around(
+        &mut self,
+        center: TileId,
+        range: i64,
+        max_loading: usize,
+        query: fn(TileId) -> OverpassQuery,
+        (client, task_runtime): (&OverpassClient, &TaskRuntime),
+    ) {
+        let loading = self.tiles.values().filter(|tile| matches!(tile, OverpassTile::Loading(_))).count();
+        let rows = 1_i64 << center.z;
+        let mut missing: Vec<TileId> = (-range..=range)
+            .flat_map(|dx| (-range..=range).map(move |dy| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                let y = center.y as i64 + dy;
+                let x = (center.column() + dx) as i32 as u32;
+                (0..rows).contains(&y).then(|| TileId::new(x, y as u32, center.z))
+            })
+            .filter(|id| !self.tiles.contains_key(id))
+            .collect();
+        missing.sort_by_key(|id| tile_distance(*id, center));
+
+        for id in missing.into_iter().take(max_loading.saturating_sub(loading)) {
+            let reply: OverpassReply = Arc::default();
+            self.tiles.insert(id, OverpassTile::Loading(Arc::clone(&reply)));
+            let client = client.clone();
+            task_runtime.spawn(async move {
+                let result = client.fetch(&query(id)).await.map_err(|e| e.to_string());
+                *reply.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+            });
+        }
+    }
+
+    /// The answers that arrived, for the caller to draw and record as Loaded
+    /// Failed queries are logged and their tiles marked Failed
+    pub fn take_answers(&mut self) -> Vec<(TileId, Arc<OverpassData>)> {
+        let mut answers = Vec::new();
+        for (id, tile) in self.tiles.iter_mut() {
+            let OverpassTile::Loading(reply) = tile else {
+                continue;
+            };
+            let Some(result) = reply.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+                continue;
+            };
+            match result {
+                Ok(data) => {
+                    answers.push((*id, data));
+                    *tile = OverpassTile::Loaded(Vec::new());
+                }
+                Err(e) => {
+                    warn!("Overpass query for tile {}/{}/{} failed: {}", id.z, id.x, id.y, e);
+                    *tile = OverpassTile::Failed;
+                }
+            }
+        }
+        answers
+    }
+}
+
+// Tiles between two tiles of the same zoom level, counting diagonal steps as one
+fn tile_distance(a: TileId, b: TileId) -> i64 {
+    (a.column() - b.column()).abs().max((a.y as i64 - b.y as i64).abs())
+}
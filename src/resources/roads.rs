@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::overlays::roads::RoadClass;
+use crate::resources::overpass::OverpassTiles;
+
+/// Roads drawn as ribbons over the tiles near the ground, see RoadPlugin
+#[derive(Resource, Default)]
+pub struct RoadNetwork {
+    pub tiles: OverpassTiles, // Loaded tiles hold a ribbon entity per road class
+    pub materials: HashMap<RoadClass, Handle<StandardMaterial>>, // Shared by the ribbons of a class
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+use crate::overlays::routing::Route;
+
+/// Where the answer of a route request is put once the routing server replied
+pub type RouteReply = Arc<Mutex<Option<Result<Route, String>>>>;
+
+/// Start and end picked in routing mode and the route between them
+#[derive(Resource, Default)]
+pub struct Routing {
+    pub active: bool,
+    pub start: Option<(f64, f64)>, // (lat, lon)
+    pub end: Option<(f64, f64)>,
+    pub route: Option<Route>,
+    pub error: Option<String>,       // Why the last request failed
+    pub pending: Option<RouteReply>, // The request in flight; a newer request replaces it, dropping the answer
+}
+
+impl Routing {
+    /// Start over with no points and no route
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+        self.route = None;
+        self.error = None;
+        self.pending = None;
+    }
+}
@@ -1,5 +1,143 @@
 use bevy::prelude::*;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::Mutex;
+use std::task::{Poll, Waker};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::runtime::Runtime;
 
+/// Runs the background work of the tile pipeline, like downloads
+/// Natively that's a Tokio runtime; in the browser there are no threads to run one on,
+/// so futures run on the page's event loop instead
 #[derive(Resource)]
-pub struct TokioRuntime(pub Runtime); 
\ No newline at end of file
+pub struct TaskRuntime {
+    #[cfg(not(target_arch = "wasm32"))]
+    runtime: Runtime,
+}
+
+/// Multi-threaded Tokio runtime with the given number of worker threads, 0 for one per CPU core
+#[cfg(not(target_arch = "wasm32"))]
+pub fn build_runtime(worker_threads: usize) -> std::io::Result<Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if worker_threads > 0 {
+        builder.worker_threads(worker_threads);
+    }
+    builder.enable_all().build()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TaskRuntime {
+    /// Runtime with the given number of worker threads, 0 for one per CPU core
+    pub fn new(worker_threads: usize) -> Self {
+        Self {
+            runtime: build_runtime(worker_threads).expect("Failed to create Tokio runtime"),
+        }
+    }
+
+    /// Run a future in the background
+    pub fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+        self.runtime.spawn(future);
+    }
+
+    /// Run blocking work, like disk IO, on a thread of its own
+    pub fn spawn_blocking(&self, work: impl FnOnce() + Send + 'static) {
+        self.runtime.spawn_blocking(work);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl TaskRuntime {
+    /// The browser has a single thread, worker_threads is ignored
+    pub fn new(_worker_threads: usize) -> Self {
+        Self {}
+    }
+
+    /// Run a future in the background
+    pub fn spawn(&self, future: impl Future<Output = ()> + 'static) {
+        wasm_bindgen_futures::spawn_local(future);
+    }
+}
+
+/// Set to stop a background task, shared between the task and whoever may cancel it
+/// Cancelling wakes the task, so one waiting on a stalled download stops right away
+/// instead of when the download times out
+#[derive(Default)]
+pub struct CancelFlag {
+    cancelled: AtomicBool,
+    waker: Mutex<Option<Waker>>, // Of the task last polled in unless_cancelled
+}
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(waker) = self.waker.lock().take() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Run a future until it finishes or the flag is cancelled, None if it was cancelled
+/// Dropping the future stops its work
+pub async fn unless_cancelled<F: Future>(cancel: &CancelFlag, future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        // Registered before checking the flag, so a cancel in between still wakes the task
+        *cancel.waker.lock() = Some(cx.waker().clone());
+        if cancel.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        future.as_mut().poll(cx).map(Some)
+    })
+    .await
+}
+
+/// Wait without blocking the thread the future runs on
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Wait without blocking the thread the future runs on
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        // Without a window (e.g. in a worker) there is no timer to wait for
+        let scheduled = web_sys::window().is_some_and(|window| {
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+                .is_ok()
+        });
+        if !scheduled {
+            let _ = resolve.call0(&wasm_bindgen::JsValue::NULL);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn cancelling_wakes_a_stalled_task() {
+        let cancel = Arc::new(CancelFlag::default());
+        let task = tokio::spawn({
+            let cancel = cancel.clone();
+            async move { unless_cancelled(&cancel, std::future::pending::<()>()).await }
+        });
+        tokio::task::yield_now().await;
+        cancel.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(1), task).await;
+        assert_eq!(result.expect("Cancelled task wasn't woken").unwrap(), None);
+
+        assert_eq!(unless_cancelled(&CancelFlag::default(), async { 7 }).await, Some(7));
+    }
+}
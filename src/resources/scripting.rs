@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+use parking_lot::Mutex;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::events::SpawnGeoModel;
+use crate::osm::TileId;
+use crate::resources::user_settings::{read_settings_table, settings_path};
+
+const SCRIPTS_TABLE: &str = "scripts";
+// The hook called every frame, with the seconds since the last frame
+const UPDATE_HOOK: &str = "on_update";
+// Operations a script may take per call, so an endless loop stops instead of freezing the viewer
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// What a script asked the viewer to do, done by the scripting systems after the script ran
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptCommand {
+    FlyTo { lat: f64, lon: f64, altitude_m: f64, duration: f32 },
+    AddMarker { lat: f64, lon: f64, label: Option<String>, icon: Option<String> }, // Icon is an image path
+    SpawnModel(SpawnGeoModel),
+}
+
+/// The viewer's state as scripts see it, updated before the scripts run each frame
+#[derive(Clone, Debug, Default)]
+pub struct ScriptView {
+    pub camera: (f64, f64, f64), // Latitude, longitude and altitude in meters
+    pub visible_tiles: Vec<TileId>,
+}
+
+// Shared between the functions registered in the engine and the resource
+#[derive(Default)]
+struct ScriptState {
+    commands: Vec<ScriptCommand>,
+    view: ScriptView,
+}
+
+// A loaded script: its compiled code, and `this` of its hooks, where it keeps its progress
+struct Script {
+    name: String,
+    ast: AST,
+    this: Dynamic,
+}
+
+/// Rhai scripts automating the viewer, see ScriptingPlugin
+/// Scripts call fly_to, add_marker, spawn_model, visible_tiles and camera; what they ask for is
+/// queued as ScriptCommands, since scripts run outside of the systems doing the work
+#[derive(Resource)]
+pub struct Scripting {
+    engine: Engine,
+    scripts: Vec<Script>,
+    state: Arc<Mutex<ScriptState>>,
+}
+
+impl Default for Scripting {
+    fn default() -> Self {
+        let state = Arc::new(Mutex::new(ScriptState::default()));
+        Self { engine: script_engine(&state), scripts: Vec::new(), state }
+    }
+}
+
+impl Scripting {
+    /// Compile a script and run its top level code; it stays loaded if it has an on_update hook
+    pub fn load(&mut self, name: &str, source: &str) -> Result<(), anyhow::Error> {
+        let ast = self.engine.compile(source).map_err(|e| anyhow::anyhow!("{}: {}", name, e))?;
+        self.engine.run_ast(&ast).map_err(|e| anyhow::anyhow!("{}: {}", name, e))?;
+
+        if ast.iter_functions().any(|function| function.name == UPDATE_HOOK && function.params.len() == 1) {
+            // A script loaded again replaces the running one
+            self.scripts.retain(|script| script.name != name);
+            self.scripts.push(Script { name: name.to_string(), ast, this: Dynamic::from_map(Map::new()) });
+        }
+        Ok(())
+    }
+
+    /// Call the on_update hook of the loaded scripts; a script failing is stopped
+    pub fn update(&mut self, delta_seconds: f32) {
+        let engine = &self.engine;
+        self.scripts.retain_mut(|script| {
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut script.this);
+            let result = engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                UPDATE_HOOK,
+                (delta_seconds as f64,),
+            );
+            match result {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("Stopped script {}: {}", script.name, e);
+                    false
+                }
+            }
+        });
+    }
+
+    /// Update what scripts see of the viewer
+    pub fn set_view(&self, view: ScriptView) {
+        self.state.lock().view = view;
+    }
+
+    /// Take what the scripts asked for since the last call
+    pub fn take_commands(&self) -> Vec<ScriptCommand> {
+        std::mem::take(&mut self.state.lock().commands)
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.scripts.is_empty()
+    }
+}
+
+// An engine with the viewer's functions, and print and debug going to the log
+fn script_engine(state: &Arc<Mutex<ScriptState>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| info!("Script: {}", text));
+    engine.on_debug(|text, source, _| debug!("Script {}: {}", source.unwrap_or_default(), text));
+
+    let queue = |state: &Arc<Mutex<ScriptState>>| {
+        let state = state.clone();
+        move |command: ScriptCommand| state.lock().commands.push(command)
+    };
+
+    let push = queue(state);
+    engine.register_fn("fly_to", move |lat: Dynamic, lon: Dynamic, altitude_m: Dynamic, duration: Dynamic| {
+        let (lat, lon, altitude_m) = (number(lat)?, number(lon)?, number(altitude_m)?);
+        push(ScriptCommand::FlyTo { lat, lon, altitude_m, duration: number(duration)? as f32 });
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let push = queue(state);
+    engine.register_fn("add_marker", move |lat: Dynamic, lon: Dynamic| {
+        push(ScriptCommand::AddMarker { lat: number(lat)?, lon: number(lon)?, label: None, icon: None });
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("add_marker", move |lat: Dynamic, lon: Dynamic, label: &str| {
+        push(ScriptCommand::AddMarker { lat: number(lat)?, lon: number(lon)?, label: Some(label.to_string()), icon: None });
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn("add_marker", move |lat: Dynamic, lon: Dynamic, label: &str, icon: &str| {
+        let (label, icon) = (Some(label.to_string()).filter(|label| !label.is_empty()), Some(icon.to_string()));
+        push(ScriptCommand::AddMarker { lat: number(lat)?, lon: number(lon)?, label, icon });
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+
+    let push = queue(state);
+    engine.register_fn("spawn_model", move |path: &str, lat: Dynamic, lon: Dynamic| {
+        let model = SpawnGeoModel { lat: number(lat)?, lon: number(lon)?, heading: 0.0, scale: 1.0, path: path.to_string() };
+        push(ScriptCommand::SpawnModel(model));
+        Ok::<_, Box<EvalAltResult>>(())
+    });
+    let push = queue(state);
+    engine.register_fn(
+        "spawn_model",
+        move |path: &str, lat: Dynamic, lon: Dynamic, heading: Dynamic, scale: Dynamic| {
+            let (lat, lon) = (number(lat)?, number(lon)?);
+            let model = SpawnGeoModel { lat, lon, heading: number(heading)?, scale: number(scale)?, path: path.to_string() };
+            push(ScriptCommand::SpawnModel(model));
+            Ok::<_, Box<EvalAltResult>>(())
+        },
+    );
+
+    let view = state.clone();
+    engine.register_fn("visible_tiles", move || -> Array {
+        let view = &view.lock().view;
+        view.visible_tiles
+            .iter()
+            .map(|tile| {
+                let mut map = Map::new();
+                map.insert("z".into(), (tile.z as i64).into());
+                map.insert("x".into(), (tile.x as i64).into());
+                map.insert("y".into(), (tile.y as i64).into());
+                Dynamic::from_map(map)
+            })
+            .collect()
+    });
+
+    let view = state.clone();
+    engine.register_fn("camera", move || -> Map {
+        let (lat, lon, altitude_m) = view.lock().view.camera;
+        let mut map = Map::new();
+        map.insert("lat".into(), lat.into());
+        map.insert("lon".into(), lon.into());
+        map.insert("altitude".into(), altitude_m.into());
+        map
+    });
+
+    engine
+}
+
+// Numbers from scripts, which may be written with or without a decimal point
+fn number(value: Dynamic) -> Result<f64, Box<EvalAltResult>> {
+    let type_name = value.type_name();
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|n| n as f64))
+        .map_err(|_| format!("Expected a number, got {}", type_name).into())
+}
+
+/// The scripts listed in the settings file ([[scripts]] with a path), run at startup
+/// Relative paths are relative to the settings file
+pub fn configured_scripts() -> Vec<PathBuf> {
+    let path = settings_path();
+    let base = path.parent().unwrap_or(Path::new("."));
+    read_settings_table().map(|table| parse_scripts(&table, base)).unwrap_or_default()
+}
+
+fn parse_scripts(settings: &toml::Table, base: &Path) -> Vec<PathBuf> {
+    let Some(scripts) = settings.get(SCRIPTS_TABLE).and_then(|value| value.as_array()) else {
+        return Vec::new();
+    };
+    scripts
+        .iter()
+        .filter_map(|script| {
+            let Some(path) = script.get("path").and_then(|value| value.as_str()) else {
+                warn!("Scripts need a path in the settings file");
+                return None;
+            };
+            Some(base.join(path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_queue_commands_and_keep_their_progress() {
+        let mut scripting = Scripting::default();
+        scripting.set_view(ScriptView { camera: (53.2, 6.5, 800.0), visible_tiles: vec![TileId::new(8414, 5384, 14)] });
+        let source = r#"
+            let tile = visible_tiles()[0];
+            add_marker(camera().lat, 6.5, `tile ${tile.z}/${tile.x}/${tile.y}`);
+            add_marker(53.1, 6.6, "", "icons/flag.png");
+            spawn_model("models/mill.glb", 53, 6.5);
+
+            fn on_update(dt) {
+                this.elapsed = (this.elapsed ?? 0.0) + dt;
+                if this.elapsed >= 1.0 {
+                    fly_to(53.22, 6.57, 500, this.elapsed);
+                }
+            }
+        "#;
+        scripting.load("tour.rhai", source).unwrap();
+        assert_eq!(
+            scripting.take_commands(),
+            vec![
+                ScriptCommand::AddMarker { lat: 53.2, lon: 6.5, label: Some("tile 14/8414/5384".to_string()), icon: None },
+                ScriptCommand::AddMarker { lat: 53.1, lon: 6.6, label: None, icon: Some("icons/flag.png".to_string()) },
+                ScriptCommand::SpawnModel(SpawnGeoModel {
+                    lat: 53.0,
+                    lon: 6.5,
+                    heading: 0.0,
+                    scale: 1.0,
+                    path: "models/mill.glb".to_string(),
+                }),
+            ]
+        );
+
+        scripting.update(0.5);
+        assert!(scripting.take_commands().is_empty());
+        scripting.update(0.5);
+        assert_eq!(
+            scripting.take_commands(),
+            vec![ScriptCommand::FlyTo { lat: 53.22, lon: 6.57, altitude_m: 500.0, duration: 1.0 }]
+        );
+
+        assert!(scripting.load("broken.rhai", "fly_to(\"north\", 6.5, 500, 1);").is_err());
+        assert!(scripting.is_running());
+    }
+
+    #[test]
+    fn scripts_are_read_from_the_settings() {
+        let settings: toml::Table = "[[scripts]]\npath = \"tours/groningen.rhai\"\n\n[[scripts]]\n".parse().unwrap();
+        assert_eq!(parse_scripts(&settings, Path::new("/config")), vec![PathBuf::from("/config/tours/groningen.rhai")]);
+    }
+}
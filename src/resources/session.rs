@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::BufWriter;
+use crate::osm::TileId;
+
+/// One frame of a camera session: the camera pose and what the tile system decided that frame
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionFrame {
+    pub delta: f32,         // Seconds since the previous frame
+    pub translation: DVec3, // World coordinates, not relative to the render origin
+    pub rotation: Quat,
+    pub requested: Vec<TileId>, // Tiles the scheduler started loading
+    pub spawned: Vec<TileId>,
+    pub despawned: Vec<TileId>,
+}
+
+impl SessionFrame {
+    /// The frame as one line of JSON, tiles written as "z/x/y"
+    pub fn to_json_line(&self) -> String {
+        let tiles = |tiles: &[TileId]| tiles.iter().map(|tile| format!("{}/{}/{}", tile.z, tile.x, tile.y)).collect::<Vec<_>>();
+        json!({
+            "delta": self.delta,
+            "translation": self.translation.to_array(),
+            "rotation": self.rotation.to_array(),
+            "requested": tiles(&self.requested),
+            "spawned": tiles(&self.spawned),
+            "despawned": tiles(&self.despawned),
+        })
+        .to_string()
+    }
+
+    /// Parse a line written by `to_json_line`
+    pub fn from_json_line(line: &str) -> Result<Self, anyhow::Error> {
+        let value: Value = serde_json::from_str(line)?;
+        let numbers = |key: &str| -> Result<Vec<f64>, anyhow::Error> {
+            value[key]
+                .as_array()
+                .and_then(|array| array.iter().map(Value::as_f64).collect())
+                .ok_or_else(|| anyhow::anyhow!("Session frame has no {}", key))
+        };
+        let tiles = |key: &str| -> Vec<TileId> {
+            let names = value[key].as_array().map(Vec::as_slice).unwrap_or_default();
+            names.iter().filter_map(Value::as_str).filter_map(parse_tile).collect()
+        };
+
+        let (translation, rotation) = (numbers("translation")?, numbers("rotation")?);
+        let (&[x, y, z], &[qx, qy, qz, qw]) = (translation.as_slice(), rotation.as_slice()) else {
+            anyhow::bail!("Session frame has a malformed camera pose");
+        };
+        Ok(Self {
+            delta: value["delta"].as_f64().unwrap_or_default() as f32,
+            translation: DVec3::new(x, y, z),
+            rotation: Quat::from_xyzw(qx as f32, qy as f32, qz as f32, qw as f32),
+            requested: tiles("requested"),
+            spawned: tiles("spawned"),
+            despawned: tiles("despawned"),
+        })
+    }
+}
+
+// A "z/x/y" tile address
+fn parse_tile(text: &str) -> Option<TileId> {
+    let mut parts = text.split('/').map(|part| part.parse::<u32>().ok());
+    let (z, x, y) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(TileId::new(x, y, z))
+}
+
+/// Read the frames of a session file, one JSON line per frame
+pub fn parse_session(contents: &str) -> Result<Vec<SessionFrame>, anyhow::Error> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| SessionFrame::from_json_line(line).map_err(|e| anyhow::anyhow!("Frame {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// Tile decisions added up over a session, printed when a replay ends
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SessionTotals {
+    pub frames: usize,
+    pub requested: usize,
+    pub spawned: usize,
+    pub despawned: usize,
+}
+
+impl SessionTotals {
+    pub fn add(&mut self, frame: &SessionFrame) {
+        self.frames += 1;
+        self.requested += frame.requested.len();
+        self.spawned += frame.spawned.len();
+        self.despawned += frame.despawned.len();
+    }
+}
+
+/// A camera session being recorded (--record-session) or replayed (--replay-session)
+/// Replaying a recording while recording another one logs the tile decisions for the same
+/// camera stream and frame times, so two versions of the scheduler can be compared
+#[derive(Resource, Default)]
+pub struct CameraSession {
+    pub recording: Option<BufWriter<File>>,
+    pub replay: Vec<SessionFrame>,
+    pub next_frame: usize,     // Replayed frame to play next
+    pub totals: SessionTotals, // Of the frames recorded so far
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip_through_json_lines() {
+        let frame = SessionFrame {
+            delta: 1.0 / 60.0,
+            translation: DVec3::new(4245.4243561, 0.75, 2660.24205),
+            rotation: Quat::from_rotation_y(0.5),
+            requested: vec![TileId::new(33963, 21281, 16), TileId::new(8490, 5320, 14)],
+            spawned: vec![TileId::new(4, 2, 3)],
+            despawned: Vec::new(),
+        };
+        let contents = format!("{}\n\n{}\n", frame.to_json_line(), SessionFrame::default().to_json_line());
+        let frames = parse_session(&contents).unwrap();
+        assert_eq!(frames, vec![frame.clone(), SessionFrame::default()]);
+
+        let mut totals = SessionTotals::default();
+        frames.iter().for_each(|frame| totals.add(frame));
+        assert_eq!(totals, SessionTotals { frames: 2, requested: 2, spawned: 1, despawned: 0 });
+        assert!(parse_session("{\"delta\": 0.1}").is_err());
+    }
+}
@@ -1,15 +1,261 @@
 use bevy::prelude::*;
+use crate::utils::geo::AntimeridianMode;
 
 // Settings for debug display
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct DebugSettings {
     pub debug_mode: bool,
+} 
+// Color filters for the map tiles
+#[derive(Resource)]
+pub struct TileAppearance {
+    pub brightness: f32,
+    pub grayscale: bool,
+    pub night_mode: bool,   // Dark map with inverted lightness
+    pub fade_duration: f32, // Seconds new tiles take to fade in, 0 shows them at once
+}
+
+impl Default for TileAppearance {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            grayscale: false,
+            night_mode: false,
+            fade_duration: 0.4,
+        }
+    }
+}
+
+/// Globe mode curves the map onto the earth when zoomed far out
+/// Between flat_height and globe_height the map bends smoothly from flat to fully round
+#[derive(Resource)]
+pub struct GlobeSettings {
+    pub enabled: bool,
+    pub flat_height: f32,     // Camera height below which the map is flat
+    pub globe_height: f32,    // Camera height above which the map is a full globe
+    pub max_curved_zoom: u32, // Batches of tiles up to this zoom level are subdivided to bend smoothly
+    pub subdivisions: u32,    // Cells along each side of a subdivided tile
+}
+
+impl Default for GlobeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            // Roughly zoom 7 and zoom 5 below the camera
+            flat_height: 100.0,
+            globe_height: 400.0,
+            max_curved_zoom: 9,
+            subdivisions: 16,
+        }
+    }
+}
+
+impl GlobeSettings {
+    /// How far the map is curved at a camera height: 0 = flat, 1 = globe
+    pub fn blend_at(&self, height: f32) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let t = ((height - self.flat_height) / (self.globe_height - self.flat_height)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Cells along each side of the tiles in a batch mesh of the given zoom level
+    pub fn subdivisions_at(&self, zoom: u32) -> u32 {
+        if self.enabled && zoom <= self.max_curved_zoom {
+            self.subdivisions
+        } else {
+            1
+        }
+    }
+}
+
+// Settings for the minimap widget
+#[derive(Resource)]
+pub struct MinimapSettings {
+    pub size_px: u32,        // Width and height of the minimap on screen
+    pub height_factor: f32,  // Visible world extent as a multiple of camera height
+    pub min_extent: f32,     // Minimum visible world extent in world units
+    pub flight_duration: f32, // Seconds to fly to a clicked location
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            size_px: 200,
+            height_factor: 6.0,
+            min_extent: 4.0,
+            flight_duration: 1.5,
+        }
+    }
+}
+
+impl MinimapSettings {
+    /// World extent covered by the minimap at the given camera height
+    pub fn extent(&self, camera_height: f32) -> f32 {
+        (camera_height * self.height_factor).max(self.min_extent)
+    }
+}
+
+/// Settings for the detached overview window, see OverviewPlugin
+#[derive(Resource)]
+pub struct OverviewSettings {
+    pub window_size: Vec2,  // Logical size the window opens with
+    pub height_factor: f32, // Visible world height as a multiple of camera height
+    pub min_extent: f32,    // Minimum visible world height in world units
+}
+
+impl Default for OverviewSettings {
+    fn default() -> Self {
+        Self {
+            window_size: Vec2::new(640.0, 640.0),
+            height_factor: 24.0,
+            min_extent: 8.0,
+        }
+    }
+}
+
+impl OverviewSettings {
+    /// World extent covered by the overview's height at the given camera height
+    pub fn extent(&self, camera_height: f32) -> f32 {
+        (camera_height * self.height_factor).max(self.min_extent)
+    }
 }
 
-impl Default for DebugSettings {
+// Settings for camera movement
+// Speed scales with altitude as speed_coefficient * height^speed_exponent, clamped
+#[derive(Resource)]
+pub struct MovementSettings {
+    pub base_speed: f32,        // World units per second at speed factor 1.0
+    pub boost_multiplier: f32,  // Speed multiplier when shift is pressed
+    pub look_sensitivity: f32,  // Radians per pixel of mouse motion
+    pub speed_coefficient: f32,
+    pub speed_exponent: f32,
+    pub min_speed_factor: f32,
+    pub max_speed_factor: f32,
+    pub prefetch_seconds: f32,  // How far ahead the tile prefetcher predicts movement
+    pub orbit_min_pitch: f32,   // Steepest orbit camera angle in radians (looking straight down is -PI/2)
+    pub orbit_max_pitch: f32,   // Shallowest orbit camera angle, must stay below the horizon
+    pub min_clearance: f32,     // Lowest camera height above the ground in world units
+    pub clearance_stiffness: f32, // How fast the camera eases back up to it, per second
+}
+
+impl Default for MovementSettings {
     fn default() -> Self {
         Self {
-            debug_mode: false,
+            base_speed: 5.0,
+            boost_multiplier: 3.0,
+            look_sensitivity: 0.002,
+            // Fitted to the old stepped table: 1x at height 5, 15x at height 100
+            speed_coefficient: 0.233,
+            speed_exponent: 0.904,
+            min_speed_factor: 1.0,
+            max_speed_factor: 5000.0,
+            prefetch_seconds: 1.0,
+            orbit_min_pitch: -1.5,
+            orbit_max_pitch: -0.15,
+            min_clearance: 0.001, // Roughly 3 meters
+            clearance_stiffness: 8.0,
+        }
+    }
+}
+
+impl MovementSettings {
+    /// Altitude-based speed multiplier, continuous in height
+    pub fn altitude_factor(&self, height: f32) -> f32 {
+        let height = height.max(1.0);
+        (self.speed_coefficient * height.powf(self.speed_exponent))
+            .clamp(self.min_speed_factor, self.max_speed_factor)
+    }
+
+    /// How far to move the camera up at a height above the ground, to keep it min_clearance
+    /// above it: the camera eases back up over a few frames instead of stopping hard, but
+    /// never stays below the ground itself
+    pub fn clearance_push(&self, height_above_ground: f32, delta: f32) -> f32 {
+        let shortfall = self.min_clearance - height_above_ground;
+        if shortfall <= 0.0 {
+            return 0.0;
         }
+        let eased = shortfall * (1.0 - (-self.clearance_stiffness * delta).exp());
+        eased.max(-height_above_ground)
     }
-} 
\ No newline at end of file
+
+    /// Maximum movement speed at a height (with boost), in world units per second
+    pub fn max_speed_at(&self, height: f32) -> f32 {
+        self.base_speed * self.altitude_factor(height) * self.boost_multiplier
+    }
+}
+
+/// Street level walk mode: the camera stays at eye height on the ground, falls when it is
+/// above it, and moves at walking pace; lengths are in meters, converted at the camera's latitude
+#[derive(Resource)]
+pub struct WalkSettings {
+    pub eye_height_m: f32,     // Camera height above the ground
+    pub walk_speed_m: f32,     // Meters per second
+    pub run_multiplier: f32,   // Speed multiplier while boosting
+    pub jump_speed_m: f32,     // Upward speed of a jump in meters per second
+    pub gravity_m: f32,        // Meters per second squared
+    pub landing_height_m: f32, // Entering walk mode higher than this above the ground flies down instead of falling
+    pub detail_radius_m: f64,  // Tiles this close to the camera are shown at the highest zoom level
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self {
+            eye_height_m: 1.7,
+            walk_speed_m: 1.4,
+            run_multiplier: 4.0,
+            jump_speed_m: 4.0,
+            gravity_m: 9.81,
+            landing_height_m: 50.0,
+            detail_radius_m: 300.0,
+        }
+    }
+}
+
+// Settings for street name decals shown at ground level
+#[derive(Resource)]
+pub struct StreetLabelSettings {
+    pub max_camera_height: f32, // Labels are only shown below this camera height (walk-mode zooms)
+    pub text_height: f32,       // Height of the text on the ground in world units
+    pub font_px: f32,           // Font size the label textures are rasterized at
+    pub elevation_offset: f32,  // Height above the tile plane, just below GPX tracks
+    pub min_spacing: f32,       // Minimum distance between two labels with the same name
+}
+
+impl Default for StreetLabelSettings {
+    fn default() -> Self {
+        Self {
+            max_camera_height: 0.05, // Roughly 150 meters at mid latitudes
+            text_height: 0.002,      // Roughly 6 meters
+            font_px: 48.0,
+            elevation_offset: 0.015,
+            min_spacing: 0.1,
+        }
+    }
+}
+
+// Settings shared by the overlay loaders, from the [overlays] table of the user settings
+#[derive(Resource, Default)]
+pub struct OverlaySettings {
+    pub antimeridian: AntimeridianMode, // How lines crossing ±180° are handled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_eases_back_up_to_the_clearance() {
+        let settings = MovementSettings::default();
+        let clearance = settings.min_clearance;
+        assert_eq!(settings.clearance_push(clearance * 2.0, 0.016), 0.0);
+
+        // Part of the way back up per frame, not all of it
+        let push = settings.clearance_push(clearance / 2.0, 0.016);
+        assert!(push > 0.0 && push < clearance / 2.0);
+
+        // Below the ground it is back on the ground at least
+        assert!(settings.clearance_push(-1.0, 0.016) >= 1.0);
+    }
+}
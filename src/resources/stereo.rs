@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+/// Eye separation per unit of camera height, the 1/30 rule of stereo photography: natural depth
+/// at eye height in walk mode, exaggerated depth from the air so the terrain and buildings still
+/// stand out when seen from far above
+pub const EYE_SEPARATION_PER_HEIGHT: f32 = 1.0 / 30.0;
+
+/// How the view is drawn for 3D viewing, picked in the settings menu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StereoMode {
+    #[default]
+    Off,
+    Anaglyph,   // Red-cyan, for glasses with a red left and a cyan right lens
+    SideBySide, // Left eye on the left half of the window, for stereo viewers and 3D displays
+}
+
+impl StereoMode {
+    /// The modes in the order the settings menu steps through them
+    pub const ALL: [StereoMode; 3] = [StereoMode::Off, StereoMode::Anaglyph, StereoMode::SideBySide];
+
+    /// Name in the settings file
+    pub fn key(self) -> &'static str {
+        match self {
+            StereoMode::Off => "off",
+            StereoMode::Anaglyph => "anaglyph",
+            StereoMode::SideBySide => "side_by_side",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+
+    /// Name on the settings menu
+    pub fn name(self) -> &'static str {
+        match self {
+            StereoMode::Off => "Off",
+            StereoMode::Anaglyph => "Red-cyan anaglyph",
+            StereoMode::SideBySide => "Side by side",
+        }
+    }
+}
+
+/// The stereo mode the eye cameras are set up for, and the images the eyes are drawn into
+/// for the anaglyph; the mode in UserSettings is the one asked for
+#[derive(Resource, Default)]
+pub struct StereoView {
+    pub mode: StereoMode,
+    pub eye_images: Option<[Handle<Image>; 2]>, // Left and right, anaglyph mode only
+}
+
+/// Left and right eye poses for a camera: moved apart along the camera's right axis by the
+/// eye separation for its height, looking in parallel
+pub fn eye_transforms(camera: &Transform) -> [Transform; 2] {
+    let separation = camera.translation.y.max(0.0) * EYE_SEPARATION_PER_HEIGHT;
+    let offset = camera.right() * (separation / 2.0);
+    [
+        camera.with_translation(camera.translation - offset),
+        camera.with_translation(camera.translation + offset),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eyes_are_apart_by_a_thirtieth_of_the_height() {
+        let camera = Transform::from_xyz(4.0, 30.0, 2.0).looking_to(Vec3::NEG_Z, Vec3::Y);
+        let [left, right] = eye_transforms(&camera);
+        assert!(left.translation.abs_diff_eq(Vec3::new(3.5, 30.0, 2.0), 1e-5));
+        assert!(right.translation.abs_diff_eq(Vec3::new(4.5, 30.0, 2.0), 1e-5));
+        assert_eq!((left.rotation, right.rotation), (camera.rotation, camera.rotation));
+
+        for mode in StereoMode::ALL {
+            assert_eq!(StereoMode::from_key(mode.key()), Some(mode));
+        }
+        assert_eq!(StereoMode::from_key("interlaced"), None);
+    }
+}
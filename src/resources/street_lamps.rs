@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+use crate::resources::overpass::OverpassTiles;
+
+/// Street lamps lit around the camera at night, see StreetLampPlugin
+#[derive(Resource, Default)]
+pub struct StreetLamps {
+    pub tiles: OverpassTiles, // Loaded tiles hold their lamp clusters
+    pub glow: Option<(Handle<Mesh>, Handle<StandardMaterial>)>, // Shared by the clusters' glows
+}
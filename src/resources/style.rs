@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::overlays::gpx::GpxStyle;
+use crate::overlays::street_labels::LABEL_FONT_FAMILY;
+use crate::resources::user_settings::settings_path;
+
+const STYLE_FILE_NAME: &str = "style.toml";
+// Seconds between checks whether the style file changed
+const STYLE_POLL_INTERVAL: f32 = 1.0;
+
+/// How overlays and markers look, from style.toml next to the settings file
+/// Everything missing from the file keeps its default, so the file only needs what it changes
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Style {
+    pub fallback_tile_color: Color, // Tiles whose imagery couldn't be loaded
+    #[cfg(not(target_arch = "wasm32"))]
+    pub islands: IslandStyle, // Islands are desktop only
+    pub gpx_color: Color,
+    pub gpx_width: f32,                   // Line width of GPX tracks in world units
+    pub label_font_family: String,        // Font of street name labels
+    pub label_font_file: Option<PathBuf>, // Font file to load for labels, e.g. one not installed
+}
+
+/// How persistent islands are outlined on the map
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IslandStyle {
+    pub highlight_color: Color, // Island borders and tiles while editing, free cells on the grid
+    pub border_color: Color,
+    pub claimed_cell_color: Color, // Grid cells another island has
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for IslandStyle {
+    fn default() -> Self {
+        Self {
+            highlight_color: Color::srgba(0.0, 1.0, 0.5, 0.5),
+            border_color: Color::srgba(0.2, 0.8, 0.3, 0.3),
+            claimed_cell_color: Color::srgb(1.0, 0.35, 0.25),
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        let gpx = GpxStyle::default();
+        Self {
+            fallback_tile_color: Color::srgb(0.8, 0.2, 0.2),
+            #[cfg(not(target_arch = "wasm32"))]
+            islands: IslandStyle::default(),
+            gpx_color: gpx.color,
+            gpx_width: gpx.width,
+            label_font_family: LABEL_FONT_FAMILY.to_string(),
+            label_font_file: None,
+        }
+    }
+}
+
+impl Style {
+    /// Parse a style file; invalid values are reported and keep their defaults
+    pub fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        let table: toml::Table = contents.parse()?;
+        let mut style = Self::default();
+        let section = |name: &str| table.get(name).and_then(|value| value.as_table());
+
+        if let Some(tiles) = section("tiles") {
+            read_color(tiles, "fallback_color", &mut style.fallback_tile_color);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(islands) = section("islands") {
+            read_color(islands, "highlight_color", &mut style.islands.highlight_color);
+            read_color(islands, "border_color", &mut style.islands.border_color);
+            read_color(islands, "claimed_color", &mut style.islands.claimed_cell_color);
+        }
+        if let Some(overlays) = section("overlays") {
+            read_color(overlays, "gpx_color", &mut style.gpx_color);
+            if let Some(width) = overlays.get("gpx_width").and_then(toml_float) {
+                style.gpx_width = (width as f32).max(0.0);
+            }
+        }
+        if let Some(labels) = section("labels") {
+            if let Some(family) = labels.get("font_family").and_then(|v| v.as_str()) {
+                style.label_font_family = family.to_string();
+            }
+            if let Some(file) = labels.get("font_file").and_then(|v| v.as_str()) {
+                style.label_font_file = Some(PathBuf::from(file));
+            }
+        }
+        Ok(style)
+    }
+
+    /// Load the style file, None if there is none or it isn't valid TOML
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        match Self::parse(&contents) {
+            Ok(style) => Some(style),
+            Err(e) => {
+                warn!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Path of the style file, next to the settings file
+pub fn style_path() -> PathBuf {
+    settings_path().with_file_name(STYLE_FILE_NAME)
+}
+
+/// Watches the style file for changes by polling its modification time,
+/// so it can be edited while the viewer runs
+#[derive(Resource)]
+pub struct StyleWatcher {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>, // Of the version in use, None while there is no file
+    pub timer: Timer,
+}
+
+impl StyleWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = file_modified(&path);
+        Self { path, modified, timer: Timer::from_seconds(STYLE_POLL_INTERVAL, TimerMode::Repeating) }
+    }
+
+    /// Whether the file was changed, created or removed since the last call
+    pub fn changed(&mut self) -> bool {
+        let modified = file_modified(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Colors are written as CSS hex: #rgb, #rgba, #rrggbb or #rrggbbaa
+fn read_color(table: &toml::Table, key: &str, color: &mut Color) {
+    let Some(value) = table.get(key).and_then(|v| v.as_str()) else {
+        return;
+    };
+    match Srgba::hex(value) {
+        Ok(parsed) => *color = parsed.into(),
+        Err(e) => warn!("Invalid style color {} = {}: {}", key, value, e),
+    }
+}
+
+// TOML floats, or integers written without a decimal point
+fn toml_float(value: &toml::Value) -> Option<f64> {
+    value.as_float().or_else(|| value.as_integer().map(|i| i as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_the_file_sets() {
+        let style = Style::parse(
+            r##"
+            [tiles]
+            fallback_color = "#333"
+
+            [islands]
+            highlight_color = "#00ff0080"
+
+            [overlays]
+            gpx_width = 0.004
+
+            [labels]
+            font_family = "Noto Sans"
+            font_file = "fonts/NotoSans-Regular.ttf"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(style.fallback_tile_color, Color::srgb_u8(0x33, 0x33, 0x33));
+        assert_eq!(style.islands.highlight_color, Color::srgba_u8(0, 255, 0, 128));
+        assert_eq!(style.gpx_width, 0.004);
+        assert_eq!(style.label_font_family, "Noto Sans");
+        assert_eq!(style.label_font_file, Some(PathBuf::from("fonts/NotoSans-Regular.ttf")));
+        assert_eq!(style.gpx_color, Style::default().gpx_color);
+    }
+
+    #[test]
+    fn invalid_values_keep_their_defaults() {
+        let style = Style::parse("[tiles]\nfallback_color = \"red\"\n[overlays]\ngpx_width = \"wide\"").unwrap();
+        assert_eq!(style, Style::default());
+        assert!(Style::parse("[tiles").is_err());
+    }
+}
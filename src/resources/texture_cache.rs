@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// A decoded tile texture kept alive in the cache
+pub struct CachedTexture {
+    pub handle: Handle<Image>,
+    pub bytes: usize,   // Decoded size (width * height * 4)
+    pub last_used: f32,
+}
+
+/// In-memory cache of decoded tile textures, separate from the on-disk cache of raw PNG bytes
+/// Re-entering an area copies the decoded image into the tile atlas instead of reading and decoding the file again
+/// Every imagery layer of a tile is cached on its own, so the layer stack can change without reloading
+pub struct TextureCache {
+    pub entries: HashMap<(u32, u32, u32, u32), CachedTexture>, // (layer id, x, y, zoom)
+    pub capacity_bytes: usize,
+    pub used_bytes: usize,
+    pub ready: Vec<(u32, u32, u32, bool)>, // Cache hits waiting to be spawned: (x, y, zoom, is_background)
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl Default for TextureCache {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity_bytes: 128 * 1024 * 1024, // ~500 tiles of 256x256 RGBA
+            used_bytes: 0,
+            ready: Vec::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+}
+
+impl TextureCache {
+    /// Drop the textures of every layer of a tile, e.g. when it is evicted to stay within the memory budget
+    pub fn remove_tile(&mut self, (x, y, zoom): (u32, u32, u32)) {
+        self.remove_where(|&(_, tx, ty, tz)| (tx, ty, tz) == (x, y, zoom));
+    }
+
+    /// Drop the textures of a layer, e.g. when its tile server changes
+    pub fn remove_layer(&mut self, layer: u32) {
+        self.remove_where(|&(id, _, _, _)| id == layer);
+    }
+
+    fn remove_where(&mut self, matches: impl Fn(&(u32, u32, u32, u32)) -> bool) {
+        let mut removed_bytes = 0;
+        self.entries.retain(|key, entry| {
+            let keep = !matches(key);
+            if !keep {
+                removed_bytes += entry.bytes;
+            }
+            keep
+        });
+        self.used_bytes -= removed_bytes;
+    }
+
+    /// Look up a texture, counting the hit or miss
+    pub fn get(&mut self, key: (u32, u32, u32, u32), now: f32) -> Option<Handle<Image>> {
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_used = now;
+                self.hits += 1;
+                Some(entry.handle.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Add a texture, evicting the least recently used entries when over capacity
+    pub fn insert(&mut self, key: (u32, u32, u32, u32), handle: Handle<Image>, bytes: usize, now: f32) {
+        if let Some(old) = self.entries.insert(key, CachedTexture { handle, bytes, last_used: now }) {
+            self.used_bytes -= old.bytes;
+        }
+        self.used_bytes += bytes;
+
+        while self.used_bytes > self.capacity_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by(|a, b| a.1.last_used.total_cmp(&b.1.last_used))
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+
+            // Dropping the handle frees the image once no tile uses it anymore
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                self.used_bytes -= evicted.bytes;
+            }
+        }
+    }
+
+    /// Fraction of lookups served from the cache
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f32 / total as f32
+        }
+    }
+}
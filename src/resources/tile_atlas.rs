@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::osm::{
+    atlas_layer_bytes, create_atlas_page, write_atlas_layer, TileFilter, TileGlobe, TileId, TileMaterial,
+    LAYERS_PER_PAGE,
+};
+
+/// An array texture holding up to LAYERS_PER_PAGE tile images, with the material drawing it
+pub struct AtlasPage {
+    pub image: Handle<Image>,
+    pub material: Handle<TileMaterial>,
+    pub free_layers: Vec<u32>,
+}
+
+/// A tile layer (mip chain) compressed in the background, waiting to be put in its layer
+pub struct CompressedTile {
+    pub entity: Entity,
+    pub id: TileId, // Tile the image is for, in case the entity was despawned and reused meanwhile
+    pub data: Vec<u8>,
+}
+
+pub type CompressedTiles = Arc<Mutex<Vec<CompressedTile>>>;
+
+/// Packs tile images into array texture pages
+/// Tiles of the same zoom level on the same page are drawn as one batch mesh
+#[derive(Resource, Default)]
+pub struct TileAtlas {
+    pub pages: Vec<Option<AtlasPage>>, // Emptied pages are released, keeping the indexes of the others
+    pub slots: HashMap<Entity, (usize, u32)>, // Tile entity -> (page, layer)
+    pub batches: HashMap<(u32, usize), Entity>, // (zoom, page) -> batch mesh entity
+    pub dirty: bool, // Batch meshes need to be rebuilt
+    pub filter: TileFilter, // Color filters of every page material
+    pub globe: TileGlobe, // Globe curvature of every page material
+    pub compressed: bool, // Pages are BC1 compressed, decided once at startup
+    pub compressed_tiles: CompressedTiles, // Tile images compressed by background tasks
+}
+
+impl TileAtlas {
+    /// Store a tile layer's data (see atlas_layer_data) in a free layer, adding a page when all are full
+    /// Returns (page, layer), or None if the data doesn't fit a layer
+    pub fn allocate(
+        &mut self,
+        entity: Entity,
+        data: &[u8],
+        images: &mut Assets<Image>,
+        materials: &mut Assets<TileMaterial>,
+    ) -> Option<(usize, u32)> {
+        let page_index = match self
+            .pages
+            .iter()
+            .position(|page| page.as_ref().is_some_and(|page| !page.free_layers.is_empty()))
+        {
+            Some(index) => index,
+            None => {
+                let image = images.add(create_atlas_page(self.compressed));
+                let page = AtlasPage {
+                    material: materials.add(TileMaterial {
+                        texture: image.clone(),
+                        filter: self.filter,
+                        globe: self.globe,
+                    }),
+                    image,
+                    // Reversed so layers are handed out from 0 up
+                    free_layers: (0..LAYERS_PER_PAGE).rev().collect(),
+                };
+                match self.pages.iter().position(Option::is_none) {
+                    Some(index) => {
+                        self.pages[index] = Some(page);
+                        index
+                    }
+                    None => {
+                        self.pages.push(Some(page));
+                        self.pages.len() - 1
+                    }
+                }
+            }
+        };
+
+        let page = self.pages[page_index].as_mut()?;
+        let layer = *page.free_layers.last()?;
+        if !write_atlas_layer(images.get_mut(&page.image)?, layer, data) {
+            return None;
+        }
+        page.free_layers.pop();
+
+        self.slots.insert(entity, (page_index, layer));
+        self.dirty = true;
+        Some((page_index, layer))
+    }
+
+    /// Replace the image data of a tile in its layer, e.g. when the imagery layers change
+    /// Returns false if the tile has no layer or the data doesn't fit it
+    pub fn write(&self, entity: Entity, data: &[u8], images: &mut Assets<Image>) -> bool {
+        let Some(&(page_index, layer)) = self.slots.get(&entity) else {
+            return false;
+        };
+        let Some(page) = self.pages.get(page_index).and_then(Option::as_ref) else {
+            return false;
+        };
+        images
+            .get_mut(&page.image)
+            .is_some_and(|page_image| write_atlas_layer(page_image, layer, data))
+    }
+
+    /// GPU memory a tile takes: its compressed layer, or the decoded layer images it was made of
+    pub fn texture_bytes(&self, decoded_bytes: usize) -> usize {
+        if self.compressed {
+            atlas_layer_bytes(true)
+        } else {
+            decoded_bytes
+        }
+    }
+
+    /// Return the layer of a despawned tile, releasing its page once it is empty
+    pub fn free(&mut self, entity: Entity) {
+        let Some((page_index, layer)) = self.slots.remove(&entity) else {
+            return;
+        };
+        self.dirty = true;
+
+        let Some(page) = self.pages[page_index].as_mut() else {
+            return;
+        };
+        page.free_layers.push(layer);
+        if page.free_layers.len() == LAYERS_PER_PAGE as usize {
+            self.pages[page_index] = None;
+        }
+    }
+
+    /// Change the color filters of all tiles
+    pub fn set_filter(&mut self, filter: TileFilter, materials: &mut Assets<TileMaterial>) {
+        self.filter = filter;
+        for page in self.pages.iter().flatten() {
+            if let Some(material) = materials.get_mut(&page.material) {
+                material.filter = filter;
+            }
+        }
+    }
+
+    /// Change how far tiles are curved onto the globe
+    pub fn set_globe(&mut self, globe: TileGlobe, materials: &mut Assets<TileMaterial>) {
+        self.globe = globe;
+        for page in self.pages.iter().flatten() {
+            if let Some(material) = materials.get_mut(&page.material) {
+                material.globe = globe;
+            }
+        }
+    }
+
+    /// Material for the tiles on a page
+    pub fn material(&self, page: usize) -> Option<Handle<TileMaterial>> {
+        self.pages.get(page)?.as_ref().map(|page| page.material.clone())
+    }
+}
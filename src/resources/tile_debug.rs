@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use crate::resources::OSMData;
+
+/// Where a tile is in the loading pipeline, as the tile debug overlay shows it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TileLoadState {
+    Downloading, // Requested, its layers are being loaded
+    Queued,      // Loaded, waiting for its turn to be uploaded and spawned
+    Loaded,
+    Failed,      // Spawned as a fallback tile, waiting for a retry
+}
+
+impl TileLoadState {
+    pub fn name(self) -> &'static str {
+        match self {
+            TileLoadState::Downloading => "downloading",
+            TileLoadState::Queued => "queued",
+            TileLoadState::Loaded => "loaded",
+            TileLoadState::Failed => "failed",
+        }
+    }
+
+    /// Color of the tile's border and label
+    pub fn color(self) -> Color {
+        match self {
+            TileLoadState::Downloading => Color::srgb(1.0, 0.8, 0.1),
+            TileLoadState::Queued => Color::srgb(0.3, 0.6, 1.0),
+            TileLoadState::Loaded => Color::srgb(0.2, 0.9, 0.3),
+            TileLoadState::Failed => Color::srgb(1.0, 0.2, 0.2),
+        }
+    }
+}
+
+/// Whether the tile debug overlay (F3 by default) is shown: every focus tile's border and
+/// z/x/y colored by its load state, with the loading progress
+#[derive(Resource, Default)]
+pub struct TileDebugOverlay {
+    pub enabled: bool,
+}
+
+/// Number of focus tiles in each load state
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TileLoadCounts {
+    pub downloading: usize,
+    pub queued: usize,
+    pub loaded: usize,
+    pub failed: usize,
+}
+
+impl TileLoadCounts {
+    pub fn count(states: &BTreeMap<(u32, u32, u32), TileLoadState>) -> Self {
+        let mut counts = Self::default();
+        for state in states.values() {
+            match state {
+                TileLoadState::Downloading => counts.downloading += 1,
+                TileLoadState::Queued => counts.queued += 1,
+                TileLoadState::Loaded => counts.loaded += 1,
+                TileLoadState::Failed => counts.failed += 1,
+            }
+        }
+        counts
+    }
+
+    /// Tiles still on their way to the screen
+    pub fn in_progress(&self) -> usize {
+        self.downloading + self.queued
+    }
+
+    /// Share of the tiles that are done loading, failed ones included; 1 with no tiles
+    pub fn progress(&self) -> f32 {
+        let total = self.in_progress() + self.loaded + self.failed;
+        if total == 0 {
+            return 1.0;
+        }
+        (self.loaded + self.failed) as f32 / total as f32
+    }
+}
+
+/// Load state of every focus tile the loader requested or spawned, by (x, y, zoom)
+/// `spawned` holds the tiles on the map, with whether they're fallback tiles
+/// Background tiles cover the whole world at a low zoom level, so they're left out
+pub fn tile_load_states(
+    osm_data: &OSMData,
+    spawned: impl IntoIterator<Item = ((u32, u32, u32), bool)>,
+) -> BTreeMap<(u32, u32, u32), TileLoadState> {
+    let mut waiting: HashSet<(u32, u32, u32)> = osm_data
+        .pending_tiles
+        .lock()
+        .iter()
+        .filter(|tile| !tile.is_background)
+        .map(|tile| (tile.x, tile.y, tile.zoom))
+        .collect();
+    waiting.extend(
+        osm_data.texture_cache.ready.iter().filter(|ready| !ready.3).map(|&(x, y, zoom, _)| (x, y, zoom)),
+    );
+
+    let mut states: BTreeMap<_, _> = osm_data
+        .loaded_tiles
+        .iter()
+        .map(|&key| {
+            let state = if waiting.contains(&key) { TileLoadState::Queued } else { TileLoadState::Downloading };
+            (key, state)
+        })
+        .collect();
+    for (key, failed) in spawned {
+        states.insert(key, if failed { TileLoadState::Failed } else { TileLoadState::Loaded });
+    }
+    states
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::PendingTile;
+
+    #[test]
+    fn tiles_are_classified_by_where_they_are_in_the_pipeline() {
+        let mut osm_data = OSMData {
+            loaded_tiles: HashSet::from([(1, 1, 13), (2, 1, 13), (3, 1, 13), (4, 1, 13), (5, 1, 13)]),
+            ..default()
+        };
+        osm_data.pending_tiles.lock().push(PendingTile { x: 2, y: 1, zoom: 13, layers: Vec::new(), is_background: false });
+        osm_data.texture_cache.ready.push((3, 1, 13, false));
+        // A background tile with the same address doesn't make the focus tile queued
+        osm_data.texture_cache.ready.push((1, 1, 13, true));
+
+        let states = tile_load_states(&osm_data, [((4, 1, 13), false), ((5, 1, 13), true)]);
+        assert_eq!(states[&(1, 1, 13)], TileLoadState::Downloading);
+        assert_eq!(states[&(2, 1, 13)], TileLoadState::Queued);
+        assert_eq!(states[&(3, 1, 13)], TileLoadState::Queued);
+        assert_eq!(states[&(4, 1, 13)], TileLoadState::Loaded);
+        assert_eq!(states[&(5, 1, 13)], TileLoadState::Failed);
+
+        let counts = TileLoadCounts::count(&states);
+        assert_eq!(counts, TileLoadCounts { downloading: 1, queued: 2, loaded: 1, failed: 1 });
+        assert_eq!(counts.progress(), 0.4);
+        assert_eq!(TileLoadCounts::default().progress(), 1.0);
+    }
+}
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemId;
+use crate::osm::{TileBounds, TileId};
+use crate::utils::geo::{lat_lon_to_world, tile_world_origin, tile_world_size};
+
+/// Input passed to a tile generator for every spawned tile
+/// Content should be spawned as children of `tile_entity` so it is despawned together with the tile.
+/// Children live in tile-local space: x and z run from 0 to 1 across the tile, y is in world units
+#[derive(Clone, Copy, Debug)]
+pub struct TileContext {
+    pub id: TileId,
+    pub bounds: TileBounds,
+    pub tile_entity: Entity,
+    pub is_background: bool,
+}
+
+impl TileContext {
+    /// Width and height of the tile in world units
+    pub fn world_size(&self) -> f32 {
+        tile_world_size(self.id.z) as f32
+    }
+
+    /// Position of a lat/lon in tile-local space (0..1 inside the tile)
+    pub fn lat_lon_to_local(&self, lat: f64, lon: f64) -> Vec2 {
+        let size = tile_world_size(self.id.z);
+        ((lat_lon_to_world(lat, lon) - tile_world_origin(self.id)) / size).as_vec2()
+    }
+}
+
+/// Generators invoked once for every tile entity that gets spawned
+#[derive(Resource, Default)]
+pub struct TileGenerators {
+    pub generators: Vec<SystemId<In<TileContext>>>,
+}
+
+/// Lets embedders add procedural content (grass, decals, game objects) to every tile
+/// The generator is a regular system taking `In<TileContext>` plus any other system parameters
+pub trait TileGeneratorAppExt {
+    fn register_tile_generator<M>(&mut self, generator: impl IntoSystem<In<TileContext>, (), M> + 'static) -> &mut Self;
+}
+
+impl TileGeneratorAppExt for App {
+    fn register_tile_generator<M>(&mut self, generator: impl IntoSystem<In<TileContext>, (), M> + 'static) -> &mut Self {
+        let id = self.world_mut().register_system(generator);
+        self.world_mut()
+            .get_resource_or_insert_with(TileGenerators::default)
+            .generators
+            .push(id);
+        self
+    }
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+/// Limit on the texture memory used by spawned tiles
+/// When exceeded, the least recently visible tiles are despawned until usage fits again
+#[derive(Resource)]
+pub struct TileMemoryBudget {
+    pub budget_bytes: usize,
+    pub used_bytes: usize,   // Texture memory of the tiles spawned right now
+    pub evicted_tiles: u64,  // Tiles despawned to stay within budget since startup
+}
+
+impl Default for TileMemoryBudget {
+    fn default() -> Self {
+        Self {
+            budget_bytes: 256 * 1024 * 1024, // ~1000 tiles of 256x256 RGBA
+            used_bytes: 0,
+            evicted_tiles: 0,
+        }
+    }
+}
+
+/// Limit on the tiles put in the atlas per frame: creating their textures and uploading
+/// them to the GPU stalls the frame when dozens finish loading at once
+/// Tiles nearest the middle of the view go first, the others wait for the next frames
+#[derive(Resource)]
+pub struct TileUploadBudget {
+    pub max_tiles: usize,     // Tiles created per frame at most
+    pub max_ms: f32,          // Milliseconds per frame spent creating tiles, checked between tiles
+    pub deferred_tiles: usize, // Tiles left waiting by the last frame
+}
+
+impl Default for TileUploadBudget {
+    fn default() -> Self {
+        Self {
+            max_tiles: 8,
+            max_ms: 4.0,
+            deferred_tiles: 0,
+        }
+    }
+}
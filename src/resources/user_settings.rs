@@ -0,0 +1,436 @@
+use bevy::prelude::*;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::osm::{DownloadLimits, DEFAULT_TILE_SERVER};
+use crate::resources::MovementSettings;
+use crate::resources::stereo::StereoMode;
+use crate::resources::http_client::{DEFAULT_USER_AGENT, PLACEHOLDER_USER_AGENT};
+use crate::overlays::routing::{DEFAULT_ROUTING_PROFILE, DEFAULT_ROUTING_SERVER};
+use crate::osm::DEFAULT_OVERPASS_SERVER;
+use crate::overlays::marker_import::MarkerColumns;
+use crate::utils::geo::AntimeridianMode;
+
+// Name of the application directory inside the platform config directory
+const APP_DIR: &str = "vibe-world";
+// Name of the settings file
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+/// Largest render distance in tiles, the number of tiles grows quadratically with it
+pub const MAX_RENDER_DISTANCE: u32 = 8;
+
+/// Range of the base camera speed in world units per second; zero or less leaves the camera stuck
+pub const MOVEMENT_SPEED_RANGE: (f32, f32) = (0.05, 500.0);
+/// Range of the look sensitivity in radians per pixel of mouse motion
+pub const LOOK_SENSITIVITY_RANGE: (f32, f32) = (0.0001, 0.05);
+
+/// Esri World Imagery, satellite and aerial photos
+pub const SATELLITE_SERVER: &str =
+    "https://server.arcgisonline.com/ArcGIS/rest/services/World_Imagery/MapServer/tile/{z}/{y}/{x}";
+
+/// Credit a provider's terms require next to its tiles, with a link to its terms or copyright page
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attribution {
+    pub text: &'static str,
+    pub link: &'static str,
+}
+
+// Credits shared by several providers
+const OSM_ATTRIBUTION: &str = "© OpenStreetMap contributors";
+const OSM_COPYRIGHT: &str = "https://www.openstreetmap.org/copyright";
+/// Esri's page with the sources of its basemaps
+pub const ESRI_ATTRIBUTIONS: &str = "https://www.esri.com/en-us/legal/terms/data-attributions";
+
+/// A tile server selectable in the settings menu
+pub struct TileProvider {
+    pub key: &'static str, // Short name for the command line (--provider)
+    pub name: &'static str,
+    pub url: &'static str, // URL template
+    pub attribution: Attribution,
+}
+
+/// Tile servers selectable in the settings menu, maps first and then photo imagery
+pub const TILE_PROVIDERS: &[TileProvider] = &[
+    TileProvider {
+        key: "osm",
+        name: "OpenStreetMap",
+        url: DEFAULT_TILE_SERVER,
+        attribution: Attribution {
+            text: OSM_ATTRIBUTION,
+            link: OSM_COPYRIGHT,
+        },
+    },
+    TileProvider {
+        key: "hot",
+        name: "OpenStreetMap HOT",
+        url: "https://a.tile.openstreetmap.fr/hot/{z}/{x}/{y}.png",
+        attribution: Attribution {
+            text: "© OpenStreetMap contributors, tiles style by Humanitarian OpenStreetMap Team hosted by OpenStreetMap France",
+            link: OSM_COPYRIGHT,
+        },
+    },
+    TileProvider {
+        key: "topo",
+        name: "OpenTopoMap",
+        url: "https://a.tile.opentopomap.org/{z}/{x}/{y}.png",
+        attribution: Attribution {
+            text: "Map data: © OpenStreetMap contributors, SRTM | Map style: © OpenTopoMap (CC-BY-SA)",
+            link: "https://opentopomap.org/about",
+        },
+    },
+    TileProvider {
+        key: "satellite",
+        name: "Esri World Imagery",
+        url: SATELLITE_SERVER,
+        attribution: Attribution {
+            text: "Tiles © Esri - Source: Esri, Maxar, Earthstar Geographics, and the GIS User Community",
+            link: ESRI_ATTRIBUTIONS,
+        },
+    },
+    TileProvider {
+        key: "sentinel",
+        name: "Sentinel-2 cloudless",
+        url: "https://tiles.maps.eox.at/wmts/1.0.0/s2cloudless-2020_3857/default/g/{z}/{y}/{x}.jpg",
+        attribution: Attribution {
+            text: "Sentinel-2 cloudless - https://s2maps.eu by EOX IT Services GmbH (Contains modified Copernicus Sentinel data 2020)",
+            link: "https://s2maps.eu",
+        },
+    },
+];
+
+/// The preset with a URL template, None for a custom server from the settings file
+pub fn tile_provider(url: &str) -> Option<&'static TileProvider> {
+    TILE_PROVIDERS.iter().find(|provider| provider.url == url)
+}
+
+/// User settings persisted in the settings file
+/// Changing this resource saves it and sends a SettingsChanged event
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct UserSettings {
+    pub tile_server: String,    // URL template of the tile server
+    pub texture_cache_mb: usize, // Memory budget of the decoded texture cache
+    pub tile_ttl_hours: u64,    // Age after which cached tiles are checked with the tile server
+    pub render_distance: u32,   // Scales how far away tiles still count as in view
+    pub fov_degrees: f32,       // Vertical field of view of the main camera
+    pub compress_tiles: bool,   // Keep tiles BC1 compressed on the GPU where it supports that, applied at startup; alpha drops to 1 bit
+    pub stereo_mode: StereoMode, // Anaglyph or side by side stereo output, or off
+    pub antimeridian: AntimeridianMode, // How dropped tracks and lines crossing ±180° are drawn
+    pub movement_speed: f32,    // Base camera speed in world units per second
+    pub look_sensitivity: f32,  // Radians per pixel of mouse motion
+    pub min_clearance: f32,     // Lowest camera height above the ground in world units
+    pub debug_mode: bool,       // Start with debug logging enabled
+    pub user_agent: String,     // Sent with every request to the tile servers
+    pub worker_threads: usize,  // Threads of the background task pool, 0 for one per CPU core, applied at startup
+    pub visible_downloads: usize,    // Tile downloads at once for tiles in view
+    pub prefetch_downloads: usize,   // ... for tiles where the camera is heading
+    pub background_downloads: usize, // ... for the low zoom context around the view
+    pub multiplayer_server: String, // ws:// or wss:// URL of the multiplayer relay, empty to stay offline
+    pub player_name: String,    // Shown to the other users above this user's avatar
+    pub metrics_file: String,   // JSON file the download and cache metrics are written to, empty for none
+    pub metrics_interval_secs: u64, // Seconds between writes of the metrics file
+    pub routing_server: String, // Base URL of the OSRM server routes are requested from
+    pub routing_profile: String, // OSRM profile, e.g. driving, cycling or foot, as the server offers them
+    pub overpass_server: String, // Overpass API endpoint OpenStreetMap data, like the roads, is queried from
+    pub marker_columns: MarkerColumns, // CSV columns imported markers are read from
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            tile_server: DEFAULT_TILE_SERVER.to_string(),
+            texture_cache_mb: 128,
+            tile_ttl_hours: 7 * 24,
+            render_distance: 3,
+            fov_degrees: 90.0,
+            compress_tiles: true,
+            stereo_mode: StereoMode::Off,
+            antimeridian: AntimeridianMode::default(),
+            movement_speed: 5.0,
+            look_sensitivity: 0.002,
+            min_clearance: MovementSettings::default().min_clearance,
+            debug_mode: false,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            worker_threads: 0,
+            visible_downloads: DownloadLimits::default().visible,
+            prefetch_downloads: DownloadLimits::default().prefetch,
+            background_downloads: DownloadLimits::default().background,
+            multiplayer_server: String::new(),
+            player_name: env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_else(|_| "Explorer".to_string()),
+            metrics_file: String::new(),
+            metrics_interval_secs: 60,
+            routing_server: DEFAULT_ROUTING_SERVER.to_string(),
+            routing_profile: DEFAULT_ROUTING_PROFILE.to_string(),
+            overpass_server: DEFAULT_OVERPASS_SERVER.to_string(),
+            marker_columns: MarkerColumns::default(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// How long a cached tile is used before it is checked with the tile server
+    pub fn tile_ttl(&self) -> Duration {
+        Duration::from_secs(self.tile_ttl_hours * 60 * 60)
+    }
+
+    /// Tile downloads allowed at once per priority class
+    pub fn download_limits(&self) -> DownloadLimits {
+        DownloadLimits {
+            visible: self.visible_downloads,
+            prefetch: self.prefetch_downloads,
+            background: self.background_downloads,
+        }
+    }
+
+    /// Load the settings file, falling back to defaults for anything missing or invalid
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+        let Some(table) = read_settings_table() else {
+            return settings;
+        };
+
+        let section = |name: &str| table.get(name).and_then(|value| value.as_table());
+
+        if let Some(tiles) = section("tiles") {
+            if let Some(url) = tiles.get("server").and_then(|v| v.as_str()) {
+                settings.tile_server = url.to_string();
+            }
+        }
+        if let Some(cache) = section("cache") {
+            if let Some(size) = cache.get("texture_cache_mb").and_then(|v| v.as_integer()) {
+                settings.texture_cache_mb = size.max(0) as usize;
+            }
+            if let Some(hours) = cache.get("tile_ttl_hours").and_then(|v| v.as_integer()) {
+                settings.tile_ttl_hours = hours.max(0) as u64;
+            }
+        }
+        if let Some(graphics) = section("graphics") {
+            if let Some(fov) = graphics.get("fov_degrees").and_then(as_f32).filter(|fov| fov.is_finite()) {
+                settings.fov_degrees = fov.clamp(10.0, 170.0);
+            }
+            if let Some(distance) = graphics.get("render_distance").and_then(|v| v.as_integer()) {
+                settings.render_distance = distance.clamp(1, MAX_RENDER_DISTANCE as i64) as u32;
+            }
+            if let Some(compress) = graphics.get("compress_tiles").and_then(|v| v.as_bool()) {
+                settings.compress_tiles = compress;
+            }
+            if let Some(mode) = graphics.get("stereo_mode").and_then(|v| v.as_str()).and_then(StereoMode::from_key) {
+                settings.stereo_mode = mode;
+            }
+        }
+        if let Some(movement) = section("movement") {
+            if let Some(speed) = movement.get("speed").and_then(as_f32).filter(|speed| speed.is_finite()) {
+                settings.movement_speed = speed.clamp(MOVEMENT_SPEED_RANGE.0, MOVEMENT_SPEED_RANGE.1);
+            }
+            let sensitivity = movement.get("look_sensitivity").and_then(as_f32).filter(|sensitivity| sensitivity.is_finite());
+            if let Some(sensitivity) = sensitivity {
+                settings.look_sensitivity = sensitivity.clamp(LOOK_SENSITIVITY_RANGE.0, LOOK_SENSITIVITY_RANGE.1);
+            }
+            if let Some(clearance) = movement.get("min_clearance").and_then(as_f32) {
+                settings.min_clearance = clearance.max(0.0);
+            }
+        }
+        if let Some(debug) = section("debug") {
+            if let Some(enabled) = debug.get("enabled").and_then(|v| v.as_bool()) {
+                settings.debug_mode = enabled;
+            }
+        }
+        if let Some(network) = section("network") {
+            let user_agent = network.get("user_agent").and_then(|v| v.as_str()).filter(|agent| *agent != PLACEHOLDER_USER_AGENT);
+            if let Some(user_agent) = user_agent {
+                settings.user_agent = user_agent.to_string();
+            }
+            if let Some(threads) = network.get("worker_threads").and_then(|v| v.as_integer()) {
+                settings.worker_threads = threads.max(0) as usize;
+            }
+            // At least one download per class, or its tiles would never load
+            let downloads = |key: &str| network.get(key).and_then(|v| v.as_integer()).map(|n| n.max(1) as usize);
+            if let Some(count) = downloads("visible_downloads") {
+                settings.visible_downloads = count;
+            }
+            if let Some(count) = downloads("prefetch_downloads") {
+                settings.prefetch_downloads = count;
+            }
+            if let Some(count) = downloads("background_downloads") {
+                settings.background_downloads = count;
+            }
+        }
+        if let Some(multiplayer) = section("multiplayer") {
+            if let Some(server) = multiplayer.get("server").and_then(|v| v.as_str()) {
+                settings.multiplayer_server = server.to_string();
+            }
+            if let Some(name) = multiplayer.get("name").and_then(|v| v.as_str()) {
+                settings.player_name = name.to_string();
+            }
+        }
+        if let Some(metrics) = section("metrics") {
+            if let Some(file) = metrics.get("file").and_then(|v| v.as_str()) {
+                settings.metrics_file = file.to_string();
+            }
+            if let Some(seconds) = metrics.get("interval_seconds").and_then(|v| v.as_integer()) {
+                settings.metrics_interval_secs = seconds.max(1) as u64;
+            }
+        }
+        if let Some(routing) = section("routing") {
+            if let Some(server) = routing.get("server").and_then(|v| v.as_str()) {
+                settings.routing_server = server.to_string();
+            }
+            if let Some(profile) = routing.get("profile").and_then(|v| v.as_str()) {
+                settings.routing_profile = profile.to_string();
+            }
+        }
+        if let Some(overpass) = section("overpass") {
+            if let Some(server) = overpass.get("server").and_then(|v| v.as_str()) {
+                settings.overpass_server = server.to_string();
+            }
+        }
+        if let Some(overlays) = section("overlays") {
+            let mode = overlays.get("antimeridian").and_then(|v| v.as_str()).and_then(AntimeridianMode::from_key);
+            if let Some(mode) = mode {
+                settings.antimeridian = mode;
+            }
+        }
+        if let Some(markers) = section("markers") {
+            if let Some(column) = markers.get("lat_column").and_then(|v| v.as_str()) {
+                settings.marker_columns.lat = column.to_string();
+            }
+            if let Some(column) = markers.get("lon_column").and_then(|v| v.as_str()) {
+                settings.marker_columns.lon = column.to_string();
+            }
+            if let Some(column) = markers.get("name_column").and_then(|v| v.as_str()) {
+                settings.marker_columns.name = column.to_string();
+            }
+        }
+
+        settings
+    }
+
+    /// Write the settings to the settings file, keeping other tables (like keybindings) in it
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let mut table = read_settings_table().unwrap_or_default();
+
+        let mut tiles = toml::Table::new();
+        tiles.insert("server".into(), self.tile_server.clone().into());
+        table.insert("tiles".into(), tiles.into());
+
+        let mut cache = toml::Table::new();
+        cache.insert("texture_cache_mb".into(), (self.texture_cache_mb as i64).into());
+        cache.insert("tile_ttl_hours".into(), (self.tile_ttl_hours as i64).into());
+        table.insert("cache".into(), cache.into());
+
+        let mut graphics = toml::Table::new();
+        graphics.insert("fov_degrees".into(), (self.fov_degrees as f64).into());
+        graphics.insert("render_distance".into(), (self.render_distance as i64).into());
+        graphics.insert("compress_tiles".into(), self.compress_tiles.into());
+        graphics.insert("stereo_mode".into(), self.stereo_mode.key().into());
+        table.insert("graphics".into(), graphics.into());
+
+        let mut movement = toml::Table::new();
+        movement.insert("speed".into(), (self.movement_speed as f64).into());
+        movement.insert("look_sensitivity".into(), (self.look_sensitivity as f64).into());
+        movement.insert("min_clearance".into(), (self.min_clearance as f64).into());
+        table.insert("movement".into(), movement.into());
+
+        let mut debug = toml::Table::new();
+        debug.insert("enabled".into(), self.debug_mode.into());
+        table.insert("debug".into(), debug.into());
+
+        let mut network = toml::Table::new();
+        network.insert("user_agent".into(), self.user_agent.clone().into());
+        network.insert("worker_threads".into(), (self.worker_threads as i64).into());
+        network.insert("visible_downloads".into(), (self.visible_downloads as i64).into());
+        network.insert("prefetch_downloads".into(), (self.prefetch_downloads as i64).into());
+        network.insert("background_downloads".into(), (self.background_downloads as i64).into());
+        table.insert("network".into(), network.into());
+
+        let mut multiplayer = toml::Table::new();
+        multiplayer.insert("server".into(), self.multiplayer_server.clone().into());
+        multiplayer.insert("name".into(), self.player_name.clone().into());
+        table.insert("multiplayer".into(), multiplayer.into());
+
+        let mut metrics = toml::Table::new();
+        metrics.insert("file".into(), self.metrics_file.clone().into());
+        metrics.insert("interval_seconds".into(), (self.metrics_interval_secs as i64).into());
+        table.insert("metrics".into(), metrics.into());
+
+        let mut routing = toml::Table::new();
+        routing.insert("server".into(), self.routing_server.clone().into());
+        routing.insert("profile".into(), self.routing_profile.clone().into());
+        table.insert("routing".into(), routing.into());
+
+        let mut overpass = toml::Table::new();
+        overpass.insert("server".into(), self.overpass_server.clone().into());
+        table.insert("overpass".into(), overpass.into());
+
+        let mut overlays = toml::Table::new();
+        overlays.insert("antimeridian".into(), self.antimeridian.key().into());
+        table.insert("overlays".into(), overlays.into());
+
+        let mut markers = toml::Table::new();
+        markers.insert("lat_column".into(), self.marker_columns.lat.clone().into());
+        markers.insert("lon_column".into(), self.marker_columns.lon.clone().into());
+        markers.insert("name_column".into(), self.marker_columns.name.clone().into());
+        table.insert("markers".into(), markers.into());
+
+        write_settings_table(&table)
+    }
+}
+
+// TOML numbers may be written with or without a decimal point
+fn as_f32(value: &toml::Value) -> Option<f32> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|i| i as f64))
+        .map(|f| f as f32)
+}
+
+/// Location of the settings file in the platform config directory
+/// (~/.config on Linux, Application Support on macOS, AppData on Windows)
+pub fn settings_path() -> PathBuf {
+    let config_dir = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    // Without a home directory, fall back to the working directory
+    match config_dir {
+        Some(dir) => dir.join(APP_DIR).join(SETTINGS_FILE_NAME),
+        None => PathBuf::from(SETTINGS_FILE_NAME),
+    }
+}
+
+/// Read the whole settings file as a TOML table
+pub fn read_settings_table() -> Option<toml::Table> {
+    let path = settings_path();
+    let contents = fs::read_to_string(&path).ok()?;
+    match contents.parse::<toml::Table>() {
+        Ok(table) => Some(table),
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Replace the settings file with the given table
+/// Written to a temporary file first and renamed over it, so a crash or a full disk never
+/// leaves a truncated file losing all settings and keybindings
+pub fn write_settings_table(table: &toml::Table) -> Result<(), anyhow::Error> {
+    let path = settings_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("toml.tmp");
+    let written = fs::write(&temp_path, toml::to_string_pretty(table)?).and_then(|_| fs::rename(&temp_path, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    Ok(written?)
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+/// Water surfaces on tiles that are mostly water
+#[derive(Resource)]
+pub struct WaterSettings {
+    pub enabled: bool,
+    pub min_coverage: f32,  // Fraction of a tile that has to be water before it gets a water surface
+    pub mask_size: u32,     // Resolution of the water mask per tile
+    pub color: Color,       // Water seen straight down in daylight
+    pub reflectivity: f32,  // How much of the sky the water reflects at grazing angles
+    pub wave_length_m: f32,
+    pub wave_speed_m: f32,  // Meters per second
+    pub wave_strength: f32, // Steepness of the waves
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_coverage: 0.5,
+            mask_size: 64,
+            color: Color::srgb(0.05, 0.2, 0.3),
+            reflectivity: 0.9,
+            wave_length_m: 40.0,
+            wave_speed_m: 4.0,
+            wave_strength: 0.15,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+use crate::overlays::weather::RadarFrame;
+
+/// Where the radar frame list is put once RainViewer answered
+pub type RadarFramesReply = Arc<Mutex<Option<Result<Vec<RadarFrame>, String>>>>;
+
+/// The animated precipitation radar: an imagery layer per frame, one of them visible at a time
+/// Hidden layers keep their imagery in the texture cache, so after the first loop the frames
+/// are recomposited from memory instead of downloaded again
+#[derive(Resource, Default)]
+pub struct WeatherRadar {
+    pub active: bool,
+    pub frames: Vec<(RadarFrame, u32)>, // Oldest first, with the id of the frame's imagery layer
+    pub current: usize,                 // Index of the visible frame
+    pub playing: bool,
+    pub shown_secs: f32,                // How long the current frame has been visible
+    pub error: Option<String>,          // Why the frame list couldn't be loaded
+    pub pending: Option<RadarFramesReply>,
+}
+
+impl WeatherRadar {
+    /// Index of the frame `step` frames after the current one, wrapping around at both ends
+    pub fn step(&self, step: i32) -> usize {
+        let count = self.frames.len().max(1) as i32;
+        (self.current as i32 + step).rem_euclid(count) as usize
+    }
+}
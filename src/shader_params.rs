@@ -0,0 +1,68 @@
+// Uniform parameters of the custom materials, laid out for WGSL by the ShaderType derive
+use bevy::prelude::*;
+use bevy::render::render_resource::ShaderType;
+use crate::utils::geo::world_size;
+
+/// Color filters applied to every tile
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct TileFilter {
+    pub brightness: f32,
+    pub grayscale: f32,     // 0 = full color, 1 = grayscale
+    pub night: f32,         // 0 = day, 1 = inverted night colors
+    pub fade_duration: f32, // Seconds a new tile takes to fade in, 0 disables fading
+}
+
+impl Default for TileFilter {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            grayscale: 0.0,
+            night: 0.0,
+            fade_duration: 0.4,
+        }
+    }
+}
+
+/// Curves tiles onto a globe touching the flat map below the camera
+/// The vertex shader moves every vertex from the flat map towards the globe by blend;
+/// the map's size and origin are also used to fade the flat map out towards the poles
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct TileGlobe {
+    pub tangent: Vec4,   // Latitude and longitude (radians), world X and Z of the point touching the map
+    pub radius: f32,     // Globe radius in world units
+    pub blend: f32,      // 0 = flat map, 1 = globe
+    pub world_size: f32, // Width of the flat map in world units
+    pub origin: Vec2,    // World X/Z at the render origin, see utils::geo::world_origin
+}
+
+impl Default for TileGlobe {
+    fn default() -> Self {
+        Self {
+            tangent: Vec4::ZERO,
+            radius: 1.0,
+            blend: 0.0,
+            world_size: world_size() as f32,
+            origin: Vec2::ZERO,
+        }
+    }
+}
+
+/// Colors of the sky and where the sun is, in linear color
+#[derive(ShaderType, Clone, Copy, Debug, Default)]
+pub struct SkyParams {
+    pub zenith: Vec4,
+    pub horizon: Vec4,      // Also the fog color, so the map fades into the sky
+    pub sun_direction: Vec4, // xyz towards the sun, w is the strength of the sun disk and glow
+    pub sun_color: Vec4,
+}
+
+/// Look of the water and the sky it reflects, in linear color
+#[derive(ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WaterParams {
+    pub color: Vec4,         // Water seen straight down, w is the reflectivity
+    pub zenith: Vec4,
+    pub horizon: Vec4,
+    pub sun_direction: Vec4, // xyz towards the sun, w is the strength of the sun glitter
+    pub sun_color: Vec4,
+    pub waves: Vec4,         // Wave length and speed in world units (per second), normal strength
+}
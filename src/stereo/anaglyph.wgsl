@@ -0,0 +1,17 @@
+// Half-color red-cyan anaglyph: the red channel is the left eye's brightness, green and blue
+// are the right eye's colors. Taking the brightness for red keeps red roofs and green fields
+// from showing in one eye only, which full color anaglyphs do
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var left_texture: texture_2d<f32>;
+@group(1) @binding(1) var left_sampler: sampler;
+@group(1) @binding(2) var right_texture: texture_2d<f32>;
+@group(1) @binding(3) var right_sampler: sampler;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let left = textureSample(left_texture, left_sampler, in.uv).rgb;
+    let right = textureSample(right_texture, right_sampler, in.uv).rgb;
+    let luma = dot(left, vec3<f32>(0.299, 0.587, 0.114));
+    return vec4<f32>(luma, right.g, right.b, 1.0);
+}
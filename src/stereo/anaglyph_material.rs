@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+/// Shader combining the two eyes' images into a red-cyan anaglyph
+pub const ANAGLYPH_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x71c4_0e9b_d563_4a2f_8b17_c2e6_5f90_3ad8);
+
+/// Material of the node covering the window in anaglyph mode, with the images the eye cameras draw into
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct AnaglyphMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub left: Handle<Image>,
+    #[texture(2)]
+    #[sampler(3)]
+    pub right: Handle<Image>,
+}
+
+impl UiMaterial for AnaglyphMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ANAGLYPH_SHADER_HANDLE.into()
+    }
+}
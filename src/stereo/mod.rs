@@ -0,0 +1,3 @@
+mod anaglyph_material;
+
+pub use anaglyph_material::{AnaglyphMaterial, ANAGLYPH_SHADER_HANDLE};
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy::pbr::{DistanceFog, FogFalloff};
+use crate::atmosphere::{SkyMaterial, SkyParams};
+use crate::components::{MainCamera, SkyDome};
+use crate::resources::{AtmosphereSettings, EnvironmentSettings, OSMData, Sun};
+use crate::utils::geo::{horizon_distance_m, tile_world_size, GeoTransform};
+
+// Color of the sun disk high in the sky; it reddens towards the horizon
+const SUN_COLOR: Color = Color::srgb(1.0, 0.95, 0.85);
+// How concentrated the sun's glow in the fog is
+const FOG_SUN_GLOW_EXPONENT: f32 = 30.0;
+
+/// Spawn the sky dome
+/// It is drawn at infinite distance, so its size only has to clear the camera's near plane
+pub fn setup_sky(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+) {
+    commands.spawn((
+        Mesh3d(meshes.add(Sphere::new(1.0).mesh().uv(32, 16))),
+        MeshMaterial3d(materials.add(SkyMaterial::default())),
+        Transform::IDENTITY,
+        SkyDome,
+        Name::new("Sky dome"),
+    ));
+}
+
+/// Color the sky for the sun's position and keep the dome around the camera
+pub fn update_sky(
+    environment: Res<EnvironmentSettings>,
+    sun: Res<Sun>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    // Only the sky dome uses the sky material
+    mut sky_query: Query<(&mut Transform, &MeshMaterial3d<SkyMaterial>), Without<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    // The disk sets with the sun and reddens close to the horizon
+    let sun_strength = ((sun.position.elevation as f32 + 1.0) / 2.0).clamp(0.0, 1.0);
+    let params = SkyParams {
+        zenith: environment.sky_color(&sun).to_linear().to_vec4(),
+        horizon: environment.horizon_color(&sun).to_linear().to_vec4(),
+        sun_direction: sun.direction().extend(sun_strength),
+        sun_color: sun_color(&environment, &sun).to_linear().to_vec4(),
+    };
+
+    for (mut transform, material) in sky_query.iter_mut() {
+        transform.translation = camera.translation;
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.params = params;
+        }
+    }
+}
+
+/// Color of the sun, reddening as it nears the horizon
+pub fn sun_color(environment: &EnvironmentSettings, sun: &Sun) -> Color {
+    SUN_COLOR.mix(&environment.twilight_sky, 1.0 - sun.daylight)
+}
+
+/// Fade the map into the horizon color with distance fog
+/// The fog hides the map where the loaded tiles end: the background tiles or the horizon
+pub fn update_distance_fog(
+    mut commands: Commands,
+    settings: Res<AtmosphereSettings>,
+    environment: Res<EnvironmentSettings>,
+    sun: Res<Sun>,
+    osm_data: Res<OSMData>,
+    mut camera_query: Query<(Entity, &Transform, Option<&mut DistanceFog>), With<MainCamera>>,
+) {
+    let Ok((camera_entity, camera, fog)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    if !settings.fog_enabled {
+        if fog.is_some() {
+            commands.entity(camera_entity).remove::<DistanceFog>();
+        }
+        return;
+    }
+
+    // Ground distance to the nearest edge of the background tiles around the camera,
+    // or to the horizon when that is closer
+    let height = camera.translation.y.max(0.0);
+    let camera_geo = GeoTransform::from_translation(camera.translation);
+    let meters_per_unit = camera_geo.position.meters_per_world_unit();
+    let horizon_distance = horizon_distance_m(camera_geo.altitude_m) / meters_per_unit;
+    let extent = tile_world_size(osm_data.background_zoom).min(horizon_distance) as f32;
+    let visibility = extent.hypot(height) * settings.visibility_factor;
+
+    let horizon = environment.horizon_color(&sun);
+    let distance_fog = DistanceFog {
+        color: horizon,
+        directional_light_color: sun_color(&environment, &sun).with_alpha(0.5 * sun.daylight),
+        directional_light_exponent: FOG_SUN_GLOW_EXPONENT,
+        falloff: FogFalloff::from_visibility_color(visibility, horizon),
+    };
+    match fog {
+        Some(mut fog) => *fog = distance_fog,
+        None => {
+            commands.entity(camera_entity).insert(distance_fog);
+        }
+    }
+}
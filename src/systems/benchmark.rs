@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use crate::events::TileSpawned;
+use crate::resources::{benchmark::benchmark_path, Benchmark, PathRecorder, PathRecorderState, TileMemoryBudget};
+
+/// Start the benchmark sweep once the warmup is over, played back like a recorded camera path
+pub fn start_benchmark(time: Res<Time>, mut benchmark: ResMut<Benchmark>, mut recorder: ResMut<PathRecorder>) {
+    if benchmark.started || time.elapsed_secs() < benchmark.warmup {
+        return;
+    }
+
+    recorder.path = benchmark_path();
+    recorder.state = PathRecorderState::Playing { elapsed: 0.0, frame: None };
+    benchmark.started = true;
+    info!("Benchmark: flying a camera sweep of {:.0}s", recorder.path.duration());
+}
+
+/// Measure every frame of the sweep, and print the report and quit once it's over
+pub fn measure_benchmark(
+    time: Res<Time>,
+    mut benchmark: ResMut<Benchmark>,
+    recorder: Res<PathRecorder>,
+    budget: Res<TileMemoryBudget>,
+    mut spawned: EventReader<TileSpawned>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let spawned_tiles = spawned.read().count();
+    if !benchmark.started {
+        return;
+    }
+
+    if matches!(recorder.state, PathRecorderState::Playing { .. }) {
+        benchmark.frame_times.push(time.delta_secs());
+        benchmark.tiles_spawned += spawned_tiles;
+        benchmark.peak_texture_budget_bytes = benchmark.peak_texture_budget_bytes.max(budget.used_bytes);
+        return;
+    }
+
+    println!("{}", benchmark.report());
+    exit.send(AppExit::Success);
+}
@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use crate::components::{LayerItem, MainCamera};
+use crate::overlays::buildings::{building_mesh, building_query};
+use crate::resources::{Buildings, HttpClient, LayerManager, Overpass, TaskRuntime, UserSettings};
+use crate::resources::layer_manager::BUILDINGS_LAYER;
+use crate::resources::overpass::OverpassTile;
+use crate::utils::geo::{tile_world_origin, GeoPos};
+
+// Zoom level of the tiles buildings are queried by, like the roads
+const BUILDING_ZOOM: u32 = 16;
+// Buildings are loaded below this camera height, higher up they are too small to tell from the tiles
+const MAX_CAMERA_HEIGHT: f32 = 0.1;
+// Tiles around the camera's tile whose buildings are loaded, and beyond which they are dropped
+const LOAD_RANGE: i64 = 1;
+const KEEP_RANGE: i64 = 2;
+const MAX_LOADING: usize = 2;
+// Buildings stand on the focus tiles, which reach 0.005
+const BUILDING_ELEVATION: f32 = 0.005;
+const BUILDING_COLOR: Color = Color::srgb(0.85, 0.82, 0.76);
+
+/// Ask the Overpass server for the buildings of the tiles around the camera while it is near the
+/// ground, and drop the buildings the camera left behind
+pub fn request_building_tiles(
+    mut commands: Commands,
+    mut buildings: ResMut<Buildings>,
+    mut overpass: ResMut<Overpass>,
+    (manager, settings): (Res<LayerManager>, Res<UserSettings>),
+    (task_runtime, http_client): (Res<TaskRuntime>, Res<HttpClient>),
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = GeoPos::from_translation(camera.translation).tile(BUILDING_ZOOM);
+    for block in buildings.tiles.drop_beyond(center, KEEP_RANGE) {
+        commands.entity(block).despawn();
+    }
+
+    let shown = manager.get(BUILDINGS_LAYER).is_none_or(|layer| layer.visible);
+    if !shown || camera.translation.y > MAX_CAMERA_HEIGHT {
+        return;
+    }
+    let client = overpass.client(&settings, &http_client);
+    buildings.tiles.request_around(center, LOAD_RANGE, MAX_LOADING, building_query, (&client, &task_runtime));
+}
+
+/// Extrude the buildings of the tiles whose query was answered, one mesh per tile
+pub fn receive_building_tiles(
+    mut commands: Commands,
+    mut buildings: ResMut<Buildings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (id, data) in buildings.tiles.take_answers() {
+        let mut blocks = Vec::new();
+        if let Some(mesh) = building_mesh(&data, id) {
+            let material = buildings.material.get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: BUILDING_COLOR,
+                    perceptual_roughness: 0.9,
+                    cull_mode: None,
+                    ..default()
+                })
+            });
+            let origin = tile_world_origin(id).as_vec2();
+            blocks.push(
+                commands
+                    .spawn((
+                        Mesh3d(meshes.add(mesh)),
+                        MeshMaterial3d(material.clone()),
+                        Transform::from_xyz(origin.x, BUILDING_ELEVATION, origin.y),
+                        LayerItem { layer: BUILDINGS_LAYER, elevation: BUILDING_ELEVATION },
+                        Name::new(format!("Buildings {}/{}/{}", id.z, id.x, id.y)),
+                    ))
+                    .id(),
+            );
+        }
+        debug!("Buildings of tile {}/{}/{}: {} ways", id.z, id.x, id.y, data.ways.len());
+        buildings.tiles.tiles.insert(id, OverpassTile::Loaded(blocks));
+    }
+}
@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use std::sync::atomic::Ordering;
+use parking_lot::Mutex;
+use crate::osm::{cache_dir, scan_cache, read_index, write_index, maintain_entry, MaintenanceOutcome};
+use crate::resources::{CacheMaintenance, CameraFlight, CameraMotion, DebugSettings, MaintenanceInbox, OSMData, TaskRuntime};
+use crate::debug_log;
+
+/// Prune, verify and recompress the disk cache while the tile pipeline is idle
+/// Work is done in small batches on the Tokio runtime, one batch at a time, and waits while offline
+pub fn run_cache_maintenance(
+    mut maintenance: ResMut<CacheMaintenance>,
+    task_runtime: Res<TaskRuntime>,
+    osm_data: Res<OSMData>,
+    camera_motion: Res<CameraMotion>,
+    camera_flight: Res<CameraFlight>,
+    debug_settings: Res<DebugSettings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    // The pipeline counts as busy while the camera moves or tiles are waiting to spawn
+    let pipeline_busy = camera_motion.velocity != Vec3::ZERO
+        || camera_flight.active.is_some()
+        || !osm_data.pending_tiles.lock().is_empty();
+    if pipeline_busy {
+        maintenance.idle_since = now;
+    }
+
+    // Offline the disk cache is all there is, so nothing gets pruned until the network is back
+    if osm_data.network.is_offline() {
+        maintenance.idle_since = now;
+    }
+
+    // Wait for the running batch to finish
+    if maintenance.busy.load(Ordering::Acquire) {
+        return;
+    }
+
+    collect_results(&mut maintenance);
+
+    // Finish the pass once every entry has been visited
+    if maintenance.pass_running && maintenance.queue.is_empty() {
+        maintenance.pass_running = false;
+        maintenance.last_pass = Some(now);
+
+        debug_log!(debug_settings, "Cache maintenance pass done: {} tiles indexed, {} pruned, {} recompressed, {} corrupt",
+                  maintenance.index.len(), maintenance.pruned, maintenance.recompressed, maintenance.corrupt);
+
+        let index = std::mem::take(&mut maintenance.index);
+        spawn_batch(&maintenance, &task_runtime, move |_| {
+            if let Err(e) = write_index(&index) {
+                warn!("Failed to write cache index: {}", e);
+            }
+        });
+        return;
+    }
+
+    if now - maintenance.idle_since < maintenance.idle_delay {
+        return;
+    }
+
+    if !maintenance.pass_running {
+        let due = maintenance
+            .last_pass
+            .is_none_or(|last| now - last >= maintenance.pass_interval);
+        if !due {
+            return;
+        }
+
+        // Start a new pass by listing the cache and loading the previous index
+        maintenance.pass_running = true;
+        maintenance.pruned = 0;
+        maintenance.recompressed = 0;
+        maintenance.corrupt = 0;
+        spawn_batch(&maintenance, &task_runtime, |inbox| {
+            let files = scan_cache();
+            let index = read_index();
+            inbox.lock().scanned = Some((files, index));
+        });
+        return;
+    }
+
+    // Handle the next batch of entries
+    let maintenance = maintenance.as_mut();
+    let batch_size = maintenance.batch_size.min(maintenance.queue.len());
+    let previous_index = &maintenance.previous_index;
+    let batch: Vec<_> = maintenance
+        .queue
+        .drain(..batch_size)
+        .map(|path| {
+            let previous = previous_index.get(&path).cloned();
+            (path, previous)
+        })
+        .collect();
+    let policy = maintenance.policy.clone();
+
+    spawn_batch(maintenance, &task_runtime, move |inbox| {
+        let outcomes: Vec<_> = batch
+            .iter()
+            .map(|(path, previous)| maintain_entry(cache_dir(), path, previous.as_ref(), &policy))
+            .collect();
+        inbox.lock().outcomes.extend(outcomes);
+    });
+}
+
+// Apply results posted by finished background batches
+fn collect_results(maintenance: &mut CacheMaintenance) {
+    let inbox = maintenance.inbox.clone();
+    let mut inbox = inbox.lock();
+
+    if let Some((files, previous_index)) = inbox.scanned.take() {
+        maintenance.queue = files.into();
+        maintenance.previous_index = previous_index;
+        maintenance.index.clear();
+    }
+
+    for outcome in inbox.outcomes.drain(..) {
+        match outcome {
+            MaintenanceOutcome::Kept(path, entry) => {
+                maintenance.index.insert(path, entry);
+            }
+            MaintenanceOutcome::Recompressed(path, entry) => {
+                maintenance.recompressed += 1;
+                maintenance.index.insert(path, entry);
+            }
+            MaintenanceOutcome::Pruned => maintenance.pruned += 1,
+            MaintenanceOutcome::Corrupt(path) => {
+                warn!("Removed corrupt cached tile {}", path.display());
+                maintenance.corrupt += 1;
+            }
+        }
+    }
+}
+
+// Run blocking cache work on the Tokio blocking pool, flagging the maintenance task busy meanwhile
+fn spawn_batch(
+    maintenance: &CacheMaintenance,
+    task_runtime: &TaskRuntime,
+    work: impl FnOnce(&Mutex<MaintenanceInbox>) + Send + 'static,
+) {
+    let busy = maintenance.busy.clone();
+    let inbox = maintenance.inbox.clone();
+
+    busy.store(true, Ordering::Release);
+    task_runtime.spawn_blocking(move || {
+        work(&inbox);
+        busy.store(false, Ordering::Release);
+    });
+}
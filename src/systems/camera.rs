@@ -1,6 +1,30 @@
 use bevy::prelude::*;
 use bevy::input::mouse::MouseMotion;
-use crate::resources::MouseLookState;
+use bevy::window::{FileDragAndDrop, PrimaryWindow};
+use crate::resources::{
+    MouseLookState, CameraFlight, CameraMode, Flight, CameraMotion, GroundHeight, MovementSettings, InputMap, WalkSettings,
+    WalkState,
+};
+use crate::resources::input_map::{
+    MOVE_FORWARD, MOVE_BACKWARD, MOVE_LEFT, MOVE_RIGHT, MOVE_UP, MOVE_DOWN, BOOST, TOGGLE_CAMERA_MODE, TOGGLE_WALK_MODE,
+};
+use crate::events::FlyTo;
+use crate::components::MainCamera;
+use crate::resources::launch_options::camera_height_for_zoom;
+use crate::utils::deep_link::find_deep_link;
+use crate::utils::geo::{world_origin, world_size, GeoPos};
+
+// Lowest camera height used to derive the orbit distance, keeps the focus in front of the camera
+const MIN_ORBIT_HEIGHT: f32 = 0.1;
+// Pan distance per pixel of mouse motion, relative to the distance to the focus
+const PAN_SENSITIVITY: f32 = 0.0015;
+// Seconds to fly to a dropped link
+const LINK_FLIGHT_DURATION: f32 = 2.0;
+// Seconds to fly down to the ground when walk mode starts high above it
+const LANDING_FLIGHT_DURATION: f32 = 2.0;
+// Dropped files read for links: plain text snippets and the shortcuts browsers and
+// desktops make of links dragged out of them
+const LINK_FILE_EXTENSIONS: [&str; 4] = ["txt", "url", "webloc", "desktop"];
 
 /// System to capture mouse movement for camera look
 pub fn mouse_look_system(
@@ -15,37 +39,17 @@ pub fn mouse_look_system(
 pub fn camera_movement(
     time: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    (movement_settings, ground): (Res<MovementSettings>, Res<GroundHeight>),
     mut mouse_look_state: ResMut<MouseLookState>,
-    mut query: Query<&mut Transform, With<Camera3d>>,
+    mut camera_motion: ResMut<CameraMotion>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
 ) {
-    // Movement settings
-    let base_movement_speed = 5.0;
-    let boost_multiplier = 3.0; // Speed multiplier when shift is pressed
-    let look_sensitivity = 0.002;
     let delta = time.delta_secs();
 
-    // Apply mouse motion to update camera rotation (looking around)
-    if !mouse_look_state.mouse_motion.is_nan() && mouse_look_state.mouse_motion.length_squared() > 0.0 {
-        // Update pitch and yaw based on mouse motion
-        mouse_look_state.yaw -= mouse_look_state.mouse_motion.x * look_sensitivity;
-        mouse_look_state.pitch -= mouse_look_state.mouse_motion.y * look_sensitivity;
-
-        // Clamp pitch to prevent the camera from flipping
-        mouse_look_state.pitch = mouse_look_state.pitch.clamp(-1.5, 1.5);
-
-        // Reset motion for next frame
-        mouse_look_state.mouse_motion = Vec2::ZERO;
-    }
-
     // Apply rotation to camera transform
     let mut transform = query.single_mut();
-
-    // Create rotation quaternion from pitch and yaw
-    let yaw_rotation = Quat::from_rotation_y(mouse_look_state.yaw);
-    let pitch_rotation = Quat::from_rotation_x(mouse_look_state.pitch);
-
-    // Combine rotations and set the camera's rotation
-    transform.rotation = yaw_rotation * pitch_rotation;
+    apply_mouse_look(&mut mouse_look_state, movement_settings.look_sensitivity, &mut transform);
 
     // Calculate movement direction based on camera orientation
     let forward = *transform.forward();
@@ -53,24 +57,24 @@ pub fn camera_movement(
     let mut movement = Vec3::ZERO;
 
     // Apply movement based on key input (relative to camera direction)
-    if keyboard_input.pressed(KeyCode::KeyW) {
+    if input_map.pressed(&keyboard_input, MOVE_FORWARD) {
         movement += forward;
     }
-    if keyboard_input.pressed(KeyCode::KeyS) {
+    if input_map.pressed(&keyboard_input, MOVE_BACKWARD) {
         movement -= forward;
     }
-    if keyboard_input.pressed(KeyCode::KeyA) {
+    if input_map.pressed(&keyboard_input, MOVE_LEFT) {
         movement -= right;
     }
-    if keyboard_input.pressed(KeyCode::KeyD) {
+    if input_map.pressed(&keyboard_input, MOVE_RIGHT) {
         movement += right;
     }
 
     // Apply up/down movement
-    if keyboard_input.pressed(KeyCode::Space) {
+    if input_map.pressed(&keyboard_input, MOVE_UP) {
         movement.y += 1.0;
     }
-    if keyboard_input.pressed(KeyCode::ControlLeft) { // Changed from ShiftLeft to ControlLeft for down movement
+    if input_map.pressed(&keyboard_input, MOVE_DOWN) {
         movement.y -= 1.0;
     }
 
@@ -80,36 +84,352 @@ pub fn camera_movement(
     }
 
     // Calculate altitude-based speed multiplier
-    // As camera height increases, speed increases proportionally
-    let height = transform.translation.y.max(1.0); // Ensure minimum height of 1.0
-    let altitude_factor = {
-        if height <= 5.0 {
-            1.0 // Base speed at low heights
-        } else if height <= 20.0 {
-            // Linear scaling for medium heights: 1.0 - 4.0x
-            1.0 + (height - 5.0) / 5.0
-        } else if height <= 50.0 {
-            // Medium-high altitudes: 4.0 - 8.0x
-            4.0 + (height - 20.0) / 10.0
-        } else if height <= 100.0 {
-            // High altitudes: 8.0 - 15.0x
-            8.0 + (height - 50.0) / 10.0
-        } else {
-            // Very high altitudes: 15.0x and above
-            15.0 + (height - 100.0) / 20.0
-        }
-    };
+    // Continuous curve so speed doesn't jump when crossing height thresholds
+    let altitude_factor = movement_settings.altitude_factor(transform.translation.y);
 
-    // Check if boost mode (Shift) is active
-    let boost = if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        boost_multiplier
+    // Check if boost mode is active
+    let boost = if input_map.pressed(&keyboard_input, BOOST) {
+        movement_settings.boost_multiplier
     } else {
         1.0
     };
 
     // Calculate final movement speed using both altitude and boost factors
-    let movement_speed = base_movement_speed * altitude_factor * boost;
+    let movement_speed = movement_settings.base_speed * altitude_factor * boost;
 
     // Apply movement to position
-    transform.translation += movement * movement_speed * delta;
-} 
\ No newline at end of file
+    camera_motion.velocity = movement * movement_speed;
+    transform.translation += camera_motion.velocity * delta;
+
+    // Keep clear of the ground, easing back up instead of stopping dead at the clearance
+    let height_above_ground = transform.translation.y - ground.under_camera;
+    transform.translation.y += movement_settings.clearance_push(height_above_ground, delta);
+}
+
+// Turn the camera by the mouse motion gathered since the last frame (first-person looking around)
+fn apply_mouse_look(mouse_look_state: &mut MouseLookState, look_sensitivity: f32, transform: &mut Transform) {
+    if !mouse_look_state.mouse_motion.is_nan() && mouse_look_state.mouse_motion.length_squared() > 0.0 {
+        // Update pitch and yaw based on mouse motion
+        mouse_look_state.yaw -= mouse_look_state.mouse_motion.x * look_sensitivity;
+        mouse_look_state.pitch -= mouse_look_state.mouse_motion.y * look_sensitivity;
+
+        // Clamp pitch to prevent the camera from flipping
+        mouse_look_state.pitch = mouse_look_state.pitch.clamp(-1.5, 1.5);
+
+        // Reset motion for next frame
+        mouse_look_state.mouse_motion = Vec2::ZERO;
+    }
+
+    // Create rotation quaternion from pitch and yaw, and combine them into the camera's rotation
+    let yaw_rotation = Quat::from_rotation_y(mouse_look_state.yaw);
+    let pitch_rotation = Quat::from_rotation_x(mouse_look_state.pitch);
+    transform.rotation = yaw_rotation * pitch_rotation;
+}
+
+/// Street level camera: walk over the ground at eye height with the movement keys, looking
+/// around with the mouse; jump with the up key, and fall whenever the ground is further down
+pub fn walk_camera(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    (walk_settings, movement_settings, ground): (Res<WalkSettings>, Res<MovementSettings>, Res<GroundHeight>),
+    (mut mouse_look_state, mut walk_state, mut camera_motion): (ResMut<MouseLookState>, ResMut<WalkState>, ResMut<CameraMotion>),
+    camera_flight: Res<CameraFlight>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+    apply_mouse_look(&mut mouse_look_state, movement_settings.look_sensitivity, &mut transform);
+
+    // Landing after entering walk mode from high above
+    if camera_flight.active.is_some() {
+        walk_state.vertical_speed = 0.0;
+        return;
+    }
+
+    let delta = time.delta_secs();
+    let units_per_meter = (1.0 / GeoPos::from_translation(transform.translation).meters_per_world_unit()) as f32;
+
+    // Walk along the ground in the direction the camera faces, whatever the pitch
+    let yaw_rotation = Quat::from_rotation_y(mouse_look_state.yaw);
+    let (forward, right) = (yaw_rotation * Vec3::NEG_Z, yaw_rotation * Vec3::X);
+    let mut movement = Vec3::ZERO;
+    for (action, direction) in [(MOVE_FORWARD, forward), (MOVE_BACKWARD, -forward), (MOVE_LEFT, -right), (MOVE_RIGHT, right)] {
+        if input_map.pressed(&keyboard_input, action) {
+            movement += direction;
+        }
+    }
+    let speed = walk_settings.walk_speed_m
+        * if input_map.pressed(&keyboard_input, BOOST) { walk_settings.run_multiplier } else { 1.0 };
+    camera_motion.velocity = movement.normalize_or_zero() * speed * units_per_meter;
+    transform.translation += camera_motion.velocity * delta;
+
+    // Jump off the ground, fall towards it otherwise
+    let eye_level = ground.under_camera + walk_settings.eye_height_m * units_per_meter;
+    let on_ground = transform.translation.y <= eye_level;
+    if on_ground && input_map.just_pressed(&keyboard_input, MOVE_UP) {
+        walk_state.vertical_speed = walk_settings.jump_speed_m;
+    } else {
+        walk_state.vertical_speed -= walk_settings.gravity_m * delta;
+    }
+    transform.translation.y += walk_state.vertical_speed * units_per_meter * delta;
+    if transform.translation.y <= eye_level {
+        transform.translation.y = eye_level;
+        walk_state.vertical_speed = 0.0;
+    }
+}
+
+/// Fly to the place a dropped text file or link shortcut points at, see find_deep_link
+/// Links with a zoom level also change the height to show tiles of that level
+pub fn fly_to_dropped_link(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut fly_to_events: EventWriter<FlyTo>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+
+        let is_link_file = path_buf
+            .extension()
+            .is_some_and(|ext| LINK_FILE_EXTENSIONS.iter().any(|link_ext| ext.eq_ignore_ascii_case(link_ext)));
+        if !is_link_file {
+            continue;
+        }
+
+        let link = match std::fs::read_to_string(path_buf) {
+            Ok(text) => find_deep_link(&text),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path_buf.display(), e);
+                continue;
+            }
+        };
+        let (Some(link), Ok(camera_transform)) = (link, camera_query.get_single()) else {
+            continue;
+        };
+
+        info!("Flying to {:.5}, {:.5} from {}", link.position.lat, link.position.lon, path_buf.display());
+        let target = link.position.world();
+        let height = link.zoom.map_or(camera_transform.translation.y, camera_height_for_zoom);
+        fly_to_events.send(FlyTo {
+            target: Vec3::new(target.x as f32, height, target.y as f32),
+            duration: LINK_FLIGHT_DURATION,
+        });
+    }
+}
+
+/// Start a camera flight for every FlyTo request (the latest one wins)
+pub fn start_camera_flight(
+    mut fly_to_events: EventReader<FlyTo>,
+    mut camera_flight: ResMut<CameraFlight>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Some(fly_to) = fly_to_events.read().last().copied() else {
+        return;
+    };
+
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+
+    if fly_to.duration <= 0.0 {
+        // Teleport
+        transform.translation = fly_to.target;
+        camera_flight.active = None;
+        return;
+    }
+
+    camera_flight.active = Some(Flight {
+        from: transform.translation,
+        to: fly_to.target,
+        duration: fly_to.duration,
+        elapsed: 0.0,
+    });
+}
+
+/// Move the camera along the active flight path
+pub fn update_camera_flight(
+    time: Res<Time>,
+    mut camera_flight: ResMut<CameraFlight>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Some(flight) = camera_flight.active.as_mut() else {
+        return;
+    };
+
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+
+    flight.elapsed += time.delta_secs();
+    let t = (flight.elapsed / flight.duration).min(1.0);
+
+    // Smoothstep easing so the camera accelerates and decelerates gently
+    let eased = t * t * (3.0 - 2.0 * t);
+    transform.translation = flight.from.lerp(flight.to, eased);
+
+    if t >= 1.0 {
+        camera_flight.active = None;
+    }
+}
+
+/// Switch between the first-person and orbit camera (V by default)
+pub fn toggle_camera_mode(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    movement_settings: Res<MovementSettings>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_CAMERA_MODE) {
+        return;
+    }
+
+    *camera_mode = match *camera_mode {
+        CameraMode::Fly | CameraMode::Walk => CameraMode::Orbit,
+        CameraMode::Orbit => CameraMode::Fly,
+    };
+
+    // Motion gathered in the other mode must not make the view jump
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    if *camera_mode == CameraMode::Orbit {
+        // The orbit focus is where the view centre meets the ground, so look down at it
+        mouse_look_state.pitch = mouse_look_state
+            .pitch
+            .clamp(movement_settings.orbit_min_pitch, movement_settings.orbit_max_pitch);
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        grab_cursor_for(*camera_mode, &mut window);
+    }
+
+    info!("Camera mode: {:?}", *camera_mode);
+}
+
+/// Switch the street level walk mode on or off (F by default), back to flying when it ends
+/// From high above the camera flies down to the ground first, lower it falls down
+pub fn toggle_walk_mode(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    (walk_settings, ground): (Res<WalkSettings>, Res<GroundHeight>),
+    (mut camera_mode, mut walk_state): (ResMut<CameraMode>, ResMut<WalkState>),
+    mut fly_to_events: EventWriter<FlyTo>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_WALK_MODE) {
+        return;
+    }
+
+    *camera_mode = match *camera_mode {
+        CameraMode::Walk => CameraMode::Fly,
+        CameraMode::Fly | CameraMode::Orbit => CameraMode::Walk,
+    };
+    walk_state.vertical_speed = 0.0;
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        grab_cursor_for(*camera_mode, &mut window);
+    }
+
+    if let (CameraMode::Walk, Ok(transform)) = (*camera_mode, camera_query.get_single()) {
+        let meters_per_unit = GeoPos::from_translation(transform.translation).meters_per_world_unit() as f32;
+        if (transform.translation.y - ground.under_camera) * meters_per_unit > walk_settings.landing_height_m {
+            let eye_level = ground.under_camera + walk_settings.eye_height_m / meters_per_unit;
+            fly_to_events.send(FlyTo {
+                target: transform.translation.with_y(eye_level),
+                duration: LANDING_FLIGHT_DURATION,
+            });
+        }
+    }
+
+    info!("Camera mode: {:?}", *camera_mode);
+}
+
+// Orbit mode drags with a visible cursor, the first-person modes look around with a locked one
+fn grab_cursor_for(camera_mode: CameraMode, window: &mut Window) {
+    let orbit = camera_mode == CameraMode::Orbit;
+    window.cursor_options.visible = orbit;
+    window.cursor_options.grab_mode = if orbit {
+        bevy::window::CursorGrabMode::None
+    } else {
+        bevy::window::CursorGrabMode::Locked
+    };
+}
+
+/// Map-style camera: right-drag orbits around the ground point in the middle of the screen,
+/// middle-drag pans over the ground
+/// The focus is derived from the camera every frame so scroll zoom and flights keep working
+pub fn orbit_camera(
+    time: Res<Time>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    movement_settings: Res<MovementSettings>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut camera_motion: ResMut<CameraMotion>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let motion: Vec2 = mouse_motion_events.read().map(|event| event.delta).sum();
+
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+
+    // Ground point under the view centre; the pitch is clamped below the horizon so it always exists
+    let height = transform.translation.y.max(MIN_ORBIT_HEIGHT);
+    let pitch = mouse_look_state
+        .pitch
+        .clamp(movement_settings.orbit_min_pitch, movement_settings.orbit_max_pitch);
+    let distance = height / -pitch.sin();
+    let focus = transform.translation + *transform.forward() * distance;
+
+    let start = transform.translation;
+
+    if mouse_input.pressed(MouseButton::Right) {
+        mouse_look_state.yaw -= motion.x * movement_settings.look_sensitivity;
+        mouse_look_state.pitch -= motion.y * movement_settings.look_sensitivity;
+    }
+    mouse_look_state.pitch = mouse_look_state
+        .pitch
+        .clamp(movement_settings.orbit_min_pitch, movement_settings.orbit_max_pitch);
+
+    let mut focus = Vec3::new(focus.x, 0.0, focus.z);
+    if mouse_input.pressed(MouseButton::Middle) {
+        // Drag the ground along with the cursor, faster the further away it is
+        let yaw_rotation = Quat::from_rotation_y(mouse_look_state.yaw);
+        let right = yaw_rotation * Vec3::X;
+        let forward = yaw_rotation * Vec3::NEG_Z;
+        let scale = distance * PAN_SENSITIVITY;
+        focus += (-right * motion.x + forward * motion.y) * scale;
+    }
+
+    // Place the camera on the orbit sphere around the focus
+    transform.rotation = Quat::from_rotation_y(mouse_look_state.yaw) * Quat::from_rotation_x(mouse_look_state.pitch);
+    transform.translation = focus - *transform.forward() * distance;
+
+    // Report the movement so tiles get prefetched in the direction of the pan
+    let delta = time.delta_secs();
+    camera_motion.velocity = if delta > 0.0 {
+        (transform.translation - start) / delta
+    } else {
+        Vec3::ZERO
+    };
+}
+
+/// Keep the camera over the map: past the Web Mercator latitude limit there are no tiles,
+/// and the projection runs off to infinity at the poles
+pub fn clamp_camera_latitude(mut query: Query<&mut Transform, With<MainCamera>>) {
+    let Ok(mut transform) = query.get_single_mut() else {
+        return;
+    };
+
+    // The map's north and south edges are world Z 0 and world_size, relative to the render origin
+    let origin = world_origin();
+    let z = transform.translation.z.clamp(-origin.y as f32, (world_size() - origin.y) as f32);
+    if z != transform.translation.z {
+        transform.translation.z = z;
+    }
+}
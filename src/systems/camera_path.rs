@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot};
+use std::fs;
+use crate::components::MainCamera;
+use crate::resources::{CameraFlight, CameraKeyframe, CameraMotion, CameraPath, InputMap, MouseLookState, PathRecorder, PathRecorderState};
+use crate::resources::input_map::{PLAY_CAMERA_PATH, RECORD_CAMERA_PATH, RENDER_CAMERA_PATH};
+use crate::utils::geo::world_origin;
+
+/// Start and stop recording (F5), playback (F6) and playback with frame dumping (F7)
+/// A finished recording is saved to the recorder's file, playback loads from it when it exists
+pub fn handle_path_recorder_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut recorder: ResMut<PathRecorder>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    if input_map.just_pressed(&keyboard_input, RECORD_CAMERA_PATH) {
+        if let PathRecorderState::Recording { elapsed, .. } = recorder.state {
+            push_keyframe(&mut recorder.path, elapsed, camera);
+            recorder.state = PathRecorderState::Idle;
+            // Saved relative to the world, not the render origin the recording was made around
+            let mut path = recorder.path.clone();
+            path.shift(render_origin());
+            match path.save(&recorder.file) {
+                Ok(()) => info!("Saved camera path of {:.1}s to {}", elapsed, recorder.file.display()),
+                Err(e) => warn!("Failed to save camera path to {}: {}", recorder.file.display(), e),
+            }
+        } else {
+            recorder.path = CameraPath::default();
+            push_keyframe(&mut recorder.path, 0.0, camera);
+            recorder.state = PathRecorderState::Recording { elapsed: 0.0, since_keyframe: 0.0 };
+            info!("Recording camera path");
+        }
+        return;
+    }
+
+    let play = input_map.just_pressed(&keyboard_input, PLAY_CAMERA_PATH);
+    let render = input_map.just_pressed(&keyboard_input, RENDER_CAMERA_PATH);
+    if !play && !render {
+        return;
+    }
+
+    if matches!(recorder.state, PathRecorderState::Playing { .. }) {
+        recorder.state = PathRecorderState::Idle;
+        info!("Stopped camera path playback");
+        return;
+    }
+
+    if recorder.file.exists() {
+        match CameraPath::load(&recorder.file) {
+            Ok(mut path) => {
+                path.shift(-render_origin());
+                recorder.path = path;
+            }
+            Err(e) => warn!("Failed to load camera path from {}: {}", recorder.file.display(), e),
+        }
+    }
+    if recorder.path.keyframes.is_empty() {
+        warn!("No camera path to play, record one first");
+        return;
+    }
+
+    let frame = if render {
+        if let Err(e) = fs::create_dir_all(&recorder.frames_dir) {
+            warn!("Failed to create frame directory {}: {}", recorder.frames_dir.display(), e);
+            return;
+        }
+        info!("Rendering camera path to {}", recorder.frames_dir.display());
+        Some(0)
+    } else {
+        info!("Playing camera path of {:.1}s", recorder.path.duration());
+        None
+    };
+    recorder.state = PathRecorderState::Playing { elapsed: 0.0, frame };
+}
+
+// The render origin as a translation, to move paths between render and world coordinates
+fn render_origin() -> Vec3 {
+    let origin = world_origin();
+    Vec3::new(origin.x as f32, 0.0, origin.y as f32)
+}
+
+fn push_keyframe(path: &mut CameraPath, time: f32, camera: &Transform) {
+    path.keyframes.push(CameraKeyframe {
+        time,
+        translation: camera.translation,
+        rotation: camera.rotation,
+    });
+}
+
+/// Add a keyframe with the camera pose every keyframe interval while recording
+pub fn record_camera_path(
+    time: Res<Time>,
+    mut recorder: ResMut<PathRecorder>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let PathRecorderState::Recording { mut elapsed, mut since_keyframe } = recorder.state else {
+        return;
+    };
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    elapsed += time.delta_secs();
+    since_keyframe += time.delta_secs();
+    if since_keyframe >= recorder.keyframe_interval {
+        since_keyframe = 0.0;
+        push_keyframe(&mut recorder.path, elapsed, camera);
+    }
+    recorder.state = PathRecorderState::Recording { elapsed, since_keyframe };
+}
+
+/// Move the camera along the path during playback, overriding manual movement and flights
+/// While dumping frames, every frame advances the path by one frame of the video and is saved
+pub fn play_camera_path(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut recorder: ResMut<PathRecorder>,
+    (mut mouse_look_state, mut camera_motion, mut camera_flight): (ResMut<MouseLookState>, ResMut<CameraMotion>, ResMut<CameraFlight>),
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let PathRecorderState::Playing { elapsed, frame } = recorder.state else {
+        return;
+    };
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+    let Some(pose) = recorder.path.sample(elapsed) else {
+        recorder.state = PathRecorderState::Idle;
+        return;
+    };
+
+    let step = match frame {
+        Some(_) => 1.0 / recorder.frame_rate,
+        None => time.delta_secs(),
+    };
+    // Tiles are prefetched in the direction the camera moves
+    camera_motion.velocity = if step > 0.0 { (pose.translation - transform.translation) / step } else { Vec3::ZERO };
+    camera_flight.active = None;
+    *transform = pose;
+
+    // Continue looking in the direction the path left off when the user takes over again
+    let (yaw, pitch, _) = pose.rotation.to_euler(EulerRot::YXZ);
+    mouse_look_state.yaw = yaw;
+    mouse_look_state.pitch = pitch;
+    mouse_look_state.mouse_motion = Vec2::ZERO;
+
+    if let Some(frame) = frame {
+        let file = recorder.frames_dir.join(format!("frame_{:05}.png", frame));
+        commands.spawn(Screenshot::primary_window()).observe(save_to_disk(file));
+    }
+
+    if elapsed >= recorder.path.duration() {
+        recorder.state = PathRecorderState::Idle;
+        info!("Finished camera path playback");
+    } else {
+        recorder.state = PathRecorderState::Playing {
+            elapsed: elapsed + step,
+            frame: frame.map(|frame| frame + 1),
+        };
+    }
+}
@@ -0,0 +1,283 @@
+use bevy::prelude::*;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use crate::components::{ChatInputText, ChatLogText, MainCamera, MapPingLabel, MapPingMarker};
+use crate::net::{chat_message, ConnectionStatus};
+use crate::resources::{Chat, ChatDraft, CursorPick, InputMap, Multiplayer, RebindState};
+use crate::resources::input_map::{OPEN_CHAT, PING_LOCATION};
+use crate::utils::geo::{GeoPos, GeoTransform};
+
+// Marker size as a fraction of its distance to the camera, like the avatars
+const PING_SCALE: f32 = 0.05;
+
+/// Sets up the chat overlay (bottom left, above the status bar)
+pub fn setup_chat(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            left: Val::Px(10.0),
+            max_width: Val::Px(480.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+                Visibility::Hidden,
+                ChatLogText,
+            ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.95, 0.6)),
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                Visibility::Hidden,
+                ChatInputText,
+            ));
+        });
+}
+
+/// Start typing a chat message, pinned to the ground under the cursor for a map ping
+pub fn open_chat(
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    rebind_state: Res<RebindState>,
+    cursor_pick: Res<CursorPick>,
+    mut chat: ResMut<Chat>,
+) {
+    // Keys pressed while waiting for a rebind belong to the rebind
+    if chat.draft.is_some() || rebind_state.waiting.is_some() {
+        return;
+    }
+
+    let location = if input_map.just_pressed(&keyboard_input, OPEN_CHAT) {
+        None
+    } else if input_map.just_pressed(&keyboard_input, PING_LOCATION) {
+        let Some(lat_lon) = cursor_pick.lat_lon else {
+            return;
+        };
+        Some(lat_lon)
+    } else {
+        return;
+    };
+
+    chat.draft = Some(ChatDraft { text: String::new(), location });
+    keyboard_input.reset_all();
+}
+
+/// Type into the chat message: Enter sends it, Escape throws it away
+/// While typing, the keys are taken from the other systems so the camera doesn't fly off
+pub fn type_chat(
+    mut key_events: EventReader<KeyboardInput>,
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    multiplayer: Res<Multiplayer>,
+    mut chat: ResMut<Chat>,
+) {
+    // Keys pressed before the chat opened are not part of the message
+    let events: Vec<&KeyboardInput> = key_events.read().collect();
+    let Some(mut draft) = chat.draft.take() else {
+        return;
+    };
+    keyboard_input.reset_all();
+
+    for event in events.into_iter().filter(|event| event.state.is_pressed()) {
+        match &event.logical_key {
+            Key::Enter => {
+                send_chat(&mut chat, &multiplayer, &draft, time.elapsed_secs());
+                return;
+            }
+            Key::Escape => return,
+            Key::Backspace => {
+                draft.text.pop();
+            }
+            Key::Space => draft.text.push(' '),
+            Key::Character(text) => {
+                draft.text.extend(text.chars().filter(|c| !c.is_control()));
+            }
+            _ => {}
+        }
+    }
+
+    let max_length = chat.max_length;
+    if let Some((cut, _)) = draft.text.char_indices().nth(max_length) {
+        draft.text.truncate(cut);
+    }
+    chat.draft = Some(draft);
+}
+
+// Send the typed message, and show it right away rather than waiting for the server's echo
+fn send_chat(chat: &mut Chat, multiplayer: &Multiplayer, draft: &ChatDraft, now: f32) {
+    let text = draft.text.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    if multiplayer.status != ConnectionStatus::Connected {
+        chat.push("", "Not connected to a multiplayer server", None, now);
+        return;
+    }
+
+    multiplayer.send(chat_message(text, draft.location));
+    chat.push(&multiplayer.name, text, draft.location, now);
+}
+
+// The log and input texts are both Text, so each query excludes the other
+type ChatLogFilter = (With<ChatLogText>, Without<ChatInputText>);
+type ChatInputFilter = (With<ChatInputText>, Without<ChatLogText>);
+
+/// Show the recent messages and the one being typed
+pub fn update_chat_overlay(
+    time: Res<Time>,
+    chat: Res<Chat>,
+    mut log_query: Query<(&mut Text, &mut Visibility), ChatLogFilter>,
+    mut input_query: Query<(&mut Text, &mut Visibility), ChatInputFilter>,
+) {
+    if let Ok((mut text, mut visibility)) = log_query.get_single_mut() {
+        let lines: Vec<String> = chat
+            .visible(time.elapsed_secs())
+            .map(|line| match line.name.as_str() {
+                "" => line.text.clone(),
+                name => format!("{}: {}", name, line.text),
+            })
+            .collect();
+        *visibility = if lines.is_empty() { Visibility::Hidden } else { Visibility::Inherited };
+        text.0 = lines.join("\n");
+    }
+
+    if let Ok((mut text, mut visibility)) = input_query.get_single_mut() {
+        match &chat.draft {
+            Some(draft) => {
+                let prompt = if draft.location.is_some() { "Ping" } else { "Say" };
+                text.0 = format!("{}: {}_", prompt, draft.text);
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// Spawn a marker for each new map ping
+pub fn spawn_map_pings(
+    mut commands: Commands,
+    mut chat: ResMut<Chat>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for ping in chat.pings.iter_mut().filter(|ping| ping.marker.is_none()) {
+        ping.marker = Some(spawn_ping_marker(&mut commands, &mut meshes, &mut materials, &ping.label));
+    }
+}
+
+/// Keep the map ping markers on their location with their message above them, until they expire
+pub fn update_map_pings(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut chat: ResMut<Chat>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut marker_query: Query<&mut Transform, With<MapPingMarker>>,
+    mut label_query: Query<(&mut Node, &mut Visibility), With<MapPingLabel>>,
+) {
+    let now = time.elapsed_secs();
+    chat.pings.retain(|ping| {
+        let alive = now < ping.expires;
+        if let (false, Some((marker, label))) = (alive, ping.marker) {
+            commands.entity(marker).despawn_recursive();
+            commands.entity(label).despawn_recursive();
+        }
+        alive
+    });
+
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    for ping in &chat.pings {
+        let Some((marker, label)) = ping.marker else {
+            continue;
+        };
+
+        let position = GeoTransform::new(GeoPos::new(ping.lat, ping.lon), 0.0).translation();
+        let scale = position.distance(camera_transform.translation()) * PING_SCALE;
+        // Bob up and down so the marker catches the eye
+        let bob = (ping.expires - now).sin().abs() * 0.3 * scale;
+
+        if let Ok(mut transform) = marker_query.get_mut(marker) {
+            *transform = Transform::from_translation(position + Vec3::Y * bob).with_scale(Vec3::splat(scale));
+        }
+
+        // The label hides while its marker is behind the camera
+        if let Ok((mut node, mut visibility)) = label_query.get_mut(label) {
+            match camera.world_to_viewport(camera_transform, position + Vec3::Y * (bob + 1.3 * scale)) {
+                Ok(screen) => {
+                    node.left = Val::Px(screen.x);
+                    node.top = Val::Px(screen.y - 20.0);
+                    *visibility = Visibility::Inherited;
+                }
+                Err(_) => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
+
+// A map ping is a pin standing on the ground, with its message on screen above it
+fn spawn_ping_marker(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    label: &str,
+) -> (Entity, Entity) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 0.3, 0.2),
+        unlit: true,
+        ..default()
+    });
+
+    let marker = commands
+        .spawn((
+            // The pole stands on the ground rather than halfway into it
+            Mesh3d(meshes.add(Cylinder::new(0.04, 1.0).mesh().build().translated_by(Vec3::Y * 0.5))),
+            MeshMaterial3d(material.clone()),
+            Transform::default(),
+            Visibility::default(),
+            MapPingMarker,
+            Name::new("Map ping"),
+        ))
+        .with_child((
+            Mesh3d(meshes.add(Sphere::new(0.15))),
+            MeshMaterial3d(material),
+            Transform::from_xyz(0.0, 1.0, 0.0),
+        ))
+        .id();
+
+    let label = commands
+        .spawn((
+            Text::new(label),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.95, 0.6)),
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::axes(Val::Px(4.0), Val::Px(1.0)),
+                max_width: Val::Px(300.0),
+                ..default()
+            },
+            Visibility::Hidden,
+            MapPingLabel,
+        ))
+        .id();
+
+    (marker, label)
+}
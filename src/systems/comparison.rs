@@ -0,0 +1,365 @@
+use bevy::prelude::*;
+use bevy::pbr::DistanceFog;
+use bevy::render::camera::Viewport;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{ComparisonCamera, ComparisonDivider, ComparisonUiCamera, MainCamera, TileCoords};
+use crate::events::OriginShifted;
+use crate::osm::{atlas_layer_data, composite_layers, load_tile_image, tile_layer_image, DownloadClass, TileId, TileMaterial};
+use crate::resources::{
+    GlobeSettings, HttpClient, ImageryLayers, InputMap, LaunchOptions, OSMData, ProviderComparison, StereoView, TaskRuntime,
+    TileAtlas, TileUploadBudget, BASE_LAYER_ID,
+};
+use crate::resources::comparison::{
+    default_comparison_provider, split_viewports, ComparisonTile, COMPARISON_LIFT, COMPARISON_RENDER_LAYER,
+};
+use crate::resources::input_map::TOGGLE_COMPARISON;
+use crate::resources::stereo::StereoMode;
+use crate::systems::tiles::{rebuild_batches, BatchTileData};
+
+// Width of the divider's grip, centred on the divider
+const DIVIDER_WIDTH: f32 = 8.0;
+
+type MainCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Camera, &'static Transform, Option<&'static Projection>, Option<&'static PerspectiveProjection>),
+    With<MainCamera>,
+>;
+type ComparisonCamerasFilter = Or<(With<ComparisonCamera>, With<ComparisonUiCamera>)>;
+type MainViewQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, Option<&'static PerspectiveProjection>, Option<&'static DistanceFog>), With<MainCamera>>;
+type ComparisonViewQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Transform, Option<&'static mut PerspectiveProjection>),
+    (With<ComparisonCamera>, Without<MainCamera>),
+>;
+
+/// Start comparing with the provider given by --compare
+pub fn start_comparison(launch: Res<LaunchOptions>, mut comparison: ResMut<ProviderComparison>) {
+    if let Some(provider) = &launch.compare {
+        comparison.provider = Some(provider.clone());
+        comparison.active = true;
+    }
+}
+
+/// Spawn the divider between the two sides, hidden until the comparison is switched on
+pub fn setup_comparison_divider(mut commands: Commands) {
+    commands.spawn((
+        Button,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(0.0),
+            width: Val::Px(DIVIDER_WIDTH),
+            height: Val::Percent(100.0),
+            margin: UiRect::left(Val::Px(-DIVIDER_WIDTH / 2.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.8)),
+        Visibility::Hidden,
+        ComparisonDivider,
+    ));
+}
+
+/// Switch the provider comparison on or off (B by default)
+/// Without a provider from the command line the base map is compared with the photo imagery
+pub fn toggle_comparison(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    layers: Res<ImageryLayers>,
+    mut comparison: ResMut<ProviderComparison>,
+    stereo: Res<StereoView>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_COMPARISON) {
+        return;
+    }
+    // The stereo eyes take the window already
+    if stereo.mode != StereoMode::Off {
+        info!("Provider comparison isn't available in stereo mode");
+        return;
+    }
+
+    comparison.active = !comparison.active;
+    if comparison.active && comparison.provider.is_none() {
+        let base = layers.layers.iter().find(|layer| layer.id == BASE_LAYER_ID);
+        let provider = default_comparison_provider(base.map_or("", |layer| layer.url.as_str()));
+        comparison.provider = Some(provider.to_string());
+    }
+    info!("Provider comparison: {}", if comparison.active { "ON" } else { "OFF" });
+}
+
+/// Spawn the comparison's cameras when it is switched on; when it is switched off remove
+/// them along with the comparison imagery, and give the main camera the whole window again
+pub fn manage_comparison_cameras(
+    mut commands: Commands,
+    mut comparison: ResMut<ProviderComparison>,
+    mut main_camera: MainCameraQuery,
+    comparison_cameras: Query<Entity, ComparisonCamerasFilter>,
+) {
+    let Ok((mut camera, transform, projection, perspective)) = main_camera.get_single_mut() else {
+        return;
+    };
+    let spawned = !comparison_cameras.is_empty();
+
+    if comparison.active && !spawned {
+        // Drawn after the main camera, into its own part of the window
+        let mut comparison_camera = commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: 1,
+                hdr: camera.hdr,
+                ..default()
+            },
+            *transform,
+            RenderLayers::from_layers(&[0, COMPARISON_RENDER_LAYER]),
+            ComparisonCamera,
+        ));
+        if let Some(projection) = projection {
+            comparison_camera.insert(projection.clone());
+        }
+        if let Some(perspective) = perspective {
+            comparison_camera.insert(perspective.clone());
+        }
+
+        commands.spawn((
+            Camera2d,
+            Camera {
+                order: 2,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            IsDefaultUiCamera,
+            ComparisonUiCamera,
+        ));
+    } else if !comparison.active && spawned {
+        for entity in &comparison_cameras {
+            commands.entity(entity).despawn_recursive();
+        }
+        for &batch in comparison.atlas.batches.values() {
+            commands.entity(batch).despawn_recursive();
+        }
+        // Tasks still loading imagery find their tiles no longer requested
+        comparison.atlas = TileAtlas::default();
+        comparison.requested.clear();
+        comparison.loaded.lock().clear();
+        camera.viewport = None;
+    }
+}
+
+/// Load the comparison provider's imagery for the map's tiles, sharing the visible tiles'
+/// download slots; tiles are loaded through the same sources and cache as the map's
+pub fn load_comparison_tiles(
+    mut comparison: ResMut<ProviderComparison>,
+    mut osm_data: ResMut<OSMData>,
+    task_runtime: Res<TaskRuntime>,
+    http_client: Res<HttpClient>,
+    tile_query: Query<(Entity, &TileCoords)>,
+) {
+    let Some(url) = comparison.provider.clone().filter(|_| comparison.active) else {
+        return;
+    };
+
+    let osm_data = osm_data.as_mut();
+    for (entity, coords) in &tile_query {
+        if comparison.requested.contains(&entity) {
+            continue;
+        }
+        if !osm_data.downloads.has_free_slot(DownloadClass::Visible) {
+            break;
+        }
+        comparison.requested.insert(entity);
+
+        let source = osm_data.sources.get(&url, &http_client.client, &osm_data.throttle, &osm_data.network, osm_data.tile_ttl);
+        let slot = osm_data.downloads.acquire(DownloadClass::Visible);
+        let loaded = comparison.loaded.clone();
+        let id = TileId::new(coords.x, coords.y, coords.zoom);
+        task_runtime.spawn(async move {
+            let image = load_tile_image(source.as_ref(), id).await.ok().map(|loaded| loaded.image);
+            loaded.lock().push(ComparisonTile { entity, id, image });
+            drop(slot);
+        });
+    }
+}
+
+/// Put loaded comparison imagery in the comparison atlas, within the tile upload budget,
+/// and release the imagery of despawned tiles
+pub fn apply_comparison_tiles(
+    mut comparison: ResMut<ProviderComparison>,
+    (mut images, mut materials): (ResMut<Assets<Image>>, ResMut<Assets<TileMaterial>>),
+    upload_budget: Res<TileUploadBudget>,
+    tile_query: Query<&TileCoords>,
+    mut removed_tiles: RemovedComponents<TileCoords>,
+) {
+    for entity in removed_tiles.read() {
+        comparison.atlas.free(entity);
+        comparison.requested.remove(&entity);
+    }
+    if !comparison.active {
+        return;
+    }
+
+    let ready: Vec<ComparisonTile> = {
+        let mut loaded = comparison.loaded.lock();
+        let count = loaded.len().min(upload_budget.max_tiles);
+        loaded.drain(..count).collect()
+    };
+    let comparison = comparison.as_mut();
+    for tile in ready {
+        // Despawned or reused while loading, or loaded before the comparison was switched off
+        let current = tile_query
+            .get(tile.entity)
+            .is_ok_and(|coords| TileId::new(coords.x, coords.y, coords.zoom) == tile.id);
+        if !current || !comparison.requested.contains(&tile.entity) || comparison.atlas.slots.contains_key(&tile.entity) {
+            continue;
+        }
+        let Some(image) = tile.image else {
+            continue;
+        };
+
+        // Blended onto paper like the map's own tiles
+        let layer_image = tile_layer_image(image);
+        let tile_image = composite_layers(&[(&layer_image, 1.0)]);
+        let data = atlas_layer_data(&tile_image.data, false);
+        if comparison.atlas.allocate(tile.entity, &data, &mut images, &mut materials).is_none() {
+            warn!("Comparison tile image doesn't fit the tile atlas");
+        }
+    }
+}
+
+/// Rebuild the comparison tiles' batch meshes, with the map's color filters and globe curvature
+pub fn rebuild_comparison_batches(
+    mut commands: Commands,
+    mut comparison: ResMut<ProviderComparison>,
+    atlas: Res<TileAtlas>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<TileMaterial>>),
+    tile_query: Query<BatchTileData>,
+    globe_settings: Res<GlobeSettings>,
+    mut shifted: EventReader<OriginShifted>,
+) {
+    let comparison = comparison.as_mut();
+    if atlas.is_changed() {
+        let was_curved = comparison.atlas.globe.blend > 0.0;
+        comparison.atlas.set_filter(atlas.filter, &mut materials);
+        comparison.atlas.set_globe(atlas.globe, &mut materials);
+
+        // Curved vertices leave the flat bounds of the batch meshes, see update_tile_globe
+        let curved = atlas.globe.blend > 0.0;
+        if curved != was_curved {
+            for &batch in comparison.atlas.batches.values() {
+                if curved {
+                    commands.entity(batch).insert(NoFrustumCulling);
+                } else {
+                    commands.entity(batch).remove::<NoFrustumCulling>();
+                }
+            }
+        }
+    }
+    // Batch vertices are in the old coordinates after a rebase, like the map's
+    if shifted.read().count() > 0 {
+        comparison.atlas.dirty = true;
+    }
+
+    let render_layers = RenderLayers::layer(COMPARISON_RENDER_LAYER);
+    let overlay = Some((COMPARISON_LIFT, &render_layers));
+    rebuild_batches(&mut commands, &mut comparison.atlas, &mut meshes, &tile_query, &globe_settings, overlay);
+}
+
+/// Drag the divider between the two sides; the cursor has to be released to grab it
+pub fn drag_comparison_divider(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut comparison: ResMut<ProviderComparison>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    divider_query: Query<&Interaction, (Changed<Interaction>, With<ComparisonDivider>)>,
+) {
+    if divider_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        comparison.dragging = true;
+    }
+    if !comparison.dragging {
+        return;
+    }
+    if !mouse_input.pressed(MouseButton::Left) {
+        comparison.dragging = false;
+        return;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if window.cursor_options.grab_mode != CursorGrabMode::None {
+        return;
+    }
+    if let Some(cursor) = window.cursor_position() {
+        comparison.set_divider(cursor.x / window.width());
+    }
+}
+
+/// Split the window between the two cameras at the divider, and place the divider
+pub fn update_comparison_viewports(
+    comparison: Res<ProviderComparison>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut main_camera: Query<&mut Camera, (With<MainCamera>, Without<ComparisonCamera>)>,
+    mut comparison_camera: Query<&mut Camera, With<ComparisonCamera>>,
+    mut divider_query: Query<(&mut Node, &mut Visibility), With<ComparisonDivider>>,
+) {
+    if let Ok((mut node, mut visibility)) = divider_query.get_single_mut() {
+        node.left = Val::Percent(comparison.divider * 100.0);
+        visibility.set_if_neq(if comparison.active { Visibility::Inherited } else { Visibility::Hidden });
+    }
+
+    let (Ok(window), Ok(mut main), Ok(mut right)) =
+        (windows.get_single(), main_camera.get_single_mut(), comparison_camera.get_single_mut())
+    else {
+        return;
+    };
+    let size = window.physical_size();
+    if size.x == 0 || size.y == 0 {
+        return;
+    }
+
+    let (left_viewport, right_viewport) = split_viewports(size, comparison.divider);
+    set_viewport(&mut main, left_viewport);
+    set_viewport(&mut right, right_viewport);
+}
+
+/// Give a camera a viewport, only touching the camera when it moves, as a changed camera
+/// recomputes its projection
+pub fn set_viewport(camera: &mut Mut<Camera>, (position, size): (UVec2, UVec2)) {
+    let unchanged = camera
+        .viewport
+        .as_ref()
+        .is_some_and(|viewport| viewport.physical_position == position && viewport.physical_size == size);
+    if !unchanged {
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
+
+/// Give the comparison camera the main camera's view: its transform, field of view and fog
+/// Runs after all camera movement and before transforms are propagated
+pub fn sync_comparison_camera(
+    mut commands: Commands,
+    main_camera: MainViewQuery,
+    mut comparison_camera: ComparisonViewQuery,
+) {
+    let (Ok((transform, perspective, fog)), Ok((entity, mut comparison_transform, comparison_perspective))) =
+        (main_camera.get_single(), comparison_camera.get_single_mut())
+    else {
+        return;
+    };
+
+    *comparison_transform = *transform;
+    // The aspect ratio is each camera's own, from its viewport
+    if let (Some(source), Some(mut target)) = (perspective, comparison_perspective) {
+        if (target.fov, target.near, target.far) != (source.fov, source.near, source.far) {
+            (target.fov, target.near, target.far) = (source.fov, source.near, source.far);
+        }
+    }
+    match fog {
+        Some(fog) => commands.entity(entity).insert(fog.clone()),
+        None => commands.entity(entity).remove::<DistanceFog>(),
+    };
+}
@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use std::sync::atomic::Ordering;
+use crate::components::{FallbackTile, LowResTile, TileCoords};
+use crate::resources::{Connectivity, HttpClient, OSMData, TaskRuntime};
+
+type MissingImageryFilter = Or<(With<FallbackTile>, With<LowResTile>)>;
+
+/// While offline, request the URL that couldn't connect again every few seconds
+pub fn probe_network(
+    time: Res<Time>,
+    mut connectivity: ResMut<Connectivity>,
+    task_runtime: Res<TaskRuntime>,
+    http_client: Res<HttpClient>,
+    osm_data: Res<OSMData>,
+) {
+    if !osm_data.network.is_offline() {
+        connectivity.probe_timer.reset();
+        return;
+    }
+    if !connectivity.probe_timer.tick(time.delta()).just_finished() || connectivity.probing.load(Ordering::Acquire) {
+        return;
+    }
+
+    connectivity.probing.store(true, Ordering::Release);
+    let probing = connectivity.probing.clone();
+    let client = http_client.client.clone();
+    let network = osm_data.network.clone();
+    task_runtime.spawn(async move {
+        network.probe(&client).await;
+        probing.store(false, Ordering::Release);
+    });
+}
+
+/// Notice the loader going offline and the network coming back
+/// Once back, the backoff of the failed requests is forgotten and tiles shown as fallbacks or
+/// low-res ancestors are requested again
+pub fn resume_after_reconnect(
+    mut connectivity: ResMut<Connectivity>,
+    mut osm_data: ResMut<OSMData>,
+    tile_query: Query<&TileCoords, MissingImageryFilter>,
+) {
+    let offline = osm_data.network.is_offline();
+    if offline == connectivity.offline {
+        return;
+    }
+    connectivity.offline = offline;
+    if offline {
+        warn!("Tile servers are unreachable, showing cached tiles only");
+        return;
+    }
+
+    info!("Network is back, loading the tiles that are missing");
+    osm_data.throttle.lock().reset();
+    osm_data.retries.entries.clear();
+    // Clearing the loaded marker makes the tile pipeline request the tile again while it is in view
+    for coords in &tile_query {
+        let key = (coords.x, coords.y, coords.zoom);
+        osm_data.loaded_tiles.remove(&key);
+        osm_data.loaded_background_tiles.remove(&key);
+    }
+}
@@ -1,14 +1,16 @@
 use bevy::prelude::*;
-use crate::resources::{OSMData, DebugSettings};
-use crate::components::{TileCoords};
-use crate::utils::coordinate_conversion::world_to_tile_coords;
+use crate::resources::{OSMData, DebugSettings, InputMap, TileMemoryBudget, TileUploadBudget};
+use crate::resources::input_map::TOGGLE_DEBUG;
+use crate::components::{TileCoords, MainCamera};
+use crate::utils::geo::GeoPos;
 
-/// System to toggle debug mode with the 1 key
+/// System to toggle debug mode (the 1 key by default)
 pub fn toggle_debug_mode(
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
     mut debug_settings: ResMut<DebugSettings>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Digit1) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_DEBUG) {
         debug_settings.debug_mode = !debug_settings.debug_mode;
         info!("Debug mode: {}", if debug_settings.debug_mode { "ON" } else { "OFF" });
     }
@@ -19,8 +21,9 @@ pub fn debug_info(
     osm_data: Res<OSMData>,
     debug_settings: Res<DebugSettings>,
     time: Res<Time>,
-    camera_query: Query<&Transform, With<Camera3d>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
     tile_query: Query<&TileCoords>,
+    (budget, upload_budget): (Res<TileMemoryBudget>, Res<TileUploadBudget>),
 ) {
     // Skip if debug mode is disabled
     if !debug_settings.debug_mode {
@@ -28,7 +31,7 @@ pub fn debug_info(
     }
 
     // Only run every few seconds
-    if time.elapsed_secs() as usize % 5 != 0 {
+    if !(time.elapsed_secs() as usize).is_multiple_of(5) {
         return;
     }
 
@@ -38,18 +41,42 @@ pub fn debug_info(
         let z = camera_transform.translation.z;
         
         // Current tile at current zoom level
-        let (tile_x, tile_y) = world_to_tile_coords(x, z, osm_data.current_zoom);
+        let tile = GeoPos::from_translation(camera_transform.translation).tile(osm_data.current_zoom);
+        
+        // Geographic extent of that tile
+        let bounds = tile.bounds();
         
         // Count active tiles
         let active_tiles = tile_query.iter().count();
         
+        // Decoded texture cache usage, to validate its sizing
+        let texture_cache = &osm_data.texture_cache;
+        
         // Debug info
         info!(
-            "Pos: ({:.1}, {:.1}, {:.1}) | Zoom: {} | Tile: {},{} | Active tiles: {}",
+            "Pos: ({:.1}, {:.1}, {:.1}) | Zoom: {} | Tile: {},{} (N {:.5} S {:.5} W {:.5} E {:.5}) | Active tiles: {}",
             x, y, z,
             osm_data.current_zoom,
-            tile_x, tile_y,
+            tile.x, tile.y,
+            bounds.north, bounds.south, bounds.west, bounds.east,
             active_tiles
         );
+        info!(
+            "Texture cache: {} textures, {:.1}/{:.1} MB | Hit rate: {:.1}% ({} hits, {} misses)",
+            texture_cache.entries.len(),
+            texture_cache.used_bytes as f32 / (1024.0 * 1024.0),
+            texture_cache.capacity_bytes as f32 / (1024.0 * 1024.0),
+            texture_cache.hit_rate() * 100.0,
+            texture_cache.hits,
+            texture_cache.misses
+        );
+        info!(
+            "Tile textures: {:.1}/{:.1} MB | Evicted over budget: {} | Waiting for upload: {}",
+            budget.used_bytes as f32 / (1024.0 * 1024.0),
+            budget.budget_bytes as f32 / (1024.0 * 1024.0),
+            budget.evicted_tiles,
+            upload_budget.deferred_tiles
+        );
+        info!("Downloads in flight: {}", osm_data.downloads.summary());
     }
 } 
\ No newline at end of file
@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use crate::components::{MainCamera, RealTimeButton, TimeSlider, TimeSliderKnob, WorldTimeText};
+use crate::resources::{EnvironmentSettings, Sun, WorldClock};
+use crate::resources::environment::wall_clock_seconds;
+use crate::utils::geo::GeoPos;
+use crate::utils::solar::{solar_time_of_day, sun_position};
+
+// Sun elevations in degrees where night ends and full daylight starts (civil twilight)
+const NIGHT_ELEVATION: f64 = -6.0;
+const DAY_ELEVATION: f64 = 10.0;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Move the world clock forward, following the wall clock unless it was scrubbed
+pub fn advance_world_clock(time: Res<Time>, mut clock: ResMut<WorldClock>) {
+    if clock.real_time {
+        clock.unix_seconds = wall_clock_seconds();
+    } else {
+        let step = time.delta_secs_f64() * clock.speed;
+        clock.unix_seconds += step;
+    }
+}
+
+// Latitude/longitude of the ground below the camera
+fn camera_lat_lon(camera: &Transform) -> (f64, f64) {
+    let geo = GeoPos::from_translation(camera.translation);
+    (geo.lat, geo.lon)
+}
+
+/// Compute where the sun is seen from below the camera
+pub fn update_sun(
+    clock: Res<WorldClock>,
+    mut sun: ResMut<Sun>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let (lat, lon) = camera_lat_lon(camera);
+    sun.position = sun_position(clock.unix_seconds, lat, lon);
+
+    let t = ((sun.position.elevation - NIGHT_ELEVATION) / (DAY_ELEVATION - NIGHT_ELEVATION)).clamp(0.0, 1.0);
+    sun.daylight = (t * t * (3.0 - 2.0 * t)) as f32;
+}
+
+/// Point the sunlight at the sun's position and set the light and sky for the time of day
+pub fn apply_sun_lighting(
+    sun: Res<Sun>,
+    settings: Res<EnvironmentSettings>,
+    mut ambient: ResMut<AmbientLight>,
+    mut clear_color: ResMut<ClearColor>,
+    mut light_query: Query<(&mut DirectionalLight, &mut Transform)>,
+) {
+    let towards_sun = sun.direction();
+    let sun_up = sun.position.elevation > 0.0;
+
+    let daylight = sun.daylight;
+    for (mut light, mut transform) in light_query.iter_mut() {
+        // Below the horizon the light comes from straight up, as moonlight
+        let direction = if sun_up { towards_sun } else { Vec3::Y };
+        *transform = Transform::IDENTITY.looking_to(-direction, Vec3::Y);
+        light.illuminance = settings.night_illuminance.lerp(settings.day_illuminance, daylight);
+        light.shadows_enabled = sun_up;
+    }
+
+    ambient.brightness = settings.night_ambient.lerp(settings.day_ambient, daylight);
+
+    // Shows wherever nothing else is drawn, like the sky when the atmosphere is left out
+    clear_color.0 = settings.sky_color(&sun);
+}
+
+/// Spawn the time of day slider (top center) with the world time and a button back to real time
+pub fn setup_time_slider(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-170.0)),
+                width: Val::Px(340.0),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Node {
+                    width: Val::Px(110.0),
+                    ..default()
+                },
+                WorldTimeText,
+            ));
+
+            // Clicking or dragging on the track sets the local time of day
+            bar.spawn((
+                Button,
+                Node {
+                    flex_grow: 1.0,
+                    height: Val::Px(12.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.3, 0.3, 0.3, 0.8)),
+                RelativeCursorPosition::default(),
+                TimeSlider,
+            ))
+            .with_child((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(6.0),
+                    height: Val::Px(16.0),
+                    top: Val::Px(-2.0),
+                    margin: UiRect::left(Val::Px(-3.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(1.0, 0.8, 0.2)),
+                TimeSliderKnob,
+            ));
+
+            bar.spawn((
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+                RealTimeButton,
+            ))
+            .with_child((
+                Text::new("Now"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Scrub the world clock with the time slider, or return it to real time
+/// Scrubbing keeps the date and sets the local solar time below the camera
+pub fn handle_time_slider(
+    mut clock: ResMut<WorldClock>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    slider_query: Query<(&Interaction, &RelativeCursorPosition), With<TimeSlider>>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<RealTimeButton>)>,
+) {
+    if button_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        clock.real_time = true;
+        return;
+    }
+
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let (_, lon) = camera_lat_lon(camera);
+
+    // Interaction stays Pressed while the button is held, so the knob follows a drag
+    for (interaction, cursor) in slider_query.iter() {
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let target = position.x.clamp(0.0, 1.0) as f64 * SECONDS_PER_DAY;
+        let current = solar_time_of_day(clock.unix_seconds, lon);
+        clock.unix_seconds += target - current;
+        clock.real_time = false;
+    }
+}
+
+/// Show the world time on the slider
+pub fn update_time_slider(
+    clock: Res<WorldClock>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut knob_query: Query<&mut Node, With<TimeSliderKnob>>,
+    mut text_query: Query<&mut Text, With<WorldTimeText>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let (_, lon) = camera_lat_lon(camera);
+    let local = solar_time_of_day(clock.unix_seconds, lon);
+
+    for mut knob in knob_query.iter_mut() {
+        knob.left = Val::Percent((local / SECONDS_PER_DAY * 100.0) as f32);
+    }
+
+    let minutes = (local / 60.0) as u32;
+    let value = format!(
+        "{:02}:{:02} local{}",
+        minutes / 60,
+        minutes % 60,
+        if clock.real_time { "" } else { " (sim)" }
+    );
+    for mut text in text_query.iter_mut() {
+        if text.0 != value {
+            text.0 = value.clone();
+        }
+    }
+}
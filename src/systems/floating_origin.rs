@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use crate::components::{GeoAnchor, MainCamera, TileBatch, TileCoords, TileZoomRoot};
+use crate::events::OriginShifted;
+use crate::resources::{CameraFlight, CursorPick, FloatingOrigin, PathRecorder, TileAtlas};
+use crate::utils::geo::{set_world_origin, world_origin};
+
+// Everything placed directly in the world, moved along with its children
+// Zoom roots stay at the render origin, UI isn't on the map
+type TopLevelTransforms<'w, 's> = Query<
+    'w,
+    's,
+    &'static mut Transform,
+    (Without<Parent>, Without<TileZoomRoot>, Without<Node>, Without<Camera2d>),
+>;
+// Tiles and batch meshes under their zoom root
+type TileTransforms<'w, 's> =
+    Query<'w, 's, &'static mut Transform, (With<Parent>, Or<(With<TileCoords>, With<TileBatch>)>)>;
+// Positions kept outside of Transforms
+type StoredPositions<'w> = (ResMut<'w, CursorPick>, ResMut<'w, CameraFlight>, ResMut<'w, PathRecorder>);
+
+/// Move the render origin to the camera once it strays too far from it, shifting everything
+/// placed in the world back by the same offset so nothing visibly moves
+/// Runs after all movement and before transforms are propagated, so the frame is drawn rebased
+pub fn rebase_floating_origin(
+    settings: Res<FloatingOrigin>,
+    camera_query: Query<Entity, With<MainCamera>>,
+    mut top_level: TopLevelTransforms,
+    mut tiles: TileTransforms,
+    mut atlas: ResMut<TileAtlas>,
+    (mut cursor_pick, mut flight, mut recorder): StoredPositions,
+    mut shifted: EventWriter<OriginShifted>,
+) {
+    let Some(camera) = camera_query.get_single().ok().and_then(|camera| top_level.get(camera).ok()) else {
+        return;
+    };
+    let Some(offset) = settings.rebase_offset(camera.translation) else {
+        return;
+    };
+
+    set_world_origin(world_origin() + offset.as_dvec2());
+    let offset = Vec3::new(offset.x, 0.0, offset.y);
+
+    for mut transform in &mut top_level {
+        transform.translation -= offset;
+    }
+    for mut transform in &mut tiles {
+        transform.translation -= offset;
+    }
+    // Batch vertices are in the old coordinates: the batches are moved until they're rebuilt
+    atlas.dirty = true;
+
+    if let Some(world) = cursor_pick.world.as_mut() {
+        *world -= offset;
+    }
+    if let Some(active) = flight.active.as_mut() {
+        active.from -= offset;
+        active.to -= offset;
+    }
+    if !recorder.path.keyframes.is_empty() {
+        recorder.path.shift(-offset);
+    }
+
+    shifted.send(OriginShifted { offset });
+    debug!("Moved the render origin to {:?}", world_origin());
+}
+
+/// Place anchored entities at their geographic position when it changed or the origin moved
+/// The rebase moves them along in f32; placing them from their f64 position keeps them from
+/// drifting over many rebases
+pub fn anchor_geo_entities(mut shifted: EventReader<OriginShifted>, mut anchor_query: Query<(Ref<GeoAnchor>, &mut Transform)>) {
+    let rebased = shifted.read().count() > 0;
+    for (anchor, mut transform) in anchor_query.iter_mut() {
+        if rebased || anchor.is_changed() {
+            transform.translation = anchor.translation();
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use crate::components::{Collectible, MainCamera, ScoreText, ZoneText};
+use crate::events::{CollectiblePicked, ZoneEntered, ZoneExited};
+use crate::resources::{ActiveZones, CollectibleSpawn, GameContent, LaunchOptions, Score, TileContext, TriggerZone};
+use crate::utils::geo::{lat_lon_to_world, meters_per_world_unit, world_to_lat_lon};
+
+// Size of a collectible and how far it floats above the ground, in meters
+const COLLECTIBLE_SIZE_M: f64 = 8.0;
+const COLLECTIBLE_HEIGHT_M: f64 = 10.0;
+// Radians per second collectibles spin around their vertical axis
+const COLLECTIBLE_SPIN_SPEED: f32 = 1.5;
+
+// Demo content around the start position: one zone and a ring of collectibles
+const DEMO_ZONE_RADIUS_M: f64 = 500.0;
+const DEMO_RING_RADIUS_M: f64 = 300.0;
+const DEMO_COLLECTIBLES: usize = 8;
+
+/// Add a demo zone and collectibles around the start position,
+/// unless the app already registered its own game content
+pub fn add_demo_game_content(mut content: ResMut<GameContent>, launch: Res<LaunchOptions>) {
+    if !content.zones.is_empty() || !content.collectibles.is_empty() {
+        return;
+    }
+
+    let start = launch.start_world();
+    let (lat, lon) = world_to_lat_lon(start.x, start.y);
+    content.zones.push(TriggerZone {
+        name: "Start area".to_string(),
+        lat,
+        lon,
+        radius_m: DEMO_ZONE_RADIUS_M,
+    });
+
+    let ring_radius = DEMO_RING_RADIUS_M / meters_per_world_unit(lat);
+    for i in 0..DEMO_COLLECTIBLES {
+        let angle = i as f64 / DEMO_COLLECTIBLES as f64 * std::f64::consts::TAU;
+        let point = start + DVec2::from_angle(angle) * ring_radius;
+        let (lat, lon) = world_to_lat_lon(point.x, point.y);
+        content.collectibles.push(CollectibleSpawn {
+            lat,
+            lon,
+            points: 10,
+        });
+    }
+}
+
+/// Tile generator spawning the collectibles that lie on a tile as its children,
+/// so they come and go with the tile
+pub fn spawn_tile_collectibles(
+    In(context): In<TileContext>,
+    mut commands: Commands,
+    content: Res<GameContent>,
+    score: Res<Score>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+) {
+    // Background tiles sit below the detailed tiles covering the same area
+    if context.is_background {
+        return;
+    }
+
+    let tile_size = context.world_size();
+    for (index, spawn) in content.collectibles.iter().enumerate() {
+        if score.collected.contains(&index) {
+            continue;
+        }
+        if !context.bounds.contains(spawn.lat, spawn.lon) {
+            continue;
+        }
+        let local = context.lat_lon_to_local(spawn.lat, spawn.lon);
+
+        let (mesh, material) = assets
+            .get_or_insert_with(|| {
+                (
+                    meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
+                    materials.add(StandardMaterial {
+                        base_color: Color::srgb(1.0, 0.8, 0.1),
+                        emissive: LinearRgba::rgb(0.6, 0.45, 0.0),
+                        ..default()
+                    }),
+                )
+            })
+            .clone();
+
+        // Children live in tile-local space, undo the tile's horizontal scale to get a cube
+        let meters = meters_per_world_unit(spawn.lat);
+        let size = (COLLECTIBLE_SIZE_M / meters) as f32;
+        let height = (COLLECTIBLE_HEIGHT_M / meters) as f32;
+        let child = commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material),
+                Transform::from_xyz(local.x, height, local.y)
+                    .with_scale(Vec3::new(size / tile_size, size, size / tile_size)),
+                Collectible { index },
+            ))
+            .id();
+        commands.entity(context.tile_entity).add_child(child);
+    }
+}
+
+/// Spin collectibles so they stand out from the map
+pub fn spin_collectibles(time: Res<Time>, mut query: Query<&mut Transform, With<Collectible>>) {
+    for mut transform in query.iter_mut() {
+        transform.rotate_y(COLLECTIBLE_SPIN_SPEED * time.delta_secs());
+    }
+}
+
+// Ground distance in meters between a world position and a coordinate
+fn ground_distance_m(world: DVec2, lat: f64, lon: f64) -> f64 {
+    (world - lat_lon_to_world(lat, lon)).length() * meters_per_world_unit(lat)
+}
+
+/// Send ZoneEntered/ZoneExited when the camera crosses the edge of a trigger zone
+pub fn update_trigger_zones(
+    content: Res<GameContent>,
+    mut active_zones: ResMut<ActiveZones>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut entered_events: EventWriter<ZoneEntered>,
+    mut exited_events: EventWriter<ZoneExited>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let position = DVec2::new(camera.translation.x as f64, camera.translation.z as f64);
+
+    for (index, zone) in content.zones.iter().enumerate() {
+        let inside = ground_distance_m(position, zone.lat, zone.lon) <= zone.radius_m;
+        let was_inside = active_zones.inside.contains(&index);
+
+        if inside && !was_inside {
+            active_zones.inside.insert(index);
+            entered_events.send(ZoneEntered { zone: index });
+        } else if !inside && was_inside {
+            active_zones.inside.remove(&index);
+            exited_events.send(ZoneExited { zone: index });
+        }
+    }
+}
+
+/// Collect the collectibles the camera gets close enough to
+pub fn pick_up_collectibles(
+    mut commands: Commands,
+    content: Res<GameContent>,
+    mut score: ResMut<Score>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    collectible_query: Query<(Entity, &Collectible)>,
+    mut picked_events: EventWriter<CollectiblePicked>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let position = DVec2::new(camera.translation.x as f64, camera.translation.z as f64);
+
+    for (entity, collectible) in collectible_query.iter() {
+        let Some(spawn) = content.collectibles.get(collectible.index) else {
+            continue;
+        };
+
+        // A collectible can briefly exist twice while tiles of two zoom levels overlap
+        if score.collected.contains(&collectible.index) {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let meters = meters_per_world_unit(spawn.lat);
+        let horizontal = ground_distance_m(position, spawn.lat, spawn.lon);
+        let vertical = (camera.translation.y as f64 * meters - COLLECTIBLE_HEIGHT_M).abs();
+        if horizontal.hypot(vertical) > content.pickup_radius_m {
+            continue;
+        }
+
+        score.points += spawn.points;
+        score.collected.insert(collectible.index);
+        picked_events.send(CollectiblePicked {
+            collectible: collectible.index,
+            points: spawn.points,
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Log zone and collectible events
+pub fn log_game_events(
+    content: Res<GameContent>,
+    mut entered_events: EventReader<ZoneEntered>,
+    mut exited_events: EventReader<ZoneExited>,
+    mut picked_events: EventReader<CollectiblePicked>,
+) {
+    for event in entered_events.read() {
+        if let Some(zone) = content.zones.get(event.zone) {
+            info!("Entered zone {}", zone.name);
+        }
+    }
+    for event in exited_events.read() {
+        if let Some(zone) = content.zones.get(event.zone) {
+            info!("Left zone {}", zone.name);
+        }
+    }
+    for event in picked_events.read() {
+        info!("Picked up collectible {} for {} points", event.collectible, event.points);
+    }
+}
+
+/// Spawn the score and zone texts (top right)
+pub fn setup_game_hud(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        ScoreText,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        ZoneText,
+    ));
+}
+
+/// Show the score and the zones the camera is in
+pub fn update_game_hud(
+    content: Res<GameContent>,
+    score: Res<Score>,
+    active_zones: Res<ActiveZones>,
+    mut score_query: Query<&mut Text, (With<ScoreText>, Without<ZoneText>)>,
+    mut zone_query: Query<&mut Text, (With<ZoneText>, Without<ScoreText>)>,
+) {
+    if score.is_changed() {
+        for mut text in score_query.iter_mut() {
+            text.0 = format!(
+                "Score: {} ({}/{} collected)",
+                score.points,
+                score.collected.len(),
+                content.collectibles.len()
+            );
+        }
+    }
+
+    if active_zones.is_changed() {
+        let mut names: Vec<&str> = active_zones
+            .inside
+            .iter()
+            .filter_map(|index| content.zones.get(*index))
+            .map(|zone| zone.name.as_str())
+            .collect();
+        names.sort_unstable();
+
+        for mut text in zone_query.iter_mut() {
+            text.0 = if names.is_empty() {
+                String::new()
+            } else {
+                format!("Zone: {}", names.join(", "))
+            };
+        }
+    }
+}
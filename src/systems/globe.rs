@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use bevy::render::view::NoFrustumCulling;
+use std::f64::consts::TAU;
+use crate::components::MainCamera;
+use crate::osm::{TileGlobe, TileMaterial};
+use crate::resources::{GlobeSettings, InputMap, TileAtlas};
+use crate::resources::input_map::TOGGLE_GLOBE;
+use crate::utils::geo::{world_origin, world_size, GeoPos};
+
+/// Switch globe mode on or off (the G key by default)
+pub fn toggle_globe_mode(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut settings: ResMut<GlobeSettings>,
+    mut atlas: ResMut<TileAtlas>,
+) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_GLOBE) {
+        settings.enabled = !settings.enabled;
+        // Low zoom batches are subdivided only while globe mode is on
+        atlas.dirty = true;
+        info!("Globe mode: {}", if settings.enabled { "ON" } else { "OFF" });
+    }
+}
+
+/// Curve the tiles onto a globe touching the map below the camera, by camera height
+pub fn update_tile_globe(
+    mut commands: Commands,
+    settings: Res<GlobeSettings>,
+    mut atlas: ResMut<TileAtlas>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let blend = settings.blend_at(camera.translation.y);
+    let was_curved = atlas.globe.blend > 0.0;
+    if blend == 0.0 && !was_curved {
+        // The flat map still needs the render origin, to fade out towards the poles
+        let origin = world_origin().as_vec2();
+        if atlas.globe.origin != origin {
+            let globe = TileGlobe { origin, ..atlas.globe };
+            atlas.set_globe(globe, &mut materials);
+        }
+        return;
+    }
+
+    let GeoPos { lat, lon } = GeoPos::from_translation(camera.translation);
+    // Web Mercator stretches the map by 1/cos(lat): a globe that large matches the flat map
+    // around the tangent point, shrinking to the true globe as the blend completes
+    let stretch = (lat.to_radians().cos() + (1.0 - lat.to_radians().cos()) * blend as f64).max(f64::EPSILON);
+    let globe = TileGlobe {
+        tangent: Vec4::new(lat.to_radians() as f32, lon.to_radians() as f32, camera.translation.x, camera.translation.z),
+        radius: (world_size() / TAU / stretch) as f32,
+        blend,
+        world_size: world_size() as f32,
+        origin: world_origin().as_vec2(),
+    };
+    atlas.set_globe(globe, &mut materials);
+
+    // Curved vertices leave the flat bounds of the batch meshes
+    let curved = blend > 0.0;
+    if curved != was_curved {
+        for &batch in atlas.batches.values() {
+            if curved {
+                commands.entity(batch).insert(NoFrustumCulling);
+            } else {
+                commands.entity(batch).remove::<NoFrustumCulling>();
+            }
+        }
+    }
+}
@@ -1,35 +1,257 @@
 use bevy::prelude::*;
-use crate::resources::DebugSettings;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::input::touch::Touch;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::resources::{CursorPick, DebugSettings, DoubleClickState, InputMap, MouseLookState, OSMData, Pinch, WhatsHereClick};
+use crate::resources::input_map::ZOOM_OUT_MODIFIER;
+use crate::events::FlyTo;
+use crate::components::MainCamera;
+use crate::utils::browser::open_url;
+use crate::utils::deep_link::osm_url;
+use crate::utils::geo::{normalize_lon, GeoPos};
 use crate::debug_log;
 
+// Fraction of the distance to the cursor point kept per scroll wheel line
+const SCROLL_ZOOM_STEP: f32 = 0.8;
+// Pixel-based scroll deltas (touchpads) are converted to lines with this factor
+const PIXELS_PER_LINE: f32 = 100.0;
+// Maximum time between two clicks to count as a double-click, in seconds
+const DOUBLE_CLICK_TIME: f32 = 0.3;
+// Duration of the double-click zoom flight
+const DOUBLE_CLICK_FLIGHT: f32 = 0.4;
+// Lowest height the zoom controls will move the camera to
+const MIN_ZOOM_HEIGHT: f32 = 0.5;
+// Mouse motion in pixels up to which a right click is still a click and not a drag
+const CLICK_SLOP_PX: f32 = 4.0;
+
+/// Raycast from the cursor to the ground plane and record the lat/lon and tile under it
+/// While the mouse is locked for camera movement the screen centre is used instead
+pub fn update_cursor_pick(
+    mut cursor_pick: ResMut<CursorPick>,
+    osm_data: Res<OSMData>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    *cursor_pick = CursorPick::default();
+
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_query.get_single()) else {
+        return;
+    };
+
+    let screen_position = match window.cursor_options.grab_mode {
+        CursorGrabMode::None => window.cursor_position(),
+        _ => Some(window.size() / 2.0),
+    };
+    let Some(screen_position) = screen_position else {
+        return;
+    };
+
+    let Ok(ray) = camera.viewport_to_world(camera_transform, screen_position) else {
+        return;
+    };
+
+    // Tiles lie on the y=0 plane
+    let Some(distance) = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y)) else {
+        return;
+    };
+    let hit_point = ray.get_point(distance);
+
+    let geo = GeoPos::from_translation(hit_point);
+    cursor_pick.world = Some(hit_point);
+    // Past ±180 the map repeats, the cursor is over the same place as within the world
+    cursor_pick.lat_lon = Some((geo.lat, normalize_lon(geo.lon)));
+
+    let tile = geo.tile(osm_data.current_zoom);
+    cursor_pick.tile = Some((tile.x, tile.y, tile.z));
+}
+
 /// System to handle user interaction with the map
 pub fn interact_with_map(
-    _keyboard_input: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     debug_settings: Res<DebugSettings>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    cursor_pick: Res<CursorPick>,
 ) {
     // Only perform actions on mouse click
     if mouse_input.just_pressed(MouseButton::Left) {
-        if let Ok((camera_transform, _camera)) = camera_query.get_single() {
-            // Get camera position and direction
-            let ray_origin = camera_transform.translation;
-            let ray_direction = camera_transform.forward();
-            
-            // Simple ray-plane intersection to find which tile we're looking at
-            // Assume tiles are on the y=0 plane
-            let t = -ray_origin.y / ray_direction.y;
-            if t > 0.0 {
-                let hit_point = ray_origin + ray_direction * t;
-                debug_log!(debug_settings, "Ray hit ground at position: {:?}", hit_point);
-                
-                // Convert hit point to tile coordinates at the current zoom level
-                // (We can add this functionality later if needed)
-            } else {
+        match (cursor_pick.world, cursor_pick.tile) {
+            (Some(hit_point), Some((x, y, zoom))) => {
+                debug_log!(debug_settings, "Ray hit ground at position: {:?}, tile {}/{}/{}", hit_point, zoom, x, y);
+            }
+            _ => {
                 debug_log!(debug_settings, "Ray didn't hit ground plane - make sure you're looking at the ground");
             }
-        } else {
-            debug_log!(debug_settings, "Camera not found!");
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Dolly the camera toward (or away from) the ground point under the cursor with the scroll wheel
+pub fn scroll_zoom(
+    mut wheel_events: EventReader<MouseWheel>,
+    cursor_pick: Res<CursorPick>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    let lines: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if lines == 0.0 {
+        return;
+    }
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // Without ground under the cursor, zoom toward a point straight ahead
+    let position = transform.translation;
+    let target = cursor_pick
+        .world
+        .unwrap_or(position + *transform.forward() * position.y.max(1.0));
+
+    // Each line forward keeps SCROLL_ZOOM_STEP of the distance, each line back divides by it
+    let factor = SCROLL_ZOOM_STEP.powf(lines);
+    let new_position = target + (position - target) * factor;
+
+    // Don't dive into the ground
+    if new_position.y >= MIN_ZOOM_HEIGHT || lines < 0.0 {
+        transform.translation = new_position;
+    }
+}
+
+/// Touchscreen gestures: one finger drags the map, two fingers pan it with their midpoint,
+/// pinch to zoom toward the point between them and twist to turn the view around it
+pub fn touch_gestures(
+    touches: Res<Touches>,
+    mut mouse_look_state: ResMut<MouseLookState>,
+    mut camera_query: Query<(&Camera, &GlobalTransform, &mut Transform), With<MainCamera>>,
+) {
+    let mut fingers: Vec<&Touch> = touches.iter().collect();
+    if fingers.is_empty() {
+        return;
+    }
+    fingers.sort_by_key(|touch| touch.id());
+
+    let Ok((camera, camera_transform, mut transform)) = camera_query.get_single_mut() else {
+        return;
+    };
+    // The ground under a screen position, as the camera saw it at the start of the frame
+    let ground = |position: Vec2| {
+        let ray = camera.viewport_to_world(camera_transform, position).ok()?;
+        let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+        Some(ray.get_point(distance))
+    };
+
+    // Keep the ground that was under the fingers under them
+    let (previous, current) = match fingers.as_slice() {
+        [finger] => (finger.previous_position(), finger.position()),
+        [first, second, ..] => {
+            let Some(pinch) = Pinch::between(
+                [first.previous_position(), second.previous_position()],
+                [first.position(), second.position()],
+            ) else {
+                return;
+            };
+            let Some(target) = ground(pinch.previous_center) else {
+                return;
+            };
+
+            // Zoom toward the point between the fingers like the scroll wheel, without diving into the ground
+            let zoomed = target + (transform.translation - target) * pinch.scale;
+            if zoomed.y >= MIN_ZOOM_HEIGHT || pinch.scale > 1.0 {
+                transform.translation = zoomed;
+            }
+
+            // Turning the view clockwise around the point turns the map under the fingers clockwise
+            let rotation = Quat::from_rotation_y(pinch.rotation);
+            transform.translation = target + rotation * (transform.translation - target);
+            transform.rotation = rotation * transform.rotation;
+            mouse_look_state.yaw += pinch.rotation;
+
+            (pinch.previous_center, pinch.center)
+        }
+        [] => return,
+    };
+    if let (Some(from), Some(to)) = (ground(previous), ground(current)) {
+        transform.translation += (from - to).with_y(0.0);
+    }
+}
+
+/// Double-click zooms in one level toward the clicked point, shift+double-click zooms out
+pub fn double_click_zoom(
+    time: Res<Time>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    (keyboard_input, input_map): (Res<ButtonInput<KeyCode>>, Res<InputMap>),
+    cursor_pick: Res<CursorPick>,
+    mut double_click: ResMut<DoubleClickState>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut fly_to_events: EventWriter<FlyTo>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    let is_double_click = double_click
+        .last_click
+        .is_some_and(|last| now - last <= DOUBLE_CLICK_TIME);
+
+    if !is_double_click {
+        double_click.last_click = Some(now);
+        return;
+    }
+    // A third click starts a new double-click
+    double_click.last_click = None;
+
+    let (Some(target), Ok(transform)) = (cursor_pick.world, camera_query.get_single()) else {
+        return;
+    };
+
+    // One zoom level is half (or double) the height, so halve the distance to the point
+    let zoom_out = input_map.pressed(&keyboard_input, ZOOM_OUT_MODIFIER);
+    let factor = if zoom_out { 2.0 } else { 0.5 };
+    let mut destination = target + (transform.translation - target) * factor;
+    destination.y = destination.y.max(MIN_ZOOM_HEIGHT);
+
+    fly_to_events.send(FlyTo {
+        target: destination,
+        duration: DOUBLE_CLICK_FLIGHT,
+    });
+}
+
+/// "What's here?": a right click opens the openstreetmap.org page of the place under the
+/// cursor, to compare the map with the live one. Right-drags (orbiting) don't count
+/// Where URLs can't be opened the link is logged instead
+pub fn whats_here(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion_events: EventReader<MouseMotion>,
+    mut click: ResMut<WhatsHereClick>,
+    cursor_pick: Res<CursorPick>,
+    osm_data: Res<OSMData>,
+) {
+    let motion: f32 = mouse_motion_events.read().map(|event| event.delta.length()).sum();
+    if mouse_input.just_pressed(MouseButton::Right) {
+        *click = WhatsHereClick { pressed: true, dragged_px: 0.0 };
+    } else if click.pressed {
+        click.dragged_px += motion;
+    }
+
+    if !click.pressed || !mouse_input.just_released(MouseButton::Right) {
+        return;
+    }
+    click.pressed = false;
+    if click.dragged_px > CLICK_SLOP_PX {
+        return;
+    }
+
+    let Some((lat, lon)) = cursor_pick.lat_lon else {
+        return;
+    };
+    let url = osm_url(GeoPos::new(lat, lon), osm_data.current_zoom);
+    match open_url(&url) {
+        Ok(()) => info!("What's here: opened {}", url),
+        Err(e) => info!("What's here: {} (couldn't open a browser: {})", url, e),
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::f32::consts::FRAC_PI_2;
+use crate::components::{IslandBorder, TileCoords, TileTint};
+use crate::osm::TileId;
+use crate::resources::{CursorPick, InputMap, IslandEditor, Islands, Style};
+use crate::resources::constants::PERSISTENT_ISLAND_ZOOM_LEVEL;
+use crate::resources::input_map::TOGGLE_ISLAND_GRID;
+use crate::utils::geo::{tile_world_origin, tile_world_size, GeoPos};
+
+// Just above the highest focus tiles, like the other map overlays
+const BOUNDS_ELEVATION: f32 = 0.006;
+// Border width as a fraction of the island's tile
+const BORDER_WIDTH: f32 = 0.03;
+// Cells drawn around the cell under the cursor, in each direction
+const GRID_RADIUS: i64 = 6;
+const GRID_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.35);
+
+/// Spawn an outline around every persistent island's tile, so islands can be found on the map
+/// before their content is loaded
+pub fn spawn_island_borders(
+    mut commands: Commands,
+    islands: Res<Islands>,
+    style: Res<Style>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let material = materials.add(StandardMaterial {
+        base_color: style.islands.border_color,
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        cull_mode: None,
+        ..default()
+    });
+    // Every tile is the same square scaled to its size
+    let mesh = meshes.add(border_mesh(BORDER_WIDTH));
+
+    for island in &islands.regions {
+        let origin = tile_world_origin(island.tile);
+        let size = tile_world_size(island.tile.z) as f32;
+        commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(origin.x as f32, BOUNDS_ELEVATION, origin.y as f32)
+                .with_scale(Vec3::new(size, 1.0, size)),
+            IslandBorder,
+            Name::new(format!("Island border {}", island.name)),
+        ));
+    }
+}
+
+// A square ring in the unit square from (0, 0) to (1, 1) on the XZ plane, `width` wide
+fn border_mesh(width: f32) -> Mesh {
+    let (a, b) = (width, 1.0 - width);
+    let positions = vec![
+        [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0], // Outer corners
+        [a, 0.0, a], [b, 0.0, a], [b, 0.0, b], [a, 0.0, b],                 // Inner corners
+    ];
+    let normals = vec![[0.0, 1.0, 0.0]; 8];
+    // Two triangles per side, between the outer and the inner corners
+    let indices = (0..4u32)
+        .flat_map(|side| {
+            let next = (side + 1) % 4;
+            [side, side + 4, next, next, side + 4, next + 4]
+        })
+        .collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Brighten the island borders while in island editing mode, and toggle the grid (H by default)
+/// The border colors follow the style when it's reloaded
+pub fn update_island_borders(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    style: Res<Style>,
+    mut editor: ResMut<IslandEditor>,
+    border_query: Query<&MeshMaterial3d<StandardMaterial>, With<IslandBorder>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if editor.active && input_map.just_pressed(&keyboard_input, TOGGLE_ISLAND_GRID) {
+        editor.show_grid = !editor.show_grid;
+    }
+    if !editor.is_changed() && !style.is_changed() {
+        return;
+    }
+
+    let color = if editor.active { style.islands.highlight_color } else { style.islands.border_color };
+    // The borders share one material
+    if let Some(material) = border_query.iter().next().and_then(|material| materials.get_mut(&material.0)) {
+        if material.base_color != color {
+            material.base_color = color;
+        }
+    }
+}
+
+/// Tint the map tiles islands are on with the style's highlight color while editing islands
+pub fn tint_island_tiles(
+    mut commands: Commands,
+    editor: Res<IslandEditor>,
+    (islands, style): (Res<Islands>, Res<Style>),
+    tile_query: Query<(Entity, &TileCoords, Option<&TileTint>)>,
+) {
+    let color = style.islands.highlight_color;
+    for (entity, coords, tint) in &tile_query {
+        // Tiles at the island's zoom level or deeper, within the island's tile
+        let on_island = editor.active
+            && islands.regions.iter().any(|island| {
+                let depth = coords.zoom.saturating_sub(island.tile.z);
+                coords.zoom >= island.tile.z && TileId::new(coords.x >> depth, coords.y >> depth, island.tile.z) == island.tile
+            });
+        match (on_island, tint) {
+            (true, Some(tint)) if tint.0 == color => {}
+            (true, _) => {
+                commands.entity(entity).insert(TileTint(color));
+            }
+            (false, Some(_)) => {
+                commands.entity(entity).remove::<TileTint>();
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+/// Draw the grid of cells islands are claimed on around the cursor while editing islands,
+/// with the cell under the cursor outlined: green when free, red when an island has it
+pub fn draw_island_grid(
+    cursor_pick: Res<CursorPick>,
+    islands: Res<Islands>,
+    style: Res<Style>,
+    mut editor: ResMut<IslandEditor>,
+    mut gizmos: Gizmos,
+) {
+    let cell = cursor_pick
+        .world
+        .filter(|_| editor.active && editor.show_grid)
+        .map(|world| GeoPos::from_translation(world).tile(PERSISTENT_ISLAND_ZOOM_LEVEL));
+    if editor.cell != cell {
+        editor.cell = cell;
+    }
+    let Some(cell) = cell else {
+        return;
+    };
+
+    let size = tile_world_size(PERSISTENT_ISLAND_ZOOM_LEVEL) as f32;
+    let origin = tile_world_origin(cell).as_vec2();
+    let extent = GRID_RADIUS as f32 * size;
+    let cells = 1i64 << PERSISTENT_ISLAND_ZOOM_LEVEL;
+    for offset in -GRID_RADIUS..=GRID_RADIUS + 1 {
+        // Lines on the edges of the map's cells only
+        let x = cell.x as i64 + offset;
+        if (0..=cells).contains(&x) {
+            let line_x = origin.x + offset as f32 * size;
+            gizmos.line(
+                Vec3::new(line_x, BOUNDS_ELEVATION, origin.y - extent),
+                Vec3::new(line_x, BOUNDS_ELEVATION, origin.y + extent + size),
+                GRID_COLOR,
+            );
+        }
+        let y = cell.y as i64 + offset;
+        if (0..=cells).contains(&y) {
+            let line_z = origin.y + offset as f32 * size;
+            gizmos.line(
+                Vec3::new(origin.x - extent, BOUNDS_ELEVATION, line_z),
+                Vec3::new(origin.x + extent + size, BOUNDS_ELEVATION, line_z),
+                GRID_COLOR,
+            );
+        }
+    }
+
+    let color = if islands.claimed_by(cell).is_some() {
+        style.islands.claimed_cell_color
+    } else {
+        style.islands.highlight_color.with_alpha(1.0)
+    };
+    let center = Vec3::new(origin.x + size / 2.0, BOUNDS_ELEVATION, origin.y + size / 2.0);
+    gizmos.rect(Isometry3d::new(center, Quat::from_rotation_x(FRAC_PI_2)), Vec2::splat(size), color);
+}
@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use crate::components::{IslandEditorText, IslandTerrain, MainCamera};
+use crate::opensim::BrushTool;
+use crate::resources::{BrushStroke, EditMode, InputMap, IslandEditor, IslandState, Islands, TaskRuntime};
+use crate::resources::input_map::{
+    BRUSH_LARGER, BRUSH_SMALLER, DELETE_OBJECT, NEXT_BRUSH_TOOL, SWITCH_EDIT_MODE, TOGGLE_ISLAND_EDITING,
+    TOGGLE_ISLAND_GRID,
+};
+
+// Brush radius limits in region meters, the bracket keys double or halve it
+const MIN_BRUSH_RADIUS: f32 = 2.0;
+const MAX_BRUSH_RADIUS: f32 = 64.0;
+const BRUSH_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+
+/// Run condition: whether island editing mode is on, so clicks sculpt or place instead of zooming
+pub fn editing_islands(editor: Option<Res<IslandEditor>>) -> bool {
+    editor.is_some_and(|editor| editor.active)
+}
+
+/// Switch island editing mode on or off (I by default), and pick what to edit in it:
+/// O switches between terrain and objects, Tab cycles the brush tools or the assets to place,
+/// [ and ] change the brush size
+pub fn toggle_island_editing(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut editor: ResMut<IslandEditor>,
+) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_ISLAND_EDITING) {
+        editor.active = !editor.active;
+        editor.stroke = None;
+        editor.hover = None;
+        editor.selected = None;
+        editor.drag = None;
+        editor.form = None;
+        info!("Island editing mode: {}", if editor.active { "ON" } else { "OFF" });
+    }
+    if !editor.active {
+        return;
+    }
+
+    if input_map.just_pressed(&keyboard_input, SWITCH_EDIT_MODE) {
+        editor.mode = editor.mode.next();
+        editor.stroke = None;
+        editor.selected = None;
+        editor.drag = None;
+    }
+    if input_map.just_pressed(&keyboard_input, NEXT_BRUSH_TOOL) {
+        match editor.mode {
+            EditMode::Terrain => editor.tool = editor.tool.next(),
+            EditMode::Objects => editor.asset = editor.asset.next(),
+            EditMode::Info => {}
+        }
+    }
+    if input_map.just_pressed(&keyboard_input, BRUSH_LARGER) {
+        editor.radius = (editor.radius * 2.0).min(MAX_BRUSH_RADIUS);
+    }
+    if input_map.just_pressed(&keyboard_input, BRUSH_SMALLER) {
+        editor.radius = (editor.radius / 2.0).max(MIN_BRUSH_RADIUS);
+    }
+}
+
+/// Find the island terrain under the cursor, also outside editing mode for the island tooltip,
+/// and draw the brush there when sculpting
+pub fn pick_island_terrain(
+    mut editor: ResMut<IslandEditor>,
+    islands: Res<Islands>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    terrain_query: Query<(&IslandTerrain, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    editor.hover = None;
+    editor.cursor = None;
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_query.get_single()) else {
+        return;
+    };
+
+    // Like the cursor pick: the screen centre while the mouse is locked
+    let screen_position = match window.cursor_options.grab_mode {
+        CursorGrabMode::None => window.cursor_position(),
+        _ => Some(window.size() / 2.0),
+    };
+    let Some((screen_position, ray)) = screen_position
+        .and_then(|position| Some((position, camera.viewport_to_world(camera_transform, position).ok()?)))
+    else {
+        return;
+    };
+    editor.cursor = Some((screen_position, ray));
+
+    // The nearest hit, islands may overlap when their tiles are at different zoom levels
+    let mut nearest: Option<(f32, usize, Vec3, Vec3)> = None;
+    for (terrain, transform) in &terrain_query {
+        let IslandState::Ready { region, .. } = &islands.regions[terrain.island].state else {
+            continue;
+        };
+        let Some(heightmap) = &region.terrain else {
+            continue;
+        };
+
+        let to_local = transform.affine().inverse();
+        let origin = to_local.transform_point3(ray.origin);
+        let direction = to_local.transform_vector3(*ray.direction).normalize();
+        if let Some(local) = heightmap.raycast(origin, direction, region.water_height) {
+            let world = transform.transform_point(local);
+            let distance = world.distance(ray.origin);
+            if nearest.is_none_or(|(nearest_distance, ..)| distance < nearest_distance) {
+                nearest = Some((distance, terrain.island, local, world));
+            }
+        }
+    }
+
+    let Some((_, island, local, world)) = nearest else {
+        return;
+    };
+    editor.hover = Some((island, local));
+    if !editor.active || editor.mode != EditMode::Terrain {
+        return;
+    }
+
+    // The brush outline, at the size it has on the ground
+    let scale = terrain_query
+        .iter()
+        .find(|(terrain, _)| terrain.island == island)
+        .map_or(1.0, |(_, transform)| transform.scale().x);
+    let flat = Quat::from_rotation_x(FRAC_PI_2);
+    gizmos.circle(Isometry3d::new(world, flat), editor.radius * scale, BRUSH_COLOR);
+}
+
+/// Sculpt the terrain under the brush while the left mouse button is held,
+/// and save it when the button is released
+pub fn sculpt_islands(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    time: Res<Time>,
+    task_runtime: Res<TaskRuntime>,
+    mut editor: ResMut<IslandEditor>,
+    mut islands: ResMut<Islands>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    terrain_query: Query<(&IslandTerrain, &Mesh3d)>,
+) {
+    if !editor.active || editor.mode != EditMode::Terrain {
+        return;
+    }
+
+    if !mouse_input.pressed(MouseButton::Left) {
+        if let Some(stroke) = editor.stroke.take() {
+            save_terrain(&task_runtime, &islands, stroke.island);
+        }
+        return;
+    }
+
+    let Some((island, local)) = editor.hover else {
+        return;
+    };
+    let IslandState::Ready { region, .. } = &mut islands.regions[island].state else {
+        return;
+    };
+    let water_height = region.water_height;
+    let Some(heightmap) = &mut region.terrain else {
+        return;
+    };
+
+    // Mesh coordinates are X east, -Z north and heights relative to the water
+    let center = Vec2::new(local.x, -local.z);
+    if mouse_input.just_pressed(MouseButton::Left) || editor.stroke.is_none() {
+        editor.stroke = Some(BrushStroke { island, target: local.y + water_height });
+    }
+    let Some(stroke) = editor.stroke.as_ref().filter(|stroke| stroke.island == island) else {
+        return;
+    };
+
+    let amount = match editor.tool {
+        BrushTool::Raise | BrushTool::Lower => editor.strength * time.delta_secs(),
+        BrushTool::Smooth | BrushTool::Flatten => editor.blend_rate * time.delta_secs(),
+    };
+    heightmap.apply_brush(editor.tool, center, editor.radius, amount, stroke.target);
+
+    if let Some((_, mesh)) = terrain_query.iter().find(|(terrain, _)| terrain.island == island) {
+        meshes.insert(&mesh.0, heightmap.mesh(water_height));
+    }
+}
+
+// Write an island's terrain to its data directory, off the main thread
+fn save_terrain(task_runtime: &TaskRuntime, islands: &Islands, index: usize) {
+    let island = &islands.regions[index];
+    let IslandState::Ready { region, .. } = &island.state else {
+        return;
+    };
+    let Some(heightmap) = &region.terrain else {
+        return;
+    };
+
+    let bytes = heightmap.to_r32();
+    let path = island.terrain_path();
+    let name = island.name.clone();
+    task_runtime.spawn_blocking(move || {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, bytes));
+        if let Err(e) = result {
+            warn!("Failed to save the terrain of island '{}' to {}: {}", name, path.display(), e);
+        }
+    });
+}
+
+/// Sets up the island editing text (bottom center, above the measurement text)
+pub fn setup_island_editor_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(90.0),
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(-200.0)),
+            width: Val::Px(400.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+        IslandEditorText,
+    ));
+}
+
+/// Show the brush or the object selection, and the island under the cursor while editing
+pub fn update_island_editor_text(
+    editor: Res<IslandEditor>,
+    islands: Res<Islands>,
+    input_map: Res<InputMap>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<IslandEditorText>>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    if !editor.active {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+
+    let island = editor
+        .hover
+        .map_or("No island under the cursor".to_string(), |(index, _)| islands.regions[index].name.clone());
+    let details = match editor.mode {
+        EditMode::Terrain => format!(
+            "{} brush ({}), {:.0} m ({} / {}), drag to sculpt",
+            editor.tool.name(),
+            input_map.describe(NEXT_BRUSH_TOOL),
+            editor.radius,
+            input_map.describe(BRUSH_SMALLER),
+            input_map.describe(BRUSH_LARGER),
+        ),
+        EditMode::Objects => {
+            let selected = editor.selected.and_then(|(island, index)| match &islands.regions[island].state {
+                IslandState::Ready { placed, .. } => placed.get(index),
+                _ => None,
+            });
+            match selected {
+                Some(object) => format!(
+                    "Selected {} ({:.0}°, x{:.2}), drag the handles, {} deletes",
+                    object.asset.name(),
+                    object.yaw.to_degrees().rem_euclid(360.0),
+                    object.scale,
+                    input_map.describe(DELETE_OBJECT),
+                ),
+                None => format!(
+                    "Click to place a {} ({}) or select an object",
+                    editor.asset.name(),
+                    input_map.describe(NEXT_BRUSH_TOOL),
+                ),
+            }
+        }
+        EditMode::Info => "Click an island to edit its owner, description and tags".to_string(),
+    };
+    text.0 = format!(
+        "Island editing: {}\n{} ({}): {}",
+        island,
+        editor.mode.name(),
+        input_map.describe(SWITCH_EDIT_MODE),
+        details,
+    );
+    if let Some(cell) = editor.cell {
+        let owner = islands
+            .claimed_by(cell)
+            .map_or("free".to_string(), |index| format!("part of {}", islands.regions[index].name));
+        text.0 += &format!(
+            "\nCell {}/{}/{}: {} (grid: {})",
+            cell.z,
+            cell.x,
+            cell.y,
+            owner,
+            input_map.describe(TOGGLE_ISLAND_GRID),
+        );
+    }
+    *visibility = Visibility::Inherited;
+}
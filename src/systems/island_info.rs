@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use std::fs;
+use crate::components::{IslandFormText, IslandTooltip, PersistentIsland};
+use crate::resources::{
+    Chat, EditMode, FormField, IslandEditor, IslandForm, IslandMetadata, IslandState, Islands, TaskRuntime,
+};
+use crate::resources::environment::wall_clock_seconds;
+use crate::resources::islands::format_date;
+
+// Longest text a form field takes, in characters
+const MAX_FIELD_LENGTH: usize = 200;
+const FIELD_COLOR: Color = Color::srgb(1.0, 0.95, 0.6);
+
+/// Sets up the island metadata form (right middle) and the island tooltip (next to the cursor)
+pub fn setup_island_info(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 15.0,
+            ..default()
+        },
+        TextColor(FIELD_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(35.0),
+            right: Val::Px(10.0),
+            width: Val::Px(360.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.75)),
+        Visibility::Hidden,
+        IslandFormText,
+    ));
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 13.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            max_width: Val::Px(280.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+        IslandTooltip,
+    ));
+}
+
+/// Open the metadata form of the island clicked in the info mode of island editing
+pub fn open_island_form(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    chat: Option<Res<Chat>>,
+    islands: Res<Islands>,
+    mut editor: ResMut<IslandEditor>,
+) {
+    if !editor.active || editor.mode != EditMode::Info || editor.form.is_some() {
+        return;
+    }
+    // Typing goes to the chat message while one is open
+    if chat.is_some_and(|chat| chat.draft.is_some()) || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some((island, _)) = editor.hover else {
+        return;
+    };
+
+    let metadata = &islands.regions[island].metadata;
+    editor.form = Some(IslandForm {
+        island,
+        field: FormField::default(),
+        owner: metadata.owner.clone(),
+        description: metadata.description.clone(),
+        tags: metadata.tags.join(", "),
+    });
+}
+
+/// Type into the island metadata form: Tab goes to the next field, Enter saves, Escape cancels
+/// While typing, the keys are taken from the other systems like with the chat
+pub fn type_island_form(
+    mut key_events: EventReader<KeyboardInput>,
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    task_runtime: Res<TaskRuntime>,
+    mut editor: ResMut<IslandEditor>,
+    mut islands: ResMut<Islands>,
+    mut island_query: Query<&mut PersistentIsland>,
+) {
+    // Keys pressed before the form opened are not part of it
+    let events: Vec<&KeyboardInput> = key_events.read().collect();
+    let Some(mut form) = editor.form.take() else {
+        return;
+    };
+    keyboard_input.reset_all();
+
+    for event in events.into_iter().filter(|event| event.state.is_pressed()) {
+        match &event.logical_key {
+            Key::Enter => {
+                save_island_form(&task_runtime, &mut islands, &mut island_query, &form);
+                return;
+            }
+            Key::Escape => return,
+            Key::Tab => form.field = form.field.next(),
+            Key::Backspace => {
+                form.text_mut().pop();
+            }
+            Key::Space => form.text_mut().push(' '),
+            Key::Character(text) => {
+                form.text_mut().extend(text.chars().filter(|c| !c.is_control()));
+            }
+            _ => {}
+        }
+    }
+
+    let text = form.text_mut();
+    if let Some((cut, _)) = text.char_indices().nth(MAX_FIELD_LENGTH) {
+        text.truncate(cut);
+    }
+    editor.form = Some(form);
+}
+
+// Keep the form's fields as the island's metadata: on the island, its spawned root and on disk
+fn save_island_form(
+    task_runtime: &TaskRuntime,
+    islands: &mut Islands,
+    island_query: &mut Query<&mut PersistentIsland>,
+    form: &IslandForm,
+) {
+    let island = &mut islands.regions[form.island];
+    island.metadata = IslandMetadata {
+        owner: form.owner.trim().to_string(),
+        description: form.description.trim().to_string(),
+        created_at: island.metadata.created_at.or(Some(wall_clock_seconds() as u64)),
+        tags: form
+            .tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect(),
+    };
+
+    if let IslandState::Ready { entity: Some(root), .. } = island.state {
+        if let Ok(mut component) = island_query.get_mut(root) {
+            component.owner = island.metadata.owner.clone();
+            component.description = island.metadata.description.clone();
+            component.created_at = island.metadata.created_at;
+            component.tags = island.metadata.tags.clone();
+        }
+    }
+
+    let contents = island.metadata.to_toml();
+    let path = island.metadata_path();
+    let name = island.name.clone();
+    task_runtime.spawn_blocking(move || {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, contents));
+        if let Err(e) = result {
+            warn!("Failed to save the metadata of island '{}' to {}: {}", name, path.display(), e);
+        }
+    });
+}
+
+/// Show the form with the field being typed in marked
+pub fn update_island_form_text(
+    editor: Res<IslandEditor>,
+    islands: Res<Islands>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<IslandFormText>>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    let Some(form) = &editor.form else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let island = &islands.regions[form.island];
+    let field = |field: FormField, label: &str, value: &str| {
+        if form.field == field {
+            format!("> {}: {}_", label, value)
+        } else {
+            format!("  {}: {}", label, value)
+        }
+    };
+    let created = island.metadata.created_at.map_or("when first saved".to_string(), format_date);
+    text.0 = format!(
+        "Island {}\n{}\n{}\n{}\n  Created: {}\nTab: next field, Enter: save, Escape: cancel",
+        island.name,
+        field(FormField::Owner, "Owner", &form.owner),
+        field(FormField::Description, "Description", &form.description),
+        field(FormField::Tags, "Tags (comma separated)", &form.tags),
+        created,
+    );
+    *visibility = Visibility::Inherited;
+}
+
+/// Describe the island under the cursor next to it, while the cursor is free
+pub fn update_island_tooltip(
+    editor: Res<IslandEditor>,
+    islands: Res<Islands>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    island_query: Query<&PersistentIsland>,
+    mut tooltip_query: Query<(&mut Text, &mut Node, &mut Visibility), With<IslandTooltip>>,
+) {
+    let Ok((mut text, mut node, mut visibility)) = tooltip_query.get_single_mut() else {
+        return;
+    };
+    let cursor_free = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor_options.grab_mode == CursorGrabMode::None);
+    let island = editor
+        .hover
+        .filter(|_| cursor_free && editor.form.is_none())
+        .and_then(|(index, _)| match islands.regions[index].state {
+            IslandState::Ready { entity: Some(root), .. } => island_query.get(root).ok(),
+            _ => None,
+        });
+    let (Some(island), Some((cursor, _))) = (island, editor.cursor) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let mut lines = vec![island.name.clone()];
+    if !island.owner.is_empty() {
+        lines.push(format!("Owner: {}", island.owner));
+    }
+    if !island.description.is_empty() {
+        lines.push(island.description.clone());
+    }
+    if !island.tags.is_empty() {
+        lines.push(format!("Tags: {}", island.tags.join(", ")));
+    }
+    if let Some(created_at) = island.created_at {
+        lines.push(format!("Created {}", format_date(created_at)));
+    }
+    text.0 = lines.join("\n");
+    node.left = Val::Px(cursor.x + 16.0);
+    node.top = Val::Px(cursor.y + 16.0);
+    *visibility = Visibility::Inherited;
+}
@@ -0,0 +1,409 @@
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use crate::components::{IslandObject, MainCamera, PersistentIsland};
+use crate::events::OriginShifted;
+use crate::resources::{
+    EditMode, GizmoHandle, InputMap, IslandAssetLibrary, IslandEditor, IslandState, Islands, ObjectDrag, PlacedObject,
+    TaskRuntime,
+};
+use crate::resources::input_map::DELETE_OBJECT;
+use crate::resources::islands::placed_objects_to_json;
+use crate::systems::islands::spawn_placed_object;
+
+// Gizmo size as a fraction of its distance to the camera, so it looks the same at every zoom
+const GIZMO_SCALE: f32 = 0.12;
+// How close in pixels the cursor has to be to a handle to grab it
+const HANDLE_PICK_RADIUS: f32 = 14.0;
+const MIN_OBJECT_SCALE: f32 = 0.1;
+const MAX_OBJECT_SCALE: f32 = 20.0;
+const ROTATE_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+const SCALE_COLOR: Color = Color::WHITE;
+
+// Camera queries that the gizmo systems share
+type CameraQuery<'w, 's> = Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>;
+
+/// Select placed objects, grab the selected object's gizmo handles and place new objects
+/// on a click in the objects mode of island editing
+pub fn select_island_objects(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    library: Res<IslandAssetLibrary>,
+    mut editor: ResMut<IslandEditor>,
+    mut islands: ResMut<Islands>,
+    object_query: Query<(&IslandObject, &GlobalTransform)>,
+    camera_query: CameraQuery,
+) {
+    if !editor.active || editor.mode != EditMode::Objects {
+        return;
+    }
+    // The selection goes when its island is despawned
+    if let Some((island, index)) = editor.selected {
+        if !object_query.iter().any(|(object, _)| object.island == island && object.index == index) {
+            editor.selected = None;
+        }
+    }
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Some((cursor, ray)), Ok((camera, camera_transform))) = (editor.cursor, camera_query.get_single()) else {
+        return;
+    };
+
+    // A handle of the selected object's gizmo
+    if let Some((island, index)) = editor.selected {
+        let object = placed_object(&islands, island, index).cloned();
+        let center = object_query
+            .iter()
+            .find(|(object, _)| object.island == island && object.index == index)
+            .map(|(_, transform)| transform.translation());
+        if let (Some(object), Some(center)) = (object, center) {
+            let size = center.distance(camera_transform.translation()) * GIZMO_SCALE;
+            let grabbed = gizmo_handles(center, object.yaw, size)
+                .into_iter()
+                .filter_map(|(handle, point)| {
+                    let screen = camera.world_to_viewport(camera_transform, point).ok()?;
+                    Some((handle, screen.distance(cursor)))
+                })
+                .filter(|(_, distance)| *distance < HANDLE_PICK_RADIUS)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((handle, _)) = grabbed {
+                let center_screen = camera.world_to_viewport(camera_transform, center).ok();
+                if let Some(grab) = drag_value(handle, center, ray, cursor, center_screen) {
+                    editor.drag = Some(ObjectDrag {
+                        handle,
+                        start_position: object.position,
+                        start_center: center,
+                        start_yaw: object.yaw,
+                        start_scale: object.scale,
+                        grab,
+                        grab_point: object.position,
+                        moved: false,
+                    });
+                    return;
+                }
+            }
+        }
+    }
+
+    // A placed object under the cursor, picked by the sphere around it
+    let mut nearest: Option<(f32, usize, usize, Vec3)> = None;
+    for (object, transform) in &object_query {
+        let Some(placed) = placed_object(&islands, object.island, object.index) else {
+            continue;
+        };
+        let (middle, radius) = placed.asset.bounds();
+        let scale = transform.scale().x;
+        let center = transform.translation() + Vec3::Y * middle * scale;
+        if let Some(distance) = ray_sphere(ray, center, radius * scale) {
+            if nearest.is_none_or(|(nearest_distance, ..)| distance < nearest_distance) {
+                nearest = Some((distance, object.island, object.index, transform.translation()));
+            }
+        }
+    }
+    if let Some((_, island, index, center)) = nearest {
+        let Some(object) = placed_object(&islands, island, index) else {
+            return;
+        };
+        let grab_point = editor.hover.filter(|(hover_island, _)| *hover_island == island).map(|(_, point)| point);
+        editor.selected = Some((island, index));
+        editor.drag = Some(body_drag(object, center, grab_point, false));
+        return;
+    }
+
+    // Empty terrain: a click there drops the selection, or places an object if there was none
+    if editor.selected.take().is_some() {
+        return;
+    }
+    let Some((island, position)) = editor.hover else {
+        return;
+    };
+    let IslandState::Ready { placed, entity: Some(root), .. } = &mut islands.regions[island].state else {
+        return;
+    };
+    let object = PlacedObject { asset: editor.asset, position, yaw: 0.0, scale: 1.0 };
+    spawn_placed_object(&mut commands, &library, *root, island, placed.len(), &object);
+    editor.selected = Some((island, placed.len()));
+    // Keep dragging to move it where it should go, it is saved on release
+    editor.drag = Some(body_drag(&object, Vec3::ZERO, Some(position), true));
+    placed.push(object);
+}
+
+/// Keep the world space start of a drag on the object when the render origin moves
+pub fn shift_object_drag(mut shifted: EventReader<OriginShifted>, mut editor: ResMut<IslandEditor>) {
+    for event in shifted.read() {
+        if let Some(drag) = editor.drag.as_mut() {
+            drag.start_center -= event.offset;
+        }
+    }
+}
+
+/// Move, rotate or scale the selected object while its gizmo handle is dragged,
+/// and save the island's placed objects when the mouse button is released
+pub fn drag_island_objects(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    task_runtime: Res<TaskRuntime>,
+    mut editor: ResMut<IslandEditor>,
+    mut islands: ResMut<Islands>,
+    camera_query: CameraQuery,
+    root_query: Query<&GlobalTransform, With<PersistentIsland>>,
+    mut object_query: Query<(&IslandObject, &mut Transform)>,
+) {
+    let Some((island, index)) = editor.selected else {
+        editor.drag = None;
+        return;
+    };
+    if !mouse_input.pressed(MouseButton::Left) {
+        if editor.drag.take().is_some_and(|drag| drag.moved) {
+            save_placed_objects(&task_runtime, &islands, island);
+        }
+        return;
+    }
+    let (Some(drag), Some((cursor, ray)), Ok((camera, camera_transform))) =
+        (editor.drag.as_ref(), editor.cursor, camera_query.get_single())
+    else {
+        return;
+    };
+
+    let IslandState::Ready { placed, entity: Some(root), .. } = &mut islands.regions[island].state else {
+        return;
+    };
+    let Some(object) = placed.get_mut(index) else {
+        return;
+    };
+    // The island's root only scales and translates, so its axes are the world's
+    let island_scale = root_query.get(*root).map_or(1.0, |transform| transform.scale().x);
+    let center_screen = camera.world_to_viewport(camera_transform, drag.start_center).ok();
+    let value = drag_value(drag.handle, drag.start_center, ray, cursor, center_screen);
+
+    let before = object.clone();
+    match (drag.handle, value) {
+        (GizmoHandle::Body, _) => {
+            // Follow the cursor over the terrain from where the object was grabbed
+            if let Some((_, position)) = editor.hover.filter(|(hover_island, _)| *hover_island == island) {
+                object.position = drag.start_position + position - drag.grab_point;
+            }
+        }
+        (GizmoHandle::MoveX | GizmoHandle::MoveY | GizmoHandle::MoveZ, Some(value)) => {
+            object.position = drag.start_position + handle_axis(drag.handle) * (value - drag.grab) / island_scale;
+        }
+        (GizmoHandle::Rotate, Some(angle)) => {
+            object.yaw = drag.start_yaw + angle - drag.grab;
+        }
+        (GizmoHandle::Scale, Some(distance)) => {
+            object.scale = (drag.start_scale * distance / drag.grab).clamp(MIN_OBJECT_SCALE, MAX_OBJECT_SCALE);
+        }
+        _ => {}
+    }
+    if *object == before {
+        return;
+    }
+
+    if let Some((_, mut transform)) = object_query
+        .iter_mut()
+        .find(|(placed_object, _)| placed_object.island == island && placed_object.index == index)
+    {
+        *transform = Transform::from_translation(object.position)
+            .with_rotation(Quat::from_rotation_y(object.yaw))
+            .with_scale(Vec3::splat(object.scale));
+    }
+    if let Some(drag) = editor.drag.as_mut() {
+        drag.moved = true;
+    }
+}
+
+/// Delete the selected object (Delete by default)
+pub fn delete_island_object(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    task_runtime: Res<TaskRuntime>,
+    mut editor: ResMut<IslandEditor>,
+    mut islands: ResMut<Islands>,
+    mut object_query: Query<(Entity, &mut IslandObject)>,
+) {
+    if !editor.active || !input_map.just_pressed(&keyboard_input, DELETE_OBJECT) {
+        return;
+    }
+    let Some((island, index)) = editor.selected.take() else {
+        return;
+    };
+    editor.drag = None;
+    let IslandState::Ready { placed, .. } = &mut islands.regions[island].state else {
+        return;
+    };
+    if index >= placed.len() {
+        return;
+    }
+
+    // The last object takes the deleted one's place
+    placed.swap_remove(index);
+    let last = placed.len();
+    for (entity, mut object) in object_query.iter_mut().filter(|(_, object)| object.island == island) {
+        if object.index == index {
+            commands.entity(entity).despawn_recursive();
+        } else if object.index == last {
+            object.index = index;
+        }
+    }
+    save_placed_objects(&task_runtime, &islands, island);
+}
+
+/// Draw the selected object's gizmo: arrows to move it along each axis,
+/// a ring to rotate it and a cube to scale it
+pub fn draw_object_gizmo(
+    editor: Res<IslandEditor>,
+    islands: Res<Islands>,
+    camera_query: CameraQuery,
+    object_query: Query<(&IslandObject, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    if !editor.active || editor.mode != EditMode::Objects {
+        return;
+    }
+    let (Some((island, index)), Ok((_, camera_transform))) = (editor.selected, camera_query.get_single()) else {
+        return;
+    };
+    let Some(object) = placed_object(&islands, island, index) else {
+        return;
+    };
+    let Some((_, transform)) = object_query
+        .iter()
+        .find(|(object, _)| object.island == island && object.index == index)
+    else {
+        return;
+    };
+
+    let center = transform.translation();
+    let size = center.distance(camera_transform.translation()) * GIZMO_SCALE;
+    let knob = size * 0.06;
+    for (handle, point) in gizmo_handles(center, object.yaw, size) {
+        match handle {
+            GizmoHandle::MoveX | GizmoHandle::MoveY | GizmoHandle::MoveZ => {
+                let color = handle_color(handle);
+                gizmos.arrow(center, point, color);
+                gizmos.sphere(Isometry3d::from_translation(point), knob, color);
+            }
+            GizmoHandle::Rotate => {
+                gizmos.circle(Isometry3d::new(center, Quat::from_rotation_x(FRAC_PI_2)), size * 0.7, ROTATE_COLOR);
+                gizmos.sphere(Isometry3d::from_translation(point), knob, ROTATE_COLOR);
+            }
+            GizmoHandle::Scale => {
+                gizmos.line(center, point, SCALE_COLOR);
+                gizmos.cuboid(Transform::from_translation(point).with_scale(Vec3::splat(knob * 2.0)), SCALE_COLOR);
+            }
+            GizmoHandle::Body => {}
+        }
+    }
+}
+
+// A placed object of a loaded island
+fn placed_object(islands: &Islands, island: usize, index: usize) -> Option<&PlacedObject> {
+    match &islands.regions.get(island)?.state {
+        IslandState::Ready { placed, .. } => placed.get(index),
+        _ => None,
+    }
+}
+
+// Dragging an object itself over the terrain, grabbed at a point of the terrain
+fn body_drag(object: &PlacedObject, center: Vec3, grab_point: Option<Vec3>, moved: bool) -> ObjectDrag {
+    ObjectDrag {
+        handle: GizmoHandle::Body,
+        start_position: object.position,
+        start_center: center,
+        start_yaw: object.yaw,
+        start_scale: object.scale,
+        grab: 0.0,
+        grab_point: grab_point.unwrap_or(object.position),
+        moved,
+    }
+}
+
+// Where the grabbable part of each handle is, around an object's origin in world space
+fn gizmo_handles(center: Vec3, yaw: f32, size: f32) -> [(GizmoHandle, Vec3); 5] {
+    [
+        (GizmoHandle::MoveX, center + Vec3::X * size),
+        (GizmoHandle::MoveY, center + Vec3::Y * size),
+        (GizmoHandle::MoveZ, center + Vec3::Z * size),
+        // On the ring, pointing where the object's -X points so it shows the rotation
+        (GizmoHandle::Rotate, center + Quat::from_rotation_y(yaw) * Vec3::NEG_X * size * 0.7),
+        (GizmoHandle::Scale, center + Vec3::new(-1.0, 1.0, -1.0).normalize() * size),
+    ]
+}
+
+fn handle_axis(handle: GizmoHandle) -> Vec3 {
+    match handle {
+        GizmoHandle::MoveX => Vec3::X,
+        GizmoHandle::MoveY => Vec3::Y,
+        GizmoHandle::MoveZ => Vec3::Z,
+        _ => Vec3::ZERO,
+    }
+}
+
+fn handle_color(handle: GizmoHandle) -> Color {
+    match handle {
+        GizmoHandle::MoveX => Color::srgb(0.9, 0.2, 0.2),
+        GizmoHandle::MoveY => Color::srgb(0.2, 0.85, 0.2),
+        GizmoHandle::MoveZ => Color::srgb(0.25, 0.4, 1.0),
+        _ => SCALE_COLOR,
+    }
+}
+
+// What the cursor says about a handle being dragged: the distance along its axis (move),
+// the angle around the vertical through the object (rotate) or the distance on screen
+// from the object (scale). Dragging compares it with its value when the handle was grabbed
+fn drag_value(handle: GizmoHandle, center: Vec3, ray: Ray3d, cursor: Vec2, center_screen: Option<Vec2>) -> Option<f32> {
+    match handle {
+        GizmoHandle::MoveX | GizmoHandle::MoveY | GizmoHandle::MoveZ => {
+            // Closest point of the axis line to the cursor ray
+            let axis = handle_axis(handle);
+            let direction = *ray.direction;
+            let offset = center - ray.origin;
+            let b = axis.dot(direction);
+            let denominator = 1.0 - b * b;
+            // Looking along the axis, moving along it is undefined
+            (denominator > 1e-4).then(|| (b * direction.dot(offset) - axis.dot(offset)) / denominator)
+        }
+        GizmoHandle::Rotate => {
+            let distance = ray.intersect_plane(center, InfinitePlane3d::new(Vec3::Y))?;
+            let around = ray.get_point(distance) - center;
+            Some((-around.z).atan2(around.x))
+        }
+        GizmoHandle::Scale => center_screen.map(|center| center.distance(cursor).max(1.0)),
+        GizmoHandle::Body => None,
+    }
+}
+
+// Distance along a ray to where it enters a sphere
+fn ray_sphere(ray: Ray3d, center: Vec3, radius: f32) -> Option<f32> {
+    let offset = ray.origin - center;
+    let b = offset.dot(*ray.direction);
+    let discriminant = b * b - (offset.length_squared() - radius * radius);
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [-b - root, -b + root].into_iter().find(|distance| *distance >= 0.0)
+}
+
+// Write an island's placed objects to its data directory, off the main thread
+fn save_placed_objects(task_runtime: &TaskRuntime, islands: &Islands, index: usize) {
+    let island = &islands.regions[index];
+    let IslandState::Ready { region, placed, .. } = &island.state else {
+        return;
+    };
+
+    let json = placed_objects_to_json(placed, region.water_height);
+    let path = island.objects_path();
+    let name = island.name.clone();
+    task_runtime.spawn_blocking(move || {
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, json));
+        if let Err(e) = result {
+            warn!("Failed to save the objects of island '{}' to {}: {}", name, path.display(), e);
+        }
+    });
+}
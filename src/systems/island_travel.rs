@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{IslandDistanceText, IslandListPanel, MainCamera, TeleportButton};
+use crate::events::FlyTo;
+use crate::osm::TileId;
+use crate::resources::{InputMap, Islands};
+use crate::resources::input_map::TOGGLE_ISLAND_LIST;
+use crate::resources::launch_options::camera_height_for_zoom;
+use crate::systems::measurement::format_distance;
+use crate::utils::geo::{geodesic_distance_m, GeoPos};
+
+// Seconds the flight to an island takes
+const TELEPORT_FLIGHT_DURATION: f32 = 3.0;
+
+/// Open or close the island list (J by default), nearest island first
+/// The cursor is released while the list is open so the buttons can be clicked
+pub fn toggle_island_list(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    islands: Res<Islands>,
+    panel_query: Query<Entity, With<IslandListPanel>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_ISLAND_LIST) {
+        return;
+    }
+
+    if let Ok(panel) = panel_query.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let camera_geo = GeoPos::from_translation(camera.translation);
+    let mut order: Vec<usize> = (0..islands.regions.len()).collect();
+    order.sort_by(|&a, &b| {
+        let distance = |island: usize| island_distance_m(islands.regions[island].tile, camera_geo);
+        distance(a).total_cmp(&distance(b))
+    });
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(110.0),
+                left: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            IslandListPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new(if order.is_empty() { "No islands in the settings file" } else { "Islands" }),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+            ));
+            for island in order {
+                panel
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceBetween,
+                        column_gap: Val::Px(10.0),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        row.spawn((
+                            Text::new(""),
+                            TextFont {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                            IslandDistanceText { island },
+                        ));
+                        row.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+                            TeleportButton { island },
+                        ))
+                        .with_child((
+                            Text::new("Teleport"),
+                            TextFont {
+                                font_size: 14.0,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// Keep the distances on the island list up to date as the camera moves
+pub fn update_island_distances(
+    islands: Res<Islands>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut text_query: Query<(&IslandDistanceText, &mut Text)>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let camera_geo = GeoPos::from_translation(camera.translation);
+    for (row, mut text) in text_query.iter_mut() {
+        let Some(island) = islands.regions.get(row.island) else {
+            continue;
+        };
+        let label = format!("{}  {}", island.name, format_distance(island_distance_m(island.tile, camera_geo)));
+        if text.0 != label {
+            text.0 = label;
+        }
+    }
+}
+
+/// Fly the camera over the middle of an island when its Teleport button is clicked, high enough
+/// to see the whole island
+pub fn handle_teleport_buttons(
+    islands: Res<Islands>,
+    button_query: Query<(&Interaction, &TeleportButton), Changed<Interaction>>,
+    mut fly_to_events: EventWriter<FlyTo>,
+) {
+    for (interaction, button) in button_query.iter() {
+        let Some(island) = islands.regions.get(button.island).filter(|_| *interaction == Interaction::Pressed) else {
+            continue;
+        };
+        let (lat, lon) = tile_center(island.tile);
+        let target = GeoPos::new(lat, lon).world();
+        info!("Teleporting to island '{}'", island.name);
+        fly_to_events.send(FlyTo {
+            target: Vec3::new(target.x as f32, camera_height_for_zoom(island.tile.z), target.y as f32),
+            duration: TELEPORT_FLIGHT_DURATION,
+        });
+    }
+}
+
+fn tile_center(tile: TileId) -> (f64, f64) {
+    let bounds = tile.bounds();
+    ((bounds.north + bounds.south) / 2.0, (bounds.west + bounds.east) / 2.0)
+}
+
+fn island_distance_m(tile: TileId, from: GeoPos) -> f64 {
+    geodesic_distance_m((from.lat, from.lon), tile_center(tile))
+}
@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::path::Path;
+use crate::components::{IslandObject, IslandTerrain, MainCamera, PersistentIsland};
+use crate::opensim::{load_region, Heightmap, PrimShape, Region};
+use crate::osm::TileId;
+use crate::resources::{GroundHeight, IslandAssetLibrary, IslandMetadata, IslandState, Islands, PlacedObject, TaskRuntime};
+use crate::resources::islands::placed_objects_from_json;
+use crate::utils::geo::{tile_world_origin, tile_world_size};
+
+// Depth below the water of the level terrain given to islands without one,
+// so it stays hidden under the map until it is sculpted
+const FLAT_TERRAIN_DEPTH: f32 = 0.5;
+
+/// Load and spawn the content of the islands the camera comes near, and despawn it when the camera leaves
+/// Regions are read from disk on a background thread the first time, and kept after that
+pub fn update_islands(
+    mut commands: Commands,
+    task_runtime: Res<TaskRuntime>,
+    mut islands: ResMut<Islands>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    library: Res<IslandAssetLibrary>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let loaded = std::mem::take(&mut *islands.loaded.lock());
+    for (index, result) in loaded {
+        let island = &mut islands.regions[index];
+        island.state = match result {
+            Ok((region, placed)) => {
+                info!("Island '{}': {} objects, {} placed", island.name, region.objects.len(), placed.len());
+                IslandState::Ready { region, placed, entity: None }
+            }
+            Err(e) => {
+                warn!("Island '{}' couldn't be loaded from {}: {}", island.name, island.source.display(), e);
+                IslandState::Failed
+            }
+        };
+    }
+
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let (spawn_distance, despawn_distance) = (islands.spawn_distance, islands.despawn_distance);
+    let shared_loaded = islands.loaded.clone();
+    for (index, island) in islands.regions.iter_mut().enumerate() {
+        // Distance from the camera to the middle of the island's tile, in tile sizes
+        let size = tile_world_size(island.tile.z);
+        let center = tile_world_origin(island.tile) + size / 2.0;
+        let distance = camera.translation.distance(Vec3::new(center.x as f32, 0.0, center.y as f32)) as f64 / size;
+
+        match &mut island.state {
+            IslandState::Idle if distance < spawn_distance => {
+                let source = island.source.clone();
+                let terrain_path = island.terrain_path();
+                let objects_path = island.objects_path();
+                let loaded = shared_loaded.clone();
+                task_runtime.spawn_blocking(move || {
+                    let result = load_island(&source, &terrain_path, &objects_path).map_err(|e| format!("{:#}", e));
+                    loaded.lock().push((index, result));
+                });
+                island.state = IslandState::Loading;
+            }
+            IslandState::Ready { region, placed, entity: entity @ None } if distance < spawn_distance => {
+                let component = persistent_island(&island.name, &island.metadata);
+                let root = spawn_island(&mut commands, &mut meshes, &mut materials, index, component, island.tile, region);
+                for (object_index, object) in placed.iter().enumerate() {
+                    spawn_placed_object(&mut commands, &library, root, index, object_index, object);
+                }
+                *entity = Some(root);
+            }
+            IslandState::Ready { entity: entity @ Some(_), .. } if distance > despawn_distance => {
+                if let Some(root) = entity.take() {
+                    commands.entity(root).despawn_recursive();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Measure the height of the islands' terrain under the camera, which keeps the camera above it
+/// Elsewhere, and where the terrain is under the water, the ground is the map at 0
+pub fn measure_island_ground(
+    islands: Res<Islands>,
+    mut ground: ResMut<GroundHeight>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    terrain_query: Query<(&IslandTerrain, &GlobalTransform)>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let mut height = 0.0f32;
+    for (terrain, transform) in &terrain_query {
+        let IslandState::Ready { region, .. } = &islands.regions[terrain.island].state else {
+            continue;
+        };
+        let Some(heightmap) = &region.terrain else {
+            continue;
+        };
+        // In the terrain mesh's coordinates north is -Z and heights are relative to the water
+        let local = transform.affine().inverse().transform_point3(camera.translation);
+        let Some(terrain_height) = heightmap.height_at(local.x, -local.z) else {
+            continue;
+        };
+        let world = transform.transform_point(Vec3::new(local.x, terrain_height - region.water_height, local.z));
+        height = height.max(world.y);
+    }
+
+    if ground.under_camera != height {
+        ground.under_camera = height;
+    }
+}
+
+// Read an island's region, with the terrain sculpted and the objects placed in island editing mode
+// Islands without terrain get a level one, so there is something to sculpt
+fn load_island(source: &Path, terrain_path: &Path, objects_path: &Path) -> Result<(Region, Vec<PlacedObject>), anyhow::Error> {
+    let mut region = load_region(source)?;
+
+    if terrain_path.exists() {
+        let terrain = Heightmap::from_r32(&fs::read(terrain_path)?).map_err(|e| anyhow::anyhow!("{}: {}", terrain_path.display(), e))?;
+        region.size = terrain.size as f32;
+        region.terrain = Some(terrain);
+    }
+    if region.terrain.is_none() {
+        region.terrain = Some(Heightmap::flat(region.size as usize, region.water_height - FLAT_TERRAIN_DEPTH));
+    }
+
+    let placed = if objects_path.exists() {
+        placed_objects_from_json(&fs::read_to_string(objects_path)?, region.water_height)
+            .map_err(|e| anyhow::anyhow!("{}: {}", objects_path.display(), e))?
+    } else {
+        Vec::new()
+    };
+
+    Ok((region, placed))
+}
+
+// Spawn a region's terrain and objects so the region fills its island's tile
+// The region keeps its proportions: heights and object sizes are scaled like its width
+fn spawn_island(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    index: usize,
+    island: PersistentIsland,
+    tile: TileId,
+    region: &Region,
+) -> Entity {
+    let tile_size = tile_world_size(tile.z);
+    let origin = tile_world_origin(tile);
+    let scale = (tile_size / region.size as f64) as f32;
+    // Region coordinates start at the southwest corner, the tile's at the northwest corner
+    let southwest = Vec3::new(origin.x as f32, 0.0, (origin.y + tile_size) as f32);
+
+    let shape_meshes: HashMap<PrimShape, Handle<Mesh>> = [
+        (PrimShape::Box, meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        (PrimShape::Cylinder, meshes.add(Cylinder::new(0.5, 1.0))),
+        (PrimShape::Sphere, meshes.add(Sphere::new(0.5))),
+        (PrimShape::Torus, meshes.add(Torus::new(0.25, 0.5))),
+        // Prims are extruded along their Z axis, which is the viewer's Y
+        (PrimShape::Prism, meshes.add(
+            Extrusion::new(Triangle2d::new(Vec2::new(-0.5, -0.5), Vec2::new(0.5, -0.5), Vec2::new(0.0, 0.5)), 1.0)
+                .mesh()
+                .build()
+                .rotated_by(Quat::from_rotation_x(-FRAC_PI_2)),
+        )),
+    ]
+    .into();
+    let mut prim_materials: HashMap<[u8; 4], Handle<StandardMaterial>> = HashMap::new();
+
+    let root = commands
+        .spawn((
+            Transform::from_translation(southwest).with_scale(Vec3::splat(scale)),
+            Visibility::default(),
+            Name::new(format!("Island {}", island.name)),
+            island,
+        ))
+        .id();
+
+    if let Some(terrain) = &region.terrain {
+        let terrain = commands
+            .spawn((
+                Mesh3d(meshes.add(terrain.mesh(region.water_height))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    perceptual_roughness: 0.95,
+                    ..default()
+                })),
+                Transform::default(),
+                IslandTerrain { island: index },
+            ))
+            .id();
+        commands.entity(root).add_child(terrain);
+    }
+
+    for object in &region.objects {
+        let group = commands
+            .spawn((
+                Transform::from_translation(object.position - Vec3::Y * region.water_height).with_rotation(object.rotation),
+                Visibility::default(),
+                Name::new(object.name.clone()),
+            ))
+            .id();
+        commands.entity(root).add_child(group);
+
+        // Fully transparent prims are invisible in OpenSim too
+        for prim in object.prims.iter().filter(|prim| prim.color.alpha() > 0.0) {
+            let rgba = prim.color.to_srgba().to_u8_array();
+            let material = prim_materials
+                .entry(rgba)
+                .or_insert_with(|| {
+                    materials.add(StandardMaterial {
+                        base_color: prim.color,
+                        alpha_mode: if rgba[3] < 255 { AlphaMode::Blend } else { AlphaMode::Opaque },
+                        ..default()
+                    })
+                })
+                .clone();
+
+            let part = commands
+                .spawn((
+                    Mesh3d(shape_meshes[&prim.shape].clone()),
+                    MeshMaterial3d(material),
+                    Transform::from_translation(prim.offset)
+                        .with_rotation(prim.rotation)
+                        .with_scale(prim.scale),
+                ))
+                .id();
+            commands.entity(group).add_child(part);
+        }
+    }
+
+    root
+}
+
+/// The component on an island's root, carrying its metadata
+pub fn persistent_island(name: &str, metadata: &IslandMetadata) -> PersistentIsland {
+    PersistentIsland {
+        name: name.to_string(),
+        owner: metadata.owner.clone(),
+        description: metadata.description.clone(),
+        created_at: metadata.created_at,
+        tags: metadata.tags.clone(),
+    }
+}
+
+/// Spawn an object from the asset library as a child of its island's root
+pub fn spawn_placed_object(
+    commands: &mut Commands,
+    library: &IslandAssetLibrary,
+    root: Entity,
+    island: usize,
+    index: usize,
+    object: &PlacedObject,
+) -> Entity {
+    let entity = commands
+        .spawn((
+            Transform::from_translation(object.position)
+                .with_rotation(Quat::from_rotation_y(object.yaw))
+                .with_scale(Vec3::splat(object.scale)),
+            Visibility::default(),
+            IslandObject { island, index },
+            Name::new(format!("Placed {}", object.asset.name())),
+        ))
+        .with_children(|parent| {
+            for part in library.parts.get(&object.asset).into_iter().flatten() {
+                parent.spawn((Mesh3d(part.mesh.clone()), MeshMaterial3d(part.material.clone()), part.transform));
+            }
+        })
+        .id();
+    commands.entity(root).add_child(entity);
+    entity
+}
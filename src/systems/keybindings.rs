@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{KeybindingsPage, RebindButton, ResetKeybindingsButton};
+use crate::resources::{InputMap, RebindState};
+use crate::resources::input_map::{is_bindable, TOGGLE_KEYBINDINGS};
+
+/// Open or close the keybindings page
+/// The cursor is released while the page is open so the buttons can be clicked
+pub fn toggle_keybindings_page(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut rebind_state: ResMut<RebindState>,
+    page_query: Query<Entity, With<KeybindingsPage>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // Keys pressed while waiting for a rebind belong to the rebind
+    if rebind_state.waiting.is_some() || !input_map.just_pressed(&keyboard_input, TOGGLE_KEYBINDINGS) {
+        return;
+    }
+
+    if let Ok(page) = page_query.get_single() {
+        commands.entity(page).despawn_recursive();
+        rebind_state.waiting = None;
+        return;
+    }
+
+    spawn_keybindings_page(&mut commands, &input_map);
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+// Build the page: one button per registered action, plus a reset button
+fn spawn_keybindings_page(commands: &mut Commands, input_map: &InputMap) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(110.0),
+                left: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            KeybindingsPage,
+        ))
+        .with_children(|page| {
+            page.spawn((
+                Text::new("Keybindings - click an action, then press a key (Esc cancels)"),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+            ));
+
+            for action in &input_map.actions {
+                page.spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+                    RebindButton {
+                        action: action.name.clone(),
+                    },
+                ))
+                .with_child((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                ));
+            }
+
+            page.spawn((
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    margin: UiRect::top(Val::Px(6.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.4, 0.1, 0.1, 0.8)),
+                ResetKeybindingsButton,
+            ))
+            .with_child((
+                Text::new("Reset to defaults"),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Start waiting for a key when an action is clicked, or restore all defaults
+pub fn handle_keybinding_buttons(
+    mut input_map: ResMut<InputMap>,
+    mut rebind_state: ResMut<RebindState>,
+    rebind_buttons: Query<(&Interaction, &RebindButton), Changed<Interaction>>,
+    reset_buttons: Query<&Interaction, (Changed<Interaction>, With<ResetKeybindingsButton>)>,
+) {
+    for (interaction, button) in rebind_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            rebind_state.waiting = Some(button.action.clone());
+        }
+    }
+
+    if reset_buttons.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        rebind_state.waiting = None;
+        for action in input_map.actions.iter_mut() {
+            action.keys = action.default_keys.clone();
+        }
+        save_bindings(&input_map);
+    }
+}
+
+/// Bind the next key press to the action waiting for it and save the bindings
+pub fn capture_rebind_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut input_map: ResMut<InputMap>,
+    mut rebind_state: ResMut<RebindState>,
+) {
+    let Some(action) = rebind_state.waiting.clone() else {
+        return;
+    };
+
+    for key in keyboard_input.get_just_pressed() {
+        if *key == KeyCode::Escape {
+            rebind_state.waiting = None;
+            return;
+        }
+
+        if is_bindable(*key) {
+            input_map.rebind(&action, vec![*key]);
+            rebind_state.waiting = None;
+            save_bindings(&input_map);
+            return;
+        }
+    }
+}
+
+// Persist bindings, a failed save only loses them for the next run
+fn save_bindings(input_map: &InputMap) {
+    if let Err(e) = input_map.save() {
+        warn!("Failed to save keybindings: {}", e);
+    }
+}
+
+/// Show the current key for every action on the keybindings page
+pub fn update_keybinding_labels(
+    input_map: Res<InputMap>,
+    rebind_state: Res<RebindState>,
+    button_query: Query<(&RebindButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (button, children) in button_query.iter() {
+        let label = if rebind_state.waiting.as_deref() == Some(button.action.as_str()) {
+            format!("{}: press a key...", button.action.replace('_', " "))
+        } else {
+            format!("{}: {}", button.action.replace('_', " "), input_map.describe(&button.action))
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                if text.0 != label {
+                    text.0 = label.clone();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,728 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use std::collections::HashMap;
+use crate::components::{
+    AttributionBar, AttributionLink, LayerAction, LayerItem, LayerOpacityFill, LayerOpacitySlider, LayerOpacityText,
+    LayerPanel, LayerPanelButton, LayerPanelCollapse, PanelLayer, TileTextureBytes, TimelineKnob, TimelinePanel,
+    TimelineSlider, TimelineStep, TimelineText,
+};
+use crate::events::SettingsChanged;
+use crate::osm::TileId;
+use crate::overlays::street_labels::StreetLabel;
+use crate::resources::{
+    ImageryLayers, InputMap, LaunchOptions, LayerManager, OSMData, ProviderComparison, RebindState, TaskRuntime, TileAtlas,
+    TimelineScrub, UserSettings, BASE_LAYER_ID, SATELLITE_LAYER_ID,
+};
+use crate::resources::input_map::{TOGGLE_LAYERS, TOGGLE_PHOTO_VIEW};
+use crate::resources::user_settings::Attribution;
+use crate::systems::tiles::{composite_tile, rewrite_in_atlas};
+use crate::utils::browser::open_url;
+
+// Opacity steps of the layer sliders
+const OPACITY_STEP: f32 = 0.1;
+const SECTION_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
+const SLIDER_TRACK_COLOR: Color = Color::srgba(0.3, 0.3, 0.3, 0.8);
+const SLIDER_FILL_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+const LINK_COLOR: Color = Color::srgb(0.1, 0.3, 0.7);
+
+type LayerItemQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, Ref<'static, LayerItem>, &'static mut Transform, &'static mut Visibility, Option<&'static Children>, Has<StreetLabel>),
+>;
+
+/// Point the base map layer at the tile server picked in the settings
+/// A --provider on the command line is used instead until another one is picked in the settings
+/// menu; it is never written to the settings file
+pub fn sync_base_layer(
+    mut changed_events: EventReader<SettingsChanged>,
+    settings: Res<UserSettings>,
+    launch: Res<LaunchOptions>,
+    mut picked_server: Local<Option<String>>,
+    mut layers: ResMut<ImageryLayers>,
+) {
+    if changed_events.read().count() == 0 {
+        return;
+    }
+
+    // Only a change of the tile server itself replaces the one from the command line
+    let first = picked_server.is_none();
+    if picked_server.as_deref() == Some(settings.tile_server.as_str()) {
+        return;
+    }
+    *picked_server = Some(settings.tile_server.clone());
+    let tile_server = match (&launch.tile_server, first) {
+        (Some(tile_server), true) => tile_server,
+        _ => &settings.tile_server,
+    };
+
+    let Some(base) = layers.layers.iter().find(|layer| layer.id == BASE_LAYER_ID) else {
+        return;
+    };
+    if base.url == *tile_server {
+        return;
+    }
+
+    info!("Tile server: {}", tile_server);
+    layers.set_base_server(tile_server);
+}
+
+/// Switch between the map and photo imagery (P by default) by showing or hiding the photo layer
+pub fn toggle_photo_view(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut layers: ResMut<ImageryLayers>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_PHOTO_VIEW) {
+        return;
+    }
+    if let Some(layer) = layers.get_mut(SATELLITE_LAYER_ID) {
+        layer.visible = !layer.visible;
+        info!("Photo view: {}", if layer.visible { "ON" } else { "OFF" });
+    }
+}
+
+/// Rebuild the loaded tiles from the new layer stack when the imagery layers change
+/// Tiles with every layer in the texture cache are recomposited in place, the others
+/// are despawned and loaded again, downloading only the layers that aren't cached
+pub fn apply_imagery_layers(
+    mut commands: Commands,
+    layers: Res<ImageryLayers>,
+    mut osm_data: ResMut<OSMData>,
+    atlas: Res<TileAtlas>,
+    mut images: ResMut<Assets<Image>>,
+    task_runtime: Res<TaskRuntime>,
+) {
+    if !layers.is_changed() {
+        return;
+    }
+    let sources = layers.sources();
+    if sources == osm_data.layers {
+        return;
+    }
+
+    // Imagery of removed layers and layers switched to another server or date is stale,
+    // hidden layers keep theirs for when they are shown again
+    let stale: Vec<u32> = osm_data
+        .layers
+        .iter()
+        .filter(|old| !layers.layers.iter().any(|layer| layer.id == old.id && layer.source_url() == old.url))
+        .map(|old| old.id)
+        .collect();
+    for id in stale {
+        osm_data.texture_cache.remove_layer(id);
+    }
+    osm_data.layers = sources;
+
+    let tiles = std::mem::take(&mut osm_data.tiles);
+    let background_tiles = std::mem::take(&mut osm_data.background_tiles);
+    let mut reloaded = 0;
+    for (is_background, ((x, y, z), entity)) in tiles
+        .into_iter()
+        .map(|tile| (false, tile))
+        .chain(background_tiles.into_iter().map(|tile| (true, tile)))
+    {
+        // Fallback tiles have no atlas layer, they get another chance to load
+        let recomposited = atlas.slots.contains_key(&entity)
+            && composite_tile(&osm_data, &HashMap::new(), &images, (x, y, z)).is_some_and(|(tile_image, bytes, _)| {
+                commands.entity(entity).insert(TileTextureBytes(atlas.texture_bytes(bytes)));
+                rewrite_in_atlas((&atlas, &task_runtime), (entity, TileId::new(x, y, z)), &tile_image, &mut images)
+            });
+
+        if recomposited {
+            if is_background {
+                osm_data.background_tiles.insert((x, y, z), entity);
+            } else {
+                osm_data.tiles.insert((x, y, z), entity);
+            }
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+        let loaded_tiles = if is_background {
+            &mut osm_data.loaded_background_tiles
+        } else {
+            &mut osm_data.loaded_tiles
+        };
+        loaded_tiles.remove(&(x, y, z));
+        reloaded += 1;
+    }
+
+    info!(
+        "Imagery layers: {} ({} tiles to reload)",
+        layers
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .map(|layer| format!("{} {:.0}%", layer.name, layer.opacity * 100.0))
+            .collect::<Vec<_>>()
+            .join(", "),
+        reloaded
+    );
+}
+
+/// Open or close the layer panel (L by default)
+/// The cursor is released while the panel is open so the buttons can be clicked
+pub fn toggle_layer_panel(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    rebind_state: Res<RebindState>,
+    panel_query: Query<Entity, With<LayerPanel>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // Keys pressed while waiting for a rebind belong to the rebind
+    if rebind_state.waiting.is_some() || !input_map.just_pressed(&keyboard_input, TOGGLE_LAYERS) {
+        return;
+    }
+
+    if let Ok(panel) = panel_query.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+
+    // The rows are added by update_layer_panel
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(110.0),
+            left: Val::Px(10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+        LayerPanel,
+    ));
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// Show or hide a layer or change its place in the stack when its button is clicked,
+/// and fold the panel up or out with the title button
+pub fn handle_layer_panel_buttons(
+    mut layers: ResMut<ImageryLayers>,
+    mut manager: ResMut<LayerManager>,
+    button_query: Query<(&Interaction, &LayerPanelButton), Changed<Interaction>>,
+    collapse_query: Query<&Interaction, (Changed<Interaction>, With<LayerPanelCollapse>)>,
+) {
+    if collapse_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        manager.collapsed = !manager.collapsed;
+    }
+
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match (button.layer, button.action) {
+            (PanelLayer::Imagery(id), LayerAction::ToggleVisible) => {
+                if let Some(layer) = layers.get_mut(id) {
+                    layer.visible = !layer.visible;
+                }
+            }
+            (PanelLayer::Imagery(id), LayerAction::Move(step)) => layers.move_layer(id, step),
+            (PanelLayer::Overlay(name), LayerAction::ToggleVisible) => {
+                if let Some(layer) = manager.get_mut(name) {
+                    layer.visible = !layer.visible;
+                }
+            }
+            (PanelLayer::Overlay(name), LayerAction::Move(step)) => manager.move_layer(name, step),
+        }
+    }
+}
+
+/// Set a layer's opacity while its slider is dragged
+/// The opacity moves in whole steps, so the imagery isn't recomposited for every pixel the
+/// cursor moves
+pub fn handle_layer_opacity_sliders(
+    mut layers: ResMut<ImageryLayers>,
+    mut manager: ResMut<LayerManager>,
+    slider_query: Query<(&Interaction, &RelativeCursorPosition, &LayerOpacitySlider)>,
+) {
+    for (interaction, cursor, slider) in slider_query.iter() {
+        let (Interaction::Pressed, Some(position)) = (*interaction, cursor.normalized) else {
+            continue;
+        };
+        let opacity = (position.x.clamp(0.0, 1.0) / OPACITY_STEP).round() * OPACITY_STEP;
+        // Looked up first, writing the same opacity would still mark the layers changed
+        if panel_layer_state(&layers, &manager, slider.0).is_none_or(|(_, current)| current == opacity) {
+            continue;
+        }
+
+        match slider.0 {
+            PanelLayer::Imagery(id) => {
+                if let Some(layer) = layers.get_mut(id) {
+                    layer.opacity = opacity;
+                }
+            }
+            PanelLayer::Overlay(name) => {
+                if let Some(layer) = manager.get_mut(name) {
+                    layer.opacity = opacity;
+                }
+            }
+        }
+    }
+}
+
+// Visibility and opacity of a layer on the panel
+fn panel_layer_state(layers: &ImageryLayers, manager: &LayerManager, layer: PanelLayer) -> Option<(bool, f32)> {
+    match layer {
+        PanelLayer::Imagery(id) => layers.layers.iter().find(|layer| layer.id == id).map(|layer| (layer.visible, layer.opacity)),
+        PanelLayer::Overlay(name) => manager.get(name).map(|layer| (layer.visible, layer.opacity)),
+    }
+}
+
+/// Fill the layer panel with a row per layer, top of the stack first: the overlay layers,
+/// then the imagery layers under them
+/// The rows are rebuilt whenever the layers change, except while an opacity slider is held;
+/// update_layer_opacity keeps the sliders up to date meanwhile
+pub fn update_layer_panel(
+    mut commands: Commands,
+    layers: Res<ImageryLayers>,
+    manager: Res<LayerManager>,
+    panel_query: Query<(Entity, Ref<LayerPanel>)>,
+    slider_query: Query<&Interaction, With<LayerOpacitySlider>>,
+    mut stale: Local<bool>,
+) {
+    let Ok((panel, marker)) = panel_query.get_single() else {
+        return;
+    };
+    *stale |= layers.is_changed() || manager.is_changed() || marker.is_added();
+    if !*stale || slider_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+    *stale = false;
+
+    commands.entity(panel).despawn_descendants().with_children(|panel| {
+        panel
+            .spawn((
+                Button,
+                Node {
+                    padding: UiRect::bottom(Val::Px(4.0)),
+                    ..default()
+                },
+                LayerPanelCollapse,
+            ))
+            .with_child((
+                Text::new(if manager.collapsed { "Layers [+]" } else { "Layers [-]" }),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+            ));
+        if manager.collapsed {
+            return;
+        }
+
+        spawn_section_title(panel, "Overlays - top first");
+        for layer in manager.layers.iter().rev() {
+            spawn_layer_row(panel, PanelLayer::Overlay(layer.name), layer.name, (layer.visible, layer.opacity));
+        }
+        spawn_section_title(panel, "Imagery - top of the stack first");
+        for layer in layers.layers.iter().rev().filter(|layer| layer.listed) {
+            spawn_layer_row(panel, PanelLayer::Imagery(layer.id), &layer.name, (layer.visible, layer.opacity));
+        }
+    });
+}
+
+fn spawn_section_title(panel: &mut ChildBuilder, title: &str) {
+    panel.spawn((
+        Text::new(title),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(SECTION_COLOR),
+    ));
+}
+
+fn spawn_layer_row(panel: &mut ChildBuilder, layer: PanelLayer, name: &str, (visible, opacity): (bool, f32)) {
+    panel
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(6.0),
+            ..default()
+        })
+        .with_children(|row| {
+            // Visibility checkbox, filled while the layer is shown
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(14.0),
+                    height: Val::Px(14.0),
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BorderColor(Color::WHITE),
+                BackgroundColor(if visible { SLIDER_FILL_COLOR } else { Color::NONE }),
+                LayerPanelButton { layer, action: LayerAction::ToggleVisible },
+            ));
+            row.spawn((
+                Text::new(name),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Node {
+                    width: Val::Px(110.0),
+                    ..default()
+                },
+            ));
+
+            // Clicking or dragging on the track sets the opacity, transparent on the left
+            row.spawn((
+                Button,
+                Node {
+                    width: Val::Px(80.0),
+                    height: Val::Px(10.0),
+                    ..default()
+                },
+                BackgroundColor(SLIDER_TRACK_COLOR),
+                RelativeCursorPosition::default(),
+                LayerOpacitySlider(layer),
+            ))
+            .with_child((
+                Node {
+                    width: Val::Percent(opacity * 100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(SLIDER_FILL_COLOR),
+                LayerOpacityFill(layer),
+            ));
+            row.spawn((
+                Text::new(format!("{:.0}%", opacity * 100.0)),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Node {
+                    min_width: Val::Px(40.0),
+                    ..default()
+                },
+                LayerOpacityText(layer),
+            ));
+
+            spawn_layer_button(row, layer, LayerAction::Move(1), "Up");
+            spawn_layer_button(row, layer, LayerAction::Move(-1), "Down");
+        });
+}
+
+fn spawn_layer_button(row: &mut ChildBuilder, layer: PanelLayer, action: LayerAction, label: &str) {
+    row.spawn((
+        Button,
+        Node {
+            padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+        LayerPanelButton { layer, action },
+    ))
+    .with_child((
+        Text::new(label),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+    ));
+}
+
+/// Move the opacity sliders and their texts along with the layers' opacity
+pub fn update_layer_opacity(
+    layers: Res<ImageryLayers>,
+    manager: Res<LayerManager>,
+    mut fill_query: Query<(&mut Node, &LayerOpacityFill)>,
+    mut text_query: Query<(&mut Text, &LayerOpacityText)>,
+) {
+    if !layers.is_changed() && !manager.is_changed() {
+        return;
+    }
+
+    for (mut node, fill) in fill_query.iter_mut() {
+        if let Some((_, opacity)) = panel_layer_state(&layers, &manager, fill.0) {
+            node.width = Val::Percent(opacity * 100.0);
+        }
+    }
+    for (mut text, label) in text_query.iter_mut() {
+        if let Some((_, opacity)) = panel_layer_state(&layers, &manager, label.0) {
+            text.0 = format!("{:.0}%", opacity * 100.0);
+        }
+    }
+}
+
+/// Show, hide, fade and lift the overlays with their layers
+/// Layers higher up the stack are lifted over the lower ones, so they are drawn on top where
+/// overlays cross; street labels only show near the ground, see update_street_label_visibility
+pub fn apply_overlay_layers(
+    manager: Res<LayerManager>,
+    mut item_query: LayerItemQuery,
+    material_query: Query<&MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, item, mut transform, mut visibility, children, is_label) in item_query.iter_mut() {
+        let Some(layer) = manager.get(item.layer) else {
+            continue;
+        };
+        // Overlays put their own height back when they are redrawn
+        let height = item.elevation + manager.lift(item.layer);
+        if transform.translation.y != height {
+            transform.translation.y = height;
+        }
+
+        if !manager.is_changed() && !item.is_added() {
+            continue;
+        }
+        if !is_label {
+            visibility.set_if_neq(if layer.visible { Visibility::Inherited } else { Visibility::Hidden });
+        }
+
+        // Track segments carry the material of a GPX track, the other overlays their own
+        let meshes = std::iter::once(entity).chain(children.into_iter().flat_map(|children| children.iter().copied()));
+        for mesh in meshes {
+            let Some(material) = material_query.get(mesh).ok().and_then(|material| materials.get_mut(&material.0)) else {
+                continue;
+            };
+            material.base_color.set_alpha(layer.opacity);
+            // Textured labels blend anyway, plain lines only while see-through
+            if material.base_color_texture.is_none() {
+                material.alpha_mode = if layer.opacity < 1.0 { AlphaMode::Blend } else { AlphaMode::Opaque };
+            }
+        }
+    }
+}
+
+/// Spawn the imagery timeline (top center, under the time of day slider), hidden until a layer
+/// with a date in its URL is visible
+pub fn setup_imagery_timeline(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(90.0),
+                left: Val::Percent(50.0),
+                margin: UiRect::left(Val::Px(-220.0)),
+                width: Val::Px(440.0),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+            Visibility::Hidden,
+            TimelinePanel,
+        ))
+        .with_children(|bar| {
+            bar.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Node {
+                    width: Val::Px(200.0),
+                    ..default()
+                },
+                TimelineText,
+            ));
+            spawn_timeline_step(bar, -1, "<");
+
+            // Dragging on the track picks the date, oldest on the left
+            bar.spawn((
+                Button,
+                Node {
+                    flex_grow: 1.0,
+                    height: Val::Px(12.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.3, 0.3, 0.3, 0.8)),
+                RelativeCursorPosition::default(),
+                TimelineSlider,
+            ))
+            .with_child((
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(6.0),
+                    height: Val::Px(16.0),
+                    top: Val::Px(-2.0),
+                    margin: UiRect::left(Val::Px(-3.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.3, 0.8, 1.0)),
+                TimelineKnob,
+            ));
+
+            spawn_timeline_step(bar, 1, ">");
+        });
+}
+
+fn spawn_timeline_step(bar: &mut ChildBuilder, step: i32, label: &str) {
+    bar.spawn((
+        Button,
+        Node {
+            padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+        TimelineStep(step),
+    ))
+    .with_child((
+        Text::new(label),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+    ));
+}
+
+/// Pick the date of the timeline's layer with the slider or the step buttons
+/// Dragging only moves the knob; the tiles are requested again for the date the drag ends on,
+/// not for every date passed on the way
+pub fn handle_imagery_timeline(
+    mut layers: ResMut<ImageryLayers>,
+    mut scrub: ResMut<TimelineScrub>,
+    slider_query: Query<(&Interaction, &RelativeCursorPosition), With<TimelineSlider>>,
+    button_query: Query<(&Interaction, &TimelineStep), Changed<Interaction>>,
+) {
+    let Some((id, count, current)) = layers.timeline_layer().map(|layer| (layer.id, layer.times.len(), layer.time_index)) else {
+        scrub.index = None;
+        return;
+    };
+    let last = count - 1;
+
+    let mut picked = None;
+    for (interaction, step) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            picked = Some((current as i32 + step.0).clamp(0, last as i32) as usize);
+        }
+    }
+
+    // Interaction stays Pressed while the button is held, so the knob follows a drag
+    for (interaction, cursor) in slider_query.iter() {
+        match (*interaction, cursor.normalized) {
+            (Interaction::Pressed, Some(position)) => {
+                let index = (position.x.clamp(0.0, 1.0) * last as f32).round() as usize;
+                if scrub.index != Some(index) {
+                    scrub.index = Some(index);
+                }
+            }
+            (Interaction::Pressed, None) => {}
+            _ => picked = picked.or(scrub.index.take()),
+        }
+    }
+
+    let Some(index) = picked.filter(|&index| index != current) else {
+        return;
+    };
+    if let Some(layer) = layers.get_mut(id) {
+        layer.time_index = index;
+        info!("{}: imagery of {}", layer.name, layer.times[index]);
+    }
+}
+
+/// Show the timeline while a layer with dates is visible, with the date shown or being picked
+pub fn update_imagery_timeline(
+    layers: Res<ImageryLayers>,
+    scrub: Res<TimelineScrub>,
+    mut panel_query: Query<&mut Visibility, With<TimelinePanel>>,
+    mut knob_query: Query<&mut Node, With<TimelineKnob>>,
+    mut text_query: Query<&mut Text, With<TimelineText>>,
+) {
+    if !layers.is_changed() && !scrub.is_changed() {
+        return;
+    }
+
+    let layer = layers.timeline_layer();
+    for mut visibility in panel_query.iter_mut() {
+        *visibility = if layer.is_some() { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    let Some(layer) = layer else {
+        return;
+    };
+
+    let index = scrub.index.unwrap_or(layer.time_index).min(layer.times.len() - 1);
+    let fraction = if layer.times.len() > 1 { index as f32 / (layer.times.len() - 1) as f32 } else { 1.0 };
+    for mut knob in knob_query.iter_mut() {
+        knob.left = Val::Percent(fraction * 100.0);
+    }
+    for mut text in text_query.iter_mut() {
+        text.0 = format!("{} {}", layer.name, layer.times[index]);
+    }
+}
+
+/// Spawn the attribution bar (bottom right corner), required by the tile providers' terms
+/// It stays on top of all other UI; the credits are added by update_attribution_bar
+pub fn setup_attribution_bar(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(0.0),
+            right: Val::Px(0.0),
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(6.0),
+            padding: UiRect::axes(Val::Px(4.0), Val::Px(1.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+        GlobalZIndex(i32::MAX),
+        AttributionBar,
+    ));
+}
+
+/// Credit the providers of the visible imagery layers, one link per provider, and the
+/// provider on the right side of the provider comparison
+pub fn update_attribution_bar(
+    mut commands: Commands,
+    layers: Res<ImageryLayers>,
+    comparison: Res<ProviderComparison>,
+    mut compared: Local<Option<Attribution>>,
+    bar_query: Query<(Entity, Ref<AttributionBar>)>,
+) {
+    let Ok((bar, marker)) = bar_query.get_single() else {
+        return;
+    };
+    let comparison_attribution = comparison.attribution();
+    if !layers.is_changed() && !marker.is_added() && *compared == comparison_attribution {
+        return;
+    }
+    *compared = comparison_attribution;
+
+    let mut attributions = layers.attributions();
+    if let Some(attribution) = comparison_attribution.filter(|attribution| !attributions.contains(attribution)) {
+        attributions.push(attribution);
+    }
+    commands.entity(bar).despawn_descendants().with_children(|bar| {
+        for attribution in attributions {
+            bar.spawn((Button, AttributionLink { url: attribution.link.to_string() }))
+                .with_child((
+                    Text::new(attribution.text),
+                    TextFont {
+                        font_size: 11.0,
+                        ..default()
+                    },
+                    TextColor(LINK_COLOR),
+                ));
+        }
+    });
+}
+
+/// Open the page behind a credit when it is clicked
+/// Where URLs can't be opened the link is logged instead
+pub fn handle_attribution_links(link_query: Query<(&Interaction, &AttributionLink), Changed<Interaction>>) {
+    for (interaction, link) in link_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match open_url(&link.url) {
+            Ok(()) => info!("Opened {}", link.url),
+            Err(e) => info!("Attribution: {} (couldn't open a browser: {})", link.url, e),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use crate::components::{LoadingScreen, LoadingText, MainCamera};
+use crate::osm::TileId;
+use crate::resources::OSMData;
+use crate::resources::loading::{ring_progress, AppState, LOADING_FADE, LOADING_TIMEOUT};
+use crate::utils::geo::world_to_tile_coords;
+
+const LOADING_BACKGROUND: Color = Color::srgb(0.08, 0.09, 0.11);
+
+/// Spawn the loading screen covering the world until the tiles around the start are in
+/// It stays below the attribution bar, which must always be visible
+pub fn setup_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(LOADING_BACKGROUND),
+            GlobalZIndex(i32::MAX - 1),
+            LoadingScreen::default(),
+        ))
+        .with_child((
+            Text::new("Loading map"),
+            TextFont {
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            LoadingText,
+        ));
+}
+
+/// Show how many tiles of the initial ring are in, and start running once all of them are:
+/// the tiles the scheduler requested in the 3x3 tiles around the ground below the camera
+/// Gives up waiting after LOADING_TIMEOUT, e.g. when the tile servers can't be reached
+pub fn update_loading_progress(
+    time: Res<Time>,
+    osm_data: Res<OSMData>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut text_query: Query<&mut Text, With<LoadingText>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let zoom = osm_data.current_zoom;
+    let (x, y) = world_to_tile_coords(camera.translation.x as f64, camera.translation.z as f64, zoom);
+    let (resident, total) = ring_progress(TileId::new(x, y, zoom), &osm_data.loaded_tiles, |tile| {
+        osm_data.tiles.contains_key(tile)
+    });
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = format!("Loading map ({}/{} tiles)", resident, total);
+    }
+
+    // Nothing requested yet means the scheduler hasn't seen the camera yet
+    let loaded = total > 0 && resident == total;
+    if loaded || time.elapsed_secs() >= LOADING_TIMEOUT {
+        if !loaded {
+            warn!("Showing the map before its tiles loaded: {}/{} after {}s", resident, total, LOADING_TIMEOUT);
+        }
+        next_state.set(AppState::Running);
+    }
+}
+
+/// Fade the loading screen into the world, then remove it
+pub fn fade_out_loading_screen(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut screen_query: Query<(Entity, &mut LoadingScreen, &mut BackgroundColor)>,
+    mut text_query: Query<&mut TextColor, With<LoadingText>>,
+) {
+    let Ok((entity, mut screen, mut background)) = screen_query.get_single_mut() else {
+        return;
+    };
+
+    screen.fading += time.delta_secs();
+    let alpha = 1.0 - (screen.fading / LOADING_FADE).min(1.0);
+    if alpha <= 0.0 {
+        commands.entity(entity).despawn_recursive();
+        return;
+    }
+    background.0.set_alpha(alpha);
+    for mut color in &mut text_query {
+        color.0.set_alpha(alpha);
+    }
+}
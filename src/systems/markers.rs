@@ -0,0 +1,342 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{ClearMarkersButton, MainCamera, MarkerImportPanel, MarkerImportText};
+use crate::events::DataFileDropped;
+use crate::overlays::dropped_data::DropTarget;
+use crate::overlays::marker_import::parse_marker_file;
+use crate::overlays::markers::{cluster_screen_points, pin_image, ScreenCluster};
+use crate::resources::{InputMap, MarkerBadges, MarkerImport, Markers, OSMData, UserSettings};
+use crate::resources::input_map::TOGGLE_MARKER_IMPORT;
+use crate::resources::markers::{MarkerBadge, MarkerIcon};
+use crate::utils::geo::GeoTransform;
+
+// Markers within this many pixels of each other are shown as one badge
+const CLUSTER_CELL_PX: f32 = 48.0;
+// From this zoom level on every marker is shown on its own
+const CLUSTER_MAX_ZOOM: u32 = 16;
+// Badges shown at most, the rest of the markers in view wait until the camera moves closer
+const MAX_BADGES: usize = 500;
+const PIN_SIZE: Vec2 = Vec2::new(20.0, 30.0);
+const COUNT_SIZE: f32 = 26.0;
+const CLUSTER_COLOR: Color = Color::srgb(0.85, 0.25, 0.2);
+const IMPORTED_PIN_COLOR: Color = Color::srgb(0.2, 0.45, 0.9);
+// Imported markers added per frame, so a file of many thousands shows its progress
+const IMPORT_BATCH: usize = 2000;
+
+type BadgeNodes<'w, 's> = Query<'w, 's, (&'static mut Node, &'static mut Visibility)>;
+
+/// Make the built-in pin icon
+pub fn setup_markers(mut badges: ResMut<MarkerBadges>, mut images: ResMut<Assets<Image>>) {
+    badges.pin = images.add(pin_image());
+}
+
+/// Draw the markers in view as pins with their label, clustering markers close together on
+/// screen into a badge with their count below CLUSTER_MAX_ZOOM
+pub fn update_markers(
+    mut commands: Commands,
+    markers: Res<Markers>,
+    osm_data: Res<OSMData>,
+    mut badges: ResMut<MarkerBadges>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut node_query: BadgeNodes,
+    (mut image_query, mut text_query): (Query<&mut ImageNode>, Query<&mut Text>),
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    let viewport = camera.logical_viewport_size().unwrap_or_default();
+
+    let mut shown = Vec::new();
+    let mut points = Vec::new();
+    for (_, marker) in markers.iter() {
+        let position = GeoTransform::new(marker.position, 0.0).translation();
+        let Ok(screen) = camera.world_to_viewport(camera_transform, position) else {
+            continue;
+        };
+        if screen.cmpge(Vec2::ZERO).all() && screen.cmplt(viewport).all() {
+            shown.push(marker);
+            points.push(screen);
+        }
+    }
+
+    let clusters = if osm_data.current_zoom < CLUSTER_MAX_ZOOM {
+        cluster_screen_points(&points, CLUSTER_CELL_PX)
+    } else {
+        points.iter().enumerate().map(|(i, &position)| ScreenCluster { position, members: vec![i] }).collect()
+    };
+    let count = clusters.len().min(MAX_BADGES);
+    while badges.badges.len() < count {
+        let badge = spawn_marker_badge(&mut commands, badges.pin.clone());
+        badges.badges.push(badge);
+    }
+
+    for (i, badge) in badges.badges.iter().enumerate() {
+        let Some(cluster) = clusters.get(i).filter(|_| i < count) else {
+            if let Ok((_, mut visibility)) = node_query.get_mut(badge.root) {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+            continue;
+        };
+        let single = cluster.members.len() == 1;
+        let marker = shown[cluster.members[0]];
+
+        // Pins point at their position with their tip, count badges are centred on it
+        let offset = if single { Vec2::new(PIN_SIZE.x / 2.0, PIN_SIZE.y) } else { Vec2::splat(COUNT_SIZE / 2.0) };
+        if let Ok((mut node, mut visibility)) = node_query.get_mut(badge.root) {
+            node.left = Val::Px(cluster.position.x - offset.x);
+            node.top = Val::Px(cluster.position.y - offset.y);
+            visibility.set_if_neq(Visibility::Inherited);
+        }
+        let display = |shown: bool| if shown { Display::Flex } else { Display::None };
+        let label = marker.label.as_ref().filter(|_| single);
+        for (entity, visible) in [(badge.icon, single), (badge.count, !single), (badge.label, label.is_some())] {
+            if let Ok((mut node, _)) = node_query.get_mut(entity) {
+                node.display = display(visible);
+            }
+        }
+
+        if single {
+            if let Ok(mut image) = image_query.get_mut(badge.icon) {
+                let (handle, color) = match &marker.icon {
+                    MarkerIcon::Pin(color) => (&badges.pin, *color),
+                    MarkerIcon::Image(handle) => (handle, Color::WHITE),
+                };
+                if image.image != *handle || image.color != color {
+                    image.image = handle.clone();
+                    image.color = color;
+                }
+            }
+            if let (Some(label), Ok(mut text)) = (label, text_query.get_mut(badge.label)) {
+                if text.0 != *label {
+                    text.0 = label.clone();
+                }
+            }
+        } else if let Ok(mut text) = text_query.get_mut(badge.count_text) {
+            let count = cluster.members.len().to_string();
+            if text.0 != count {
+                text.0 = count;
+            }
+        }
+    }
+}
+
+// A badge is a row of the marker's icon or the cluster's count, and the marker's label
+fn spawn_marker_badge(commands: &mut Commands, pin: Handle<Image>) -> MarkerBadge {
+    let icon = commands
+        .spawn((
+            ImageNode::new(pin),
+            Node {
+                width: Val::Px(PIN_SIZE.x),
+                height: Val::Px(PIN_SIZE.y),
+                ..default()
+            },
+        ))
+        .id();
+    let count_text = commands
+        .spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 12.0,
+                ..default()
+            },
+        ))
+        .id();
+    let count = commands
+        .spawn((
+            Node {
+                width: Val::Px(COUNT_SIZE),
+                height: Val::Px(COUNT_SIZE),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(CLUSTER_COLOR),
+        ))
+        .add_child(count_text)
+        .id();
+    let label = commands
+        .spawn((
+            Text::new(""),
+            TextFont {
+                font_size: 13.0,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            Node {
+                padding: UiRect::axes(Val::Px(4.0), Val::Px(1.0)),
+                margin: UiRect::left(Val::Px(2.0)),
+                ..default()
+            },
+        ))
+        .id();
+    let root = commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Start,
+                ..default()
+            },
+            Visibility::Hidden,
+            Name::new("Marker badge"),
+        ))
+        .add_children(&[icon, count, label])
+        .id();
+
+    MarkerBadge { root, icon, count, count_text, label }
+}
+
+/// Switch importing dropped files as markers on or off (K by default)
+/// While on, dropped CSV and GeoJSON files become markers instead of heatmap layers or street labels
+pub fn toggle_marker_import(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut import: ResMut<MarkerImport>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_MARKER_IMPORT) {
+        return;
+    }
+
+    import.active = !import.active;
+    info!("Marker import: {}", if import.active { "ON" } else { "OFF" });
+    // The cursor is released so the Clear button can be clicked
+    if let (true, Ok(mut window)) = (import.active, windows.get_single_mut()) {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// Read the markers of CSV and GeoJSON files dropped while marker import is on
+/// They are added to the map a batch per frame by import_pending_markers
+pub fn handle_marker_file_drop(
+    mut import: ResMut<MarkerImport>,
+    settings: Res<UserSettings>,
+    mut dropped_events: EventReader<DataFileDropped>,
+) {
+    for event in dropped_events.read().filter(|event| event.target == DropTarget::Markers) {
+        let path_buf = &event.path;
+        let name = path_buf.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        match parse_marker_file(&name, &event.data, &settings.marker_columns) {
+            Ok(markers) => {
+                info!("Importing {} markers from {}", markers.len(), path_buf.display());
+                import.total += markers.len();
+                import.pending.extend(markers);
+                import.error = None;
+            }
+            Err(e) => {
+                warn!("Failed to import markers from {}: {}", path_buf.display(), e);
+                import.error = Some(format!("{}: {}", name, e));
+            }
+        }
+    }
+}
+
+/// Add the next batch of markers read from dropped files
+pub fn import_pending_markers(mut import: ResMut<MarkerImport>, mut markers: ResMut<Markers>) {
+    if import.pending.is_empty() {
+        return;
+    }
+
+    let batch = import.pending.len().min(IMPORT_BATCH);
+    let added: Vec<_> = import
+        .pending
+        .drain(..batch)
+        .map(|marker| markers.add_marker(marker.lat, marker.lon, MarkerIcon::Pin(IMPORTED_PIN_COLOR), marker.name))
+        .collect();
+    import.imported.extend(added);
+    if import.pending.is_empty() {
+        info!("Imported {} markers", import.imported.len());
+    }
+}
+
+/// Spawn the marker import panel (top right, under the weather panel), hidden until marker
+/// import is on
+pub fn setup_marker_import_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(110.0),
+                right: Val::Px(10.0),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            Visibility::Hidden,
+            MarkerImportPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                MarkerImportText,
+            ));
+            panel
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+                    ClearMarkersButton,
+                ))
+                .with_child((
+                    Text::new("Clear"),
+                    TextFont {
+                        font_size: 14.0,
+                        ..default()
+                    },
+                ));
+        });
+}
+
+/// Remove the imported markers, and stop an import in progress, when Clear is clicked
+pub fn handle_clear_markers_button(
+    mut import: ResMut<MarkerImport>,
+    mut markers: ResMut<Markers>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<ClearMarkersButton>)>,
+) {
+    if !button_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+
+    for id in import.imported.drain(..) {
+        markers.remove_marker(id);
+    }
+    import.pending.clear();
+    import.total = 0;
+    import.error = None;
+    info!("Cleared the imported markers");
+}
+
+/// Show the progress of an import, or how many markers were imported
+pub fn update_marker_import_panel(
+    import: Res<MarkerImport>,
+    mut panel_query: Query<&mut Visibility, With<MarkerImportPanel>>,
+    mut text_query: Query<&mut Text, With<MarkerImportText>>,
+) {
+    if !import.is_changed() {
+        return;
+    }
+
+    if let Ok(mut visibility) = panel_query.get_single_mut() {
+        *visibility = if import.active { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = match (&import.error, import.pending.is_empty()) {
+            (_, false) => format!("Importing markers: {} / {}", import.imported.len(), import.total),
+            (Some(error), true) => error.clone(),
+            (None, true) if import.imported.is_empty() => "Drop a CSV or GeoJSON file of markers".to_string(),
+            (None, true) => format!("{} markers imported", import.imported.len()),
+        };
+    }
+}
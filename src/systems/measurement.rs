@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use crate::components::{LayerItem, MainCamera, MeasurementLine, MeasurementText};
+use crate::overlays::gpx::create_polyline_mesh;
+use crate::resources::{CursorPick, InputMap, Measurement};
+use crate::resources::input_map::TOGGLE_MEASURE;
+use crate::resources::layer_manager::MEASUREMENT_LAYER;
+use crate::utils::geo::lat_lon_to_world;
+
+// Clicking within this fraction of the camera height of the first point closes the shape
+const CLOSE_DISTANCE: f32 = 0.03;
+// Line width as a fraction of the camera height, so the line looks the same at any altitude
+const LINE_WIDTH: f32 = 0.004;
+// Height of the line above the tiles
+const LINE_ELEVATION: f32 = 0.02;
+const LINE_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+
+/// Run condition: whether measurement mode is on, so clicks place points instead of zooming
+pub fn measuring(measurement: Option<Res<Measurement>>) -> bool {
+    measurement.is_some_and(|measurement| measurement.active)
+}
+
+/// Switch measurement mode on or off (M by default), dropping the previous measurement
+pub fn toggle_measurement(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut measurement: ResMut<Measurement>,
+) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_MEASURE) {
+        measurement.active = !measurement.active;
+        measurement.clear();
+        info!("Measurement mode: {}", if measurement.active { "ON" } else { "OFF" });
+    }
+}
+
+/// Add the ground point under the cursor on every click
+/// Clicking the first point again closes the shape, the next click starts a new one
+pub fn place_measurement_points(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor_pick: Res<CursorPick>,
+    mut measurement: ResMut<Measurement>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    ui_query: Query<&Interaction>,
+) {
+    if !measurement.active || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    // Clicks on buttons are not meant for the map
+    if ui_query.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+    let (Some(world), Some(lat_lon), Ok(camera)) = (cursor_pick.world, cursor_pick.lat_lon, camera_query.get_single()) else {
+        return;
+    };
+
+    if measurement.closed {
+        measurement.clear();
+    }
+
+    if let Some(&(lat, lon)) = measurement.points.first() {
+        let first = lat_lon_to_world(lat, lon).as_vec2();
+        let near_first = first.distance(Vec2::new(world.x, world.z)) < camera.translation.y * CLOSE_DISTANCE;
+        if near_first && measurement.points.len() >= 3 {
+            measurement.closed = true;
+            return;
+        }
+    }
+
+    measurement.points.push(lat_lon);
+}
+
+/// Draw the measured shape, with a line following the cursor from the last point while it is open
+pub fn update_measurement_line(
+    mut commands: Commands,
+    measurement: Res<Measurement>,
+    cursor_pick: Res<CursorPick>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    camera_query: Query<&GlobalTransform, With<MainCamera>>,
+    mut line_query: Query<(Entity, &mut Transform, &Mesh3d), With<MeasurementLine>>,
+) {
+    let mut points: Vec<(f64, f64)> = measurement.points.clone();
+    if measurement.closed {
+        points.extend(measurement.points.first().copied());
+    } else if let Some(cursor) = cursor_pick.lat_lon.filter(|_| measurement.active) {
+        points.push(cursor);
+    }
+
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    if !measurement.active || points.len() < 2 {
+        for (entity, ..) in line_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    // Vertices relative to the first point keep full f32 precision, like GPX tracks
+    let anchor = lat_lon_to_world(points[0].0, points[0].1);
+    let vertices: Vec<Vec3> = points
+        .iter()
+        .map(|&(lat, lon)| {
+            let offset = lat_lon_to_world(lat, lon) - anchor;
+            Vec3::new(offset.x as f32, 0.0, offset.y as f32)
+        })
+        .collect();
+    let mesh = create_polyline_mesh(&vertices, camera.translation().y.max(0.01) * LINE_WIDTH);
+    let translation = Vec3::new(anchor.x as f32, LINE_ELEVATION, anchor.y as f32);
+
+    match line_query.get_single_mut() {
+        Ok((_, mut transform, line_mesh)) => {
+            transform.translation = translation;
+            if let Some(line_mesh) = meshes.get_mut(&line_mesh.0) {
+                *line_mesh = mesh;
+            }
+        }
+        Err(_) => {
+            commands.spawn((
+                Mesh3d(meshes.add(mesh)),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: LINE_COLOR,
+                    unlit: true,
+                    double_sided: true,
+                    cull_mode: None,
+                    ..default()
+                })),
+                Transform::from_translation(translation),
+                MeasurementLine,
+                LayerItem { layer: MEASUREMENT_LAYER, elevation: LINE_ELEVATION },
+                Name::new("Measurement"),
+            ));
+        }
+    }
+}
+
+/// Spawn the measurement totals panel (bottom center), hidden until measuring
+pub fn setup_measurement_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(-150.0)),
+            width: Val::Px(300.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+        MeasurementText,
+    ));
+}
+
+/// Show the measured distance and area
+pub fn update_measurement_text(
+    measurement: Res<Measurement>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<MeasurementText>>,
+) {
+    if !measurement.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    *visibility = if measurement.active { Visibility::Inherited } else { Visibility::Hidden };
+
+    text.0 = match (measurement.points.len(), measurement.area_m2()) {
+        (0, _) => "Measure: click on the map to add points".to_string(),
+        (_, Some(area)) => format!(
+            "Perimeter: {}\nArea: {}",
+            format_distance(measurement.distance_m()),
+            format_area(area)
+        ),
+        (count, None) => format!(
+            "Distance: {}\n{} points, click the first point to measure the area",
+            format_distance(measurement.distance_m()),
+            count
+        ),
+    };
+}
+
+/// Distance in meters, or in kilometers from one kilometer on
+pub fn format_distance(meters: f64) -> String {
+    if meters < 1000.0 {
+        format!("{:.0} m", meters)
+    } else {
+        format!("{:.2} km", meters / 1000.0)
+    }
+}
+
+fn format_area(square_meters: f64) -> String {
+    if square_meters < 1e6 {
+        format!("{:.0} m² ({:.2} ha)", square_meters, square_meters / 1e4)
+    } else {
+        format!("{:.2} km²", square_meters / 1e6)
+    }
+}
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy::render::camera::{RenderTarget, ScalingMode};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{MainCamera, MinimapCamera, MinimapImage, MinimapHeading};
+use crate::resources::{MinimapSettings, MouseLookState};
+use crate::events::FlyTo;
+
+/// Create the minimap render target, its top-down camera and the UI widget showing it
+pub fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<MinimapSettings>,
+) {
+    // Render target texture for the minimap camera
+    let size = Extent3d {
+        width: settings.size_px,
+        height: settings.size_px,
+        ..default()
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    let image_handle = images.add(image);
+
+    // Orthographic camera looking straight down, north up
+    // Renders before the main camera so the texture is ready when the UI draws it
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(image_handle.clone()),
+            order: -1,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.1, 0.1, 0.15)),
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed { width: 1.0, height: 1.0 },
+            far: 1_000_000.0, // Camera follows the main camera height, which can be very high
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_xyz(0.0, 100.0, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+        MinimapCamera,
+    ));
+
+    // Minimap widget in the bottom right corner, above the attribution text
+    commands
+        .spawn((
+            ImageNode::new(image_handle),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.0),
+                right: Val::Px(10.0),
+                width: Val::Px(settings.size_px as f32),
+                height: Val::Px(settings.size_px as f32),
+                border: UiRect::all(Val::Px(2.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            RelativeCursorPosition::default(),
+            MinimapImage,
+        ))
+        .with_children(|parent| {
+            // Heading indicator: a thin bar pointing in the view direction,
+            // offset so it rotates around the player position in the centre
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(0.0),
+                        height: Val::Px(0.0),
+                        ..default()
+                    },
+                    MinimapHeading,
+                ))
+                .with_children(|heading| {
+                    heading.spawn((
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(-2.0),
+                            top: Val::Px(-16.0),
+                            width: Val::Px(4.0),
+                            height: Val::Px(16.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(1.0, 0.2, 0.2)),
+                    ));
+                });
+        });
+}
+
+/// Keep the minimap camera centred above the main camera, zoomed by altitude
+pub fn update_minimap_camera(
+    settings: Res<MinimapSettings>,
+    main_camera: Query<&Transform, (With<MainCamera>, Without<MinimapCamera>)>,
+    mut minimap_camera: Query<(&mut Transform, &mut Projection), With<MinimapCamera>>,
+) {
+    let Ok(main_transform) = main_camera.get_single() else {
+        return;
+    };
+    let Ok((mut transform, mut projection)) = minimap_camera.get_single_mut() else {
+        return;
+    };
+
+    let position = main_transform.translation;
+    let height = position.y.max(1.0);
+
+    *transform = Transform::from_xyz(position.x, height + 10.0, position.z)
+        .looking_at(Vec3::new(position.x, 0.0, position.z), Vec3::NEG_Z);
+
+    if let Projection::Orthographic(ortho) = projection.as_mut() {
+        ortho.scale = settings.extent(height);
+    }
+}
+
+/// Rotate the heading indicator to match the camera yaw
+pub fn update_minimap_heading(
+    mouse_look_state: Res<MouseLookState>,
+    mut heading_query: Query<&mut Transform, With<MinimapHeading>>,
+) {
+    for mut transform in heading_query.iter_mut() {
+        // Yaw turns counter-clockwise seen from above, while UI rotations turn clockwise
+        // on screen (y points down), so negate it
+        transform.rotation = Quat::from_rotation_z(-mouse_look_state.yaw);
+    }
+}
+
+/// Fly the main camera to the location clicked on the minimap
+pub fn minimap_click_to_fly(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    settings: Res<MinimapSettings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    minimap_query: Query<&RelativeCursorPosition, With<MinimapImage>>,
+    main_camera: Query<&Transform, With<MainCamera>>,
+    mut fly_to_events: EventWriter<FlyTo>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // Clicks only reach the UI when the cursor is released
+    let cursor_free = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor_options.grab_mode == CursorGrabMode::None);
+    if !cursor_free {
+        return;
+    }
+
+    let (Ok(cursor), Ok(camera_transform)) = (minimap_query.get_single(), main_camera.get_single()) else {
+        return;
+    };
+
+    if !cursor.mouse_over() {
+        return;
+    }
+    let Some(normalized) = cursor.normalized else {
+        return;
+    };
+
+    // (0,0) is the top-left (north-west) corner of the minimap, (1,1) the bottom-right
+    let position = camera_transform.translation;
+    let extent = settings.extent(position.y.max(1.0));
+    let offset = (normalized - Vec2::splat(0.5)) * extent;
+
+    fly_to_events.send(FlyTo {
+        target: Vec3::new(position.x + offset.x, position.y, position.z + offset.y),
+        duration: settings.flight_duration,
+    });
+}
@@ -1,9 +1,64 @@
 pub mod setup;
 pub mod camera;
+pub mod floating_origin;
 pub mod tiles;
+pub mod tile_debug;
+pub mod tile_diagnostics;
 pub mod interaction;
 pub mod debug;
 pub mod window;
 pub mod ui;
+pub mod loading;
+pub mod comparison;
+pub mod stereo;
+pub mod overlays;
+pub mod minimap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod overview;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache_maintenance;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod connectivity;
+pub mod keybindings;
+pub mod settings;
+pub mod settings_menu;
+pub mod style;
+pub mod game;
+pub mod globe;
+pub mod environment;
+pub mod atmosphere;
+pub mod water;
+pub mod camera_path;
+pub mod measurement;
+pub mod routing;
+pub mod buildings;
+pub mod roads;
+pub mod street_lamps;
+pub mod weather;
+pub mod layers;
+pub mod markers;
+pub mod models;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod multiplayer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod chat;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod islands;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_editor;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_objects;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_info;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_bounds;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod island_travel;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod scripting;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod session;
 
 // Systems are imported directly where needed 
\ No newline at end of file
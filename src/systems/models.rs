@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use crate::components::GeoAnchor;
+use crate::events::SpawnGeoModel;
+use crate::resources::models::configured_models;
+
+/// Place the models listed in the settings file
+pub fn spawn_configured_models(mut spawn_events: EventWriter<SpawnGeoModel>) {
+    spawn_events.send_batch(configured_models());
+}
+
+/// Load the glTF model of each spawn request and anchor it at its position, at its true size
+pub fn spawn_geo_models(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnGeoModel>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in spawn_events.read() {
+        let anchor = GeoAnchor::new(event.lat, event.lon, 0.0);
+        let scale = (event.scale / anchor.position().meters_per_world_unit()) as f32;
+        info!("Placing model {} at {:.5}, {:.5}", event.path, event.lat, event.lon);
+        commands.spawn((
+            SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(event.path.clone()))),
+            // North is -Z, so turning clockwise seen from above is a negative turn around Y
+            Transform::from_translation(anchor.translation())
+                .with_rotation(Quat::from_rotation_y(-event.heading.to_radians() as f32))
+                .with_scale(Vec3::splat(scale)),
+            anchor,
+            Name::new(format!("Model {}", event.path)),
+        ));
+    }
+}
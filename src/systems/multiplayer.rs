@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+use tokio::sync::mpsc::unbounded_channel;
+use crate::components::{Avatar, AvatarLabel, MainCamera};
+use crate::events::SettingsChanged;
+use crate::net::{hello_message, position_message, run_session, ConnectionStatus, GeoPose, ServerMessage, SharedInbox};
+use crate::resources::{Chat, Multiplayer, Peer, TaskRuntime, UserSettings};
+use crate::utils::geo::{GeoPos, GeoTransform};
+
+// Avatar size as a fraction of its distance to the camera, so avatars stay visible from afar
+const AVATAR_SCALE: f32 = 0.03;
+// Smallest pose change worth telling the other users about, in degrees and meters
+const MIN_MOVE_DEGREES: f64 = 1e-7;
+const MIN_MOVE_METERS: f64 = 0.1;
+const MIN_TURN_DEGREES: f64 = 1.0;
+
+/// Connect to the relay server from the settings, or disconnect when it is cleared
+/// A changed name is announced to the server without reconnecting
+pub fn connect_multiplayer(
+    mut commands: Commands,
+    mut changed_events: EventReader<SettingsChanged>,
+    settings: Res<UserSettings>,
+    task_runtime: Res<TaskRuntime>,
+    mut multiplayer: ResMut<Multiplayer>,
+) {
+    if changed_events.read().count() == 0 {
+        return;
+    }
+
+    if multiplayer.server == settings.multiplayer_server {
+        if multiplayer.name != settings.player_name {
+            multiplayer.name = settings.player_name.clone();
+            multiplayer.send(hello_message(&settings.player_name));
+        }
+        return;
+    }
+
+    // Dropping the sender ends the old session
+    multiplayer.outgoing = None;
+    for (_, peer) in multiplayer.peers.drain() {
+        commands.entity(peer.avatar).despawn_recursive();
+        commands.entity(peer.label).despawn_recursive();
+    }
+    multiplayer.own_id = None;
+    multiplayer.last_sent = None;
+    multiplayer.server = settings.multiplayer_server.clone();
+    multiplayer.name = settings.player_name.clone();
+
+    if multiplayer.server.is_empty() {
+        multiplayer.status = ConnectionStatus::Offline;
+        return;
+    }
+
+    info!("Multiplayer: connecting to {}", multiplayer.server);
+    let (sender, receiver) = unbounded_channel();
+    // A fresh inbox, so nothing the old session still delivers ends up here
+    let inbox = SharedInbox::default();
+    task_runtime.spawn(run_session(multiplayer.server.clone(), receiver, Arc::clone(&inbox)));
+    multiplayer.outgoing = Some(sender);
+    multiplayer.inbox = inbox;
+    multiplayer.status = ConnectionStatus::Connecting;
+}
+
+/// Apply what the relay server sent: the other users' positions, arrivals, departures and chat
+/// Users that haven't sent anything for a while are taken off the map
+pub fn receive_presence(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut multiplayer: ResMut<Multiplayer>,
+    mut chat: ResMut<Chat>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let now = time.elapsed_secs();
+    let (messages, status) = {
+        let mut inbox = multiplayer.inbox.lock();
+        (std::mem::take(&mut inbox.messages), inbox.status.take())
+    };
+
+    if let Some(status) = status {
+        match &status {
+            ConnectionStatus::Connected => {
+                info!("Multiplayer: connected to {}", multiplayer.server);
+                multiplayer.send(hello_message(&multiplayer.name));
+                multiplayer.last_sent = None;
+            }
+            ConnectionStatus::Disconnected(reason) => warn!("Multiplayer: disconnected ({}), retrying", reason),
+            _ => {}
+        }
+        // Users seen before the connection dropped may have left meanwhile, the server sends them again
+        if status != ConnectionStatus::Connected {
+            for (_, peer) in multiplayer.peers.drain() {
+                commands.entity(peer.avatar).despawn_recursive();
+                commands.entity(peer.label).despawn_recursive();
+            }
+        }
+        multiplayer.status = status;
+    }
+
+    for message in messages {
+        match message {
+            ServerMessage::Welcome { id } => multiplayer.own_id = Some(id),
+            ServerMessage::Position { id, name, pose } => {
+                if multiplayer.own_id == Some(id) {
+                    continue;
+                }
+                if let Some(peer) = multiplayer.peers.get_mut(&id) {
+                    if peer.name != name {
+                        commands.entity(peer.label).insert(Text::new(name.clone()));
+                        peer.name = name;
+                    }
+                    peer.pose = pose;
+                    peer.last_seen = now;
+                    continue;
+                }
+
+                info!("Multiplayer: {} joined", name);
+                let (avatar, label) = spawn_avatar(&mut commands, &mut meshes, &mut materials, id, &name);
+                multiplayer.peers.insert(id, Peer { name, pose, last_seen: now, avatar, label });
+            }
+            ServerMessage::Chat { id, name, text, location } => {
+                // Own messages were shown when they were sent
+                if multiplayer.own_id != Some(id) {
+                    chat.push(&name, &text, location, now);
+                }
+            }
+            ServerMessage::Leave { id } => {
+                if let Some(peer) = multiplayer.peers.remove(&id) {
+                    info!("Multiplayer: {} left", peer.name);
+                    commands.entity(peer.avatar).despawn_recursive();
+                    commands.entity(peer.label).despawn_recursive();
+                }
+            }
+        }
+    }
+
+    let timeout = multiplayer.peer_timeout;
+    multiplayer.peers.retain(|_, peer| {
+        let alive = now - peer.last_seen < timeout;
+        if !alive {
+            commands.entity(peer.avatar).despawn_recursive();
+            commands.entity(peer.label).despawn_recursive();
+        }
+        alive
+    });
+}
+
+// An avatar is a capsule with a visor showing where the user looks, colored after their id
+fn spawn_avatar(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    id: u64,
+    name: &str,
+) -> (Entity, Entity) {
+    let color = Color::hsl((id as f32 * 137.5) % 360.0, 0.7, 0.55);
+    let body = materials.add(StandardMaterial {
+        base_color: color,
+        unlit: true,
+        ..default()
+    });
+    let visor = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.1, 0.1, 0.1),
+        unlit: true,
+        ..default()
+    });
+
+    let avatar = commands
+        .spawn((
+            Mesh3d(meshes.add(Capsule3d::new(0.25, 0.5))),
+            MeshMaterial3d(body),
+            Transform::default(),
+            Visibility::default(),
+            Avatar,
+            Name::new(format!("Avatar {}", name)),
+        ))
+        .with_child((
+            // Forward is -Z, like the camera
+            Mesh3d(meshes.add(Cuboid::new(0.3, 0.12, 0.2))),
+            MeshMaterial3d(visor),
+            Transform::from_xyz(0.0, 0.3, -0.2),
+        ))
+        .id();
+
+    let label = commands
+        .spawn((
+            Text::new(name),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::axes(Val::Px(4.0), Val::Px(1.0)),
+                ..default()
+            },
+            Visibility::Hidden,
+            AvatarLabel,
+        ))
+        .id();
+
+    (avatar, label)
+}
+
+/// Send the camera's position to the other users while it moves, and now and then while it doesn't
+pub fn broadcast_position(
+    time: Res<Time>,
+    mut multiplayer: ResMut<Multiplayer>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    if multiplayer.status != ConnectionStatus::Connected {
+        return;
+    }
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+    let pose = camera_pose(camera);
+    if let Some((sent_at, sent)) = multiplayer.last_sent {
+        let moved = (pose.lat - sent.lat).abs() > MIN_MOVE_DEGREES
+            || (pose.lon - sent.lon).abs() > MIN_MOVE_DEGREES
+            || (pose.altitude - sent.altitude).abs() > MIN_MOVE_METERS
+            || (pose.heading - sent.heading).abs() > MIN_TURN_DEGREES;
+        let due = if moved { multiplayer.send_interval } else { multiplayer.keepalive };
+        if now - sent_at < due {
+            return;
+        }
+    }
+
+    multiplayer.send(position_message(&pose));
+    multiplayer.last_sent = Some((now, pose));
+}
+
+// Geographic pose of the camera: where it is, how high above the ground and which way it looks
+fn camera_pose(camera: &Transform) -> GeoPose {
+    let geo = GeoTransform::from_translation(camera.translation);
+    let forward = camera.forward();
+    // North is -Z and east +X in world space
+    let heading = (forward.x as f64).atan2(-forward.z as f64).to_degrees().rem_euclid(360.0);
+    GeoPose {
+        lat: geo.position.lat,
+        lon: geo.position.lon,
+        altitude: geo.altitude_m,
+        heading,
+    }
+}
+
+/// Place the avatars at the other users' positions, with their names above them
+pub fn update_avatars(
+    multiplayer: Res<Multiplayer>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut avatar_query: Query<&mut Transform, With<Avatar>>,
+    mut label_query: Query<(&mut Node, &mut Visibility), With<AvatarLabel>>,
+) {
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    for peer in multiplayer.peers.values() {
+        let position = GeoTransform::new(GeoPos::new(peer.pose.lat, peer.pose.lon), peer.pose.altitude).translation();
+        let scale = position.distance(camera_transform.translation()) * AVATAR_SCALE;
+
+        if let Ok(mut transform) = avatar_query.get_mut(peer.avatar) {
+            *transform = Transform::from_translation(position)
+                .with_rotation(Quat::from_rotation_y(-(peer.pose.heading.to_radians() as f32)))
+                .with_scale(Vec3::splat(scale));
+        }
+
+        // The label hides while its avatar is behind the camera
+        if let Ok((mut node, mut visibility)) = label_query.get_mut(peer.label) {
+            match camera.world_to_viewport(camera_transform, position + Vec3::Y * scale * 0.8) {
+                Ok(screen) => {
+                    node.left = Val::Px(screen.x);
+                    node.top = Val::Px(screen.y - 20.0);
+                    *visibility = Visibility::Inherited;
+                }
+                Err(_) => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use bevy::math::DVec2;
+use bevy::window::FileDragAndDrop;
+use std::collections::HashMap;
+use crate::overlays::gpx::{load_gpx_file, spawn_gpx_track, GpxStyle};
+use crate::overlays::dropped_data::{is_data_file, route_dropped_data, DropTarget, DroppedData};
+use crate::overlays::street_labels::{spawn_street_label, street_lines, LabelFonts, StreetLabel};
+use crate::resources::{LayerManager, MarkerImport, OverlaySettings, StreetLabelSettings, Style};
+use crate::resources::layer_manager::{GPX_LAYER, STREET_NAMES_LAYER};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osm::HEATMAP_SCHEME;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::ImageryLayers;
+use crate::components::{LayerItem, MainCamera};
+use crate::events::{DataFileDropped, FlyTo};
+use crate::utils::geo::GeoPos;
+
+// Seconds to fly to a dropped track that is out of view
+const TRACK_FLIGHT_DURATION: f32 = 2.0;
+// Opacity a dropped heatmap starts with, so the map shows through; the layer panel changes it
+#[cfg(not(target_arch = "wasm32"))]
+const HEATMAP_OPACITY: f32 = 0.7;
+
+/// Load GPX files dropped onto the window and render them as overlays
+/// The camera flies to the track when it isn't already above it
+pub fn handle_gpx_file_drop(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    (overlay_settings, style): (Res<OverlaySettings>, Res<Style>),
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut fly_to_events: EventWriter<FlyTo>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+
+        // Only handle .gpx files, other overlay types can hook in here later
+        let is_gpx = path_buf
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gpx"));
+        if !is_gpx {
+            continue;
+        }
+
+        match load_gpx_file(path_buf, overlay_settings.antimeridian) {
+            Ok(track) => {
+                info!(
+                    "Loaded GPX {} with {} segments, {} points",
+                    path_buf.display(),
+                    track.segments.len(),
+                    track.point_count()
+                );
+                let gpx_style = GpxStyle { width: style.gpx_width, color: style.gpx_color, ..default() };
+                let parent = spawn_gpx_track(&mut commands, &mut meshes, &mut materials, &track, &gpx_style);
+                // The segments carry the style's height, the parent stays on the ground
+                commands.entity(parent).insert(LayerItem { layer: GPX_LAYER, elevation: 0.0 });
+
+                let (Some(bounds), Ok(camera_transform)) = (track.bounds(), camera_query.get_single()) else {
+                    continue;
+                };
+                let camera = camera_transform.translation;
+                let camera_geo = GeoPos::from_translation(camera);
+                if !bounds.contains(camera_geo.lat, camera_geo.lon) {
+                    // The bounds centre is correct for tracks crossing the antimeridian too
+                    let (lat, lon) = bounds.center();
+                    let target = GeoPos::new(lat, lon).world();
+                    fly_to_events.send(FlyTo {
+                        target: Vec3::new(target.x as f32, camera.y, target.y as f32),
+                        duration: TRACK_FLIGHT_DURATION,
+                    });
+                }
+            }
+            Err(e) => warn!("Failed to load GPX file {}: {}", path_buf.display(), e),
+        }
+    }
+}
+
+/// Read CSV and GeoJSON files dropped onto the window once and hand each to the one target
+/// that shows it: markers while marker import is on, otherwise a heatmap or street labels
+pub fn route_dropped_data_files(
+    mut marker_import: ResMut<MarkerImport>,
+    mut drop_events: EventReader<FileDragAndDrop>,
+    mut dropped_events: EventWriter<DataFileDropped>,
+) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        let name = path_buf.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if !is_data_file(&name) {
+            continue;
+        }
+
+        let routed = std::fs::read_to_string(path_buf)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| route_dropped_data(&name, text, marker_import.active));
+        match routed {
+            Ok(Some((target, data))) => {
+                dropped_events.send(DataFileDropped { path: path_buf.clone(), target, data });
+            }
+            Ok(None) => info!("Nothing to show in {}", path_buf.display()),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path_buf.display(), e);
+                // Shown on the marker import panel
+                if marker_import.active {
+                    marker_import.error = Some(format!("{}: {}", name, e));
+                }
+            }
+        }
+    }
+}
+
+/// Lay the names of the roads of dropped GeoJSON files on the ground
+/// Long roads split into many features only get a label every `min_spacing` world units
+pub fn handle_street_lines_drop(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut fonts: ResMut<LabelFonts>,
+    (settings, overlay_settings): (Res<StreetLabelSettings>, Res<OverlaySettings>),
+    mut dropped_events: EventReader<DataFileDropped>,
+) {
+    for event in dropped_events.read() {
+        let (DropTarget::StreetLabels, DroppedData::GeoJson(json)) = (event.target, &event.data) else {
+            continue;
+        };
+
+        let lines = match street_lines(json, overlay_settings.antimeridian) {
+            Ok(lines) => lines,
+            Err(e) => {
+                warn!("Failed to load street names from {}: {}", event.path.display(), e);
+                continue;
+            }
+        };
+
+        // Label centres placed so far, per street name
+        let mut placed: HashMap<&str, Vec<DVec2>> = HashMap::new();
+        let mut label_count = 0;
+
+        for line in &lines {
+            let centre = line.points[line.points.len() / 2];
+            let too_close = placed
+                .get(line.name.as_str())
+                .is_some_and(|centres| centres.iter().any(|c| c.distance(centre) < settings.min_spacing as f64));
+            if too_close {
+                continue;
+            }
+
+            let spawned = spawn_street_label(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut images,
+                &mut fonts,
+                line,
+                &settings,
+            );
+            if let Some(label) = spawned {
+                commands.entity(label).insert(LayerItem { layer: STREET_NAMES_LAYER, elevation: settings.elevation_offset });
+                placed.entry(line.name.as_str()).or_default().push(centre);
+                label_count += 1;
+            }
+        }
+
+        info!("Loaded {} street lines from {}, placed {} labels", lines.len(), event.path.display(), label_count);
+    }
+}
+
+/// Add dropped files of weighted points as heatmap layers on top of the imagery, see
+/// osm::heatmap; the layer reads the points from the file itself
+#[cfg(not(target_arch = "wasm32"))]
+pub fn handle_heatmap_drop(mut layers: ResMut<ImageryLayers>, mut dropped_events: EventReader<DataFileDropped>) {
+    for event in dropped_events.read().filter(|event| event.target == DropTarget::Heatmap) {
+        let path = &event.path;
+        let name = path.file_name().map_or("Heatmap".into(), |name| name.to_string_lossy());
+        let url = format!("{}{}", HEATMAP_SCHEME, path.display());
+        if layers.layers.iter().any(|layer| layer.url == url) {
+            info!("Heatmap {} is already a layer", path.display());
+            continue;
+        }
+        layers.add(&name, &url, None, HEATMAP_OPACITY, true);
+        info!("Added heatmap layer {}", path.display());
+    }
+}
+
+/// Only show street labels at walk-mode heights, from higher up they are unreadable clutter
+/// Labels hidden on the layer panel stay hidden at any height
+pub fn update_street_label_visibility(
+    settings: Res<StreetLabelSettings>,
+    manager: Res<LayerManager>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut labels: Query<&mut Visibility, With<StreetLabel>>,
+) {
+    let Ok(camera_transform) = camera_query.get_single() else {
+        return;
+    };
+
+    let shown = manager.get(STREET_NAMES_LAYER).is_none_or(|layer| layer.visible);
+    let visibility = if shown && camera_transform.translation.y < settings.max_camera_height {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for mut label_visibility in labels.iter_mut() {
+        label_visibility.set_if_neq(visibility);
+    }
+}
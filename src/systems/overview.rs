@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy::render::camera::{RenderTarget, ScalingMode};
+use bevy::window::{WindowRef, WindowResolution};
+use crate::components::{MainCamera, OverviewCamera, OverviewWindow};
+use crate::resources::{InputMap, OverviewSettings};
+use crate::resources::input_map::TOGGLE_OVERVIEW_WINDOW;
+
+/// Open or close the overview window (F9 by default): a second window looking straight down
+/// on the same world, centred below the main camera, to watch the tile selection from above
+/// It shows the tiles the main camera loaded, it doesn't load any of its own
+pub fn toggle_overview_window(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    settings: Res<OverviewSettings>,
+    window_query: Query<Entity, With<OverviewWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_OVERVIEW_WINDOW) {
+        return;
+    }
+
+    // Its camera goes along, see close_overview_camera
+    if let Ok(window) = window_query.get_single() {
+        commands.entity(window).despawn_recursive();
+        return;
+    }
+
+    let window = commands
+        .spawn((
+            Window {
+                title: "Vibers overview".to_string(),
+                resolution: WindowResolution::new(settings.window_size.x, settings.window_size.y),
+                ..default()
+            },
+            OverviewWindow,
+        ))
+        .id();
+
+    // Orthographic camera looking straight down, north up, like the minimap's
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Window(WindowRef::Entity(window)),
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.1, 0.1, 0.15)),
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::FixedVertical { viewport_height: 1.0 },
+            far: 1_000_000.0, // Camera follows the main camera height, which can be very high
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_xyz(0.0, 100.0, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+        OverviewCamera { window },
+    ));
+}
+
+/// Remove the overview camera once its window is closed, from the window's close button too
+pub fn close_overview_camera(
+    mut commands: Commands,
+    camera_query: Query<(Entity, &OverviewCamera)>,
+    window_query: Query<(), With<Window>>,
+) {
+    for (entity, camera) in &camera_query {
+        if window_query.get(camera.window).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Keep the overview camera centred above the main camera, zoomed by altitude
+pub fn update_overview_camera(
+    settings: Res<OverviewSettings>,
+    main_camera: Query<&Transform, (With<MainCamera>, Without<OverviewCamera>)>,
+    mut overview_camera: Query<(&mut Transform, &mut Projection), With<OverviewCamera>>,
+) {
+    let Ok(main_transform) = main_camera.get_single() else {
+        return;
+    };
+
+    let position = main_transform.translation;
+    let height = position.y.max(1.0);
+    for (mut transform, mut projection) in &mut overview_camera {
+        *transform = Transform::from_xyz(position.x, height + 10.0, position.z)
+            .looking_at(Vec3::new(position.x, 0.0, position.z), Vec3::NEG_Z);
+
+        if let Projection::Orthographic(ortho) = projection.as_mut() {
+            ortho.scale = settings.extent(height);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use crate::components::{LayerItem, MainCamera};
+use crate::overlays::roads::{road_meshes, road_query};
+use crate::resources::{HttpClient, LayerManager, Overpass, RoadNetwork, TaskRuntime, UserSettings};
+use crate::resources::layer_manager::ROADS_LAYER;
+use crate::resources::overpass::OverpassTile;
+use crate::utils::geo::{tile_world_origin, GeoPos};
+
+// Zoom level of the tiles roads are queried by, each about a kilometer wide
+const ROAD_ZOOM: u32 = 16;
+// Roads are loaded below this camera height, higher up the raster tiles show them sharp enough
+const MAX_CAMERA_HEIGHT: f32 = 0.1;
+// Tiles around the camera's tile whose roads are loaded, and beyond which they are dropped
+const LOAD_RANGE: i64 = 1;
+const KEEP_RANGE: i64 = 2;
+// Queries in flight at once; the public Overpass servers allow a couple per client
+const MAX_LOADING: usize = 2;
+// Height of the ribbons over the tiles (the focus tiles reach 0.005), each class a little above
+// the one under it so crossings don't flicker
+const ROAD_ELEVATION: f32 = 0.008;
+const CLASS_STEP: f32 = 0.0002;
+
+/// Ask the Overpass server for the roads of the tiles around the camera while it is near the
+/// ground, nearest tiles first, and drop the roads the camera left behind
+pub fn request_road_tiles(
+    mut commands: Commands,
+    mut network: ResMut<RoadNetwork>,
+    mut overpass: ResMut<Overpass>,
+    (manager, settings): (Res<LayerManager>, Res<UserSettings>),
+    (task_runtime, http_client): (Res<TaskRuntime>, Res<HttpClient>),
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = GeoPos::from_translation(camera.translation).tile(ROAD_ZOOM);
+    for ribbon in network.tiles.drop_beyond(center, KEEP_RANGE) {
+        commands.entity(ribbon).despawn();
+    }
+
+    let shown = manager.get(ROADS_LAYER).is_none_or(|layer| layer.visible);
+    if !shown || camera.translation.y > MAX_CAMERA_HEIGHT {
+        return;
+    }
+    let client = overpass.client(&settings, &http_client);
+    network.tiles.request_around(center, LOAD_RANGE, MAX_LOADING, road_query, (&client, &task_runtime));
+}
+
+/// Draw the roads of the tiles whose query was answered, a ribbon mesh per road class
+pub fn receive_road_tiles(
+    mut commands: Commands,
+    mut network: ResMut<RoadNetwork>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (id, data) in network.tiles.take_answers() {
+        let origin = tile_world_origin(id).as_vec2();
+        let ribbons = road_meshes(&data, id)
+            .into_iter()
+            .map(|(class, mesh)| {
+                let material = network.materials.entry(class).or_insert_with(|| {
+                    materials.add(StandardMaterial {
+                        base_color: class.color(),
+                        unlit: true,
+                        double_sided: true,
+                        cull_mode: None,
+                        ..default()
+                    })
+                });
+                let elevation = ROAD_ELEVATION + class as usize as f32 * CLASS_STEP;
+                commands
+                    .spawn((
+                        Mesh3d(meshes.add(mesh)),
+                        MeshMaterial3d(material.clone()),
+                        Transform::from_xyz(origin.x, elevation, origin.y),
+                        LayerItem { layer: ROADS_LAYER, elevation },
+                        Name::new(format!("Roads {}/{}/{} {:?}", id.z, id.x, id.y, class)),
+                    ))
+                    .id()
+            })
+            .collect();
+        debug!("Roads of tile {}/{}/{}: {} ways", id.z, id.x, id.y, data.ways.len());
+        network.tiles.tiles.insert(id, OverpassTile::Loaded(ribbons));
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use std::sync::Arc;
+use crate::components::{LayerItem, MainCamera, RouteLine, RouteText};
+use crate::overlays::gpx::create_polyline_mesh;
+use crate::overlays::routing::{osrm_route_url, request_route};
+use crate::resources::{CursorPick, HttpClient, InputMap, Routing, TaskRuntime, UserSettings};
+use crate::resources::input_map::TOGGLE_ROUTING;
+use crate::resources::layer_manager::ROUTE_LAYER;
+use crate::resources::routing::RouteReply;
+use crate::systems::measurement::format_distance;
+use crate::utils::geo::lat_lon_to_world;
+
+// Line width as a fraction of the camera height, so the route looks the same at any altitude
+const LINE_WIDTH: f32 = 0.006;
+// Height of the route above the tiles; the layer manager lifts it over lower overlay layers
+const LINE_ELEVATION: f32 = 0.02;
+const LINE_COLOR: Color = Color::srgb(0.15, 0.5, 1.0);
+// The route mesh is rebuilt when the camera height changes the line width by more than this
+const WIDTH_TOLERANCE: f32 = 0.1;
+
+/// Run condition: whether routing mode is on, so clicks pick route points instead of zooming
+pub fn routing(routing: Option<Res<Routing>>) -> bool {
+    routing.is_some_and(|routing| routing.active)
+}
+
+/// Switch routing mode on or off (R by default), dropping the previous route
+pub fn toggle_routing(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut routing: ResMut<Routing>,
+) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_ROUTING) {
+        routing.active = !routing.active;
+        routing.clear();
+        info!("Routing mode: {}", if routing.active { "ON" } else { "OFF" });
+    }
+}
+
+/// The first click picks the start, the second the end and requests the route between them
+/// from the routing server; the next click starts over
+pub fn place_route_points(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    cursor_pick: Res<CursorPick>,
+    mut routing: ResMut<Routing>,
+    ui_query: Query<&Interaction>,
+    (settings, task_runtime, http_client): (Res<UserSettings>, Res<TaskRuntime>, Res<HttpClient>),
+) {
+    if !routing.active || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    // Clicks on buttons are not meant for the map
+    if ui_query.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+    let Some(lat_lon) = cursor_pick.lat_lon else {
+        return;
+    };
+
+    let Some(start) = routing.start.filter(|_| routing.end.is_none()) else {
+        routing.clear();
+        routing.start = Some(lat_lon);
+        return;
+    };
+    routing.end = Some(lat_lon);
+
+    let url = osrm_route_url(&settings.routing_server, &settings.routing_profile, start, lat_lon);
+    info!("Requesting route: {}", url);
+    let reply: RouteReply = Arc::default();
+    routing.pending = Some(Arc::clone(&reply));
+    let client = http_client.client.clone();
+    task_runtime.spawn(async move {
+        let result = request_route(&client, &url).await.map_err(|e| e.to_string());
+        *reply.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+    });
+}
+
+/// Take the route from the routing server once it answered
+pub fn receive_route(mut routing: ResMut<Routing>) {
+    // Only reading the reply doesn't mark the routing changed, so the route isn't redrawn every frame
+    let Some(result) = routing.pending.as_ref().and_then(|reply| reply.lock().unwrap_or_else(|e| e.into_inner()).take()) else {
+        return;
+    };
+
+    routing.pending = None;
+    match result {
+        Ok(route) => {
+            info!("Route of {:.0} m with {} points", route.distance_m, route.points.len());
+            routing.route = Some(route);
+        }
+        Err(e) => {
+            warn!("Failed to get a route: {}", e);
+            routing.error = Some(e);
+        }
+    }
+}
+
+/// Draw the route, or a straight line from the start to the cursor while picking the end
+pub fn update_route_line(
+    mut commands: Commands,
+    routing: Res<Routing>,
+    cursor_pick: Res<CursorPick>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>),
+    camera_query: Query<&GlobalTransform, With<MainCamera>>,
+    mut line_query: Query<(Entity, &mut Transform, &Mesh3d), With<RouteLine>>,
+    mut drawn_width: Local<f32>,
+) {
+    let points: Vec<(f64, f64)> = match (&routing.route, routing.start, routing.end) {
+        (Some(route), ..) => route.points.clone(),
+        (None, Some(start), None) => [Some(start), cursor_pick.lat_lon].into_iter().flatten().collect(),
+        _ => Vec::new(),
+    };
+
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    if !routing.active || points.len() < 2 {
+        for (entity, ..) in line_query.iter() {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    // Long routes have thousands of points, their mesh is only rebuilt when the route or the
+    // camera height changed; the line to the cursor follows it every frame
+    let width = camera.translation().y.max(0.01) * LINE_WIDTH;
+    let width_changed = (width - *drawn_width).abs() > *drawn_width * WIDTH_TOLERANCE;
+    let following_cursor = routing.route.is_none();
+    if !line_query.is_empty() && !routing.is_changed() && !width_changed && !following_cursor {
+        return;
+    }
+    *drawn_width = width;
+
+    // Vertices relative to the first point keep full f32 precision, like GPX tracks
+    let anchor = lat_lon_to_world(points[0].0, points[0].1);
+    let vertices: Vec<Vec3> = points
+        .iter()
+        .map(|&(lat, lon)| {
+            let offset = lat_lon_to_world(lat, lon) - anchor;
+            Vec3::new(offset.x as f32, 0.0, offset.y as f32)
+        })
+        .collect();
+    let mesh = create_polyline_mesh(&vertices, width);
+    let translation = Vec3::new(anchor.x as f32, LINE_ELEVATION, anchor.y as f32);
+
+    match line_query.get_single_mut() {
+        Ok((_, mut transform, line_mesh)) => {
+            transform.translation = translation;
+            if let Some(line_mesh) = meshes.get_mut(&line_mesh.0) {
+                *line_mesh = mesh;
+            }
+        }
+        Err(_) => {
+            commands.spawn((
+                Mesh3d(meshes.add(mesh)),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: LINE_COLOR,
+                    unlit: true,
+                    double_sided: true,
+                    cull_mode: None,
+                    ..default()
+                })),
+                Transform::from_translation(translation),
+                RouteLine,
+                LayerItem { layer: ROUTE_LAYER, elevation: LINE_ELEVATION },
+                Name::new("Route"),
+            ));
+        }
+    }
+}
+
+/// Spawn the route panel (bottom center, above the measurement panel), hidden until routing
+pub fn setup_route_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(110.0),
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(-150.0)),
+            width: Val::Px(300.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        Visibility::Hidden,
+        RouteText,
+    ));
+}
+
+/// Show what to click next, and the route's distance and duration once it arrived
+pub fn update_route_text(
+    routing: Res<Routing>,
+    mut text_query: Query<(&mut Text, &mut Visibility), With<RouteText>>,
+) {
+    if !routing.is_changed() {
+        return;
+    }
+
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+    *visibility = if routing.active { Visibility::Inherited } else { Visibility::Hidden };
+
+    text.0 = match (&routing.route, &routing.error, routing.start, routing.end) {
+        (Some(route), ..) => format!(
+            "Distance: {}\nDuration: {}",
+            format_distance(route.distance_m),
+            format_duration(route.duration_s)
+        ),
+        (None, Some(error), ..) => format!("No route: {}", error),
+        (None, None, None, _) => "Route: click on the map to pick the start".to_string(),
+        (None, None, Some(_), None) => "Route: click on the map to pick the destination".to_string(),
+        (None, None, Some(_), Some(_)) => "Route: requesting...".to_string(),
+    };
+}
+
+fn format_duration(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    if minutes < 60 {
+        format!("{} min", minutes.max(1))
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
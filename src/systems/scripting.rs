@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy::window::FileDragAndDrop;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::components::MainCamera;
+use crate::events::{FlyTo, SpawnGeoModel, TileDespawned, TileVisibilityChanged};
+use crate::osm::TileId;
+use crate::resources::Markers;
+use crate::resources::markers::MarkerIcon;
+use crate::resources::scripting::{configured_scripts, ScriptCommand, ScriptView, Scripting};
+use crate::utils::geo::{GeoPos, GeoTransform};
+
+// Color of the pins scripts put on the map
+const SCRIPT_PIN_COLOR: Color = Color::srgb(0.55, 0.3, 0.85);
+
+/// Run the scripts listed in the settings file
+pub fn load_configured_scripts(mut scripting: ResMut<Scripting>) {
+    for path in configured_scripts() {
+        load_script(&mut scripting, &path);
+    }
+}
+
+/// Run Rhai scripts dropped on the window; dropping a running script again restarts it
+pub fn handle_script_drop(mut scripting: ResMut<Scripting>, mut drop_events: EventReader<FileDragAndDrop>) {
+    for event in drop_events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        if path_buf.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("rhai")) {
+            load_script(&mut scripting, path_buf);
+        }
+    }
+}
+
+fn load_script(scripting: &mut Scripting, path: &Path) {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let loaded = fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|source| scripting.load(&name, &source));
+    match loaded {
+        Ok(()) => info!("Ran script {}", path.display()),
+        Err(e) => warn!("Failed to run script {}: {}", path.display(), e),
+    }
+}
+
+/// Show the scripts where the camera is and which tiles are in view, then call their
+/// on_update hooks
+/// The tiles in view are followed through the tile events, also while no script runs
+pub fn run_scripts(
+    mut scripting: ResMut<Scripting>,
+    time: Res<Time>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    (mut visibility_events, mut despawned_events): (EventReader<TileVisibilityChanged>, EventReader<TileDespawned>),
+    mut visible: Local<HashMap<Entity, TileId>>,
+) {
+    for event in visibility_events.read() {
+        if event.visible {
+            visible.insert(event.entity, event.id);
+        } else {
+            visible.remove(&event.entity);
+        }
+    }
+    for event in despawned_events.read() {
+        visible.remove(&event.entity);
+    }
+
+    if !scripting.is_running() {
+        return;
+    }
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let geo = GeoTransform::from_translation(camera.translation);
+    let visible_tiles = visible.values().copied().collect();
+    scripting.set_view(ScriptView { camera: (geo.position.lat, geo.position.lon, geo.altitude_m), visible_tiles });
+    scripting.update(time.delta_secs());
+}
+
+/// Do what the scripts asked for
+pub fn apply_script_commands(
+    scripting: Res<Scripting>,
+    asset_server: Res<AssetServer>,
+    mut markers: ResMut<Markers>,
+    mut fly_to_events: EventWriter<FlyTo>,
+    mut spawn_events: EventWriter<SpawnGeoModel>,
+) {
+    for command in scripting.take_commands() {
+        match command {
+            ScriptCommand::FlyTo { lat, lon, altitude_m, duration } => {
+                let target = GeoTransform::new(GeoPos::new(lat, lon), altitude_m).translation();
+                fly_to_events.send(FlyTo { target, duration });
+            }
+            ScriptCommand::AddMarker { lat, lon, label, icon } => {
+                let icon = match icon {
+                    Some(path) => MarkerIcon::Image(asset_server.load(path)),
+                    None => MarkerIcon::Pin(SCRIPT_PIN_COLOR),
+                };
+                markers.add_marker(lat, lon, icon, label);
+            }
+            ScriptCommand::SpawnModel(model) => {
+                spawn_events.send(model);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::time::TimeUpdateStrategy;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+use crate::components::MainCamera;
+use crate::events::{TileDespawned, TileSpawned};
+use crate::osm::TileId;
+use crate::resources::{LaunchOptions, OSMData};
+use crate::resources::session::{parse_session, CameraSession, SessionFrame, SessionTotals};
+use crate::utils::geo::world_origin;
+
+/// Read the session to replay and create the log to record, as given on the command line
+/// The app quits when either fails, as a run without them would compare nothing
+pub fn start_camera_session(
+    mut commands: Commands,
+    launch: Res<LaunchOptions>,
+    mut session: ResMut<CameraSession>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(path) = &launch.replay_session {
+        let frames = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| parse_session(&contents));
+        match frames {
+            Ok(frames) if !frames.is_empty() => {
+                info!("Replaying {} frames from {}", frames.len(), path.display());
+                commands.insert_resource(frame_time(frames[0].delta));
+                session.replay = frames;
+            }
+            Ok(_) => {
+                error!("No frames to replay in {}", path.display());
+                exit.send(AppExit::error());
+            }
+            Err(e) => {
+                error!("Failed to read camera session {}: {}", path.display(), e);
+                exit.send(AppExit::error());
+            }
+        }
+    }
+
+    if let Some(path) = &launch.record_session {
+        match File::create(path) {
+            Ok(file) => {
+                info!("Recording camera session to {}", path.display());
+                session.recording = Some(BufWriter::new(file));
+            }
+            Err(e) => {
+                error!("Failed to create camera session {}: {}", path.display(), e);
+                exit.send(AppExit::error());
+            }
+        }
+    }
+}
+
+// Let the next frame take as long as it did when recorded
+fn frame_time(seconds: f32) -> TimeUpdateStrategy {
+    TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(seconds.max(0.0)))
+}
+
+/// Put the camera where it was in the next recorded frame, overriding manual movement, flights
+/// and camera paths; time advances by the recorded frame times, so the tile decisions that
+/// depend on time see the same frames too
+/// Quits with the tile totals of the recorded and, when recording, the replayed run at the end
+pub fn replay_camera_session(
+    mut commands: Commands,
+    mut session: ResMut<CameraSession>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if session.replay.is_empty() {
+        return;
+    }
+    let Some(frame) = session.replay.get(session.next_frame) else {
+        let mut recorded = SessionTotals::default();
+        session.replay.iter().for_each(|frame| recorded.add(frame));
+        println!("Recorded: {}", describe_totals(&recorded));
+        if session.recording.is_some() {
+            println!("Replayed: {}", describe_totals(&session.totals));
+        }
+        session.replay.clear();
+        exit.send(AppExit::Success);
+        return;
+    };
+    let Ok(mut camera) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let origin = world_origin();
+    camera.translation = (frame.translation - DVec3::new(origin.x, 0.0, origin.y)).as_vec3();
+    camera.rotation = frame.rotation;
+    let next_delta = session.replay.get(session.next_frame + 1).map_or(frame.delta, |next| next.delta);
+    commands.insert_resource(frame_time(next_delta));
+    session.next_frame += 1;
+}
+
+fn describe_totals(totals: &SessionTotals) -> String {
+    format!(
+        "{} frames, {} tiles requested, {} spawned, {} despawned",
+        totals.frames, totals.requested, totals.spawned, totals.despawned
+    )
+}
+
+/// Log the camera pose and the tiles requested, spawned and despawned this frame
+/// Runs last, after the tile events of the frame are sent; tiles are sorted so logs of two
+/// runs can be compared line by line
+pub fn record_camera_session(
+    mut session: ResMut<CameraSession>,
+    time: Res<Time>,
+    osm_data: Res<OSMData>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    (mut spawned_events, mut despawned_events): (EventReader<TileSpawned>, EventReader<TileDespawned>),
+    mut known_tiles: Local<HashSet<(u32, u32, u32)>>,
+) {
+    if session.recording.is_none() {
+        return;
+    }
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    // Tiles are requested by adding them to the loaded sets, and stay there while spawned
+    let loaded: HashSet<(u32, u32, u32)> = osm_data.loaded_tiles.union(&osm_data.loaded_background_tiles).copied().collect();
+    let requested = loaded.difference(&known_tiles).map(|&(x, y, z)| TileId::new(x, y, z));
+    let origin = world_origin();
+    let frame = SessionFrame {
+        delta: time.delta_secs(),
+        translation: camera.translation.as_dvec3() + DVec3::new(origin.x, 0.0, origin.y),
+        rotation: camera.rotation,
+        requested: sorted(requested),
+        spawned: sorted(spawned_events.read().map(|event| event.id)),
+        despawned: sorted(despawned_events.read().map(|event| event.id)),
+    };
+    *known_tiles = loaded;
+
+    session.totals.add(&frame);
+    let Some(writer) = session.recording.as_mut() else {
+        return;
+    };
+    if let Err(e) = writeln!(writer, "{}", frame.to_json_line()).and_then(|_| writer.flush()) {
+        warn!("Stopped recording the camera session: {}", e);
+        session.recording = None;
+    }
+}
+
+fn sorted(tiles: impl Iterator<Item = TileId>) -> Vec<TileId> {
+    let mut tiles: Vec<TileId> = tiles.collect();
+    tiles.sort_by_key(|tile| (tile.z, tile.x, tile.y));
+    tiles
+}
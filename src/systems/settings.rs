@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use std::time::Duration;
+use crate::events::SettingsChanged;
+use crate::resources::{DebugSettings, HttpClient, MovementSettings, OSMData, OverlaySettings, UserSettings};
+use crate::components::MainCamera;
+
+/// Announce changes to the user settings and save them
+/// The first run (right after loading) only announces, there is nothing new to save
+pub fn announce_settings_changes(
+    settings: Res<UserSettings>,
+    mut changed_events: EventWriter<SettingsChanged>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if !settings.is_added() {
+        if let Err(e) = settings.save() {
+            warn!("Failed to save settings: {}", e);
+        }
+    }
+
+    changed_events.send(SettingsChanged);
+}
+
+// The settings the tile sources are opened with: user agent, cache ttl and tile server URL
+type SourceSettings = (String, Duration, String);
+
+/// Apply the user settings to the resources and camera that use them
+pub fn apply_settings(
+    mut changed_events: EventReader<SettingsChanged>,
+    settings: Res<UserSettings>,
+    (mut movement_settings, mut debug_settings, mut overlay_settings): (
+        ResMut<MovementSettings>,
+        ResMut<DebugSettings>,
+        ResMut<OverlaySettings>,
+    ),
+    mut osm_data: ResMut<OSMData>,
+    mut http_client: ResMut<HttpClient>,
+    mut camera_query: Query<(Option<&mut Projection>, Option<&mut PerspectiveProjection>), With<MainCamera>>,
+    mut source_settings: Local<Option<SourceSettings>>,
+) {
+    if changed_events.read().count() == 0 {
+        return;
+    }
+
+    movement_settings.base_speed = settings.movement_speed;
+    movement_settings.look_sensitivity = settings.look_sensitivity;
+    movement_settings.min_clearance = settings.min_clearance;
+    debug_settings.debug_mode = settings.debug_mode;
+    // Tracks and lines already on the map keep the way they were drawn, the next dropped ones use it
+    overlay_settings.antimeridian = settings.antimeridian;
+
+    // A smaller budget takes effect as new textures are inserted and old ones evicted
+    osm_data.texture_cache.capacity_bytes = settings.texture_cache_mb * 1024 * 1024;
+
+    osm_data.render_distance = settings.render_distance as i32;
+    osm_data.tile_ttl = settings.tile_ttl();
+    // Downloads over a lowered limit finish, no new ones start until the class is below it
+    osm_data.downloads.limits = settings.download_limits();
+
+    // Requests already underway finish on the old client and its connections
+    if http_client.user_agent != settings.user_agent {
+        *http_client = HttpClient::new(&settings.user_agent);
+    }
+    // Sources are opened with the client and ttl, new tiles go through new ones once they
+    // change; other settings keep the open sources and what they have read so far
+    let current = (settings.user_agent.clone(), settings.tile_ttl(), settings.tile_server.clone());
+    if source_settings.as_ref() != Some(&current) {
+        osm_data.sources.clear();
+        *source_settings = Some(current);
+    }
+
+    // Camera3d adds a Projection next to the PerspectiveProjection the camera is spawned with,
+    // update both so the new field of view applies whichever one the camera ends up using
+    let fov = settings.fov_degrees.to_radians();
+    for (projection, perspective) in camera_query.iter_mut() {
+        if let Some(mut projection) = projection {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = fov;
+            }
+        }
+        if let Some(mut perspective) = perspective {
+            perspective.fov = fov;
+        }
+    }
+}
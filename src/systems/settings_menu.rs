@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use crate::components::{SettingsMenu, SettingsMenuButton, SettingsField, SettingValueText};
+use crate::resources::{ImageryLayers, InputMap, RebindState, UserSettings, BASE_LAYER_ID};
+use crate::resources::input_map::TOGGLE_SETTINGS_MENU;
+use crate::resources::stereo::StereoMode;
+use crate::resources::user_settings::{
+    tile_provider, LOOK_SENSITIVITY_RANGE, MAX_RENDER_DISTANCE, MOVEMENT_SPEED_RANGE, TILE_PROVIDERS,
+};
+use crate::utils::geo::AntimeridianMode;
+
+// Fields in the order they are shown, with their labels
+const MENU_FIELDS: &[(SettingsField, &str)] = &[
+    (SettingsField::TileProvider, "Tile provider"),
+    (SettingsField::TextureCache, "Texture cache"),
+    (SettingsField::RenderDistance, "Render distance"),
+    (SettingsField::FieldOfView, "Field of view"),
+    (SettingsField::MovementSpeed, "Movement speed"),
+    (SettingsField::LookSensitivity, "Mouse sensitivity"),
+    (SettingsField::Stereo, "Stereo 3D"),
+    (SettingsField::Antimeridian, "Lines across ±180°"),
+    (SettingsField::DebugOverlays, "Debug overlays"),
+];
+
+// Texture cache budget change per click, and the smallest budget the menu allows
+const CACHE_STEP_MB: usize = 32;
+const MIN_CACHE_MB: usize = 16;
+// Factor speed and sensitivity are multiplied or divided by per click
+const SCALE_STEP: f32 = 1.25;
+
+/// Open or close the settings menu
+/// The cursor is released while the menu is open so the buttons can be clicked
+pub fn toggle_settings_menu(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    rebind_state: Res<RebindState>,
+    menu_query: Query<Entity, With<SettingsMenu>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // Keys pressed while waiting for a rebind belong to the rebind
+    if rebind_state.waiting.is_some() || !input_map.just_pressed(&keyboard_input, TOGGLE_SETTINGS_MENU) {
+        return;
+    }
+
+    if let Ok(menu) = menu_query.get_single() {
+        commands.entity(menu).despawn_recursive();
+        return;
+    }
+
+    spawn_settings_menu(&mut commands);
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+// Build the menu: one row per setting with buttons to step it down and up
+fn spawn_settings_menu(commands: &mut Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(110.0),
+                right: Val::Px(10.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            SettingsMenu,
+        ))
+        .with_children(|menu| {
+            menu.spawn((
+                Text::new("Settings - changes apply immediately and are saved"),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+            ));
+
+            for (field, label) in MENU_FIELDS {
+                menu.spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Text::new(*label),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        Node {
+                            width: Val::Px(140.0),
+                            ..default()
+                        },
+                    ));
+                    spawn_step_button(row, *field, -1);
+                    row.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        Node {
+                            min_width: Val::Px(140.0),
+                            ..default()
+                        },
+                        SettingValueText { field: *field },
+                    ));
+                    spawn_step_button(row, *field, 1);
+                });
+            }
+        });
+}
+
+fn spawn_step_button(row: &mut ChildBuilder, field: SettingsField, step: i32) {
+    row.spawn((
+        Button,
+        Node {
+            padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+        SettingsMenuButton { field, step },
+    ))
+    .with_child((
+        Text::new(if step < 0 { "<" } else { ">" }),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+    ));
+}
+
+/// Step a setting when its button is clicked
+/// Writing to UserSettings saves the settings and applies them right away
+/// A picked tile provider also replaces the one from --provider, even when it is the saved one
+pub fn handle_settings_menu_buttons(
+    mut settings: ResMut<UserSettings>,
+    mut layers: ResMut<ImageryLayers>,
+    button_query: Query<(&Interaction, &SettingsMenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            let tile_server = base_server(&layers, &settings);
+            step_setting(&mut settings, button.field, button.step, tile_server);
+            if button.field == SettingsField::TileProvider {
+                layers.set_base_server(&settings.tile_server);
+            }
+        }
+    }
+}
+
+// The tile server the map shows, the one from --provider until another one is picked
+fn base_server(layers: &ImageryLayers, settings: &UserSettings) -> String {
+    let base = layers.layers.iter().find(|layer| layer.id == BASE_LAYER_ID);
+    base.map_or_else(|| settings.tile_server.clone(), |layer| layer.url.clone())
+}
+
+fn step_setting(settings: &mut UserSettings, field: SettingsField, step: i32, tile_server: String) {
+    match field {
+        SettingsField::TileProvider => {
+            // A custom server from the settings file counts as sitting before the first provider
+            let count = TILE_PROVIDERS.len() as i32;
+            let current = TILE_PROVIDERS
+                .iter()
+                .position(|provider| provider.url == tile_server)
+                .map_or(if step > 0 { -1 } else { 0 }, |index| index as i32);
+            let next = (current + step).rem_euclid(count) as usize;
+            settings.tile_server = TILE_PROVIDERS[next].url.to_string();
+        }
+        SettingsField::TextureCache => {
+            settings.texture_cache_mb = if step > 0 {
+                settings.texture_cache_mb + CACHE_STEP_MB
+            } else {
+                settings.texture_cache_mb.saturating_sub(CACHE_STEP_MB).max(MIN_CACHE_MB)
+            };
+        }
+        SettingsField::RenderDistance => {
+            settings.render_distance = settings
+                .render_distance
+                .saturating_add_signed(step)
+                .clamp(1, MAX_RENDER_DISTANCE);
+        }
+        SettingsField::FieldOfView => {
+            settings.fov_degrees = (settings.fov_degrees + 5.0 * step as f32).clamp(10.0, 170.0);
+        }
+        SettingsField::MovementSpeed => {
+            let (min, max) = MOVEMENT_SPEED_RANGE;
+            settings.movement_speed = (settings.movement_speed * SCALE_STEP.powi(step)).clamp(min, max);
+        }
+        SettingsField::LookSensitivity => {
+            let (min, max) = LOOK_SENSITIVITY_RANGE;
+            settings.look_sensitivity = (settings.look_sensitivity * SCALE_STEP.powi(step)).clamp(min, max);
+        }
+        SettingsField::Stereo => {
+            let modes = StereoMode::ALL;
+            let current = modes.iter().position(|mode| *mode == settings.stereo_mode).unwrap_or(0) as i32;
+            settings.stereo_mode = modes[(current + step).rem_euclid(modes.len() as i32) as usize];
+        }
+        SettingsField::Antimeridian => {
+            let modes = AntimeridianMode::ALL;
+            let current = modes.iter().position(|mode| *mode == settings.antimeridian).unwrap_or(0) as i32;
+            settings.antimeridian = modes[(current + step).rem_euclid(modes.len() as i32) as usize];
+        }
+        SettingsField::DebugOverlays => {
+            settings.debug_mode = !settings.debug_mode;
+        }
+    }
+}
+
+/// Show the current value of every setting on the settings menu
+pub fn update_settings_menu_values(
+    settings: Res<UserSettings>,
+    layers: Res<ImageryLayers>,
+    mut text_query: Query<(&SettingValueText, &mut Text)>,
+) {
+    for (value_text, mut text) in text_query.iter_mut() {
+        let value = describe_setting(&settings, value_text.field, &base_server(&layers, &settings));
+        if text.0 != value {
+            text.0 = value;
+        }
+    }
+}
+
+fn describe_setting(settings: &UserSettings, field: SettingsField, tile_server: &str) -> String {
+    match field {
+        SettingsField::TileProvider => tile_provider(tile_server)
+            .map_or_else(|| "Custom".to_string(), |provider| provider.name.to_string()),
+        SettingsField::TextureCache => format!("{} MB", settings.texture_cache_mb),
+        SettingsField::RenderDistance => format!("{} tiles", settings.render_distance),
+        SettingsField::FieldOfView => format!("{:.0} degrees", settings.fov_degrees),
+        SettingsField::MovementSpeed => format!("{:.1}", settings.movement_speed),
+        SettingsField::LookSensitivity => format!("{:.4}", settings.look_sensitivity),
+        SettingsField::Stereo => settings.stereo_mode.name().to_string(),
+        SettingsField::Antimeridian => match settings.antimeridian {
+            AntimeridianMode::Split => "Split",
+            AntimeridianMode::Wrap => "Wrapped",
+        }
+        .to_string(),
+        SettingsField::DebugOverlays => if settings.debug_mode { "On" } else { "Off" }.to_string(),
+    }
+}
@@ -1,60 +1,20 @@
 use bevy::prelude::*;
-use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL, GRONINGEN_X, GRONINGEN_Y, MAX_TILE_INDEX, zoom_level_from_camera_height};
+use crate::resources::constants::{DEFAULT_ZOOM_LEVEL, MAX_TILE_INDEX};
+#[cfg(not(target_arch = "wasm32"))]
 use crate::osm::init_tile_cache;
-use crate::resources::{OSMData, TokioRuntime, DebugSettings};
-use std::sync::Arc;
-use parking_lot::Mutex;
-use tokio::runtime::Runtime;
+use crate::resources::{OSMData, DebugSettings, LaunchOptions};
+use crate::components::MainCamera;
 use crate::debug_log;
 
 /// Initialize resources for the application
-pub fn init_resources() -> (OSMData, TokioRuntime) {
-    // Create the Tokio runtime
-    let runtime = Runtime::new().expect("Failed to create Tokio runtime");
-
-    // Initialize tile cache
+pub fn init_resources() -> OSMData {
+    // Initialize tile cache, the browser's HTTP cache stands in for it on the web
+    #[cfg(not(target_arch = "wasm32"))]
     if let Err(e) = init_tile_cache() {
         eprintln!("Warning: Failed to initialize tile cache: {}", e);
     }
 
-    // Calculate zoom level height thresholds using the standardized function
-    let mut height_thresholds = Vec::new();
-    
-    // Start from a low height (near the ground) and go up
-    // This provides a more balanced distribution following OpenStreetMap conventions
-    let heights = [
-        1.5, 3.0, 5.0, 8.0, 12.0, 20.0, 35.0, 60.0, 
-        100.0, 150.0, 200.0, 250.0, 300.0, 400.0, 500.0, 
-        700.0, 1000.0, 1500.0, 2000.0
-    ];
-    
-    for height in heights.iter() {
-        let zoom = zoom_level_from_camera_height(*height);
-        if zoom >= MIN_ZOOM_LEVEL && zoom <= MAX_ZOOM_LEVEL {
-            height_thresholds.push((*height, zoom));
-        }
-    }
-    
-    // Sort by height ascending (lower height = higher zoom)
-    height_thresholds.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-    // Log the height thresholds for debugging
-    for (height, zoom) in &height_thresholds {
-        println!("Height {:.1}: zoom level {}", height, zoom);
-    }
-
-    let osm_data = OSMData {
-        tiles: Vec::new(),
-        background_tiles: Vec::new(),
-        loaded_tiles: Vec::new(),
-        loaded_background_tiles: Vec::new(),
-        pending_tiles: Arc::new(Mutex::new(Vec::new())),
-        current_zoom: DEFAULT_ZOOM_LEVEL,
-        background_zoom: BACKGROUND_ZOOM_LEVEL,
-        total_time: 0.0,
-    };
-
-    (osm_data, TokioRuntime(runtime))
+    OSMData::default()
 }
 
 /// Setup the scene with initial camera, lighting, and ground plane
@@ -63,16 +23,19 @@ pub fn setup(
     _meshes: ResMut<Assets<Mesh>>,
     _materials: ResMut<Assets<StandardMaterial>>,
     debug_settings: Res<DebugSettings>,
+    launch: Res<LaunchOptions>,
 ) {
-    // Calculate world coordinates for Groningen location
+    // Calculate world coordinates for the start location, Groningen unless given on the command line
     // With our new coordinate system:
     // - X = OSM tile X (increasing eastward)
     // - Z = OSM tile Y (increasing southward)
-    let world_x = GRONINGEN_X as f32;
-    let world_z = GRONINGEN_Y as f32;  // Direct mapping now, no need to invert
+    let start = launch.start_world();
+    let world_x = start.x as f32;
+    let world_z = start.y as f32;  // Direct mapping now, no need to invert
+    let height = launch.camera_height().unwrap_or(200.0); // Higher camera for better overview
 
     // Camera - positioned slightly elevated with a first-person view
-    // Position at Groningen coordinates
+    // Position at the start coordinates
     commands.spawn((
         Camera3d::default(),
         PerspectiveProjection {
@@ -81,8 +44,9 @@ pub fn setup(
             near: 0.1,
             far: 10000.0,
         },
-        Transform::from_xyz(world_x, 200.0, world_z) // Higher camera for better overview
+        Transform::from_xyz(world_x, height, world_z)
             .looking_at(Vec3::new(world_x, 0.0, world_z), Vec3::Y),
+        MainCamera,
     ));
 
     // Main light - directional to simulate sunlight
@@ -105,6 +69,6 @@ pub fn setup(
 
     // Log current position for debugging (console only)
     debug_log!(debug_settings, "Starting at world position: ({}, {})", world_x, world_z);
-    debug_log!(debug_settings, "Corresponding to OSM tile: ({}, {})", GRONINGEN_X, GRONINGEN_Y);
+    debug_log!(debug_settings, "Corresponding to OSM tile: ({}, {})", start.x.floor(), start.y.floor());
     debug_log!(debug_settings, "Zoom level: {}, MAX_TILE_INDEX: {}", DEFAULT_ZOOM_LEVEL, MAX_TILE_INDEX);
 } 
\ No newline at end of file
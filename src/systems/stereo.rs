@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use bevy::pbr::DistanceFog;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::ui::FocusPolicy;
+use bevy::window::PrimaryWindow;
+use crate::components::{AnaglyphView, MainCamera, StereoEye, StereoUiCamera};
+use crate::resources::{ProviderComparison, StereoView, UserSettings};
+use crate::resources::comparison::split_viewports;
+use crate::resources::stereo::{eye_transforms, StereoMode};
+use crate::stereo::AnaglyphMaterial;
+use crate::systems::comparison::set_viewport;
+
+type MainCameraQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Camera, &'static Transform, Option<&'static Projection>, Option<&'static PerspectiveProjection>),
+    (With<MainCamera>, Without<StereoEye>),
+>;
+type StereoEntitiesFilter = Or<(With<StereoEye>, With<StereoUiCamera>, With<AnaglyphView>)>;
+type MainViewQuery<'w, 's> =
+    Query<'w, 's, (&'static Transform, Option<&'static PerspectiveProjection>, Option<&'static DistanceFog>), With<MainCamera>>;
+type EyeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static StereoEye, &'static mut Transform, Option<&'static mut PerspectiveProjection>),
+    Without<MainCamera>,
+>;
+
+/// Set up the eye cameras for the stereo mode picked in the settings, or take them down
+/// While they are there the main camera only moves and steers the tiles, it doesn't draw
+pub fn manage_stereo_cameras(
+    mut commands: Commands,
+    settings: Res<UserSettings>,
+    (mut stereo, mut comparison): (ResMut<StereoView>, ResMut<ProviderComparison>),
+    (mut images, mut materials): (ResMut<Assets<Image>>, ResMut<Assets<AnaglyphMaterial>>),
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut main_camera: MainCameraQuery,
+    stereo_entities: Query<Entity, StereoEntitiesFilter>,
+) {
+    let mode = settings.stereo_mode;
+    if mode == stereo.mode {
+        return;
+    }
+    let Ok((mut camera, transform, projection, perspective)) = main_camera.get_single_mut() else {
+        return;
+    };
+
+    for entity in &stereo_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    stereo.eye_images = None;
+    stereo.mode = mode;
+    camera.is_active = mode == StereoMode::Off;
+    if mode == StereoMode::Off {
+        info!("Stereo 3D: OFF");
+        return;
+    }
+    // The comparison has cameras of its own and splits the window too
+    comparison.active = false;
+
+    // The anaglyph eyes draw into images, combined on a node under the UI
+    if mode == StereoMode::Anaglyph {
+        let size = windows.get_single().map_or(UVec2::ONE, |window| window.physical_size().max(UVec2::ONE));
+        let eye_images = [images.add(eye_image(size)), images.add(eye_image(size))];
+        commands.spawn((
+            MaterialNode(materials.add(AnaglyphMaterial {
+                left: eye_images[0].clone(),
+                right: eye_images[1].clone(),
+            })),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            FocusPolicy::Pass,
+            GlobalZIndex(i32::MIN),
+            AnaglyphView,
+        ));
+        stereo.eye_images = Some(eye_images);
+    }
+
+    for (index, left) in [true, false].into_iter().enumerate() {
+        let target = stereo
+            .eye_images
+            .as_ref()
+            .map_or_else(RenderTarget::default, |eye_images| RenderTarget::Image(eye_images[index].clone()));
+        let mut eye = commands.spawn((
+            Camera3d::default(),
+            Camera {
+                order: index as isize + 1,
+                hdr: camera.hdr,
+                target,
+                ..default()
+            },
+            eye_transforms(transform)[index],
+            StereoEye { left },
+        ));
+        if let Some(projection) = projection {
+            eye.insert(projection.clone());
+        }
+        if let Some(perspective) = perspective {
+            eye.insert(perspective.clone());
+        }
+    }
+
+    // With the main camera off the UI needs a camera of its own, over the whole window
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 3,
+            clear_color: if mode == StereoMode::Anaglyph { ClearColorConfig::Default } else { ClearColorConfig::None },
+            ..default()
+        },
+        IsDefaultUiCamera,
+        StereoUiCamera,
+    ));
+    info!("Stereo 3D: {}", mode.name());
+}
+
+// An image for an eye camera to draw into and the anaglyph material to read from
+fn eye_image(size: UVec2) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+/// Keep the eyes' views the size of the window: the halves of the window side by side,
+/// the eye images for the anaglyph
+pub fn resize_stereo_views(
+    stereo: Res<StereoView>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut eyes: Query<(&mut Camera, &StereoEye)>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = window.physical_size();
+    if size.x == 0 || size.y == 0 {
+        return;
+    }
+
+    match (stereo.mode, &stereo.eye_images) {
+        (StereoMode::SideBySide, _) => {
+            let (left_viewport, right_viewport) = split_viewports(size, 0.5);
+            for (mut camera, eye) in eyes.iter_mut() {
+                set_viewport(&mut camera, if eye.left { left_viewport } else { right_viewport });
+            }
+        }
+        (StereoMode::Anaglyph, Some(eye_images)) => {
+            for handle in eye_images {
+                // Looked up read-only first, getting an image mutably uploads it again
+                if images.get(handle).is_some_and(|image| image.size() != size) {
+                    if let Some(image) = images.get_mut(handle) {
+                        image.resize(Extent3d {
+                            width: size.x,
+                            height: size.y,
+                            depth_or_array_layers: 1,
+                        });
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Give the eye cameras the main camera's view, each moved half the eye separation to its side,
+/// with its field of view and fog
+/// Runs after all camera movement and before transforms are propagated
+pub fn sync_stereo_eyes(mut commands: Commands, main_camera: MainViewQuery, mut eyes: EyeQuery) {
+    let Ok((transform, perspective, fog)) = main_camera.get_single() else {
+        return;
+    };
+    let [left, right] = eye_transforms(transform);
+
+    for (entity, eye, mut eye_transform, eye_perspective) in eyes.iter_mut() {
+        *eye_transform = if eye.left { left } else { right };
+        // The aspect ratio is each eye's own, from its viewport or image
+        if let (Some(source), Some(mut target)) = (perspective, eye_perspective) {
+            if (target.fov, target.near, target.far) != (source.fov, source.near, source.far) {
+                (target.fov, target.near, target.far) = (source.fov, source.near, source.far);
+            }
+        }
+        match fog {
+            Some(fog) => commands.entity(entity).insert(fog.clone()),
+            None => commands.entity(entity).remove::<DistanceFog>(),
+        };
+    }
+}
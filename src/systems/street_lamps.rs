@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use crate::components::{LayerItem, MainCamera};
+use crate::overlays::street_lamps::{cluster_lamps, lamp_glow_image, lamp_query, StreetLampCluster, StreetLampLight};
+use crate::resources::{HttpClient, LayerManager, OSMData, Overpass, StreetLamps, Sun, TaskRuntime, TileAppearance, UserSettings};
+use crate::resources::layer_manager::STREET_LAMPS_LAYER;
+use crate::resources::overpass::OverpassTile;
+use crate::utils::geo::{lat_lon_to_world, meters_per_world_unit, GeoPos};
+
+// Zoom level of the tiles lamps are queried by, like the roads
+const LAMP_TILE_ZOOM: u32 = 16;
+// Lamps are loaded from this map zoom on, further out single lamps can't be told apart
+const MIN_ZOOM: u32 = 18;
+// Tiles around the camera's tile whose lamps are loaded, and beyond which they are dropped
+const LOAD_RANGE: i64 = 1;
+const KEEP_RANGE: i64 = 2;
+const MAX_LOADING: usize = 2;
+// Lamps closer together than this are lit as one cluster
+const CLUSTER_SIZE_M: f64 = 50.0;
+// Radius of the glow a cluster casts on the ground
+const GLOW_RADIUS_M: f64 = 30.0;
+// Height of the glow over the roads (0.008 to 0.01 within their layer)
+const GLOW_ELEVATION: f32 = 0.0097;
+// Lamp post height, range and light output of a single lamp
+const LAMP_HEIGHT_M: f64 = 8.0;
+const LIGHT_RANGE_M: f64 = 40.0;
+const LAMP_LUMENS: f64 = 2000.0;
+const LAMP_COLOR: Color = Color::srgb(1.0, 0.78, 0.47);
+// Point lights lit at once; the renderer gets slow with many, so only the clusters nearest
+// the camera have one on
+const MAX_LAMP_LIGHTS: usize = 24;
+// Daylight below which the lamps are on
+const NIGHT_DAYLIGHT: f32 = 0.3;
+
+/// Ask the Overpass server for the street lamps of the tiles around the camera while zoomed in
+/// far enough, and drop the lamps the camera left behind
+pub fn request_lamp_tiles(
+    mut commands: Commands,
+    mut lamps: ResMut<StreetLamps>,
+    mut overpass: ResMut<Overpass>,
+    (manager, settings, osm_data): (Res<LayerManager>, Res<UserSettings>, Res<OSMData>),
+    (task_runtime, http_client): (Res<TaskRuntime>, Res<HttpClient>),
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let center = GeoPos::from_translation(camera.translation).tile(LAMP_TILE_ZOOM);
+    for cluster in lamps.tiles.drop_beyond(center, KEEP_RANGE) {
+        commands.entity(cluster).despawn_recursive();
+    }
+
+    let shown = manager.get(STREET_LAMPS_LAYER).is_none_or(|layer| layer.visible);
+    if !shown || osm_data.current_zoom < MIN_ZOOM {
+        return;
+    }
+    let client = overpass.client(&settings, &http_client);
+    lamps.tiles.request_around(center, LOAD_RANGE, MAX_LOADING, lamp_query, (&client, &task_runtime));
+}
+
+/// Spawn the lamp clusters of the tiles whose query was answered, unlit until the night
+pub fn receive_lamp_tiles(
+    mut commands: Commands,
+    mut lamps: ResMut<StreetLamps>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    for (id, data) in lamps.tiles.take_answers() {
+        let (glow_mesh, glow_material) = lamps
+            .glow
+            .get_or_insert_with(|| {
+                let mesh = meshes.add(Plane3d::new(Vec3::Y, Vec2::splat(0.5)));
+                let material = materials.add(StandardMaterial {
+                    base_color_texture: Some(images.add(lamp_glow_image())),
+                    alpha_mode: AlphaMode::Add,
+                    unlit: true,
+                    ..default()
+                });
+                (mesh, material)
+            })
+            .clone();
+
+        let positions: Vec<(f64, f64)> = data.nodes.iter().map(|node| (node.lat, node.lon)).collect();
+        let clusters = cluster_lamps(&positions, CLUSTER_SIZE_M)
+            .into_iter()
+            .map(|cluster| {
+                let world = lat_lon_to_world(cluster.lat, cluster.lon);
+                let meters = meters_per_world_unit(cluster.lat);
+                let glow_size = (2.0 * GLOW_RADIUS_M / meters) as f32;
+                commands
+                    .spawn((
+                        Transform::from_xyz(world.x as f32, GLOW_ELEVATION, world.y as f32),
+                        Visibility::default(),
+                        StreetLampCluster,
+                        LayerItem { layer: STREET_LAMPS_LAYER, elevation: GLOW_ELEVATION },
+                        Name::new(format!("Street lamps ({})", cluster.count)),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Mesh3d(glow_mesh.clone()),
+                            MeshMaterial3d(glow_material.clone()),
+                            Transform::from_scale(Vec3::new(glow_size, 1.0, glow_size)),
+                            Visibility::Hidden,
+                        ));
+                        // Light output in world units, which are kilometers wide, so the
+                        // light reaches as far as a lamp's does in meters
+                        parent.spawn((
+                            PointLight {
+                                color: LAMP_COLOR,
+                                intensity: (LAMP_LUMENS * cluster.count as f64 / (meters * meters)) as f32,
+                                range: (LIGHT_RANGE_M / meters) as f32,
+                                radius: 0.0,
+                                shadows_enabled: false,
+                                ..default()
+                            },
+                            Transform::from_xyz(0.0, (LAMP_HEIGHT_M / meters) as f32, 0.0),
+                            Visibility::Hidden,
+                            StreetLampLight,
+                        ));
+                    })
+                    .id()
+            })
+            .collect();
+        debug!("Street lamps of tile {}/{}/{}: {}", id.z, id.x, id.y, data.nodes.len());
+        lamps.tiles.tiles.insert(id, OverpassTile::Loaded(clusters));
+    }
+}
+
+/// Light the lamps at night: every cluster glows on the ground, and the clusters nearest the
+/// camera light their surroundings
+pub fn update_street_lamps(
+    sun: Res<Sun>,
+    appearance: Res<TileAppearance>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    cluster_query: Query<(&Transform, &Children), With<StreetLampCluster>>,
+    mut visibility_query: Query<(&mut Visibility, Has<StreetLampLight>)>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let night = sun.daylight < NIGHT_DAYLIGHT || appearance.night_mode;
+
+    let mut clusters: Vec<(f32, &Children)> = cluster_query
+        .iter()
+        .map(|(transform, children)| (transform.translation.distance_squared(camera.translation), children))
+        .collect();
+    clusters.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (rank, (_, children)) in clusters.into_iter().enumerate() {
+        for &child in children.iter() {
+            let Ok((mut visibility, is_light)) = visibility_query.get_mut(child) else {
+                continue;
+            };
+            let lit = night && (!is_light || rank < MAX_LAMP_LIGHTS);
+            visibility.set_if_neq(if lit { Visibility::Inherited } else { Visibility::Hidden });
+        }
+    }
+}
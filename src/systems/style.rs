@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use crate::components::FallbackTile;
+use crate::osm::fallback_glow;
+use crate::overlays::street_labels::LabelFonts;
+use crate::resources::{Style, StyleWatcher};
+
+/// Load the style file again when it was changed, created or removed
+/// A removed or broken file brings back the defaults
+pub fn reload_style(time: Res<Time>, mut watcher: ResMut<StyleWatcher>, mut style: ResMut<Style>) {
+    if !watcher.timer.tick(time.delta()).just_finished() || !watcher.changed() {
+        return;
+    }
+
+    let reloaded = Style::load(&watcher.path).unwrap_or_default();
+    info!("Reloaded the style from {}", watcher.path.display());
+    style.set_if_neq(reloaded);
+}
+
+/// Recolor the fallback tiles on the map when the style changes
+pub fn apply_fallback_tile_style(
+    style: Res<Style>,
+    fallback_query: Query<&MeshMaterial3d<StandardMaterial>, With<FallbackTile>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !style.is_changed() || style.is_added() {
+        return;
+    }
+
+    for material in &fallback_query {
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.base_color = style.fallback_tile_color;
+            material.emissive = fallback_glow(style.fallback_tile_color);
+        }
+    }
+}
+
+/// Set street labels in the style's font, loading its font file if it has one
+/// Labels already on the map keep their font until they are loaded again
+pub fn apply_label_style(style: Res<Style>, mut fonts: ResMut<LabelFonts>) {
+    if !style.is_changed() {
+        return;
+    }
+
+    if let Some(path) = &style.label_font_file {
+        if let Err(e) = fonts.font_system.db_mut().load_font_file(path) {
+            warn!("Failed to load label font {}: {}", path.display(), e);
+        }
+    }
+    if fonts.family != style.label_font_family {
+        fonts.family = style.label_font_family.clone();
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use crate::components::{BackgroundTile, FallbackTile, MainCamera, TileCoords, TileDebugLabel, TileProgressText};
+use crate::osm::TileId;
+use crate::resources::{InputMap, OSMData, TileDebugOverlay, TileLoadCounts};
+use crate::resources::input_map::TOGGLE_TILE_OVERLAY;
+use crate::resources::tile_debug::tile_load_states;
+use crate::utils::geo::{tile_world_origin, tile_world_size};
+
+// Above the highest focus tiles, so borders aren't hidden by the tiles they outline
+const OVERLAY_ELEVATION: f32 = 0.008;
+// Borders are drawn inset by this fraction of the tile, so duplicated tiles show as
+// overlapping outlines of different colors instead of one line
+const BORDER_INSET: f32 = 0.02;
+// Labels drawn at most, the closest tiles to the screen centre win
+const MAX_LABELS: usize = 150;
+
+type LabelFilter = (With<TileDebugLabel>, Without<TileProgressText>);
+type ProgressFilter = (With<TileProgressText>, Without<TileDebugLabel>);
+type LabelData<'a> = (Entity, &'a mut Text, &'a mut TextColor, &'a mut Node, &'a mut Visibility);
+
+/// Toggle the tile debug overlay (F3 by default)
+pub fn toggle_tile_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut overlay: ResMut<TileDebugOverlay>,
+) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_TILE_OVERLAY) {
+        overlay.enabled = !overlay.enabled;
+        info!("Tile overlay: {}", if overlay.enabled { "ON" } else { "OFF" });
+    }
+}
+
+/// Spawn the loading progress text (bottom centre), hidden until tiles load
+pub fn setup_tile_progress(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0), // Above the status bar
+            left: Val::Percent(50.0),
+            width: Val::Px(440.0),
+            margin: UiRect::left(Val::Px(-220.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        Visibility::Hidden,
+        TileProgressText,
+    ));
+}
+
+/// Outline every focus tile in the color of its load state and label it with its z/x/y,
+/// and show the loading progress while tiles are on their way or the overlay is on
+pub fn draw_tile_overlay(
+    mut commands: Commands,
+    (overlay, osm_data): (Res<TileDebugOverlay>, Res<OSMData>),
+    tile_query: Query<(&TileCoords, Has<FallbackTile>), Without<BackgroundTile>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut label_query: Query<LabelData, LabelFilter>,
+    mut progress_query: Query<(&mut Text, &mut Visibility), ProgressFilter>,
+    mut gizmos: Gizmos,
+) {
+    let spawned = tile_query.iter().map(|(coords, failed)| ((coords.x, coords.y, coords.zoom), failed));
+    let states = tile_load_states(&osm_data, spawned);
+    let counts = TileLoadCounts::count(&states);
+
+    if let Ok((mut text, mut visibility)) = progress_query.get_single_mut() {
+        let show = overlay.enabled || counts.in_progress() > 0;
+        let progress = format!(
+            "Tiles {:.0}% | {} loaded, {} downloading, {} queued, {} failed",
+            counts.progress() * 100.0,
+            counts.loaded,
+            counts.downloading,
+            counts.queued,
+            counts.failed
+        );
+        if show && text.0 != progress {
+            text.0 = progress;
+        }
+        visibility.set_if_neq(if show { Visibility::Inherited } else { Visibility::Hidden });
+    }
+
+    let mut labels = label_query.iter_mut();
+    if overlay.enabled {
+        let camera = camera_query.get_single().ok();
+        let viewport = camera.and_then(|(camera, _)| camera.logical_viewport_size());
+        // Labels for the tiles nearest the screen centre, which is where one looks
+        let mut on_screen = Vec::new();
+
+        for (&(x, y, zoom), state) in &states {
+            let origin = tile_world_origin(TileId::new(x, y, zoom)).as_vec2();
+            let size = tile_world_size(zoom) as f32;
+            let center = Vec3::new(origin.x + size / 2.0, OVERLAY_ELEVATION, origin.y + size / 2.0);
+            let rotation = Quat::from_rotation_x(FRAC_PI_2);
+            gizmos.rect(Isometry3d::new(center, rotation), Vec2::splat(size * (1.0 - BORDER_INSET)), state.color());
+
+            let (Some((camera, camera_transform)), Some(viewport)) = (camera, viewport) else {
+                continue;
+            };
+            let Ok(position) = camera.world_to_viewport(camera_transform, center) else {
+                continue;
+            };
+            if position.cmpge(Vec2::ZERO).all() && position.cmplt(viewport).all() {
+                on_screen.push((position.distance_squared(viewport / 2.0), position, (x, y, zoom), *state));
+            }
+        }
+
+        on_screen.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, position, (x, y, zoom), state) in on_screen.into_iter().take(MAX_LABELS) {
+            // Tiles across the antimeridian are labelled with the address they repeat
+            let label = format!("{}/{}/{}\n{}", zoom, TileId::new(x, y, zoom).wrapped().x, y, state.name());
+            let node = Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                ..default()
+            };
+            match labels.next() {
+                Some((_, mut text, mut color, mut existing, mut visibility)) => {
+                    if text.0 != label {
+                        text.0 = label;
+                    }
+                    if color.0 != state.color() {
+                        color.0 = state.color();
+                    }
+                    *existing = node;
+                    visibility.set_if_neq(Visibility::Inherited);
+                }
+                None => {
+                    commands.spawn((
+                        Text::new(label),
+                        TextFont {
+                            font_size: 11.0,
+                            ..default()
+                        },
+                        TextColor(state.color()),
+                        node,
+                        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+                        TileDebugLabel,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Labels left over are hidden while the overlay is on, and removed once it's off
+    for (entity, _, _, _, mut visibility) in labels {
+        if overlay.enabled {
+            visibility.set_if_neq(Visibility::Hidden);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::diagnostic::DiagnosticsStore;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use crate::components::{BackgroundTile, FallbackTile, TileCoords};
+use crate::osm::download_totals;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osm::{error_counts, host_totals};
+use crate::resources::{OSMData, TileLoadCounts};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::{MetricsExport, TileMemoryBudget, UserSettings};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::resources::metrics::{metrics_json, write_metrics};
+use crate::resources::tile_debug::tile_load_states;
+
+/// Focus tiles on the map with their imagery
+pub const TILES_LOADED: DiagnosticPath = DiagnosticPath::const_new("tiles/loaded");
+/// Focus tiles requested but not on the map yet
+pub const TILES_PENDING: DiagnosticPath = DiagnosticPath::const_new("tiles/pending");
+/// Percentage of texture cache lookups that found the tile's decoded imagery
+pub const TILE_CACHE_HIT_RATE: DiagnosticPath = DiagnosticPath::const_new("tiles/cache_hit_rate");
+/// Megabytes downloaded from tile servers since the start
+pub const TILE_DOWNLOADED_MB: DiagnosticPath = DiagnosticPath::const_new("tiles/downloaded_mb");
+/// Mean milliseconds tile servers took to respond
+pub const TILE_DOWNLOAD_LATENCY: DiagnosticPath = DiagnosticPath::const_new("tiles/download_latency_ms");
+
+/// The tile diagnostics, in the order the FPS counter shows them
+pub const TILE_DIAGNOSTICS: [DiagnosticPath; 5] =
+    [TILES_LOADED, TILES_PENDING, TILE_CACHE_HIT_RATE, TILE_DOWNLOADED_MB, TILE_DOWNLOAD_LATENCY];
+
+/// Register the tile diagnostics with the DiagnosticsStore
+pub fn register_tile_diagnostics(app: &mut App) {
+    app
+        .register_diagnostic(Diagnostic::new(TILES_LOADED))
+        .register_diagnostic(Diagnostic::new(TILES_PENDING))
+        .register_diagnostic(Diagnostic::new(TILE_CACHE_HIT_RATE).with_suffix("%"))
+        .register_diagnostic(Diagnostic::new(TILE_DOWNLOADED_MB).with_suffix(" MB"))
+        .register_diagnostic(Diagnostic::new(TILE_DOWNLOAD_LATENCY).with_suffix(" ms"));
+}
+
+/// Measure the tile diagnostics every frame
+pub fn update_tile_diagnostics(
+    mut diagnostics: Diagnostics,
+    osm_data: Res<OSMData>,
+    tile_query: Query<(&TileCoords, Has<FallbackTile>), Without<BackgroundTile>>,
+) {
+    let spawned = tile_query.iter().map(|(coords, failed)| ((coords.x, coords.y, coords.zoom), failed));
+    let counts = TileLoadCounts::count(&tile_load_states(&osm_data, spawned));
+    let downloads = download_totals();
+
+    diagnostics.add_measurement(&TILES_LOADED, || counts.loaded as f64);
+    diagnostics.add_measurement(&TILES_PENDING, || counts.in_progress() as f64);
+    diagnostics.add_measurement(&TILE_CACHE_HIT_RATE, || osm_data.texture_cache.hit_rate() as f64 * 100.0);
+    diagnostics.add_measurement(&TILE_DOWNLOADED_MB, || downloads.bytes as f64 / (1024.0 * 1024.0));
+    diagnostics.add_measurement(&TILE_DOWNLOAD_LATENCY, || downloads.average_latency().as_secs_f64() * 1000.0);
+}
+
+/// Write the diagnostics with the download, cache and memory metrics to the metrics file
+/// every metrics interval, for monitoring long-running sessions; off without a metrics file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_metrics(
+    time: Res<Time>,
+    settings: Res<UserSettings>,
+    mut export: ResMut<MetricsExport>,
+    diagnostics: Res<DiagnosticsStore>,
+    (osm_data, memory): (Res<OSMData>, Res<TileMemoryBudget>),
+) {
+    if settings.metrics_file.is_empty() {
+        return;
+    }
+    export.since_export += time.delta_secs();
+    if export.since_export < settings.metrics_interval_secs as f32 {
+        return;
+    }
+    export.since_export = 0.0;
+
+    let metrics = metrics_json(
+        time.elapsed_secs_f64(),
+        &diagnostics,
+        download_totals(),
+        &host_totals(),
+        &error_counts(),
+        &osm_data.texture_cache,
+        &memory,
+    );
+    if let Err(e) = write_metrics(Path::new(&settings.metrics_file), &metrics) {
+        warn!("Failed to write metrics to {}: {}", settings.metrics_file, e);
+    }
+}
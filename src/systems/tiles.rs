@@ -1,257 +1,231 @@
 use bevy::prelude::*;
-use crate::resources::{OSMData, TokioRuntime, DebugSettings};
-use crate::components::{TileCoords};
-use crate::osm::{OSMTile, load_tile_image, create_tile_mesh, create_fallback_tile_mesh};
-use crate::utils::coordinate_conversion::world_to_tile_coords;
-use crate::resources::constants::{max_tile_index, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL};
+use bevy::math::DVec2;
+use bevy::utils::Instant;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use bevy::render::mesh::MeshAabb;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::settings::WgpuFeatures;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use crate::resources::{OSMData, PendingLayer, PendingTile, TaskRuntime, HttpClient, DebugSettings, MovementSettings, CameraMode, CameraMotion, WalkSettings, GlobeSettings, TileAppearance, TileAtlas, TileContext, TileGenerators, TileMemoryBudget, TileUploadBudget, CompressedTile, Style, TileDownload, UserSettings, CancelFlag, unless_cancelled};
+use crate::components::{TileCoords, MainCamera, FallbackTile, LowResTile, BackgroundTile, TileTextureBytes, TileFadeIn, TileTint, TileBatch, TileZoomRoot};
+use crate::osm::{OSMTile, TileId, BatchQuad, DownloadClass, composite_layers, TileFilter, TileMaterial, load_tile_image, create_tile_texture, create_atlas_tile, create_fallback_tile_mesh, build_batch_mesh, atlas_layer_data, select_lod_tiles, redundant_requests, LodView, TileError, TileRequest, ViewFootprint};
+use crate::utils::geo::world_to_tile_coords;
+use crate::utils::geo::{horizon_distance_m, tile_world_origin, tile_world_size, world_origin, GeoTransform};
+use crate::resources::constants::{max_tile_index, MIN_ZOOM_LEVEL, MAX_ZOOM_LEVEL, BACKGROUND_ZOOM_LEVEL, LOD_MAX_TILE_PIXELS};
+use crate::events::{TileDespawned, TileSpawned, TileVisibilityChanged};
 use crate::debug_log;
 
+// Rays cast along each edge of the view to find the ground it covers
+const FOOTPRINT_RAYS_PER_EDGE: usize = 4;
+// Visible tile downloads are dropped once their priority is worse than this many times the
+// worst priority of the tiles selected, so tiles at the edge of the selection don't flip
+// between loading and dropped while the camera moves a little
+const DROP_PRIORITY_FACTOR: i32 = 2;
+
 // Process tiles based on camera position and view direction
 pub fn process_tiles(
     mut osm_data: ResMut<OSMData>,
-    tokio_runtime: Res<TokioRuntime>,
+    task_runtime: Res<TaskRuntime>,
+    http_client: Res<HttpClient>,
     debug_settings: Res<DebugSettings>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    movement_settings: Res<MovementSettings>,
+    (camera_motion, camera_mode, walk_settings): (Res<CameraMotion>, Res<CameraMode>, Res<WalkSettings>),
+    camera_query: Query<(&Transform, &Camera), With<MainCamera>>,
 ) {
-    // Skip if we have no camera yet
-    if let Ok((camera_transform, _camera)) = camera_query.get_single() {
+    // Skip if we have no camera yet, or its viewport size isn't known
+    if let Ok((camera_transform, camera)) = camera_query.get_single() {
         let camera_pos = camera_transform.translation;
-        let camera_forward = camera_transform.forward();
+        let Some(mut lod_view) = camera_lod_view(camera, camera_transform) else {
+            return;
+        };
+        // Walking shows the streets around the player in full detail
+        if *camera_mode == CameraMode::Walk {
+            let meters_per_unit = GeoTransform::from_translation(camera_pos).position.meters_per_world_unit();
+            lod_view = lod_view.with_detail_radius(walk_settings.detail_radius_m / meters_per_unit);
+        }
         
-        // Calculate base zoom level from camera height - this determines the detail level
-        let base_zoom = calculate_base_zoom_level(camera_pos.y);
+        // Zoom level of the ground right below the camera
+        let base_zoom = lod_view.zoom_at_distance(camera_pos.y.max(0.0) as f64);
         
         // Update global zoom level for UI and other systems
         osm_data.current_zoom = base_zoom;
         
-        // Set a fixed lower zoom level for background (global context)
-        let background_zoom = (base_zoom.saturating_sub(4)).max(MIN_ZOOM_LEVEL).min(6);
-        osm_data.background_zoom = background_zoom;
-        
         // Generate adaptive tiles with varying zoom levels
-        // This system uses larger tiles (lower zoom) for areas further from view center
+        // Tiles are subdivided where they would appear too large on screen
         generate_adaptive_tiles(
             &mut osm_data,
-            &tokio_runtime,
+            &task_runtime,
+            &http_client,
             &debug_settings,
-            camera_pos,
-            camera_forward.into(),
+            &lod_view,
             base_zoom,
         );
+        
+        // Prefetch tiles where the camera is heading
+        prefetch_predicted_tiles(
+            &mut osm_data,
+            &task_runtime,
+            &http_client,
+            &debug_settings,
+            &movement_settings,
+            &lod_view,
+            camera_motion.velocity,
+        );
+    }
+}
+
+// Screen-space error view of the camera, None until its viewport size is known
+// Tile selection is limited to the ground inside the view frustum, up to the horizon
+fn camera_lod_view(camera: &Camera, camera_transform: &Transform) -> Option<LodView> {
+    let viewport = camera.physical_viewport_size()?;
+    let camera_pos = camera_transform.translation;
+
+    // Half extents of the view at distance 1, from the projection the camera actually renders with
+    let clip_from_view = camera.clip_from_view();
+    let tan_half_x = 1.0 / clip_from_view.x_axis.x;
+    let tan_half_y = 1.0 / clip_from_view.y_axis.y;
+    let fov_y = 2.0 * tan_half_y.atan();
+
+    // Rays along the edges of the view, several per edge so the far edge follows the horizon
+    let (forward, right, up) = (camera_transform.forward(), camera_transform.right(), camera_transform.up());
+    let edge_rays = (0..4).flat_map(|edge| {
+        (0..FOOTPRINT_RAYS_PER_EDGE).map(move |step| {
+            let along = step as f32 / FOOTPRINT_RAYS_PER_EDGE as f32 * 2.0 - 1.0;
+            let (x, y) = match edge {
+                0 => (along, -1.0), // Bottom, left to right
+                1 => (1.0, along),  // Right, bottom to top
+                2 => (-along, 1.0), // Top, right to left
+                _ => (-1.0, -along), // Left, top to bottom
+            };
+            (*forward + *right * x * tan_half_x + *up * y * tan_half_y).as_dvec3()
+        })
+    });
+
+    // The horizon depends on the altitude in meters, which varies with latitude for the same height
+    let camera_geo = GeoTransform::from_translation(camera_pos);
+    let meters_per_unit = camera_geo.position.meters_per_world_unit();
+    let horizon = horizon_distance_m(camera_geo.altitude_m) / meters_per_unit;
+
+    let footprint = ViewFootprint::from_rays(camera_pos.as_dvec3(), edge_rays, horizon);
+    let view = LodView::new(camera_pos.as_dvec3(), fov_y, viewport.y as f32, LOD_MAX_TILE_PIXELS);
+    Some(view.with_origin(world_origin()).with_footprint(footprint))
+}
+
+// Load tiles around the position the camera is predicted to reach shortly
+// Uses the same speed curve as camera movement so predictions never overshoot
+fn prefetch_predicted_tiles(
+    osm_data: &mut OSMData,
+    task_runtime: &TaskRuntime,
+    http_client: &HttpClient,
+    debug_settings: &DebugSettings,
+    movement_settings: &MovementSettings,
+    lod_view: &LodView,
+    velocity: Vec3,
+) {
+    if velocity.length_squared() < f32::EPSILON {
+        return;
+    }
+
+    let camera_pos = lod_view.camera;
+
+    // Predicted travel, capped to the fastest possible movement at this height
+    let lookahead = movement_settings.prefetch_seconds;
+    let max_distance = movement_settings.max_speed_at(camera_pos.y as f32) * lookahead;
+    let predicted = camera_pos + (velocity * lookahead).clamp_length_max(max_distance).as_dvec3();
+
+    let zoom = lod_view.zoom_at_distance(predicted.y.max(0.0));
+    let (center_x, center_y) = world_to_tile_coords(predicted.x, predicted.z, zoom);
+    let max_index = max_tile_index(zoom) as i32;
+
+    // Small ring around the predicted ground position, after all visible tiles
+    let mut prefetch_tiles = Vec::new();
+    for x_offset in -1..=1 {
+        for y_offset in -1..=1 {
+            let tile_x = center_x.wrapping_add_signed(x_offset);
+            let tile_y = (center_y as i32 + y_offset).clamp(0, max_index) as u32;
+            let priority = 500 + x_offset.abs() + y_offset.abs();
+            prefetch_tiles.push((tile_x, tile_y, zoom, priority));
+        }
     }
+
+    debug_log!(debug_settings, "Prefetching around predicted position ({:.1}, {:.1}, {:.1})",
+              predicted.x, predicted.y, predicted.z);
+
+    load_tiles(
+        osm_data,
+        task_runtime,
+        http_client,
+        debug_settings,
+        &prefetch_tiles,
+        DownloadClass::Prefetch,
+    );
 }
 
-// Calculate appropriate base zoom level from camera height
-pub fn calculate_base_zoom_level(height: f32) -> u32 {
-    // Reduce height increments to make zoom level changes more responsive
-    match height {
-        h if h <= 1.0 => 19,   // Level 19: Local highways, crossings (1:1000 scale)
-        h if h <= 2.0 => 18,   // Level 18: Buildings, trees (1:2000 scale)
-        h if h <= 4.0 => 17,   // Level 17: Building blocks, parks, addresses
-        h if h <= 8.0 => 16,   // Level 16: Streets
-        h if h <= 15.0 => 15,  // Level 15: Small roads
-        h if h <= 30.0 => 14,  // Level 14: Detailed roads
-        h if h <= 60.0 => 13,  // Level 13: Villages, suburbs
-        h if h <= 120.0 => 12, // Level 12: Towns, city districts
-        h if h <= 250.0 => 11, // Level 11: Cities
-        h if h <= 500.0 => 10, // Level 10: Metropolitan areas
-        h if h <= 1000.0 => 9, // Level 9: Large metro areas
-        h if h <= 2000.0 => 8, // Level 8
-        h if h <= 4000.0 => 7, // Level 7: Small countries, US states
-        h if h <= 8000.0 => 6, // Level 6: Large European countries
-        h if h <= 16000.0 => 5, // Level 5: Large African countries
-        h if h <= 32000.0 => 4, // Level 4
-        h if h <= 64000.0 => 3, // Level 3: Largest countries
-        h if h <= 128000.0 => 2, // Level 2: Subcontinental areas
-        _ => 1,                  // Level 1: Whole world
-    }
-}
-
-// Generate an adaptive grid of tiles with varying zoom levels
+// Generate tiles with varying zoom levels by screen-space error
+// The quadtree below the background tiles is subdivided wherever a tile would appear larger
+// than LOD_MAX_TILE_PIXELS, so looking at the horizon loads coarse tiles far away
+// and looking straight down loads detail right below the camera
+// Only tiles inside the view footprint are selected: a trapezoid for tilted views,
+// ending at the horizon
 fn generate_adaptive_tiles(
     osm_data: &mut OSMData,
-    tokio_runtime: &TokioRuntime,
+    task_runtime: &TaskRuntime,
+    http_client: &HttpClient,
     debug_settings: &DebugSettings,
-    camera_pos: Vec3,
-    camera_forward: Vec3,
+    lod_view: &LodView,
     base_zoom: u32,
 ) {
-    // Project camera forward onto XZ plane
-    let view_dir_xz = Vec3::new(camera_forward.x, 0.0, camera_forward.z).normalize();
-    
-    // Calculate viewing distance based on camera height and viewing angle
-    let cam_height = camera_pos.y;
-    let _angle_factor = 1.0 + (1.0 - camera_forward.y.abs()) * 2.0;
-    
-    // For more horizontal views, look farther ahead
-    // For more vertical views, look closer to camera position
-    let horizontal_factor = (1.0 - camera_forward.y.abs()).powf(0.5); // Square root for smoother transition
-    let view_distance = cam_height * 1.5 * (1.0 + horizontal_factor * 3.0);
-    
-    // Calculate the exact point on the ground where the camera ray intersects
-    // This is where we want to center our high-detail tiles
-    let ground_y = 0.0; // Ground level
-    let t = if camera_forward.y != 0.0 {
-        // Parameter for ray-plane intersection: camera_pos + t * camera_forward = point on ground
-        (ground_y - camera_pos.y) / camera_forward.y
-    } else {
-        // If camera is perfectly horizontal, use a default distance
-        view_distance
-    };
-    
-    // Only use the intersection point if it's in front of the camera (t > 0)
-    // and within a reasonable distance
-    let view_target = if t > 0.0 && t < view_distance * 2.0 {
-        camera_pos + camera_forward * t
-    } else {
-        // Fallback: look ahead based on camera height
-        camera_pos + view_dir_xz * view_distance
-    };
-    
-    debug_log!(debug_settings, "View target: ({:.1}, {:.1}, {:.1}), height: {:.1}", 
-              view_target.x, view_target.y, view_target.z, cam_height);
+    let camera_pos = lod_view.camera;
     
     // All tiles to load with their coordinates and priority
     let mut tiles_to_load = Vec::new();
     
     // Handle background (global context) tiles - use even lower zoom level
     // and much fewer tiles to reduce the total load
-    let bg_zoom = (base_zoom.saturating_sub(5)).max(MIN_ZOOM_LEVEL).min(4);
+    let bg_zoom = base_zoom.saturating_sub(5).clamp(MIN_ZOOM_LEVEL, 4);
     osm_data.background_zoom = bg_zoom;
     
     // Get tile at camera position for background layer
     let (bg_center_x, bg_center_y) = world_to_tile_coords(camera_pos.x, camera_pos.z, bg_zoom);
+    let bg_max_index = max_tile_index(bg_zoom) as i32;
     
     // Add minimal set of background tiles (just enough for context)
+    // These are also the roots of the quadtree the detailed tiles are selected from
     let bg_range = 1; // Minimal background
+    let mut roots = Vec::new();
     for x_offset in -bg_range..=bg_range {
         for y_offset in -bg_range..=bg_range {
-            let tile_x = (bg_center_x as i32 + x_offset).max(0) as u32;
-            let tile_y = (bg_center_y as i32 + y_offset).max(0) as u32;
+            let tile_x = bg_center_x.wrapping_add_signed(x_offset);
+            let tile_y = (bg_center_y as i32 + y_offset).clamp(0, bg_max_index) as u32;
+            
+            let root = TileId::new(tile_x, tile_y, bg_zoom);
+            if roots.contains(&root) {
+                continue; // Clamped at the north or south edge of the world
+            }
+            roots.push(root);
             
             let priority = 1000 + x_offset.abs() + y_offset.abs(); // Lowest priority
             tiles_to_load.push((tile_x, tile_y, bg_zoom, priority, true)); // true = background
         }
     }
     
-    // Create a multi-level adaptive grid around the view target
-    // The key is to use larger tiles (lower zoom) for areas further from the view center
-    
-    // Determine the highest zoom level we'll use (based on camera height)
-    let highest_zoom = base_zoom.min(MAX_ZOOM_LEVEL);
-    
-    // OPTIMIZATION: Create much more aggressive zoom level reduction
-    // Based on camera height, dynamically calculate how many zoom levels to use
-    // and drastically reduce the number of tiles loaded
-    
-    // Dynamic zoom reduction based on camera height
-    let max_zoom_levels = if cam_height > 500.0 {
-        1 // At very high heights, just use one zoom level
-    } else if cam_height > 200.0 {
-        2 // At high heights, use two zoom levels
-    } else {
-        3 // At lower heights, use three zoom levels for more detail variation
-    };
-    
-    // Create zoom level array
-    let mut zoom_levels = Vec::with_capacity(max_zoom_levels);
-    
-    // Add highest zoom first - this is the center of view
-    zoom_levels.push(highest_zoom);
-    
-    // Add lower zoom levels as needed
-    if max_zoom_levels > 1 {
-        zoom_levels.push((highest_zoom.saturating_sub(2)).max(MIN_ZOOM_LEVEL));
-    }
-    
-    if max_zoom_levels > 2 {
-        zoom_levels.push((highest_zoom.saturating_sub(4)).max(MIN_ZOOM_LEVEL));
-    }
-    
-    // OPTIMIZATION: Keep track of covered areas to avoid loading redundant tiles
-    let mut covered_areas: Vec<(u32, u32, u32)> = Vec::new(); // (tile_x, tile_y, zoom)
-    
-    // 2. Generate tiles for each zoom level ring
-    for (ring_idx, &zoom) in zoom_levels.iter().enumerate() {
-        // Skip this ring if it's too similar to background
-        if zoom <= bg_zoom + 1 {
+    // Nearest tiles load first, distance counted in tiles of the zoom level below the camera
+    for tile in select_lod_tiles(lod_view, &roots, MAX_ZOOM_LEVEL) {
+        // Tiles barely more detailed than the background add little over it
+        if tile.z <= bg_zoom + 1 {
             continue;
         }
         
-        // OPTIMIZATION: Use smaller radius for each ring
-        // Higher zoom levels (more detailed) should cover smaller areas
-        let radius = match ring_idx {
-            0 => 3, // Increased radius for highest detail ring
-            1 => 2, // Increased radius for middle ring 
-            _ => 2, // Increased radius for outer ring
-        };
-        
-        // Calculate target center - inner rings are centered precisely at view_target
-        // Outer rings can be slightly biased towards the camera position
-        let ring_center = if ring_idx == 0 {
-            view_target // Center ring is at exact view target
-        } else {
-            // Blend between view_target and camera_pos for outer rings
-            // This creates a better distribution for angled views
-            let blend_factor = ring_idx as f32 * 0.25; // 0.25 for ring 1, 0.5 for ring 2...
-            Vec3::lerp(
-                view_target,
-                Vec3::new(camera_pos.x, 0.0, camera_pos.z), // Project camera to ground
-                blend_factor
-            )
+        let Some(priority) = lod_view.download_priority(tile) else {
+            continue;
         };
-        
-        // Get tile coordinates for center of this ring
-        let (center_x, center_y) = world_to_tile_coords(ring_center.x, ring_center.z, zoom);
-        
-        // Max tile index for this zoom level
-        let max_index = max_tile_index(zoom);
-        
-        // Priority base for this ring - inner rings have higher priority
-        let priority_base = ring_idx as i32 * 100;
-        
-        // Add tiles in a square pattern to cover the area
-        for x_offset in -radius as i32..=radius as i32 {
-            for y_offset in -radius as i32..=radius as i32 {
-                // For outer rings, focus on the edges and corners
-                let manhattan_dist = x_offset.abs() + y_offset.abs();
-                
-                // Skip inner tiles in outer rings to avoid redundancy
-                if ring_idx > 0 && manhattan_dist < ring_idx as i32 {
-                    continue;
-                }
-                
-                // Add extra coverage for diagonal directions
-                // This helps fill in gaps in the corners of the view
-                let is_diagonal = x_offset.abs() == y_offset.abs() && x_offset != 0;
-                
-                // Calculate tile coordinates with bounds checking
-                let tile_x = (center_x as i32 + x_offset).clamp(0, max_index as i32) as u32;
-                let tile_y = (center_y as i32 + y_offset).clamp(0, max_index as i32) as u32;
-                
-                // OPTIMIZATION: Check if this area is already covered by a higher zoom level
-                // Skip this tile if it would be redundant
-                let is_covered = covered_areas.iter().any(|&(x, y, z)| 
-                    is_same_area(tile_x, tile_y, zoom, x, y, z));
-                
-                if is_covered {
-                    continue;
-                }
-                
-                // Add this tile to covered areas
-                covered_areas.push((tile_x, tile_y, zoom));
-                
-                // Calculate priority - closer to center = higher priority
-                // Give diagonals slightly better priority to improve corner coverage
-                let priority_adjustment = if is_diagonal { -1 } else { 0 };
-                let priority = priority_base + manhattan_dist + priority_adjustment;
-                
-                // Add to tiles to load (false = not background)
-                tiles_to_load.push((tile_x, tile_y, zoom, priority, false));
-            }
-        }
+        tiles_to_load.push((tile.x, tile.y, tile.z, priority, false));
     }
     
+    debug_log!(debug_settings, "Selected {} tiles by screen-space error, height: {:.1}",
+              tiles_to_load.len(), camera_pos.y);
+    
     // No need to sort by priority - deduplication step will handle proper ordering
     
     // Further reduce total number of tiles
@@ -280,6 +254,7 @@ fn generate_adaptive_tiles(
     let (foreground_tiles, background_tiles): (Vec<_>, Vec<_>) = 
         tiles_to_load.into_iter()
                     .partition(|&(_, _, _, _, is_bg)| !is_bg);
+    osm_data.selection_cutoff = foreground_tiles.iter().map(|&(_, _, _, priority, _)| priority).max().unwrap_or(0);
     
     // Load foreground tiles
     if !foreground_tiles.is_empty() {
@@ -293,11 +268,11 @@ fn generate_adaptive_tiles(
             
         load_tiles(
             osm_data,
-            tokio_runtime,
+            task_runtime,
+            http_client,
             debug_settings,
             &fg_tiles,
-            16, // Increased concurrent loads for smoother loading
-            false, // Not background
+            DownloadClass::Visible,
         );
     }
     
@@ -313,108 +288,64 @@ fn generate_adaptive_tiles(
             
         load_tiles(
             osm_data,
-            tokio_runtime,
+            task_runtime,
+            http_client,
             debug_settings,
             &bg_tiles,
-            4, // Limit concurrent loads
-            true, // Background tiles
+            DownloadClass::Background,
         );
     }
 }
 
 // Helper function to remove duplicate tiles, preferring higher zoom (detail) levels
+// A tile is dropped when it's listed twice or a more detailed tile inside it is listed too,
+// background and focus tiles apart. Every tile marks its ancestors until it reaches one
+// marked before, so this stays linear in the number of tiles
 fn dedup_tiles(tiles: &mut Vec<(u32, u32, u32, i32, bool)>) {
-    // Sort by coordinates and background flag
-    tiles.sort_by(|a, b| {
-        // Compare background flag first (group backgrounds together)
-        a.4.cmp(&b.4)
-        // Then by coordinates
-        .then(a.0.cmp(&b.0))
-        .then(a.1.cmp(&b.1))
-        // Then by zoom level in DESCENDING order (higher zoom = more detail)
-        .then(b.2.cmp(&a.2))
-    });
-    
-    // Dedup by coordinates - this keeps the first occurrence which will be 
-    // the highest zoom level (most detailed) version
-    let mut i = 0;
-    while i < tiles.len() {
-        let mut j = i + 1;
-        while j < tiles.len() {
-            // Check if tiles refer to the same area
-            if is_same_area(tiles[i].0, tiles[i].1, tiles[i].2, 
-                           tiles[j].0, tiles[j].1, tiles[j].2) &&
-               tiles[i].4 == tiles[j].4 { // And same background status
-                // Remove the duplicate (lower zoom version)
-                tiles.remove(j);
-            } else {
-                j += 1;
+    // (x, y, zoom, is_background) of tiles with a more detailed tile inside them
+    let mut covered = HashSet::new();
+    for &(x, y, z, _, is_bg) in tiles.iter() {
+        let mut tile = TileId::new(x, y, z);
+        while let Some(parent) = tile.parent() {
+            if !covered.insert((parent.x, parent.y, parent.z, is_bg)) {
+                break; // Its ancestors are marked already
             }
+            tile = parent;
         }
-        i += 1;
     }
-    
-    // Resort by priority
-    tiles.sort_by_key(|&(_, _, _, priority, _)| priority);
-}
 
-// Helper function to check if two tiles refer to the same geographic area
-// A higher zoom tile (z2) is contained within a lower zoom tile (z1) if its coordinates
-// are derived from the lower zoom tile's coordinates
-fn is_same_area(x1: u32, y1: u32, z1: u32, x2: u32, y2: u32, z2: u32) -> bool {
-    // First check if tiles are exactly the same
-    if x1 == x2 && y1 == y2 && z1 == z2 {
-        return true;
-    }
-    
-    // If zoom levels are the same but coordinates differ, they're different areas
-    if z1 == z2 {
-        return false;
-    }
-    
-    // Handle case where one tile is at a higher zoom level than the other
-    if z1 < z2 {
-        // z1 is the lower zoom (larger tile)
-        // Check if the higher zoom tile (x2,y2,z2) is contained within (x1,y1,z1)
-        let zoom_diff = z2 - z1;
-        let factor = 1 << zoom_diff; // 2^zoom_diff
-        
-        // Calculate the expected range of higher zoom tiles that would fit in the lower zoom tile
-        let min_x2 = x1 * factor;
-        let min_y2 = y1 * factor;
-        let max_x2 = min_x2 + factor - 1;
-        let max_y2 = min_y2 + factor - 1;
-        
-        // Check if the higher zoom tile is within this range
-        return x2 >= min_x2 && x2 <= max_x2 && y2 >= min_y2 && y2 <= max_y2;
-    } else {
-        // z2 is the lower zoom (larger tile) - reverse the check
-        let zoom_diff = z1 - z2;
-        let factor = 1 << zoom_diff; // 2^zoom_diff
-        
-        // Calculate the expected range of higher zoom tiles that would fit in the lower zoom tile
-        let min_x1 = x2 * factor;
-        let min_y1 = y2 * factor;
-        let max_x1 = min_x1 + factor - 1;
-        let max_y1 = min_y1 + factor - 1;
-        
-        // Check if the higher zoom tile is within this range
-        return x1 >= min_x1 && x1 <= max_x1 && y1 >= min_y1 && y1 <= max_y1;
-    }
+    // Sorted by priority first, so of duplicates the highest priority one is kept
+    tiles.sort_by_key(|&(_, _, _, priority, _)| priority);
+    let mut seen = HashSet::new();
+    tiles.retain(|&(x, y, z, _, is_bg)| !covered.contains(&(x, y, z, is_bg)) && seen.insert((x, y, z, is_bg)));
 }
 
 // Function to handle the actual tile loading logic (shared between adaptive and background systems)
 fn load_tiles(
     osm_data: &mut OSMData,
-    tokio_runtime: &TokioRuntime,
+    task_runtime: &TaskRuntime,
+    http_client: &HttpClient,
     debug_settings: &DebugSettings,
     tiles_to_load: &[(u32, u32, u32, i32)], // (x, y, zoom, priority)
-    max_concurrent_loads: usize,
-    is_background: bool,
+    class: DownloadClass,
 ) {
-    let mut concurrent_loads = 0;
+    let is_background = class == DownloadClass::Background;
+
+    // Tiles loaded and waiting to be spawned
+    let pending: HashSet<_> = osm_data
+        .pending_tiles
+        .lock()
+        .iter()
+        .filter(|p| p.is_background == is_background)
+        .map(|p| (p.x, p.y, p.zoom))
+        .collect();
 
-    // Get appropriate tracking list based on tile type
+    // Background tiles are too coarse to be a parent or child of the others
+    let redundant = if is_background {
+        HashSet::new()
+    } else {
+        coalesce_downloads(osm_data, tiles_to_load, debug_settings)
+    };
     let loaded_tiles = if is_background {
         &mut osm_data.loaded_background_tiles
     } else {
@@ -422,171 +353,774 @@ fn load_tiles(
     };
 
     // Process tiles in order of priority
-    for &(tile_x, tile_y, tile_zoom, _) in tiles_to_load {
-        // Check if we've reached the maximum concurrent load limit
-        if concurrent_loads >= max_concurrent_loads {
+    for &(tile_x, tile_y, tile_zoom, priority) in tiles_to_load {
+        if redundant.contains(&TileId::new(tile_x, tile_y, tile_zoom)) {
+            continue;
+        }
+
+        // Check if the class has reached its number of downloads at once
+        // The rest are selected again on later frames, once downloads finish
+        if !osm_data.downloads.has_free_slot(class) {
             break;
         }
 
         // Check if tile is already loaded or pending
-        let already_pending = osm_data.pending_tiles.lock().iter().any(
-            |(x, y, z, _, bg)| *x == tile_x && *y == tile_y && *z == tile_zoom && *bg == is_background
-        );
+        let already_pending = pending.contains(&(tile_x, tile_y, tile_zoom));
 
         if !loaded_tiles.contains(&(tile_x, tile_y, tile_zoom)) && !already_pending {
             // Mark as loaded to prevent duplicate requests
-            loaded_tiles.push((tile_x, tile_y, tile_zoom));
-            concurrent_loads += 1;
+            loaded_tiles.insert((tile_x, tile_y, tile_zoom));
+
+            // Reuse the decoded textures if we've seen this tile recently - no download or decode needed
+            // Only the layers missing from the texture cache are loaded
+            let now = osm_data.total_time;
+            let missing: Vec<_> = osm_data
+                .layers
+                .iter()
+                .filter(|layer| osm_data.texture_cache.get((layer.id, tile_x, tile_y, tile_zoom), now).is_none())
+                .map(|layer| (layer.id, osm_data.sources.get(&layer.url, &http_client.client, &osm_data.throttle, &osm_data.network, osm_data.tile_ttl)))
+                .collect();
+            if missing.is_empty() {
+                osm_data.texture_cache.ready.push((tile_x, tile_y, tile_zoom, is_background));
+                continue;
+            }
+
+            // Held by the task until its layers are loaded
+            let slot = osm_data.downloads.acquire(class);
+            let cancel = Arc::new(CancelFlag::default());
+            if !is_background {
+                let download = TileDownload { class, priority, cancel: cancel.clone() };
+                osm_data.downloads_in_flight.insert((tile_x, tile_y, tile_zoom), download);
+            }
 
             // Clone the pending_tiles for the async task
             let pending_tiles = osm_data.pending_tiles.clone();
-            let tile = OSMTile::new(tile_x, tile_y, tile_zoom);
+            let tile = TileId::new(tile_x, tile_y, tile_zoom);
 
             // Log what we're loading
-            debug_log!(debug_settings, "Loading {} tile: {}, {}, zoom {}", 
-                      if is_background { "background" } else { "focus" }, 
-                      tile_x, tile_y, tile_zoom);
+            debug_log!(debug_settings, "Loading {} tile: {}, {}, zoom {} ({} layers)", 
+                      class.name(), 
+                      tile_x, tile_y, tile_zoom, missing.len());
             
             // Use debug flag for async task
             let debug_mode = debug_settings.debug_mode;
 
-            // Spawn async task to load the tile image using the Tokio runtime
-            tokio_runtime.0.spawn(async move {
-                match load_tile_image(&tile).await {
-                    Ok(image) => {
-                        if debug_mode {
-                            info!("Successfully loaded {} tile: {}, {}, zoom {}", 
-                                 if is_background { "background" } else { "focus" },
-                                 tile.x, tile.y, tile.z);
-                        }
-                        pending_tiles.lock().push((tile.x, tile.y, tile.z, Some(image), is_background));
-                    },
-                    Err(e) => {
-                        if debug_mode {
-                            info!("Failed to load {} tile: {}, {}, zoom {} - using fallback. Error: {}", 
-                                 if is_background { "background" } else { "focus" },
-                                 tile.x, tile.y, tile.z, e);
+            // Spawn async task to load the tile images on the task runtime
+            task_runtime.spawn(async move {
+                let mut layers = Vec::with_capacity(missing.len());
+                for (id, source) in missing {
+                    // Cancelled as redundant, the tile is requested again if it is still wanted
+                    let Some(result) = unless_cancelled(&cancel, load_tile_image(source.as_ref(), tile)).await else {
+                        return;
+                    };
+                    let (image, low_res, error) = match result {
+                        Ok(loaded) => {
+                            if debug_mode {
+                                info!("Successfully loaded {} tile: {}, {}, zoom {}, layer {}{}", 
+                                     if is_background { "background" } else { "focus" },
+                                     tile.x, tile.y, tile.z, id,
+                                     if loaded.low_res { " (low-res ancestor)" } else { "" });
+                            }
+                            (Some(loaded.image), loaded.low_res, None)
+                        },
+                        Err(e) => {
+                            if debug_mode {
+                                info!("Failed to load {} tile: {}, {}, zoom {}, layer {}. Error: {}", 
+                                     if is_background { "background" } else { "focus" },
+                                     tile.x, tile.y, tile.z, id, e);
+                            }
+                            (None, false, Some(TileError::of(&e)))
                         }
-                        pending_tiles.lock().push((tile.x, tile.y, tile.z, None, is_background)); // None means use fallback
-                    }
+                    };
+                    layers.push(PendingLayer { id, image, low_res, error });
                 }
+                pending_tiles.lock().push(PendingTile {
+                    x: tile.x,
+                    y: tile.y,
+                    zoom: tile.z,
+                    layers,
+                    is_background,
+                });
+                drop(slot);
             });
         }
     }
 }
 
+// Cancel the downloads in flight that a parent or its children in the selection make redundant,
+// see redundant_requests, and return the selected tiles not to start for the same reason
+// Downloads still selected take their new priority
+fn coalesce_downloads(
+    osm_data: &mut OSMData,
+    selected: &[(u32, u32, u32, i32)],
+    debug_settings: &DebugSettings,
+) -> HashSet<TileId> {
+    let OSMData { downloads_in_flight, loaded_tiles, .. } = osm_data;
+
+    let mut requests = Vec::with_capacity(selected.len() + downloads_in_flight.len());
+    for &(x, y, z, priority) in selected {
+        if let Some(download) = downloads_in_flight.get_mut(&(x, y, z)) {
+            download.priority = priority;
+        }
+        requests.push(TileRequest { tile: TileId::new(x, y, z), priority, selected: true });
+    }
+    requests.extend(downloads_in_flight.iter().map(|(&(x, y, z), download)| TileRequest {
+        tile: TileId::new(x, y, z),
+        priority: download.priority,
+        selected: false,
+    }));
+
+    let redundant = redundant_requests(&requests);
+    downloads_in_flight.retain(|&(x, y, z), download| {
+        if !redundant.contains(&TileId::new(x, y, z)) {
+            return true;
+        }
+        debug_log!(debug_settings, "Cancelling redundant download of tile {}, {}, zoom {}", x, y, z);
+        download.cancel.cancel();
+        loaded_tiles.remove(&(x, y, z));
+        false
+    });
+    redundant
+}
+
+/// Recalculate the priorities of the visible tiles' downloads in flight from where the camera is
+/// now, and drop those it no longer sees or that fell far behind the tiles selected, e.g. after
+/// the camera turned around, so their download slots go to the tiles in view
+/// Prefetched tiles are where the camera is heading rather than in view, they keep theirs
+pub fn reprioritize_downloads(
+    mut osm_data: ResMut<OSMData>,
+    debug_settings: Res<DebugSettings>,
+    camera_query: Query<(&Transform, &Camera), With<MainCamera>>,
+) {
+    let Ok((camera_transform, camera)) = camera_query.get_single() else {
+        return;
+    };
+    let Some(lod_view) = camera_lod_view(camera, camera_transform) else {
+        return;
+    };
+    let drop_after = osm_data.selection_cutoff.max(1) * DROP_PRIORITY_FACTOR;
+
+    let OSMData { downloads_in_flight, loaded_tiles, .. } = osm_data.as_mut();
+    downloads_in_flight.retain(|&(x, y, z), download| {
+        if download.class != DownloadClass::Visible {
+            return true;
+        }
+        match lod_view.download_priority(TileId::new(x, y, z)) {
+            Some(priority) if priority <= drop_after => {
+                download.priority = priority;
+                true
+            }
+            _ => {
+                debug_log!(debug_settings, "Dropping download of tile {}, {}, zoom {}, out of view or too far", x, y, z);
+                download.cancel.cancel();
+                loaded_tiles.remove(&(x, y, z));
+                false
+            }
+        }
+    });
+}
+
+// A tile whose textures are ready to be put in the atlas
+enum ReadyTile {
+    Cached((u32, u32, u32), bool), // All layers still in the decoded-texture cache, is_background
+    Loaded(PendingTile),
+}
+
+impl ReadyTile {
+    fn id(&self) -> TileId {
+        match self {
+            ReadyTile::Cached((x, y, z), _) => TileId::new(*x, *y, *z),
+            ReadyTile::Loaded(tile) => TileId::new(tile.x, tile.y, tile.zoom),
+        }
+    }
+}
+
+// Ground point in the middle of the view, or below the camera when it looks above the horizon
+fn view_center(camera: &Transform) -> DVec2 {
+    let forward = camera.forward();
+    let center = if forward.y < 0.0 {
+        camera.translation + *forward * (camera.translation.y / -forward.y)
+    } else {
+        camera.translation
+    };
+    center.xz().as_dvec2()
+}
+
+// Distance from a ground point to the nearest point of a tile
+fn distance_to_tile(point: DVec2, id: TileId) -> f64 {
+    let origin = tile_world_origin(id);
+    let nearest = point.clamp(origin, origin + DVec2::splat(tile_world_size(id.z)));
+    point.distance(nearest)
+}
+
 // This system processes any pending tiles and creates entities for them
+// Within the upload budget, nearest the middle of the view first; the rest waits for the next frames
 pub fn apply_pending_tiles(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<StandardMaterial>>),
+    (mut atlas, mut atlas_materials): (ResMut<TileAtlas>, ResMut<Assets<TileMaterial>>),
     mut images: ResMut<Assets<Image>>,
-    mut osm_data: ResMut<OSMData>,
-    debug_settings: Res<DebugSettings>,
-    time: Res<Time>,
+    (mut osm_data, mut upload_budget): (ResMut<OSMData>, ResMut<TileUploadBudget>),
+    (debug_settings, time, task_runtime, style): (Res<DebugSettings>, Res<Time>, Res<TaskRuntime>, Res<Style>),
+    camera_query: Query<&Transform, With<MainCamera>>,
 ) {
-    // Take pending tiles
+    let started = Instant::now();
+
+    // Take pending tiles, and the tiles whose layers were all still in the decoded-texture cache
     let mut pending = osm_data.pending_tiles.lock();
-    let pending_tiles: Vec<_> = pending.drain(..).collect();
+    let mut ready: Vec<ReadyTile> = pending.drain(..).map(ReadyTile::Loaded).collect();
     drop(pending);
+    for tile in &ready {
+        if let ReadyTile::Loaded(tile) = tile {
+            osm_data.downloads_in_flight.remove(&(tile.x, tile.y, tile.zoom));
+        }
+    }
+    ready.extend(
+        osm_data
+            .texture_cache
+            .ready
+            .drain(..)
+            .map(|(x, y, z, is_background)| ReadyTile::Cached((x, y, z), is_background)),
+    );
+
+    if let Ok(camera) = camera_query.get_single() {
+        let center = view_center(camera);
+        ready.sort_by(|a, b| distance_to_tile(center, a.id()).total_cmp(&distance_to_tile(center, b.id())));
+    }
 
     // Get current time for tile usage tracking
     let current_time = time.elapsed_secs();
+    let cache_time = osm_data.total_time;
+    // The tile shader only sees the wrapped time
+    let fade_start = time.elapsed_secs_wrapped();
 
-    // Process each pending tile
-    for (x, y, z, image_opt, is_background) in pending_tiles {
-        let tile = OSMTile::new(x, y, z);
-        
-        // Create entity with either the loaded image or a fallback
-        let entity = match image_opt {
-            Some(image) => {
-                debug_log!(debug_settings, "Creating {} tile: {}, {}, zoom {}", 
+    let mut ready = ready.into_iter();
+    let mut created = 0;
+    while created < upload_budget.max_tiles && started.elapsed().as_secs_f32() * 1000.0 < upload_budget.max_ms {
+        let Some(ready_tile) = ready.next() else {
+            break;
+        };
+        created += 1;
+
+        let pending_tile = match ready_tile {
+            ReadyTile::Cached((x, y, z), is_background) => {
+                let Some((tile_image, bytes, _)) = composite_tile(&osm_data, &HashMap::new(), &images, (x, y, z)) else {
+                    // Evicted in the meantime, let the tile be requested again
+                    osm_data.loaded_tiles.remove(&(x, y, z));
+                    osm_data.loaded_background_tiles.remove(&(x, y, z));
+                    continue;
+                };
+
+                debug_log!(debug_settings, "Creating {} tile from texture cache: {}, {}, zoom {}", 
                           if is_background { "background" } else { "focus" }, x, y, z);
-                
-                // Standard tile creation with current time included
-                create_tile_mesh(
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                    &mut images,
-                    &tile,
-                    image,
-                    current_time,
-                    is_background
-                )
-            },
+
+                let entity = create_atlas_tile(&mut commands, &OSMTile::new(x, y, z), current_time, is_background);
+                commands.entity(entity).insert((TileTextureBytes(atlas.texture_bytes(bytes)), TileFadeIn(fade_start)));
+                let atlas_tile = (entity, TileId::new(x, y, z));
+                store_in_atlas((&mut atlas, &task_runtime), atlas_tile, &tile_image, &mut images, &mut atlas_materials);
+
+                osm_data.retries.remove((x, y, z));
+                add_active_tile(&mut commands, &mut osm_data, (x, y, z, entity), is_background);
+                continue;
+            }
+            ReadyTile::Loaded(pending_tile) => pending_tile,
+        };
+
+        let PendingTile { x, y, zoom: z, layers, is_background } = pending_tile;
+        let tile = OSMTile::new(x, y, z);
+        // Tiles that are missing or refused would fail the same way again
+        let retryable = layers.iter().filter_map(|layer| layer.error).all(TileError::retryable);
+
+        // Keep the decoded textures around for when this area is revisited
+        // Upscaled ancestor imagery is not cached so the real tile replaces it later
+        let mut low_res = false;
+        let mut loaded = HashMap::new();
+        for layer in layers {
+            let texture = layer.image.map(|image| {
+                let (texture, bytes) = create_tile_texture(&mut images, image);
+                if !layer.low_res {
+                    osm_data.texture_cache.insert((layer.id, x, y, z), texture.clone(), bytes, cache_time);
+                }
+                (texture, bytes)
+            });
+            // A layer that failed to load leaves the tile incomplete, like upscaled imagery
+            low_res |= layer.low_res || texture.is_none();
+            loaded.insert(layer.id, texture);
+        }
+
+        let Some((tile_image, bytes, drawn)) = composite_tile(&osm_data, &loaded, &images, (x, y, z)) else {
+            // The layer stack changed while the tile loaded, let it be requested again
+            osm_data.loaded_tiles.remove(&(x, y, z));
+            osm_data.loaded_background_tiles.remove(&(x, y, z));
+            continue;
+        };
+
+        // Create entity with either the layer imagery or a fallback when none of it loaded
+        let entity = if drawn > 0 || osm_data.layers.is_empty() {
+            debug_log!(debug_settings, "Creating {} tile: {}, {}, zoom {}", 
+                      if is_background { "background" } else { "focus" }, x, y, z);
+
+            let entity = create_atlas_tile(&mut commands, &tile, current_time, is_background);
+            commands.entity(entity).insert((TileTextureBytes(atlas.texture_bytes(bytes)), TileFadeIn(fade_start)));
+            let atlas_tile = (entity, TileId::new(x, y, z));
+            store_in_atlas((&mut atlas, &task_runtime), atlas_tile, &tile_image, &mut images, &mut atlas_materials);
+
+            if low_res {
+                commands.entity(entity).insert(LowResTile);
+            }
+
+            osm_data.retries.remove((x, y, z));
+            entity
+        } else {
+            debug_log!(debug_settings, "Creating fallback entity for {} tile: {}, {}, zoom {}", 
+                      if is_background { "background" } else { "focus" }, x, y, z);
+
+            if !retryable {
+                osm_data.retries.give_up((x, y, z));
+                debug_log!(debug_settings, "Not retrying tile {}, {}, zoom {}, it is missing or refused", x, y, z);
+            } else if !osm_data.retries.record_failure((x, y, z), current_time) {
+                debug_log!(debug_settings, "Giving up on tile {}, {}, zoom {} after repeated failures", x, y, z);
+            }
+
+            // Standard fallback with current time included
+            create_fallback_tile_mesh(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &tile,
+                style.fallback_tile_color,
+                current_time,
+                is_background
+            )
+        };
+
+        add_active_tile(&mut commands, &mut osm_data, (x, y, z, entity), is_background);
+    }
+
+    // Tiles over budget go back in their queues
+    upload_budget.deferred_tiles = ready.len();
+    for ready_tile in ready {
+        match ready_tile {
+            ReadyTile::Cached((x, y, z), is_background) => osm_data.texture_cache.ready.push((x, y, z, is_background)),
+            ReadyTile::Loaded(pending_tile) => osm_data.pending_tiles.lock().push(pending_tile),
+        }
+    }
+}
+
+// Add a tile to the appropriate list of active tiles
+// A retried tile replaces the fallback it got before
+fn add_active_tile(commands: &mut Commands, osm_data: &mut OSMData, tile: (u32, u32, u32, Entity), is_background: bool) {
+    let active = if is_background {
+        &mut osm_data.background_tiles
+    } else {
+        &mut osm_data.tiles
+    };
+    let (x, y, z, entity) = tile;
+    if let Some(previous) = active.insert((x, y, z), entity) {
+        commands.entity(previous).despawn_recursive();
+    }
+
+    // The loaded marker may have been cleaned up while the tile was on its way
+    let loaded = if is_background {
+        &mut osm_data.loaded_background_tiles
+    } else {
+        &mut osm_data.loaded_tiles
+    };
+    loaded.insert((x, y, z));
+}
+
+/// Request failed tiles again once their retry is due
+/// The fallback stays in place until the new attempt arrives and replaces it; tiles whose
+/// fallback was cleaned up in the meantime start over when they come back into view
+pub fn retry_failed_tiles(
+    mut osm_data: ResMut<OSMData>,
+    time: Res<Time>,
+    fallback_query: Query<&TileCoords, With<FallbackTile>>,
+) {
+    let fallbacks: HashSet<_> = fallback_query.iter().map(|coords| (coords.x, coords.y, coords.zoom)).collect();
+    osm_data.retries.entries.retain(|key, _| fallbacks.contains(key));
+
+    // Clearing the loaded marker makes the tile pipeline request the tile again while it is in view
+    for key in osm_data.retries.take_due(time.elapsed_secs()) {
+        osm_data.loaded_tiles.remove(&key);
+        osm_data.loaded_background_tiles.remove(&key);
+    }
+}
+
+/// Blend a tile's image from the imagery of every layer in the stack, bottom first
+/// Layers come from the freshly loaded textures (None if a layer failed) or the texture cache
+/// Returns the image, the decoded bytes of its layers and how many layers were drawn,
+/// or None if a layer's imagery is missing, e.g. because the stack changed
+pub fn composite_tile(
+    osm_data: &OSMData,
+    loaded: &HashMap<u32, Option<(Handle<Image>, usize)>>,
+    images: &Assets<Image>,
+    (x, y, z): (u32, u32, u32),
+) -> Option<(Image, usize, usize)> {
+    let mut bytes = 0;
+    let mut stack = Vec::with_capacity(osm_data.layers.len());
+    for layer in &osm_data.layers {
+        let texture = match loaded.get(&layer.id) {
+            Some(texture) => texture.as_ref().map(|(handle, bytes)| (handle, *bytes)),
             None => {
-                debug_log!(debug_settings, "Creating fallback entity for {} tile: {}, {}, zoom {}", 
-                          if is_background { "background" } else { "focus" }, x, y, z);
-                
-                // Standard fallback with current time included
-                create_fallback_tile_mesh(
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                    &tile,
-                    current_time,
-                    is_background
-                )
+                let cached = osm_data.texture_cache.entries.get(&(layer.id, x, y, z))?;
+                Some((&cached.handle, cached.bytes))
             }
         };
+        let Some((handle, layer_bytes)) = texture else {
+            continue;
+        };
+        if let Some(image) = images.get(handle) {
+            stack.push((image, layer.opacity));
+            bytes += layer_bytes;
+        }
+    }
+
+    let drawn = stack.len();
+    Some((composite_layers(&stack), bytes, drawn))
+}
+
+// Copy a tile's composited image into the atlas, or have it compressed first for a compressed atlas
+fn store_in_atlas(
+    (atlas, task_runtime): (&mut TileAtlas, &TaskRuntime),
+    (entity, id): (Entity, TileId),
+    tile_image: &Image,
+    images: &mut Assets<Image>,
+    atlas_materials: &mut Assets<TileMaterial>,
+) {
+    if atlas.compressed {
+        compress_tile(atlas, task_runtime, (entity, id), tile_image);
+    } else if atlas.allocate(entity, &atlas_layer_data(&tile_image.data, false), images, atlas_materials).is_none() {
+        warn!("Tile image of {}x{} doesn't fit the tile atlas", tile_image.width(), tile_image.height());
+    }
+}
+
+/// Replace the image of a tile that already has an atlas layer, e.g. after the imagery layers changed
+/// Returns false if the tile has no layer or the image doesn't fit it
+pub fn rewrite_in_atlas(
+    (atlas, task_runtime): (&TileAtlas, &TaskRuntime),
+    (entity, id): (Entity, TileId),
+    tile_image: &Image,
+    images: &mut Assets<Image>,
+) -> bool {
+    if !atlas.slots.contains_key(&entity) {
+        return false;
+    }
+    if atlas.compressed {
+        compress_tile(atlas, task_runtime, (entity, id), tile_image);
+        return true;
+    }
+    atlas.write(entity, &atlas_layer_data(&tile_image.data, false), images)
+}
+
+// Compress a tile image and its mip chain in the background, store_compressed_tiles puts it in the atlas
+fn compress_tile(atlas: &TileAtlas, task_runtime: &TaskRuntime, (entity, id): (Entity, TileId), tile_image: &Image) {
+    let compressed_tiles = atlas.compressed_tiles.clone();
+    let rgba = tile_image.data.clone();
+    let compress = move || {
+        let data = atlas_layer_data(&rgba, true);
+        compressed_tiles.lock().push(CompressedTile { entity, id, data });
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    task_runtime.spawn_blocking(compress);
+    // There are no threads to compress on in the browser
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = task_runtime;
+        compress();
+    }
+}
+
+/// Compress the tile atlas when the settings ask for it and the GPU can sample BC1 textures
+/// Decided once, before the first atlas page is made
+pub fn setup_tile_compression(
+    settings: Res<UserSettings>,
+    render_device: Option<Res<RenderDevice>>,
+    mut atlas: ResMut<TileAtlas>,
+) {
+    let supported = render_device.is_some_and(|device| device.features().contains(WgpuFeatures::TEXTURE_COMPRESSION_BC));
+    atlas.compressed = settings.compress_tiles && supported;
+    if settings.compress_tiles && !supported {
+        info!("Tile textures are not compressed, the GPU doesn't support BC compression");
+    }
+}
+
+/// Put tile images compressed in the background into their atlas layers
+pub fn store_compressed_tiles(
+    mut commands: Commands,
+    (mut atlas, mut atlas_materials): (ResMut<TileAtlas>, ResMut<Assets<TileMaterial>>),
+    mut images: ResMut<Assets<Image>>,
+    tile_query: Query<&TileCoords>,
+    time: Res<Time>,
+) {
+    let compressed: Vec<CompressedTile> = atlas.compressed_tiles.lock().drain(..).collect();
+    for tile in compressed {
+        // Despawned while it was compressed
+        let Ok(coords) = tile_query.get(tile.entity) else {
+            continue;
+        };
+        if TileId::new(coords.x, coords.y, coords.zoom) != tile.id {
+            continue;
+        }
 
-        // Add to appropriate list of active tiles
-        if is_background {
-            osm_data.background_tiles.push((x, y, z, entity));
+        if atlas.slots.contains_key(&tile.entity) {
+            atlas.write(tile.entity, &tile.data, &mut images);
+        } else if atlas.allocate(tile.entity, &tile.data, &mut images, &mut atlas_materials).is_some() {
+            // Fade in from when the tile can be drawn
+            commands.entity(tile.entity).insert(TileFadeIn(time.elapsed_secs_wrapped()));
         } else {
-            osm_data.tiles.push((x, y, z, entity));
+            warn!("Compressed tile image of {} bytes doesn't fit the tile atlas", tile.data.len());
+        }
+    }
+}
+
+// Tint for tiles showing upscaled ancestor imagery, so stale or low-res areas are recognizable
+const LOW_RES_TINT: Color = Color::srgb(0.85, 0.85, 0.95);
+
+/// Return the atlas layers of despawned tiles
+pub fn release_atlas_layers(mut atlas: ResMut<TileAtlas>, mut removed_tiles: RemovedComponents<TileCoords>) {
+    for entity in removed_tiles.read() {
+        atlas.free(entity);
+    }
+}
+
+/// Keep every zoom level's tiles and batch meshes under one parent entity, so a whole zoom
+/// ring can be hidden, moved or despawned at once instead of tile by tile
+/// Parents are spawned for the first tile of their zoom level and despawned after the last one
+pub fn parent_tiles_by_zoom(
+    mut commands: Commands,
+    mut roots: Local<HashMap<u32, Entity>>,
+    new_tiles: Query<(Entity, &TileCoords), Without<Parent>>,
+    new_batches: Query<(Entity, &TileBatch), Without<Parent>>,
+    root_query: Query<(Entity, &TileZoomRoot, Option<&Children>)>,
+) {
+    let orphans: Vec<(Entity, u32)> = new_tiles
+        .iter()
+        .map(|(entity, coords)| (entity, coords.zoom))
+        .chain(new_batches.iter().map(|(entity, batch)| (entity, batch.zoom)))
+        .collect();
+
+    for (entity, root, children) in &root_query {
+        let empty = children.is_none_or(|children| children.is_empty());
+        if empty && !orphans.iter().any(|&(_, zoom)| zoom == root.zoom) {
+            roots.remove(&root.zoom);
+            commands.entity(entity).despawn();
         }
     }
+
+    for (entity, zoom) in orphans {
+        let root = *roots.entry(zoom).or_insert_with(|| {
+            commands
+                .spawn((
+                    Transform::IDENTITY,
+                    Visibility::default(),
+                    Name::new(format!("Tiles zoom {}", zoom)),
+                    TileZoomRoot { zoom },
+                ))
+                .id()
+        });
+        commands.entity(root).add_child(entity);
+    }
 }
 
-// This system updates which tiles are visible and marks the last time they were seen
+/// Tell other plugins about tiles being spawned and despawned and coming into or going out of view
+/// Runs after the visibility check, so what is in view is this frame's
+pub fn send_tile_events(
+    tile_query: Query<(Entity, &TileCoords, &ViewVisibility)>,
+    mut removed_tiles: RemovedComponents<TileCoords>,
+    mut known_tiles: Local<HashMap<Entity, (TileId, bool)>>,
+    mut spawned: EventWriter<TileSpawned>,
+    mut despawned: EventWriter<TileDespawned>,
+    mut visibility_changed: EventWriter<TileVisibilityChanged>,
+) {
+    for entity in removed_tiles.read() {
+        if let Some((id, _)) = known_tiles.remove(&entity) {
+            despawned.send(TileDespawned { id, entity });
+        }
+    }
+
+    for (entity, coords, view_visibility) in &tile_query {
+        let visible = view_visibility.get();
+        let (id, was_visible) = known_tiles.entry(entity).or_insert_with(|| {
+            let id = TileId::new(coords.x, coords.y, coords.zoom);
+            spawned.send(TileSpawned { id, entity });
+            (id, false)
+        });
+        if *was_visible != visible {
+            *was_visible = visible;
+            visibility_changed.send(TileVisibilityChanged { id: *id, entity, visible });
+        }
+    }
+}
+
+/// Push the tile appearance settings to the tile materials
+pub fn apply_tile_appearance(
+    appearance: Res<TileAppearance>,
+    mut atlas: ResMut<TileAtlas>,
+    mut materials: ResMut<Assets<TileMaterial>>,
+) {
+    if !appearance.is_changed() {
+        return;
+    }
+
+    let filter = TileFilter {
+        brightness: appearance.brightness,
+        grayscale: if appearance.grayscale { 1.0 } else { 0.0 },
+        night: if appearance.night_mode { 1.0 } else { 0.0 },
+        fade_duration: appearance.fade_duration,
+    };
+    atlas.set_filter(filter, &mut materials);
+}
+
+/// What a batch mesh needs to know about a tile
+pub type BatchTileData = (
+    &'static TileCoords,
+    &'static Transform,
+    Has<LowResTile>,
+    Option<&'static TileTint>,
+    Option<&'static TileFadeIn>,
+);
+
+/// Rebuild the batch meshes after tiles were added to or removed from the atlas
+/// Every zoom level on every atlas page is drawn as one mesh, so with one material per page
+/// whole zoom rings take a handful of draw calls
+pub fn rebuild_tile_batches(
+    mut commands: Commands,
+    mut atlas: ResMut<TileAtlas>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    tile_query: Query<BatchTileData>,
+    changed_tints: Query<(), Changed<TileTint>>,
+    mut removed_tints: RemovedComponents<TileTint>,
+    globe_settings: Res<GlobeSettings>,
+) {
+    if !changed_tints.is_empty() || removed_tints.read().next().is_some() {
+        atlas.dirty = true;
+    }
+    rebuild_batches(&mut commands, &mut atlas, &mut meshes, &tile_query, &globe_settings, None);
+}
+
+/// Rebuild the batch meshes of an atlas if it is dirty
+/// The provider comparison's atlas passes a lift and render layers: its batches are drawn just
+/// above the map's, for the comparison camera only
+pub fn rebuild_batches(
+    commands: &mut Commands,
+    atlas: &mut TileAtlas,
+    meshes: &mut Assets<Mesh>,
+    tile_query: &Query<BatchTileData>,
+    globe_settings: &GlobeSettings,
+    overlay: Option<(f32, &RenderLayers)>,
+) {
+    if !atlas.dirty {
+        return;
+    }
+    atlas.dirty = false;
+
+    // Group tiles by (zoom, page); slots of tiles spawned this frame are skipped
+    // until their entity exists, which marks the atlas dirty again next frame
+    let mut groups: HashMap<(u32, usize), Vec<BatchQuad>> = HashMap::new();
+    let mut pending = false;
+    for (entity, &(page, layer)) in &atlas.slots {
+        let Ok((coords, transform, low_res, tint, fade_in)) = tile_query.get(*entity) else {
+            pending = true;
+            continue;
+        };
+        let tint = match tint {
+            Some(tint) => tint.0,
+            None if low_res => LOW_RES_TINT,
+            None => Color::NONE,
+        };
+        groups.entry((coords.zoom, page)).or_default().push(BatchQuad {
+            transform: *transform,
+            layer,
+            tint,
+            fade_start: fade_in.map_or(f32::MIN, |fade_in| fade_in.0),
+        });
+    }
+    atlas.dirty = pending;
+
+    // Drop batches that no longer have tiles
+    let empty: Vec<_> = atlas.batches.keys().filter(|key| !groups.contains_key(key)).copied().collect();
+    for key in empty {
+        if let Some(entity) = atlas.batches.remove(&key) {
+            // Recursive, so its zoom level's parent lets go of it
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for ((zoom, page), quads) in groups {
+        let Some(material) = atlas.material(page) else {
+            continue;
+        };
+        let mesh = build_batch_mesh(&quads, globe_settings.subdivisions_at(zoom));
+        // Bevy only computes bounds for new entities, so keep them in sync with the mesh here
+        let Some(aabb) = mesh.compute_aabb() else {
+            continue;
+        };
+        let mesh = meshes.add(mesh);
+        let transform = Transform::from_xyz(0.0, overlay.map_or(0.0, |(lift, _)| lift), 0.0);
+
+        match atlas.batches.get(&(zoom, page)) {
+            Some(&entity) => {
+                // Back at its zoom root's origin, in case the render origin moved since the last build
+                commands.entity(entity).insert((Mesh3d(mesh), aabb, transform));
+            }
+            None => {
+                let entity = commands
+                    .spawn((
+                        Mesh3d(mesh),
+                        MeshMaterial3d(material),
+                        transform,
+                        aabb,
+                        Name::new(format!("Tile batch zoom {}, page {}", zoom, page)),
+                        TileBatch { zoom },
+                    ))
+                    .id();
+                if let Some((_, render_layers)) = overlay {
+                    commands.entity(entity).insert(render_layers.clone());
+                }
+                // Curved tiles leave the flat bounds, see update_tile_globe
+                if atlas.globe.blend > 0.0 {
+                    commands.entity(entity).insert(NoFrustumCulling);
+                }
+                atlas.batches.insert((zoom, page), entity);
+            }
+        }
+    }
+}
+
+// This system marks the last time tiles were seen and removes tiles that have been out of view for a while
+// Whether a tile is in view comes from Bevy's frustum culling, see TilesPlugin
 pub fn update_visible_tiles(
-    mut tile_query: Query<(&mut TileCoords, &Transform, Entity)>,
-    camera_query: Query<&Transform, With<Camera3d>>,
+    mut tile_query: Query<(&mut TileCoords, &Transform, &ViewVisibility, Entity)>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    osm_data: Res<OSMData>,
     time: Res<Time>,
     mut commands: Commands,
 ) {
     if let Ok(camera_transform) = camera_query.get_single() {
         let current_time = time.elapsed_secs();
-        
-        // Get camera position and forward direction
         let camera_pos = camera_transform.translation;
-        let camera_forward = camera_transform.forward();
-        
+
         // Create list of entities to despawn
         let mut to_despawn = Vec::new();
-        
+
         // Update all tiles
-        for (mut tile_coords, tile_transform, entity) in tile_query.iter_mut() {
-            let tile_pos = tile_transform.translation;
-            
-            // Calculate the vector from camera to tile center
-            let to_tile = tile_pos - camera_pos;
-            let distance = to_tile.length();
-            
-            // For tiles to be visible, they should be:
-            // 1. Within a reasonable distance (based on zoom level)
-            // 2. Roughly within the camera's field of view
-            
-            // Calculate max visible distance based on zoom and allow larger view area
+        for (mut tile_coords, tile_transform, view_visibility, entity) in tile_query.iter_mut() {
+            let distance = (tile_transform.translation - camera_pos).length();
+
+            // The far plane is far beyond the loaded rings, so distance still limits what counts as seen
             let zoom_factor = 1.0 + 0.7 * (MAX_ZOOM_LEVEL - tile_coords.zoom) as f32;
-            let max_distance = 75.0 * zoom_factor; // Increased from 50.0 to 75.0 for wider view
-            
-            // Use a wider angle check (more permissive) to avoid gaps at edges
-            let forward_dot = camera_forward.dot(to_tile.normalize());
-            
-            // Is the tile visible? More permissive check
-            // Forward dot > -0.3 means roughly within ~110 degree field of view (instead of 90)
-            let is_visible = distance < max_distance && forward_dot > -0.3;
-            
-            if is_visible {
+            // Scaled with the render distance, 75 units at the default of 3 tiles
+            let max_distance = 25.0 * osm_data.render_distance as f32 * zoom_factor;
+
+            if view_visibility.get() && distance < max_distance {
                 // Update last used time if visible
                 tile_coords.last_used = current_time;
             } else {
                 // Tile is not visible
                 let time_since_used = current_time - tile_coords.last_used;
-                
+
                 // After 1.5 seconds of being outside view, remove non-background tiles
                 // Slightly increased from 1.0 to 1.5 to prevent rapid flickering at edges
                 if time_since_used > 1.5 && tile_coords.zoom > 6 {
@@ -594,7 +1128,7 @@ pub fn update_visible_tiles(
                 }
             }
         }
-        
+
         // Despawn entities outside view
         for entity in to_despawn {
             commands.entity(entity).despawn_recursive();
@@ -627,8 +1161,6 @@ pub fn cleanup_old_tiles(
 
     let mut focus_tiles_to_remove = Vec::new();
     let mut background_tiles_to_remove = Vec::new();
-    let mut focus_indices_to_remove = Vec::new();
-    let mut background_indices_to_remove = Vec::new();
 
     // Check all tiles in the system
     for (entity, tile_coords) in tile_query.iter() {
@@ -646,42 +1178,20 @@ pub fn cleanup_old_tiles(
 
         // Check if the timeout has been exceeded
         if time_since_used > timeout {
-            if is_background {
-                // Check if it's a background tile
-                if let Some(idx) = osm_data.background_tiles.iter().position(|&(x, y, z, e)|
-                    x == tile_coords.x && y == tile_coords.y && z == tile_coords.zoom && e == entity) {
-                    background_tiles_to_remove.push(entity);
-                    background_indices_to_remove.push(idx);
-                }
+            let key = (tile_coords.x, tile_coords.y, tile_coords.zoom);
+            let (active, to_remove) = if is_background {
+                (&mut osm_data.background_tiles, &mut background_tiles_to_remove)
             } else {
-                // Check if it's a focus tile
-                if let Some(idx) = osm_data.tiles.iter().position(|&(x, y, z, e)|
-                    x == tile_coords.x && y == tile_coords.y && z == tile_coords.zoom && e == entity) {
-                    focus_tiles_to_remove.push(entity);
-                    focus_indices_to_remove.push(idx);
-                }
+                (&mut osm_data.tiles, &mut focus_tiles_to_remove)
+            };
+            // Remove the tile from our tracking map, if this entity is the one tracked
+            if active.get(&key) == Some(&entity) {
+                active.remove(&key);
+                to_remove.push(entity);
             }
         }
     }
 
-    // Sort indices in reverse order so we can remove without changing other indices
-    focus_indices_to_remove.sort_by(|a, b| b.cmp(a));
-    background_indices_to_remove.sort_by(|a, b| b.cmp(a));
-
-    // Remove focus tiles from our tracking list
-    for &idx in &focus_indices_to_remove {
-        if idx < osm_data.tiles.len() {
-            osm_data.tiles.remove(idx);
-        }
-    }
-
-    // Remove background tiles from our tracking list
-    for &idx in &background_indices_to_remove {
-        if idx < osm_data.background_tiles.len() {
-            osm_data.background_tiles.remove(idx);
-        }
-    }
-
     // Count the number of tiles to be removed
     let focus_removed = focus_tiles_to_remove.len();
     let background_removed = background_tiles_to_remove.len();
@@ -691,21 +1201,20 @@ pub fn cleanup_old_tiles(
         commands.entity(entity).despawn_recursive();
     }
 
-    // Also clean up the loaded_tiles lists periodically to prevent them from growing too large
+    // Also clean up the loaded_tiles sets periodically to prevent them from growing too large
     // Keep entries for currently loaded tiles
-    let active_focus_coords: Vec<(u32, u32, u32)> = osm_data.tiles
+    // Tiles waiting to be spawned stay loaded too, or they would be requested again
+    let waiting: HashSet<(u32, u32, u32)> = osm_data.pending_tiles
+        .lock()
         .iter()
-        .map(|&(x, y, z, _)| (x, y, z))
+        .map(|tile| (tile.x, tile.y, tile.zoom))
+        .chain(osm_data.texture_cache.ready.iter().map(|&(x, y, z, _)| (x, y, z)))
         .collect();
-    
-    let active_background_coords: Vec<(u32, u32, u32)> = osm_data.background_tiles
-        .iter()
-        .map(|&(x, y, z, _)| (x, y, z))
-        .collect();
-    
+
     // Remove entries from loaded_tiles that are no longer needed
-    osm_data.loaded_tiles.retain(|coords| active_focus_coords.contains(coords));
-    osm_data.loaded_background_tiles.retain(|coords| active_background_coords.contains(coords));
+    let OSMData { tiles, background_tiles, loaded_tiles, loaded_background_tiles, .. } = &mut *osm_data;
+    loaded_tiles.retain(|coords| tiles.contains_key(coords) || waiting.contains(coords));
+    loaded_background_tiles.retain(|coords| background_tiles.contains_key(coords) || waiting.contains(coords));
 
     // Log cleanup results if any tiles were removed
     if focus_removed > 0 || background_removed > 0 {
@@ -714,8 +1223,315 @@ pub fn cleanup_old_tiles(
     }
 }
 
+/// Despawn the least recently visible tiles while their textures exceed the memory budget
+/// Evicted tiles also leave the texture cache so their GPU textures are freed
+pub fn enforce_tile_memory_budget(
+    mut commands: Commands,
+    mut osm_data: ResMut<OSMData>,
+    mut budget: ResMut<TileMemoryBudget>,
+    debug_settings: Res<DebugSettings>,
+    time: Res<Time>,
+    tile_query: Query<(Entity, &TileCoords, &TileTextureBytes)>,
+) {
+    budget.used_bytes = tile_query.iter().map(|(_, _, bytes)| bytes.0).sum();
+    if budget.used_bytes <= budget.budget_bytes {
+        return;
+    }
+
+    // Tiles seen this frame are never evicted, even if that leaves usage over budget
+    let now = time.elapsed_secs();
+    let mut candidates: Vec<_> = tile_query
+        .iter()
+        .filter(|(_, coords, _)| coords.last_used < now)
+        .collect();
+    candidates.sort_by(|a, b| a.1.last_used.total_cmp(&b.1.last_used));
+
+    let mut evicted = 0;
+    for (entity, coords, bytes) in candidates {
+        if budget.used_bytes <= budget.budget_bytes {
+            break;
+        }
+
+        let key = (coords.x, coords.y, coords.zoom);
+        if osm_data.tiles.get(&key) == Some(&entity) {
+            osm_data.tiles.remove(&key);
+        }
+        if osm_data.background_tiles.get(&key) == Some(&entity) {
+            osm_data.background_tiles.remove(&key);
+        }
+        osm_data.loaded_tiles.remove(&key);
+        osm_data.loaded_background_tiles.remove(&key);
+        osm_data.texture_cache.remove_tile(key);
+        commands.entity(entity).despawn_recursive();
+
+        budget.used_bytes -= bytes.0;
+        evicted += 1;
+    }
+
+    budget.evicted_tiles += evicted;
+    if evicted > 0 {
+        debug_log!(debug_settings, "Evicted {} tiles to stay within the tile memory budget ({:.1}/{:.1} MB)",
+                  evicted,
+                  budget.used_bytes as f32 / (1024.0 * 1024.0),
+                  budget.budget_bytes as f32 / (1024.0 * 1024.0));
+    }
+}
+
 // The auto_detect_zoom_level system is no longer needed as our adaptive system handles zoom levels
 // Keep this system empty as a placeholder in case other systems depend on it being registered
-pub fn auto_detect_zoom_level(_: ResMut<OSMData>, _: Query<&Transform, With<Camera3d>>, _: Commands, _: Res<DebugSettings>) {
+pub fn auto_detect_zoom_level(_: ResMut<OSMData>, _: Query<&Transform, With<MainCamera>>, _: Commands, _: Res<DebugSettings>) {
     // Intentionally empty - zoom level detection is now handled in process_tiles
-} 
\ No newline at end of file
+} 
+/// Run the registered tile generators for every newly spawned tile
+pub fn run_tile_generators(
+    mut commands: Commands,
+    generators: Res<TileGenerators>,
+    mut spawned: EventReader<TileSpawned>,
+    tile_query: Query<Has<BackgroundTile>, With<TileCoords>>,
+) {
+    for event in spawned.read() {
+        // Tiles replaced since they were spawned have nothing left to attach content to
+        let Ok(is_background) = tile_query.get(event.entity) else {
+            continue;
+        };
+        let context = TileContext {
+            id: event.id,
+            bounds: event.id.bounds(),
+            tile_entity: event.entity,
+            is_background,
+        };
+
+        for generator in &generators.generators {
+            commands.run_system_with_input(*generator, context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::camera::Viewport;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+    use crate::osm::LayerSource;
+    use crate::resources::{BASE_LAYER_ID, DebugSettings, MovementSettings, CameraMode, CameraMotion, WalkSettings};
+    use crate::utils::geo::{GeoPos, GeoTransform};
+
+    // Simulated time per frame, short enough that no tile times out while the others load
+    const FRAME: Duration = Duration::from_millis(10);
+
+    // Tiles spawned and despawned over a simulation
+    #[derive(Resource, Default)]
+    struct TileLog {
+        spawned: Vec<TileId>,
+        despawned: Vec<TileId>,
+    }
+
+    fn log_tile_events(mut log: ResMut<TileLog>, mut spawned: EventReader<TileSpawned>, mut despawned: EventReader<TileDespawned>) {
+        log.spawned.extend(spawned.read().map(|event| event.id));
+        log.despawned.extend(despawned.read().map(|event| event.id));
+    }
+
+    // Stands in for Bevy's frustum culling, which needs the renderer: tiles within a few
+    // camera heights of the ground below the camera are in view
+    fn see_tiles_near_camera(
+        camera_query: Query<&Transform, With<MainCamera>>,
+        mut tile_query: Query<(&TileCoords, &mut ViewVisibility)>,
+    ) {
+        let Ok(camera) = camera_query.get_single() else {
+            return;
+        };
+        let (ground, reach) = (camera.translation.xz(), camera.translation.y * 10.0);
+        for (coords, mut view_visibility) in &mut tile_query {
+            let origin = tile_world_origin(TileId::new(coords.x, coords.y, coords.zoom)).as_vec2();
+            let nearest = ground.clamp(origin, origin + Vec2::splat(tile_world_size(coords.zoom) as f32));
+            *view_visibility = ViewVisibility::HIDDEN;
+            if nearest.distance(ground) < reach {
+                view_visibility.set();
+            }
+        }
+    }
+
+    fn camera_above(lat: f64, lon: f64, altitude_m: f64) -> Transform {
+        let translation = GeoTransform::new(GeoPos::new(lat, lon), altitude_m).translation();
+        Transform::from_translation(translation).with_rotation(Quat::from_euler(EulerRot::YXZ, 0.0, -1.2, 0.0))
+    }
+
+    // The tile pipeline from requesting tiles to despawning them, without a window or renderer,
+    // loading from the mock tile server
+    fn simulation(camera: Transform) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<StandardMaterial>()
+            .init_asset::<TileMaterial>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .insert_resource(OSMData {
+                layers: vec![LayerSource { id: BASE_LAYER_ID, url: "mock://tiles".to_string(), opacity: 1.0 }],
+                ..default()
+            })
+            .insert_resource(TaskRuntime::new(2))
+            .insert_resource(HttpClient::new("vibers-tests"))
+            .init_resource::<DebugSettings>()
+            .init_resource::<MovementSettings>()
+            .init_resource::<CameraMotion>()
+            .init_resource::<CameraMode>()
+            .init_resource::<WalkSettings>()
+            .init_resource::<TileAtlas>()
+            .init_resource::<TileUploadBudget>()
+            .init_resource::<Style>()
+            .init_resource::<TileLog>()
+            .add_event::<TileSpawned>()
+            .add_event::<TileDespawned>()
+            .add_event::<TileVisibilityChanged>()
+            .add_systems(Update, (
+                process_tiles,
+                apply_pending_tiles,
+                see_tiles_near_camera,
+                update_visible_tiles,
+                cleanup_old_tiles,
+                send_tile_events,
+                log_tile_events,
+            ).chain());
+
+        app.world_mut().spawn((
+            Camera {
+                viewport: Some(Viewport { physical_size: UVec2::new(1280, 720), ..default() }),
+                ..default()
+            },
+            MainCamera,
+            camera,
+        ));
+        app
+    }
+
+    // Run frames until no download is in flight and nothing waits to be spawned
+    fn settle(app: &mut App) {
+        for _ in 0..500 {
+            app.update();
+            let osm_data = app.world().resource::<OSMData>();
+            let in_flight: usize = DownloadClass::ALL.iter().map(|&class| osm_data.downloads.in_flight(class)).sum();
+            if in_flight == 0 && osm_data.pending_tiles.lock().is_empty() && osm_data.texture_cache.ready.is_empty() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("Tiles were still loading after 500 frames");
+    }
+
+    #[test]
+    fn dedup_keeps_the_most_detailed_tiles_once() {
+        let mut tiles = vec![
+            (4, 4, 3, 5, false),
+            (9, 8, 4, 2, false),  // Inside (4, 4, 3)
+            (9, 8, 4, 1, false),  // Duplicate with a higher priority
+            (4, 4, 3, 1000, true), // Background tiles are deduplicated on their own
+            (20, 20, 5, 3, false),
+        ];
+        dedup_tiles(&mut tiles);
+        assert_eq!(tiles, vec![(9, 8, 4, 1, false), (20, 20, 5, 3, false), (4, 4, 3, 1000, true)]);
+    }
+
+    fn requested(app: &App) -> HashSet<TileId> {
+        let osm_data = app.world().resource::<OSMData>();
+        osm_data
+            .loaded_tiles
+            .iter()
+            .chain(&osm_data.loaded_background_tiles)
+            .map(|&(x, y, z)| TileId::new(x, y, z))
+            .collect()
+    }
+
+    #[test]
+    fn requests_and_spawns_the_same_tiles_on_every_run() {
+        let camera = camera_above(53.2194, 6.5665, 1500.0);
+        let runs: Vec<(HashSet<TileId>, HashSet<TileId>)> = (0..2)
+            .map(|_| {
+                let mut app = simulation(camera);
+                settle(&mut app);
+                let spawned = app.world().resource::<TileLog>().spawned.iter().copied().collect();
+                (requested(&app), spawned)
+            })
+            .collect();
+
+        let (requested, spawned) = &runs[0];
+        assert!(!requested.is_empty());
+        assert_eq!(requested, spawned, "every requested tile is spawned, and only those");
+        assert_eq!(runs[0], runs[1]);
+
+        // The ground below the camera is covered in detail, with background tiles around it
+        let below = GeoPos::from_translation(camera.translation);
+        let detail = spawned.iter().map(|id| id.z).max().unwrap();
+        assert!(detail >= 13, "most detailed zoom was {}", detail);
+        assert!(spawned.contains(&below.tile(detail)));
+        let background_zoom = requested.iter().map(|id| id.z).min().unwrap();
+        assert!(spawned.contains(&below.tile(background_zoom)));
+    }
+
+    // Every spawned tile is one entity, tracked under its address in OSMData
+    fn assert_tiles_spawned_once(app: &mut App) {
+        let mut tile_query = app.world_mut().query::<(Entity, &TileCoords)>();
+        let mut entities: HashMap<(u32, u32, u32), Vec<Entity>> = HashMap::new();
+        for (entity, coords) in tile_query.iter(app.world()) {
+            entities.entry((coords.x, coords.y, coords.zoom)).or_default().push(entity);
+        }
+        let duplicated: Vec<_> = entities.iter().filter(|(_, entities)| entities.len() > 1).collect();
+        assert!(duplicated.is_empty(), "tiles spawned more than once: {:?}", duplicated);
+
+        let osm_data = app.world().resource::<OSMData>();
+        let tracked: HashMap<_, _> = osm_data.tiles.iter().chain(&osm_data.background_tiles).map(|(&key, &entity)| (key, vec![entity])).collect();
+        assert_eq!(tracked, entities);
+    }
+
+    #[test]
+    fn no_tile_is_spawned_twice() {
+        let mut app = simulation(camera_above(53.2194, 6.5665, 1500.0));
+        settle(&mut app);
+        assert_tiles_spawned_once(&mut app);
+
+        // Requesting every tile again, like a retry, replaces the tiles on the map
+        {
+            let mut osm_data = app.world_mut().resource_mut::<OSMData>();
+            osm_data.loaded_tiles.clear();
+            osm_data.loaded_background_tiles.clear();
+        }
+        settle(&mut app);
+        assert_tiles_spawned_once(&mut app);
+
+        // And so does moving back and forth over tiles that were just loaded
+        let mut camera_query = app.world_mut().query_filtered::<&mut Transform, With<MainCamera>>();
+        for lon in [6.58, 6.5665, 6.58] {
+            *camera_query.single_mut(app.world_mut()) = camera_above(53.2194, lon, 1500.0);
+            settle(&mut app);
+            assert_tiles_spawned_once(&mut app);
+        }
+    }
+
+    #[test]
+    fn tiles_left_behind_are_despawned() {
+        let start = camera_above(53.2194, 6.5665, 1500.0);
+        let mut app = simulation(start);
+        settle(&mut app);
+        let first_spawned: HashSet<TileId> = app.world().resource::<TileLog>().spawned.iter().copied().collect();
+        let detail = first_spawned.iter().map(|id| id.z).max().unwrap();
+
+        // About 70 km east, past Leer
+        let destination = camera_above(53.2194, 7.6, 1500.0);
+        let mut camera_query = app.world_mut().query_filtered::<&mut Transform, With<MainCamera>>();
+        *camera_query.single_mut(app.world_mut()) = destination;
+        // Long enough for tiles out of view to time out
+        for _ in 0..3 {
+            settle(&mut app);
+            for _ in 0..100 {
+                app.update();
+            }
+        }
+        settle(&mut app);
+
+        let log = app.world().resource::<TileLog>();
+        assert!(log.despawned.iter().all(|id| first_spawned.contains(id) || log.spawned.contains(id)));
+        assert!(log.despawned.contains(&GeoPos::from_translation(start.translation).tile(detail)));
+        assert!(log.spawned.contains(&GeoPos::from_translation(destination.translation).tile(detail)));
+    }
+}
@@ -1,6 +1,20 @@
 use bevy::prelude::*;
-use crate::components::{ZoomLevelText, TileCountText, FpsCounterText, TileCoords};
-use crate::systems::tiles;
+use bevy::utils::Instant;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use crate::components::{ZoomLevelText, TileCountText, FpsCounterText, StatusBarText, ServerWarningText, ToastText, CopyLocationButton, MainCamera, TileCoords};
+use crate::events::ShowToast;
+use crate::resources::{CursorPick, DebugSettings, InputMap, OSMData, TileMemoryBudget};
+use crate::resources::input_map::COPY_LOCATION;
+use crate::osm::error_counts;
+use crate::systems::tile_diagnostics::TILE_DIAGNOSTICS;
+use crate::utils::clipboard::copy_to_clipboard;
+use crate::utils::deep_link::location_text;
+use crate::utils::geo::{normalize_lon, GeoPos};
+
+// Shown while the tile servers can't be reached
+const OFFLINE_WARNING: &str = "Offline: showing cached tiles, downloads resume when the network is back";
+// Seconds a toast stays on screen
+const TOAST_DURATION: f32 = 2.5;
 
 /// Sets up the UI elements for the game
 pub fn setup_ui(mut commands: Commands) {
@@ -55,47 +69,258 @@ pub fn setup_ui(mut commands: Commands) {
         BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
         FpsCounterText,
     ));
+    
+    // Spawn status bar text (bottom left)
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        // Set a background color to make text more visible
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        StatusBarText,
+    ));
+
+    // Spawn the tile server warning (top center, below the time slider), hidden until needed
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.85, 0.3)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            left: Val::Percent(50.0),
+            width: Val::Px(400.0),
+            margin: UiRect::left(Val::Px(-200.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.4, 0.1, 0.0, 0.7)),
+        Visibility::Hidden,
+        ServerWarningText,
+    ));
+
+    // Spawn the copy location button (top left, next to the zoom level)
+    commands
+        .spawn((
+            Button,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(120.0),
+                padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+            CopyLocationButton,
+        ))
+        .with_child((
+            Text::new("Copy link"),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+        ));
+
+    // Spawn the toast (bottom center, above the status bar), hidden until there is a message
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(70.0),
+            left: Val::Percent(50.0),
+            width: Val::Px(400.0),
+            margin: UiRect::left(Val::Px(-200.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+        ToastText::default(),
+    ));
 }
 
-/// Updates the zoom level text based on the camera's current position
+/// Updates the zoom level text with the zoom level of the ground below the camera
 pub fn update_zoom_level_text(
     mut text_query: Query<&mut Text, With<ZoomLevelText>>,
-    camera_query: Query<(&Transform, &Camera), With<Camera3d>>,
+    osm_data: Res<OSMData>,
 ) {
-    let (transform, _) = if let Ok(cam) = camera_query.get_single() {
-        cam
-    } else {
-        return;
-    };
-
-    // Function is in the same module, we can access it directly
-    let zoom_level = tiles::calculate_base_zoom_level(transform.translation.y);
-
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.0 = format!("Zoom: {}", zoom_level);
+        text.0 = format!("Zoom: {}", osm_data.current_zoom);
     }
 }
 
 /// Updates the tile count text with the number of tiles currently in the scene
+/// and the texture memory they use against the tile memory budget
+/// In debug mode it also shows the tile downloads in flight per priority class
 pub fn update_tile_count_text(
     mut text_query: Query<&mut Text, With<TileCountText>>,
     tile_query: Query<&TileCoords>,
+    budget: Res<TileMemoryBudget>,
+    osm_data: Res<OSMData>,
+    debug_settings: Res<DebugSettings>,
 ) {
     let tile_count = tile_query.iter().count();
     
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.0 = format!("Tiles: {}", tile_count);
+        text.0 = format!(
+            "Tiles: {} | Textures: {:.0}/{:.0} MB",
+            tile_count,
+            budget.used_bytes as f32 / (1024.0 * 1024.0),
+            budget.budget_bytes as f32 / (1024.0 * 1024.0)
+        );
+        if debug_settings.debug_mode {
+            text.0 += &format!("\nDownloads: {}", osm_data.downloads.summary());
+        }
     }
 }
 
 /// Updates the FPS counter text
+/// In debug mode it also lists the tile diagnostics, smoothed like the FPS, and the failed
+/// tile loads per kind of error
 pub fn update_fps_counter(
     mut text_query: Query<&mut Text, With<FpsCounterText>>,
     time: Res<Time>,
+    diagnostics: Res<DiagnosticsStore>,
+    debug_settings: Res<DebugSettings>,
 ) {
-    let fps = 1.0 / time.delta_secs();
-    
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(1.0 / time.delta_secs() as f64);
+
     if let Ok(mut text) = text_query.get_single_mut() {
         text.0 = format!("FPS: {:.1}", fps);
+        if debug_settings.debug_mode {
+            for path in &TILE_DIAGNOSTICS {
+                let Some(diagnostic) = diagnostics.get(path) else {
+                    continue;
+                };
+                if let Some(value) = diagnostic.smoothed() {
+                    text.0 += &format!("\n{}: {:.1}{}", path, value, diagnostic.suffix);
+                }
+            }
+            let errors = error_counts();
+            if !errors.is_empty() {
+                let counts: Vec<_> = errors.iter().map(|(error, count)| format!("{} {}", error.name(), count)).collect();
+                text.0 += &format!("\ntile errors: {}", counts.join(", "));
+            }
+        }
+    }
+}
+
+/// Updates the status bar with the lat/lon and tile under the cursor
+pub fn update_status_bar(
+    mut text_query: Query<&mut Text, With<StatusBarText>>,
+    cursor_pick: Res<CursorPick>,
+) {
+    let status = match (cursor_pick.lat_lon, cursor_pick.tile) {
+        (Some((lat, lon)), Some((x, y, zoom))) => {
+            format!("Lat: {:.6}  Lon: {:.6}  |  Tile: {}/{}/{}", lat, lon, zoom, x, y)
+        }
+        _ => "No ground under cursor".to_string(),
+    };
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = status;
+    }
+}
+
+/// Warn about tile servers the loader has paused after repeated errors or disabled after
+/// refusing requests, and about being offline, when only cached tiles are shown (paused
+/// servers are implied then)
+pub fn update_server_warning(
+    mut text_query: Query<(&mut Text, &mut Visibility), With<ServerWarningText>>,
+    osm_data: Res<OSMData>,
+) {
+    let Ok((mut text, mut visibility)) = text_query.get_single_mut() else {
+        return;
+    };
+
+    let offline = osm_data.network.is_offline();
+
+    let warning = if offline {
+        OFFLINE_WARNING.to_string()
+    } else {
+        let throttle = osm_data.throttle.lock();
+        let disabled = throttle
+            .disabled_hosts()
+            .into_iter()
+            .map(|host| format!("{} refuses access (HTTP 403), disabled", host));
+        let paused = throttle
+            .paused_hosts(Instant::now())
+            .into_iter()
+            .map(|(host, left)| format!("{} is failing, paused for {}s", host, left.as_secs() + 1));
+        disabled.chain(paused).collect::<Vec<_>>().join("\n")
+    };
+    if text.0 != warning {
+        text.0 = warning;
+    }
+    visibility.set_if_neq(if text.0.is_empty() { Visibility::Hidden } else { Visibility::Inherited });
+}
+
+/// Show the latest toast message, and hide it once its time is up
+pub fn update_toast(
+    time: Res<Time>,
+    mut toast_events: EventReader<ShowToast>,
+    mut toast_query: Query<(&mut Text, &mut ToastText, &mut Visibility)>,
+) {
+    let Ok((mut text, mut toast, mut visibility)) = toast_query.get_single_mut() else {
+        return;
+    };
+
+    if let Some(ShowToast(message)) = toast_events.read().last() {
+        text.0.clone_from(message);
+        toast.remaining = TOAST_DURATION;
+    } else if toast.remaining > 0.0 {
+        toast.remaining -= time.delta_secs();
     }
+    visibility.set_if_neq(if toast.remaining > 0.0 { Visibility::Inherited } else { Visibility::Hidden });
+}
+
+/// Copy the view to the clipboard with the copy location key (C by default) or button:
+/// the camera's position as `lat,lon,zoom` and as an OpenStreetMap share link
+pub fn copy_location(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    osm_data: Res<OSMData>,
+    button_query: Query<&Interaction, (Changed<Interaction>, With<CopyLocationButton>)>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut toast_events: EventWriter<ShowToast>,
+) {
+    let clicked = button_query.iter().any(|interaction| *interaction == Interaction::Pressed);
+    if !clicked && !input_map.just_pressed(&keyboard_input, COPY_LOCATION) {
+        return;
+    }
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let geo = GeoPos::from_translation(camera.translation);
+    let text = location_text(GeoPos::new(geo.lat, normalize_lon(geo.lon)), osm_data.current_zoom);
+    let message = match copy_to_clipboard(&text) {
+        Ok(()) => format!("Copied {}", text.lines().next().unwrap_or_default()),
+        Err(e) => {
+            warn!("Couldn't copy the location to the clipboard: {}", e);
+            format!("Couldn't copy: {}", e)
+        }
+    };
+    info!("{}", text.replace('\n', " "));
+    toast_events.send(ShowToast(message));
 }
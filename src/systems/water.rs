@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::image::ImageSampler;
+use crate::components::{MainCamera, WaterSurface};
+use crate::resources::{EnvironmentSettings, OSMData, BASE_LAYER_ID, Sun, TileAtlas, TileContext, WaterSettings};
+use crate::systems::atmosphere::sun_color;
+use crate::utils::geo::GeoPos;
+use crate::water::{water_mask, WaterMaterial, WaterParams};
+
+// Height of the water surface above its tile in world units, enough to stay clear of the map
+// without lifting it above the tiles of higher zoom levels
+const WATER_HEIGHT: f32 = 0.002;
+
+/// Tile generator putting a water surface on tiles that are mostly water
+/// The water is found from the tile's base map image in the texture cache; tiles without one
+/// (fallbacks, upscaled ancestor imagery, a hidden base map) are left dry until their real image arrives
+pub fn spawn_tile_water(
+    In(context): In<TileContext>,
+    mut commands: Commands,
+    settings: Res<WaterSettings>,
+    osm_data: Res<OSMData>,
+    mut images: ResMut<Assets<Image>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<WaterMaterial>>),
+    mut plane: Local<Option<Handle<Mesh>>>,
+) {
+    let id = context.id;
+    let Some(cached) = osm_data.texture_cache.entries.get(&(BASE_LAYER_ID, id.x, id.y, id.z)) else {
+        return;
+    };
+    let Some(image) = images.get(&cached.handle) else {
+        return;
+    };
+
+    let mask = water_mask(&image.data, image.width(), settings.mask_size);
+    if mask.coverage < settings.min_coverage {
+        return;
+    }
+
+    let mut mask_image = Image::new(
+        Extent3d {
+            width: mask.size,
+            height: mask.size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        mask.texels,
+        TextureFormat::R8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    // Smooths the coarse mask into a coastline
+    mask_image.sampler = ImageSampler::linear();
+
+    let plane = plane
+        .get_or_insert_with(|| meshes.add(Plane3d::default().mesh().size(1.0, 1.0)))
+        .clone();
+    let material = materials.add(WaterMaterial {
+        params: WaterParams::default(),
+        mask: images.add(mask_image),
+    });
+
+    // Children live in tile-local space, the plane is centered on the tile
+    let surface = commands
+        .spawn((
+            Mesh3d(plane),
+            MeshMaterial3d(material),
+            Transform::from_xyz(0.5, WATER_HEIGHT, 0.5),
+            WaterSurface,
+            Name::new("Water"),
+        ))
+        .id();
+    commands.entity(context.tile_entity).add_child(surface);
+}
+
+/// Reflect the sky and sun of the moment in the water, and scale the waves to the latitude
+pub fn update_water_surfaces(
+    settings: Res<WaterSettings>,
+    environment: Res<EnvironmentSettings>,
+    sun: Res<Sun>,
+    mut materials: ResMut<Assets<WaterMaterial>>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let meters_per_unit = GeoPos::from_translation(camera.translation).meters_per_world_unit() as f32;
+
+    // The water itself darkens at night, leaving the reflections to light it
+    let color = settings.color.to_linear() * (0.1 + 0.9 * sun.daylight);
+    let glitter = ((sun.position.elevation as f32 + 1.0) / 2.0).clamp(0.0, 1.0);
+    let params = WaterParams {
+        color: color.to_vec3().extend(settings.reflectivity),
+        zenith: environment.sky_color(&sun).to_linear().to_vec4(),
+        horizon: environment.horizon_color(&sun).to_linear().to_vec4(),
+        sun_direction: sun.direction().extend(glitter),
+        sun_color: sun_color(&environment, &sun).to_linear().to_vec4(),
+        waves: Vec4::new(
+            settings.wave_length_m / meters_per_unit,
+            settings.wave_speed_m / meters_per_unit,
+            settings.wave_strength,
+            0.0,
+        ),
+    };
+
+    // Only touch materials that are out of date, every change re-uploads the material
+    let stale: Vec<_> = materials
+        .iter()
+        .filter(|(_, material)| material.params != params)
+        .map(|(id, _)| id)
+        .collect();
+    for id in stale {
+        if let Some(material) = materials.get_mut(id) {
+            material.params = params;
+        }
+    }
+}
+
+/// Hide the water when it is switched off or while globe mode curves the map,
+/// since the flat water surfaces would not follow the curve
+pub fn update_water_visibility(
+    settings: Res<WaterSettings>,
+    atlas: Res<TileAtlas>,
+    mut water_query: Query<&mut Visibility, With<WaterSurface>>,
+) {
+    let visibility = if settings.enabled && atlas.globe.blend == 0.0 {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    for mut water in water_query.iter_mut() {
+        water.set_if_neq(visibility);
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use std::sync::Arc;
+use crate::components::{BackgroundTile, FallbackTile, TileCoords, WeatherButton, WeatherPanel, WeatherText};
+use crate::overlays::weather::{request_radar_frames, RAINVIEWER_ATTRIBUTION};
+use crate::resources::{HttpClient, ImageryLayers, InputMap, OSMData, TaskRuntime, TileLoadCounts, WeatherRadar};
+use crate::resources::input_map::TOGGLE_WEATHER;
+use crate::resources::tile_debug::tile_load_states;
+use crate::resources::weather::RadarFramesReply;
+
+// Opacity of the radar over the map, light enough to read the streets under the rain
+const RADAR_OPACITY: f32 = 0.6;
+const RADAR_LAYER_NAME: &str = "Weather radar";
+// Shortest time a frame is shown while playing; frames whose tiles are still loading stay longer
+const FRAME_SECS: f32 = 0.5;
+
+type TileLoadQuery<'w, 's> = Query<'w, 's, (&'static TileCoords, Has<FallbackTile>), Without<BackgroundTile>>;
+
+/// Switch the weather radar on or off (F8 by default)
+/// Switching it on requests the frame list, switching it off removes the frames' layers
+pub fn toggle_weather(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut weather: ResMut<WeatherRadar>,
+    mut layers: ResMut<ImageryLayers>,
+    (task_runtime, http_client): (Res<TaskRuntime>, Res<HttpClient>),
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !input_map.just_pressed(&keyboard_input, TOGGLE_WEATHER) {
+        return;
+    }
+
+    if weather.active {
+        for &(_, id) in &weather.frames {
+            layers.remove(id);
+        }
+        *weather = WeatherRadar::default();
+        info!("Weather radar: OFF");
+        return;
+    }
+
+    weather.active = true;
+    let reply: RadarFramesReply = Arc::default();
+    weather.pending = Some(Arc::clone(&reply));
+    let client = http_client.client.clone();
+    task_runtime.spawn(async move {
+        let result = request_radar_frames(&client).await.map_err(|e| e.to_string());
+        *reply.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+    });
+    info!("Weather radar: ON");
+
+    // The cursor is released so the playback buttons can be clicked
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.cursor_options.visible = true;
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+/// Add an imagery layer per radar frame once the frame list arrived, and start playing from
+/// the oldest frame; the shown frame is listed on the layer panel as the weather radar
+pub fn receive_radar_frames(mut weather: ResMut<WeatherRadar>, mut layers: ResMut<ImageryLayers>) {
+    let Some(result) = weather.pending.as_ref().and_then(|reply| reply.lock().unwrap_or_else(|e| e.into_inner()).take()) else {
+        return;
+    };
+
+    weather.pending = None;
+    let frames = match result {
+        Ok(frames) => frames,
+        Err(e) => {
+            warn!("Failed to load the weather radar frames: {}", e);
+            weather.error = Some(e);
+            return;
+        }
+    };
+
+    info!("Weather radar: {} frames", frames.len());
+    weather.frames = frames
+        .into_iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let id = layers.add(RADAR_LAYER_NAME, &frame.url, Some(RAINVIEWER_ATTRIBUTION), RADAR_OPACITY, i == 0);
+            if let Some(layer) = layers.get_mut(id) {
+                layer.listed = i == 0;
+            }
+            (frame, id)
+        })
+        .collect();
+    weather.playing = true;
+    show_frame(&mut weather, &mut layers, 0);
+}
+
+/// Step through the frames while playing
+/// A frame stays until the tiles in view have loaded, so the first loop waits for the downloads
+pub fn animate_radar(
+    time: Res<Time>,
+    mut weather: ResMut<WeatherRadar>,
+    mut layers: ResMut<ImageryLayers>,
+    osm_data: Res<OSMData>,
+    tile_query: TileLoadQuery,
+) {
+    if !weather.playing || weather.frames.is_empty() {
+        return;
+    }
+    weather.shown_secs += time.delta_secs();
+    if weather.shown_secs < FRAME_SECS {
+        return;
+    }
+
+    let spawned = tile_query.iter().map(|(coords, failed)| ((coords.x, coords.y, coords.zoom), failed));
+    if TileLoadCounts::count(&tile_load_states(&osm_data, spawned)).in_progress() > 0 {
+        return;
+    }
+
+    let next = weather.step(1);
+    show_frame(&mut weather, &mut layers, next);
+}
+
+// Show the frame at `index` in place of the shown frame
+// The next frame takes over the shown frame's row on the layer panel, its visibility, opacity
+// and place in the stack, so the radar stays hidden, faded or moved while it plays
+fn show_frame(weather: &mut WeatherRadar, layers: &mut ImageryLayers, index: usize) {
+    let position = |layers: &ImageryLayers, i: usize| {
+        let (_, id) = weather.frames.get(i)?;
+        layers.layers.iter().position(|layer| layer.id == *id)
+    };
+    if let (Some(shown), Some(next)) = (position(layers, weather.current), position(layers, index)) {
+        let (visible, opacity) = (layers.layers[shown].visible, layers.layers[shown].opacity);
+        let shown_layer = &mut layers.layers[shown];
+        shown_layer.visible = false;
+        shown_layer.listed = false;
+        let next_layer = &mut layers.layers[next];
+        next_layer.visible = visible;
+        next_layer.opacity = opacity;
+        next_layer.listed = true;
+        layers.layers.swap(shown, next);
+    }
+    weather.current = index;
+    weather.shown_secs = 0.0;
+}
+
+/// Spawn the weather panel (top right, under the game status), hidden until the radar is on
+pub fn setup_weather_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(70.0),
+                right: Val::Px(10.0),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            Visibility::Hidden,
+            WeatherPanel,
+        ))
+        .with_children(|panel| {
+            spawn_weather_button(panel, WeatherButton::Previous, "<");
+            spawn_weather_button(panel, WeatherButton::PlayPause, "Pause");
+            spawn_weather_button(panel, WeatherButton::Next, ">");
+            panel.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Node {
+                    min_width: Val::Px(130.0),
+                    ..default()
+                },
+                WeatherText,
+            ));
+        });
+}
+
+fn spawn_weather_button(panel: &mut ChildBuilder, button: WeatherButton, label: &str) {
+    panel
+        .spawn((
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.2, 0.2, 0.2, 0.8)),
+            button,
+        ))
+        .with_child((
+            Text::new(label),
+            TextFont {
+                font_size: 14.0,
+                ..default()
+            },
+        ));
+}
+
+/// Play, pause or step through the frames when a panel button is clicked
+/// Stepping pauses, so the chosen frame stays
+pub fn handle_weather_buttons(
+    mut weather: ResMut<WeatherRadar>,
+    mut layers: ResMut<ImageryLayers>,
+    button_query: Query<(&Interaction, &WeatherButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed || weather.frames.is_empty() {
+            continue;
+        }
+
+        match button {
+            WeatherButton::PlayPause => weather.playing = !weather.playing,
+            WeatherButton::Previous | WeatherButton::Next => {
+                let step = if *button == WeatherButton::Next { 1 } else { -1 };
+                let index = weather.step(step);
+                weather.playing = false;
+                show_frame(&mut weather, &mut layers, index);
+            }
+        }
+    }
+}
+
+/// Show the time of the visible frame and whether the radar is playing
+pub fn update_weather_panel(
+    weather: Res<WeatherRadar>,
+    mut panel_query: Query<&mut Visibility, With<WeatherPanel>>,
+    mut text_query: Query<&mut Text, With<WeatherText>>,
+    button_query: Query<(&WeatherButton, &Children)>,
+    mut label_query: Query<&mut Text, Without<WeatherText>>,
+) {
+    if !weather.is_changed() {
+        return;
+    }
+
+    if let Ok(mut visibility) = panel_query.get_single_mut() {
+        *visibility = if weather.active { Visibility::Inherited } else { Visibility::Hidden };
+    }
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.0 = match (weather.frames.get(weather.current), &weather.error) {
+            (Some((frame, _)), _) => format!("Radar {} ({}/{})", frame.label(), weather.current + 1, weather.frames.len()),
+            (None, Some(_)) => "Radar unavailable".to_string(),
+            (None, None) => "Loading radar...".to_string(),
+        };
+    }
+
+    for (button, children) in button_query.iter() {
+        if *button != WeatherButton::PlayPause {
+            continue;
+        }
+        if let Some(mut label) = children.first().and_then(|&child| label_query.get_mut(child).ok()) {
+            label.0 = if weather.playing { "Pause" } else { "Play" }.to_string();
+        }
+    }
+}
@@ -1,19 +1,23 @@
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::resources::InputMap;
+use crate::resources::input_map::TOGGLE_CURSOR_GRAB;
 
 /// Grab the mouse cursor when the app starts
-pub fn grab_mouse(mut windows: Query<&mut Window>) {
+pub fn grab_mouse(mut windows: Query<&mut Window, With<PrimaryWindow>>) {
     if let Ok(mut window) = windows.get_single_mut() {
         window.cursor_options.visible = false;
         window.cursor_options.grab_mode = bevy::window::CursorGrabMode::Locked;
     }
 }
 
-/// Toggle cursor grab with Escape key
+/// Toggle cursor grab (Escape by default)
 pub fn toggle_cursor_grab(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut windows: Query<&mut Window>,
+    input_map: Res<InputMap>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if input_map.just_pressed(&keyboard_input, TOGGLE_CURSOR_GRAB) {
         if let Ok(mut window) = windows.get_single_mut() {
             match window.cursor_options.grab_mode {
                 bevy::window::CursorGrabMode::None => {
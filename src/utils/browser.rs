@@ -0,0 +1,42 @@
+// Opens links in the user's web browser with the platform's opener
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::{Command, Stdio};
+
+/// Open a URL in the default browser
+/// Fails with Unsupported on platforms without a known opener
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_url(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "windows") {
+        // The empty title keeps start from taking the URL as the window title
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(unix) {
+        Command::new("xdg-open")
+    } else {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "no known way to open URLs"));
+    };
+
+    command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Open a URL in a new browser tab
+/// Fails when the browser blocks the popup
+#[cfg(target_arch = "wasm32")]
+pub fn open_url(url: &str) -> io::Result<()> {
+    let opened = web_sys::window()
+        .and_then(|window| window.open_with_url_and_target(url, "_blank").ok().flatten());
+    match opened {
+        Some(_) => Ok(()),
+        None => Err(io::Error::other("the browser didn't open a window")),
+    }
+}
@@ -0,0 +1,26 @@
+// Copies text to the system clipboard
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+
+// Kept for the whole run: on X11 and Wayland the copied text is served by the clipboard
+// that copied it, and lost when that is dropped
+#[cfg(not(target_arch = "wasm32"))]
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Put text on the system clipboard
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    let clipboard = match &mut *clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(arboard::Clipboard::new().map_err(io::Error::other)?),
+    };
+    clipboard.set_text(text).map_err(io::Error::other)
+}
+
+/// Browser builds don't copy, the clipboard API needs a permission prompt
+#[cfg(target_arch = "wasm32")]
+pub fn copy_to_clipboard(_text: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no clipboard in the browser build"))
+}
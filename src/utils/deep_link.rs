@@ -0,0 +1,154 @@
+// Links to a place on the map: geo: URIs and openstreetmap.org share URLs
+use crate::resources::constants::{MAX_ZOOM_LEVEL, MIN_ZOOM_LEVEL};
+use crate::utils::geo::{GeoPos, MAX_LATITUDE};
+
+/// A place a link points at, with the zoom level it was shared at if it has one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeepLink {
+    pub position: GeoPos,
+    pub zoom: Option<u32>,
+}
+
+/// Parse a link to a place:
+/// - geo:53.21,6.56 or geo:53.21,6.56?z=16 (RFC 5870, an altitude and parameters are ignored)
+/// - https://www.openstreetmap.org/#map=16/53.21/6.56, the URL of OpenStreetMap's share button
+///
+/// Zoom levels are rounded and clamped to the ones the map has
+pub fn parse_deep_link(link: &str) -> Option<DeepLink> {
+    let link = link.trim();
+    if let Some(rest) = strip_prefix_ignore_case(link, "geo:") {
+        return parse_geo_uri(rest);
+    }
+    if link.starts_with("http://") || link.starts_with("https://") {
+        return parse_osm_url(link);
+    }
+    None
+}
+
+/// The first link to a place in a text, e.g. a dropped .url shortcut or a pasted snippet
+pub fn find_deep_link(text: &str) -> Option<DeepLink> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>'))
+        .filter_map(|word| {
+            let start = ["geo:", "http://", "https://"].iter().filter_map(|scheme| word.find(scheme)).min()?;
+            parse_deep_link(&word[start..])
+        })
+        .next()
+}
+
+/// The openstreetmap.org page for a place, with a marker on it, at a zoom level of the map
+/// Five decimals are about a meter, finer than any zoom level shows
+pub fn osm_url(position: GeoPos, zoom: u32) -> String {
+    let (lat, lon) = (position.lat, position.lon);
+    format!("https://www.openstreetmap.org/?mlat={lat:.5}&mlon={lon:.5}#map={zoom}/{lat:.5}/{lon:.5}")
+}
+
+/// The link OpenStreetMap's share button makes for a view of a place
+pub fn osm_share_url(position: GeoPos, zoom: u32) -> String {
+    format!("https://www.openstreetmap.org/#map={}/{:.5}/{:.5}", zoom, position.lat, position.lon)
+}
+
+/// A view to share as text: `lat,lon,zoom` on the first line, the share link on the second
+/// Dropped onto the window as a text file, find_deep_link reads the view back from the link
+pub fn location_text(position: GeoPos, zoom: u32) -> String {
+    format!("{:.5},{:.5},{}\n{}", position.lat, position.lon, zoom, osm_share_url(position, zoom))
+}
+
+// geo:<lat>,<lon>[,<alt>][;param=value...][?z=<zoom>]
+fn parse_geo_uri(rest: &str) -> Option<DeepLink> {
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let coordinates = path.split(';').next()?;
+    let mut parts = coordinates.split(',');
+    let position = parse_position(parts.next()?, parts.next()?)?;
+
+    let zoom = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("z="))
+        .and_then(parse_zoom);
+    Some(DeepLink { position, zoom })
+}
+
+// https://www.openstreetmap.org/...#map=<zoom>/<lat>/<lon>[&layers=...]
+fn parse_osm_url(url: &str) -> Option<DeepLink> {
+    let (address, fragment) = url.split_once('#')?;
+    let host = address.split("://").nth(1)?.split(['/', '?']).next()?;
+    if host != "openstreetmap.org" && !host.ends_with(".openstreetmap.org") {
+        return None;
+    }
+
+    let map = fragment.split('&').find_map(|pair| pair.strip_prefix("map="))?;
+    let mut parts = map.split('/');
+    let zoom = parse_zoom(parts.next()?)?;
+    let position = parse_position(parts.next()?, parts.next()?)?;
+    Some(DeepLink { position, zoom: Some(zoom) })
+}
+
+fn parse_position(lat: &str, lon: &str) -> Option<GeoPos> {
+    let (lat, lon): (f64, f64) = (lat.trim().parse().ok()?, lon.trim().parse().ok()?);
+    let valid = (-MAX_LATITUDE..=MAX_LATITUDE).contains(&lat) && (-180.0..=180.0).contains(&lon);
+    valid.then(|| GeoPos::new(lat, lon))
+}
+
+fn parse_zoom(value: &str) -> Option<u32> {
+    let zoom: f64 = value.trim().parse().ok()?;
+    zoom.is_finite()
+        .then(|| (zoom.round() as i64).clamp(MIN_ZOOM_LEVEL as i64, MAX_ZOOM_LEVEL as i64) as u32)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(lat: f64, lon: f64, zoom: Option<u32>) -> Option<DeepLink> {
+        Some(DeepLink { position: GeoPos::new(lat, lon), zoom })
+    }
+
+    #[test]
+    fn parses_geo_uris() {
+        assert_eq!(parse_deep_link("geo:53.21,6.56"), link(53.21, 6.56, None));
+        assert_eq!(parse_deep_link("GEO:53.21,6.56,12;u=35?z=16"), link(53.21, 6.56, Some(16)));
+        assert_eq!(parse_deep_link("geo:53.21,6.56?q=Groningen&z=30"), link(53.21, 6.56, Some(MAX_ZOOM_LEVEL)));
+        assert_eq!(parse_deep_link("geo:89,6.56"), None);
+        assert_eq!(parse_deep_link("geo:53.21"), None);
+    }
+
+    #[test]
+    fn parses_openstreetmap_share_urls() {
+        assert_eq!(
+            parse_deep_link("https://www.openstreetmap.org/#map=16/53.21/6.56"),
+            link(53.21, 6.56, Some(16))
+        );
+        assert_eq!(
+            parse_deep_link("https://openstreetmap.org/node/1?mlat=1#map=12/-33.86/151.21&layers=C"),
+            link(-33.86, 151.21, Some(12))
+        );
+        assert_eq!(parse_deep_link("https://example.com/#map=16/53.21/6.56"), None);
+        assert_eq!(parse_deep_link("https://www.openstreetmap.org/"), None);
+    }
+
+    #[test]
+    fn openstreetmap_urls_point_back_at_the_place() {
+        let url = osm_url(GeoPos::new(53.219_43, 6.566_51), 17);
+        assert_eq!(url, "https://www.openstreetmap.org/?mlat=53.21943&mlon=6.56651#map=17/53.21943/6.56651");
+        assert_eq!(parse_deep_link(&url), link(53.219_43, 6.566_51, Some(17)));
+    }
+
+    #[test]
+    fn shared_locations_read_back() {
+        let text = location_text(GeoPos::new(53.219_43, 6.566_51), 16);
+        assert_eq!(text, "53.21943,6.56651,16\nhttps://www.openstreetmap.org/#map=16/53.21943/6.56651");
+        assert_eq!(find_deep_link(&text), link(53.219_43, 6.566_51, Some(16)));
+    }
+
+    #[test]
+    fn finds_links_in_text() {
+        let shortcut = "[InternetShortcut]\nURL=https://www.openstreetmap.org/#map=16/53.21/6.56\n";
+        assert_eq!(find_deep_link(shortcut), link(53.21, 6.56, Some(16)));
+        assert_eq!(find_deep_link("Meet at <geo:53.21,6.56?z=18> tonight"), link(53.21, 6.56, Some(18)));
+        assert_eq!(find_deep_link("no place here"), None);
+    }
+}
@@ -0,0 +1,134 @@
+use bevy::math::{DVec2, Vec3, Vec3Swizzles};
+use std::sync::atomic::{AtomicU64, Ordering};
+use vibe_world_core::projection;
+use crate::osm::TileId;
+
+pub use vibe_world_core::projection::*;
+
+// World position at the render origin as f64 bits (0 is 0.0), moved along with the camera
+// by the floating origin so Transforms stay small enough for f32
+static ORIGIN_X: AtomicU64 = AtomicU64::new(0);
+static ORIGIN_Z: AtomicU64 = AtomicU64::new(0);
+
+/// World X/Z position that sits at the render origin
+/// The core projection is absolute; the conversions below shadow it relative to the render
+/// origin, so their world coordinates are the ones Transforms use. Add it back for positions
+/// that have to outlive a rebase (e.g. on disk)
+pub fn world_origin() -> DVec2 {
+    DVec2::new(
+        f64::from_bits(ORIGIN_X.load(Ordering::Relaxed)),
+        f64::from_bits(ORIGIN_Z.load(Ordering::Relaxed)),
+    )
+}
+
+/// Move the render origin; only the floating origin rebase should call this,
+/// as it shifts everything already placed along with it
+pub fn set_world_origin(origin: DVec2) {
+    ORIGIN_X.store(origin.x.to_bits(), Ordering::Relaxed);
+    ORIGIN_Z.store(origin.y.to_bits(), Ordering::Relaxed);
+}
+
+/// Convert a WGS84 latitude/longitude (degrees) to world X/Z coordinates
+pub fn lat_lon_to_world(lat: f64, lon: f64) -> DVec2 {
+    projection::lat_lon_to_world(lat, lon) - world_origin()
+}
+
+/// Convert world X/Z coordinates back to a WGS84 latitude/longitude (degrees)
+pub fn world_to_lat_lon(x: f64, z: f64) -> (f64, f64) {
+    let origin = world_origin();
+    projection::world_to_lat_lon(x + origin.x, z + origin.y)
+}
+
+/// World X/Z position of a tile's northwest corner
+pub fn tile_world_origin(id: TileId) -> DVec2 {
+    projection::tile_world_origin(id) - world_origin()
+}
+
+/// Convert camera world coordinates to OSM tile coordinates
+pub fn world_to_tile_coords(x: f64, z: f64, zoom: u32) -> (u32, u32) {
+    let origin = world_origin();
+    projection::world_to_tile_coords(x + origin.x, z + origin.y, zoom)
+}
+
+/// A WGS84 latitude/longitude in degrees
+/// Geographic positions are kept as these and converted to f32 world coordinates only
+/// when something is placed, so they don't pick up f32 rounding on the way
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeoPos {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPos {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// The position at world X/Z coordinates
+    pub fn from_world(world: DVec2) -> Self {
+        let (lat, lon) = world_to_lat_lon(world.x, world.y);
+        Self { lat, lon }
+    }
+
+    /// The position on the map below a translation, e.g. the camera's
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self::from_world(translation.xz().as_dvec2())
+    }
+
+    /// World X/Z coordinates of the position
+    pub fn world(&self) -> DVec2 {
+        lat_lon_to_world(self.lat, self.lon)
+    }
+
+    /// The tile containing the position at a zoom level
+    pub fn tile(&self, zoom: u32) -> TileId {
+        TileId::from_lat_lon(self.lat, self.lon, zoom)
+    }
+
+    /// Ground distance in meters covered by one world unit here
+    pub fn meters_per_world_unit(&self) -> f64 {
+        meters_per_world_unit(self.lat)
+    }
+}
+
+/// A position above the map in f64: where on the earth and how high above the ground
+/// The f32 translation relative to the render origin is derived from it at render time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeoTransform {
+    pub position: GeoPos,
+    pub altitude_m: f64,
+}
+
+impl GeoTransform {
+    pub fn new(position: GeoPos, altitude_m: f64) -> Self {
+        Self { position, altitude_m }
+    }
+
+    /// Geographic position of a translation, e.g. the camera's
+    pub fn from_translation(translation: Vec3) -> Self {
+        let position = GeoPos::from_translation(translation);
+        let altitude_m = translation.y as f64 * position.meters_per_world_unit();
+        Self { position, altitude_m }
+    }
+
+    /// Translation to place something here with
+    pub fn translation(&self) -> Vec3 {
+        let world = self.position.world();
+        let height = self.altitude_m / self.position.meters_per_world_unit();
+        Vec3::new(world.x as f32, height as f32, world.y as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geo_transform_round_trip() {
+        let transform = GeoTransform::new(GeoPos::new(-33.8688, 151.2093), 120.0);
+        let back = GeoTransform::from_translation(transform.translation());
+
+        assert_eq!(transform.position.tile(19), back.position.tile(19));
+        assert!((transform.altitude_m - back.altitude_m).abs() < 0.5);
+    }
+}
@@ -1,4 +1,8 @@
-pub mod coordinate_conversion;
+pub mod geo;
 pub mod logging;
+pub mod solar;
+pub mod browser;
+pub mod clipboard;
+pub mod deep_link;
 
-// These are imported directly where needed 
\ No newline at end of file
+// These are imported directly where needed
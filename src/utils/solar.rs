@@ -0,0 +1,80 @@
+// Sun position from the NOAA low precision formulas, accurate to about a degree
+// between 1950 and 2050, which is plenty for lighting
+
+// Julian date of the Unix epoch and of J2000.0
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+const J2000_JD: f64 = 2_451_545.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// Where the sun is in the sky, in degrees
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SunPosition {
+    pub azimuth: f64,   // Clockwise from north: 90 = east, 180 = south
+    pub elevation: f64, // Above the horizon, negative at night
+}
+
+/// Position of the sun seen from a latitude/longitude (degrees) at a Unix time in seconds
+pub fn sun_position(unix_seconds: f64, lat: f64, lon: f64) -> SunPosition {
+    let days = unix_seconds / SECONDS_PER_DAY + UNIX_EPOCH_JD - J2000_JD;
+
+    // Ecliptic longitude of the sun from its mean longitude and mean anomaly
+    let mean_longitude = (280.460 + 0.985_647_4 * days).rem_euclid(360.0);
+    let mean_anomaly = (357.528 + 0.985_600_3 * days).rem_euclid(360.0).to_radians();
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_4 * days).to_radians();
+
+    // Equatorial coordinates
+    let right_ascension = (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+
+    // Hour angle from the local sidereal time
+    let sidereal_degrees = (280.460_618_37 + 360.985_647_366_29 * days + lon).rem_euclid(360.0);
+    let hour_angle = sidereal_degrees.to_radians() - right_ascension;
+
+    let lat = lat.to_radians();
+    let elevation = (lat.sin() * declination.sin() + lat.cos() * declination.cos() * hour_angle.cos()).asin();
+    let azimuth = (-hour_angle.sin() * declination.cos())
+        .atan2(declination.sin() * lat.cos() - declination.cos() * lat.sin() * hour_angle.cos());
+
+    SunPosition {
+        azimuth: azimuth.to_degrees().rem_euclid(360.0),
+        elevation: elevation.to_degrees(),
+    }
+}
+
+/// Local mean solar time at a longitude, in seconds since midnight
+pub fn solar_time_of_day(unix_seconds: f64, lon: f64) -> f64 {
+    (unix_seconds + lon / 15.0 * 3600.0).rem_euclid(SECONDS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2024-03-20 12:00 UTC, a few hours after the March equinox
+    const EQUINOX_NOON: f64 = 1_710_936_000.0;
+    // 2024-06-21 00:00 UTC
+    const JUNE_MIDNIGHT: f64 = 1_718_928_000.0;
+
+    #[test]
+    fn equinox_noon_sun_is_overhead_at_the_equator() {
+        let sun = sun_position(EQUINOX_NOON, 0.0, 0.0);
+        assert!(sun.elevation > 85.0, "{sun:?}");
+    }
+
+    #[test]
+    fn sun_rises_in_the_east_and_sets_in_the_west() {
+        // Groningen, six hours before and after solar noon
+        let morning = sun_position(EQUINOX_NOON - 6.0 * 3600.0 - 6.6 / 15.0 * 3600.0, 53.2, 6.6);
+        let evening = sun_position(EQUINOX_NOON + 6.0 * 3600.0 - 6.6 / 15.0 * 3600.0, 53.2, 6.6);
+        assert!((morning.azimuth - 90.0).abs() < 5.0, "{morning:?}");
+        assert!((evening.azimuth - 270.0).abs() < 5.0, "{evening:?}");
+    }
+
+    #[test]
+    fn midsummer_midnight_is_dark_in_groningen_but_not_at_the_north_cape() {
+        assert!(sun_position(JUNE_MIDNIGHT, 53.2, 6.6).elevation < 0.0);
+        assert!(sun_position(JUNE_MIDNIGHT, 71.2, 25.8).elevation > 0.0);
+    }
+}
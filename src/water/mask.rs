@@ -0,0 +1,84 @@
+// Finds the water on a raster tile from the color of its water fill
+
+// Water fill of the OpenStreetMap Carto style; the other map styles in TILE_PROVIDERS use light
+// blues close enough to be caught by the tolerance, photo imagery has no flat fill and stays dry
+const WATER_COLOR: [u8; 3] = [170, 211, 223];
+// Largest difference per color channel still counted as water, covers antialiasing and
+// the slightly different blues of other styles
+const WATER_TOLERANCE: u8 = 20;
+
+/// Which parts of a tile are water, as a coarse grid of texels
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterMask {
+    pub size: u32,       // Width and height of the grid
+    pub texels: Vec<u8>, // 255 for water, 0 for land, row by row from the tile's northwest corner
+    pub coverage: f32,   // Fraction of the tile that is water
+}
+
+/// Sample a tile's RGBA8 pixels (`width` x `width`) into a `size` x `size` water mask
+/// Every texel looks at the pixel in the middle of the block of the tile it covers
+pub fn water_mask(rgba: &[u8], width: u32, size: u32) -> WaterMask {
+    let mut texels = Vec::with_capacity((size * size) as usize);
+    let mut water = 0usize;
+
+    for row in 0..size {
+        for column in 0..size {
+            let x = ((column * 2 + 1) * width / (size * 2)).min(width - 1);
+            let y = ((row * 2 + 1) * width / (size * 2)).min(width - 1);
+            let offset = ((y * width + x) * 4) as usize;
+            let is_water = rgba
+                .get(offset..offset + 3)
+                .is_some_and(|pixel| pixel.iter().zip(WATER_COLOR).all(|(&c, w)| c.abs_diff(w) <= WATER_TOLERANCE));
+
+            if is_water {
+                water += 1;
+            }
+            texels.push(if is_water { 255 } else { 0 });
+        }
+    }
+
+    WaterMask {
+        size,
+        coverage: water as f32 / texels.len().max(1) as f32,
+        texels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAND: [u8; 4] = [242, 239, 233, 255];
+    const SEA: [u8; 4] = [170, 211, 223, 255];
+
+    // A width x width tile with water on the columns where `is_water(x)` holds
+    fn tile(width: u32, is_water: impl Fn(u32) -> bool) -> Vec<u8> {
+        (0..width * width)
+            .flat_map(|i| if is_water(i % width) { SEA } else { LAND })
+            .collect()
+    }
+
+    #[test]
+    fn open_sea_is_all_water() {
+        let mask = water_mask(&tile(256, |_| true), 256, 64);
+        assert_eq!(mask.coverage, 1.0);
+        assert!(mask.texels.iter().all(|&t| t == 255));
+    }
+
+    #[test]
+    fn coast_is_masked_on_the_water_side() {
+        // Sea in the western quarter of the tile
+        let mask = water_mask(&tile(256, |x| x < 64), 256, 16);
+        assert_eq!(mask.coverage, 0.25);
+        assert_eq!(mask.texels[0], 255);
+        assert_eq!(mask.texels[15], 0);
+    }
+
+    #[test]
+    fn nearby_blues_count_but_land_does_not() {
+        let mut pixels = tile(4, |_| true);
+        pixels[..4].copy_from_slice(&[181, 208, 208, 255]);
+        assert_eq!(water_mask(&pixels, 4, 4).coverage, 1.0);
+        assert_eq!(water_mask(&tile(4, |_| false), 4, 4).coverage, 0.0);
+    }
+}
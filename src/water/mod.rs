@@ -0,0 +1,6 @@
+mod mask;
+mod water_material;
+
+pub use mask::water_mask;
+pub use water_material::{WaterMaterial, WATER_SHADER_HANDLE};
+pub use crate::shader_params::WaterParams;
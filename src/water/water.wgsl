@@ -0,0 +1,98 @@
+// Water surface over the water of a tile: a sum of moving waves perturbs the normal,
+// which reflects the sky (more so at grazing angles) and makes the sun glitter
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_world}
+#import bevy_pbr::mesh_view_bindings::{fog, globals, view}
+#import bevy_pbr::pbr_functions::apply_fog
+
+struct WaterParams {
+    color: vec4<f32>,         // w is the reflectivity
+    zenith: vec4<f32>,
+    horizon: vec4<f32>,
+    sun_direction: vec4<f32>, // xyz towards the sun, w is the strength of the glitter
+    sun_color: vec4<f32>,
+    waves: vec4<f32>,         // Wave length, wave speed, normal strength
+};
+
+@group(2) @binding(0) var<uniform> water: WaterParams;
+@group(2) @binding(1) var mask_texture: texture_2d<f32>;
+@group(2) @binding(2) var mask_sampler: sampler;
+
+const PI: f32 = 3.14159265358979;
+// The waves repeat over this many wave lengths; every wave fits a whole number of times
+const PERIOD_WAVES: f32 = 8.0;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let world = mesh_position_local_to_world(
+        get_world_from_local(vertex.instance_index),
+        vec4<f32>(vertex.position, 1.0),
+    );
+    // The plane is centered on the tile, the mask covers the whole tile
+    out.uv = vertex.position.xz + vec2<f32>(0.5);
+    out.world_position = world.xyz;
+    out.clip_position = view.clip_from_world * world;
+    return out;
+}
+
+// Slope of the water surface at a point, from a few waves in different directions
+fn wave_slope(position: vec2<f32>, time: f32) -> vec2<f32> {
+    let period = water.waves.x * PERIOD_WAVES;
+    // Wrap the position so the phases stay precise far from the world origin
+    let p = position - floor(position / period) * period;
+
+    // Whole numbers of waves per period, so the wrapping leaves no seams
+    var frequencies = array<vec2<f32>, 4>(
+        vec2<f32>(7.0, 3.0),
+        vec2<f32>(-4.0, 7.0),
+        vec2<f32>(11.0, -5.0),
+        vec2<f32>(2.0, -13.0),
+    );
+    var amplitudes = array<f32, 4>(1.0, 0.7, 0.4, 0.25);
+
+    var slope = vec2<f32>(0.0);
+    for (var i = 0; i < 4; i++) {
+        let k = frequencies[i] * 2.0 * PI / period;
+        let phase = dot(k, p) - length(k) * water.waves.y * time;
+        slope += k * amplitudes[i] * cos(phase) / length(k);
+    }
+    return slope;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    if textureSample(mask_texture, mask_sampler, in.uv).r < 0.5 {
+        discard;
+    }
+
+    let to_camera = view.world_position - in.world_position;
+    let distance = length(to_camera);
+    let view_direction = to_camera / distance;
+
+    // Waves much smaller than a pixel would only flicker, flatten them with distance
+    let detail = clamp(water.waves.x * 200.0 / distance, 0.0, 1.0);
+    let slope = wave_slope(in.world_position.xz, globals.time) * water.waves.z * detail;
+    let normal = normalize(vec3<f32>(-slope.x, 1.0, -slope.y));
+
+    // Sky seen in the water, with Schlick's Fresnel approximation for water
+    let reflected = reflect(-view_direction, normal);
+    let sky = mix(water.horizon.rgb, water.zenith.rgb, sqrt(clamp(reflected.y, 0.0, 1.0)));
+    let fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(normal, view_direction), 0.0), 5.0);
+    var color = mix(water.color.rgb, sky, clamp(fresnel * water.color.w, 0.0, 1.0));
+
+    let glitter = pow(max(dot(reflected, water.sun_direction.xyz), 0.0), 300.0);
+    color += water.sun_color.rgb * glitter * water.sun_direction.w;
+
+    return apply_fog(fog, vec4<f32>(color, 1.0), in.world_position, view.world_position);
+}
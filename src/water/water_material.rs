@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use crate::shader_params::WaterParams;
+
+/// Shader drawing the animated water surface
+pub const WATER_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x8c42_d7e1_5a39_4f06_b2d8_6e14_a9c3_70f5);
+
+/// Material of a tile's water surface
+/// Only the parts of the tile marked as water in the mask are drawn, the map shows elsewhere
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct WaterMaterial {
+    #[uniform(0)]
+    pub params: WaterParams,
+    #[texture(1)]
+    #[sampler(2)]
+    pub mask: Handle<Image>,
+}
+
+impl Material for WaterMaterial {
+    fn vertex_shader() -> ShaderRef {
+        WATER_SHADER_HANDLE.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        WATER_SHADER_HANDLE.into()
+    }
+}